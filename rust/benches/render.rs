@@ -0,0 +1,134 @@
+/// Benchmarks of the waveform renderer: drawing the columns, scanning the
+/// decoded samples and encoding the PNG.
+///
+/// ```bash
+/// cargo bench --bench render -- --save-baseline before
+/// # make the change
+/// cargo bench --bench render -- --baseline before
+/// ```
+///
+/// Criterion reports the change against the saved baseline and flags it as
+/// a regression or improvement when it is outside the noise threshold.
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use waver::cli::{Height, Width};
+use waver::color::Rgba;
+use waver::image::{ImageOptions, WaveImage};
+use waver::scan::{min_max, peak_abs, sum_squares};
+
+/// Image sizes (width, height): the micro size, the default and a large one.
+const SIZES: [(u32, u32); 3] = [(256, 32), (2048, 128), (8192, 256)];
+
+/// Samples per column, about a 3 minute track at 44.1 kHz drawn 2048 wide.
+const COLUMN_SAMPLES: usize = 4096;
+
+/// Returns a repeatable amplitude from 0.05 to 1.0 for column `x`, so every
+/// bar height occurs and the branches are not all predicted the same way.
+fn amplitude(x: u32) -> f32 {
+    let hash = x.wrapping_mul(2_654_435_761) >> 16;
+    0.05 + (hash % 96) as f32 / 100.0
+}
+
+/// Returns repeatable samples from -1.0 to 1.0: a tone with noise on top.
+fn samples(count: usize) -> Vec<f32> {
+    let mut state = 0x2545_f491_u32;
+    (0..count)
+        .map(|i| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let noise = (state >> 8) as f32 / (1 << 24) as f32 - 0.5;
+            ((i as f32 * 0.03).sin() * 0.7 + noise * 0.6).clamp(-1.0, 1.0)
+        })
+        .collect()
+}
+
+/// Returns a new image of the size with the given options.
+fn image(width: u32, height: u32, options: &ImageOptions) -> WaveImage {
+    WaveImage::with_options(Width::new(width).unwrap(), Height::new(height).unwrap(), options)
+}
+
+/// Returns an image with one stereo bar drawn in every column.
+fn drawn_image(width: u32, height: u32) -> WaveImage {
+    let mut image = image(width, height, &ImageOptions::default());
+    for x in 0..width {
+        image.draw_point(x, amplitude(x), amplitude(x + 1));
+    }
+    image
+}
+
+/// Draws every column of an image once, as a render does, with each of the
+/// column drawing variants.
+fn bench_draw_point(c: &mut Criterion) {
+    let plain = ImageOptions::default();
+    let tinted = ImageOptions { tinted: true, ..plain };
+    let layered = ImageOptions { rms_layer: true, ..plain };
+    let antialiased = ImageOptions { antialias: true, ..plain };
+
+    let mut group = c.benchmark_group("draw_point");
+    for (width, height) in SIZES {
+        let size = format!("{width}x{height}");
+        group.throughput(Throughput::Elements(width as u64));
+        let mut bench = |name: &str, options: &ImageOptions, draw: fn(&mut WaveImage, u32, f32, f32)| {
+            let mut image = image(width, height, options);
+            group.bench_function(BenchmarkId::new(name, &size), |b| {
+                b.iter(|| {
+                    for x in 0..width {
+                        draw(&mut image, x, black_box(amplitude(x)), black_box(amplitude(x + 1)));
+                    }
+                })
+            });
+        };
+        bench("mono", &plain, |image, x, left, _| image.draw_point_mono(x, left));
+        bench("stereo", &plain, |image, x, left, right| image.draw_point(x, left, right));
+        bench("antialias", &antialiased, |image, x, left, right| image.draw_point(x, left, right));
+        bench("tinted", &tinted, |image, x, left, right| {
+            image.draw_point_tinted(x, left, right, right, left)
+        });
+        bench("layered", &layered, |image, x, left, right| {
+            image.draw_point_layered(x, left, right, left * 0.5, right * 0.5)
+        });
+        bench("envelope", &plain, |image, x, left, right| {
+            image.draw_envelope_point(x, (-right, left), (-left, right))
+        });
+    }
+    group.finish();
+}
+
+/// Scans the samples of one column, the loop run over every decoded sample.
+fn bench_accumulate(c: &mut Criterion) {
+    let column = samples(COLUMN_SAMPLES);
+
+    let mut group = c.benchmark_group("accumulate");
+    group.throughput(Throughput::Elements(COLUMN_SAMPLES as u64));
+    group.bench_function("peak_abs", |b| b.iter(|| peak_abs(black_box(&column))));
+    group.bench_function("sum_squares", |b| b.iter(|| sum_squares(black_box(&column))));
+    group.bench_function("min_max", |b| b.iter(|| min_max(black_box(&column))));
+    group.finish();
+}
+
+/// Encodes a drawn waveform as a PNG into memory.
+fn bench_png(c: &mut Criterion) {
+    let background = Rgba::new(0xff, 0xff, 0xff, 0x00);
+    let left = Rgba::rgb(0x00, 0xff, 0x99);
+    let right = Rgba::rgb(0x99, 0xff, 0x00);
+
+    let mut group = c.benchmark_group("png");
+    for (width, height) in SIZES {
+        let image = drawn_image(width, height);
+        let mut bytes = Vec::new();
+        group.throughput(Throughput::Elements(width as u64 * height as u64));
+        group.bench_function(BenchmarkId::from_parameter(format!("{width}x{height}")), |b| {
+            b.iter(|| {
+                bytes.clear();
+                image.write_png(&background, &left, &right, &mut bytes).unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_draw_point, bench_accumulate, bench_png);
+criterion_main!(benches);
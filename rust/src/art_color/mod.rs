@@ -0,0 +1,130 @@
+/// The colors of the waveform from the cover art (`--auto-color`).
+///
+/// The dominant color of the picture embedded in the tags gives each track
+/// a waveform in the colors of its album, with no colors to pick by hand.
+/// The picture is decoded (PNG and WebP in full, JPEG scaled down to an
+/// eighth as it is decoded, which is all a color needs) and its pixels
+/// counted in bins of similar colors.  The fullest bin of the colorful
+/// pixels wins, so a small red title on a black and white photo does not
+/// lose to the black and the white; only when almost nothing in the picture
+/// is colorful do the grey pixels count.
+///
+/// The waveform is then drawn in that hue, the right channel
+/// `SEEDED_HUE_STEP` degrees below it like the seeded colors, at a
+/// lightness that shows on both dark and light pages.
+use jpeg_decoder::PixelFormat;
+
+use crate::backdrop::Backdrop;
+use crate::color::{Rgba, SEEDED_HUE_STEP};
+use crate::error::{Result, WaverError};
+use crate::metadata::CoverArt;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// Bits kept of each channel when counting the colors (8 levels each).
+const BIN_BITS: u32 = 3;
+
+/// The least difference between the largest and the smallest channel of a
+/// colorful pixel; greys, black and white are below it.
+const COLORFUL_CHROMA: u8 = 48;
+
+/// The share of colorful pixels below which the grey ones count too.
+const COLORFUL_SHARE: f64 = 0.05;
+
+/// The range of lightness the waveform colors are drawn in.
+const LIGHTNESS: (f32, f32) = (0.35, 0.65);
+
+/// Returns the left and right colors of the waveform for the cover art,
+/// or `None` if the picture has no opaque pixels.
+pub fn art_colors(art: &CoverArt) -> Result<Option<(Rgba, Rgba)>> {
+    Ok(dominant_color(&art_pixels(art)?).map(waveform_colors))
+}
+
+/// Decodes the pixels of the cover art.
+fn art_pixels(art: &CoverArt) -> Result<Vec<Rgba>> {
+    match art.extension() {
+        "jpg" => jpeg_pixels(&art.data),
+        "png" => Ok(Backdrop::read(&art.data[..])?.pixels().to_vec()),
+        "webp" => webp_pixels(&art.data),
+        other => Err(WaverError::generation_error(format!("The colors of {other} cover art can not be read"))),
+    }
+}
+
+/// Decodes a JPEG picture at an eighth of its size, the averages of its 8x8
+/// blocks.
+fn jpeg_pixels(data: &[u8]) -> Result<Vec<Rgba>> {
+    let invalid = |err: jpeg_decoder::Error| WaverError::generation_error(format!("Not a readable JPEG: {err}"));
+    let mut decoder = jpeg_decoder::Decoder::new(data);
+    decoder.scale(1, 1).map_err(invalid)?;
+    let buffer = decoder.decode().map_err(invalid)?;
+    match decoder.info().map(|info| info.pixel_format) {
+        Some(PixelFormat::L8) => Ok(buffer.iter().map(|&l| Rgba::rgb(l, l, l)).collect()),
+        // Big endian, so the first byte is the high one
+        Some(PixelFormat::L16) => Ok(buffer.chunks_exact(2).map(|l| Rgba::rgb(l[0], l[0], l[0])).collect()),
+        Some(PixelFormat::RGB24) => Ok(buffer.chunks_exact(3).map(|p| Rgba::rgb(p[0], p[1], p[2])).collect()),
+        Some(PixelFormat::CMYK32) | None => Err(WaverError::generation_error("The colors of a CMYK JPEG can not be read")),
+    }
+}
+
+/// Decodes a WebP picture (the first frame of an animation).
+fn webp_pixels(data: &[u8]) -> Result<Vec<Rgba>> {
+    let invalid = |err: image_webp::DecodingError| WaverError::generation_error(format!("Not a readable WebP: {err}"));
+    let mut decoder = image_webp::WebPDecoder::new(std::io::Cursor::new(data)).map_err(invalid)?;
+    let size = decoder
+        .output_buffer_size()
+        .ok_or_else(|| WaverError::generation_error("Not a readable WebP: the picture is too large"))?;
+    let mut buffer = vec![0; size];
+    decoder.read_image(&mut buffer).map_err(invalid)?;
+    Ok(if decoder.has_alpha() {
+        buffer.chunks_exact(4).map(|p| Rgba::new(p[0], p[1], p[2], p[3])).collect()
+    } else {
+        buffer.chunks_exact(3).map(|p| Rgba::rgb(p[0], p[1], p[2])).collect()
+    })
+}
+
+/// The pixels counted in one bin of similar colors.
+#[derive(Debug, Clone, Copy, Default)]
+struct Bin {
+    pixels: u64,
+    sums: [u64; 3],
+}
+
+/// Returns the dominant color of the pixels: the average of the fullest
+/// bin of similar colors, of the colorful pixels unless there are almost
+/// none.  Pixels less than half opaque are left out.
+pub fn dominant_color(pixels: &[Rgba]) -> Option<Rgba> {
+    let size = 1 << (3 * BIN_BITS);
+    let (mut colorful, mut all) = (vec![Bin::default(); size], vec![Bin::default(); size]);
+    for pixel in pixels.iter().filter(|pixel| pixel.alpha >= 128) {
+        let channels = [pixel.red, pixel.green, pixel.blue];
+        let bin = channels.iter().fold(0, |bin, &channel| (bin << BIN_BITS) | (channel >> (8 - BIN_BITS)) as usize);
+        let chroma = channels.iter().max().unwrap() - channels.iter().min().unwrap();
+        let colorful_bin = (chroma >= COLORFUL_CHROMA).then_some(&mut colorful[bin]);
+        for bin in [Some(&mut all[bin]), colorful_bin].into_iter().flatten() {
+            bin.pixels += 1;
+            for (sum, channel) in bin.sums.iter_mut().zip(channels) {
+                *sum += channel as u64;
+            }
+        }
+    }
+
+    let count = |bins: &[Bin]| bins.iter().map(|bin| bin.pixels).sum::<u64>();
+    let bins = if count(&colorful) as f64 >= COLORFUL_SHARE * count(&all) as f64 { &colorful } else { &all };
+    let fullest = bins.iter().max_by_key(|bin| bin.pixels).filter(|bin| bin.pixels > 0)?;
+    let [red, green, blue] = fullest.sums.map(|sum| ((sum + fullest.pixels / 2) / fullest.pixels) as u8);
+    Some(Rgba::rgb(red, green, blue))
+}
+
+/// Returns the left and right colors of the waveform for a dominant color:
+/// its hue and saturation, the right one `SEEDED_HUE_STEP` degrees below,
+/// both at a lightness kept within `LIGHTNESS`.
+pub fn waveform_colors(dominant: Rgba) -> (Rgba, Rgba) {
+    let (hue, saturation, lightness) = dominant.to_hsl();
+    let lightness = lightness.clamp(LIGHTNESS.0, LIGHTNESS.1);
+    (
+        Rgba::from_hsl(hue, saturation, lightness),
+        Rgba::from_hsl(hue - SEEDED_HUE_STEP, saturation, lightness),
+    )
+}
@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod tests {
+    use jpeg_encoder::{ColorType, Encoder, SamplingFactor};
+
+    use crate::art_color::{art_colors, dominant_color, jpeg_pixels, waveform_colors};
+    use crate::color::{Rgba, SEEDED_HUE_STEP};
+    use crate::metadata::CoverArt;
+
+    const RED: Rgba = Rgba::rgb(0xe0, 0x20, 0x20);
+    const BLUE: Rgba = Rgba::rgb(0x20, 0x40, 0xe0);
+
+    /// A 40x24 picture: red on the left 16 columns, blue on the rest.
+    fn picture() -> Vec<u8> {
+        let pixel = |x| if x < 16 { RED } else { BLUE };
+        (0..24 * 40).map(|i| pixel(i % 40)).flat_map(|color: Rgba| [color.red, color.green, color.blue]).collect()
+    }
+
+    fn encode(setup: impl FnOnce(&mut Encoder<&mut Vec<u8>>)) -> Vec<u8> {
+        let mut jpeg = Vec::new();
+        let mut encoder = Encoder::new(&mut jpeg, 90);
+        setup(&mut encoder);
+        encoder.encode(&picture(), 40, 24, ColorType::Rgb).unwrap();
+        jpeg
+    }
+
+    fn assert_near(color: Rgba, expected: Rgba, what: &str) {
+        let near = |a: u8, b: u8| a.abs_diff(b) <= 24;
+        assert!(
+            near(color.red, expected.red) && near(color.green, expected.green) && near(color.blue, expected.blue),
+            "{what}: {color} is not near {expected}"
+        );
+    }
+
+    // Test the block averages of the JPEG variants cover art comes in
+    #[test]
+    fn test_jpeg_blocks() {
+        let variants: [(&str, Vec<u8>); 5] = [
+            ("4:4:4", encode(|encoder| encoder.set_sampling_factor(SamplingFactor::R_4_4_4))),
+            ("4:2:0", encode(|encoder| encoder.set_sampling_factor(SamplingFactor::R_4_2_0))),
+            ("progressive", encode(|encoder| encoder.set_progressive(true))),
+            ("restarts", encode(|encoder| encoder.set_restart_interval(2))),
+            ("optimized", encode(|encoder| encoder.set_optimized_huffman_tables(true))),
+        ];
+        for (what, jpeg) in variants {
+            let colors = jpeg_pixels(&jpeg).unwrap();
+            assert_eq!(colors.len(), 5 * 3, "{what}: 5 blocks in each of 3 rows");
+            for row in colors.chunks_exact(5) {
+                assert_near(row[0], RED, what);
+                assert_near(row[4], BLUE, what);
+            }
+        }
+
+        // Grey pictures have a single component
+        let mut jpeg = Vec::new();
+        Encoder::new(&mut jpeg, 90).encode(&[0x80; 16 * 8], 16, 8, ColorType::Luma).unwrap();
+        assert_eq!(jpeg_pixels(&jpeg).unwrap(), vec![Rgba::rgb(0x80, 0x80, 0x80); 2]);
+
+        assert!(jpeg_pixels(b"not a jpeg").is_err());
+        let jpeg = encode(|_| {});
+        assert!(jpeg_pixels(&jpeg[..jpeg.len() / 2]).is_err(), "Cut short");
+
+        // Damaged pictures are errors or wrong colors, never a panic
+        for at in (2..jpeg.len()).step_by(7) {
+            let mut damaged = jpeg.clone();
+            damaged[at] ^= 0xa5;
+            let _ = jpeg_pixels(&damaged);
+        }
+    }
+
+    #[test]
+    fn test_dominant_color() {
+        assert_eq!(dominant_color(&[]), None);
+        assert_eq!(dominant_color(&[RED.with_alpha(0)]), None, "Transparent pixels are left out");
+
+        // The most common color wins
+        let pixels = [vec![RED; 10], vec![BLUE; 30]].concat();
+        assert_eq!(dominant_color(&pixels), Some(BLUE));
+
+        // A little color wins over much grey, black and white
+        let white = Rgba::rgb(0xff, 0xff, 0xff);
+        let pixels = [vec![white; 60], vec![Rgba::rgb(0, 0, 0); 30], vec![RED; 10]].concat();
+        assert_eq!(dominant_color(&pixels), Some(RED));
+        // But not when there is next to none of it
+        let pixels = [vec![white; 200], vec![RED; 1]].concat();
+        assert_eq!(dominant_color(&pixels), Some(white));
+
+        // Similar colors are averaged
+        let pixels = [Rgba::rgb(0xe0, 0x20, 0x20), Rgba::rgb(0xe2, 0x22, 0x24)];
+        assert_eq!(dominant_color(&pixels), Some(Rgba::rgb(0xe1, 0x21, 0x22)));
+    }
+
+    #[test]
+    fn test_waveform_colors() {
+        let (left, right) = waveform_colors(RED);
+        assert_eq!(left, RED);
+        let (hue, ..) = right.to_hsl();
+        assert!((hue - (360.0 - SEEDED_HUE_STEP)).abs() < 1.0);
+
+        // Too dark and too light colors are brought within reach
+        let (dark, _) = waveform_colors(Rgba::rgb(0x10, 0, 0));
+        let (light, _) = waveform_colors(Rgba::rgb(0xff, 0xf0, 0xf0));
+        assert!((dark.to_hsl().2 - 0.35).abs() < 0.01);
+        assert!((light.to_hsl().2 - 0.65).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_art_colors() {
+        let jpeg = CoverArt { media_type: "image/jpeg".to_string(), data: encode(|_| {}).into() };
+        let (left, _) = art_colors(&jpeg).unwrap().unwrap();
+        assert_eq!(left.to_hsl().0.round(), BLUE.to_hsl().0.round(), "Most of the picture is blue");
+
+        let gif = CoverArt { media_type: "image/gif".to_string(), data: b"GIF89a".to_vec().into() };
+        assert!(art_colors(&gif).is_err());
+        let broken = CoverArt { media_type: "image/png".to_string(), data: vec![0x89, b'P', b'N', b'G'].into() };
+        assert!(art_colors(&broken).is_err());
+    }
+}
@@ -1,128 +1,1585 @@
 /// Audio processing functionality for waveform generation.
 use std::fs::File;
+use std::io::{self, BufWriter, Cursor, Read, Write};
+use std::ops::Range;
 use std::path::Path;
 
-use symphonia::core::audio::{AudioBuffer, Signal};
-use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
-use symphonia::core::formats::FormatOptions;
-use symphonia::core::io::MediaSourceStream;
+use sha2::{Digest, Sha256};
+use symphonia::core::audio::{AudioBuffer, AudioBufferRef, Signal};
+use symphonia::core::codecs::{CodecParameters, Decoder, CODEC_TYPE_NULL};
+use symphonia::core::units::{Time, TimeBase};
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::{MediaSource, MediaSourceStream};
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
+use tracing::{debug, info_span};
 
-use crate::cli::{AudioPath, WaverArgs};
+use crate::art_color::art_colors;
+use crate::audit;
+use crate::cli::{FileExtension, ImageFormat, RenderMode, WaveLayout};
+use crate::color::{stem_colors, Rgba};
+use crate::config::{checkerboard_path, is_stdin, is_stdout, played_path, ExistingOutput, TimeRange, WaveformConfig};
+use crate::decoder_pool;
+use crate::diff::PEAK_HASH_TEXT;
 use crate::error::{Result, WaverError};
-use crate::image::WaveImage;
+use crate::image::{ImageOptions, WaveImage, MAX_STEMS, MIN_LANE_ROWS};
+use crate::image_pool;
+use crate::loudness::{Loudness, LoudnessMeter};
+use crate::metadata::{read_cover_art, read_markers, Marker};
+use crate::mix::{ChannelMixer, ChannelMode};
+use crate::packet_guard::PacketGuard;
+use crate::peak_cache::{PeakCache, PeakCacheBuilder, PeakIndex};
+use crate::peaks::{ColumnPeak, Envelope, Peaks};
+use crate::preview::{Preview, SecondPeaks};
+use crate::quality::{Quality, QualityScanner, QualityWeights};
+use crate::render::{peak_gain, render, render_stems, RenderOptions};
+use crate::scan::{min_max, peak_abs, sum_squares};
+use crate::sidecar::{Colors, Sidecar};
+use crate::silence::{SilenceDetection, SilenceDetector, SilenceMap};
+use crate::spectrum::{Spectrogram, SpectrogramBuilder};
+use crate::tempo::TempoDetector;
+use crate::ticks::{TickMap, TickSpacing};
+use crate::tiles::{save_tiles, tiles_dir, Manifest};
+use crate::trim::SilenceBounds;
+use crate::video::save_video;
+
+/// What `generate_waveform` did with an audio file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The image (and sidecar, if enabled) was written
+    Created,
+    /// The image was rendered but not written (dry run)
+    DryRun,
+    /// The output already exists and was left alone
+    Skipped,
+    /// The output exists and is newer than the audio file
+    UpToDate,
+}
 
 /// Generates a waveform visualization from an audio file.
 ///
 /// # Arguments
 ///
-/// * `input_path` - Path to the input audio file
-/// * `output_path` - Path where the output PNG will be saved
-/// * `args` - Command-line arguments containing configuration
+/// * `input_path` - Path to the input audio file, or `-` for stdin
+/// * `output_path` - Path where the output PNG will be saved, or `-` for stdout
+/// * `config` - The waveform settings
 ///
 /// # Returns
 ///
-/// `Ok(())` on success, or an error if processing fails
+/// The `Outcome` on success, or an error if processing fails
 pub fn generate_waveform(
-    input_path: &AudioPath,
+    input_path: impl AsRef<Path>,
     output_path: impl AsRef<Path>,
-    args: &WaverArgs,
-) -> Result<()> {
-    let input_path = input_path.path();
+    config: &WaveformConfig,
+) -> Result<Outcome> {
+    generate_waveform_measured(input_path, output_path, config).map(|(outcome, _)| outcome)
+}
+
+/// Generates a waveform visualization like `generate_waveform` and also
+/// returns what was measured of the audio while decoding it.
+///
+/// The loudness and the tempo are only measured as set up with
+/// `WaveformConfigBuilder::measure_loudness` and `detect_bpm`, and only when
+/// the audio was decoded (not when the output was skipped).
+pub fn generate_waveform_measured(
+    input_path: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    config: &WaveformConfig,
+) -> Result<(Outcome, Measures)> {
+    let input_path = input_path.as_ref();
     let output_path = output_path.as_ref();
+    let target_path = config.target_path(output_path);
+    let to_stdout = is_stdout(output_path);
 
-    // Skip if output exists and overwrite isn't allowed
-    if !args.overwrite && output_path.exists() {
-        if args.verbose {
-            return Err(WaverError::generation_error(format!(
-                "Output file '{}' already exists - use --overwrite",
-                output_path.display()
-            )));
+    // Tiles, sidecars, previews, tick maps and the played and checkerboard
+    // copies are separate files that need a real image path
+    let extra_files = config.tile_width.is_some()
+        || config.sidecar
+        || config.preview
+        || config.silence.is_some()
+        || config.ticks_json
+        || config.played_colors.is_some()
+        || config.checkerboard.is_some();
+    if to_stdout && extra_files {
+        return Err(WaverError::argument_error(
+            "Tiles, sidecar, preview, silence and tick map files, the progress pair and the checkerboard copy cannot be written with the image on stdout",
+        ));
+    }
+
+    // With IfNewer an existing output is only replaced if it is older
+    // than the audio file, which makes re-running over a library cheap
+    // (stdin has no modification time so it always counts as newer), and a
+    // damaged image is replaced whatever its age
+    if !to_stdout && target_path.exists() && !config.is_damaged(&target_path) {
+        match config.existing_output {
+            ExistingOutput::Skip => return Ok((Outcome::Skipped, Measures::default())),
+            ExistingOutput::IfNewer
+                if !is_stdin(input_path) && !is_newer(input_path, &target_path)? =>
+            {
+                return Ok((Outcome::UpToDate, Measures::default()));
+            }
+            _ => {}
         }
-        return Ok(());
     }
 
-    // Generate the image buffer
-    let mut image = WaveImage::new(args.width, args.height);
+    // The video is encoded by ffmpeg, which also reads the audio file again
+    if config.video.is_some() && (to_stdout || is_stdin(input_path)) {
+        return Err(WaverError::argument_error(
+            "A video needs an audio file and can not be written to stdout",
+        ));
+    }
+
+    // The art is read from the tags of the file after the image is written
+    if config.art_dir.is_some() && is_stdin(input_path) {
+        return Err(WaverError::argument_error(
+            "Cover art needs an audio file and can not be extracted from stdin",
+        ));
+    }
 
-    // Process audio file and generate waveform
-    process_audio_file(input_path, &mut image, args.width())?;
+    if config.auto_color && is_stdin(input_path) {
+        return Err(WaverError::argument_error(
+            "The colors of the cover art need an audio file and can not be read from stdin",
+        ));
+    }
 
-    // Save or log the result
-    if !args.dry_run {
-        image.save_png(&args.background_color, &args.left_color, &args.right_color, output_path)?;
-        args.print_to_stdout(&format!("Created {}", output_path.display()));
-    } else if args.verbose {
-        args.print_verbose(&format!("DryRun {}", output_path.display()));
+    if config.trim_silence.is_some() && is_stdin(input_path) {
+        return Err(WaverError::argument_error(
+            "Trimming the silence reads the audio twice and can not be done from stdin",
+        ));
     }
 
-    Ok(())
+    // The silence is found in a first pass over the audio, and what is
+    // between it is then drawn as a time range
+    let trimmed;
+    let config = match config.trim_silence {
+        Some(threshold) => {
+            let range = info_span!("trim").in_scope(|| silence_range(input_path, threshold, config))?;
+            trimmed = WaveformConfig { range, trim_silence: None, ..config.clone() };
+            &trimmed
+        }
+        None => config,
+    };
+
+    if config.mode == RenderMode::Spectrogram {
+        return generate_spectrogram(input_path, output_path, config).map(|outcome| (outcome, Measures::default()));
+    }
+
+    // With a color map, a color seed or the colors of the cover art every
+    // file is drawn in colors of its own: those of its row of the map, then
+    // those of its art, then the seeded or set colors
+    let own_colors;
+    let config = if config.color_map.is_some() || config.color_seed.is_some() || config.auto_color {
+        let mapped = config.color_map.as_ref().and_then(|map| map.lookup(input_path)).copied().unwrap_or_default();
+        let needs_art = config.auto_color && (mapped.left.is_none() || mapped.right.is_none());
+        let art = if needs_art { cover_art_colors(input_path)? } else { None };
+        let (left_color, right_color) = art.unwrap_or_else(|| config.file_colors(input_path));
+        own_colors = WaveformConfig {
+            left_color: mapped.left.unwrap_or(left_color),
+            right_color: mapped.right.unwrap_or(right_color),
+            background_color: mapped.background.unwrap_or(config.background_color),
+            color_seed: None,
+            auto_color: false,
+            color_map: None,
+            ..config.clone()
+        };
+        &own_colors
+    } else {
+        config
+    };
+
+    let Drawn { image, image_options, options, peaks, info, tick_map, markers } = draw_waveform(input_path, config)?;
+
+    if config.dry_run {
+        image_pool::give_back(config.width, config.height, &image_options, image);
+        return Ok((Outcome::DryRun, Measures::of(&info)));
+    }
+
+    // Everything from here on writes files (the images are encoded as they
+    // are written, in the nested encode spans)
+    let _write_span = info_span!("write").entered();
+    let text = if config.embed_metadata { png_text(input_path, &info, Some(&peaks), config) } else { Vec::new() };
+    if let Some(tile_width) = config.tile_width {
+        // The manifest is written last so it only exists once all of its tiles do
+        let tiles = info_span!("encode", format = "png").in_scope(|| {
+            save_tiles(
+                &image,
+                tile_width.value(),
+                &config.background_color,
+                &config.left_color,
+                &config.right_color,
+                &tiles_dir(output_path),
+            )
+        })?;
+        Manifest {
+            source: input_path.display().to_string(),
+            width: image.width(),
+            height: image.height(),
+            tile_width: tile_width.value(),
+            duration_seconds: info.duration(),
+            tiles,
+        }
+        .save(&target_path)?;
+    } else if let Some(format) = config.video {
+        let colors = (&config.background_color, &config.left_color, &config.right_color);
+        save_video(&image, colors, input_path, config.range, info.duration(), format, output_path)?;
+    } else if to_stdout {
+        let mut out = BufWriter::new(io::stdout().lock());
+        write_image(&image, config, (&config.left_color, &config.right_color), &text, &mut out)?;
+        out.flush()?;
+    } else {
+        let mut out = audit::create_atomic(output_path)?;
+        write_image(&image, config, (&config.left_color, &config.right_color), &text, &mut out)?;
+        out.commit()?;
+
+        // The played copy is the same pixels with another palette, so the
+        // two line up exactly
+        if let Some((left, right)) = &config.played_colors {
+            let mut out = audit::create_atomic(played_path(output_path))?;
+            write_image(&image, config, (left, right), &text, &mut out)?;
+            out.commit()?;
+        }
+
+        // The checkerboard copy is for checking the transparency by eye
+        if let Some(square) = config.checkerboard {
+            let (background, left, right) = (&config.background_color, &config.left_color, &config.right_color);
+            let mut out = audit::create_atomic(checkerboard_path(output_path))?;
+            info_span!("encode", format = "checkerboard")
+                .in_scope(|| image.write_checkerboard_png(background, left, right, square, &mut out))?;
+            out.commit()?;
+        }
+    }
+
+    let measures = Measures::of(&info);
+    if config.sidecar {
+        let size = (image.width(), image.height());
+        save_sidecar(input_path, output_path, &target_path, size, info, markers, config)?;
+    }
+
+    if let Some(art_dir) = &config.art_dir {
+        save_cover_art(input_path, art_dir)?;
+    }
+
+    if let Some(tick_map) = tick_map.filter(|_| config.ticks_json) {
+        tick_map.save(TickMap::path_for(output_path))?;
+    }
+
+    // The preview peaks are scaled like the image
+    if config.preview {
+        let gain = peak_gain(&peaks, &options);
+        Preview {
+            source: input_path.display().to_string(),
+            seconds_per_peak: 1,
+            peaks: Preview::quantize(peaks.seconds.iter().map(|&peak| config.map.apply(peak * gain))),
+        }
+        .save(Preview::path_for(output_path))?;
+    }
+
+    if let Some(detection) = config.silence {
+        SilenceMap {
+            source: input_path.display().to_string(),
+            threshold_db: detection.threshold_db,
+            min_duration_seconds: detection.min_seconds,
+            silences: peaks.silences,
+        }
+        .save(SilenceMap::path_for(output_path))?;
+    }
+
+    image_pool::give_back(config.width, config.height, &image_options, image);
+    Ok((Outcome::Created, measures))
+}
+
+/// A waveform drawn into its image, with what was found on the way.
+pub(crate) struct Drawn {
+    pub(crate) image: WaveImage,
+    /// The options the image was taken from the pool with (to give it back)
+    pub(crate) image_options: ImageOptions,
+    options: RenderOptions,
+    peaks: Peaks,
+    pub(crate) info: AudioInfo,
+    tick_map: Option<TickMap>,
+    markers: Vec<Marker>,
 }
 
-/// Processes an audio file and generates a waveform visualization using a streaming approach.
+/// Reduces an audio file to the column peaks a waveform is drawn from,
+/// without drawing it.
 ///
-/// This function opens an audio file, decodes it frame by frame, and immediately
-/// processes each frame to generate the waveform image, without storing all audio data in memory.
+/// The peaks are collected as `config` would draw them (its width, time
+/// range, channels and layout), and read from the peak cache when it is on.
+/// They are drawn with `config.render_options()`; the gain is not applied
+/// to them yet.
 ///
-/// # Performance
+/// # Arguments
 ///
-/// This is a performance-critical function.  It uses a streaming approach rather than buffering the
-/// entire audio file, which results in:
-/// - ~24x lower memory usage
-/// - ~6.7x faster execution time
-/// - Significantly fewer system calls
+/// * `input_path` - Path to the input audio file, or `-` for stdin
+/// * `config` - The waveform settings
+pub fn generate_peaks(input_path: impl AsRef<Path>, config: &WaveformConfig) -> Result<Peaks> {
+    let options = config.render_options();
+    decode_peaks(input_path.as_ref(), config, &options).map(|(peaks, _)| peaks)
+}
+
+/// Reduces the audio to the column peaks `options` draws, from the peak
+/// cache when it has them.
+fn decode_peaks(input_path: &Path, config: &WaveformConfig, options: &RenderOptions) -> Result<(Peaks, AudioInfo)> {
+    let scan = ScanOptions {
+        width: config.width(),
+        range: config.range,
+        channels: config.channels,
+        rms: options.needs_rms(),
+        envelope: options.envelope,
+        lanes: options.lanes,
+        preview: config.preview,
+        silence: config.silence,
+        loudness: config.measure_loudness,
+        bpm: config.detect_bpm,
+        pcm_hash: config.pcm_hash,
+        max_packets: config.max_packets,
+    };
+    match cached_peaks(input_path, config)? {
+        Some(cached) => Ok(cached),
+        None => {
+            let (source, hint) = open_source(input_path, config.stdin_format.as_ref())?;
+            process_audio_file(source, &hint, input_path, &scan)
+        }
+    }
+}
+
+/// Reduces the audio to column peaks and draws the waveform with its
+/// overlays, the part of `generate_waveform` before anything is written.
+pub(crate) fn draw_waveform(input_path: &Path, config: &WaveformConfig) -> Result<Drawn> {
+    // Generate the image buffer - tinting and the balance strip need
+    // a larger palette than the default 2-bit image
+    let options = config.render_options();
+
+    // Reduce the audio to column peaks
+    let (peaks, info) = info_span!("decode").in_scope(|| decode_peaks(input_path, config, &options))?;
+
+    // The number of lanes is only known once the track is open
+    let lanes = peaks.lanes.len() as u32;
+    if config.height() < lanes * MIN_LANE_ROWS {
+        return Err(WaverError::generation_error(format!(
+            "The image must be at least {} pixels high for {lanes} lanes",
+            lanes * MIN_LANE_ROWS
+        )));
+    }
+
+    // In batch runs the image of the previous file on this thread is reused
+    let render_span = info_span!("render").entered();
+    let image_options = ImageOptions {
+        tinted: options.tint,
+        balance_strip: config.balance_strip.unwrap_or(0),
+        overview_ratio: config.overview_ratio.map(|ratio| ratio.value()),
+        antialias: config.antialias,
+        lanes,
+        rms_layer: options.rms_layer,
+        mono_style: config.mono_style,
+        stems: 0,
+        overlap: config.overlap,
+    };
+    let mut image = image_pool::take(config.width, config.height, &image_options);
+    render(&peaks, &mut image, &options);
+
+    // The grid and the axis go behind the finished waveform, where it is
+    // background
+    if let Some(interval) = config.grid {
+        draw_time_grid(&mut image, config, interval, info.duration());
+    }
+    if let Some(color) = &config.center_line {
+        image.draw_axis(color, config.borders);
+    }
+
+    // Overlays are drawn on top of the finished waveform
+    let tick_map = config.ticks.and_then(|interval| {
+        let (spacing, color) = time_ticks(&image, config, interval, info.duration())?;
+        let columns = image.draw_tick_marks(&color, spacing.first_x, spacing.spacing);
+        Some(TickMap::new(input_path.display().to_string(), image.width(), &spacing, &columns))
+    });
+    let markers = cue_markers(input_path, config.cue_markers || config.sidecar)?;
+    let drawn = config.markers.iter().flat_map(|file| file.iter());
+    let drawn: Vec<Marker> = drawn.chain(markers.iter().filter(|_| config.cue_markers)).copied().collect();
+    if !drawn.is_empty() {
+        let columns = marker_columns(&drawn, config, info.duration(), image.width());
+        image.draw_markers(&axis_color(config), &columns);
+    }
+    if let Some(corner) = config.duration_badge {
+        image.draw_badge(corner, &format_duration(info.duration()));
+    }
+    drop(render_span);
+
+    Ok(Drawn { image, image_options, options, peaks, info, tick_map, markers })
+}
+
+/// Returns the hash of the column peaks that the image of the audio file
+/// would be drawn from with the settings (see `Peaks::hash`), as it is
+/// embedded in the image with `embed_metadata`.
 ///
-/// Do not change this to buffer all audio samples, as that would cause severe performance degradation.
+/// Nothing is drawn or written, not even a missing peak cache.
+pub fn peak_hash(input_path: impl AsRef<Path>, config: &WaveformConfig) -> Result<String> {
+    let input_path = input_path.as_ref();
+    let range = match config.trim_silence {
+        Some(threshold) => silence_range(input_path, threshold, config)?,
+        None => config.range,
+    };
+    let config = WaveformConfig { range, trim_silence: None, dry_run: true, ..config.clone() };
+    let scan = ScanOptions {
+        width: config.width(),
+        range: config.range,
+        channels: config.channels,
+        lanes: config.layout == WaveLayout::Stacked,
+        max_packets: config.max_packets,
+        ..ScanOptions::default()
+    };
+    let (peaks, _) = match cached_peaks(input_path, &config)? {
+        Some(cached) => cached,
+        None => {
+            let (source, hint) = open_source(input_path, config.stdin_format.as_ref())?;
+            process_audio_file(source, &hint, input_path, &scan)?
+        }
+    };
+    Ok(peaks.hash())
+}
+
+/// Returns the SHA-256 (hex) of the encoded audio of a file, which names
+/// its image with `--hash-names`.
 ///
-/// # Arguments
+/// Only the packets of the track are hashed (they are not decoded), so two
+/// copies of a track with other tags or cover art have the same hash.
+pub fn content_hash(input_path: impl AsRef<Path>) -> Result<String> {
+    let input_path = input_path.as_ref();
+    let OpenTrack {
+        mut format,
+        track_id,
+        decoder,
+        params,
+        n_frames,
+        ..
+    } = open_track(Box::new(File::open(input_path)?), &Hint::new(), input_path)?;
+    decoder_pool::give_back(params, decoder);
+
+    let mut hasher = Sha256::new();
+    let mut guard = PacketGuard::new(n_frames, None);
+    while let Ok(packet) = format.next_packet() {
+        guard.count(input_path)?;
+        if packet.track_id() == track_id {
+            hasher.update(&packet.data);
+        }
+    }
+    Ok(hasher.finalize().iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+/// Generates a waveform image for every segment of an audio file.
 ///
-/// * `input_path` - Path to the input audio file
-/// * `image` - The waveform image to draw into
-/// * `width` - Width of the output image in pixels
+/// The audio is split as set up with `WaveformConfigBuilder::segment` and
+/// each segment is rendered as a time range of its own with
+/// `generate_waveform`, so decoding seeks to its start rather than running
+/// through the whole file every time.
 ///
 /// # Returns
 ///
-/// `Ok(())` on success, or an error if processing fails
-fn process_audio_file(input_path: &Path, image: &mut WaveImage, width: u32) -> Result<()> {
-    // Open and probe the audio file
-    let file = File::open(input_path)?;
-    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+/// The image path and `Outcome` of every segment in order, or the first
+/// error
+pub fn generate_segments(input_path: impl AsRef<Path>, config: &WaveformConfig) -> Result<Vec<(String, Outcome)>> {
+    let input_path = input_path.as_ref();
+    let Some(segmenting) = config.segmenting() else {
+        return Err(WaverError::argument_error("No segment length is set"));
+    };
+    if is_stdin(input_path) {
+        return Err(WaverError::argument_error(
+            "Segments need an audio file and can not be read from stdin",
+        ));
+    }
+
+    // The cover art is the same for every segment, so it is written once
+    let mut segment_config = config.clone();
+    segment_config.segmenting = None;
+    segment_config.art_dir = None;
+    let mut outcomes = Vec::new();
+    for segment in segmenting.segments(probe_duration(input_path)?) {
+        let output_path = segmenting.path(input_path, &segment, config.output_extension());
+        segment_config.range = Some(segment.range());
+        let outcome = generate_waveform(input_path, &output_path, &segment_config)?;
+        outcomes.push((output_path, outcome));
+    }
+    if let Some(art_dir) = &config.art_dir {
+        if outcomes.iter().any(|(_, outcome)| *outcome == Outcome::Created) {
+            save_cover_art(input_path, art_dir)?;
+        }
+    }
+    Ok(outcomes)
+}
+
+/// Generates one image of several stems of a mix, like the drums, bass and
+/// vocals of a song.
+///
+/// Every stem is mixed to mono and drawn in a color of its own: as
+/// translucent layers over each other, or with `WaveLayout::Stacked` in a
+/// lane each, top to bottom in the given order.  The stems share the time
+/// axis, so a shorter stem ends before the right edge of the image.
+///
+/// The stems are plain peak bars, so only the size, colors, amplitude scale,
+/// normalization, mono style and time range of the config apply.
+///
+/// # Returns
+///
+/// The `Outcome` on success, or an error if a stem can not be processed
+pub fn generate_stems<P: AsRef<Path>>(
+    stems: &[P],
+    output_path: impl AsRef<Path>,
+    config: &WaveformConfig,
+) -> Result<Outcome> {
+    let output_path = output_path.as_ref();
+    let to_stdout = is_stdout(output_path);
+    let stems: Vec<&Path> = stems.iter().map(AsRef::as_ref).collect();
+
+    if !(2..=MAX_STEMS).contains(&stems.len()) {
+        return Err(WaverError::argument_error(format!("Stems need 2 to {MAX_STEMS} audio files")));
+    }
+    if stems.iter().any(|stem| is_stdin(stem)) {
+        return Err(WaverError::argument_error("Stems need audio files and can not be read from stdin"));
+    }
+    let colors = if config.stem_colors.is_empty() {
+        stem_colors(stems.len())
+    } else if config.stem_colors.len() >= stems.len() {
+        config.stem_colors.clone()
+    } else {
+        return Err(WaverError::argument_error(format!(
+            "{} stem colors are not enough for {} stems",
+            config.stem_colors.len(),
+            stems.len()
+        )));
+    };
+
+    // Each stem is a plain mono peak layer in a palette of the stem colors
+    let drawing = config.mode != RenderMode::Waveform
+        || config.tint_by_loudness
+        || config.antialias
+        || config.envelope
+        || config.rms_layer
+        || config.balance_strip.is_some()
+        || config.overview_ratio.is_some()
+        || config.duration_badge.is_some()
+        || config.center_line.is_some()
+        || config.grid.is_some()
+        || config.ticks.is_some()
+        || config.cue_markers
+        || config.markers.is_some()
+        || config.color_seed.is_some()
+        || config.auto_color
+        || config.color_map.is_some()
+        || config.trim_silence.is_some()
+        || config.channels != ChannelMode::Stereo;
+    let extra_files = config.tile_width.is_some()
+        || config.video.is_some()
+        || config.segmenting.is_some()
+        || config.sidecar
+        || config.embed_metadata
+        || config.art_dir.is_some()
+        || config.peak_cache
+        || config.preview
+        || config.silence.is_some()
+        || config.measure_loudness
+        || config.detect_bpm
+        || config.played_colors.is_some()
+        || config.checkerboard.is_some();
+    if drawing || extra_files {
+        return Err(WaverError::argument_error(
+            "Stems can only be drawn as plain waveforms in a single image, without the other drawing options or extra files",
+        ));
+    }
+
+    // The image is up to date if it is newer than every stem
+    if !to_stdout && output_path.exists() && !config.is_damaged(output_path) {
+        match config.existing_output {
+            ExistingOutput::Skip => return Ok(Outcome::Skipped),
+            ExistingOutput::IfNewer => {
+                let mut newer = false;
+                for stem in &stems {
+                    newer |= is_newer(stem, output_path)?;
+                }
+                if !newer {
+                    return Ok(Outcome::UpToDate);
+                }
+            }
+            ExistingOutput::Overwrite => {}
+        }
+    }
+
+    let stacked = config.layout == WaveLayout::Stacked;
+    let lanes = if stacked { stems.len() as u32 } else { 0 };
+    if config.height() < lanes * MIN_LANE_ROWS {
+        return Err(WaverError::generation_error(format!(
+            "The image must be at least {} pixels high for {lanes} lanes",
+            lanes * MIN_LANE_ROWS
+        )));
+    }
+
+    // The longest stem spans the whole width and the others their share
+    let peaks = info_span!("decode").in_scope(|| -> Result<_> {
+        let range = config.range.unwrap_or(TimeRange { start: 0.0, end: None });
+        let mut spans = Vec::with_capacity(stems.len());
+        for stem in &stems {
+            let duration = probe_duration(stem)?;
+            spans.push((range.end.unwrap_or(duration).min(duration) - range.start).max(0.0));
+        }
+        let longest = spans.iter().copied().fold(0.0, f64::max);
+
+        let mut peaks = Vec::with_capacity(stems.len());
+        for (stem, span) in stems.iter().zip(spans) {
+            let share = if longest > 0.0 { span / longest } else { 1.0 };
+            let scan = ScanOptions {
+                width: ((config.width() as f64 * share).round() as u32).max(1),
+                range: config.range,
+                channels: ChannelMode::Mono,
+                max_packets: config.max_packets,
+                ..ScanOptions::default()
+            };
+            let (source, hint) = open_source(stem, None)?;
+            peaks.push(process_audio_file(source, &hint, stem, &scan)?.0);
+        }
+        Ok(peaks)
+    })?;
+
+    let render_span = info_span!("render").entered();
+    let options = RenderOptions {
+        normalize: config.normalize,
+        auto_gain: config.auto_gain,
+        map: config.map,
+        ..RenderOptions::default()
+    };
+    let image_options = ImageOptions {
+        lanes,
+        mono_style: config.mono_style,
+        stems: stems.len() as u32,
+        ..ImageOptions::default()
+    };
+    let mut image = image_pool::take(config.width, config.height, &image_options);
+    image.set_stem_colors(&colors);
+    render_stems(&peaks, &mut image, &options);
+    drop(render_span);
+
+    if config.dry_run {
+        image_pool::give_back(config.width, config.height, &image_options, image);
+        return Ok(Outcome::DryRun);
+    }
+
+    // The palette has the stem colors, the channel colors go unused
+    let _write_span = info_span!("write").entered();
+    let channels = (&config.left_color, &config.right_color);
+    if to_stdout {
+        let mut out = BufWriter::new(io::stdout().lock());
+        write_image(&image, config, channels, &[], &mut out)?;
+        out.flush()?;
+    } else {
+        let mut out = audit::create_atomic(output_path)?;
+        write_image(&image, config, channels, &[], &mut out)?;
+        out.commit()?;
+    }
+
+    image_pool::give_back(config.width, config.height, &image_options, image);
+    Ok(Outcome::Created)
+}
+
+/// Returns whether the audio of the file is longer than `seconds`, before
+/// any of it is decoded.
+///
+/// Most containers record the length of the track, otherwise the packets
+/// are read (without decoding them) only until one ends past it.
+pub(crate) fn longer_than(input_path: &Path, seconds: f64) -> Result<bool> {
+    let OpenTrack {
+        mut format,
+        track_id,
+        decoder,
+        params,
+        sample_rate,
+        n_frames,
+        ..
+    } = open_track(Box::new(File::open(input_path)?), &Hint::new(), input_path)?;
+
+    let limit = (seconds * sample_rate as f64) as u64;
+    let longer = match n_frames {
+        Some(frames) => frames > limit,
+        None => {
+            let mut guard = PacketGuard::new(None, None);
+            let mut longer = false;
+            while let Ok(packet) = format.next_packet() {
+                guard.count(input_path)?;
+                let end = ts_to_frame(packet.ts() + packet.dur(), params.time_base, sample_rate);
+                if packet.track_id() == track_id && end > limit {
+                    longer = true;
+                    break;
+                }
+            }
+            longer
+        }
+    };
+    decoder_pool::give_back(params, decoder);
+    Ok(longer)
+}
+
+/// Returns the duration of an audio file in seconds.
+///
+/// Most containers record the length of the track, otherwise the packets
+/// are read through (without decoding them) to find the end.
+fn probe_duration(input_path: &Path) -> Result<f64> {
+    let OpenTrack {
+        mut format,
+        track_id,
+        decoder,
+        params,
+        sample_rate,
+        channels,
+        n_frames,
+    } = open_track(Box::new(File::open(input_path)?), &Hint::new(), input_path)?;
+
+    let frames = match n_frames {
+        Some(frames) => frames,
+        None => count_frames(format.as_mut(), track_id, &params, input_path)?,
+    };
+    decoder_pool::give_back(params, decoder);
+
+    let info = AudioInfo {
+        sample_rate,
+        channels,
+        frames,
+        pcm_sha256: None,
+        loudness: None,
+        bpm: None,
+    };
+    Ok(info.duration())
+}
+
+/// Returns the number of frames of a track by reading through its packets
+/// (without decoding them), for containers that do not record the length.
+pub(crate) fn count_frames(
+    format: &mut dyn FormatReader,
+    track_id: u32,
+    params: &CodecParameters,
+    name: &Path,
+) -> Result<u64> {
+    let mut end = 0;
+    let mut guard = PacketGuard::new(None, None);
+    while let Ok(packet) = format.next_packet() {
+        guard.count(name)?;
+        if packet.track_id() == track_id {
+            end = end.max(packet.ts() + packet.dur());
+        }
+    }
+    Ok(ts_to_frame(end, params.time_base, params.sample_rate.unwrap_or(0)))
+}
+
+/// Generates the spectrogram of an audio file (see `spectrum`), the
+/// `RenderMode::Spectrogram` part of `generate_waveform`.
+fn generate_spectrogram(input_path: &Path, output_path: &Path, config: &WaveformConfig) -> Result<Outcome> {
+    let (source, hint) = open_source(input_path, config.stdin_format.as_ref())?;
+    let scan = ScanOptions {
+        width: config.width(),
+        range: config.range,
+        channels: config.channels,
+        pcm_hash: config.pcm_hash,
+        max_packets: config.max_packets,
+        ..Default::default()
+    };
+    // The spectrum of each column is taken while decoding, so there is no
+    // separate render span
+    let (spectrogram, info) = info_span!("decode").in_scope(|| {
+        process_spectrogram(source, &hint, input_path, &scan, config.height(), config.map.floor_db)
+    })?;
+
+    if config.dry_run {
+        return Ok(Outcome::DryRun);
+    }
+
+    let _write_span = info_span!("write").entered();
+    let text = if config.embed_metadata { png_text(input_path, &info, None, config) } else { Vec::new() };
+    let encode = || info_span!("encode", format = "png");
+    if is_stdout(output_path) {
+        let mut out = BufWriter::new(io::stdout().lock());
+        encode().in_scope(|| spectrogram.write_png(config.colormap, &text, &mut out))?;
+        out.flush()?;
+    } else {
+        let mut out = audit::create_atomic(output_path)?;
+        encode().in_scope(|| spectrogram.write_png(config.colormap, &text, &mut out))?;
+        out.commit()?;
+    }
+
+    if config.sidecar {
+        let size = (spectrogram.width(), spectrogram.height());
+        let markers = cue_markers(input_path, true)?;
+        save_sidecar(input_path, output_path, output_path, size, info, markers, config)?;
+    }
+    if let Some(art_dir) = &config.art_dir {
+        save_cover_art(input_path, art_dir)?;
+    }
+    Ok(Outcome::Created)
+}
+
+/// Returns the column peaks from the peak cache of the audio file (see
+/// `peak_cache`), if `--peak-cache` is enabled.
+///
+/// A missing cache, or one older than the audio file or made with other
+/// channels, is built from the whole file first and saved (unless this is a
+/// dry run), and with `--peak-index` so is its index.  Returns `None` if the
+/// peaks have to be decoded instead: audio from stdin, or a cache with fewer
+/// blocks than the image has columns.
+fn cached_peaks(input_path: &Path, config: &WaveformConfig) -> Result<Option<(Peaks, AudioInfo)>> {
+    if !config.peak_cache || is_stdin(input_path) {
+        return Ok(None);
+    }
+
+    let cache_path = PeakCache::path_for(input_path);
+    let fresh = cache_path.exists() && !is_newer(input_path, &cache_path)?;
+    let (cache, built) = match fresh.then(|| PeakCache::load(&cache_path, config.channels)).flatten() {
+        Some(cache) => {
+            debug!(path = %cache_path.display(), "Using the peak cache");
+            (cache, false)
+        }
+        None => {
+            debug!(path = %cache_path.display(), "Building the peak cache");
+            let (source, hint) = open_source(input_path, config.stdin_format.as_ref())?;
+            let cache = process_peak_cache(source, &hint, input_path, config.channels, config.max_packets)?;
+            if !config.dry_run {
+                cache.save(&cache_path)?;
+            }
+            (cache, true)
+        }
+    };
+
+    // The index goes with the cache file, so it is only rewritten with it
+    let index_path = PeakIndex::path_for(&cache_path);
+    if config.peak_index && !config.dry_run && (built || !index_path.exists() || is_newer(&cache_path, &index_path)?) {
+        let name = cache_path.file_name().unwrap_or_default().to_string_lossy();
+        cache.index(&name).save(&index_path)?;
+    }
+
+    let Some((mut peaks, frames)) = cache.peaks(config.width(), config.range)? else {
+        return Ok(None);
+    };
+
+    // Seconds are just wider columns made from the same blocks, so they are
+    // rounded to whole blocks (and the last partial second is spread out)
+    if config.preview {
+        let seconds = (frames as f64 / cache.sample_rate().max(1) as f64).ceil().max(1.0) as u32;
+        if let Some((by_second, _)) = cache.peaks(seconds, config.range)? {
+            let stereo = by_second.stereo;
+            peaks.seconds = by_second
+                .columns
+                .iter()
+                .map(|column| if stereo { column.left.max(column.right) } else { column.left })
+                .collect();
+        }
+    }
+
+    let info = AudioInfo {
+        sample_rate: cache.sample_rate(),
+        channels: cache.channels(),
+        frames,
+        pcm_sha256: None,
+        loudness: None,
+        bpm: None,
+    };
+    Ok(Some((peaks, info)))
+}
+
+/// Draws the grid lines at every multiple of `interval` seconds of the
+/// audio, so with a time range they stay on the same times as in the
+/// whole track.
+///
+/// The grid shares the palette entry of the center line, so it takes the
+/// center line color if there is one and otherwise the left color faded
+/// three quarters of the way to the background.
+fn draw_time_grid(image: &mut WaveImage, config: &WaveformConfig, interval: f64, duration: f64) {
+    if let Some((spacing, color)) = time_ticks(image, config, interval, duration) {
+        image.draw_grid(&color, spacing.first_x, spacing.spacing);
+    }
+}
+
+/// Returns where the lines every `interval` seconds of the grid and the
+/// tick marks go in the image, with the color of the `AXIS` entry they are
+/// drawn in, or `None` for audio without a duration.
+fn time_ticks(image: &WaveImage, config: &WaveformConfig, interval: f64, duration: f64) -> Option<(TickSpacing, Rgba)> {
+    if duration <= 0.0 {
+        return None;
+    }
+    let start = config.range.as_ref().map_or(0.0, |range| range.start);
+    Some((TickSpacing::new(interval, start, duration, image.width()), axis_color(config)))
+}
+
+/// Returns the color of the grid, the tick marks and the markers: the
+/// center line color, or else a faint left color.
+fn axis_color(config: &WaveformConfig) -> Rgba {
+    config
+        .center_line
+        .unwrap_or_else(|| config.left_color.mix(&config.background_color, 0.75))
+}
+
+/// Reads the markers of the cuesheet embedded in the audio file if they
+/// are `wanted`, which stdin can not be probed for a second time.
+fn cue_markers(input_path: &Path, wanted: bool) -> Result<Vec<Marker>> {
+    if wanted && !is_stdin(input_path) {
+        read_markers(input_path)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// Returns the nearest column of every marker in an image `width` pixels
+/// wide of `duration` seconds of audio from the start of the time range,
+/// leaving out the markers outside of the range.
+fn marker_columns(markers: &[Marker], config: &WaveformConfig, duration: f64, width: u32) -> Vec<u32> {
+    if duration <= 0.0 {
+        return Vec::new();
+    }
+    let start = config.range.as_ref().map_or(0.0, |range| range.start);
+    let pixels_per_second = width as f64 / duration;
+    markers
+        .iter()
+        .map(|marker| ((marker.seconds - start) * pixels_per_second).round())
+        .filter(|&x| x >= 0.0 && x < width as f64)
+        .map(|x| x as u32)
+        .collect()
+}
+
+/// Writes the JSON sidecar of an image next to it.
+///
+/// `target_path` is the file the sidecar describes, which in tiles mode is
+/// the manifest rather than `output_path`.  The colors of a waveform are
+/// those it was drawn with; a spectrogram has none.
+fn save_sidecar(
+    input_path: &Path,
+    output_path: &Path,
+    target_path: &Path,
+    (width, height): (u32, u32),
+    info: AudioInfo,
+    markers: Vec<Marker>,
+    config: &WaveformConfig,
+) -> Result<()> {
+    let colors = (config.mode != RenderMode::Spectrogram).then(|| Colors {
+        background: config.background_color,
+        left: config.left_color,
+        right: config.right_color,
+        center_line: config.center_line,
+        played_left: config.played_colors.map(|(left, _)| left),
+        played_right: config.played_colors.map(|(_, right)| right),
+    });
+    Sidecar {
+        source: input_path.display().to_string(),
+        image: target_path.display().to_string(),
+        width,
+        height,
+        duration_seconds: info.duration(),
+        sample_rate: info.sample_rate,
+        channels: info.channels,
+        frames: info.frames,
+        pcm_sha256: info.pcm_sha256,
+        loudness: info.loudness,
+        colors,
+        markers,
+        run_id: config.run_id.clone(),
+    }
+    .save(Sidecar::path_for(output_path))
+}
+
+/// Returns the audio facts embedded in a PNG as text chunks, so the
+/// duration can be read from the image without probing the audio again.
+///
+/// The duration is that of the rendered audio (the time range if one was
+/// given) in seconds.  A waveform has the hash of its column peaks (see
+/// `Peaks::hash`) for `waver diff`.  The integrated loudness (LUFS) and true
+/// peak (dBTP) are written when measured, and with `--run-id` the ID of the
+/// run too.
+fn png_text(
+    input_path: &Path,
+    info: &AudioInfo,
+    peaks: Option<&Peaks>,
+    config: &WaveformConfig,
+) -> Vec<(&'static str, String)> {
+    let source = input_path.file_name().unwrap_or(input_path.as_os_str());
+    let mut text = vec![
+        ("Software", format!("waver {}", env!("CARGO_PKG_VERSION"))),
+        ("Source File", source.to_string_lossy().into_owned()),
+        ("Duration", format!("{:.3}", info.duration())),
+        ("Sample Rate", info.sample_rate.to_string()),
+        ("Channels", info.channels.to_string()),
+    ];
+    text.extend(peaks.map(|peaks| (PEAK_HASH_TEXT, peaks.hash())));
+    if let Some(loudness) = &info.loudness {
+        text.extend(loudness.integrated_lufs.map(|lufs| ("Integrated Loudness", format!("{lufs:.1}"))));
+        text.extend(loudness.true_peak_dbtp.map(|dbtp| ("True Peak", format!("{dbtp:.1}"))));
+    }
+    text.extend(config.run_id.as_ref().map(|run_id| ("Run ID", run_id.to_string())));
+    text
+}
+
+/// Returns the waveform colors of the cover art of the audio file, or
+/// `None` if it has no art or the art can not be decoded.
+fn cover_art_colors(input_path: &Path) -> Result<Option<(Rgba, Rgba)>> {
+    let Some(art) = read_cover_art(input_path)? else {
+        return Ok(None);
+    };
+    match art_colors(&art) {
+        Ok(colors) => Ok(colors),
+        Err(err) => {
+            debug!(path = %input_path.display(), "Not using the colors of the cover art: {err}");
+            Ok(None)
+        }
+    }
+}
+
+/// Writes the cover art of the audio file into the directory (made if it
+/// does not exist).  Files without art are left out.
+fn save_cover_art(input_path: &Path, dir: &Path) -> Result<()> {
+    let Some(art) = read_cover_art(input_path)? else {
+        return Ok(());
+    };
+    audit::create_dir_all(dir)?;
+    let path = art.path_for(dir, input_path);
+    audit::write(&path, &art.data)?;
+    debug!(path = %path.display(), "Extracted the cover art");
+    Ok(())
+}
+
+/// What is measured of the audio while decoding it for the image (see
+/// `generate_waveform_measured`).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Measures {
+    /// Integrated loudness and true peak, with `measure_loudness`
+    pub loudness: Option<Loudness>,
+    /// Estimated tempo in BPM, with `detect_bpm`
+    pub bpm: Option<f64>,
+}
+
+impl Measures {
+    fn of(info: &AudioInfo) -> Self {
+        Self { loudness: info.loudness, bpm: info.bpm }
+    }
+}
+
+/// Basic facts about a decoded audio track.
+#[derive(Debug, Clone)]
+pub struct AudioInfo {
+    /// Sample rate in Hz
+    pub sample_rate: u32,
+    /// Number of channels in the track
+    pub channels: usize,
+    /// Number of frames (samples per channel) that were decoded
+    pub frames: u64,
+    /// SHA-256 (hex) of the decoded PCM samples, if it was computed
+    pub pcm_sha256: Option<String>,
+    /// Integrated loudness and true peak of the decoded samples, if they
+    /// were measured
+    pub loudness: Option<Loudness>,
+    /// Estimated tempo of the decoded samples in BPM, if it was detected
+    pub bpm: Option<f64>,
+}
+
+impl AudioInfo {
+    /// Returns the duration of the track in seconds.
+    pub fn duration(&self) -> f64 {
+        if self.sample_rate == 0 {
+            return 0.0;
+        }
+        self.frames as f64 / self.sample_rate as f64
+    }
+}
+
+/// Formats a duration in seconds as `m:ss`, or `h:mm:ss` from one hour on,
+/// rounded to the nearest second.
+pub fn format_duration(seconds: f64) -> String {
+    let total = seconds.max(0.0).round() as u64;
+    let (hours, minutes, seconds) = (total / 3600, (total / 60) % 60, total % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+/// Returns a size in bytes in the unit that fits it.
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{bytes} B"),
+        0x400..0x10_0000 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        0x10_0000..0x4000_0000 => format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0)),
+        _ => format!("{:.1} GiB", bytes as f64 / (1024.0 * 1024.0 * 1024.0)),
+    }
+}
+
+/// Hashes the decoded PCM samples, independent of the container and tags.
+///
+/// The samples of all channels are hashed interleaved, frame by frame, as
+/// little endian `f32` values so that the same audio stored in a different
+/// lossless container (or with different tags) gets the same hash.
+struct PcmHasher {
+    hasher: Sha256,
+    /// Reused buffer for the interleaved bytes of one packet
+    scratch: Vec<u8>,
+}
+
+impl PcmHasher {
+    fn new() -> Self {
+        Self {
+            hasher: Sha256::new(),
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Adds the given frames of one decoded packet to the hash.
+    fn update(&mut self, buffer: &AudioBuffer<f32>, frames: Range<usize>) {
+        let channels = buffer.spec().channels.count();
+        self.scratch.clear();
+        self.scratch.reserve(frames.len() * channels * 4);
+        for frame in frames {
+            for channel in 0..channels {
+                self.scratch.extend_from_slice(&buffer.chan(channel)[frame].to_le_bytes());
+            }
+        }
+        self.hasher.update(&self.scratch);
+    }
+
+    /// Returns the hash as a lowercase hex string.
+    fn finish(self) -> String {
+        self.hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+}
+
+/// Returns true if the source file was modified after the target file.
+///
+/// # Arguments
+///
+/// * `source` - The file the target is generated from
+/// * `target` - The generated file (must exist)
+fn is_newer(source: &Path, target: &Path) -> Result<bool> {
+    let source_time = source.metadata()?.modified()?;
+    let target_time = target.metadata()?.modified()?;
+    Ok(source_time > target_time)
+}
+
+/// Encodes the image in the configured format into the writer, with the
+/// given left and right channel colors and (for PNG) text chunks.
+pub(crate) fn write_image(
+    image: &WaveImage,
+    config: &WaveformConfig,
+    (left, right): (&Rgba, &Rgba),
+    text: &[(&str, String)],
+    writer: impl Write,
+) -> Result<()> {
+    let _span = info_span!("encode", format = ?config.format).entered();
+    let background = &config.background_color;
+    match config.format {
+        ImageFormat::Png if text.is_empty() => match config.background_image() {
+            Some(backdrop) => image.write_png_over(background, left, right, backdrop, text, writer),
+            None => image.write_png(background, left, right, writer),
+        },
+        ImageFormat::Png => {
+            // The colors this image was written with go along with the
+            // audio facts, so it can be drawn again the same way
+            let mut text = text.to_vec();
+            text.push(("Background Color", background.to_string()));
+            text.push(("Left Color", left.to_string()));
+            text.push(("Right Color", right.to_string()));
+            match config.background_image() {
+                Some(backdrop) => image.write_png_over(background, left, right, backdrop, &text, writer),
+                None => image.write_png_with_text(background, left, right, &text, writer),
+            }
+        }
+        ImageFormat::Jpeg => image.write_jpeg(background, left, right, config.jpeg_quality.value(), writer),
+        ImageFormat::Bmp => image.write_bmp(background, left, right, writer),
+        ImageFormat::Rgb565 => image.write_rgb565(background, left, right, writer),
+        ImageFormat::Ppm => image.write_ppm(background, left, right, writer),
+        ImageFormat::Farbfeld => image.write_farbfeld(background, left, right, writer),
+        ImageFormat::Qoi => image.write_qoi(background, left, right, writer),
+        ImageFormat::Webp => image.write_webp(background, left, right, writer),
+        #[cfg(feature = "avif")]
+        ImageFormat::Avif => image.write_avif(background, left, right, config.jpeg_quality.value(), writer),
+        #[cfg(not(feature = "avif"))]
+        ImageFormat::Avif => unreachable!("The config rejects AVIF without the avif feature"),
+    }
+}
+
+/// Gives access to decoded packets as `f32` samples without allocating a
+/// buffer for every packet.
+///
+/// Packets the decoder already produces as `f32` are used as they are, other
+/// sample formats are converted into one buffer that is reused for as long
+/// as the packets keep the same capacity and signal spec (which for most
+/// decoders is the whole stream).
+#[derive(Default)]
+struct F32Converter {
+    buffer: Option<AudioBuffer<f32>>,
+}
+
+impl F32Converter {
+    /// Returns the samples of the decoded packet as `f32`.
+    fn convert<'a>(&'a mut self, decoded: &'a AudioBufferRef<'_>) -> &'a AudioBuffer<f32> {
+        if let AudioBufferRef::F32(buffer) = decoded {
+            return buffer;
+        }
+
+        // The conversion copies each channel at the offset of the source
+        // capacity, so the buffer must match it exactly and not just be large enough
+        let (capacity, spec) = (decoded.capacity(), *decoded.spec());
+        let buffer = match self.buffer.take() {
+            Some(buffer) if buffer.capacity() == capacity && *buffer.spec() == spec => buffer,
+            _ => AudioBuffer::new(capacity as u64, spec),
+        };
+        let buffer = self.buffer.insert(buffer);
+        decoded.convert(buffer);
+        buffer
+    }
+}
+
+/// An opened audio file, ready to decode its first audio track.
+struct OpenTrack {
+    format: Box<dyn FormatReader>,
+    /// Id of the decoded track (to seek in it)
+    track_id: u32,
+    decoder: Box<dyn Decoder>,
+    /// The codec parameters the decoder was made for (to give it back to the pool)
+    params: CodecParameters,
+    /// Sample rate in Hz (0 if unknown)
+    sample_rate: u32,
+    /// Number of channels in the track
+    channels: usize,
+    /// Number of frames in the track, if the container records it
+    n_frames: Option<u64>,
+}
+
+/// Opens the audio at the input path, or all of stdin for `-`.
+///
+/// Stdin is read into memory first: the length of the track is needed
+/// before the first column can be drawn, and formats like MP3 only know
+/// it after seeking through the stream.  The format of stdin (a file
+/// extension like `mp3`) is passed on as a hint for the probe.
+fn open_source(
+    input_path: &Path,
+    stdin_format: Option<&FileExtension>,
+) -> Result<(Box<dyn MediaSource>, Hint)> {
+    let mut hint = Hint::new();
+    if !is_stdin(input_path) {
+        return Ok((Box::new(File::open(input_path)?), hint));
+    }
+
+    if let Some(format) = stdin_format {
+        hint.with_extension(format.as_str());
+    }
+    let mut bytes = Vec::new();
+    io::stdin().lock().read_to_end(&mut bytes)?;
+    Ok((Box::new(Cursor::new(bytes)), hint))
+}
+
+/// Probes an audio source and sets up the decoder for its first audio track.
+///
+/// The name is only used in error messages.
+fn open_track(source: Box<dyn MediaSource>, hint: &Hint, name: &Path) -> Result<OpenTrack> {
+    let mss = MediaSourceStream::new(source, Default::default());
 
     let probed = symphonia::default::get_probe().format(
-        &Hint::new(),
+        hint,
         mss,
         &FormatOptions::default(),
         &MetadataOptions::default(),
     )?;
 
     // Extract the first audio track
-    let mut format = probed.format;
+    let format = probed.format;
     let track = format
         .tracks()
         .iter()
         .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
-        .ok_or_else(|| WaverError::generation_error(format!(
-            "No audio track found in '{}'",
-            input_path.display()
-        )))?;
-
-    // Initialize decoder
-    let mut decoder = symphonia::default::get_codecs().make(
-        &track.codec_params,
-        &DecoderOptions { ..Default::default() },
-    )?;
+        .ok_or_else(|| WaverError::no_audio_track(name))?;
+
+    // Initialize decoder, reusing one from an earlier file of the same format
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+    let decoder = decoder_pool::take(&params)?;
+
+    // The sample rate is needed to turn the decoded frame count into a duration
+    let sample_rate = params.sample_rate.unwrap_or(0);
+    let channels = params.channels.map(|c| c.count()).unwrap_or(1);
+    let n_frames = params.n_frames;
+
+    Ok(OpenTrack {
+        format,
+        track_id,
+        decoder,
+        params,
+        sample_rate,
+        channels,
+        n_frames,
+    })
+}
+
+/// Decodes a whole audio file into mono samples (the average of the first
+/// two channels).
+///
+/// Unlike `process_audio_file` this keeps all of the samples in memory, so it
+/// is only meant for tools that need the samples themselves (like comparing
+/// two files) and not for the waveform generation.
+pub fn decode_mono(input_path: &Path) -> Result<(Vec<f32>, AudioInfo)> {
+    let OpenTrack {
+        mut format,
+        mut decoder,
+        params,
+        sample_rate,
+        channels,
+        n_frames,
+        ..
+    } = open_track(Box::new(File::open(input_path)?), &Hint::new(), input_path)?;
+
+    let mut samples = Vec::with_capacity(n_frames.unwrap_or(0) as usize);
+    let mut converter = F32Converter::default();
+    let mut guard = PacketGuard::new(n_frames, None);
+    while let Ok(packet) = format.next_packet() {
+        guard.count(input_path)?;
+        let decoded = decoder.decode(&packet)?;
+        let buffer = converter.convert(&decoded);
+
+        if buffer.spec().channels.count() > 1 {
+            let (left, right) = (buffer.chan(0), buffer.chan(1));
+            samples.extend(left.iter().zip(right).map(|(l, r)| (l + r) * 0.5));
+        } else {
+            samples.extend_from_slice(buffer.chan(0));
+        }
+    }
+
+    decoder_pool::give_back(params, decoder);
+
+    let info = AudioInfo {
+        sample_rate,
+        channels,
+        frames: samples.len() as u64,
+        pcm_sha256: None,
+        loudness: None,
+        bpm: None,
+    };
+    Ok((samples, info))
+}
+
+/// Decodes every packet of an audio file into `f32` samples and drops them,
+/// for timing the decoder by itself (`waver bench`).
+pub(crate) fn decode_only(input_path: &Path, max_packets: Option<u64>) -> Result<AudioInfo> {
+    let OpenTrack {
+        mut format,
+        track_id,
+        mut decoder,
+        params,
+        sample_rate,
+        channels,
+        n_frames,
+    } = open_track(Box::new(File::open(input_path)?), &Hint::new(), input_path)?;
+
+    let mut frames = 0;
+    let mut converter = F32Converter::default();
+    let mut guard = PacketGuard::new(n_frames, max_packets);
+    while let Ok(packet) = format.next_packet() {
+        guard.count(input_path)?;
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = decoder.decode(&packet)?;
+        frames += converter.convert(&decoded).frames() as u64;
+    }
+    decoder_pool::give_back(params, decoder);
+
+    Ok(AudioInfo {
+        sample_rate,
+        channels,
+        frames,
+        pcm_sha256: None,
+        loudness: None,
+        bpm: None,
+    })
+}
+
+/// Decodes a whole audio file and returns its quality measures and score
+/// (see `quality`), for `--qc`.
+///
+/// Like `decode_mono` this decodes the file again, apart from its image, so
+/// the score does not depend on the time range or the channels drawn.
+pub fn scan_quality(input_path: &Path, weights: &QualityWeights, max_packets: Option<u64>) -> Result<Quality> {
+    let OpenTrack {
+        mut format,
+        mut decoder,
+        params,
+        sample_rate,
+        n_frames,
+        ..
+    } = open_track(Box::new(File::open(input_path)?), &Hint::new(), input_path)?;
+
+    let mut scanner = QualityScanner::new(sample_rate);
+    let mut converter = F32Converter::default();
+    let mut guard = PacketGuard::new(n_frames, max_packets);
+    while let Ok(packet) = format.next_packet() {
+        guard.count(input_path)?;
+        let decoded = decoder.decode(&packet)?;
+        let buffer = converter.convert(&decoded);
+        scanner.push(buffer.planes().planes());
+    }
+
+    decoder_pool::give_back(params, decoder);
+    Ok(scanner.finish(weights))
+}
+
+/// Returns the time range of the audio file from the first to the last
+/// sample above the threshold (in dBFS), or `None` if it is all silence.
+pub(crate) fn silence_range(input_path: &Path, threshold_db: f32, config: &WaveformConfig) -> Result<Option<TimeRange>> {
+    let (source, hint) = open_source(input_path, config.stdin_format.as_ref())?;
+    let OpenTrack {
+        mut format,
+        mut decoder,
+        params,
+        sample_rate,
+        n_frames,
+        ..
+    } = open_track(source, &hint, input_path)?;
+
+    let mut bounds = SilenceBounds::new(threshold_db);
+    let mut converter = F32Converter::default();
+    let mut guard = PacketGuard::new(n_frames, config.max_packets);
+    while let Ok(packet) = format.next_packet() {
+        guard.count(input_path)?;
+        let decoded = decoder.decode(&packet)?;
+        let buffer = converter.convert(&decoded);
+        bounds.push(buffer.planes().planes());
+    }
+
+    decoder_pool::give_back(params, decoder);
+    Ok(bounds.range(sample_rate))
+}
+
+/// The frames of a track selected by a `TimeRange`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FrameSelection {
+    /// First selected frame
+    start: u64,
+    /// Frame just past the selection, if the end is known
+    end: Option<u64>,
+}
+
+impl FrameSelection {
+    /// Returns true if a packet starting at the frame is past the selection.
+    fn is_after(&self, packet_start: u64) -> bool {
+        self.end.is_some_and(|end| packet_start >= end)
+    }
+
+    /// Returns the selected part of a packet of `frames` frames that starts
+    /// at the given frame, as a range of indices into the packet.
+    fn within(&self, packet_start: u64, frames: usize) -> (usize, usize) {
+        let clamp = |frame: u64| frame.saturating_sub(packet_start).min(frames as u64) as usize;
+        let first = clamp(self.start);
+        let last = self.end.map_or(frames, clamp);
+        (first, last.max(first))
+    }
+}
+
+/// Converts a packet timestamp in the time base of its track to frames.
+fn ts_to_frame(ts: u64, time_base: Option<TimeBase>, sample_rate: u32) -> u64 {
+    match time_base {
+        Some(TimeBase { numer, denom }) if sample_rate > 0 => {
+            (ts as u128 * numer as u128 * sample_rate as u128 / denom as u128) as u64
+        }
+        _ => ts,
+    }
+}
+
+/// Finds the frames of the time range and seeks to just before its start.
+///
+/// The end is clamped to the length of the track if that is known, and a
+/// start past the end of the track is an error.
+fn select_frames(
+    format: &mut dyn FormatReader,
+    decoder: &mut dyn Decoder,
+    track_id: u32,
+    sample_rate: u32,
+    n_frames: Option<u64>,
+    range: TimeRange,
+) -> Result<FrameSelection> {
+    let to_frame = |seconds: f64| (seconds * sample_rate as f64).round() as u64;
+    let start = to_frame(range.start);
+    let end = match (range.end.map(to_frame), n_frames) {
+        (Some(end), Some(n_frames)) => Some(end.min(n_frames)),
+        (end, n_frames) => end.or(n_frames),
+    };
+    if end.is_some_and(|end| start >= end) {
+        return Err(WaverError::generation_error(
+            "The start time is past the end of the audio",
+        ));
+    }
+
+    // The accurate seek lands on a packet at or before the start, the
+    // frames before the start are then cut off the decoded packets
+    if start > 0 {
+        format.seek(
+            SeekMode::Accurate,
+            SeekTo::Time {
+                time: Time::from(range.start),
+                track_id: Some(track_id),
+            },
+        )?;
+        decoder.reset();
+    }
+    Ok(FrameSelection { start, end })
+}
+
+/// Processes an audio file into per-column peaks using a streaming approach.
+///
+/// This function opens an audio file, decodes it frame by frame, and immediately
+/// reduces each frame into the peak of its pixel column, without storing all audio data in memory.
+///
+/// # Performance
+///
+/// This is a performance-critical function.  It uses a streaming approach rather than buffering the
+/// entire audio file, which results in:
+/// - ~24x lower memory usage
+/// - ~6.7x faster execution time
+/// - Significantly fewer system calls
+///
+/// Do not change this to buffer all audio samples, as that would cause severe performance degradation.
+///
+/// # Arguments
+///
+/// * `source` - The encoded audio, from a file or any other media source
+/// * `hint` - What is known about the format of the source
+/// * `name` - Name of the source for error messages
+/// * `scan` - The width, time range and what to collect besides the peaks
+///
+/// # Returns
+///
+/// The column `Peaks` and `AudioInfo` of the decoded track on success, or an error if processing fails
+fn process_audio_file(
+    source: Box<dyn MediaSource>,
+    hint: &Hint,
+    name: &Path,
+    scan: &ScanOptions,
+) -> Result<(Peaks, AudioInfo)> {
+    let width = scan.width;
+    let OpenTrack {
+        mut format,
+        track_id,
+        mut decoder,
+        params,
+        sample_rate,
+        channels: track_channels,
+        n_frames,
+    } = open_track(source, hint, name)?;
+    let stereo = scan.channels.is_stereo(track_channels);
 
-    // Get channel information
-    let channel_count = track
-        .codec_params
-        .channels
-        .map(|c| c.count())
-        .unwrap_or(1)
-        .min(2) as usize;
+    // Only the frames of the selected time range are drawn, and the part
+    // before its start is skipped by seeking rather than decoding it
+    let selection = scan
+        .range
+        .map(|range| select_frames(&mut *format, &mut *decoder, track_id, sample_rate, n_frames, range))
+        .transpose()?;
 
     // Get total number of frames (samples per channel) for scaling calculation
-    let total_samples = track.codec_params.n_frames.unwrap_or(0).max(1) as u64;
+    let total_samples = match selection {
+        Some(selection) => selection.end.map_or(0, |end| end - selection.start),
+        None => n_frames.unwrap_or(0),
+    }
+    .max(1);
 
     // Calculate samples per pixel and the fractional
     // samples per pixel in 1/width units - since we have
@@ -135,35 +1592,78 @@ fn process_audio_file(input_path: &Path, image: &mut WaveImage, width: u32) -> R
     let mut left = 0.0f32;
     let mut right = 0.0f32;
     let mut sample_progress = samples_per_pixel;
-    let mut partial_progress = 0 as u64;
+    let mut partial_progress = 0_u64;
     let mut pixel_pos = 0;
+    let mut frames = 0_u64;
+    let mut hasher = scan.pcm_hash.then(PcmHasher::new);
+    let mut peaks = Peaks::new(stereo, width);
+    let mut seconds = scan.preview.then(|| SecondPeaks::new(sample_rate));
+    let offset = selection.map_or(0, |selection| selection.start);
+    let mut silence = scan.silence.map(|detection| SilenceDetector::new(detection, sample_rate, offset));
+    let mut loudness = scan.loudness.then(|| LoudnessMeter::new(sample_rate, track_channels));
+    let mut tempo = scan.bpm.then(|| TempoDetector::new(sample_rate));
+    let mut lane_peaks = Vec::new();
+    if scan.lanes {
+        peaks = peaks.with_lanes(track_channels, width);
+        lane_peaks = vec![0.0f32; track_channels];
+    }
+    let mut converter = F32Converter::default();
+    let mut mixer = ChannelMixer::default();
 
-    // Process audio stream packet by packet
+    // Sum of squares and envelope of the current pixel (only when needed)
+    let mut column = ColumnAccumulator::default();
+
+    // Process audio stream packet by packet (a damaged file may never end)
+    let mut guard = PacketGuard::new(n_frames, scan.max_packets);
     while let Ok(packet) = format.next_packet() {
+        guard.count(name)?;
+        // Nothing after the end of the selection is needed
+        let packet_start = ts_to_frame(packet.ts(), params.time_base, sample_rate);
+        if selection.is_some_and(|selection| selection.is_after(packet_start)) {
+            break;
+        }
+
         let decoded = decoder.decode(&packet)?;
-        let mut buffer = AudioBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
-        decoded.convert(&mut buffer);
+        let buffer = converter.convert(&decoded);
+        let (first, last) = match selection {
+            Some(selection) => selection.within(packet_start, buffer.frames()),
+            None => (0, buffer.frames()),
+        };
+        frames += (last - first) as u64;
+        if let Some(hasher) = hasher.as_mut() {
+            hasher.update(buffer, first..last);
+        }
 
-        // Process each frame in the current packet
-        for frame in 0..buffer.frames() {
-            // Update max amplitude values for each channel
-            left = left.max(buffer.chan(0)[frame].abs().min(1.0));
-            if channel_count > 1 {
-                right = right.max(buffer.chan(1)[frame].abs().min(1.0));
+        // Scan the packet in runs of the frames that belong to one pixel
+        // so the channels are processed as whole slices
+        let planes = buffer.planes();
+        let (left_samples, right_samples) = mixer.mix(planes.planes(), scan.channels);
+        // The track parameters decide the layout even if a packet disagrees
+        let right_samples = right_samples.filter(|_| stereo);
+        if let Some(seconds) = seconds.as_mut() {
+            seconds.push(&left_samples[first..last], right_samples.map(|samples| &samples[first..last]));
+        }
+        if silence.is_some() || loudness.is_some() || tempo.is_some() {
+            let selected = planes.planes().iter().map(|plane| &plane[first..last]).collect::<Vec<_>>();
+            if let Some(silence) = silence.as_mut() {
+                silence.push(&selected);
             }
-
-            // Map samples to pixels
-            sample_progress -= 1;
-
+            if let Some(loudness) = loudness.as_mut() {
+                loudness.push(&selected);
+            }
+            if let Some(tempo) = tempo.as_mut() {
+                tempo.push(&selected);
+            }
+        }
+        let mut pos = first;
+        while pos < last {
             if sample_progress == 0 {
-                // When we've accumulated enough samples for a pixel, draw it
-                if channel_count > 1 {
-                    image.draw_point(pixel_pos, left, right);
-                } else {
-                    image.draw_point_mono(pixel_pos, left);
-                }
+                // When we've accumulated enough samples for a pixel, record it
+                peaks.columns.push(column.peak(left, right));
+                push_lane_peaks(&mut peaks.lanes, &mut lane_peaks);
                 left = 0.0;
                 right = 0.0; // Reset max values for next pixel
+                column = ColumnAccumulator::default();
                 pixel_pos += 1;
                 sample_progress = samples_per_pixel;
                 partial_progress += fractional_samples;
@@ -174,18 +1674,297 @@ fn process_audio_file(input_path: &Path, image: &mut WaveImage, width: u32) -> R
                     partial_progress -= width64;
                     sample_progress += 1;
                 }
+                // Any frames past the expected length are not drawn
+                if pixel_pos >= width {
+                    sample_progress = u64::MAX;
+                }
+                continue;
             }
+
+            let end = pos + sample_progress.min((last - pos) as u64) as usize;
+            let run_left = &left_samples[pos..end];
+            let run_right = right_samples.map(|samples| &samples[pos..end]);
+
+            // Update max amplitude values for each channel
+            left = left.max(peak_abs(run_left));
+            if let Some(run_right) = run_right {
+                right = right.max(peak_abs(run_right));
+            }
+
+            if scan.rms {
+                column.add_squares(run_left, run_right);
+            }
+            if scan.envelope {
+                column.add_envelope(run_left, run_right);
+            }
+            for (peak, channel) in lane_peaks.iter_mut().zip(planes.planes()) {
+                *peak = peak.max(peak_abs(&channel[pos..end]));
+            }
+
+            sample_progress -= (end - pos) as u64;
+            pos = end;
         }
     }
 
-    // Draw any remaining partial pixel
+    // Record any remaining partial pixel
     if pixel_pos < width {
-        if channel_count > 1 {
-            image.draw_point(pixel_pos, left, right);
-        } else {
-            image.draw_point_mono(pixel_pos, left);
+        peaks.columns.push(column.peak(left, right));
+        push_lane_peaks(&mut peaks.lanes, &mut lane_peaks);
+    }
+
+    peaks.seconds = seconds.map(SecondPeaks::finish).unwrap_or_default();
+    peaks.silences = silence.map(SilenceDetector::finish).unwrap_or_default();
+
+    // The stream decoded without errors so the decoder can be reused
+    decoder_pool::give_back(params, decoder);
+
+    let info = AudioInfo {
+        sample_rate,
+        channels: track_channels,
+        frames,
+        pcm_sha256: hasher.map(PcmHasher::finish),
+        loudness: loudness.map(|meter| meter.finish()),
+        bpm: tempo.and_then(|detector| detector.finish()),
+    };
+    Ok((peaks, info))
+}
+
+/// Appends the peak of each channel to its lane and resets it for the next
+/// column.
+fn push_lane_peaks(lanes: &mut [Vec<f32>], peaks: &mut [f32]) {
+    for (lane, peak) in lanes.iter_mut().zip(peaks) {
+        lane.push(*peak);
+        *peak = 0.0;
+    }
+}
+
+/// Streams the decoded (mono) audio into a spectrogram of `scan.width` by
+/// `height` pixels.
+///
+/// This is the same streaming decode as `process_audio_file`, only the
+/// samples go to a `SpectrogramBuilder` instead of the column peaks.  Stereo
+/// audio is mixed to mono unless `scan.channels` selects something else.
+fn process_spectrogram(
+    source: Box<dyn MediaSource>,
+    hint: &Hint,
+    name: &Path,
+    scan: &ScanOptions,
+    height: u32,
+    floor_db: f32,
+) -> Result<(Spectrogram, AudioInfo)> {
+    let OpenTrack {
+        mut format,
+        track_id,
+        mut decoder,
+        params,
+        sample_rate,
+        channels,
+        n_frames,
+    } = open_track(source, hint, name)?;
+
+    let selection = scan
+        .range
+        .map(|range| select_frames(&mut *format, &mut *decoder, track_id, sample_rate, n_frames, range))
+        .transpose()?;
+    let total_samples = match selection {
+        Some(selection) => selection.end.map_or(0, |end| end - selection.start),
+        None => n_frames.unwrap_or(0),
+    };
+
+    let mode = match scan.channels {
+        ChannelMode::Stereo => ChannelMode::Mono,
+        mode => mode,
+    };
+    let mut builder = SpectrogramBuilder::new(scan.width, height, total_samples, floor_db);
+    let mut frames = 0_u64;
+    let mut hasher = scan.pcm_hash.then(PcmHasher::new);
+    let mut converter = F32Converter::default();
+    let mut mixer = ChannelMixer::default();
+
+    let mut guard = PacketGuard::new(n_frames, scan.max_packets);
+    while let Ok(packet) = format.next_packet() {
+        guard.count(name)?;
+        let packet_start = ts_to_frame(packet.ts(), params.time_base, sample_rate);
+        if selection.is_some_and(|selection| selection.is_after(packet_start)) {
+            break;
+        }
+
+        let decoded = decoder.decode(&packet)?;
+        let buffer = converter.convert(&decoded);
+        let (first, last) = match selection {
+            Some(selection) => selection.within(packet_start, buffer.frames()),
+            None => (0, buffer.frames()),
+        };
+        frames += (last - first) as u64;
+        if let Some(hasher) = hasher.as_mut() {
+            hasher.update(buffer, first..last);
         }
+
+        let planes = buffer.planes();
+        let (mono, _) = mixer.mix(planes.planes(), mode);
+        builder.push(&mono[first..last]);
     }
 
-    Ok(())
-}
\ No newline at end of file
+    decoder_pool::give_back(params, decoder);
+
+    let info = AudioInfo {
+        sample_rate,
+        channels,
+        frames,
+        pcm_sha256: hasher.map(PcmHasher::finish),
+        loudness: None,
+        bpm: None,
+    };
+    Ok((builder.finish(), info))
+}
+
+/// Streams the whole decoded audio into the blocks of a peak cache.
+///
+/// This is the same streaming decode as `process_audio_file`, with the
+/// channels mixed by `mode`, only the samples are reduced to fixed size
+/// blocks (see `PeakCacheBuilder`) instead of the columns of one image.
+fn process_peak_cache(
+    source: Box<dyn MediaSource>,
+    hint: &Hint,
+    name: &Path,
+    mode: ChannelMode,
+    max_packets: Option<u64>,
+) -> Result<PeakCache> {
+    let OpenTrack {
+        mut format,
+        mut decoder,
+        params,
+        sample_rate,
+        channels,
+        n_frames,
+        ..
+    } = open_track(source, hint, name)?;
+    let stereo = mode.is_stereo(channels);
+
+    let mut builder = PeakCacheBuilder::new(mode, stereo, sample_rate, channels);
+    let mut converter = F32Converter::default();
+    let mut mixer = ChannelMixer::default();
+
+    let mut guard = PacketGuard::new(n_frames, max_packets);
+    while let Ok(packet) = format.next_packet() {
+        guard.count(name)?;
+        let decoded = decoder.decode(&packet)?;
+        let buffer = converter.convert(&decoded);
+        let planes = buffer.planes();
+        let (left, right) = mixer.mix(planes.planes(), mode);
+        builder.push(left, right.filter(|_| stereo));
+    }
+
+    decoder_pool::give_back(params, decoder);
+    Ok(builder.finish())
+}
+
+/// What `process_audio_file` collects besides the column peaks.
+#[derive(Debug, Clone, Copy, Default)]
+struct ScanOptions {
+    /// Width of the output image in pixels (the number of columns)
+    width: u32,
+    /// The part of the audio to render, if not all of it
+    range: Option<TimeRange>,
+    /// Which channels are drawn, and how they are mixed
+    channels: ChannelMode,
+    /// Collect the per-column RMS (loudness tint, balance strip)
+    rms: bool,
+    /// Collect the per-column signed envelope
+    envelope: bool,
+    /// Collect the per-column peak of every channel of the track
+    lanes: bool,
+    /// Collect the peak of every second (see `preview`)
+    preview: bool,
+    /// Find the silences (see `silence`)
+    silence: Option<SilenceDetection>,
+    /// Measure the loudness of the track (see `loudness`)
+    loudness: bool,
+    /// Estimate the tempo of the track (see `tempo`)
+    bpm: bool,
+    /// Hash the decoded samples (see `PcmHasher`)
+    pcm_hash: bool,
+    /// The packets read before giving up instead of the limit from the
+    /// length of the track (see `PacketGuard`)
+    max_packets: Option<u64>,
+}
+
+/// Running sum of squares used to compute the RMS loudness of a column, and
+/// the running signed envelope of the column.
+struct ColumnAccumulator {
+    left: f32,
+    right: f32,
+    frames: u32,
+    left_range: (f32, f32),
+    right_range: (f32, f32),
+}
+
+impl Default for ColumnAccumulator {
+    fn default() -> Self {
+        // The ranges start empty so they cover only the samples seen
+        Self {
+            left: 0.0,
+            right: 0.0,
+            frames: 0,
+            left_range: (f32::INFINITY, f32::NEG_INFINITY),
+            right_range: (f32::INFINITY, f32::NEG_INFINITY),
+        }
+    }
+}
+
+impl ColumnAccumulator {
+    /// Adds the squares of a run of frames to the column (`right` is `None`
+    /// for mono).
+    fn add_squares(&mut self, left: &[f32], right: Option<&[f32]>) {
+        self.left += sum_squares(left);
+        self.right += right.map_or(0.0, sum_squares);
+        self.frames += left.len() as u32;
+    }
+
+    /// Widens the envelope of the column to a run of frames (`right` is
+    /// `None` for mono).
+    fn add_envelope(&mut self, left: &[f32], right: Option<&[f32]>) {
+        let widen = |range: &mut (f32, f32), samples: &[f32]| {
+            let (min, max) = min_max(samples);
+            *range = (range.0.min(min), range.1.max(max));
+        };
+        widen(&mut self.left_range, left);
+        if let Some(right) = right {
+            widen(&mut self.right_range, right);
+        }
+    }
+
+    /// Returns the envelope of left and right, clamped to full scale.
+    fn envelopes(&self) -> (Envelope, Envelope) {
+        let envelope = |(min, max): (f32, f32)| {
+            if min > max {
+                return Envelope::default();
+            }
+            Envelope {
+                min: min.clamp(-1.0, 1.0),
+                max: max.clamp(-1.0, 1.0),
+            }
+        };
+        (envelope(self.left_range), envelope(self.right_range))
+    }
+
+    /// Returns the RMS amplitudes of left and right.
+    fn rms(&self) -> (f32, f32) {
+        let frames = self.frames.max(1) as f32;
+        ((self.left / frames).sqrt(), (self.right / frames).sqrt())
+    }
+
+    /// Returns the column peak for the given maximum amplitudes.
+    fn peak(&self, left: f32, right: f32) -> ColumnPeak {
+        let (left_rms, right_rms) = self.rms();
+        let (left_envelope, right_envelope) = self.envelopes();
+        ColumnPeak {
+            left,
+            right,
+            left_rms,
+            right_rms,
+            left_envelope,
+            right_envelope,
+        }
+    }
+}
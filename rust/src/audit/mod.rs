@@ -0,0 +1,273 @@
+/// The audit log of the files waver writes (`--audit-log`).
+///
+/// In an archive every change to the stored files may have to be accounted
+/// for.  Every file waver writes (images, copies, tiles, sidecars, caches,
+/// reports, bundles, videos) is created through this module, which appends
+/// a JSON line to the audit log once one is opened with `open`:
+///
+/// ```text
+/// {"time":"2026-10-17T09:41:07Z","action":"overwrite","path":"/music/a.flac.png"}
+/// ```
+///
+/// The action is `create` for a new file, `overwrite` for a file that was
+/// replaced and `create_dir` for a new directory.  Waver deletes no files
+/// (but the temporary file of an image it could not finish).
+/// With `--run-id` each record also has the `run_id` of the run.
+/// The log is only ever appended to, one whole line per write so the
+/// records of the parallel workers do not mix, and a record that can not be
+/// written fails the file like any other write error.
+///
+/// The images are written atomically with `create_atomic`: to `name.tmp`
+/// next to the image, which is renamed to the image once it is complete,
+/// so an interrupted run never leaves a cut off image that the next run
+/// takes for a finished one.  The temporary file is synced to the disk
+/// before the rename, so after a power loss the image is either the old one
+/// or the whole new one.  A run killed outright (Ctrl-C, or a panic in a
+/// release build, which aborts) leaves its `name.tmp` behind; the next
+/// write of that image removes it.  `--no-atomic` writes them in place
+/// instead.
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::error::{Result, WaverError};
+use crate::summary::RunId;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The audit log of the process, once opened.
+static LOG: OnceLock<Log> = OnceLock::new();
+
+/// Whether the images are written to a temporary file first (on unless
+/// `--no-atomic`).
+static ATOMIC: AtomicBool = AtomicBool::new(true);
+
+/// An open audit log and the ID of the run it records.
+struct Log {
+    file: Mutex<File>,
+    run_id: Option<RunId>,
+}
+
+/// What was done to a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Create,
+    Overwrite,
+    CreateDir,
+}
+
+/// One line of the audit log.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Record {
+    /// When it was done, in UTC
+    pub time: String,
+    pub action: Action,
+    /// The absolute path
+    pub path: String,
+    /// The ID of the run (`--run-id`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<RunId>,
+}
+
+impl Record {
+    /// Creates the record of an action on a path done now by the run.
+    pub fn now(action: Action, path: &Path, run_id: Option<RunId>) -> Self {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        Self { time: utc_timestamp(seconds), action, path: path.display().to_string(), run_id }
+    }
+
+    /// Returns the record as a line of JSON, with its newline.
+    pub fn to_line(&self) -> String {
+        let mut line = serde_json::to_string(self).expect("A record is always valid JSON");
+        line.push('\n');
+        line
+    }
+}
+
+/// Opens the audit log of the process, creating it if needed, to append
+/// the records of the files the run writes from now on.
+///
+/// The log can be opened once; it stays open until the process ends.
+pub fn open(path: &Path, run_id: Option<RunId>) -> Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path).map_err(|err| {
+        WaverError::argument_error(format!("Can not open the audit log '{}': {err}", path.display()))
+    })?;
+    LOG.set(Log { file: Mutex::new(file), run_id })
+        .map_err(|_| WaverError::argument_error("The audit log is already open"))
+}
+
+/// Appends the record of an action on a path to the audit log, if one is
+/// open.
+pub fn record(action: Action, path: &Path) -> io::Result<()> {
+    let Some(log) = LOG.get() else {
+        return Ok(());
+    };
+    let line = Record::now(action, path, log.run_id.clone()).to_line();
+    log.file.lock().unwrap().write_all(line.as_bytes())
+}
+
+/// Returns the action of writing to a path: an overwrite if it exists.
+pub fn write_action(path: &Path) -> Action {
+    if path.exists() {
+        Action::Overwrite
+    } else {
+        Action::Create
+    }
+}
+
+/// Creates (or truncates) a file for writing, like `File::create`, and
+/// records it.
+pub fn create(path: impl AsRef<Path>) -> io::Result<File> {
+    let path = path.as_ref();
+    let action = write_action(path);
+    let file = File::create(path)?;
+    record(action, path)?;
+    Ok(file)
+}
+
+/// Opens a file to append to, creating it if needed, and records it if it
+/// was created.
+pub fn append(path: impl AsRef<Path>) -> io::Result<File> {
+    let path = path.as_ref();
+    let created = !path.exists();
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    if created {
+        record(Action::Create, path)?;
+    }
+    Ok(file)
+}
+
+/// Sets whether `create_atomic` writes to a temporary file that is renamed
+/// once it is complete, or to the path itself.
+pub fn set_atomic(atomic: bool) {
+    ATOMIC.store(atomic, Ordering::Relaxed);
+}
+
+/// A file being written, buffered and, unless `--no-atomic`, under a
+/// temporary name until it is committed.
+pub struct AtomicFile {
+    out: BufWriter<File>,
+    path: PathBuf,
+    /// The temporary file, until it is renamed to the path
+    temp: Option<PathBuf>,
+    action: Action,
+}
+
+impl AtomicFile {
+    /// Finishes the file: flushes it, syncs the temporary file to the disk
+    /// and renames it to the path, replacing the file there, and records it.
+    pub fn commit(mut self) -> io::Result<()> {
+        self.out.flush()?;
+        if let Some(temp) = &self.temp {
+            // Without the sync the rename can reach the disk before the data
+            self.out.get_ref().sync_all()?;
+            fs::rename(temp, &self.path)?;
+            self.temp = None;
+        }
+        record(self.action, &self.path)
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.out.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    /// Removes the temporary file of a file that was not committed.
+    fn drop(&mut self) {
+        if let Some(temp) = &self.temp {
+            let _ = fs::remove_file(temp);
+        }
+    }
+}
+
+/// Returns the temporary file an image is written to: `song.mp3.png.tmp`
+/// for `song.mp3.png`.
+pub fn temp_path(path: &Path) -> PathBuf {
+    let mut temp = OsString::from(path.as_os_str());
+    temp.push(".tmp");
+    PathBuf::from(temp)
+}
+
+/// Creates a file for writing like `create`, but (unless `--no-atomic`)
+/// under the temporary name, so the path only ever has a whole file.
+/// Nothing is at the path (or recorded) until the file is committed.
+pub fn create_atomic(path: impl AsRef<Path>) -> io::Result<AtomicFile> {
+    create_file(path.as_ref(), ATOMIC.load(Ordering::Relaxed))
+}
+
+/// Creates a file for writing, under the temporary name if `atomic`.
+fn create_file(path: &Path, atomic: bool) -> io::Result<AtomicFile> {
+    let path = path.to_path_buf();
+    let action = write_action(&path);
+    // The temporary file of a run that was killed is not left lying around
+    let temp = temp_path(&path);
+    match fs::remove_file(&temp) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => {}
+    }
+    if !atomic {
+        let out = BufWriter::new(File::create(&path)?);
+        return Ok(AtomicFile { out, path, temp: None, action });
+    }
+    let out = BufWriter::new(File::create(&temp)?);
+    Ok(AtomicFile { out, path, temp: Some(temp), action })
+}
+
+/// Writes a whole file, like `fs::write`, and records it.
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    create(path)?.write_all(contents.as_ref())
+}
+
+/// Creates a directory and its missing parents, like `fs::create_dir_all`,
+/// and records each directory that was created.
+pub fn create_dir_all(dir: &Path) -> io::Result<()> {
+    let missing = dir.ancestors().take_while(|ancestor| !ancestor.as_os_str().is_empty() && !ancestor.exists());
+    let mut missing = missing.map(Path::to_path_buf).collect::<Vec<_>>();
+    fs::create_dir_all(dir)?;
+    // Outermost first, the order they were made in
+    missing.reverse();
+    missing.iter().try_for_each(|created| record(Action::CreateDir, created))
+}
+
+/// Returns the UTC time of the seconds since the Unix epoch in the form
+/// `2026-10-17T09:41:07Z`.
+pub fn utc_timestamp(seconds: u64) -> String {
+    let days = (seconds / 86_400) as i64;
+    let time = seconds % 86_400;
+
+    // The civil date of the days since 1970-01-01, after Howard Hinnant's
+    // `civil_from_days`
+    let shifted = days + 719_468;
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+    use std::path::Path;
+
+    use tempfile::tempdir;
+
+    use crate::audit::{self, create_file, utc_timestamp, Action, Record};
+    use crate::summary::RunId;
+
+    #[test]
+    fn test_utc_timestamp() {
+        assert_eq!(utc_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(utc_timestamp(951_782_400), "2000-02-29T00:00:00Z", "A leap day");
+        assert_eq!(utc_timestamp(1_792_230_067), "2026-10-17T09:41:07Z");
+        assert_eq!(utc_timestamp(4_107_542_399), "2100-02-28T23:59:59Z", "2100 is not a leap year");
+    }
+
+    #[test]
+    fn test_record_line() {
+        let record = Record {
+            time: utc_timestamp(0),
+            action: Action::CreateDir,
+            path: "/music/a.flac.tiles".to_string(),
+            run_id: None,
+        };
+        assert_eq!(
+            record.to_line(),
+            "{\"time\":\"1970-01-01T00:00:00Z\",\"action\":\"create_dir\",\"path\":\"/music/a.flac.tiles\"}\n"
+        );
+        let record = Record { run_id: Some("nightly-42".parse().unwrap()), ..record };
+        assert!(record.to_line().ends_with(",\"run_id\":\"nightly-42\"}\n"));
+        assert!(Path::new(&Record::now(Action::Create, Path::new("a.png"), None).path).is_absolute());
+    }
+
+    // The writes are given the mode rather than switching --no-atomic for
+    // the whole process, which the other tests write their files with
+    #[test]
+    fn test_atomic_writes() {
+        let dir = tempdir().unwrap();
+        let image = dir.path().join("a.flac.png");
+        let temp = audit::temp_path(&image);
+        assert_eq!(temp, dir.path().join("a.flac.png.tmp"));
+
+        let mut out = create_file(&image, true).unwrap();
+        out.write_all(b"whole").unwrap();
+        assert!(temp.exists() && !image.exists(), "The image is only there once it is complete");
+        out.commit().unwrap();
+        assert!(!temp.exists());
+        assert_eq!(fs::read(&image).unwrap(), b"whole");
+
+        let mut out = create_file(&image, true).unwrap();
+        out.write_all(b"cut off").unwrap();
+        drop(out);
+        assert!(!temp.exists(), "An image that was not finished is removed");
+        assert_eq!(fs::read(&image).unwrap(), b"whole", "and the earlier one is kept");
+
+        // A run that was killed leaves its temporary file behind
+        fs::write(&temp, b"killed").unwrap();
+        let mut out = create_file(&image, false).unwrap();
+        assert!(!temp.exists(), "The next write of the image removes it");
+        out.write_all(b"in place").unwrap();
+        out.flush().unwrap();
+        assert_eq!(fs::read(&image).unwrap(), b"in place");
+        out.commit().unwrap();
+    }
+
+    // The log is of the whole process, so this is the only test that opens it
+    #[test]
+    fn test_audit_log() {
+        let dir = tempdir().unwrap();
+        let log = dir.path().join("audit.log");
+        fs::write(&log, "{\"earlier\":true}\n").unwrap();
+        let run_id: RunId = "nightly-42".parse().unwrap();
+        audit::open(&log, Some(run_id)).unwrap();
+        assert!(audit::open(&log, None).is_err(), "The log is opened once");
+
+        let tiles = dir.path().join("a.flac.tiles").join("x");
+        audit::create_dir_all(&tiles).unwrap();
+        audit::create_dir_all(&tiles).unwrap();
+        let image = dir.path().join("a.flac.png");
+        audit::write(&image, b"first").unwrap();
+        audit::write(&image, b"second").unwrap();
+        assert_eq!(fs::read(&image).unwrap(), b"second");
+
+        // Other tests write files as well, only the ones in the directory count
+        let text = fs::read_to_string(&log).unwrap();
+        assert!(text.starts_with("{\"earlier\":true}\n"), "The log is appended to");
+        let ours = text
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .filter(|record| record["path"].as_str().is_some_and(|path| path.starts_with(&*dir.path().to_string_lossy())))
+            .inspect(|record| assert_eq!(record["run_id"], "nightly-42"))
+            .map(|record| (record["action"].as_str().unwrap().to_string(), record["path"].as_str().unwrap().to_string()))
+            .collect::<Vec<_>>();
+        let path = |path: &Path| path.display().to_string();
+        assert_eq!(
+            ours,
+            vec![
+                ("create_dir".to_string(), path(tiles.parent().unwrap())),
+                ("create_dir".to_string(), path(&tiles)),
+                ("create".to_string(), path(&image)),
+                ("overwrite".to_string(), path(&image)),
+            ]
+        );
+    }
+}
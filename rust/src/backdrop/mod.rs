@@ -0,0 +1,163 @@
+/// Background images the waveform is composited over (`--background-image`).
+///
+/// The backdrop (blurred album art, a texture, a screenshot of the page the
+/// player sits on) is a PNG of any size and color type.  It is loaded once
+/// for the whole batch and scaled to cover the image, keeping its aspect
+/// ratio and cropping what sticks out evenly on both sides, the way a web
+/// page shows a cover background.  The waveform colors, the background
+/// color included, are then blended over its pixels.
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use png::{ColorType, Decoder, Transformations};
+
+use crate::color::Rgba;
+use crate::error::{Result, WaverError};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// An RGBA image the waveform is drawn over.
+#[derive(Clone, PartialEq)]
+pub struct Backdrop {
+    width: u32,
+    height: u32,
+    pixels: Vec<Rgba>,
+}
+
+impl fmt::Debug for Backdrop {
+    // The pixels would fill the screen
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Backdrop({}x{})", self.width, self.height)
+    }
+}
+
+impl Backdrop {
+    /// Creates a backdrop from its pixels, top row first.
+    pub fn new(width: u32, height: u32, pixels: Vec<Rgba>) -> Result<Self> {
+        if width == 0 || height == 0 || pixels.len() != width as usize * height as usize {
+            return Err(WaverError::argument_error(format!(
+                "A {width}x{height} background image needs {} pixels, not {}",
+                width as usize * height as usize,
+                pixels.len()
+            )));
+        }
+        Ok(Self { width, height, pixels })
+    }
+
+    /// Loads a PNG file as a backdrop.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(|err| {
+            WaverError::argument_error(format!("Can not open the background image '{}': {err}", path.display()))
+        })?;
+        Self::read(BufReader::new(file)).map_err(|err| match err {
+            WaverError::ArgumentError(message) => {
+                WaverError::argument_error(format!("The background image '{}': {message}", path.display()))
+            }
+            other => other,
+        })
+    }
+
+    /// Decodes a PNG of any color type and bit depth as a backdrop.
+    pub fn read(reader: impl Read) -> Result<Self> {
+        let invalid = |err: png::DecodingError| WaverError::argument_error(format!("Not a readable PNG: {err}"));
+        let mut decoder = Decoder::new(reader);
+        // Palettes, 16-bit and fewer than 8 bits all become 8-bit channels
+        decoder.set_transformations(Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(invalid)?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut buffer).map_err(invalid)?;
+        let bytes = &buffer[..frame.buffer_size()];
+
+        let pixels = match frame.color_type {
+            ColorType::Grayscale => bytes.iter().map(|&g| Rgba::rgb(g, g, g)).collect(),
+            ColorType::GrayscaleAlpha => bytes.chunks_exact(2).map(|p| Rgba::new(p[0], p[0], p[0], p[1])).collect(),
+            ColorType::Rgb => bytes.chunks_exact(3).map(|p| Rgba::rgb(p[0], p[1], p[2])).collect(),
+            ColorType::Rgba => bytes.chunks_exact(4).map(|p| Rgba::new(p[0], p[1], p[2], p[3])).collect(),
+            ColorType::Indexed => unreachable!("The palette is expanded by the decoder"),
+        };
+        Self::new(frame.width, frame.height, pixels)
+    }
+
+    /// Returns the width in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the pixels, top row first.
+    pub fn pixels(&self) -> &[Rgba] {
+        &self.pixels
+    }
+
+    /// Returns the pixel in column `x` of row `y`.
+    pub fn pixel(&self, x: u32, y: u32) -> Rgba {
+        self.pixels[y as usize * self.width as usize + x as usize]
+    }
+
+    /// Returns the backdrop scaled to cover `width` by `height` pixels.
+    ///
+    /// The aspect ratio is kept, so the longer side is cropped evenly on
+    /// both ends.  The pixels are sampled bilinearly, which is smooth
+    /// enough for the blurred or soft images a backdrop usually is.  A
+    /// backdrop of the right size is returned as it is.
+    pub fn cover(&self, width: u32, height: u32) -> Self {
+        if (width, height) == (self.width, self.height) {
+            return self.clone();
+        }
+        let scale = f64::max(width as f64 / self.width as f64, height as f64 / self.height as f64);
+        let offset_x = (self.width as f64 * scale - width as f64) / 2.0;
+        let offset_y = (self.height as f64 * scale - height as f64) / 2.0;
+
+        let mut pixels = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..height {
+            // Pixel centers of the output mapped back into the backdrop
+            let source_y = (y as f64 + 0.5 + offset_y) / scale - 0.5;
+            for x in 0..width {
+                let source_x = (x as f64 + 0.5 + offset_x) / scale - 0.5;
+                pixels.push(self.sample(source_x, source_y));
+            }
+        }
+        Self { width, height, pixels }
+    }
+
+    /// Returns the bilinear blend of the four pixels around a point, the
+    /// edge pixels repeating beyond the edges.
+    fn sample(&self, x: f64, y: f64) -> Rgba {
+        let clamp = |value: f64, size: u32| value.clamp(0.0, (size - 1) as f64);
+        let (x, y) = (clamp(x, self.width), clamp(y, self.height));
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+
+        let corners = [
+            (self.pixel(x0, y0), (1.0 - fx) * (1.0 - fy)),
+            (self.pixel(x1, y0), fx * (1.0 - fy)),
+            (self.pixel(x0, y1), (1.0 - fx) * fy),
+            (self.pixel(x1, y1), fx * fy),
+        ];
+        // The colors are weighted by their alpha so transparent pixels
+        // do not darken the edges of opaque ones
+        let alpha: f64 = corners.iter().map(|(pixel, weight)| pixel.alpha as f64 * weight).sum();
+        if alpha <= 0.0 {
+            return Rgba::new(0, 0, 0, 0);
+        }
+        let channel = |get: fn(&Rgba) -> u8| {
+            let sum: f64 = corners.iter().map(|(pixel, weight)| get(pixel) as f64 * pixel.alpha as f64 * weight).sum();
+            (sum / alpha + 0.5).min(255.0) as u8
+        };
+        Rgba::new(
+            channel(|pixel| pixel.red),
+            channel(|pixel| pixel.green),
+            channel(|pixel| pixel.blue),
+            (alpha + 0.5).min(255.0) as u8,
+        )
+    }
+}
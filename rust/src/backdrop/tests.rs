@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod tests {
+    use crate::backdrop::Backdrop;
+    use crate::color::Rgba;
+
+    /// Encodes 8-bit pixels of the color type as a PNG.
+    fn encode(width: u32, height: u32, color: png::ColorType, data: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(data).unwrap();
+        writer.finish().unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_read_color_types() {
+        let gray = Backdrop::read(encode(2, 1, png::ColorType::Grayscale, &[0, 200]).as_slice()).unwrap();
+        assert_eq!(gray.pixel(1, 0), Rgba::rgb(200, 200, 200));
+
+        let faded = Backdrop::read(encode(1, 1, png::ColorType::GrayscaleAlpha, &[50, 128]).as_slice()).unwrap();
+        assert_eq!(faded.pixel(0, 0), Rgba::new(50, 50, 50, 128));
+
+        let rgb = Backdrop::read(encode(1, 2, png::ColorType::Rgb, &[1, 2, 3, 4, 5, 6]).as_slice()).unwrap();
+        assert_eq!((rgb.width(), rgb.height()), (1, 2));
+        assert_eq!(rgb.pixel(0, 1), Rgba::rgb(4, 5, 6));
+
+        assert!(Backdrop::read(&b"not a png"[..]).is_err());
+    }
+
+    #[test]
+    fn test_new_checks_the_size() {
+        assert!(Backdrop::new(2, 2, vec![Rgba::rgb(0, 0, 0); 4]).is_ok());
+        assert!(Backdrop::new(2, 2, vec![Rgba::rgb(0, 0, 0); 3]).is_err());
+        assert!(Backdrop::new(0, 0, Vec::new()).is_err(), "Nothing to draw over");
+    }
+
+    #[test]
+    fn test_cover_crops_the_sides() {
+        // A square of three columns covering a wide image keeps the middle rows
+        let black = Rgba::rgb(0, 0, 0);
+        let white = Rgba::rgb(255, 255, 255);
+        let square = Backdrop::new(3, 3, vec![black, black, black, white, white, white, black, black, black]).unwrap();
+        let wide = square.cover(30, 10);
+        assert_eq!((wide.width(), wide.height()), (30, 10));
+        assert!(wide.pixel(0, 5).red > 200, "The middle row fills the middle");
+        assert!(wide.pixel(29, 4).red > 200, "Across the whole width");
+        assert_eq!(wide.pixel(15, 0), wide.pixel(15, 9), "Cropped evenly");
+        assert!(wide.pixel(15, 0).red > 127, "Most of the black rows are cropped away");
+
+        assert_eq!(square.cover(3, 3), square, "The right size is kept as it is");
+    }
+
+    #[test]
+    fn test_cover_weights_by_alpha() {
+        // Blending with a transparent pixel keeps the color of the opaque one
+        let clear = Rgba::new(0, 0, 0, 0);
+        let red = Rgba::rgb(255, 0, 0);
+        let pair = Backdrop::new(2, 1, vec![red, clear]).unwrap();
+        let between = pair.cover(4, 2).pixel(1, 0);
+        assert_eq!((between.red, between.green, between.blue), (255, 0, 0));
+        assert!(between.alpha < 255);
+    }
+}
@@ -0,0 +1,224 @@
+/// Timing the stages of the waveform of audio files (`waver bench`).
+///
+/// Which image format to write, and what the options cost, depends on the
+/// audio and the machine more than on anything the criterion benchmarks in
+/// `benches/` can show with their made-up samples.  `waver bench` times the
+/// stages of the real pipeline on the given files:
+///
+/// * decode: decoding the track into samples, and nothing else
+/// * draw: decoding it again, reducing it to column peaks and drawing the
+///   image with its overlays (everything but the write), so what the drawing
+///   costs is the draw time less the decode time
+/// * encode: encoding the drawn image in each of the formats compared, to
+///   memory, with the size of the result
+///
+/// Every stage runs once to warm up (the file cache, and the decoder and
+/// image pools a batch run reuses) and is then timed over a number of runs.
+/// Like criterion the report has the mean with its standard deviation, the
+/// median and the fastest run:
+///
+/// ```text
+/// song.flac: 3:45 of audio, 10 runs
+/// stage             median  mean ± std dev           fastest  throughput
+/// decode          120.3 ms  121.0 ms ± 2.1 ms       118.9 ms  1873x realtime
+/// draw            131.8 ms  132.2 ms ± 1.7 ms       130.4 ms  1710x realtime
+/// encode png        4.1 ms  4.2 ms ± 210.5 µs         4.0 ms  12.3 KiB
+/// ```
+use std::io::Write;
+use std::path::Path;
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::audio::{decode_only, draw_waveform, format_duration, format_size, silence_range, write_image, Drawn};
+use crate::cli::RenderMode;
+use crate::config::WaveformConfig;
+use crate::error::{Result, WaverError};
+use crate::image_pool;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The default number of timed runs of every stage.
+pub const DEFAULT_RUNS: u32 = 10;
+
+/// The statistics of the timed runs of a stage, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Stats {
+    /// Number of timed runs
+    pub runs: usize,
+    pub mean: f64,
+    /// Sample standard deviation (0 for a single run)
+    pub std_dev: f64,
+    pub median: f64,
+    pub fastest: f64,
+}
+
+impl Stats {
+    /// Returns the statistics of the times of the runs.
+    pub fn of(seconds: &[f64]) -> Self {
+        let runs = seconds.len();
+        let mut sorted = seconds.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let mean = sorted.iter().sum::<f64>() / runs.max(1) as f64;
+        let variance = sorted.iter().map(|time| (time - mean).powi(2)).sum::<f64>() / runs.saturating_sub(1).max(1) as f64;
+        let median = match runs {
+            0 => 0.0,
+            _ if runs.is_multiple_of(2) => (sorted[runs / 2 - 1] + sorted[runs / 2]) / 2.0,
+            _ => sorted[runs / 2],
+        };
+        Self { runs, mean, std_dev: variance.sqrt(), median, fastest: sorted.first().copied().unwrap_or(0.0) }
+    }
+}
+
+/// The timing of one stage of a file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Stage {
+    /// The stage: decode, draw, or encode and the format
+    pub name: String,
+    #[serde(flatten)]
+    pub stats: Stats,
+    /// Seconds of audio per second of the stage (by the median), for the
+    /// decode and the draw
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub realtime: Option<f64>,
+    /// Size of the encoded image in bytes, for the encodes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<usize>,
+}
+
+/// The timings of the stages of one audio file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileBench {
+    /// Path of the audio file
+    pub path: String,
+    /// Length of the audio, in seconds
+    pub duration_seconds: f64,
+    pub stages: Vec<Stage>,
+}
+
+/// Runs the stage once to warm up and then `runs` times, returning the
+/// statistics of the timed runs and the result of the last one.
+///
+/// The results of the other runs are handed to `recycle` outside of the
+/// timing, so pooled buffers are reused like they are in a batch run.
+fn time_runs<T>(
+    runs: u32,
+    mut stage: impl FnMut() -> Result<T>,
+    mut recycle: impl FnMut(T),
+) -> Result<(Stats, T)> {
+    let mut last = stage()?;
+    let mut seconds = Vec::with_capacity(runs as usize);
+    for _ in 0..runs.max(1) {
+        recycle(last);
+        let started = Instant::now();
+        last = stage()?;
+        seconds.push(started.elapsed().as_secs_f64());
+    }
+    Ok((Stats::of(&seconds), last))
+}
+
+/// Times the stages of the audio file with the settings of the run, and its
+/// image encoded with each of the `encodings` (the settings of the run with
+/// another format).
+pub fn bench_file(
+    input_path: &Path,
+    config: &WaveformConfig,
+    encodings: &[WaveformConfig],
+    runs: u32,
+) -> Result<FileBench> {
+    if config.mode == RenderMode::Spectrogram {
+        return Err(WaverError::argument_error("waver bench times the waveform, not the spectrogram"));
+    }
+
+    // Like for the image, the silence is trimmed before the audio is drawn
+    let range = match config.trim_silence {
+        Some(threshold) => silence_range(input_path, threshold, config)?,
+        None => config.range,
+    };
+    let config = WaveformConfig { range, trim_silence: None, dry_run: true, ..config.clone() };
+    let realtime = |duration: f64, stats: &Stats| (stats.median > 0.0).then(|| duration / stats.median);
+
+    let (decode, decoded) = time_runs(runs, || decode_only(input_path, config.max_packets), drop)?;
+    let recycle = |drawn: Drawn| {
+        image_pool::give_back(config.width, config.height, &drawn.image_options, drawn.image)
+    };
+    let (draw, drawn) = time_runs(runs, || draw_waveform(input_path, &config), recycle)?;
+
+    let mut stages = vec![
+        Stage { name: "decode".to_string(), stats: decode, realtime: realtime(decoded.duration(), &decode), bytes: None },
+        Stage { name: "draw".to_string(), stats: draw, realtime: realtime(drawn.info.duration(), &draw), bytes: None },
+    ];
+    for encoding in encodings {
+        let colors = (&encoding.left_color, &encoding.right_color);
+        let encode = || {
+            let mut bytes = Vec::new();
+            write_image(&drawn.image, encoding, colors, &[], &mut bytes)?;
+            Ok(bytes.len())
+        };
+        let (stats, bytes) = time_runs(runs, encode, drop)?;
+        let name = format!("encode {}", encoding.format.extension());
+        stages.push(Stage { name, stats, realtime: None, bytes: Some(bytes) });
+    }
+
+    let duration_seconds = decoded.duration();
+    recycle(drawn);
+    Ok(FileBench { path: input_path.display().to_string(), duration_seconds, stages })
+}
+
+/// Returns a time in seconds in the unit that fits it, like criterion.
+pub fn format_time(seconds: f64) -> String {
+    match seconds {
+        _ if seconds < 1e-6 => format!("{:.0} ns", seconds * 1e9),
+        _ if seconds < 1e-3 => format!("{:.1} µs", seconds * 1e6),
+        _ if seconds < 1.0 => format!("{:.1} ms", seconds * 1e3),
+        _ => format!("{seconds:.2} s"),
+    }
+}
+
+/// The timings of all files of a `waver bench` run.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BenchReport {
+    /// Number of timed runs of every stage
+    pub runs: u32,
+    pub files: Vec<FileBench>,
+}
+
+impl BenchReport {
+    /// Writes the report as pretty printed JSON.
+    pub fn write_json(&self, mut writer: impl Write) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    /// Writes the report as a table of the stages for every file.
+    pub fn write_text(&self, mut writer: impl Write) -> Result<()> {
+        for (index, file) in self.files.iter().enumerate() {
+            if index > 0 {
+                writeln!(writer)?;
+            }
+            let duration = format_duration(file.duration_seconds);
+            writeln!(writer, "{}: {duration} of audio, {} runs", file.path, self.runs)?;
+            writeln!(writer, "{:<14} {:>9}  {:<22} {:>9}  throughput", "stage", "median", "mean ± std dev", "fastest")?;
+            for stage in &file.stages {
+                let stats = &stage.stats;
+                let spread = format!("{} ± {}", format_time(stats.mean), format_time(stats.std_dev));
+                let throughput = match (stage.realtime, stage.bytes) {
+                    (Some(realtime), _) => format!("{realtime:.0}x realtime"),
+                    (None, Some(bytes)) => format_size(bytes as u64),
+                    (None, None) => String::new(),
+                };
+                writeln!(
+                    writer,
+                    "{:<14} {:>9}  {spread:<22} {:>9}  {throughput}",
+                    stage.name,
+                    format_time(stats.median),
+                    format_time(stats.fastest)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
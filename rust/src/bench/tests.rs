@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+    use crate::audio::format_size;
+    use crate::bench::{format_time, BenchReport, FileBench, Stage, Stats};
+
+    #[test]
+    fn test_stats() {
+        let stats = Stats::of(&[0.3, 0.1, 0.2, 0.6]);
+        assert_eq!(stats.runs, 4);
+        assert!((stats.mean - 0.3).abs() < 1e-12);
+        assert!((stats.median - 0.25).abs() < 1e-12, "The middle two of an even number");
+        assert_eq!(stats.fastest, 0.1);
+        // The sample standard deviation, of n - 1
+        assert!((stats.std_dev - (0.14f64 / 3.0).sqrt()).abs() < 1e-12);
+
+        let single = Stats::of(&[0.5]);
+        assert_eq!((single.median, single.std_dev, single.fastest), (0.5, 0.0, 0.5));
+    }
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(format_time(0.000_000_25), "250 ns");
+        assert_eq!(format_time(0.000_012_34), "12.3 µs");
+        assert_eq!(format_time(0.120_3), "120.3 ms");
+        assert_eq!(format_time(2.5), "2.50 s");
+        assert_eq!(format_size(900), "900 B");
+        assert_eq!(format_size(12_595), "12.3 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MiB");
+        assert_eq!(format_size(5 << 30), "5.0 GiB");
+    }
+
+    #[test]
+    fn test_write_text() {
+        let stats = Stats { runs: 3, mean: 0.121, std_dev: 0.0021, median: 0.1203, fastest: 0.1189 };
+        let report = BenchReport {
+            runs: 3,
+            files: vec![FileBench {
+                path: "song.flac".to_string(),
+                duration_seconds: 225.0,
+                stages: vec![
+                    Stage { name: "decode".to_string(), stats, realtime: Some(1870.3), bytes: None },
+                    Stage { name: "encode png".to_string(), stats, realtime: None, bytes: Some(12_595) },
+                ],
+            }],
+        };
+        let mut text = Vec::new();
+        report.write_text(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 4, "{text}");
+        assert!(lines[0].starts_with("song.flac: ") && lines[0].ends_with(" of audio, 3 runs"), "{text}");
+        assert!(lines[2].starts_with("decode") && lines[2].contains("121.0 ms ± 2.1 ms"), "{text}");
+        assert!(lines[2].ends_with("1870x realtime"), "{text}");
+        assert!(lines[3].ends_with("12.3 KiB"), "{text}");
+
+        let mut json = Vec::new();
+        report.write_json(&mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let stage = &json["files"][0]["stages"][1];
+        assert_eq!(stage["median"], 0.1203, "The statistics are flattened into the stage");
+        assert_eq!(stage["bytes"], 12_595);
+        assert!(stage.get("realtime").is_none());
+    }
+}
@@ -0,0 +1,166 @@
+/// Bundles of the files written for the audio, for handing them off.
+///
+/// With `--bundle zip` the image and the extra files of each audio file (the
+/// sidecar, preview, tick map, played and checkerboard copies) are also
+/// packed into one ZIP archive, or with `--bundle-by directory` those of all
+/// files of a directory into one archive there.  The files are stored
+/// without compression, as the images are compressed already and the JSON
+/// files are small, and with a fixed date so the same files always make the
+/// same archive.
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::audit;
+use crate::error::{Result, WaverError};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The file name of the bundle of a directory (`--bundle-by directory`).
+pub const DIRECTORY_BUNDLE_NAME: &str = "waver-bundle.zip";
+
+/// The version of the ZIP format needed to extract the archive (2.0, the
+/// first one with folders).
+const VERSION_NEEDED: u16 = 20;
+
+/// The flag of the entries whose names are UTF-8.
+const UTF8_NAMES: u16 = 1 << 11;
+
+/// The modification date of every entry, 1980-01-01 in the MS-DOS form (the
+/// earliest it can hold); the time is midnight.
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+/// Returns the path of the bundle for the given image path.
+///
+/// The image extension is replaced with `.zip`, so `song.mp3.png` gets the
+/// bundle `song.mp3.zip`.
+pub fn bundle_path(image_path: &Path) -> PathBuf {
+    image_path.with_extension("zip")
+}
+
+/// Returns the path of the bundle of all files of a directory.
+pub fn directory_bundle_path(dir: &Path) -> PathBuf {
+    dir.join(DIRECTORY_BUNDLE_NAME)
+}
+
+/// Packs the files into a ZIP archive at `path`, each under its file name.
+pub fn write_bundle(path: &Path, files: &[PathBuf]) -> Result<()> {
+    let mut zip = ZipWriter::new(BufWriter::new(audit::create(path)?));
+    for file in files {
+        let name = file
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| WaverError::generation_error(format!("Cannot bundle '{}' without a file name", file.display())))?;
+        zip.add(name, &fs::read(file)?)?;
+    }
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+/// An entry of the archive, as listed in the central directory at its end.
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Writes a ZIP archive of stored (uncompressed) files.
+///
+/// The archive has no ZIP64 extensions, so it holds at most 65535 files and
+/// neither a file nor the whole archive may reach 4 GiB.
+pub struct ZipWriter<W: Write> {
+    writer: W,
+    /// Bytes written so far, where the next entry starts
+    offset: u64,
+    entries: Vec<Entry>,
+}
+
+impl<W: Write> ZipWriter<W> {
+    /// Starts an empty archive.
+    pub fn new(writer: W) -> Self {
+        Self { writer, offset: 0, entries: Vec::new() }
+    }
+
+    /// Adds a file with the given name (which must be unique) and contents.
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        if self.entries.iter().any(|entry| entry.name == name) {
+            return Err(WaverError::generation_error(format!("Two files named '{name}' in one bundle")));
+        }
+        let too_large = || WaverError::generation_error("The bundle is too large for a ZIP archive without ZIP64");
+        let size = u32::try_from(data.len()).map_err(|_| too_large())?;
+        let offset = u32::try_from(self.offset).map_err(|_| too_large())?;
+        let name_len = u16::try_from(name.len()).map_err(|_| too_large())?;
+        if self.entries.len() >= u16::MAX as usize {
+            return Err(too_large());
+        }
+        let entry = Entry {
+            name: name.to_string(),
+            crc: crc32fast::hash(data),
+            size,
+            offset,
+        };
+
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend(0x0403_4b50_u32.to_le_bytes());
+        header.extend(VERSION_NEEDED.to_le_bytes());
+        header.extend(entry.fields());
+        header.extend(name_len.to_le_bytes());
+        header.extend(0_u16.to_le_bytes()); // No extra field
+        header.extend(name.as_bytes());
+        self.writer.write_all(&header)?;
+        self.writer.write_all(data)?;
+        self.offset += header.len() as u64 + data.len() as u64;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Writes the central directory and returns the writer.
+    pub fn finish(mut self) -> Result<W> {
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            directory.extend(0x0201_4b50_u32.to_le_bytes());
+            directory.extend(VERSION_NEEDED.to_le_bytes()); // Made by
+            directory.extend(VERSION_NEEDED.to_le_bytes());
+            directory.extend(entry.fields());
+            directory.extend((entry.name.len() as u16).to_le_bytes());
+            directory.extend([0; 12]); // Extra field, comment, disk and attributes
+            directory.extend(entry.offset.to_le_bytes());
+            directory.extend(entry.name.as_bytes());
+        }
+
+        let too_large = || WaverError::generation_error("The bundle is too large for a ZIP archive without ZIP64");
+        let start = u32::try_from(self.offset).map_err(|_| too_large())?;
+        let count = self.entries.len() as u16;
+        let mut end = Vec::with_capacity(22);
+        end.extend(0x0605_4b50_u32.to_le_bytes());
+        end.extend([0; 4]); // This disk and the disk of the directory
+        end.extend(count.to_le_bytes());
+        end.extend(count.to_le_bytes());
+        end.extend((directory.len() as u32).to_le_bytes());
+        end.extend(start.to_le_bytes());
+        end.extend(0_u16.to_le_bytes()); // No comment
+
+        self.writer.write_all(&directory)?;
+        self.writer.write_all(&end)?;
+        Ok(self.writer)
+    }
+}
+
+impl Entry {
+    /// Returns the fields the local header and the central directory share,
+    /// from the flags to the sizes.
+    fn fields(&self) -> Vec<u8> {
+        let mut fields = Vec::with_capacity(20);
+        fields.extend(UTF8_NAMES.to_le_bytes());
+        fields.extend(0_u16.to_le_bytes()); // Stored
+        fields.extend(0_u16.to_le_bytes()); // Midnight
+        fields.extend(DOS_DATE.to_le_bytes());
+        fields.extend(self.crc.to_le_bytes());
+        fields.extend(self.size.to_le_bytes()); // Compressed
+        fields.extend(self.size.to_le_bytes());
+        fields
+    }
+}
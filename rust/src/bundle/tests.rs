@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod tests {
+    use crate::bundle::{bundle_path, directory_bundle_path, write_bundle, ZipWriter};
+    use std::path::Path;
+
+    fn u16_at(bytes: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([bytes[at], bytes[at + 1]])
+    }
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_bundle_paths() {
+        assert_eq!(bundle_path(Path::new("a/song.mp3.png")), Path::new("a/song.mp3.zip"));
+        assert_eq!(directory_bundle_path(Path::new("a")), Path::new("a/waver-bundle.zip"));
+    }
+
+    #[test]
+    fn test_zip_layout() {
+        let mut zip = ZipWriter::new(Vec::new());
+        zip.add("a.txt", b"hello").unwrap();
+        zip.add("b.json", b"{}").unwrap();
+        assert!(zip.add("a.txt", b"again").is_err(), "Names are unique");
+        let bytes = zip.finish().unwrap();
+
+        // The first local header, its name and the stored contents
+        assert_eq!(u32_at(&bytes, 0), 0x0403_4b50);
+        assert_eq!(u16_at(&bytes, 8), 0, "Stored");
+        assert_eq!(u32_at(&bytes, 14), 0x3610_a686, "CRC-32 of hello");
+        assert_eq!((u32_at(&bytes, 18), u32_at(&bytes, 22)), (5, 5));
+        assert_eq!(&bytes[30..35], b"a.txt");
+        assert_eq!(&bytes[35..40], b"hello");
+        assert_eq!(u32_at(&bytes, 40), 0x0403_4b50, "The second entry follows");
+
+        // The end record points at the central directory with both entries
+        let end = bytes.len() - 22;
+        assert_eq!(u32_at(&bytes, end), 0x0605_4b50);
+        assert_eq!((u16_at(&bytes, end + 8), u16_at(&bytes, end + 10)), (2, 2));
+        let (size, start) = (u32_at(&bytes, end + 12) as usize, u32_at(&bytes, end + 16) as usize);
+        assert_eq!(start + size, end);
+        assert_eq!(u32_at(&bytes, start), 0x0201_4b50);
+        assert_eq!(u32_at(&bytes, start + 42), 0, "Offset of the first entry");
+        assert_eq!(&bytes[start + 46..start + 51], b"a.txt");
+        assert_eq!(u32_at(&bytes, start + 51 + 42), 40, "Offset of the second entry");
+    }
+
+    #[test]
+    fn test_write_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = dir.path().join("song.mp3.png");
+        let sidecar = dir.path().join("song.mp3.json");
+        std::fs::write(&image, b"png").unwrap();
+        std::fs::write(&sidecar, b"{}").unwrap();
+
+        let path = bundle_path(&image);
+        write_bundle(&path, &[image, sidecar]).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(&bytes[30..42], b"song.mp3.png", "Entries are named after the files");
+        assert_eq!(u16_at(&bytes, bytes.len() - 12), 2);
+
+        // The same files make the same archive
+        let again = dir.path().join("again.zip");
+        write_bundle(&again, &[dir.path().join("song.mp3.png"), dir.path().join("song.mp3.json")]).unwrap();
+        assert_eq!(std::fs::read(again).unwrap(), bytes);
+
+        assert!(write_bundle(&path, &[dir.path().join("missing.png")]).is_err());
+    }
+}
@@ -3,51 +3,350 @@ mod types;
 #[cfg(test)]
 mod tests;
 
-use clap::Parser;
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
 
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+
+use crate::bench;
+use crate::collect::Glob;
 use crate::color::Rgba;
-use crate::error::{Result, WaverError};
+use crate::config::{is_stdin, is_stdout, ExistingOutput, WaveformConfig, WaveformConfigBuilder};
+use crate::cross_check::ReferenceDecoder;
+use crate::dashboard;
+use crate::error::{ErrorBudget, ExitStatus, Result, WaverError};
+use crate::limits::Limits;
+use crate::mix::ChannelMode;
+use crate::output::{FileOutput, LogLevel, Logger, OutputOrder};
+use crate::quality::QualityWeights;
+use crate::scale::AmplitudeScale;
+use crate::segment::DEFAULT_TEMPLATE;
+use crate::summary::{PreviousRun, RunId};
+use crate::spectrum::Colormap;
+use crate::video::VideoFormat;
 
 pub use types::*;
 
+/// The prefix of the environment variables the options can be set with.
+pub const ENV_PREFIX: &str = "WAVER_";
+
 /// Command line arguments for waveform generation.
 #[derive(Parser, Debug)]
 #[command(
     name = "waver",
     about = "Generate waveform visualizations from audio files",
     version,
-    author
+    author,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    after_help = "Every option can also be set with an environment variable named after it, like WAVER_WIDTH \
+                  for --width or WAVER_LEFT_COLOR for --left-color (the command line wins).  \
+                  `waver config --show` prints where each value came from."
 )]
 pub struct WaverArgs {
-    /// Width of the output image in pixels
-    #[arg(long = "width", default_value = "2048", value_parser = clap::value_parser!(Width))]
-    pub width: Width,
+    /// Optional tool to run instead of generating waveforms
+    #[command(subcommand)]
+    pub command: Option<Command>,
 
-    /// Height of the output image in pixels (must be even)
-    #[arg(long = "height", default_value = "128", value_parser = clap::value_parser!(Height))]
-    pub height: Height,
+    /// Width of the output image in pixels (at least 16, or 4 with --micro)
+    #[arg(long = "width", default_value = "2048")]
+    pub width: u32,
+
+    /// Height of the output image in pixels (must be even, at least 6, or 2 with --micro)
+    #[arg(long = "height", default_value = "128")]
+    pub height: u32,
+
+    /// Allow sparkline sized images below the normal minimum size
+    #[arg(long = "micro")]
+    pub micro: bool,
+
+    /// What to draw (waveform, or spectrogram for the frequencies over time)
+    #[arg(long = "mode", default_value = "waveform", value_parser = clap::value_parser!(RenderMode))]
+    pub mode: RenderMode,
 
-    /// Color for left channel (RGB, RRGGBB, or RRGGBBAA)
+    /// Colors of the spectrogram levels (magma, viridis, or gray)
+    #[arg(long = "colormap", default_value = "magma", value_parser = clap::value_parser!(Colormap))]
+    pub colormap: Colormap,
+
+    /// Color for left channel (RGB, RRGGBB, or RRGGBBAA hex, or a CSS color name)
     #[arg(long = "left-color", default_value = "00ff99", value_parser = clap::value_parser!(Rgba))]
     pub left_color: Rgba,
 
-    /// Color for right channel (RGB, RRGGBB, or RRGGBBAA)
+    /// Color for right channel (RGB, RRGGBB, or RRGGBBAA hex, or a CSS color name)
     #[arg(long = "right-color", default_value = "99ff00", value_parser = clap::value_parser!(Rgba))]
     pub right_color: Rgba,
 
-    /// Background color (RGB, RRGGBB, or RRGGBBAA)
+    /// Give each file its own colors, a hue picked from SEED and the file name (the same on every run)
+    #[arg(long = "color-seed", value_name = "SEED", conflicts_with_all = ["left_color", "right_color"])]
+    pub color_seed: Option<u64>,
+
+    /// Draw each file in the dominant color of its embedded cover art (files without art keep the other colors)
+    #[arg(long = "auto-color")]
+    pub auto_color: bool,
+
+    /// Give the files matching the path patterns of this CSV file the colors of their row (pattern,left,right,background)
+    #[arg(long = "color-map", value_name = "CSV")]
+    pub color_map: Option<PathBuf>,
+
+    /// Background color (RGB, RRGGBB, or RRGGBBAA hex, or a CSS color name)
     #[arg(long = "background-color", default_value = "ffffff00", value_parser = clap::value_parser!(Rgba))]
     pub background_color: Rgba,
 
-    /// Output PNG file name (only in single-file mode)
+    /// Composite the waveform over this PNG image, scaled to cover the image (only for a single PNG)
+    #[arg(long = "background-image", value_name = "PNG", conflicts_with = "preview_checkerboard")]
+    pub background_image: Option<PathBuf>,
+
+    /// Output image file name, or - for stdout (only in single-file mode)
     #[arg(short = 'o', long = "output-filename")]
     pub output_filename: Option<String>,
 
+    /// Name the images by a hash of the audio content, in this directory, and skip audio whose image is there already
+    #[arg(long = "hash-names", value_name = "DIR", conflicts_with_all = ["output_filename", "stems", "stdin", "segment"])]
+    pub hash_names: Option<PathBuf>,
+
+    /// Image format (png, qoi, farbfeld, webp, avif, or jpeg, bmp, ppm or raw rgb565 with an opaque background)
+    #[arg(long = "output-format", value_name = "FORMAT", default_value = "png", value_parser = clap::value_parser!(ImageFormat))]
+    pub output_format: ImageFormat,
+
+    /// JPEG and AVIF quality from 1 to 100 (only with --output-format jpeg or avif)
+    #[arg(long = "quality", value_name = "N", default_value = "85", value_parser = clap::value_parser!(JpegQuality))]
+    pub quality: JpegQuality,
+
+    /// Write a video of the waveform with a moving cursor and the audio instead (mp4 or webm, needs ffmpeg)
+    #[arg(long = "video", value_name = "FORMAT", value_parser = clap::value_parser!(VideoFormat))]
+    pub video: Option<VideoFormat>,
+
     /// Comma-separated list of audio file extensions
     #[arg(long = "file-extensions", default_value = "mp3", value_parser = clap::value_parser!(FileExtensions))]
     pub file_extensions: FileExtensions,
 
-    /// Perform actions without generating files
+    /// Do not follow symbolic links while searching directories
+    #[arg(long = "no-follow-symlinks")]
+    pub no_follow_symlinks: bool,
+
+    /// Also search hidden files and directories (names starting with a dot)
+    #[arg(long = "hidden")]
+    pub hidden: bool,
+
+    /// Search directories at most N levels deep (1 for only the files in them)
+    #[arg(long = "max-depth", value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_depth: Option<u32>,
+
+    /// Skip the files and directories matching this glob pattern (like *.demo.mp3 or Outtakes; can be repeated)
+    #[arg(long = "exclude", value_name = "GLOB", value_parser = clap::value_parser!(Glob))]
+    pub exclude: Vec<Glob>,
+
+    /// Also process the audio files listed in FILE (- for stdin), one per line or NUL-separated like find -print0 writes
+    #[arg(long = "files-from", value_name = "FILE", conflicts_with_all = ["output_filename", "stems", "retry_from"])]
+    pub files_from: Option<PathBuf>,
+
+    /// Amplitude scale of the waveform (linear or db)
+    #[arg(long = "scale", default_value = "linear", value_parser = clap::value_parser!(AmplitudeScale))]
+    pub scale: AmplitudeScale,
+
+    /// Level in dB drawn as silence with --scale db or in the spectrogram (must be negative)
+    #[arg(long = "db-floor", default_value = "-60", allow_hyphen_values = true, value_parser = clap::value_parser!(DbFloor))]
+    pub db_floor: DbFloor,
+
+    /// Gamma on the scaled heights: above 1 spreads out loud, dense material, below 1 lifts quiet parts
+    #[arg(long = "contrast", value_name = "GAMMA", default_value = "1")]
+    pub contrast: f32,
+
+    /// Channels to draw (stereo, mono, left, right, mid, or side)
+    #[arg(long = "channels", default_value = "stereo", value_parser = clap::value_parser!(ChannelMode))]
+    pub channels: ChannelMode,
+
+    /// Channel layout (combined, or stacked with every channel in its own lane)
+    #[arg(long = "layout", default_value = "combined", value_parser = clap::value_parser!(WaveLayout))]
+    pub layout: WaveLayout,
+
+    /// Draw these audio files (2 to 8) as the stems of one image, in layers or with --layout stacked in lanes
+    #[arg(long = "stems", value_name = "FILE", num_args = 2.., conflicts_with_all = ["audio_paths", "stdin"], value_parser = clap::value_parser!(AudioPath))]
+    pub stems: Vec<AudioPath>,
+
+    /// Colors of the stems in their order (comma-separated, default: hues spread around the color wheel)
+    #[arg(long = "stem-colors", value_name = "COLORS", value_delimiter = ',', requires = "stems", value_parser = clap::value_parser!(Rgba))]
+    pub stem_colors: Vec<Rgba>,
+
+    /// How mono waveforms are drawn (mirrored around the center, or top or bottom from a baseline)
+    #[arg(long = "mono-style", value_name = "STYLE", default_value = "mirrored", value_parser = clap::value_parser!(MonoStyle))]
+    pub mono_style: MonoStyle,
+
+    /// Scale the waveform so the loudest peak reaches full height
+    #[arg(long = "normalize")]
+    pub normalize: bool,
+
+    /// Scale the waveform so a percentile of its column peaks reaches full height, clipping the louder ones (like percentile=95)
+    #[arg(long = "auto-gain", value_name = "GAIN", conflicts_with = "normalize", value_parser = clap::value_parser!(AutoGain))]
+    pub auto_gain: Option<AutoGain>,
+
+    /// Color each column by its RMS loudness (quiet = dark, loud = bright)
+    #[arg(long = "tint-by-loudness")]
+    pub tint_by_loudness: bool,
+
+    /// Smooth the waveform edges with partial coverage (8-bit PNG)
+    #[arg(long = "antialias", conflicts_with = "tint_by_loudness")]
+    pub antialias: bool,
+
+    /// Draw each column from its lowest to its highest sample (keeps asymmetric shapes)
+    #[arg(long = "envelope", conflicts_with_all = ["tint_by_loudness", "antialias"])]
+    pub envelope: bool,
+
+    /// Draw the RMS level in the channel colors over the peaks in a faded shade
+    #[arg(long = "rms-layer", conflicts_with_all = ["tint_by_loudness", "antialias", "envelope"])]
+    pub rms_layer: bool,
+
+    /// Height in pixels of a strip below the waveform showing the L/R balance
+    #[arg(long = "balance-strip", value_name = "PX", value_parser = clap::value_parser!(u32).range(1..=1024))]
+    pub balance_strip: Option<u32>,
+
+    /// Stack a compressed overview below the waveform, taking RATIO of the height
+    #[arg(long = "composite", value_name = "RATIO", num_args = 0..=1, default_missing_value = "0.25", value_parser = clap::value_parser!(OverviewRatio))]
+    pub composite: Option<OverviewRatio>,
+
+    /// Draw the track duration as a badge in a corner (top-left, top-right, bottom-left, bottom-right)
+    #[arg(long = "duration-badge", value_name = "CORNER", value_parser = clap::value_parser!(Corner))]
+    pub duration_badge: Option<Corner>,
+
+    /// Draw a line in COLOR along the center of the waveform, behind the audio
+    #[arg(long = "center-line", value_name = "COLOR", value_parser = clap::value_parser!(Rgba))]
+    pub center_line: Option<Rgba>,
+
+    /// Also draw the center line color along the top and bottom of the waveform
+    #[arg(long = "borders", requires = "center_line")]
+    pub borders: bool,
+
+    /// Draw faint vertical lines behind the waveform every TIME of the audio ([[hh:]mm:]ss[.mmm])
+    #[arg(long = "grid", value_name = "TIME", value_parser = clap::value_parser!(TimeOffset))]
+    pub grid: Option<TimeOffset>,
+
+    /// Draw short tick marks along the bottom of the waveform every TIME of the audio ([[hh:]mm:]ss[.mmm])
+    #[arg(long = "ticks", value_name = "TIME", value_parser = clap::value_parser!(TimeOffset))]
+    pub ticks: Option<TimeOffset>,
+
+    /// Also write the time and column of every tick mark as JSON next to each image (song.mp3.ticks.json)
+    #[arg(long = "ticks-json", requires = "ticks")]
+    pub ticks_json: bool,
+
+    /// Draw the index points of a cuesheet embedded in FLAC files as lines over the waveform
+    #[arg(long = "cue-markers")]
+    pub cue_markers: bool,
+
+    /// Draw the index points of a cue sheet (.cue) or the times of a JSON file as lines over the waveform, like chapter starts
+    #[arg(long = "markers", value_name = "FILE")]
+    pub markers: Option<PathBuf>,
+
+    /// Write a JSON sidecar with the audio facts next to each image
+    #[arg(long = "sidecar")]
+    pub sidecar: bool,
+
+    /// Also write the embedded cover art of each audio file into DIR (as song.mp3.jpg)
+    #[arg(long = "extract-art", value_name = "DIR")]
+    pub extract_art: Option<PathBuf>,
+
+    /// Write the duration, sample rate, channels and source file name into PNG images as text chunks
+    #[arg(long = "embed-metadata", conflicts_with = "tiles")]
+    pub embed_metadata: bool,
+
+    /// Also write the peak of every second as JSON next to each image, for seekbar hover previews
+    #[arg(long = "preview")]
+    pub preview: bool,
+
+    /// Also write the silent parts of the audio as JSON next to each image, for the gaps between tracks or chapters
+    #[arg(long = "detect-silence")]
+    pub detect_silence: bool,
+
+    /// Level in dBFS below which every channel must be for the audio to be silent
+    #[arg(long = "silence-threshold", value_name = "DB", default_value = "-60", allow_hyphen_values = true, requires = "detect_silence")]
+    pub silence_threshold: f32,
+
+    /// Shortest silence that is written ([[hh:]mm:]ss[.mmm])
+    #[arg(long = "silence-min-duration", value_name = "TIME", default_value = "2", requires = "detect_silence", value_parser = clap::value_parser!(TimeOffset))]
+    pub silence_min_duration: TimeOffset,
+
+    /// Measure the integrated loudness (LUFS) and true peak (dBTP) after EBU R128 while decoding, for the --report, sidecar and PNG text
+    #[arg(long = "measure-loudness", conflicts_with_all = ["segment", "peak_cache"])]
+    pub measure_loudness: bool,
+
+    /// Estimate the tempo of every file in BPM from the autocorrelation of its onsets while decoding, for the --report
+    #[arg(long = "detect-bpm", conflicts_with_all = ["segment", "peak_cache"])]
+    pub detect_bpm: bool,
+
+    /// Also write a .played copy of the image with both channels in COLOR, for a progress fill
+    #[arg(long = "progress-pair", value_name = "COLOR", num_args = 0..=1, default_missing_value = "ff5500", value_parser = clap::value_parser!(Rgba))]
+    pub progress_pair: Option<Rgba>,
+
+    /// Also write a .played copy of the image with the channels in LEFT[,RIGHT], or faded halfway to the background without colors
+    #[arg(long = "played-colors", value_name = "LEFT[,RIGHT]", num_args = 0..=1, value_delimiter = ',', conflicts_with = "progress_pair", value_parser = clap::value_parser!(Rgba))]
+    pub played_colors: Option<Vec<Rgba>>,
+
+    /// Also write a .checker.png copy of the image over a checkerboard of SIZE pixel squares, to check the transparency
+    #[arg(long = "preview-checkerboard", value_name = "SIZE", num_args = 0..=1, default_missing_value = "8", value_parser = clap::value_parser!(u32).range(1..=1024))]
+    pub preview_checkerboard: Option<u32>,
+
+    /// Keep the decoded peaks in a .peaks file next to each audio file and reuse them
+    #[arg(long = "peak-cache", conflicts_with = "pcm_hash")]
+    pub peak_cache: bool,
+
+    /// Also write a .peaks.json index of the byte ranges of the cached peaks, for HTTP range requests
+    #[arg(long = "peak-index", requires = "peak_cache")]
+    pub peak_index: bool,
+
+    /// Record a SHA-256 of the decoded PCM samples in the sidecar
+    #[arg(long = "pcm-hash", requires = "sidecar")]
+    pub pcm_hash: bool,
+
+    /// Also pack the image and the extra files of each audio file into an archive (zip, as song.mp3.zip)
+    #[arg(long = "bundle", value_name = "FORMAT", conflicts_with = "tiles", value_parser = clap::value_parser!(BundleFormat))]
+    pub bundle: Option<BundleFormat>,
+
+    /// Bundle the files of each audio file, or of all audio files of a directory (as waver-bundle.zip)
+    #[arg(long = "bundle-by", value_name = "SCOPE", default_value = "file", requires = "bundle", value_parser = clap::value_parser!(BundleScope))]
+    pub bundle_by: BundleScope,
+
+    /// Cut the waveform into tiles PX wide with an index.json manifest (use a large --width)
+    #[arg(long = "tiles", value_name = "PX", num_args = 0..=1, default_missing_value = "512", value_parser = clap::value_parser!(TileWidth))]
+    pub tiles: Option<TileWidth>,
+
+    /// Render only the audio from this time on ([[hh:]mm:]ss[.mmm])
+    #[arg(long = "start", value_name = "TIME", value_parser = clap::value_parser!(TimeOffset))]
+    pub start: Option<TimeOffset>,
+
+    /// Render only the audio up to this time ([[hh:]mm:]ss[.mmm])
+    #[arg(long = "end", value_name = "TIME", conflicts_with = "duration", value_parser = clap::value_parser!(TimeOffset))]
+    pub end: Option<TimeOffset>,
+
+    /// Render only this much of the audio from the start time ([[hh:]mm:]ss[.mmm])
+    #[arg(long = "duration", value_name = "TIME", value_parser = clap::value_parser!(TimeOffset))]
+    pub duration: Option<TimeOffset>,
+
+    /// Leave out the silence before the first and after the last sample above this level in dBFS (like -50), so the sound fills the width
+    #[arg(long = "trim-silence", value_name = "DB", allow_hyphen_values = true, conflicts_with_all = ["start", "end", "duration", "segment", "stdin"])]
+    pub trim_silence: Option<f32>,
+
+    /// Split the audio into an image for every part of this length (like 10m)
+    #[arg(long = "segment", value_name = "TIME", conflicts_with_all = ["start", "end", "duration", "output_filename"], value_parser = clap::value_parser!(TimeOffset))]
+    pub segment: Option<TimeOffset>,
+
+    /// Let each segment run on this long into the next one
+    #[arg(long = "segment-overlap", value_name = "TIME", requires = "segment", value_parser = clap::value_parser!(TimeOffset))]
+    pub segment_overlap: Option<TimeOffset>,
+
+    /// Name of the segment images, from {file}, {index}, {start} and {ext}
+    #[arg(long = "segment-name", value_name = "TEMPLATE", requires = "segment", default_value = DEFAULT_TEMPLATE)]
+    pub segment_name: String,
+
+    /// Read the audio from stdin (needs --output-filename)
+    #[arg(long = "stdin", conflicts_with = "audio_paths")]
+    pub stdin: bool,
+
+    /// Format of the audio on stdin as a file extension (e.g. mp3)
+    #[arg(long = "stdin-format", value_name = "EXT", value_parser = clap::value_parser!(FileExtension))]
+    pub stdin_format: Option<FileExtension>,
+
+    /// Print what would be written (path, action and image size) without writing files
     #[arg(long = "dry-run")]
     pub dry_run: bool,
 
@@ -55,27 +354,495 @@ pub struct WaverArgs {
     #[arg(long = "overwrite")]
     pub overwrite: bool,
 
-    /// Suppress most output
-    #[arg(long = "quiet")]
+    /// Regenerate existing output files only if the audio file is newer
+    #[arg(long = "if-newer", conflicts_with = "overwrite")]
+    pub if_newer: bool,
+
+    /// Keep an existing PNG image only if it decodes to the end at the size of the image, and draw it again otherwise (reads every existing image in full)
+    #[arg(long = "verify-existing", conflicts_with = "overwrite")]
+    pub verify_existing: bool,
+
+    /// Report progress with an ETA (weighted by file size) on stderr
+    #[arg(long = "progress")]
+    pub progress: bool,
+
+    /// Show a live dashboard of the workers, throughput and recent errors, with keys to pause and skip (needs the tui feature)
+    #[arg(long = "tui", conflicts_with = "progress")]
+    pub tui: bool,
+
+    /// When the messages of the files are printed (completion, input order as the files finish, or in input order at the end)
+    #[arg(long = "output-order", value_name = "ORDER", default_value = "completion", value_parser = clap::value_parser!(OutputOrder))]
+    pub output_order: OutputOrder,
+
+    /// Skip files without an audio track (like videos without sound) with a warning instead of failing them
+    #[arg(long = "skip-non-audio")]
+    pub skip_non_audio: bool,
+
+    /// Skip files whose audio is longer than TIME ([[hh:]mm:]ss[.mmm] or like 2h) with a warning
+    #[arg(long = "max-duration", value_name = "TIME", value_parser = clap::value_parser!(TimeOffset))]
+    pub max_duration: Option<TimeOffset>,
+
+    /// Skip files larger than SIZE (bytes or like 500M or 2G) with a warning
+    #[arg(long = "max-file-size", value_name = "SIZE", value_parser = clap::value_parser!(FileSize))]
+    pub max_file_size: Option<FileSize>,
+
+    /// Treat warnings, like outputs skipped because they exist, as errors of their files
+    #[arg(long = "strict")]
+    pub strict: bool,
+
+    /// Stop at the first file that fails instead of processing the rest
+    #[arg(long = "fail-fast")]
+    pub fail_fast: bool,
+
+    /// Exit with success even if files fail, writing the failed files as JSON to REPORT (- for stdout)
+    #[arg(long = "ignore-errors", value_name = "REPORT", conflicts_with = "fail_fast")]
+    pub ignore_errors: Option<PathBuf>,
+
+    /// Succeed even if up to N files fail (the failures are still reported)
+    #[arg(long = "max-failures", value_name = "N", conflicts_with = "ignore_errors")]
+    pub max_failures: Option<usize>,
+
+    /// Succeed even if up to this share of the files fail, like 1%
+    #[arg(long = "max-failure-rate", value_name = "RATE", conflicts_with = "ignore_errors", value_parser = clap::value_parser!(FailureRate))]
+    pub max_failure_rate: Option<FailureRate>,
+
+    /// Write a JSON summary of the run (processed, skipped and failed files and their outputs) to FILE (- for stdout)
+    #[arg(long = "report", value_name = "FILE")]
+    pub report: Option<PathBuf>,
+
+    /// Process only the files that failed in the --report summary REPORT of an earlier run, with the options of that run
+    #[arg(long = "retry-from", value_name = "REPORT", conflicts_with_all = ["audio_paths", "stdin"])]
+    pub retry_from: Option<PathBuf>,
+
+    /// Append every finished file to FILE and skip the files it lists as finished, to resume an interrupted run (even with --overwrite)
+    #[arg(long = "resume", value_name = "FILE")]
+    pub resume: Option<PathBuf>,
+
+    /// Append a JSON line to FILE for every file and directory written or overwritten (for the subcommands, set WAVER_AUDIT_LOG)
+    #[arg(long = "audit-log", value_name = "FILE")]
+    pub audit_log: Option<PathBuf>,
+
+    /// Write the images in place instead of to a .tmp file that is renamed once it is complete
+    #[arg(long = "no-atomic")]
+    pub no_atomic: bool,
+
+    /// Record an ID of the run in the report, the audit log, the sidecars and the PNG text; --run-id=ID gives the ID (like that of a CI job), --run-id alone makes one
+    #[arg(long = "run-id", value_name = "ID", num_args = 0..=1, require_equals = true)]
+    pub run_id: Option<Option<RunId>>,
+
+    /// Give up on a file after reading N packets (by default a limit from the length the file reports)
+    #[arg(long = "max-packets", value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_packets: Option<u64>,
+
+    /// Decode the files again with a reference decoder (ffmpeg) and warn where the waveform peaks differ
+    #[arg(long = "cross-check", value_name = "DECODER", value_parser = clap::value_parser!(ReferenceDecoder))]
+    pub cross_check: Option<ReferenceDecoder>,
+
+    /// Cross-check only one of every N files (picked by path, so the same files every run)
+    #[arg(long = "cross-check-every", value_name = "N", default_value_t = 1, requires = "cross_check", value_parser = clap::value_parser!(u32).range(1..))]
+    pub cross_check_every: u32,
+
+    /// Score every file for QC triage from its clipping, long silences, DC offset and channel imbalance (in the --report)
+    #[arg(long = "qc")]
+    pub qc: bool,
+
+    /// Weights of the measures in the QC score, like clipping=2,silence=1,dc=1,imbalance=0.5 (each 1 if not given)
+    #[arg(long = "qc-weights", value_name = "WEIGHTS", requires = "qc", value_parser = clap::value_parser!(QualityWeights))]
+    pub qc_weights: Option<QualityWeights>,
+
+    /// Print the pipeline stages of every file with their timings to stderr (filtered with WAVER_LOG)
+    #[arg(long = "trace")]
+    pub trace: bool,
+
+    /// Export the pipeline stages as OTLP/HTTP spans to URL, like http://localhost:4318/v1/traces (needs the otlp feature)
+    #[arg(long = "otlp-endpoint", value_name = "URL")]
+    pub otlp_endpoint: Option<String>,
+
+    /// Decode at most N files at once (default: one per core)
+    #[arg(long = "jobs", value_name = "N", value_parser = clap::value_parser!(u32).range(1..=1024))]
+    pub jobs: Option<u32>,
+
+    /// Run the decoding at a low scheduling priority so other programs stay responsive
+    #[arg(long = "nice")]
+    pub nice: bool,
+
+    /// Suppress most output (like --log-level off)
+    #[arg(long = "quiet", conflicts_with_all = ["verbose", "log_level"])]
     pub quiet: bool,
 
-    /// Print additional information
-    #[arg(long = "verbose")]
+    /// Print additional information (like --log-level debug)
+    #[arg(long = "verbose", conflicts_with = "log_level")]
     pub verbose: bool,
 
-    /// Audio files or directories to process
-    #[arg(required = true, num_args = 1.., value_parser = clap::value_parser!(AudioPath))]
+    /// Print the messages up to this level (off, error, warn, info or debug)
+    #[arg(long = "log-level", value_name = "LEVEL", value_parser = clap::value_parser!(LogLevel))]
+    pub log_level: Option<LogLevel>,
+
+    /// Print the messages as JSON lines on stderr, with the time, the level and the file
+    #[arg(long = "log-json")]
+    pub log_json: bool,
+
+    /// Audio files, directories or glob patterns to process (- reads the audio from stdin)
+    #[arg(required_unless_present_any = ["stdin", "stems", "retry_from", "files_from"], num_args = 1.., value_parser = clap::value_parser!(AudioPath))]
+    pub audio_paths: Vec<AudioPath>,
+
+    /// The options of the run as command line arguments, recorded in the
+    /// summary for `--retry-from` (see `WaverArgs::run_arguments`)
+    #[arg(skip)]
+    pub arguments: Vec<String>,
+}
+
+/// Tools that are run instead of the waveform generation.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Compare the decoded audio of two files, e.g. to verify a transcode
+    Compare(CompareArgs),
+    /// Write the tags (title, artist, album), duration and format of audio files as JSON
+    Metadata(MetadataArgs),
+    /// Show the options a run would use and where their values came from
+    Config(ConfigArgs),
+    /// Check whether waveform images still show their audio, from the peak hash embedded with --embed-metadata
+    Diff(DiffArgs),
+    /// Time the decode, the draw and the encode of audio files, to compare image formats and options
+    Bench(BenchArgs),
+}
+
+/// Arguments of `waver compare`.
+#[derive(Args, Debug)]
+pub struct CompareArgs {
+    /// The reference audio file (e.g. the lossless original)
+    #[arg(value_parser = clap::value_parser!(AudioPath))]
+    pub reference: AudioPath,
+
+    /// The audio file to compare against the reference (e.g. a transcode)
+    #[arg(value_parser = clap::value_parser!(AudioPath))]
+    pub candidate: AudioPath,
+
+    /// Format of the report (text or json)
+    #[arg(long = "report", default_value = "text", value_parser = clap::value_parser!(ReportFormat))]
+    pub report: ReportFormat,
+
+    /// Also render an overlay image where only the differences are drawn
+    #[arg(long = "diff-image", value_name = "PNG")]
+    pub diff_image: Option<PathBuf>,
+
+    /// Width of the diff image in pixels
+    #[arg(long = "width", default_value = "2048", value_parser = clap::value_parser!(Width))]
+    pub width: Width,
+
+    /// Height of the diff image in pixels (must be even)
+    #[arg(long = "height", default_value = "128", value_parser = clap::value_parser!(Height))]
+    pub height: Height,
+
+    /// What the diff image draws where the two waveforms overlap (keep-left, keep-right, or mark to leave only the differences)
+    #[arg(long = "overlap", default_value = "mark", value_parser = clap::value_parser!(Overlap))]
+    pub overlap: Overlap,
+}
+
+/// Arguments of `waver metadata`.
+#[derive(Args, Debug)]
+pub struct MetadataArgs {
+    /// Comma-separated list of audio file extensions
+    #[arg(long = "file-extensions", default_value = "mp3", value_parser = clap::value_parser!(FileExtensions))]
+    pub file_extensions: FileExtensions,
+
+    /// Do not follow symbolic links while searching directories
+    #[arg(long = "no-follow-symlinks")]
+    pub no_follow_symlinks: bool,
+
+    /// Also search hidden files and directories (names starting with a dot)
+    #[arg(long = "hidden")]
+    pub hidden: bool,
+
+    /// Search directories at most N levels deep (1 for only the files in them)
+    #[arg(long = "max-depth", value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_depth: Option<u32>,
+
+    /// Skip the files and directories matching this glob pattern (like *.demo.mp3 or Outtakes; can be repeated)
+    #[arg(long = "exclude", value_name = "GLOB", value_parser = clap::value_parser!(Glob))]
+    pub exclude: Vec<Glob>,
+
+    /// Also read the audio files listed in FILE (- for stdin), one per line or NUL-separated like find -print0 writes
+    #[arg(long = "files-from", value_name = "FILE")]
+    pub files_from: Option<PathBuf>,
+
+    /// Write the JSON to this file instead of stdout
+    #[arg(long = "output", short = 'o', value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Audio files, directories or glob patterns to read
+    #[arg(required_unless_present = "files_from", num_args = 1.., value_parser = clap::value_parser!(AudioPath))]
     pub audio_paths: Vec<AudioPath>,
 }
 
+impl MetadataArgs {
+    /// Returns how directories are searched for audio files.
+    pub fn traversal(&self) -> Traversal {
+        Traversal::new(!self.no_follow_symlinks, self.hidden, self.max_depth)
+    }
+}
+
+/// Arguments of `waver config`.
+#[derive(Args, Debug)]
+pub struct ConfigArgs {
+    /// Print every option with its value and where the value came from
+    #[arg(long = "show", required = true)]
+    pub show: bool,
+
+    /// The options of the run, as they would be given to waver
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true, value_name = "OPTIONS")]
+    pub options: Vec<OsString>,
+}
+
+impl ConfigArgs {
+    /// Parses the options of the run like waver would, including the
+    /// environment variables, but without requiring any audio files.
+    pub fn matches(&self) -> clap::error::Result<ArgMatches> {
+        WaverArgs::matches_without_audio(&self.options)
+    }
+}
+
+/// Arguments of `waver diff`.
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    /// The waveform images made with --embed-metadata: two to compare, or those to check against --audio
+    #[arg(required = true, num_args = 1..=2, value_name = "PNG")]
+    pub images: Vec<PathBuf>,
+
+    /// The audio file to check the images against, which is decoded again
+    #[arg(long = "audio", value_name = "FILE", value_parser = clap::value_parser!(AudioPath))]
+    pub audio: Option<AudioPath>,
+
+    /// Format of the report (text or json)
+    #[arg(long = "report", default_value = "text", value_parser = clap::value_parser!(ReportFormat))]
+    pub report: ReportFormat,
+
+    /// The options the images were made with, as they would be given to waver (after --)
+    #[arg(last = true, allow_hyphen_values = true, value_name = "OPTIONS")]
+    pub options: Vec<OsString>,
+}
+
+impl DiffArgs {
+    /// Parses the options the images were made with like waver would,
+    /// including the environment variables.
+    pub fn matches(&self) -> clap::error::Result<ArgMatches> {
+        WaverArgs::matches_without_audio(&self.options)
+    }
+}
+
+/// Arguments of `waver bench`.
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    /// The audio files to time
+    #[arg(required = true, value_name = "FILE", value_parser = clap::value_parser!(AudioPath))]
+    pub files: Vec<AudioPath>,
+
+    /// Number of timed runs of every stage, after one to warm up
+    #[arg(long = "runs", value_name = "N", default_value_t = bench::DEFAULT_RUNS, value_parser = clap::value_parser!(u32).range(1..=1000))]
+    pub runs: u32,
+
+    /// The image formats to time the encode of (comma-separated, default: the --output-format of the options)
+    #[arg(long = "formats", value_name = "FORMATS", value_delimiter = ',', value_parser = clap::value_parser!(ImageFormat))]
+    pub formats: Vec<ImageFormat>,
+
+    /// Format of the report (text or json)
+    #[arg(long = "report", default_value = "text", value_parser = clap::value_parser!(ReportFormat))]
+    pub report: ReportFormat,
+
+    /// The options of the run to time, as they would be given to waver (after --)
+    #[arg(last = true, allow_hyphen_values = true, value_name = "OPTIONS")]
+    pub options: Vec<OsString>,
+}
+
+impl BenchArgs {
+    /// Parses the options of the run to time like waver would, including
+    /// the environment variables.
+    pub fn matches(&self) -> clap::error::Result<ArgMatches> {
+        WaverArgs::matches_without_audio(&self.options)
+    }
+}
+
+/// Where the value of an option came from.
+///
+/// The sources are ordered from the weakest to the strongest, a value from
+/// the environment replaces the default and the command line replaces both.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Origin {
+    /// The built-in default
+    Default,
+    /// The environment variable of the option
+    Environment(String),
+    /// The command line
+    CommandLine,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Default => write!(f, "default"),
+            Origin::Environment(name) => write!(f, "environment {name}"),
+            Origin::CommandLine => write!(f, "command line"),
+        }
+    }
+}
+
+/// An option with the value a run uses and where it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedOption {
+    /// The option, like `--width`
+    pub option: String,
+    /// The value (the values separated by commas for repeated options)
+    pub value: String,
+    /// Where the value came from
+    pub origin: Origin,
+}
+
+/// Returns the environment variable of an option (`WAVER_DB_FLOOR` for `db-floor`).
+pub fn env_name(long: &str) -> String {
+    format!("{ENV_PREFIX}{}", long.to_uppercase().replace('-', "_"))
+}
+
+/// Prints a command line error (or the help or version) and exits.
+///
+/// Unlike `clap::Error::exit` an invalid command line exits with the status
+/// of an argument error, as clap's own 2 is the status of failed files.
+pub fn exit_with(err: clap::Error) -> ! {
+    let _ = err.print();
+    let status = if err.use_stderr() { ExitStatus::ArgumentError } else { ExitStatus::Success };
+    std::process::exit(status.code() as i32)
+}
+
 impl WaverArgs {
-    /// Parse command-line arguments and validate them.
+    /// Returns the command line parser with every option also read from its
+    /// environment variable.
+    pub fn command_with_env() -> clap::Command {
+        Self::command().mut_args(|arg| match arg.get_long().map(env_name) {
+            Some(name) => arg.env(name).hide_env(true),
+            None => arg,
+        })
+    }
+
+    /// Parses the options of a run, including the environment variables,
+    /// without requiring any audio files (for the subcommands that take
+    /// them).
+    pub fn matches_without_audio(options: &[OsString]) -> clap::error::Result<ArgMatches> {
+        Self::command_with_env()
+            .mut_arg("audio_paths", |arg| arg.required_unless_present(clap::builder::Resettable::Reset))
+            .no_binary_name(true)
+            .try_get_matches_from(options)
+    }
+
+    /// Returns every option that has a value in the parsed arguments, with
+    /// where the value came from, in the order of the help.
+    pub fn resolved_options(matches: &ArgMatches) -> Vec<ResolvedOption> {
+        Self::command_with_env()
+            .get_arguments()
+            .filter_map(|arg| {
+                let long = arg.get_long()?;
+                let id = arg.get_id().as_str();
+                let origin = match matches.value_source(id)? {
+                    ValueSource::DefaultValue => Origin::Default,
+                    ValueSource::EnvVariable => Origin::Environment(env_name(long)),
+                    _ => Origin::CommandLine,
+                };
+                let value = matches
+                    .get_raw(id)?
+                    .map(|value| value.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(",");
+                Some(ResolvedOption { option: format!("--{long}"), value, origin })
+            })
+            .collect()
+    }
+
+    /// Returns the options that were given on the command line or in the
+    /// environment as command line arguments, to record them in the summary
+    /// for `--retry-from`.
+    ///
+    /// Where the reports go and what the retry reads are left to the retry,
+    /// so those options are not recorded.
+    pub fn run_arguments(matches: &ArgMatches) -> Vec<String> {
+        const UNRECORDED: [&str; 5] = ["retry-from", "report", "ignore-errors", "stdin", "files-from"];
+        let mut arguments = Vec::new();
+        for arg in Self::command_with_env().get_arguments() {
+            let Some(long) = arg.get_long().filter(|long| !UNRECORDED.contains(long)) else {
+                continue;
+            };
+            let id = arg.get_id().as_str();
+            if !matches!(matches.value_source(id), Some(ValueSource::CommandLine | ValueSource::EnvVariable)) {
+                continue;
+            }
+            // Flags from the environment can be false
+            if !arg.get_action().takes_values() {
+                if matches.get_flag(id) {
+                    arguments.push(format!("--{long}"));
+                }
+                continue;
+            }
+            for occurrence in matches.get_raw_occurrences(id).into_iter().flatten() {
+                let values: Vec<String> = occurrence.map(|value| value.to_string_lossy().into_owned()).collect();
+                match (&values[..], arg.get_value_delimiter()) {
+                    ([], _) => arguments.push(format!("--{long}")),
+                    // With = so values like -60 are not taken for options
+                    (values, Some(delimiter)) => {
+                        arguments.push(format!("--{long}={}", values.join(&delimiter.to_string())))
+                    }
+                    ([value], None) => arguments.push(format!("--{long}={value}")),
+                    (values, None) => {
+                        arguments.push(format!("--{long}"));
+                        arguments.extend(values.iter().cloned());
+                    }
+                }
+            }
+        }
+        arguments
+    }
+
+    /// Parse command-line arguments (and the environment variables of the
+    /// options) and validate them.
+    ///
+    /// With `--retry-from` the options recorded in the summary come first,
+    /// so the options on the command line replace them, and the failed
+    /// files of the summary are the files to process.
     pub fn parse_and_validate() -> Result<Self> {
-        let args = Self::parse();
+        let mut matches = Self::command_with_env().try_get_matches().unwrap_or_else(|err| exit_with(err));
+        let mut retry = None;
+        if let Some(report) = matches.get_one::<PathBuf>("retry_from") {
+            let previous = PreviousRun::read(report)?;
+            let arguments = previous.arguments.iter().map(OsString::from).chain(std::env::args_os().skip(1));
+            matches = Self::command_with_env()
+                .args_override_self(true)
+                .no_binary_name(true)
+                .try_get_matches_from(arguments)
+                .unwrap_or_else(|err| exit_with(err));
+            retry = Some(previous.failed);
+        }
+
+        let mut args = Self::from_arg_matches(&matches).unwrap_or_else(|err| exit_with(err));
+        args.arguments = Self::run_arguments(&matches);
+        if let Some(failed) = retry {
+            // The audio from stdin is gone, and the stems make one image,
+            // which is retried if it failed
+            let failed: Vec<&String> = failed.iter().filter(|path| !is_stdin(Path::new(path))).collect();
+            if args.stems.is_empty() {
+                args.audio_paths = failed.into_iter().map(AudioPath::new).collect::<Result<_>>()?;
+            } else if failed.is_empty() {
+                args.stems.clear();
+            }
+        }
+        // Made once, so everything the run writes has the same ID
+        if let Some(run_id @ None) = &mut args.run_id {
+            *run_id = Some(RunId::generate());
+        }
         args.validate()?;
         Ok(args)
     }
 
+    /// Returns the ID of the run, if it has one (`--run-id`).
+    pub fn run_id(&self) -> Option<RunId> {
+        self.run_id.clone().flatten()
+    }
+
     /// Validates inter-argument constraints that can't be handled by individual type validations.
     pub fn validate(&self) -> Result<()> {
         // Validate output filename constraints
@@ -85,60 +852,310 @@ impl WaverArgs {
             ));
         }
 
+        if self.trim_silence.is_some_and(|threshold| !threshold.is_finite() || threshold >= 0.0) {
+            return Err(WaverError::argument_error("--trim-silence must be a negative dB value"));
+        }
+        if !self.contrast.is_finite() || self.contrast <= 0.0 {
+            return Err(WaverError::argument_error("--contrast must be a positive number"));
+        }
+        if self.max_duration.is_some_and(|duration| duration.seconds() == 0.0) {
+            return Err(WaverError::argument_error("--max-duration must be longer than zero"));
+        }
+
+        // The audio is read once for the name and again for the image
+        if self.reads_stdin() && self.hash_names.is_some() {
+            return Err(WaverError::argument_error(
+                "Cannot use --hash-names with the audio from stdin",
+            ));
+        }
+
+        // The list and the audio can not both come from stdin
+        if self.reads_stdin() && self.files_from.as_deref().is_some_and(is_stdin) {
+            return Err(WaverError::argument_error(
+                "Cannot read both the --files-from list and the audio from stdin",
+            ));
+        }
+
+        // Audio from stdin has no file name to derive the image name from
+        if self.reads_stdin() && self.output_filename.is_none() {
+            return Err(WaverError::argument_error(
+                "Reading the audio from stdin requires --output-filename",
+            ));
+        }
+
+        // The art is read from the tags of the file after the image is written
+        if self.reads_stdin() && self.extract_art.is_some() {
+            return Err(WaverError::argument_error(
+                "Cannot use --extract-art with the audio from stdin",
+            ));
+        }
+
+        // The art is read from the tags of the file before it is decoded
+        if self.reads_stdin() && self.auto_color {
+            return Err(WaverError::argument_error(
+                "Cannot use --auto-color with the audio from stdin",
+            ));
+        }
+
+        // The reference decoder reads the file again
+        if self.reads_stdin() && self.cross_check.is_some() {
+            return Err(WaverError::argument_error(
+                "Cannot use --cross-check with the audio from stdin",
+            ));
+        }
+
+        if self.played_colors.as_ref().is_some_and(|colors| colors.len() > 2) {
+            return Err(WaverError::argument_error(
+                "--played-colors takes at most two colors, for the left and right channel",
+            ));
+        }
+
+        // Tiles, sidecars and the played and checkerboard images are extra
+        // files next to the image so there is nowhere to put them when the
+        // image goes to stdout
+        let extra_files = self.tiles.is_some()
+            || self.sidecar
+            || self.preview
+            || self.ticks_json
+            || self.progress_pair.is_some()
+            || self.played_colors.is_some()
+            || self.preview_checkerboard.is_some()
+            || self.bundle.is_some();
+        if self.writes_to_stdout() && extra_files {
+            return Err(WaverError::argument_error(
+                "Cannot use --tiles, --sidecar, --preview, --ticks-json, --progress-pair, --played-colors, --preview-checkerboard or --bundle with --output-filename -",
+            ));
+        }
+
+        // The reports would end up in the image or in each other
+        let reports_to_stdout = [&self.ignore_errors, &self.report]
+            .into_iter()
+            .filter(|report| report.as_deref().is_some_and(is_stdout))
+            .count();
+        if reports_to_stdout > 0 && self.writes_to_stdout() {
+            return Err(WaverError::argument_error(
+                "Cannot write the --ignore-errors or --report report to stdout with --output-filename -",
+            ));
+        }
+        if reports_to_stdout > 1 {
+            return Err(WaverError::argument_error(
+                "Only one of the --ignore-errors and --report reports can be written to stdout",
+            ));
+        }
+
+        // The dashboard takes over the terminal, which the image can not share
+        if self.tui && !dashboard::AVAILABLE {
+            return Err(WaverError::argument_error("The dashboard needs waver built with the tui feature"));
+        }
+        if self.tui && self.writes_to_stdout() {
+            return Err(WaverError::argument_error("Cannot use --tui with --output-filename -"));
+        }
+
         // Check directory constraints
         if self.output_filename.is_some() {
             for path in &self.audio_paths {
-                if path.is_dir() {
+                if path.is_dir() || path.is_pattern() {
                     return Err(WaverError::argument_error(
-                        "Cannot specify --output-filename with a directory",
+                        "Cannot specify --output-filename with a directory or a pattern",
                     ));
                 }
             }
         }
 
+        // The stems are the files of one image, so they are named one by one
+        if self.stems.iter().any(AudioPath::is_pattern) {
+            return Err(WaverError::argument_error("The --stems must be files, not patterns"));
+        }
+
         Ok(())
     }
 
-    /// Prints messages to stderr unless quiet mode is enabled.
-    pub fn print_to_stderr(&self, message: &str) {
-        if !self.quiet {
-            eprintln!("{message}");
-        }
+    /// Builds the waveform settings from the arguments.
+    pub fn config(&self) -> Result<WaveformConfig> {
+        self.config_builder().build()
+    }
+
+    /// Returns the builder of the waveform settings from the arguments, for
+    /// changing some of them before they are built.
+    pub fn config_builder(&self) -> WaveformConfigBuilder {
+        let existing_output = if self.overwrite {
+            ExistingOutput::Overwrite
+        } else if self.if_newer {
+            ExistingOutput::IfNewer
+        } else {
+            ExistingOutput::Skip
+        };
+
+        WaveformConfig::builder()
+            .width(self.width())
+            .height(self.height())
+            .micro(self.micro)
+            .mode(self.mode)
+            .colormap(self.colormap)
+            .left_color(self.left_color)
+            .right_color(self.right_color)
+            .background_color(self.background_color)
+            .background_image(self.background_image.clone())
+            .color_seed(self.color_seed)
+            .auto_color(self.auto_color)
+            .color_map(self.color_map.clone())
+            .scale(self.scale)
+            .db_floor(self.db_floor.value())
+            .contrast(self.contrast)
+            .channels(self.channels)
+            .layout(self.layout)
+            .mono_style(self.mono_style)
+            .stem_colors(self.stem_colors.clone())
+            .normalize(self.normalize)
+            .auto_gain(self.auto_gain)
+            .tint_by_loudness(self.tint_by_loudness)
+            .antialias(self.antialias)
+            .envelope(self.envelope)
+            .rms_layer(self.rms_layer)
+            .balance_strip(self.balance_strip)
+            .overview_ratio(self.composite.map(|ratio| ratio.value()))
+            .duration_badge(self.duration_badge)
+            .center_line(self.center_line)
+            .borders(self.borders)
+            .grid(self.grid.map(|interval| interval.seconds()))
+            .ticks(self.ticks.map(|interval| interval.seconds()))
+            .ticks_json(self.ticks_json)
+            .cue_markers(self.cue_markers)
+            .markers(self.markers.clone())
+            .max_packets(self.max_packets)
+            .sidecar(self.sidecar)
+            .embed_metadata(self.embed_metadata)
+            .run_id(self.run_id())
+            .extract_art(self.extract_art.clone())
+            .preview(self.preview)
+            .detect_silence(self.detect_silence)
+            .silence_threshold(self.silence_threshold)
+            .silence_min_duration(self.silence_min_duration.seconds())
+            .measure_loudness(self.measure_loudness)
+            .detect_bpm(self.detect_bpm)
+            .progress_pair(self.progress_pair)
+            .played_colors(self.played_colors())
+            .preview_checkerboard(self.preview_checkerboard)
+            .peak_cache(self.peak_cache)
+            .peak_index(self.peak_index)
+            .pcm_hash(self.pcm_hash)
+            .tiles(self.tiles.map(|width| width.value()))
+            .start(self.start.map(|start| start.seconds()))
+            .end(self.end_seconds())
+            .trim_silence(self.trim_silence)
+            .stdin_format(self.stdin_format.as_ref().map(|format| format.as_str().to_string()))
+            .format(self.output_format)
+            .video(self.video)
+            .segment(self.segment.map(|length| length.seconds()))
+            .segment_overlap(self.segment_overlap.map_or(0.0, |overlap| overlap.seconds()))
+            .segment_name(Some(self.segment_name.clone()))
+            .jpeg_quality(self.quality.value())
+            .existing_output(existing_output)
+            .verify_existing(self.verify_existing)
+            .dry_run(self.dry_run)
     }
 
-    /// Prints messages to stdout (usually for successful operations).
-    pub fn print_to_stdout(&self, message: &str) {
-        if !self.quiet {
-            println!("{message}");
+    /// Returns the level of the messages that are printed.
+    pub fn log_level(&self) -> LogLevel {
+        match self.log_level {
+            Some(level) => level,
+            None if self.quiet => LogLevel::Off,
+            None if self.verbose => LogLevel::Debug,
+            None => LogLevel::Info,
         }
     }
 
-    /// Prints verbose messages if verbose mode is enabled.
-    pub fn print_verbose(&self, message: &str) {
-        if self.verbose {
-            println!("{message}");
+    /// Returns true if an output skipped because it exists fails its file:
+    /// when asked for details or for warnings to be errors, but not in a
+    /// dry run, which only reports what it would skip.
+    pub fn skip_fails(&self) -> bool {
+        !self.dry_run && (self.strict || self.log_level() == LogLevel::Debug)
+    }
+
+    /// Returns the logger of the run, which prints its messages with the
+    /// --log-level and --log-json.
+    pub fn logger(&self) -> Logger {
+        Logger::new(self.log_level(), self.log_json, self.writes_to_stdout())
+    }
+
+    /// Returns the channel colors of the played copy from --played-colors:
+    /// one color is used for both channels, and without colors the channel
+    /// colors are faded halfway to the background.
+    fn played_colors(&self) -> Option<(Rgba, Rgba)> {
+        let colors = self.played_colors.as_ref()?;
+        let faded = |color: &Rgba| color.mix(&self.background_color, 0.5);
+        Some(match colors[..] {
+            [left, right] => (left, right),
+            [both] => (both, both),
+            _ => (faded(&self.left_color), faded(&self.right_color)),
+        })
+    }
+
+    /// Returns the end time in seconds, from either --end or --duration.
+    fn end_seconds(&self) -> Option<f64> {
+        let start = self.start.map_or(0.0, |start| start.seconds());
+        self.end
+            .map(|end| end.seconds())
+            .or_else(|| self.duration.map(|duration| start + duration.seconds()))
+    }
+
+    /// Returns true if the audio is read from stdin.
+    pub fn reads_stdin(&self) -> bool {
+        self.stdin || self.audio_paths.iter().any(AudioPath::is_stdin)
+    }
+
+    /// Returns true if the image is written to stdout.
+    pub fn writes_to_stdout(&self) -> bool {
+        self.output_filename
+            .as_deref()
+            .is_some_and(|name| is_stdout(Path::new(name)))
+    }
+
+    /// Returns the output of one file of the batch, which collects its
+    /// messages with the rules of the logger until they are printed in the
+    /// `--output-order`.
+    pub fn file_output(&self, file: &Path) -> FileOutput {
+        FileOutput::new(self.logger(), file.display().to_string())
+    }
+
+    /// Returns how many files may fail while the run still succeeds.
+    pub fn error_budget(&self) -> ErrorBudget {
+        ErrorBudget {
+            max_failures: self.max_failures,
+            max_rate: self.max_failure_rate.map(|rate| rate.fraction()),
         }
     }
 
     /// Returns the width value.
     pub fn width(&self) -> u32 {
-        self.width.value()
+        self.width
     }
 
     /// Returns the height value.
-    #[allow(dead_code)]
     pub fn height(&self) -> u32 {
-        self.height.value()
+        self.height
     }
 
     /// Returns the center line position.
-    #[allow(dead_code)]
     pub fn center(&self) -> u32 {
-        self.height.center()
+        self.height / 2
     }
 
     /// Returns the file extensions as strings.
     pub fn file_extensions(&self) -> Vec<String> {
         self.file_extensions.as_strings()
     }
+
+    /// Returns how directories are searched for audio files.
+    pub fn traversal(&self) -> Traversal {
+        Traversal::new(!self.no_follow_symlinks, self.hidden, self.max_depth)
+    }
+
+    /// Returns the limits of the files that are drawn.
+    pub fn limits(&self) -> Limits {
+        Limits {
+            max_file_size: self.max_file_size.map(|size| size.bytes()),
+            max_duration: self.max_duration.map(|duration| duration.seconds()),
+        }
+    }
 }
\ No newline at end of file
@@ -1,7 +1,7 @@
 //! Unit tests for the CLI types module.
 
 use std::str::FromStr;
-use crate::cli::types::{Width, Height, AudioPath, FileExtension, FileExtensions};
+use crate::cli::types::{Width, Height, AudioPath, FileExtension, FileExtensions, Corner, OverviewRatio, FailureRate, AutoGain, TileWidth, TimeOffset, DbFloor, FileSize, ImageFormat, JpegQuality, WaveLayout, MonoStyle, Overlap, RenderMode, BundleFormat, BundleScope};
 use std::path::Path;
 use tempfile::NamedTempFile;
 
@@ -164,6 +164,15 @@ mod audio_path_tests {
         let audio_path = AudioPath::from_str(&path_str).unwrap();
         assert_eq!(audio_path.path(), Path::new(&path_str));
         assert!(!audio_path.is_dir());
+        assert!(!audio_path.is_stdin());
+    }
+
+    #[test]
+    fn test_stdin_audio_path() {
+        // The dash stands for stdin and never exists as a file
+        let audio_path = AudioPath::from_str("-").unwrap();
+        assert!(audio_path.is_stdin());
+        assert!(!audio_path.is_dir());
     }
 
     #[test]
@@ -287,4 +296,514 @@ mod file_extensions_tests {
         assert_eq!(err.to_string(), "Invalid argument: No file extensions specified", 
                   "Should provide clear error message for whitespace and comma list");
     }
-}
\ No newline at end of file
+}
+// Test Corner from_str implementation
+#[cfg(test)]
+mod corner_tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_corners() {
+        assert_eq!(Corner::from_str("top-left").unwrap(), Corner::TopLeft);
+        assert_eq!(Corner::from_str("top-right").unwrap(), Corner::TopRight);
+        assert_eq!(Corner::from_str("bottom-left").unwrap(), Corner::BottomLeft);
+        assert_eq!(Corner::from_str("bottom-right").unwrap(), Corner::BottomRight);
+
+        // Case and whitespace are ignored
+        assert_eq!(Corner::from_str(" Bottom-Right ").unwrap(), Corner::BottomRight);
+    }
+
+    #[test]
+    fn test_corner_edges() {
+        assert!(Corner::TopLeft.is_top() && Corner::TopLeft.is_left());
+        assert!(Corner::TopRight.is_top() && !Corner::TopRight.is_left());
+        assert!(!Corner::BottomLeft.is_top() && Corner::BottomLeft.is_left());
+        assert!(!Corner::BottomRight.is_top() && !Corner::BottomRight.is_left());
+    }
+
+    #[test]
+    fn test_invalid_corner() {
+        let result = Corner::from_str("middle");
+        assert!(result.is_err(), "Should reject unknown corners");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid argument: Corner must be top-left, top-right, bottom-left, or bottom-right"
+        );
+    }
+}
+
+// Test OverviewRatio from_str implementation
+#[cfg(test)]
+mod overview_ratio_tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_ratio() {
+        assert_eq!(OverviewRatio::from_str("0.25").unwrap().value(), 0.25);
+        assert_eq!(OverviewRatio::from_str("0.5").unwrap().value(), 0.5);
+        assert_eq!(OverviewRatio::from_str(" 0.1 ").unwrap().value(), 0.1);
+    }
+
+    #[test]
+    fn test_invalid_ratio() {
+        for input in ["0", "-0.1", "0.6", "1", "NaN"] {
+            let result = OverviewRatio::from_str(input);
+            assert!(result.is_err(), "Should reject ratio {}", input);
+            assert_eq!(
+                result.unwrap_err().to_string(),
+                "Invalid argument: Overview ratio must be above 0 and at most 0.5"
+            );
+        }
+
+        let result = OverviewRatio::from_str("quarter");
+        assert!(result.is_err(), "Should reject non-numeric input");
+        assert_eq!(result.unwrap_err().to_string(), "Invalid argument: Overview ratio must be a number");
+    }
+}
+
+// Test FailureRate from_str implementation
+#[cfg(test)]
+mod failure_rate_tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_rate() {
+        assert_eq!(FailureRate::from_str("1%").unwrap().fraction(), 0.01);
+        assert_eq!(FailureRate::from_str(" 0.5 % ").unwrap().fraction(), 0.005);
+        assert_eq!(FailureRate::from_str("100%").unwrap().fraction(), 1.0);
+        assert_eq!(FailureRate::from_str("0%").unwrap().fraction(), 0.0);
+    }
+
+    #[test]
+    fn test_invalid_rate() {
+        for input in ["1", "0.01", "%", "one%", ""] {
+            let result = FailureRate::from_str(input);
+            assert!(result.is_err(), "Should reject rate {}", input);
+            assert_eq!(result.unwrap_err().to_string(), "Invalid argument: Failure rate must be a percentage like 1%");
+        }
+        for input in ["101%", "-1%", "NaN%"] {
+            let result = FailureRate::from_str(input);
+            assert_eq!(result.unwrap_err().to_string(), "Invalid argument: Failure rate must be 0% to 100%");
+        }
+    }
+}
+
+// Test AutoGain from_str implementation
+#[cfg(test)]
+mod auto_gain_tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_gains() {
+        assert_eq!(AutoGain::from_str("percentile=95").unwrap().percentile(), 95.0);
+        assert_eq!(AutoGain::from_str(" percentile=99.5 ").unwrap().percentile(), 99.5);
+        assert_eq!(AutoGain::from_str("percentile=100").unwrap().percentile(), 100.0);
+        for input in ["95", "percentile=", "percentile=high", "peak=95", ""] {
+            let result = AutoGain::from_str(input);
+            assert_eq!(
+                result.unwrap_err().to_string(),
+                "Invalid argument: Auto gain must be given as percentile=N, like percentile=95"
+            );
+        }
+        for input in ["percentile=0", "percentile=101", "percentile=-5", "percentile=NaN"] {
+            let result = AutoGain::from_str(input);
+            assert!(result.is_err(), "Should reject {}", input);
+        }
+    }
+}
+
+// Test TileWidth from_str implementation
+#[cfg(test)]
+mod tile_width_tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_tile_width() {
+        assert_eq!(TileWidth::from_str("512").unwrap().value(), 512);
+        assert_eq!(TileWidth::from_str("16").unwrap().value(), 16);
+    }
+
+    #[test]
+    fn test_invalid_tile_width() {
+        for input in ["8", "500", "0"] {
+            let result = TileWidth::from_str(input);
+            assert!(result.is_err(), "Should reject tile width {}", input);
+            assert_eq!(
+                result.unwrap_err().to_string(),
+                "Invalid argument: Tile width must be a multiple of 8 and at least 16 pixels"
+            );
+        }
+
+        let result = TileWidth::from_str("wide");
+        assert!(result.is_err(), "Should reject non-numeric input");
+        assert_eq!(result.unwrap_err().to_string(), "Invalid argument: Tile width must be a positive integer");
+    }
+}
+
+// Test DbFloor from_str implementation
+#[cfg(test)]
+mod db_floor_tests {
+    use super::*;
+
+    #[test]
+    fn test_db_floor() {
+        assert_eq!(DbFloor::from_str("-60").unwrap().value(), -60.0);
+        assert_eq!(DbFloor::from_str(" -12.5").unwrap().value(), -12.5);
+        for input in ["0", "6", "-inf", "inf", "NaN", "quiet"] {
+            let result = DbFloor::from_str(input);
+            assert!(result.is_err(), "Should reject dB floor {}", input);
+            assert_eq!(result.unwrap_err().to_string(), "Invalid argument: --db-floor must be a negative dB value");
+        }
+    }
+}
+
+// Test TimeOffset from_str implementation
+#[cfg(test)]
+mod time_offset_tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_times() {
+        assert_eq!(TimeOffset::from_str("90").unwrap().seconds(), 90.0);
+        assert_eq!(TimeOffset::from_str("1:30").unwrap().seconds(), 90.0);
+        assert_eq!(TimeOffset::from_str("01:30.250").unwrap().seconds(), 90.25);
+        assert_eq!(TimeOffset::from_str("1:01:30").unwrap().seconds(), 3690.0);
+        assert_eq!(TimeOffset::from_str("0.5").unwrap().seconds(), 0.5);
+        assert_eq!(TimeOffset::from_str("10m").unwrap().seconds(), 600.0);
+        assert_eq!(TimeOffset::from_str("1h30m").unwrap().seconds(), 5400.0);
+        assert_eq!(TimeOffset::from_str("1m2.5s").unwrap().seconds(), 62.5);
+        assert_eq!(TimeOffset::from_str(" 2h ").unwrap().seconds(), 7200.0);
+    }
+
+    #[test]
+    fn test_invalid_times() {
+        let invalid = [
+            "", "1:60", "1:60:00", "-5", "1:-5", "1.5:00", "a:00", "1:2:3:4", "+3", "inf",
+            "m", "10x", "1m1h", "1m1m", "-5m", ".5m", "1:30m",
+        ];
+        for input in invalid {
+            let result = TimeOffset::from_str(input);
+            assert!(result.is_err(), "Should reject time {:?}", input);
+            assert_eq!(result.unwrap_err().to_string(), "Invalid argument: Time must be [[hh:]mm:]ss[.mmm] or like 1h30m");
+        }
+    }
+}
+
+// Test ImageFormat and JpegQuality from_str implementations
+#[cfg(test)]
+mod image_format_tests {
+    use super::*;
+
+    #[test]
+    fn test_image_formats() {
+        assert_eq!(ImageFormat::from_str("png").unwrap(), ImageFormat::Png);
+        assert_eq!(ImageFormat::from_str(" JPEG ").unwrap(), ImageFormat::Jpeg);
+        assert_eq!(ImageFormat::from_str("jpg").unwrap(), ImageFormat::Jpeg);
+        assert_eq!(ImageFormat::from_str("bmp").unwrap(), ImageFormat::Bmp);
+        assert_eq!(ImageFormat::from_str("RGB565").unwrap(), ImageFormat::Rgb565);
+        assert_eq!(ImageFormat::from_str("ppm").unwrap(), ImageFormat::Ppm);
+        assert_eq!(ImageFormat::from_str("farbfeld").unwrap(), ImageFormat::Farbfeld);
+        assert_eq!(ImageFormat::from_str("qoi").unwrap(), ImageFormat::Qoi);
+        assert_eq!(ImageFormat::from_str("WebP").unwrap(), ImageFormat::Webp);
+        assert_eq!(ImageFormat::from_str("avif").unwrap(), ImageFormat::Avif);
+        assert_eq!(ImageFormat::Jpeg.extension(), "jpg");
+        assert_eq!(ImageFormat::Webp.extension(), "webp");
+
+        let result = ImageFormat::from_str("gif");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid argument: Output format must be png, jpeg, bmp, rgb565, ppm, farbfeld, qoi, webp, or avif"
+        );
+    }
+
+    #[test]
+    fn test_jpeg_quality() {
+        assert_eq!(JpegQuality::from_str("1").unwrap().value(), 1);
+        assert_eq!(JpegQuality::from_str("100").unwrap().value(), 100);
+
+        for input in ["0", "101", "high", "-5"] {
+            let result = JpegQuality::from_str(input);
+            assert!(result.is_err(), "Should reject quality {}", input);
+            assert_eq!(result.unwrap_err().to_string(), "Invalid argument: JPEG quality must be 1 to 100");
+        }
+    }
+}
+
+// Test WaveLayout from_str implementation
+#[cfg(test)]
+mod wave_layout_tests {
+    use super::*;
+
+    #[test]
+    fn test_layouts() {
+        assert_eq!(WaveLayout::from_str("combined").unwrap(), WaveLayout::Combined);
+        assert_eq!(WaveLayout::from_str(" Stacked ").unwrap(), WaveLayout::Stacked);
+
+        let result = WaveLayout::from_str("grid");
+        assert_eq!(result.unwrap_err().to_string(), "Invalid argument: Layout must be combined or stacked");
+    }
+
+    #[test]
+    fn test_mono_styles() {
+        assert_eq!(MonoStyle::from_str("mirrored").unwrap(), MonoStyle::Mirrored);
+        assert_eq!(MonoStyle::from_str(" Top").unwrap(), MonoStyle::Top);
+        assert_eq!(MonoStyle::from_str("BOTTOM").unwrap(), MonoStyle::Bottom);
+
+        let result = MonoStyle::from_str("center");
+        assert_eq!(result.unwrap_err().to_string(), "Invalid argument: Mono style must be mirrored, top or bottom");
+    }
+
+    #[test]
+    fn test_overlaps() {
+        assert_eq!(Overlap::from_str("keep-left").unwrap(), Overlap::KeepLeft);
+        assert_eq!(Overlap::from_str(" Keep-Right").unwrap(), Overlap::KeepRight);
+        assert_eq!(Overlap::from_str("MARK").unwrap(), Overlap::Mark);
+        assert_eq!(Overlap::default(), Overlap::Mark);
+
+        let result = Overlap::from_str("blend");
+        assert_eq!(result.unwrap_err().to_string(), "Invalid argument: Overlap must be keep-left, keep-right or mark");
+    }
+}
+
+// Test BundleFormat and BundleScope from_str implementations
+#[cfg(test)]
+mod bundle_tests {
+    use super::*;
+
+    #[test]
+    fn test_bundles() {
+        assert_eq!(BundleFormat::from_str(" ZIP").unwrap(), BundleFormat::Zip);
+        let result = BundleFormat::from_str("tar");
+        assert_eq!(result.unwrap_err().to_string(), "Invalid argument: Bundle format must be zip");
+
+        assert_eq!(BundleScope::from_str("file").unwrap(), BundleScope::File);
+        assert_eq!(BundleScope::from_str("Directory").unwrap(), BundleScope::Directory);
+        assert_eq!(BundleScope::from_str("dir").unwrap(), BundleScope::Directory);
+        let result = BundleScope::from_str("album");
+        assert_eq!(result.unwrap_err().to_string(), "Invalid argument: Bundles must be by file or directory");
+    }
+}
+
+// Test RenderMode from_str implementation
+#[cfg(test)]
+mod render_mode_tests {
+    use super::*;
+
+    #[test]
+    fn test_render_modes() {
+        assert_eq!(RenderMode::from_str("waveform").unwrap(), RenderMode::Waveform);
+        assert_eq!(RenderMode::from_str(" Spectrogram ").unwrap(), RenderMode::Spectrogram);
+
+        let result = RenderMode::from_str("spectrum");
+        assert_eq!(result.unwrap_err().to_string(), "Invalid argument: Mode must be waveform or spectrogram");
+    }
+}
+
+// Test the relaxed minimums of micro images
+#[cfg(test)]
+mod micro_size_tests {
+    use super::*;
+
+    #[test]
+    fn test_micro_minimums() {
+        assert_eq!(Width::new_micro(4).unwrap().value(), 4);
+        assert_eq!(Height::new_micro(2).unwrap().value(), 2);
+        assert!(Width::new(4).is_err(), "The normal minimum still applies");
+        assert!(Height::new(2).is_err(), "The normal minimum still applies");
+
+        let result = Width::new_micro(3);
+        assert_eq!(result.unwrap_err().to_string(), "Invalid argument: Width must be at least 4 pixels");
+        let result = Height::new_micro(0);
+        assert_eq!(result.unwrap_err().to_string(), "Invalid argument: Height must be at least 2 pixels");
+        let result = Height::new_micro(3);
+        assert_eq!(result.unwrap_err().to_string(), "Invalid argument: Height must be an even number");
+    }
+}
+
+// Test the sizes of --max-file-size
+#[cfg(test)]
+mod file_size_tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_sizes() {
+        assert_eq!(FileSize::from_str("1000").unwrap().bytes(), 1000);
+        assert_eq!(FileSize::from_str("2k").unwrap().bytes(), 2048);
+        assert_eq!(FileSize::from_str("500M").unwrap(), FileSize::from_str("500MiB").unwrap());
+        assert_eq!(FileSize::from_str(" 500mb ").unwrap().bytes(), 500 << 20);
+        assert_eq!(FileSize::from_str("1.5G").unwrap().bytes(), 3 << 29);
+        assert_eq!(FileSize::from_str("10B").unwrap().bytes(), 10);
+    }
+
+    #[test]
+    fn test_invalid_sizes() {
+        for input in ["", "M", "-5M", "5X", "5 M", "5mib2", ".5G", "inf"] {
+            let result = FileSize::from_str(input);
+            assert!(result.is_err(), "Should reject size {:?}", input);
+        }
+        assert_eq!(FileSize::from_str("0").unwrap_err().to_string(), "Invalid argument: File size must be more than zero");
+    }
+}
+
+// Test which names a directory search includes
+#[cfg(test)]
+mod traversal_tests {
+    use crate::cli::types::Traversal;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn test_hidden_names() {
+        let traversal = Traversal::default();
+        assert!(traversal.follow_symlinks && traversal.max_depth.is_none());
+        assert!(traversal.includes(OsStr::new("song.mp3")));
+        assert!(traversal.includes(OsStr::new("live.2024.flac")), "Only a leading dot hides");
+        assert!(!traversal.includes(OsStr::new("._song.mp3")));
+        assert!(!traversal.includes(OsStr::new(".Trash")));
+
+        let hidden = Traversal::new(false, true, Some(2));
+        assert!(hidden.includes(OsStr::new("._song.mp3")));
+        assert_eq!(hidden.max_depth, Some(2));
+    }
+}
+
+// Test the validated values compare and hash by their value
+#[cfg(test)]
+mod equality_tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_sizes_compare_by_value() {
+        assert_eq!(Width::from_str("2048").unwrap(), Width::new(2048).unwrap());
+        assert_ne!(Width::new(2048).unwrap(), Width::new(1024).unwrap());
+        assert_eq!(Height::new_micro(128).unwrap(), Height::new(128).unwrap(), "Micro is only a minimum");
+
+        let sizes: HashSet<(Width, Height)> = [(2048, 128), (1024, 64), (2048, 128)]
+            .iter()
+            .map(|&(width, height)| (Width::new(width).unwrap(), Height::new(height).unwrap()))
+            .collect();
+        assert_eq!(sizes.len(), 2);
+    }
+
+    #[test]
+    fn test_extensions_compare_normalized() {
+        assert_eq!(FileExtension::from_str(" MP3").unwrap(), FileExtension::from_str("mp3").unwrap());
+        assert_eq!(FileExtensions::from_str("MP3, wav").unwrap(), FileExtensions::from_str("mp3,WAV").unwrap());
+        assert_ne!(
+            FileExtensions::from_str("mp3,wav").unwrap(),
+            FileExtensions::from_str("wav,mp3").unwrap(),
+            "The order is kept"
+        );
+
+        let lists: HashSet<FileExtensions> = ["mp3", "MP3", "flac"]
+            .iter()
+            .map(|list| FileExtensions::from_str(list).unwrap())
+            .collect();
+        assert_eq!(lists.len(), 2);
+    }
+}
+
+// Test the options read from the environment and where the values came from
+#[cfg(test)]
+mod origin_tests {
+    use crate::cli::{env_name, ConfigArgs, Origin, WaverArgs};
+
+    fn resolve(options: &[&str]) -> Vec<(String, String, Origin)> {
+        let config = ConfigArgs { show: true, options: options.iter().map(Into::into).collect() };
+        WaverArgs::resolved_options(&config.matches().unwrap())
+            .into_iter()
+            .map(|option| (option.option, option.value, option.origin))
+            .collect()
+    }
+
+    fn find(resolved: &[(String, String, Origin)], option: &str) -> (String, Origin) {
+        let (_, value, origin) = resolved.iter().find(|(name, _, _)| name == option).unwrap();
+        (value.clone(), origin.clone())
+    }
+
+    #[test]
+    fn test_env_name() {
+        assert_eq!(env_name("width"), "WAVER_WIDTH");
+        assert_eq!(env_name("left-color"), "WAVER_LEFT_COLOR");
+    }
+
+    #[test]
+    fn test_origins() {
+        // Only this test sets the variable, the other tests see its default
+        std::env::set_var("WAVER_QUALITY", "70");
+        let resolved = resolve(&["--width", "512", "--rms-layer"]);
+        std::env::remove_var("WAVER_QUALITY");
+
+        assert_eq!(find(&resolved, "--width"), ("512".to_string(), Origin::CommandLine));
+        assert_eq!(find(&resolved, "--rms-layer"), ("true".to_string(), Origin::CommandLine));
+        assert_eq!(find(&resolved, "--height"), ("128".to_string(), Origin::Default));
+        assert_eq!(
+            find(&resolved, "--quality"),
+            ("70".to_string(), Origin::Environment("WAVER_QUALITY".to_string()))
+        );
+        assert!(resolved.iter().all(|(name, _, _)| name != "--output-filename"), "Options without a value are left out");
+        assert!(Origin::Default < Origin::Environment(String::new()) && Origin::Environment(String::new()) < Origin::CommandLine);
+    }
+
+    #[test]
+    fn test_command_line_wins() {
+        std::env::set_var("WAVER_DB_FLOOR", "-40");
+        let resolved = resolve(&["--db-floor", "-30"]);
+        std::env::remove_var("WAVER_DB_FLOOR");
+        assert_eq!(find(&resolved, "--db-floor"), ("-30".to_string(), Origin::CommandLine));
+    }
+
+    #[test]
+    fn test_run_arguments() {
+        let options = ["--width", "512", "--db-floor", "-30", "--sidecar", "--played-colors", "f00,0f0"];
+        let config = ConfigArgs {
+            show: true,
+            options: options.iter().chain(&["--report", "run.json", "--preview-checkerboard"]).map(Into::into).collect(),
+        };
+        let arguments = WaverArgs::run_arguments(&config.matches().unwrap());
+        let expected = [
+            "--width=512",
+            "--db-floor=-30",
+            "--sidecar",
+            "--played-colors=f00,0f0",
+            "--preview-checkerboard=8",
+        ];
+        assert_eq!(arguments, expected, "In the order of the help, without the report and the defaults");
+    }
+
+    #[test]
+    fn test_run_id() {
+        use clap::FromArgMatches;
+
+        let parse = |options: &[&str]| {
+            let config = ConfigArgs { show: true, options: options.iter().map(Into::into).collect() };
+            let matches = config.matches().unwrap();
+            (WaverArgs::from_arg_matches(&matches).unwrap().run_id, WaverArgs::run_arguments(&matches))
+        };
+        let (run_id, arguments) = parse(&["--run-id", "-"]);
+        assert_eq!(run_id, Some(None), "The ID is made after parsing, the path is not taken for it");
+        assert_eq!(arguments, ["--run-id"], "A retry makes its own ID");
+        let (run_id, arguments) = parse(&["--run-id=nightly-42"]);
+        assert_eq!(run_id, Some(Some("nightly-42".parse().unwrap())));
+        assert_eq!(arguments, ["--run-id=nightly-42"]);
+        assert_eq!(parse(&[]).0, None);
+        let config = ConfigArgs { show: true, options: vec!["--run-id=a/b".into()] };
+        assert!(config.matches().is_err());
+    }
+
+    #[test]
+    fn test_skip_fails() {
+        use clap::FromArgMatches;
+
+        let skip_fails = |options: &[&str]| {
+            let config = ConfigArgs { show: true, options: options.iter().map(Into::into).collect() };
+            WaverArgs::from_arg_matches(&config.matches().unwrap()).unwrap().skip_fails()
+        };
+        assert!(!skip_fails(&[]));
+        assert!(skip_fails(&["--verbose"]));
+        assert!(skip_fails(&["--log-level", "debug"]));
+        assert!(skip_fails(&["--strict"]));
+        assert!(!skip_fails(&["--dry-run", "--verbose"]), "A dry run only reports the skip");
+        assert!(!skip_fails(&["--dry-run", "--strict"]));
+    }
+}
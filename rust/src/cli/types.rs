@@ -15,30 +15,46 @@
 /// # Usage
 ///
 /// These types implement FromStr and can be used with clap's value_parser:
-/// ```
+/// ```ignore
 /// #[arg(value_parser = clap::value_parser!(Width))]
 /// pub width: Width,
 /// ```
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
+use crate::collect::is_pattern;
+use crate::config::is_stdin;
 use crate::error::{Result, WaverError};
 
 /// A validated width value for the waveform image.
 ///
-/// Ensures the width is at least 16 pixels.
-#[derive(Debug, Clone, Copy)]
+/// Ensures the width is at least 16 pixels, or 4 pixels for micro
+/// (sparkline) images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Width(u32);
 
 impl Width {
     /// The minimum allowed width in pixels.
     pub const MIN_WIDTH: u32 = 16;
 
+    /// The minimum allowed width in pixels of a micro image.
+    pub const MICRO_MIN_WIDTH: u32 = 4;
+
     /// Creates a new validated width.
     pub fn new(width: u32) -> Result<Self> {
-        if width < Self::MIN_WIDTH {
+        Self::at_least(width, Self::MIN_WIDTH)
+    }
+
+    /// Creates a new validated width for a micro (sparkline) image.
+    pub fn new_micro(width: u32) -> Result<Self> {
+        Self::at_least(width, Self::MICRO_MIN_WIDTH)
+    }
+
+    fn at_least(width: u32, min: u32) -> Result<Self> {
+        if width < min {
             return Err(WaverError::argument_error(
-                format!("Width must be at least {} pixels", Self::MIN_WIDTH)
+                format!("Width must be at least {min} pixels")
             ));
         }
         Ok(Self(width))
@@ -60,24 +76,74 @@ impl FromStr for Width {
     }
 }
 
+/// A validated width of the tiles in `--tiles` mode.
+///
+/// Ensures the tile width is at least 16 pixels and a multiple of 8 so that
+/// every tile starts on a byte boundary of the packed image rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TileWidth(u32);
+
+impl TileWidth {
+    /// The minimum allowed tile width in pixels.
+    pub const MIN_WIDTH: u32 = 16;
+
+    /// Creates a new validated tile width.
+    pub fn new(width: u32) -> Result<Self> {
+        if width < Self::MIN_WIDTH || !width.is_multiple_of(8) {
+            return Err(WaverError::argument_error(
+                format!("Tile width must be a multiple of 8 and at least {} pixels", Self::MIN_WIDTH)
+            ));
+        }
+        Ok(Self(width))
+    }
+
+    /// Returns the tile width value.
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+}
+
+impl FromStr for TileWidth {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let width = s.parse::<u32>()
+            .map_err(|_| WaverError::argument_error("Tile width must be a positive integer"))?;
+        Self::new(width)
+    }
+}
+
 /// A validated height value for the waveform image.
 ///
-/// Ensures the height is at least 6 pixels and even.
-#[derive(Debug, Clone, Copy)]
+/// Ensures the height is even and at least 6 pixels, or 2 pixels for micro
+/// (sparkline) images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Height(u32);
 
 impl Height {
     /// The minimum allowed height in pixels.
     pub const MIN_HEIGHT: u32 = 6;
 
+    /// The minimum allowed height in pixels of a micro image.
+    pub const MICRO_MIN_HEIGHT: u32 = 2;
+
     /// Creates a new validated height.
     pub fn new(height: u32) -> Result<Self> {
-        if height < Self::MIN_HEIGHT {
+        Self::at_least(height, Self::MIN_HEIGHT)
+    }
+
+    /// Creates a new validated height for a micro (sparkline) image.
+    pub fn new_micro(height: u32) -> Result<Self> {
+        Self::at_least(height, Self::MICRO_MIN_HEIGHT)
+    }
+
+    fn at_least(height: u32, min: u32) -> Result<Self> {
+        if height < min {
             return Err(WaverError::argument_error(
-                format!("Height must be at least {} pixels", Self::MIN_HEIGHT)
+                format!("Height must be at least {min} pixels")
             ));
         }
-        if height % 2 != 0 {
+        if !height.is_multiple_of(2) {
             return Err(WaverError::argument_error("Height must be an even number"));
         }
         Ok(Self(height))
@@ -104,17 +170,312 @@ impl FromStr for Height {
     }
 }
 
+/// A validated fraction of the waveform height given to the overview strip.
+///
+/// Ensures the ratio is above 0 and at most 0.5 so that the detail waveform
+/// is never smaller than the overview.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverviewRatio(f32);
+
+impl OverviewRatio {
+    /// The largest allowed ratio.
+    pub const MAX_RATIO: f32 = 0.5;
+
+    /// Creates a new validated overview ratio.
+    pub fn new(ratio: f32) -> Result<Self> {
+        if !(ratio > 0.0 && ratio <= Self::MAX_RATIO) {
+            return Err(WaverError::argument_error(
+                format!("Overview ratio must be above 0 and at most {}", Self::MAX_RATIO)
+            ));
+        }
+        Ok(Self(ratio))
+    }
+
+    /// Returns the ratio value.
+    pub fn value(&self) -> f32 {
+        self.0
+    }
+}
+
+impl FromStr for OverviewRatio {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let ratio = s.trim().parse::<f32>()
+            .map_err(|_| WaverError::argument_error("Overview ratio must be a number"))?;
+        Self::new(ratio)
+    }
+}
+
+/// A validated share of the files of a batch that may fail, given as a
+/// percentage like `1%` or `0.5%`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FailureRate(f64);
+
+impl FailureRate {
+    /// Creates a new validated failure rate from a fraction (0.01 for 1%).
+    pub fn new(fraction: f64) -> Result<Self> {
+        if !(0.0..=1.0).contains(&fraction) {
+            return Err(WaverError::argument_error("Failure rate must be 0% to 100%"));
+        }
+        Ok(Self(fraction))
+    }
+
+    /// Returns the rate as a fraction (0.01 for 1%).
+    pub fn fraction(&self) -> f64 {
+        self.0
+    }
+}
+
+impl FromStr for FailureRate {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let percent = s
+            .trim()
+            .strip_suffix('%')
+            .and_then(|percent| percent.trim().parse::<f64>().ok())
+            .ok_or_else(|| WaverError::argument_error("Failure rate must be a percentage like 1%"))?;
+        Self::new(percent / 100.0)
+    }
+}
+
+/// A validated automatic gain, given as `percentile=95`: the waveform is
+/// scaled so that percentile of its column peaks reaches full height, and
+/// the few louder columns are clipped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoGain(f32);
+
+impl AutoGain {
+    /// Creates a new validated automatic gain from the percentile of the
+    /// peaks brought to full height (100 is the loudest peak).
+    pub fn new(percentile: f32) -> Result<Self> {
+        if !(percentile > 0.0 && percentile <= 100.0) {
+            return Err(WaverError::argument_error("Auto gain percentile must be above 0 and at most 100"));
+        }
+        Ok(Self(percentile))
+    }
+
+    /// Returns the percentile of the peaks brought to full height.
+    pub fn percentile(&self) -> f32 {
+        self.0
+    }
+}
+
+impl FromStr for AutoGain {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let percentile = s
+            .trim()
+            .strip_prefix("percentile=")
+            .and_then(|percentile| percentile.trim().parse::<f32>().ok())
+            .ok_or_else(|| WaverError::argument_error("Auto gain must be given as percentile=N, like percentile=95"))?;
+        Self::new(percentile)
+    }
+}
+
+/// A validated JPEG quality from 1 (smallest) to 100 (best).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct JpegQuality(u8);
+
+impl JpegQuality {
+    /// The quality used when none is given.
+    pub const DEFAULT: u8 = 85;
+
+    /// Creates a new validated JPEG quality.
+    pub fn new(quality: u8) -> Result<Self> {
+        if !(1..=100).contains(&quality) {
+            return Err(WaverError::argument_error("JPEG quality must be 1 to 100"));
+        }
+        Ok(Self(quality))
+    }
+
+    /// Returns the quality value.
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+impl FromStr for JpegQuality {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let quality = s.trim().parse::<u8>()
+            .map_err(|_| WaverError::argument_error("JPEG quality must be 1 to 100"))?;
+        Self::new(quality)
+    }
+}
+
+/// A validated dB level drawn as silence, below full scale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DbFloor(f32);
+
+impl DbFloor {
+    /// Creates a new validated dB floor.
+    pub fn new(db: f32) -> Result<Self> {
+        // Not finite (like -inf) would make every level NaN
+        if !(db.is_finite() && db < 0.0) {
+            return Err(WaverError::argument_error("--db-floor must be a negative dB value"));
+        }
+        Ok(Self(db))
+    }
+
+    /// Returns the floor in dB.
+    pub fn value(&self) -> f32 {
+        self.0
+    }
+}
+
+impl FromStr for DbFloor {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let db = s.trim().parse::<f32>()
+            .map_err(|_| WaverError::argument_error("--db-floor must be a negative dB value"))?;
+        Self::new(db)
+    }
+}
+
+/// A validated time in the audio, in seconds from the start.
+///
+/// Parsed from `[[hh:]mm:]ss[.mmm]` or in units, so `90`, `1:30`,
+/// `0:01:30.000` and `1m30s` are all the same time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeOffset(f64);
+
+impl TimeOffset {
+    /// Creates a new validated time from seconds.
+    pub fn new(seconds: f64) -> Result<Self> {
+        if !(seconds.is_finite() && seconds >= 0.0) {
+            return Err(WaverError::argument_error("Time must not be negative"));
+        }
+        Ok(Self(seconds))
+    }
+
+    /// Returns the time in seconds.
+    pub fn seconds(&self) -> f64 {
+        self.0
+    }
+
+    /// Parses a time in units like `10m`, `1h30m` or `2.5s`, with the units
+    /// in the order h, m, s and each at most once.
+    fn parse_units(s: &str) -> Option<f64> {
+        let mut total = 0.0;
+        let mut rest = s;
+        let mut units = "hms";
+        while !rest.is_empty() {
+            let end = rest.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+            if !rest.starts_with(|c: char| c.is_ascii_digit()) {
+                return None;
+            }
+            let value = rest[..end].parse::<f64>().ok()?;
+            let unit = rest[end..].chars().next()?;
+            units = &units[units.find(unit)? + 1..];
+            total += value * match unit {
+                'h' => 3600.0,
+                'm' => 60.0,
+                _ => 1.0,
+            };
+            rest = &rest[end + 1..];
+        }
+        Some(total)
+    }
+}
+
+impl FromStr for TimeOffset {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let error = || WaverError::argument_error("Time must be [[hh:]mm:]ss[.mmm] or like 1h30m");
+        let s = s.trim();
+        if s.ends_with(['h', 'm', 's']) {
+            return Self::new(Self::parse_units(s).ok_or_else(error)?);
+        }
+
+        let parts: Vec<&str> = s.split(':').collect();
+        if parts.len() > 3 {
+            return Err(error());
+        }
+
+        // Only the seconds may have a fraction and both the seconds and
+        // minutes have to stay below 60 once there is a larger unit
+        let (whole, seconds) = parts.split_at(parts.len() - 1);
+        let seconds = seconds[0];
+        if !seconds.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(error());
+        }
+        let mut total = seconds.parse::<f64>().map_err(|_| error())?;
+        if !whole.is_empty() && total >= 60.0 {
+            return Err(error());
+        }
+        for (unit, part) in whole.iter().rev().enumerate() {
+            let value = part.parse::<u32>().map_err(|_| error())?;
+            if unit == 0 && whole.len() > 1 && value >= 60 {
+                return Err(error());
+            }
+            total += value as f64 * 60f64.powi(unit as i32 + 1);
+        }
+        Self::new(total)
+    }
+}
+
+/// A validated size of a file in bytes.
+///
+/// Parsed from a number of bytes with an optional unit of 1024 bytes, so
+/// `500M`, `500MB` and `500MiB` are all the same size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileSize(u64);
+
+impl FileSize {
+    /// Creates a new validated size from bytes.
+    pub fn new(bytes: u64) -> Result<Self> {
+        if bytes == 0 {
+            return Err(WaverError::argument_error("File size must be more than zero"));
+        }
+        Ok(Self(bytes))
+    }
+
+    /// Returns the size in bytes.
+    pub fn bytes(&self) -> u64 {
+        self.0
+    }
+}
+
+impl FromStr for FileSize {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let error = || WaverError::argument_error("File size must be bytes or like 500M or 2G");
+        let s = s.trim().to_ascii_lowercase();
+        let number = s.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        let scale: f64 = match &s[number.len()..] {
+            "" | "b" => 1.0,
+            "k" | "kb" | "kib" => 1024.0,
+            "m" | "mb" | "mib" => 1024.0 * 1024.0,
+            "g" | "gb" | "gib" => 1024.0 * 1024.0 * 1024.0,
+            "t" | "tb" | "tib" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+            _ => return Err(error()),
+        };
+        if !number.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(error());
+        }
+        let value = number.parse::<f64>().map_err(|_| error())?;
+        Self::new((value * scale).round() as u64)
+    }
+}
+
 /// A validated audio file path.
 ///
-/// Ensures the path exists and is a file.
-#[derive(Debug, Clone)]
+/// Ensures the path exists and is a file, or is `-` for stdin.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AudioPath(PathBuf);
 
 impl AudioPath {
     /// Creates a new validated audio path.
     pub fn new(path: impl AsRef<Path>) -> Result<Self> {
         let path = path.as_ref();
-        if !path.exists() {
+        if !is_stdin(path) && !path.exists() && !is_pattern(path) {
             return Err(WaverError::argument_error(
                 format!("Path does not exist: {}", path.display())
             ));
@@ -127,6 +488,17 @@ impl AudioPath {
         self.0.is_dir()
     }
 
+    /// Returns whether the audio is read from stdin.
+    pub fn is_stdin(&self) -> bool {
+        is_stdin(&self.0)
+    }
+
+    /// Returns whether this is a glob pattern of audio files rather than a
+    /// file or directory.
+    pub fn is_pattern(&self) -> bool {
+        !self.0.exists() && is_pattern(&self.0)
+    }
+
     /// Returns the path.
     pub fn path(&self) -> &Path {
         &self.0
@@ -144,7 +516,7 @@ impl FromStr for AudioPath {
 /// A validated audio file extension.
 ///
 /// Ensures the extension is valid.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FileExtension(String);
 
 impl FileExtension {
@@ -172,7 +544,7 @@ impl FromStr for FileExtension {
 }
 
 /// A collection of validated file extensions.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FileExtensions(Vec<FileExtension>);
 
 impl FileExtensions {
@@ -214,4 +586,296 @@ impl FromStr for FileExtensions {
 
         Self::new(extensions)
     }
-}
\ No newline at end of file
+}
+/// A corner of the image, used to place overlays like the duration badge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    /// Returns true for the two corners along the top edge.
+    pub fn is_top(&self) -> bool {
+        matches!(self, Corner::TopLeft | Corner::TopRight)
+    }
+
+    /// Returns true for the two corners along the left edge.
+    pub fn is_left(&self) -> bool {
+        matches!(self, Corner::TopLeft | Corner::BottomLeft)
+    }
+}
+
+impl FromStr for Corner {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "top-left" => Ok(Corner::TopLeft),
+            "top-right" => Ok(Corner::TopRight),
+            "bottom-left" => Ok(Corner::BottomLeft),
+            "bottom-right" => Ok(Corner::BottomRight),
+            _ => Err(WaverError::argument_error(
+                "Corner must be top-left, top-right, bottom-left, or bottom-right",
+            )),
+        }
+    }
+}
+
+/// Output format of reports like the one from `waver compare`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ReportFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+    /// A JSON object
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "text" => Ok(ReportFormat::Text),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(WaverError::argument_error("Report must be text or json")),
+        }
+    }
+}
+
+/// What kind of image is generated from the audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RenderMode {
+    /// The amplitude over time
+    #[default]
+    Waveform,
+    /// The frequency content over time as a heatmap
+    Spectrogram,
+}
+
+impl FromStr for RenderMode {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "waveform" => Ok(RenderMode::Waveform),
+            "spectrogram" => Ok(RenderMode::Spectrogram),
+            _ => Err(WaverError::argument_error("Mode must be waveform or spectrogram")),
+        }
+    }
+}
+
+/// How the channels of the audio are laid out in the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WaveLayout {
+    /// Left above and right below the center line (mono files centered)
+    #[default]
+    Combined,
+    /// Every channel in a lane of its own, top to bottom
+    Stacked,
+}
+
+impl FromStr for WaveLayout {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "combined" => Ok(WaveLayout::Combined),
+            "stacked" => Ok(WaveLayout::Stacked),
+            _ => Err(WaverError::argument_error("Layout must be combined or stacked")),
+        }
+    }
+}
+
+/// Archive format of the bundles of the written files (`--bundle`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BundleFormat {
+    /// A ZIP archive of the files, stored without compression
+    #[default]
+    Zip,
+}
+
+impl FromStr for BundleFormat {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "zip" => Ok(BundleFormat::Zip),
+            _ => Err(WaverError::argument_error("Bundle format must be zip")),
+        }
+    }
+}
+
+/// Which written files go into one bundle (`--bundle-by`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BundleScope {
+    /// The files of each audio file in a bundle of their own
+    #[default]
+    File,
+    /// The files of all audio files of a directory in one bundle there
+    Directory,
+}
+
+impl FromStr for BundleScope {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "file" => Ok(BundleScope::File),
+            "directory" | "dir" => Ok(BundleScope::Directory),
+            _ => Err(WaverError::argument_error("Bundles must be by file or directory")),
+        }
+    }
+}
+
+/// Where the bars of a single (mono) waveform are drawn from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MonoStyle {
+    /// Mirrored around the center line
+    #[default]
+    Mirrored,
+    /// Up from a baseline at the bottom, using the whole height
+    Top,
+    /// Down from a baseline at the top, using the whole height
+    Bottom,
+}
+
+impl FromStr for MonoStyle {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "mirrored" => Ok(MonoStyle::Mirrored),
+            "top" => Ok(MonoStyle::Top),
+            "bottom" => Ok(MonoStyle::Bottom),
+            _ => Err(WaverError::argument_error("Mono style must be mirrored, top or bottom")),
+        }
+    }
+}
+
+/// What a pixel covered by both the left and the right bar is drawn as,
+/// like where the two waveforms of the `waver compare` diff image meet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Overlap {
+    /// The left color wins
+    KeepLeft,
+    /// The right color wins
+    KeepRight,
+    /// The pixel gets the `AXIS` palette entry, the axis color or the
+    /// background when there is no axis, so only the differences show
+    #[default]
+    Mark,
+}
+
+impl FromStr for Overlap {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "keep-left" => Ok(Overlap::KeepLeft),
+            "keep-right" => Ok(Overlap::KeepRight),
+            "mark" => Ok(Overlap::Mark),
+            _ => Err(WaverError::argument_error("Overlap must be keep-left, keep-right or mark")),
+        }
+    }
+}
+
+/// File format of the waveform image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ImageFormat {
+    /// Indexed PNG with a transparent background
+    #[default]
+    Png,
+    /// JPEG with the background flattened to an opaque color
+    Jpeg,
+    /// Uncompressed 24-bit BMP with the background flattened
+    Bmp,
+    /// Raw 16-bit RGB565 pixels without any header, background flattened
+    Rgb565,
+    /// Binary PPM (P6) with the background flattened
+    Ppm,
+    /// Farbfeld, 16-bit RGBA with the transparency kept
+    Farbfeld,
+    /// QOI, lossless RGBA that encodes much faster than PNG
+    Qoi,
+    /// Lossless WebP with the transparency kept
+    Webp,
+    /// Lossy AVIF (builds with the `avif` feature only)
+    Avif,
+}
+
+impl ImageFormat {
+    /// Returns the file extension of the format, without the dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Bmp => "bmp",
+            ImageFormat::Rgb565 => "rgb565",
+            ImageFormat::Ppm => "ppm",
+            ImageFormat::Farbfeld => "ff",
+            ImageFormat::Qoi => "qoi",
+            ImageFormat::Webp => "webp",
+            ImageFormat::Avif => "avif",
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "png" => Ok(ImageFormat::Png),
+            "jpeg" | "jpg" => Ok(ImageFormat::Jpeg),
+            "bmp" => Ok(ImageFormat::Bmp),
+            "rgb565" => Ok(ImageFormat::Rgb565),
+            "ppm" => Ok(ImageFormat::Ppm),
+            "farbfeld" | "ff" => Ok(ImageFormat::Farbfeld),
+            "qoi" => Ok(ImageFormat::Qoi),
+            "webp" => Ok(ImageFormat::Webp),
+            "avif" => Ok(ImageFormat::Avif),
+            _ => Err(WaverError::argument_error(
+                "Output format must be png, jpeg, bmp, rgb565, ppm, farbfeld, qoi, webp, or avif",
+            )),
+        }
+    }
+}
+
+/// How directories are searched for audio files (`--no-follow-symlinks`,
+/// `--hidden` and `--max-depth`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Traversal {
+    /// Whether symbolic links are followed, which can reach into network
+    /// mounts and find the same file twice
+    pub follow_symlinks: bool,
+    /// Whether the files and directories whose names start with a dot are
+    /// searched too (like the `._` files macOS leaves on other disks)
+    pub hidden: bool,
+    /// How many levels deep directories are searched, 1 for only the files
+    /// in them
+    pub max_depth: Option<usize>,
+}
+
+impl Traversal {
+    pub fn new(follow_symlinks: bool, hidden: bool, max_depth: Option<u32>) -> Self {
+        Self { follow_symlinks, hidden, max_depth: max_depth.map(|depth| depth as usize) }
+    }
+
+    /// Returns true if a file or directory of this name is searched.
+    ///
+    /// The directories given on the command line are searched whatever
+    /// their names, so only the names found below them are checked.
+    pub fn includes(&self, name: &OsStr) -> bool {
+        self.hidden || !name.as_encoded_bytes().starts_with(b".")
+    }
+}
+
+impl Default for Traversal {
+    fn default() -> Self {
+        Self::new(true, false, None)
+    }
+}
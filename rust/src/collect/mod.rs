@@ -0,0 +1,278 @@
+/// Finding the audio files of the inputs (`--exclude`, glob inputs and
+/// `--files-from`).
+///
+/// The inputs of a run are files, which are used as they are, directories,
+/// which are searched for the files with one of the `--file-extensions`,
+/// and glob patterns like `music/**/*.flac`, whose matches are used like
+/// files given by name.  A pattern is only taken as one when no file of
+/// that name exists, so a quoted pattern works in every shell.
+///
+/// Patterns match one `/` separated part of the path at a time: `*`
+/// matches any characters within a part, `?` one character and `**` any
+/// number of parts.  An input pattern must match the whole path, and is
+/// searched for from the directory before its first wildcard.  An
+/// `--exclude` pattern matches the end of the path (or the whole path if it
+/// starts with `/`), like the rows of a `--color-map`, and an excluded
+/// directory is not searched at all:
+///
+/// ```text
+/// waver 'music/**/*.flac' --exclude '*.demo.flac' --exclude 'music/Outtakes'
+/// ```
+///
+/// A pipeline that already knows its files hands them over as a list with
+/// `--files-from`, one path per line or separated by NULs (which `find
+/// -print0` writes, for names with line breaks in them).  The listed files
+/// are used like files given by name, without searching anything.
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+use walkdir::WalkDir;
+
+use crate::cli::{AudioPath, Traversal};
+use crate::config::is_stdin;
+use crate::error::{Result, WaverError};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// A path pattern with `*`, `?` and `**`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob {
+    /// The `/` separated parts; a leading empty part anchors the pattern
+    /// to the start of the path
+    parts: Vec<String>,
+}
+
+impl Glob {
+    /// Parses a pattern that matches the end of a path, or the whole path
+    /// if it starts with `/`.
+    pub fn new(pattern: &str) -> Self {
+        let parts = pattern
+            .trim_end_matches('/')
+            .split('/')
+            .enumerate()
+            .filter(|(index, part)| *index == 0 || (!part.is_empty() && *part != "."))
+            .map(|(_, part)| part.to_string())
+            .collect::<Vec<_>>();
+        match parts.split_first() {
+            // A pattern like `./demos` is relative like `demos`
+            Some((first, rest)) if first == "." => Self { parts: rest.to_vec() },
+            _ => Self { parts },
+        }
+    }
+
+    /// Parses an input pattern, which must match the whole path.
+    pub fn whole(pattern: &Path) -> Self {
+        let mut parts = vec![String::new()];
+        parts.extend(path_parts(pattern));
+        Self { parts }
+    }
+
+    /// Returns whether the pattern matches the path.
+    pub fn matches(&self, path: &Path) -> bool {
+        pattern_matches(&self.parts, &path_parts(path))
+    }
+}
+
+impl FromStr for Glob {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.trim_matches('/').is_empty() {
+            return Err(WaverError::argument_error("A path pattern can not be empty"));
+        }
+        Ok(Self::new(s))
+    }
+}
+
+/// Returns whether the path is a pattern, with a `*` or a `?` in it.
+pub fn is_pattern(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?'])
+}
+
+/// Returns the names of the path, without the root and the `.` and `..`
+/// parts, as the patterns see them.
+fn path_parts(path: &Path) -> Vec<String> {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns whether the pattern parts match the end of the path parts (or
+/// all of them, for a pattern that starts with `/`).
+fn pattern_matches(pattern: &[String], parts: &[String]) -> bool {
+    match pattern.split_first() {
+        // A leading `/` leaves an empty first part
+        Some((first, rest)) if first.is_empty() => parts_match(rest, parts),
+        _ => (0..=parts.len()).any(|start| parts_match(pattern, &parts[start..])),
+    }
+}
+
+/// Returns whether the pattern parts match all of the path parts.
+fn parts_match(pattern: &[String], parts: &[String]) -> bool {
+    match pattern.split_first() {
+        None => parts.is_empty(),
+        Some((first, rest)) if first == "**" => (0..=parts.len()).any(|skip| parts_match(rest, &parts[skip..])),
+        Some((first, rest)) => parts
+            .split_first()
+            .is_some_and(|(part, parts)| wildcard_match(first.as_bytes(), part.as_bytes()) && parts_match(rest, parts)),
+    }
+}
+
+/// Returns whether a part of a pattern, with `*` and `?`, matches a part
+/// of the path.
+fn wildcard_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| wildcard_match(rest, &text[skip..])),
+        Some((b'?', rest)) => {
+            // One character, which may be several bytes of UTF-8
+            let length = text.first().map_or(0, |byte| utf8_length(*byte));
+            length > 0 && wildcard_match(rest, &text[length.min(text.len())..])
+        }
+        Some((byte, rest)) => text.first() == Some(byte) && wildcard_match(rest, &text[1..]),
+    }
+}
+
+/// Returns the number of bytes of the UTF-8 character with the lead byte.
+fn utf8_length(lead: u8) -> usize {
+    match lead.leading_ones() {
+        0 => 1,
+        ones => ones as usize,
+    }
+}
+
+/// Returns the directory an input pattern is searched from (the part
+/// before its first wildcard) and how deep its matches can be, if it has
+/// no `**`.
+pub fn search_root(pattern: &Path) -> (PathBuf, Option<usize>) {
+    let mut root = PathBuf::new();
+    let mut components = pattern.components().peekable();
+    while let Some(component) = components.next_if(|component| !is_pattern(component.as_os_str().as_ref())) {
+        root.push(component);
+    }
+    let rest = components.map(|component| component.as_os_str().to_os_string()).collect::<Vec<_>>();
+    let depth = (!rest.iter().any(|part| part == "**")).then_some(rest.len());
+    if root.as_os_str().is_empty() {
+        root.push(".");
+    }
+    (root, depth)
+}
+
+/// Returns the audio files of the inputs, in the order they were given:
+/// files (and - for stdin) as they are, the files with one of the
+/// extensions found in directories and the files matching the patterns, as
+/// far as the traversal goes, all without the excluded ones.
+pub fn collect_audio_files(
+    inputs: &[AudioPath],
+    extensions: &[String],
+    traversal: Traversal,
+    excludes: &[Glob],
+) -> Vec<PathBuf> {
+    let excluded = |path: &Path| is_excluded(path, excludes);
+    let has_extension = |path: &Path| {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| extensions.iter().any(|extension| extension == ext))
+    };
+
+    let mut audio_files = Vec::new();
+    for input in inputs {
+        let path = input.path();
+        if input.is_stdin() || path.is_file() {
+            // Directly entered file names (and - for stdin) are not filtered
+            // to the extensions
+            if input.is_stdin() || !excluded(path) {
+                audio_files.push(path.to_path_buf());
+            }
+        } else if path.is_dir() {
+            audio_files.extend(search(path, traversal, excludes).filter(|file| has_extension(file)));
+        } else if input.is_pattern() {
+            // The matches are named by the pattern, like files by their names
+            let glob = Glob::whole(path);
+            let (root, depth) = search_root(path);
+            let max_depth = match (traversal.max_depth, depth) {
+                (Some(max), Some(depth)) => Some(max.min(depth)),
+                (max, depth) => max.or(depth),
+            };
+            let traversal = Traversal { max_depth, ..traversal };
+            audio_files.extend(search(&root, traversal, excludes).filter(|file| glob.matches(file)));
+        }
+    }
+    audio_files
+}
+
+/// Returns whether the path, or a directory it is in, matches one of the
+/// `--exclude` patterns.
+pub fn is_excluded(path: &Path, excludes: &[Glob]) -> bool {
+    path.ancestors().any(|path| excludes.iter().any(|glob| glob.matches(path)))
+}
+
+/// Returns the paths of a file list: separated by NULs if there is one in
+/// it, and by line breaks otherwise, without the empty ones.
+pub fn parse_file_list(list: &[u8]) -> Vec<PathBuf> {
+    let separator = if list.contains(&0) { b'\0' } else { b'\n' };
+    list.split(|byte| *byte == separator)
+        .map(|entry| match separator {
+            // Lists written on Windows end their lines with \r\n
+            b'\n' => entry.strip_suffix(b"\r").unwrap_or(entry),
+            _ => entry,
+        })
+        .filter(|entry| !entry.is_empty())
+        .map(path_from_bytes)
+        .collect()
+}
+
+/// Returns the path of the bytes, which need not be UTF-8 on Unix.
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// Reads the file list of `--files-from` (- reads it from stdin) and
+/// returns its files without the excluded ones.
+pub fn read_file_list(list: &Path, excludes: &[Glob]) -> Result<Vec<PathBuf>> {
+    let mut bytes = Vec::new();
+    let read = if is_stdin(list) {
+        io::stdin().lock().read_to_end(&mut bytes).map(drop)
+    } else {
+        fs::read(list).map(|contents| bytes = contents)
+    };
+    read.map_err(|err| {
+        WaverError::argument_error(format!("Can not read the file list '{}': {err}", list.display()))
+    })?;
+    Ok(parse_file_list(&bytes).into_iter().filter(|file| !is_excluded(file, excludes)).collect())
+}
+
+/// Returns the files below the directory, in name order, as far as the
+/// traversal goes and without the excluded ones.
+fn search<'a>(dir: &Path, traversal: Traversal, excludes: &'a [Glob]) -> impl Iterator<Item = PathBuf> + 'a {
+    // We use WalkDir such that the complexity of loops/etc are handled for
+    // us rather than getting us stuck (in name order, so the files are
+    // found in the same order on every file system)
+    let mut walk = WalkDir::new(dir).follow_links(traversal.follow_symlinks).sort_by_file_name();
+    if let Some(depth) = traversal.max_depth {
+        walk = walk.max_depth(depth);
+    }
+    walk.into_iter()
+        // A hidden or excluded directory is not searched at all
+        .filter_entry(move |entry| {
+            entry.depth() == 0
+                || traversal.includes(entry.file_name()) && !excludes.iter().any(|glob| glob.matches(entry.path()))
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+}
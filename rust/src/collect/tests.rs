@@ -0,0 +1,97 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use std::str::FromStr;
+
+    use crate::cli::{AudioPath, Traversal};
+    use crate::collect::{collect_audio_files, is_pattern, parse_file_list, read_file_list, search_root, Glob};
+
+    // Test the patterns of --exclude, which match the end of the path
+    #[test]
+    fn test_exclude_patterns() {
+        let demo = Glob::from_str("*.demo.mp3").unwrap();
+        assert!(demo.matches(Path::new("music/Album/01.demo.mp3")));
+        assert!(!demo.matches(Path::new("music/Album/01.mp3")));
+
+        let outtakes = Glob::from_str("./Outtakes/").unwrap();
+        assert_eq!(outtakes, Glob::new("Outtakes"), "A leading ./ and a trailing / change nothing");
+        assert!(outtakes.matches(Path::new("music/Outtakes")));
+        assert!(!outtakes.matches(Path::new("music/Outtakes/01.mp3")), "The directory is pruned, not its files");
+
+        let anchored = Glob::new("/music/Live/**");
+        assert!(anchored.matches(Path::new("music/Live/1999/01.mp3")));
+        assert!(!anchored.matches(Path::new("old/music/Live/01.mp3")), "A leading / matches the whole path");
+
+        assert!(Glob::from_str("").is_err());
+        assert!(Glob::from_str("/").is_err());
+    }
+
+    // Test where the search of an input pattern starts and how deep it goes
+    #[test]
+    fn test_input_patterns() {
+        assert!(is_pattern(Path::new("music/*.flac")));
+        assert!(is_pattern(Path::new("track-0?.mp3")));
+        assert!(!is_pattern(Path::new("music/song.flac")));
+
+        assert_eq!(search_root(Path::new("music/**/*.flac")), (PathBuf::from("music"), None));
+        assert_eq!(search_root(Path::new("music/*/Live/*.flac")), (PathBuf::from("music"), Some(3)));
+        assert_eq!(search_root(Path::new("*.mp3")), (PathBuf::from("."), Some(1)));
+
+        let glob = Glob::whole(Path::new("music/**/*.flac"));
+        assert!(glob.matches(Path::new("music/a.flac")), "** matches no folder too");
+        assert!(glob.matches(Path::new("./music/Jazz/Live/a.flac")));
+        assert!(!glob.matches(Path::new("other/music/a.flac")), "An input pattern matches the whole path");
+        assert!(!glob.matches(Path::new("music/a.mp3")));
+    }
+
+    // Test the files found for files, directories and patterns
+    #[test]
+    fn test_collect_audio_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for file in ["a.mp3", "b.demo.mp3", "c.flac", "Live/d.mp3", "Live/e.flac", "Outtakes/f.mp3", ".hidden/g.mp3"] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, b"").unwrap();
+        }
+        let names = |files: Vec<PathBuf>| {
+            files.iter().map(|file| file.strip_prefix(root).unwrap().display().to_string()).collect::<Vec<_>>()
+        };
+        let input = |path: &str| AudioPath::new(root.join(path)).unwrap();
+        let mp3 = ["mp3".to_string()];
+
+        let files = collect_audio_files(&[input("")], &mp3, Traversal::default(), &[]);
+        assert_eq!(names(files), ["Live/d.mp3", "Outtakes/f.mp3", "a.mp3", "b.demo.mp3"]);
+
+        let excludes = [Glob::new("*.demo.mp3"), Glob::new("Outtakes")];
+        let files = collect_audio_files(&[input(""), input("b.demo.mp3")], &mp3, Traversal::default(), &excludes);
+        assert_eq!(names(files), ["Live/d.mp3", "a.mp3"], "Files given by name are excluded too");
+
+        let pattern = input("**/*.flac");
+        assert!(pattern.is_pattern());
+        let files = collect_audio_files(&[pattern], &mp3, Traversal::default(), &[]);
+        assert_eq!(names(files), ["Live/e.flac", "c.flac"], "The matches of a pattern are not filtered to the extensions");
+
+        let files = collect_audio_files(&[input("*/*.mp3")], &mp3, Traversal::default(), &excludes);
+        assert_eq!(names(files), ["Live/d.mp3"], "Only as deep as the pattern, and not into hidden directories");
+    }
+
+    // Test the file lists of --files-from, by line or NUL-separated
+    #[test]
+    fn test_file_lists() {
+        let lines = parse_file_list(b"music/a.mp3\r\n\nmusic/b c.mp3\n");
+        assert_eq!(lines, [PathBuf::from("music/a.mp3"), PathBuf::from("music/b c.mp3")]);
+
+        let nuls = parse_file_list(b"music/line\nbreak.mp3\0music/d.mp3\0");
+        assert_eq!(nuls, [PathBuf::from("music/line\nbreak.mp3"), PathBuf::from("music/d.mp3")], "A NUL separates like find -print0");
+        assert!(parse_file_list(b"\n\n").is_empty());
+
+        let mut list = tempfile::NamedTempFile::new().unwrap();
+        list.write_all(b"a.mp3\nb.demo.mp3\nOuttakes/c.mp3\nmissing.mp3\n").unwrap();
+        let files = read_file_list(list.path(), &[Glob::new("*.demo.mp3"), Glob::new("Outtakes")]).unwrap();
+        assert_eq!(files, [PathBuf::from("a.mp3"), PathBuf::from("missing.mp3")], "Listed files are not checked, only excluded (also by their directories)");
+        assert!(read_file_list(Path::new("no/such/list.txt"), &[]).is_err());
+    }
+}
@@ -0,0 +1,134 @@
+/// Colors per album or folder from a CSV file (`--color-map`).
+///
+/// Each row maps a path pattern to the colors of the files it matches:
+///
+/// ```text
+/// pattern,left,right,background
+/// Jazz/**,steelblue,lightsteelblue
+/// "Crosby, Stills & Nash/*",#c04000
+/// *.ogg,,,000000
+/// ```
+///
+/// A pattern is matched against the end of the path, one `/` separated
+/// part at a time: `*` matches any characters within a part, `?` one
+/// character and `**` any number of parts.  A pattern that starts with `/`
+/// must match the whole path, as it was given or found.  The first row that
+/// matches a file wins, and the colors it leaves empty (or out) are those of
+/// the run.
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::collect::Glob;
+use crate::color::Rgba;
+use crate::error::{Result, WaverError};
+
+/// The colors a row of the color map sets; `None` keeps the run's color.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MappedColors {
+    pub left: Option<Rgba>,
+    pub right: Option<Rgba>,
+    pub background: Option<Rgba>,
+}
+
+/// The rows of a color map, in the order of the file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColorMap {
+    rows: Vec<(Glob, MappedColors)>,
+}
+
+impl ColorMap {
+    /// Loads a color map from a CSV file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(|err| {
+            WaverError::argument_error(format!("Can not read the color map '{}': {err}", path.display()))
+        })?;
+        text.parse().map_err(|err| match err {
+            WaverError::ArgumentError(message) => {
+                WaverError::argument_error(format!("The color map '{}': {message}", path.display()))
+            }
+            other => other,
+        })
+    }
+
+    /// Returns the colors of the first row whose pattern matches the path,
+    /// if any does.
+    pub fn lookup(&self, path: &Path) -> Option<&MappedColors> {
+        self.rows.iter().find(|(pattern, _)| pattern.matches(path)).map(|(_, colors)| colors)
+    }
+}
+
+impl FromStr for ColorMap {
+    type Err = WaverError;
+
+    /// Parses the CSV rows; blank lines, `#` comments and a header row that
+    /// starts with `pattern` are skipped.
+    fn from_str(text: &str) -> Result<Self> {
+        let mut rows = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields = split_fields(line).map_err(|message| line_error(number, &message))?;
+            if rows.is_empty() && fields[0].eq_ignore_ascii_case("pattern") {
+                continue;
+            }
+            if fields.len() < 2 || fields.len() > 4 || fields[0].is_empty() {
+                return Err(line_error(number, "a row is a pattern and one to three colors (left, right, background)"));
+            }
+            let color = |index: usize| -> Result<Option<Rgba>> {
+                match fields.get(index).map(String::as_str) {
+                    None | Some("") => Ok(None),
+                    Some(color) => Rgba::from_str(color).map(Some).map_err(|err| match err {
+                        WaverError::ArgumentError(message) => line_error(number, &message),
+                        other => other,
+                    }),
+                }
+            };
+            let colors = MappedColors { left: color(1)?, right: color(2)?, background: color(3)? };
+            let pattern = Glob::new(&fields[0]);
+            rows.push((pattern, colors));
+        }
+        Ok(Self { rows })
+    }
+}
+
+/// Returns the error of a row of the color map.
+fn line_error(number: usize, message: &str) -> WaverError {
+    WaverError::argument_error(format!("line {}: {message}", number + 1))
+}
+
+/// Splits a CSV line into its trimmed fields; a field in double quotes may
+/// hold commas, and `""` in it is a quote.
+fn split_fields(line: &str) -> std::result::Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+        let mut field = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') if chars.next_if_eq(&'"').is_some() => field.push('"'),
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err("a quoted field is not closed".to_string()),
+                }
+            }
+            while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+            if !matches!(chars.peek(), None | Some(',')) {
+                return Err("text after a quoted field".to_string());
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                field.push(c);
+            }
+            field.truncate(field.trim_end().len());
+        }
+        fields.push(field);
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
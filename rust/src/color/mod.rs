@@ -1,13 +1,23 @@
 /// Color handling functionality for waveform visualization.
+use std::fmt;
 use std::str::FromStr;
 
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
 use crate::error::{Result, WaverError};
 
+mod map;
+mod names;
+
+pub use map::{ColorMap, MappedColors};
+pub use names::named_color;
+
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests;
 
 /// Represents an RGBA color.
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Rgba {
     /// Red component (0-255)
     pub red: u8,
@@ -22,7 +32,7 @@ pub struct Rgba {
 impl Rgba {
     /// Creates a new RGBA color with the given components.
     #[allow(dead_code)]
-    pub fn new(red: u8, green: u8, blue: u8, alpha: u8) -> Self {
+    pub const fn new(red: u8, green: u8, blue: u8, alpha: u8) -> Self {
         Self {
             red,
             green,
@@ -33,9 +43,174 @@ impl Rgba {
 
     /// Creates a new opaque RGB color with the given components and alpha=255.
     #[allow(dead_code)]
-    pub fn rgb(red: u8, green: u8, blue: u8) -> Self {
+    pub const fn rgb(red: u8, green: u8, blue: u8) -> Self {
         Self::new(red, green, blue, 255)
     }
+
+    /// Returns the color with the alpha replaced.
+    pub fn with_alpha(&self, alpha: u8) -> Self {
+        Self { alpha, ..*self }
+    }
+
+    /// Returns the color `t` of the way from this color to `other`, alpha
+    /// included (0.0 is this color and 1.0 is `other`).
+    ///
+    /// This is the mix the palette ramps of the image are made of.
+    pub fn mix(&self, other: &Rgba, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t + 0.5) as u8;
+        Self {
+            red: mix(self.red, other.red),
+            green: mix(self.green, other.green),
+            blue: mix(self.blue, other.blue),
+            alpha: mix(self.alpha, other.alpha),
+        }
+    }
+
+    /// Returns the color moved `amount` of the way towards white (1.0 is
+    /// white), keeping its alpha.
+    pub fn lighten(&self, amount: f32) -> Self {
+        self.mix(&Rgba::rgb(255, 255, 255), amount).with_alpha(self.alpha)
+    }
+
+    /// Returns the color moved `amount` of the way towards black (1.0 is
+    /// black), keeping its alpha.
+    pub fn darken(&self, amount: f32) -> Self {
+        self.mix(&Rgba::rgb(0, 0, 0), amount).with_alpha(self.alpha)
+    }
+
+    /// Returns the color drawn over `background` (the "over" operator of
+    /// alpha compositing).
+    ///
+    /// An opaque color stays as it is and a transparent one gives the
+    /// background.  The result is only transparent where both are.
+    pub fn blend_over(&self, background: &Rgba) -> Self {
+        let alpha = self.alpha as f32 / 255.0;
+        let behind = background.alpha as f32 / 255.0 * (1.0 - alpha);
+        let out = alpha + behind;
+        if out <= 0.0 {
+            return Rgba::new(0, 0, 0, 0);
+        }
+        let blend = |front: u8, back: u8| ((front as f32 * alpha + back as f32 * behind) / out + 0.5) as u8;
+        Self {
+            red: blend(self.red, background.red),
+            green: blend(self.green, background.green),
+            blue: blend(self.blue, background.blue),
+            alpha: (out * 255.0 + 0.5) as u8,
+        }
+    }
+
+    /// Returns the color as 8 lowercase hex digits (`RRGGBBAA`), which
+    /// parses back to the same color.
+    pub fn to_hex(&self) -> String {
+        self.to_string()
+    }
+
+    /// Creates an opaque color from its hue (in degrees, any value is
+    /// wrapped), saturation and lightness (0.0 to 1.0).
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32) -> Self {
+        let hue = hue.rem_euclid(360.0) / 60.0;
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        let second = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let (red, green, blue) = match hue as u32 {
+            0 => (chroma, second, 0.0),
+            1 => (second, chroma, 0.0),
+            2 => (0.0, chroma, second),
+            3 => (0.0, second, chroma),
+            4 => (second, 0.0, chroma),
+            _ => (chroma, 0.0, second),
+        };
+        let lightest = lightness - chroma / 2.0;
+        let channel = |value: f32| ((value + lightest) * 255.0 + 0.5).clamp(0.0, 255.0) as u8;
+        Self::rgb(channel(red), channel(green), channel(blue))
+    }
+
+    /// Returns the hue (in degrees, 0.0 to 360.0), saturation and lightness
+    /// (0.0 to 1.0) of the color, ignoring its alpha; the reverse of
+    /// `from_hsl`.
+    pub fn to_hsl(&self) -> (f32, f32, f32) {
+        let [red, green, blue] = [self.red, self.green, self.blue].map(|channel| channel as f32 / 255.0);
+        let largest = red.max(green).max(blue);
+        let smallest = red.min(green).min(blue);
+        let chroma = largest - smallest;
+        let lightness = (largest + smallest) / 2.0;
+        if chroma == 0.0 {
+            return (0.0, 0.0, lightness);
+        }
+        let hue = if largest == red {
+            (green - blue) / chroma
+        } else if largest == green {
+            (blue - red) / chroma + 2.0
+        } else {
+            (red - green) / chroma + 4.0
+        };
+        let saturation = chroma / (1.0 - (2.0 * lightness - 1.0).abs());
+        ((hue * 60.0).rem_euclid(360.0), saturation.min(1.0), lightness)
+    }
+}
+
+/// How many degrees the hue of the right color is below the left color in
+/// the seeded colors, as between the default `00ff99` and `99ff00`.
+pub const SEEDED_HUE_STEP: f32 = 72.0;
+
+/// Returns the hue (in degrees, from 0.0 up to 360.0) of a name for a seed
+/// (`--color-seed`).
+///
+/// This is a FNV-1a hash of the seed and the name rather than the hasher of
+/// the standard library, which may change between Rust versions, so the
+/// same file keeps its color across runs and builds.
+pub fn seeded_hue(seed: u64, name: &str) -> f32 {
+    let hash = seed
+        .to_le_bytes()
+        .iter()
+        .chain(name.as_bytes())
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+    (hash % 3600) as f32 / 10.0
+}
+
+/// Returns the left and right colors of a name for a seed: full colors of
+/// the seeded hue, the right one `SEEDED_HUE_STEP` degrees below it like
+/// the default colors.
+pub fn seeded_colors(seed: u64, name: &str) -> (Rgba, Rgba) {
+    let hue = seeded_hue(seed, name);
+    (Rgba::from_hsl(hue, 1.0, 0.5), Rgba::from_hsl(hue - SEEDED_HUE_STEP, 1.0, 0.5))
+}
+
+/// Hue (in degrees) of the first of the default stem colors, the hue of the
+/// default left color `00ff99`.
+pub const FIRST_STEM_HUE: f32 = 156.0;
+
+/// Returns the default colors of `count` stems: full colors with their hues
+/// spread evenly around the color wheel, starting at `FIRST_STEM_HUE`.
+pub fn stem_colors(count: usize) -> Vec<Rgba> {
+    let step = 360.0 / count.max(1) as f32;
+    (0..count).map(|stem| Rgba::from_hsl(FIRST_STEM_HUE + stem as f32 * step, 1.0, 0.5)).collect()
+}
+
+impl fmt::Display for Rgba {
+    /// Writes the canonical `RRGGBBAA` form, with the alpha even for opaque
+    /// colors, so every color has exactly one spelling.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02x}{:02x}{:02x}{:02x}", self.red, self.green, self.blue, self.alpha)
+    }
+}
+
+/// Colors are serialized in the canonical hex form, as they are given on
+/// the command line.
+impl Serialize for Rgba {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Any of the forms of the command line is accepted.
+impl<'de> Deserialize<'de> for Rgba {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let color = String::deserialize(deserializer)?;
+        color.parse().map_err(de::Error::custom)
+    }
 }
 
 impl FromStr for Rgba {
@@ -45,10 +220,23 @@ impl FromStr for Rgba {
     /// - RGB (3-digit hex): e.g. "F00" for bright red
     /// - RRGGBB (6-digit hex): e.g. "FF0000" for bright red
     /// - RRGGBBAA (8-digit hex): e.g. "FF0000FF" for opaque bright red
+    /// - Any of those after a `#`, as in CSS: e.g. "#FF0000"
+    /// - A CSS color name: e.g. "red", "slategray" or "transparent"
     fn from_str(color: &str) -> Result<Self> {
-        let hex = color.trim();
-        let value = u32::from_str_radix(hex, 16)
-            .map_err(|e| WaverError::argument_error(format!("Invalid color format: {}", e)))?;
+        let color = color.trim();
+        let hex = color.strip_prefix('#').unwrap_or(color);
+        let value = match u32::from_str_radix(hex, 16) {
+            Ok(value) => value,
+            // No color name is made of hex digits only, so a name is only
+            // looked up when the hex digits do not parse
+            Err(e) => {
+                return named_color(color).filter(|_| hex.len() == color.len()).ok_or_else(|| {
+                    WaverError::argument_error(format!(
+                        "Invalid color format: {e} - use RGB, RRGGBB or RRGGBBAA hex (optionally after #) or a CSS color name"
+                    ))
+                })
+            }
+        };
 
         match hex.len() {
             3 => Ok(Rgba {
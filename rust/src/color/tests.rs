@@ -227,4 +227,240 @@ mod tests {
         assert_eq!(color.blue, 255);
         assert_eq!(color.alpha, 255);
     }
-}
\ No newline at end of file
+
+    // Test replacing the alpha
+    #[test]
+    fn test_with_alpha() {
+        let color = Rgba::rgb(10, 20, 30);
+        assert_eq!(color.with_alpha(128), Rgba::new(10, 20, 30, 128));
+        assert_eq!(color.with_alpha(255), color);
+    }
+
+    // Test mixing two colors, alpha included
+    #[test]
+    fn test_mix() {
+        let black = Rgba::new(0, 0, 0, 0);
+        let white = Rgba::rgb(255, 255, 255);
+        assert_eq!(black.mix(&white, 0.0), black);
+        assert_eq!(black.mix(&white, 1.0), white);
+        assert_eq!(black.mix(&white, 0.5), Rgba::new(128, 128, 128, 128), "Halves round up");
+        assert_eq!(white.mix(&black, 0.25), Rgba::new(191, 191, 191, 191));
+
+        // Mixing towards a color moves each channel on its own
+        let from = Rgba::new(100, 200, 0, 255);
+        let to = Rgba::new(200, 100, 50, 55);
+        assert_eq!(from.mix(&to, 0.5), Rgba::new(150, 150, 25, 155));
+
+        // Out of range amounts are clamped
+        assert_eq!(black.mix(&white, 2.0), white);
+        assert_eq!(black.mix(&white, -1.0), black);
+    }
+
+    // Test lightening and darkening, which keep the alpha
+    #[test]
+    fn test_lighten_darken() {
+        let color = Rgba::new(100, 0, 200, 64);
+        assert_eq!(color.lighten(0.0), color);
+        assert_eq!(color.lighten(1.0), Rgba::new(255, 255, 255, 64));
+        assert_eq!(color.lighten(0.5), Rgba::new(178, 128, 228, 64));
+        assert_eq!(color.darken(0.0), color);
+        assert_eq!(color.darken(1.0), Rgba::new(0, 0, 0, 64));
+        assert_eq!(color.darken(0.5), Rgba::new(50, 0, 100, 64));
+    }
+
+    // Test alpha compositing of a color over a background
+    #[test]
+    fn test_blend_over() {
+        let red = Rgba::rgb(255, 0, 0);
+        let blue = Rgba::rgb(0, 0, 255);
+        let clear = Rgba::new(0, 0, 0, 0);
+
+        // Opaque colors cover the background, transparent ones show it
+        assert_eq!(red.blend_over(&blue), red);
+        assert_eq!(clear.blend_over(&blue), blue);
+        assert_eq!(red.with_alpha(0).blend_over(&blue), blue);
+
+        // Half transparent red over opaque blue is an opaque purple
+        assert_eq!(red.with_alpha(128).blend_over(&blue), Rgba::rgb(128, 0, 127));
+
+        // Over a transparent background the color keeps its own alpha
+        let half = Rgba::new(40, 80, 120, 128);
+        assert_eq!(half.blend_over(&clear), half);
+        assert_eq!(clear.blend_over(&clear), clear);
+
+        // Two half transparent colors give three quarters coverage
+        let over = red.with_alpha(128).blend_over(&blue.with_alpha(128));
+        assert_eq!(over.alpha, 192);
+        assert_eq!((over.red, over.green, over.blue), (170, 0, 85), "The front color weighs twice");
+    }
+
+    // Test the hex form parses back to the same color
+    #[test]
+    fn test_to_hex() {
+        assert_eq!(Rgba::rgb(255, 0, 0).to_hex(), "ff0000ff");
+        assert_eq!(Rgba::new(0x01, 0xab, 0x10, 0x00).to_hex(), "01ab1000");
+        for color in [Rgba::rgb(0, 0, 0), Rgba::new(0x12, 0x34, 0x56, 0x78), Rgba::from_str("abc").unwrap()] {
+            assert_eq!(Rgba::from_str(&color.to_hex()).unwrap(), color);
+        }
+    }
+
+    // Test the colors display and serialize in the canonical form
+    #[test]
+    fn test_display_and_serde() {
+        let color = Rgba::from_str("0AF").unwrap();
+        assert_eq!(color.to_string(), "00aaffff");
+        assert_eq!(format!("{}", Rgba::new(1, 2, 3, 4)), "01020304");
+
+        let json = serde_json::to_string(&color).unwrap();
+        assert_eq!(json, "\"00aaffff\"");
+        assert_eq!(serde_json::from_str::<Rgba>(&json).unwrap(), color);
+        assert_eq!(serde_json::from_str::<Rgba>("\"F00\"").unwrap(), Rgba::rgb(255, 0, 0));
+        assert_eq!(serde_json::from_str::<Rgba>("\"red\"").unwrap(), Rgba::rgb(255, 0, 0));
+        assert!(serde_json::from_str::<Rgba>("\"reddish\"").is_err());
+    }
+
+    // Test colors are plain values that can be keys
+    #[test]
+    fn test_copy_and_hash() {
+        use std::collections::HashSet;
+
+        let color = Rgba::rgb(1, 2, 3);
+        let copy = color;
+        assert_eq!(copy, color, "Still usable after the copy");
+
+        let colors: HashSet<Rgba> = ["f00", "ff0000", "ff0000ff", "ff000080"]
+            .iter()
+            .map(|hex| Rgba::from_str(hex).unwrap())
+            .collect();
+        assert_eq!(colors.len(), 2, "The forms of one color are the same key");
+    }
+
+    // Test the colors made from a hue
+    #[test]
+    fn test_from_hsl() {
+        assert_eq!(Rgba::from_hsl(0.0, 1.0, 0.5), Rgba::rgb(255, 0, 0));
+        assert_eq!(Rgba::from_hsl(240.0, 1.0, 0.5), Rgba::rgb(0, 0, 255));
+        assert_eq!(Rgba::from_hsl(156.0, 1.0, 0.5), Rgba::from_str("00ff99").unwrap(), "The default left color");
+        assert_eq!(Rgba::from_hsl(84.0, 1.0, 0.5), Rgba::from_str("99ff00").unwrap(), "The default right color");
+        assert_eq!(Rgba::from_hsl(-120.0, 1.0, 0.5), Rgba::from_hsl(240.0, 1.0, 0.5), "The hue wraps");
+        assert_eq!(Rgba::from_hsl(60.0, 0.0, 0.5), Rgba::rgb(128, 128, 128));
+        assert_eq!(Rgba::from_hsl(300.0, 1.0, 1.0), Rgba::rgb(255, 255, 255));
+    }
+
+    #[test]
+    fn test_to_hsl() {
+        assert_eq!(Rgba::rgb(255, 0, 0).to_hsl(), (0.0, 1.0, 0.5));
+        assert_eq!(Rgba::rgb(0, 0, 255).to_hsl(), (240.0, 1.0, 0.5));
+        assert_eq!(Rgba::rgb(128, 128, 128).to_hsl().1, 0.0, "Greys have no saturation");
+        // Back and forth gives the same color
+        for color in ["00ff99", "99ff00", "336699", "c0ffee", "800000"] {
+            let color = Rgba::from_str(color).unwrap();
+            let (hue, saturation, lightness) = color.to_hsl();
+            assert_eq!(Rgba::from_hsl(hue, saturation, lightness), color);
+        }
+    }
+
+    // Test the seeded colors are stable and differ between names and seeds
+    #[test]
+    fn test_seeded_colors() {
+        use crate::color::{seeded_colors, seeded_hue, SEEDED_HUE_STEP};
+
+        // Fixed values, the hue of a file must not change between builds
+        assert_eq!(seeded_hue(0, ""), 200.5);
+        let hue = seeded_hue(7, "song.mp3");
+        assert_eq!(hue, 320.3);
+
+        let hues: std::collections::HashSet<u32> =
+            (0..50).map(|track| (seeded_hue(7, &format!("track{track}.mp3")) * 10.0) as u32).collect();
+        assert!(hues.len() > 45, "Names get varied hues");
+        assert_ne!(seeded_hue(7, "song.mp3"), seeded_hue(8, "song.mp3"), "The seed changes the hues");
+
+        let (left, right) = seeded_colors(7, "song.mp3");
+        assert_eq!(left, Rgba::from_hsl(hue, 1.0, 0.5));
+        assert_eq!(right, Rgba::from_hsl(hue - SEEDED_HUE_STEP, 1.0, 0.5));
+    }
+
+    // Test the default stem colors are spread around the color wheel
+    #[test]
+    fn test_stem_colors() {
+        use crate::color::stem_colors;
+
+        let colors = stem_colors(3);
+        assert_eq!(colors[0], Rgba::from_str("00ff99").unwrap(), "Starts at the default left color");
+        assert_eq!(colors[1], Rgba::from_hsl(276.0, 1.0, 0.5));
+        assert_eq!(colors[2], Rgba::from_hsl(36.0, 1.0, 0.5));
+        assert_eq!(stem_colors(8).len(), 8);
+        assert!(stem_colors(0).is_empty());
+    }
+
+    // Test the CSS forms: hex after a # and color names
+    #[test]
+    fn test_css_colors() {
+        assert_eq!(Rgba::from_str("#00ff99").unwrap(), Rgba::rgb(0, 255, 153));
+        assert_eq!(Rgba::from_str(" #F00 ").unwrap(), Rgba::rgb(255, 0, 0));
+        assert_eq!(Rgba::from_str("#00ff9980").unwrap(), Rgba::new(0, 255, 153, 128));
+
+        assert_eq!(Rgba::from_str("red").unwrap(), Rgba::rgb(255, 0, 0));
+        assert_eq!(Rgba::from_str("SlateGray").unwrap(), Rgba::rgb(112, 128, 144), "Names ignore case");
+        assert_eq!(Rgba::from_str("slategrey").unwrap(), Rgba::rgb(112, 128, 144));
+        assert_eq!(Rgba::from_str("transparent").unwrap(), Rgba::new(0, 0, 0, 0));
+        assert_eq!(Rgba::from_str("rebeccapurple").unwrap(), Rgba::rgb(102, 51, 153));
+        assert_eq!(Rgba::from_str("aliceblue").unwrap(), Rgba::rgb(240, 248, 255), "The first name");
+        assert_eq!(Rgba::from_str("yellowgreen").unwrap(), Rgba::rgb(154, 205, 50), "The last name");
+
+        // Names round-trip through the canonical hex form
+        let color = Rgba::from_str("tomato").unwrap();
+        assert_eq!(Rgba::from_str(&color.to_hex()).unwrap(), color);
+
+        assert!(Rgba::from_str("#red").is_err(), "Names take no #");
+        assert!(Rgba::from_str("#").is_err());
+        assert!(Rgba::from_str("#12").is_err());
+        let err = Rgba::from_str("bleu").unwrap_err();
+        assert!(err.to_string().contains("CSS color name"), "The error should mention the names");
+    }
+
+    // Test the rows of a color map and the paths they match
+    #[test]
+    fn test_color_map() {
+        use crate::color::{ColorMap, MappedColors};
+        use std::path::Path;
+
+        let map = ColorMap::from_str(
+            "pattern,left,right,background\n\
+             # The live albums stand out\n\
+             **/Live/**,red,#ffa500\n\
+             \n\
+             \"Crosby, Stills & Nash/*\", 336699\n\
+             /music/Jazz/*.flac,,,000000\n\
+             track-??.ogg,blue,blue,\n",
+        )
+        .unwrap();
+        let lookup = |path: &str| map.lookup(Path::new(path)).copied();
+        let live = MappedColors {
+            left: Some(Rgba::rgb(255, 0, 0)),
+            right: Some(Rgba::rgb(255, 165, 0)),
+            background: None,
+        };
+        assert_eq!(lookup("/music/Rock/Live/1999/01.mp3"), Some(live), "** matches any number of folders");
+        assert_eq!(lookup("Live/01.mp3"), Some(live));
+        assert_eq!(lookup("Alive/01.mp3"), None, "Whole folder names only");
+        let left = Some(Rgba::rgb(0x33, 0x66, 0x99));
+        assert_eq!(lookup("/music/Crosby, Stills & Nash/01.mp3").map(|colors| colors.left), Some(left));
+        assert_eq!(lookup("/music/Crosby, Stills & Nash/Deja Vu/01.mp3"), None, "* stays in its folder");
+        let black = Some(Rgba::rgb(0, 0, 0));
+        assert_eq!(lookup("/music/Jazz/a.flac").map(|colors| colors.background), Some(black));
+        assert_eq!(lookup("/other/music/Jazz/a.flac"), None, "A leading / matches the whole path");
+        assert!(lookup("a/track-07.ogg").is_some());
+        assert!(lookup("a/track-7.ogg").is_none(), "? is one character");
+        assert!(lookup("a/track-é7.ogg").is_some(), "? is one character, not one byte");
+        // The first row that matches wins
+        assert_eq!(lookup("Live/Crosby, Stills & Nash/01.mp3"), Some(live));
+
+        for broken in ["song.mp3", "song.mp3,red,red,red,red", ",red", "song.mp3,reddish", "\"song.mp3,red"] {
+            assert!(ColorMap::from_str(broken).is_err(), "{broken}");
+        }
+        let err = ColorMap::from_str("a,red\nb,bleu").unwrap_err();
+        assert!(err.to_string().contains("line 2"), "The error names the line: {err}");
+        assert!(ColorMap::load(Path::new("missing.csv")).is_err());
+    }
+}
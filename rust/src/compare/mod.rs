@@ -0,0 +1,234 @@
+/// Comparison of the decoded audio of two files.
+///
+/// This is used to verify transcodes: the reference (e.g. a FLAC original)
+/// and the candidate (e.g. an MP3 made from it) are decoded to mono, aligned
+/// on their loudest peak (lossy encoders add a delay at the start) and then
+/// compared sample by sample.
+use std::io::Write;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::audio::decode_mono;
+use crate::config::WaveformConfig;
+use crate::error::{Result, WaverError};
+use crate::image::{ImageOptions, WaveImage};
+use crate::peaks::{ColumnPeak, Peaks};
+use crate::render::render_overlay;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// How many frames around the peak are used to refine the alignment.
+const ALIGN_WINDOW: usize = 4096;
+
+/// How far (in frames) the refinement searches around the peak alignment.
+const ALIGN_SEARCH: i64 = 64;
+
+/// The result of comparing two audio files.
+#[derive(Debug, Clone, Serialize)]
+pub struct Comparison {
+    /// Path of the reference audio file
+    pub reference: String,
+    /// Path of the compared audio file
+    pub candidate: String,
+    /// Sample rate of both files in Hz
+    pub sample_rate: u32,
+    /// Frames the candidate is delayed against the reference (negative if ahead)
+    pub offset_frames: i64,
+    /// Number of aligned frames that were compared
+    pub compared_frames: u64,
+    /// Duration of the candidate minus the duration of the reference in seconds
+    pub duration_delta_seconds: f64,
+    /// Correlation of the aligned samples (1.0 for identical audio)
+    pub correlation: f64,
+    /// Largest absolute difference between two aligned samples
+    pub max_sample_difference: f32,
+}
+
+impl Comparison {
+    /// Writes the comparison as pretty printed JSON.
+    pub fn write_json(&self, mut writer: impl Write) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    /// Writes the comparison as human readable lines.
+    pub fn write_text(&self, mut writer: impl Write) -> Result<()> {
+        writeln!(writer, "Reference:             {}", self.reference)?;
+        writeln!(writer, "Candidate:             {}", self.candidate)?;
+        writeln!(writer, "Sample rate:           {} Hz", self.sample_rate)?;
+        writeln!(writer, "Offset:                {} frames", self.offset_frames)?;
+        writeln!(writer, "Compared:              {} frames", self.compared_frames)?;
+        writeln!(writer, "Duration delta:        {:+.3} s", self.duration_delta_seconds)?;
+        writeln!(writer, "Correlation:           {:.6}", self.correlation)?;
+        writeln!(writer, "Max sample difference: {:.6}", self.max_sample_difference)?;
+        Ok(())
+    }
+}
+
+/// Decodes and compares two audio files.
+///
+/// If `diff_image` is given an overlay of the two aligned waveforms is saved
+/// there, using the size and colors of `config`.
+pub fn compare_files(
+    reference: &Path,
+    candidate: &Path,
+    diff_image: Option<&Path>,
+    config: &WaveformConfig,
+) -> Result<Comparison> {
+    let (reference_samples, reference_info) = decode_mono(reference)?;
+    let (candidate_samples, candidate_info) = decode_mono(candidate)?;
+
+    if reference_info.sample_rate != candidate_info.sample_rate {
+        return Err(WaverError::generation_error(format!(
+            "Cannot compare audio with different sample rates ({} Hz vs {} Hz)",
+            reference_info.sample_rate, candidate_info.sample_rate
+        )));
+    }
+
+    let max_offset = reference_info.sample_rate as i64;
+    let offset = align(&reference_samples, &candidate_samples, max_offset);
+    let (first, second) = overlap(&reference_samples, &candidate_samples, offset);
+    let (correlation, max_sample_difference) = similarity(first, second);
+
+    if let Some(path) = diff_image {
+        let options = ImageOptions { overlap: config.overlap, ..ImageOptions::default() };
+        let mut image = WaveImage::with_options(config.width, config.height, &options);
+        render_overlay(&overlay_peaks(first, second, config.width()), &mut image);
+        image.save_png(
+            &config.background_color,
+            &config.left_color,
+            &config.right_color,
+            path,
+        )?;
+    }
+
+    Ok(Comparison {
+        reference: reference.display().to_string(),
+        candidate: candidate.display().to_string(),
+        sample_rate: reference_info.sample_rate,
+        offset_frames: offset,
+        compared_frames: first.len() as u64,
+        duration_delta_seconds: candidate_info.duration() - reference_info.duration(),
+        correlation,
+        max_sample_difference,
+    })
+}
+
+/// Finds how many frames the candidate is delayed against the reference.
+///
+/// The loudest samples of both are lined up first, then the offset is
+/// refined by the best correlation of a window around the peak.  Peak
+/// offsets beyond `max_offset` frames are not trusted and searched around
+/// zero instead.
+pub fn align(reference: &[f32], candidate: &[f32], max_offset: i64) -> i64 {
+    let (Some(reference_peak), Some(candidate_peak)) = (peak_index(reference), peak_index(candidate)) else {
+        return 0;
+    };
+
+    let mut coarse = candidate_peak as i64 - reference_peak as i64;
+    if coarse.abs() > max_offset {
+        coarse = 0;
+    }
+
+    let start = reference_peak.saturating_sub(ALIGN_WINDOW / 2);
+    let end = (start + ALIGN_WINDOW).min(reference.len());
+    let window = &reference[start..end];
+
+    let mut best = (coarse, f64::MIN);
+    for offset in coarse - ALIGN_SEARCH..=coarse + ALIGN_SEARCH {
+        let score: f64 = window
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &sample)| {
+                let j = start as i64 + i as i64 + offset;
+                usize::try_from(j)
+                    .ok()
+                    .and_then(|j| candidate.get(j))
+                    .map(|&other| sample as f64 * other as f64)
+            })
+            .sum();
+        if score > best.1 {
+            best = (offset, score);
+        }
+    }
+    best.0
+}
+
+/// Returns the index of the sample with the largest absolute value.
+fn peak_index(samples: &[f32]) -> Option<usize> {
+    samples
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+        .map(|(index, _)| index)
+}
+
+/// Returns the overlapping parts of the two sample slices when the
+/// candidate is delayed by `offset` frames.
+pub fn overlap<'a>(reference: &'a [f32], candidate: &'a [f32], offset: i64) -> (&'a [f32], &'a [f32]) {
+    let skip = offset.unsigned_abs() as usize;
+    let (reference, candidate) = if offset >= 0 {
+        (reference, candidate.get(skip..).unwrap_or_default())
+    } else {
+        (reference.get(skip..).unwrap_or_default(), candidate)
+    };
+    let len = reference.len().min(candidate.len());
+    (&reference[..len], &candidate[..len])
+}
+
+/// Returns the correlation and the largest absolute sample difference of
+/// two aligned sample slices of the same length.
+///
+/// Two silent slices count as identical (correlation 1.0), silence against
+/// audio as unrelated (correlation 0.0).
+pub fn similarity(first: &[f32], second: &[f32]) -> (f64, f32) {
+    let n = first.len().min(second.len());
+    if n == 0 {
+        return (0.0, 0.0);
+    }
+
+    let mean_first = first[..n].iter().map(|&s| s as f64).sum::<f64>() / n as f64;
+    let mean_second = second[..n].iter().map(|&s| s as f64).sum::<f64>() / n as f64;
+
+    let (mut covariance, mut var_first, mut var_second) = (0.0f64, 0.0f64, 0.0f64);
+    let mut max_difference = 0.0f32;
+    for (&a, &b) in first[..n].iter().zip(&second[..n]) {
+        let (da, db) = (a as f64 - mean_first, b as f64 - mean_second);
+        covariance += da * db;
+        var_first += da * da;
+        var_second += db * db;
+        max_difference = max_difference.max((a - b).abs());
+    }
+
+    let correlation = if var_first == 0.0 && var_second == 0.0 {
+        if max_difference == 0.0 { 1.0 } else { 0.0 }
+    } else if var_first == 0.0 || var_second == 0.0 {
+        0.0
+    } else {
+        covariance / (var_first * var_second).sqrt()
+    };
+    (correlation, max_difference)
+}
+
+/// Reduces two aligned sample slices to one column peak each per pixel
+/// column, the first in `left` and the second in `right`.
+pub fn overlay_peaks(first: &[f32], second: &[f32], width: u32) -> Peaks {
+    let mut peaks = Peaks::new(true, width);
+    let len = first.len().min(second.len()) as u64;
+    let peak = |samples: &[f32]| samples.iter().fold(0.0f32, |max, s| max.max(s.abs().min(1.0)));
+
+    for x in 0..width as u64 {
+        let start = (x * len / width as u64) as usize;
+        let end = ((x + 1) * len / width as u64) as usize;
+        peaks.columns.push(ColumnPeak {
+            left: peak(&first[start..end]),
+            right: peak(&second[start..end]),
+            ..Default::default()
+        });
+    }
+    peaks
+}
@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod tests {
+    use crate::compare::{align, overlap, overlay_peaks, similarity};
+
+    /// A test signal with a single loud click so the peak is unambiguous.
+    fn signal(len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                let tone = 0.3 * (i as f32 * 0.05).sin();
+                if i == len / 3 { 0.9 } else { tone }
+            })
+            .collect()
+    }
+
+    /// Returns the signal delayed by `delay` frames of silence.
+    fn delayed(samples: &[f32], delay: usize) -> Vec<f32> {
+        let mut out = vec![0.0; delay];
+        out.extend_from_slice(samples);
+        out
+    }
+
+    #[test]
+    fn test_align_delayed_copy() {
+        let reference = signal(20_000);
+        assert_eq!(align(&reference, &reference, 8000), 0);
+        assert_eq!(align(&reference, &delayed(&reference, 1105), 8000), 1105);
+        assert_eq!(align(&delayed(&reference, 576), &reference, 8000), -576);
+    }
+
+    #[test]
+    fn test_align_silence() {
+        assert_eq!(align(&[], &[0.5], 100), 0);
+    }
+
+    #[test]
+    fn test_overlap() {
+        let reference = [1.0, 2.0, 3.0, 4.0];
+        let candidate = [0.0, 0.0, 1.0, 2.0, 3.0];
+
+        let (a, b) = overlap(&reference, &candidate, 2);
+        assert_eq!(a, &[1.0, 2.0, 3.0]);
+        assert_eq!(b, &[1.0, 2.0, 3.0]);
+
+        let (a, b) = overlap(&candidate, &reference, -2);
+        assert_eq!(a, &[1.0, 2.0, 3.0]);
+        assert_eq!(b, &[1.0, 2.0, 3.0]);
+
+        let (a, b) = overlap(&reference, &candidate, 10);
+        assert!(a.is_empty() && b.is_empty());
+    }
+
+    #[test]
+    fn test_similarity() {
+        let reference = signal(1000);
+        let (correlation, max_difference) = similarity(&reference, &reference);
+        assert!((correlation - 1.0).abs() < 1e-9);
+        assert_eq!(max_difference, 0.0);
+
+        let inverted: Vec<f32> = reference.iter().map(|s| -s).collect();
+        let (correlation, max_difference) = similarity(&reference, &inverted);
+        assert!((correlation + 1.0).abs() < 1e-9);
+        assert!((max_difference - 1.8).abs() < 1e-6);
+
+        let quieter: Vec<f32> = reference.iter().map(|s| s * 0.5).collect();
+        let (correlation, _) = similarity(&reference, &quieter);
+        assert!((correlation - 1.0).abs() < 1e-9, "Correlation ignores the gain");
+    }
+
+    #[test]
+    fn test_similarity_silence() {
+        assert_eq!(similarity(&[0.0; 8], &[0.0; 8]), (1.0, 0.0));
+        assert_eq!(similarity(&[0.0; 8], &signal(8)).0, 0.0);
+        assert_eq!(similarity(&[], &[]), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_overlay_peaks() {
+        let first = [0.1, -0.5, 0.2, 0.3];
+        let second = [0.1, 0.2, -0.8, 0.3];
+        let peaks = overlay_peaks(&first, &second, 2);
+
+        assert!(peaks.stereo);
+        assert_eq!(peaks.columns.len(), 2);
+        assert_eq!((peaks.columns[0].left, peaks.columns[0].right), (0.5, 0.2));
+        assert_eq!((peaks.columns[1].left, peaks.columns[1].right), (0.3, 0.8));
+    }
+}
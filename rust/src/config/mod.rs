@@ -0,0 +1,1295 @@
+/// Waveform generation settings for library users.
+///
+/// `WaveformConfig` holds everything that controls how a waveform is
+/// generated, independent of the command line.  It is created through
+/// `WaveformConfig::builder()` which validates the settings the same way the
+/// command line arguments are validated:
+///
+/// ```no_run
+/// use waver::{generate_waveform, WaveformConfig};
+/// use waver::color::Rgba;
+///
+/// let config = WaveformConfig::builder()
+///     .width(1024)
+///     .height(96)
+///     .left_color(Rgba::rgb(0x33, 0x66, 0xff))
+///     .normalize(true)
+///     .build()?;
+/// generate_waveform("input.mp3", "input.mp3.png", &config)?;
+/// # Ok::<(), waver::WaverError>(())
+/// ```
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tracing::debug;
+
+use crate::backdrop::Backdrop;
+
+use crate::cli::{
+    AutoGain, Corner, FileExtension, Height, ImageFormat, JpegQuality, MonoStyle, Overlap, OverviewRatio, RenderMode, TileWidth,
+    TimeOffset, WaveLayout, Width,
+};
+use crate::color::{seeded_colors, ColorMap, Rgba};
+use crate::error::{Result, WaverError};
+use crate::image::MAX_STEMS;
+use crate::markers::load_markers;
+use crate::metadata::Marker;
+use crate::mix::ChannelMode;
+use crate::peak_cache::{PeakCache, PeakIndex};
+use crate::render::RenderOptions;
+use crate::scale::{AmplitudeMap, AmplitudeScale, DEFAULT_DB_FLOOR, NEUTRAL_CONTRAST};
+use crate::segment::{Segmenting, DEFAULT_TEMPLATE};
+use crate::silence::{SilenceDetection, DEFAULT_MIN_SECONDS, DEFAULT_THRESHOLD_DB};
+use crate::spectrum::Colormap;
+use crate::summary::RunId;
+use crate::tiles;
+use crate::video::VideoFormat;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The largest allowed balance strip height in pixels.
+pub const MAX_BALANCE_STRIP: u32 = 1024;
+
+/// What to do when the output image already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExistingOutput {
+    /// Leave the existing image alone
+    #[default]
+    Skip,
+    /// Always replace the existing image
+    Overwrite,
+    /// Replace the existing image only if the audio file is newer
+    IfNewer,
+}
+
+/// The part of the audio that is rendered, in seconds from the start.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeRange {
+    /// Where the rendered part starts
+    pub start: f64,
+    /// Where the rendered part ends, or the end of the audio if `None`
+    pub end: Option<f64>,
+}
+
+/// Validated settings for generating a waveform image.
+#[derive(Debug, Clone)]
+pub struct WaveformConfig {
+    pub(crate) width: Width,
+    pub(crate) height: Height,
+    pub(crate) mode: RenderMode,
+    pub(crate) colormap: Colormap,
+    pub(crate) left_color: Rgba,
+    pub(crate) right_color: Rgba,
+    pub(crate) background_color: Rgba,
+    pub(crate) background_image: Option<Arc<Backdrop>>,
+    pub(crate) color_seed: Option<u64>,
+    pub(crate) auto_color: bool,
+    pub(crate) color_map: Option<Arc<ColorMap>>,
+    pub(crate) map: AmplitudeMap,
+    pub(crate) channels: ChannelMode,
+    pub(crate) layout: WaveLayout,
+    pub(crate) mono_style: MonoStyle,
+    pub(crate) overlap: Overlap,
+    pub(crate) normalize: bool,
+    pub(crate) auto_gain: Option<AutoGain>,
+    pub(crate) tint_by_loudness: bool,
+    pub(crate) antialias: bool,
+    pub(crate) envelope: bool,
+    pub(crate) rms_layer: bool,
+    pub(crate) balance_strip: Option<u32>,
+    pub(crate) overview_ratio: Option<OverviewRatio>,
+    pub(crate) duration_badge: Option<Corner>,
+    pub(crate) center_line: Option<Rgba>,
+    pub(crate) borders: bool,
+    pub(crate) grid: Option<f64>,
+    pub(crate) ticks: Option<f64>,
+    pub(crate) ticks_json: bool,
+    pub(crate) cue_markers: bool,
+    pub(crate) markers: Option<Arc<Vec<Marker>>>,
+    pub(crate) max_packets: Option<u64>,
+    pub(crate) sidecar: bool,
+    pub(crate) embed_metadata: bool,
+    pub(crate) run_id: Option<RunId>,
+    pub(crate) art_dir: Option<PathBuf>,
+    pub(crate) peak_cache: bool,
+    pub(crate) peak_index: bool,
+    pub(crate) preview: bool,
+    pub(crate) silence: Option<SilenceDetection>,
+    pub(crate) measure_loudness: bool,
+    pub(crate) detect_bpm: bool,
+    pub(crate) played_colors: Option<(Rgba, Rgba)>,
+    pub(crate) stem_colors: Vec<Rgba>,
+    pub(crate) checkerboard: Option<u32>,
+    pub(crate) pcm_hash: bool,
+    pub(crate) tile_width: Option<TileWidth>,
+    pub(crate) stdin_format: Option<FileExtension>,
+    pub(crate) range: Option<TimeRange>,
+    pub(crate) trim_silence: Option<f32>,
+    pub(crate) format: ImageFormat,
+    pub(crate) jpeg_quality: JpegQuality,
+    pub(crate) video: Option<VideoFormat>,
+    pub(crate) segmenting: Option<Segmenting>,
+    pub(crate) existing_output: ExistingOutput,
+    pub(crate) verify_existing: bool,
+    pub(crate) dry_run: bool,
+}
+
+impl WaveformConfig {
+    /// Returns a builder starting from the default settings.
+    pub fn builder() -> WaveformConfigBuilder {
+        WaveformConfigBuilder::default()
+    }
+
+    /// Returns the image width in pixels.
+    pub fn width(&self) -> u32 {
+        self.width.value()
+    }
+
+    /// Returns the image height in pixels.
+    pub fn height(&self) -> u32 {
+        self.height.value()
+    }
+
+    /// Returns the width and height of the written image in pixels,
+    /// which includes the rows of the balance strip below the waveform.
+    pub fn image_size(&self) -> (u32, u32) {
+        (self.width(), self.height() + self.balance_strip.unwrap_or(0))
+    }
+
+    /// Returns the file format of the image.
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    /// Returns the file extension of the output, the video format's if a
+    /// video is written instead of the image.
+    pub fn output_extension(&self) -> &'static str {
+        match self.video {
+            Some(video) => video.extension(),
+            None => self.format.extension(),
+        }
+    }
+
+    /// Returns true if a JSON sidecar is written next to each image.
+    pub fn sidecar(&self) -> bool {
+        self.sidecar
+    }
+
+    /// Returns true if the audio facts are written into the PNG as text.
+    pub fn embed_metadata(&self) -> bool {
+        self.embed_metadata
+    }
+
+    /// Returns the directory the cover art is extracted into, if it is.
+    pub fn art_dir(&self) -> Option<&Path> {
+        self.art_dir.as_deref()
+    }
+
+    /// Returns true if a preview is written next to each image.
+    pub fn preview(&self) -> bool {
+        self.preview
+    }
+
+    /// Returns true if the silences are written next to each image.
+    pub fn detect_silence(&self) -> bool {
+        self.silence.is_some()
+    }
+
+    /// Returns the peak cache of the audio file and its index, the ones
+    /// that `--peak-cache` and `--peak-index` keep (none for stdin).
+    pub fn peak_cache_paths(&self, audio_path: &Path) -> Vec<PathBuf> {
+        if !self.peak_cache || is_stdin(audio_path) {
+            return Vec::new();
+        }
+        let cache = PeakCache::path_for(audio_path);
+        let index = self.peak_index.then(|| PeakIndex::path_for(&cache));
+        std::iter::once(cache).chain(index).collect()
+    }
+
+    /// Returns true if the loudness of the audio is measured.
+    pub fn measure_loudness(&self) -> bool {
+        self.measure_loudness
+    }
+
+    /// Returns true if the tempo of the audio is estimated.
+    pub fn detect_bpm(&self) -> bool {
+        self.detect_bpm
+    }
+
+    /// Returns true if a played copy is written next to each image.
+    pub fn progress_pair(&self) -> bool {
+        self.played_colors.is_some()
+    }
+
+    /// Returns the square size of the checkerboard copy written next to
+    /// each image, if one is.
+    pub fn checkerboard(&self) -> Option<u32> {
+        self.checkerboard
+    }
+
+    /// Returns the backdrop the images are composited over, already
+    /// scaled to the image size, if there is one.
+    pub fn background_image(&self) -> Option<&Backdrop> {
+        self.background_image.as_deref()
+    }
+
+    /// Returns the left and right colors of a file: the set colors, or with
+    /// a color seed the colors of the seed and the file name (without the
+    /// directory, so a moved file keeps its colors).
+    pub fn file_colors(&self, input_path: &Path) -> (Rgba, Rgba) {
+        match self.color_seed {
+            Some(seed) => {
+                let name = input_path.file_name().unwrap_or(input_path.as_os_str());
+                seeded_colors(seed, &name.to_string_lossy())
+            }
+            None => (self.left_color, self.right_color),
+        }
+    }
+
+    /// Returns how the audio is split into segments, if it is.
+    pub fn segmenting(&self) -> Option<&Segmenting> {
+        self.segmenting.as_ref()
+    }
+
+    /// Returns the options the peaks are drawn with under these settings.
+    pub fn render_options(&self) -> RenderOptions {
+        RenderOptions {
+            tint: self.tint_by_loudness,
+            balance_strip: self.balance_strip.is_some(),
+            overview: self.overview_ratio.is_some(),
+            envelope: self.envelope,
+            lanes: self.layout == WaveLayout::Stacked,
+            rms_layer: self.rms_layer,
+            normalize: self.normalize,
+            auto_gain: self.auto_gain,
+            map: self.map,
+        }
+    }
+
+    /// Returns the file that is written for the given image path.
+    ///
+    /// This is the image itself, or in tiles mode the manifest of the tiles.
+    pub fn target_path(&self, image_path: &Path) -> PathBuf {
+        if self.tile_width.is_some() {
+            tiles::manifest_path(image_path)
+        } else {
+            image_path.to_path_buf()
+        }
+    }
+
+    /// Returns true if `--verify-existing` finds the existing image damaged:
+    /// a PNG that does not decode to the end (with the checksums of its
+    /// chunks), or not at the size the settings draw.  Other formats, tiles
+    /// and videos are not checked.
+    ///
+    /// The whole image is decoded, so this reads every existing image again.
+    pub(crate) fn is_damaged(&self, image_path: &Path) -> bool {
+        if !self.verify_existing || self.format != ImageFormat::Png || self.video.is_some() || self.tile_width.is_some() {
+            return false;
+        }
+        let decode = || -> std::result::Result<(u32, u32), png::DecodingError> {
+            let mut reader = png::Decoder::new(std::io::BufReader::new(std::fs::File::open(image_path)?)).read_info()?;
+            let mut pixels = vec![0; reader.output_buffer_size()];
+            reader.next_frame(&mut pixels)?;
+            reader.finish()?;
+            Ok((reader.info().width, reader.info().height))
+        };
+        let whole = decode().is_ok_and(|size| size == self.image_size());
+        if !whole {
+            debug!(path = %image_path.display(), "The existing image is damaged, drawing it again");
+        }
+        !whole
+    }
+}
+
+/// The file name that stands for stdin as the input or stdout as the output.
+pub const STDIO_PATH: &str = "-";
+
+/// Returns true if the input path asks for the audio from stdin.
+pub fn is_stdin(input_path: &Path) -> bool {
+    input_path == Path::new(STDIO_PATH)
+}
+
+/// Returns true if the output path asks for the image on stdout.
+pub fn is_stdout(output_path: &Path) -> bool {
+    output_path == Path::new(STDIO_PATH)
+}
+
+/// Returns the path of the checkerboard copy of an image.
+///
+/// The copy is always a PNG, so the image extension is replaced with
+/// `.checker.png`: `song.mp3.jpg` gets the copy `song.mp3.checker.png`.
+pub fn checkerboard_path(image_path: &Path) -> PathBuf {
+    image_path.with_extension("checker.png")
+}
+
+/// Returns the path of the played copy of an image in a progress pair.
+///
+/// `.played` goes before the image extension, so `song.mp3.png` gets the
+/// played copy `song.mp3.played.png`.
+pub fn played_path(image_path: &Path) -> PathBuf {
+    match image_path.extension() {
+        Some(extension) => {
+            let mut played = std::ffi::OsString::from("played.");
+            played.push(extension);
+            image_path.with_extension(played)
+        }
+        None => {
+            let mut played = image_path.as_os_str().to_owned();
+            played.push(".played");
+            PathBuf::from(played)
+        }
+    }
+}
+
+impl Default for WaveformConfig {
+    fn default() -> Self {
+        Self::builder()
+            .build()
+            .expect("default waveform settings are valid")
+    }
+}
+
+/// Builder for `WaveformConfig`.
+///
+/// Each setter takes a plain value; the values are validated by `build()`.
+#[derive(Debug, Clone)]
+pub struct WaveformConfigBuilder {
+    width: u32,
+    height: u32,
+    micro: bool,
+    mode: RenderMode,
+    colormap: Colormap,
+    left_color: Rgba,
+    right_color: Rgba,
+    background_color: Rgba,
+    background_image: Option<PathBuf>,
+    color_seed: Option<u64>,
+    auto_color: bool,
+    color_map: Option<PathBuf>,
+    scale: AmplitudeScale,
+    db_floor: f32,
+    contrast: f32,
+    channels: ChannelMode,
+    layout: WaveLayout,
+    mono_style: MonoStyle,
+    overlap: Overlap,
+    normalize: bool,
+    auto_gain: Option<AutoGain>,
+    tint_by_loudness: bool,
+    antialias: bool,
+    envelope: bool,
+    rms_layer: bool,
+    balance_strip: Option<u32>,
+    overview_ratio: Option<f32>,
+    duration_badge: Option<Corner>,
+    center_line: Option<Rgba>,
+    borders: bool,
+    grid: Option<f64>,
+    ticks: Option<f64>,
+    ticks_json: bool,
+    cue_markers: bool,
+    markers: Option<PathBuf>,
+    max_packets: Option<u64>,
+    sidecar: bool,
+    embed_metadata: bool,
+    run_id: Option<RunId>,
+    art_dir: Option<PathBuf>,
+    peak_cache: bool,
+    peak_index: bool,
+    preview: bool,
+    detect_silence: bool,
+    silence_threshold: f32,
+    silence_min_duration: f64,
+    measure_loudness: bool,
+    detect_bpm: bool,
+    played_colors: Option<(Rgba, Rgba)>,
+    stem_colors: Vec<Rgba>,
+    checkerboard: Option<u32>,
+    pcm_hash: bool,
+    tile_width: Option<u32>,
+    stdin_format: Option<String>,
+    start: Option<f64>,
+    end: Option<f64>,
+    trim_silence: Option<f32>,
+    format: ImageFormat,
+    jpeg_quality: u8,
+    video: Option<VideoFormat>,
+    segment: Option<f64>,
+    segment_overlap: f64,
+    segment_name: Option<String>,
+    existing_output: ExistingOutput,
+    verify_existing: bool,
+    dry_run: bool,
+}
+
+impl Default for WaveformConfigBuilder {
+    /// The defaults match the command line defaults.
+    fn default() -> Self {
+        Self {
+            width: 2048,
+            height: 128,
+            micro: false,
+            mode: RenderMode::Waveform,
+            colormap: Colormap::Magma,
+            left_color: Rgba::rgb(0x00, 0xff, 0x99),
+            right_color: Rgba::rgb(0x99, 0xff, 0x00),
+            color_seed: None,
+            auto_color: false,
+            color_map: None,
+            background_color: Rgba::new(0xff, 0xff, 0xff, 0x00),
+            background_image: None,
+            scale: AmplitudeScale::Linear,
+            db_floor: DEFAULT_DB_FLOOR,
+            contrast: NEUTRAL_CONTRAST,
+            channels: ChannelMode::Stereo,
+            layout: WaveLayout::Combined,
+            mono_style: MonoStyle::Mirrored,
+            overlap: Overlap::Mark,
+            normalize: false,
+            auto_gain: None,
+            tint_by_loudness: false,
+            antialias: false,
+            envelope: false,
+            rms_layer: false,
+            balance_strip: None,
+            overview_ratio: None,
+            duration_badge: None,
+            center_line: None,
+            borders: false,
+            grid: None,
+            ticks: None,
+            ticks_json: false,
+            cue_markers: false,
+            markers: None,
+            max_packets: None,
+            sidecar: false,
+            embed_metadata: false,
+            run_id: None,
+            art_dir: None,
+            peak_cache: false,
+            peak_index: false,
+            preview: false,
+            detect_silence: false,
+            silence_threshold: DEFAULT_THRESHOLD_DB,
+            silence_min_duration: DEFAULT_MIN_SECONDS,
+            measure_loudness: false,
+            detect_bpm: false,
+            played_colors: None,
+            stem_colors: Vec::new(),
+            checkerboard: None,
+            pcm_hash: false,
+            tile_width: None,
+            stdin_format: None,
+            start: None,
+            end: None,
+            trim_silence: None,
+            format: ImageFormat::Png,
+            jpeg_quality: JpegQuality::DEFAULT,
+            video: None,
+            segment: None,
+            segment_overlap: 0.0,
+            segment_name: None,
+            existing_output: ExistingOutput::Skip,
+            verify_existing: false,
+            dry_run: false,
+        }
+    }
+}
+
+impl WaveformConfigBuilder {
+    /// Sets the image width in pixels (at least 16).
+    pub fn width(mut self, width: u32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Sets the image height in pixels (at least 6 and even).
+    pub fn height(mut self, height: u32) -> Self {
+        self.height = height;
+        self
+    }
+
+    /// Allows sparkline sized images down to 4 x 2 pixels instead of the
+    /// normal minimum of 16 x 6.
+    pub fn micro(mut self, micro: bool) -> Self {
+        self.micro = micro;
+        self
+    }
+
+    /// Sets whether a waveform or a spectrogram is generated.
+    pub fn mode(mut self, mode: RenderMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets the colors of the spectrogram levels.
+    pub fn colormap(mut self, colormap: Colormap) -> Self {
+        self.colormap = colormap;
+        self
+    }
+
+    /// Sets the color of the left (or mono) channel.
+    pub fn left_color(mut self, color: Rgba) -> Self {
+        self.left_color = color;
+        self
+    }
+
+    /// Sets the color of the right channel.
+    pub fn right_color(mut self, color: Rgba) -> Self {
+        self.right_color = color;
+        self
+    }
+
+    /// Gives each file its own left and right colors, a hue picked from the
+    /// seed and the file name, instead of the set colors.
+    pub fn color_seed(mut self, seed: Option<u64>) -> Self {
+        self.color_seed = seed;
+        self
+    }
+
+    /// Draws each file in the dominant color of its cover art; files
+    /// without art, or with art that can not be read, keep the set or
+    /// seeded colors.
+    pub fn auto_color(mut self, auto_color: bool) -> Self {
+        self.auto_color = auto_color;
+        self
+    }
+
+    /// Gives the files matched by the rows of the CSV color map at this path
+    /// the colors of their row (see `ColorMap`).  The map is loaded once,
+    /// when the config is built.
+    pub fn color_map(mut self, path: Option<PathBuf>) -> Self {
+        self.color_map = path;
+        self
+    }
+
+    /// Sets the background color.
+    pub fn background_color(mut self, color: Rgba) -> Self {
+        self.background_color = color;
+        self
+    }
+
+    /// Sets the amplitude scale of the waveform.
+    pub fn scale(mut self, scale: AmplitudeScale) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Sets the level in dB drawn as silence in dB scale (must be negative).
+    pub fn db_floor(mut self, db_floor: f32) -> Self {
+        self.db_floor = db_floor;
+        self
+    }
+
+    /// Sets the gamma applied to the scaled heights (must be positive, 1.0
+    /// leaves them as they are).
+    pub fn contrast(mut self, contrast: f32) -> Self {
+        self.contrast = contrast;
+        self
+    }
+
+    /// Sets which channels are drawn and how they are mixed.
+    pub fn channels(mut self, channels: ChannelMode) -> Self {
+        self.channels = channels;
+        self
+    }
+
+    /// Sets how the channels are laid out, combined or each in its own lane.
+    pub fn layout(mut self, layout: WaveLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Sets where the bars of a single (mono) waveform are drawn from.
+    pub fn mono_style(mut self, style: MonoStyle) -> Self {
+        self.mono_style = style;
+        self
+    }
+
+    /// Sets what the pixels covered by both the left and the right bar are
+    /// drawn as, like in the `waver compare` diff image.
+    pub fn overlap(mut self, overlap: Overlap) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    /// Scales the waveform so the loudest peak reaches full height.
+    pub fn normalize(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// Scales the waveform so the percentile of its column peaks reaches
+    /// full height, clipping the louder columns, instead of the loudest
+    /// peak as with `normalize`.
+    pub fn auto_gain(mut self, auto_gain: Option<AutoGain>) -> Self {
+        self.auto_gain = auto_gain;
+        self
+    }
+
+    /// Colors each column by its RMS loudness.
+    pub fn tint_by_loudness(mut self, tint: bool) -> Self {
+        self.tint_by_loudness = tint;
+        self
+    }
+
+    /// Draws the bar ends with their fractional coverage for smooth edges
+    /// (an 8-bit image, can not be combined with the loudness tint).
+    pub fn antialias(mut self, antialias: bool) -> Self {
+        self.antialias = antialias;
+        self
+    }
+
+    /// Draws the signed envelope (lowest to highest sample) of each column
+    /// instead of the symmetric peak bars.
+    pub fn envelope(mut self, envelope: bool) -> Self {
+        self.envelope = envelope;
+        self
+    }
+
+    /// Draws the RMS of each column in the channel colors over its peak in
+    /// a faded shade.
+    pub fn rms_layer(mut self, rms_layer: bool) -> Self {
+        self.rms_layer = rms_layer;
+        self
+    }
+
+    /// Adds a balance strip of the given height (1 to 1024 pixels).
+    pub fn balance_strip(mut self, pixels: Option<u32>) -> Self {
+        self.balance_strip = pixels;
+        self
+    }
+
+    /// Adds a compressed overview taking the given ratio of the height
+    /// (above 0 and at most 0.5).
+    pub fn overview_ratio(mut self, ratio: Option<f32>) -> Self {
+        self.overview_ratio = ratio;
+        self
+    }
+
+    /// Draws the track duration as a badge in the given corner.
+    pub fn duration_badge(mut self, corner: Option<Corner>) -> Self {
+        self.duration_badge = corner;
+        self
+    }
+
+    /// Draws a line in the color along the center of the waveform, behind
+    /// the columns.
+    pub fn center_line(mut self, color: Option<Rgba>) -> Self {
+        self.center_line = color;
+        self
+    }
+
+    /// Also draws the center line color along the top and bottom of the
+    /// waveform (needs a center line).
+    pub fn borders(mut self, borders: bool) -> Self {
+        self.borders = borders;
+        self
+    }
+
+    /// Draws vertical lines behind the waveform at every multiple of this
+    /// many seconds, in the center line color or else a faint left color.
+    pub fn grid(mut self, seconds: Option<f64>) -> Self {
+        self.grid = seconds;
+        self
+    }
+
+    /// Draws short tick marks along the baseline at every multiple of this
+    /// many seconds, in the color of the grid.
+    pub fn ticks(mut self, seconds: Option<f64>) -> Self {
+        self.ticks = seconds;
+        self
+    }
+
+    /// Sets how many packets of a track are read before giving up, instead
+    /// of the limit derived from the length the track reports.
+    pub fn max_packets(mut self, max_packets: Option<u64>) -> Self {
+        self.max_packets = max_packets;
+        self
+    }
+
+    /// Also writes the time and column of every tick mark as JSON next to
+    /// each image (needs ticks).
+    pub fn ticks_json(mut self, ticks_json: bool) -> Self {
+        self.ticks_json = ticks_json;
+        self
+    }
+
+    /// Draws the index points of a cuesheet embedded in the audio file as
+    /// lines over the waveform, in the color of the grid.
+    pub fn cue_markers(mut self, cue_markers: bool) -> Self {
+        self.cue_markers = cue_markers;
+        self
+    }
+
+    /// Draws the markers of the cue sheet or JSON file at this path as
+    /// lines over the waveform, like `cue_markers` (see `load_markers`).
+    /// The file is loaded once, when the config is built.
+    pub fn markers(mut self, path: Option<PathBuf>) -> Self {
+        self.markers = path;
+        self
+    }
+
+    /// Writes a JSON sidecar with the audio facts next to the image.
+    pub fn sidecar(mut self, sidecar: bool) -> Self {
+        self.sidecar = sidecar;
+        self
+    }
+
+    /// Writes the duration, sample rate, channels, source file name and
+    /// waver version into the PNG as text chunks.
+    pub fn embed_metadata(mut self, embed_metadata: bool) -> Self {
+        self.embed_metadata = embed_metadata;
+        self
+    }
+
+    /// Records the ID of the run in the sidecars and the PNG text chunks.
+    pub fn run_id(mut self, run_id: Option<RunId>) -> Self {
+        self.run_id = run_id;
+        self
+    }
+
+    /// Writes the embedded cover art of each audio file into the directory.
+    pub fn extract_art(mut self, dir: Option<PathBuf>) -> Self {
+        self.art_dir = dir;
+        self
+    }
+
+    /// Records a SHA-256 of the decoded PCM samples in the sidecar.
+    pub fn pcm_hash(mut self, pcm_hash: bool) -> Self {
+        self.pcm_hash = pcm_hash;
+        self
+    }
+
+    /// Also writes the peak of every second next to each image (see
+    /// `preview`).
+    pub fn preview(mut self, preview: bool) -> Self {
+        self.preview = preview;
+        self
+    }
+
+    /// Also writes the silences of the audio next to each image.
+    pub fn detect_silence(mut self, detect_silence: bool) -> Self {
+        self.detect_silence = detect_silence;
+        self
+    }
+
+    /// Sets the level below which the audio is silent, in dBFS.
+    pub fn silence_threshold(mut self, threshold_db: f32) -> Self {
+        self.silence_threshold = threshold_db;
+        self
+    }
+
+    /// Sets the shortest silence that is written, in seconds.
+    pub fn silence_min_duration(mut self, seconds: f64) -> Self {
+        self.silence_min_duration = seconds;
+        self
+    }
+
+    /// Also measures the integrated loudness and the true peak of the audio
+    /// (see `generate_waveform_measured`).
+    pub fn measure_loudness(mut self, measure_loudness: bool) -> Self {
+        self.measure_loudness = measure_loudness;
+        self
+    }
+
+    /// Also estimates the tempo of the audio (see
+    /// `generate_waveform_measured`).
+    pub fn detect_bpm(mut self, detect_bpm: bool) -> Self {
+        self.detect_bpm = detect_bpm;
+        self
+    }
+
+    /// Also writes a copy of the image with both channels in the played
+    /// color (see `played_path`), for players that reveal it over the
+    /// image as the audio plays.
+    pub fn progress_pair(mut self, played_color: Option<Rgba>) -> Self {
+        self.played_colors = played_color.map(|color| (color, color));
+        self
+    }
+
+    /// Like `progress_pair`, with the left and right channel of the played
+    /// copy each in a color of its own.
+    pub fn played_colors(mut self, colors: Option<(Rgba, Rgba)>) -> Self {
+        self.played_colors = colors;
+        self
+    }
+
+    /// Sets the colors of the stems drawn by `generate_stems`, in the order
+    /// of the stems (empty for colors spread around the color wheel).
+    pub fn stem_colors(mut self, colors: Vec<Rgba>) -> Self {
+        self.stem_colors = colors;
+        self
+    }
+
+    /// Also writes a `.checker.png` copy of each image over a checkerboard
+    /// of squares of the given size, to check its transparency.
+    pub fn preview_checkerboard(mut self, square: Option<u32>) -> Self {
+        self.checkerboard = square;
+        self
+    }
+
+    /// Composites each image over the PNG image at this path, scaled to
+    /// cover the image.  The image is loaded once, when the config is built.
+    pub fn background_image(mut self, path: Option<PathBuf>) -> Self {
+        self.background_image = path;
+        self
+    }
+
+    /// Keeps the decoded peaks of each audio file in a `.peaks` file next
+    /// to it and renders from those when they are up to date.
+    pub fn peak_cache(mut self, peak_cache: bool) -> Self {
+        self.peak_cache = peak_cache;
+        self
+    }
+
+    /// Writes a `.peaks.json` index of the byte ranges of the peak cache
+    /// (needs `peak_cache`).
+    pub fn peak_index(mut self, peak_index: bool) -> Self {
+        self.peak_index = peak_index;
+        self
+    }
+
+    /// Cuts the waveform into tiles of the given width (a multiple of 8,
+    /// at least 16) with a manifest instead of writing a single image.
+    pub fn tiles(mut self, tile_width: Option<u32>) -> Self {
+        self.tile_width = tile_width;
+        self
+    }
+
+    /// Sets the format (a file extension like `mp3`) of audio read from
+    /// stdin, which has no file name to tell it apart.
+    pub fn stdin_format(mut self, format: Option<String>) -> Self {
+        self.stdin_format = format;
+        self
+    }
+
+    /// Renders only the audio from this time (in seconds) on.
+    pub fn start(mut self, seconds: Option<f64>) -> Self {
+        self.start = seconds;
+        self
+    }
+
+    /// Renders only the audio up to this time (in seconds).
+    pub fn end(mut self, seconds: Option<f64>) -> Self {
+        self.end = seconds;
+        self
+    }
+
+    /// Renders only the audio from the first to the last sample above the
+    /// threshold (in dBFS), leaving out the silence before and after it.
+    pub fn trim_silence(mut self, threshold_db: Option<f32>) -> Self {
+        self.trim_silence = threshold_db;
+        self
+    }
+
+    /// Sets the file format of the image.
+    pub fn format(mut self, format: ImageFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets the JPEG quality (1 to 100), used for the JPEG and AVIF formats only.
+    pub fn jpeg_quality(mut self, quality: u8) -> Self {
+        self.jpeg_quality = quality;
+        self
+    }
+
+    /// Writes a video of the waveform with a moving cursor and the audio
+    /// instead of the image.
+    pub fn video(mut self, video: Option<VideoFormat>) -> Self {
+        self.video = video;
+        self
+    }
+
+    /// Splits the audio into segments of this length (in seconds) with an
+    /// image each, see `generate_segments`.
+    pub fn segment(mut self, seconds: Option<f64>) -> Self {
+        self.segment = seconds;
+        self
+    }
+
+    /// Sets how many seconds each segment runs on into the next one.
+    pub fn segment_overlap(mut self, seconds: f64) -> Self {
+        self.segment_overlap = seconds;
+        self
+    }
+
+    /// Sets the image name template of the segments (see `Segmenting::new`).
+    pub fn segment_name(mut self, template: Option<String>) -> Self {
+        self.segment_name = template;
+        self
+    }
+
+    /// Sets what to do when the output image already exists.
+    pub fn existing_output(mut self, existing: ExistingOutput) -> Self {
+        self.existing_output = existing;
+        self
+    }
+
+    /// Checks that an existing PNG image decodes to the end at the size of
+    /// the image before keeping it, and draws it again otherwise.  Every
+    /// existing image is decoded in full, so a run over images that are up
+    /// to date costs about as much as reading all of them.
+    pub fn verify_existing(mut self, verify: bool) -> Self {
+        self.verify_existing = verify;
+        self
+    }
+
+    /// Decodes and renders without writing any files.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Validates the start and end times into the time range, if any.
+    fn time_range(&self) -> Result<Option<TimeRange>> {
+        if self.start.is_none() && self.end.is_none() {
+            return Ok(None);
+        }
+
+        let start = TimeOffset::new(self.start.unwrap_or(0.0))?.seconds();
+        let end = self.end.map(TimeOffset::new).transpose()?.map(|end| end.seconds());
+        if end.is_some_and(|end| end <= start) {
+            return Err(WaverError::argument_error("The end time must be after the start time"));
+        }
+        Ok(Some(TimeRange { start, end }))
+    }
+
+    /// Validates the settings and builds the configuration.
+    pub fn build(self) -> Result<WaveformConfig> {
+        // The dB floor is the level drawn as silence so it must be below full scale
+        if !self.db_floor.is_finite() || self.db_floor >= 0.0 {
+            return Err(WaverError::argument_error("dB floor must be a negative dB value"));
+        }
+        if !self.contrast.is_finite() || self.contrast <= 0.0 {
+            return Err(WaverError::argument_error("The contrast must be a positive number"));
+        }
+
+        if let Some(pixels) = self.balance_strip {
+            if !(1..=MAX_BALANCE_STRIP).contains(&pixels) {
+                return Err(WaverError::argument_error(format!(
+                    "Balance strip must be 1 to {MAX_BALANCE_STRIP} pixels"
+                )));
+            }
+        }
+
+        // Both use the 8-bit palette entries after the normal colors
+        if self.antialias && self.tint_by_loudness {
+            return Err(WaverError::argument_error(
+                "Anti-aliasing can not be combined with the loudness tint",
+            ));
+        }
+
+        // A time range needs at least one frame to draw
+        let range = self.time_range()?;
+
+        let (width, height) = if self.micro {
+            (Width::new_micro(self.width)?, Height::new_micro(self.height)?)
+        } else {
+            (Width::new(self.width)?, Height::new(self.height)?)
+        };
+
+        // The envelope is drawn in the plain channel colors only
+        if self.envelope && (self.antialias || self.tint_by_loudness) {
+            return Err(WaverError::argument_error(
+                "The envelope can not be combined with anti-aliasing or the loudness tint",
+            ));
+        }
+
+        // The RMS layer replaces the plain peak bars
+        if self.rms_layer && (self.envelope || self.antialias || self.tint_by_loudness) {
+            return Err(WaverError::argument_error(
+                "The RMS layer can not be combined with the envelope, anti-aliasing or the loudness tint",
+            ));
+        }
+
+        #[cfg(not(feature = "avif"))]
+        if self.format == ImageFormat::Avif {
+            return Err(WaverError::argument_error("Writing AVIF images needs waver built with the avif feature"));
+        }
+
+        // The tiles are indexed PNGs meant to be stitched back together
+        if self.format != ImageFormat::Png && self.tile_width.is_some() {
+            return Err(WaverError::argument_error("Tiles can only be written as PNG"));
+        }
+
+        // The video frames are made from the rendered image
+        if self.video.is_some() {
+            if self.format != ImageFormat::Png || self.tile_width.is_some() {
+                return Err(WaverError::argument_error(
+                    "A video can not be combined with another output format or tiles",
+                ));
+            }
+            // Video is subsampled in 2x2 blocks so both sizes must be even
+            if !self.width.is_multiple_of(2) {
+                return Err(WaverError::argument_error("A video needs an even width"));
+            }
+            // The height is even, but the balance strip is added below it
+            if !(self.height + self.balance_strip.unwrap_or(0)).is_multiple_of(2) {
+                return Err(WaverError::argument_error("A video needs an even height with the balance strip"));
+            }
+        }
+
+        // The lanes are plain peak bars of every channel as decoded
+        if self.layout == WaveLayout::Stacked {
+            if self.envelope || self.antialias || self.tint_by_loudness || self.rms_layer {
+                return Err(WaverError::argument_error(
+                    "The stacked layout can not be combined with the envelope, anti-aliasing, the loudness tint or the RMS layer",
+                ));
+            }
+            if self.channels != ChannelMode::Stereo {
+                return Err(WaverError::argument_error(
+                    "The stacked layout draws every channel and can not be combined with a channel selection",
+                ));
+            }
+        }
+
+        // The signed envelope and the lanes are always drawn around a center
+        if self.mono_style != MonoStyle::Mirrored && (self.envelope || self.layout == WaveLayout::Stacked) {
+            return Err(WaverError::argument_error(
+                "The mono style can not be combined with the envelope or the stacked layout",
+            ));
+        }
+
+        // The spectrogram is its own 8-bit image with the colormap palette
+        if self.mode == RenderMode::Spectrogram {
+            if self.format != ImageFormat::Png || self.tile_width.is_some() || self.video.is_some() {
+                return Err(WaverError::argument_error("The spectrogram can only be written as a single PNG"));
+            }
+            let drawing = self.tint_by_loudness
+                || self.antialias
+                || self.envelope
+                || self.rms_layer
+                || self.layout == WaveLayout::Stacked
+                || self.balance_strip.is_some()
+                || self.overview_ratio.is_some()
+                || self.duration_badge.is_some()
+                || self.center_line.is_some()
+                || self.grid.is_some()
+                || self.ticks.is_some()
+                || self.cue_markers
+                || self.markers.is_some()
+                || self.contrast != NEUTRAL_CONTRAST
+                || self.color_seed.is_some()
+                || self.auto_color
+                || self.color_map.is_some()
+                || self.auto_gain.is_some()
+                || self.mono_style != MonoStyle::Mirrored
+                || self.preview;
+            if drawing {
+                return Err(WaverError::argument_error(
+                    "The spectrogram can not be combined with the waveform drawing options",
+                ));
+            }
+        }
+
+        // Only PNG images have text chunks, the tiles have their manifest
+        if self.embed_metadata && (self.format != ImageFormat::Png || self.tile_width.is_some() || self.video.is_some()) {
+            return Err(WaverError::argument_error(
+                "The metadata can only be embedded in PNG images, not in tiles or a video",
+            ));
+        }
+
+        if self.borders && self.center_line.is_none() {
+            return Err(WaverError::argument_error("The borders are drawn in the center line color and need one"));
+        }
+        if self.grid.is_some_and(|seconds| seconds.is_nan() || seconds <= 0.0) {
+            return Err(WaverError::argument_error("The grid interval must be longer than zero"));
+        }
+        if self.ticks.is_some_and(|seconds| seconds.is_nan() || seconds <= 0.0) {
+            return Err(WaverError::argument_error("The tick interval must be longer than zero"));
+        }
+        if self.max_packets == Some(0) {
+            return Err(WaverError::argument_error("The packet limit must allow at least one packet"));
+        }
+        if self.ticks_json && self.ticks.is_none() {
+            return Err(WaverError::argument_error("The tick map lists the tick marks and needs them"));
+        }
+
+        if self.pcm_hash && !self.sidecar {
+            return Err(WaverError::argument_error("The PCM hash requires a sidecar"));
+        }
+
+        // The played copy is the same image written with other colors
+        if self.played_colors.is_some() && (self.tile_width.is_some() || self.video.is_some() || self.mode == RenderMode::Spectrogram) {
+            return Err(WaverError::argument_error(
+                "The progress pair can not be combined with tiles, a video or the spectrogram",
+            ));
+        }
+
+        // The checkerboard copy is made from the waveform pixels
+        if self.checkerboard.is_some() && (self.tile_width.is_some() || self.video.is_some() || self.mode == RenderMode::Spectrogram) {
+            return Err(WaverError::argument_error(
+                "The checkerboard copy can not be combined with tiles, a video or the spectrogram",
+            ));
+        }
+        if self.stem_colors.len() > MAX_STEMS {
+            return Err(WaverError::argument_error(format!("At most {MAX_STEMS} stem colors can be used")));
+        }
+
+        if self.normalize && self.auto_gain.is_some() {
+            return Err(WaverError::argument_error("The auto gain replaces the normalization, use one of them"));
+        }
+
+        if self.checkerboard == Some(0) {
+            return Err(WaverError::argument_error("The checkerboard squares must be at least one pixel"));
+        }
+
+        // The backdrop makes a truecolor PNG of the finished waveform
+        if self.background_image.is_some() {
+            let single_png = self.format == ImageFormat::Png && self.tile_width.is_none() && self.video.is_none();
+            if !single_png || self.mode == RenderMode::Spectrogram || self.checkerboard.is_some() {
+                return Err(WaverError::argument_error(
+                    "The background image can only be used for a single PNG of the waveform, without the checkerboard copy",
+                ));
+            }
+        }
+        let background_image = self
+            .background_image
+            .as_deref()
+            .map(|path| Backdrop::load(path).map(|backdrop| Arc::new(backdrop.cover(width.value(), height.value()))))
+            .transpose()?;
+        let color_map = self.color_map.as_deref().map(|path| ColorMap::load(path).map(Arc::new)).transpose()?;
+        let markers = self.markers.as_deref().map(|path| load_markers(path).map(Arc::new)).transpose()?;
+
+        if self.peak_index && !self.peak_cache {
+            return Err(WaverError::argument_error("The peak index describes the peak cache and needs it"));
+        }
+
+        // The cache has the blocks of the drawn channels, not the samples
+        if self.peak_cache {
+            if self.pcm_hash {
+                return Err(WaverError::argument_error(
+                    "The PCM hash needs the decoded samples and can not be combined with the peak cache",
+                ));
+            }
+            if self.layout == WaveLayout::Stacked || self.mode == RenderMode::Spectrogram {
+                return Err(WaverError::argument_error(
+                    "The peak cache can not be used for the stacked layout or the spectrogram",
+                ));
+            }
+        }
+
+        // The silences are found in the decode of the image
+        let silence = self
+            .detect_silence
+            .then(|| SilenceDetection::new(self.silence_threshold, self.silence_min_duration))
+            .transpose()?;
+        if silence.is_some() && (self.peak_cache || self.mode == RenderMode::Spectrogram) {
+            return Err(WaverError::argument_error(
+                "The silences need the decoded samples and can not be found with the peak cache or the spectrogram",
+            ));
+        }
+
+        // The loudness and the tempo are measured in the decode of the image too
+        if self.measure_loudness && (self.peak_cache || self.mode == RenderMode::Spectrogram) {
+            return Err(WaverError::argument_error(
+                "The loudness needs the decoded samples and can not be measured with the peak cache or the spectrogram",
+            ));
+        }
+        if self.detect_bpm && (self.peak_cache || self.mode == RenderMode::Spectrogram) {
+            return Err(WaverError::argument_error(
+                "The tempo needs the decoded samples and can not be detected with the peak cache or the spectrogram",
+            ));
+        }
+
+        // Each segment is rendered as a time range of its own
+        let segmenting = self
+            .segment
+            .map(|length| {
+                let template = self.segment_name.as_deref().unwrap_or(DEFAULT_TEMPLATE);
+                Segmenting::new(length, self.segment_overlap, template)
+            })
+            .transpose()?;
+        if segmenting.is_some() && range.is_some() {
+            return Err(WaverError::argument_error("Segments can not be combined with a time range"));
+        }
+        if segmenting.is_some() && self.measure_loudness {
+            return Err(WaverError::argument_error("The loudness is measured of whole files, not of segments"));
+        }
+        if segmenting.is_some() && self.detect_bpm {
+            return Err(WaverError::argument_error("The tempo is detected of whole files, not of segments"));
+        }
+
+        // The trimmed audio is the time range, so there can not be another
+        if let Some(threshold) = self.trim_silence {
+            if !threshold.is_finite() || threshold >= 0.0 {
+                return Err(WaverError::argument_error("The silence threshold must be a negative dB value"));
+            }
+            if range.is_some() || segmenting.is_some() {
+                return Err(WaverError::argument_error(
+                    "Trimming the silence can not be combined with a time range or segments",
+                ));
+            }
+        }
+
+        Ok(WaveformConfig {
+            width,
+            height,
+            mode: self.mode,
+            colormap: self.colormap,
+            left_color: self.left_color,
+            right_color: self.right_color,
+            background_color: self.background_color,
+            background_image,
+            color_seed: self.color_seed,
+            auto_color: self.auto_color,
+            color_map,
+            map: AmplitudeMap {
+                scale: self.scale,
+                floor_db: self.db_floor,
+                contrast: self.contrast,
+            },
+            channels: self.channels,
+            layout: self.layout,
+            mono_style: self.mono_style,
+            overlap: self.overlap,
+            normalize: self.normalize,
+            auto_gain: self.auto_gain,
+            tint_by_loudness: self.tint_by_loudness,
+            antialias: self.antialias,
+            envelope: self.envelope,
+            rms_layer: self.rms_layer,
+            balance_strip: self.balance_strip,
+            overview_ratio: self.overview_ratio.map(OverviewRatio::new).transpose()?,
+            duration_badge: self.duration_badge,
+            center_line: self.center_line,
+            borders: self.borders,
+            grid: self.grid,
+            ticks: self.ticks,
+            ticks_json: self.ticks_json,
+            cue_markers: self.cue_markers,
+            markers,
+            max_packets: self.max_packets,
+            sidecar: self.sidecar,
+            embed_metadata: self.embed_metadata,
+            run_id: self.run_id,
+            art_dir: self.art_dir,
+            peak_cache: self.peak_cache,
+            peak_index: self.peak_index,
+            preview: self.preview,
+            silence,
+            measure_loudness: self.measure_loudness,
+            detect_bpm: self.detect_bpm,
+            played_colors: self.played_colors,
+            stem_colors: self.stem_colors,
+            checkerboard: self.checkerboard,
+            pcm_hash: self.pcm_hash,
+            tile_width: self.tile_width.map(TileWidth::new).transpose()?,
+            stdin_format: self.stdin_format.map(FileExtension::new).transpose()?,
+            range,
+            trim_silence: self.trim_silence,
+            format: self.format,
+            jpeg_quality: JpegQuality::new(self.jpeg_quality)?,
+            video: self.video,
+            segmenting,
+            existing_output: self.existing_output,
+            verify_existing: self.verify_existing,
+            dry_run: self.dry_run,
+        })
+    }
+}
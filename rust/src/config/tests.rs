@@ -0,0 +1,257 @@
+#[cfg(test)]
+mod tests {
+    use crate::cli::{AutoGain, Corner, ImageFormat, MonoStyle, RenderMode, WaveLayout};
+    use crate::mix::ChannelMode;
+    use crate::config::{checkerboard_path, played_path, ExistingOutput, TimeRange, WaveformConfig};
+    use crate::color::Rgba;
+    use std::path::Path;
+    use crate::scale::AmplitudeScale;
+    use crate::video::VideoFormat;
+
+    #[test]
+    fn test_defaults_match_cli() {
+        let config = WaveformConfig::default();
+        assert_eq!(config.width(), 2048);
+        assert_eq!(config.height(), 128);
+        assert_eq!(config.left_color.red, 0x00);
+        assert_eq!(config.left_color.green, 0xff);
+        assert_eq!(config.left_color.blue, 0x99);
+        assert_eq!(config.background_color.alpha, 0x00);
+        assert_eq!(config.map.scale, AmplitudeScale::Linear);
+        assert_eq!(config.existing_output, ExistingOutput::Skip);
+        assert!(!config.sidecar());
+        assert_eq!(config.format(), ImageFormat::Png);
+    }
+
+    #[test]
+    fn test_builder_settings() {
+        let config = WaveformConfig::builder()
+            .width(512)
+            .height(64)
+            .scale(AmplitudeScale::Db)
+            .db_floor(-48.0)
+            .balance_strip(Some(8))
+            .overview_ratio(Some(0.25))
+            .duration_badge(Some(Corner::BottomRight))
+            .sidecar(true)
+            .pcm_hash(true)
+            .stdin_format(Some(" MP3".to_string()))
+            .end(Some(30.0))
+            .format(ImageFormat::Jpeg)
+            .jpeg_quality(70)
+            .existing_output(ExistingOutput::IfNewer)
+            .build()
+            .unwrap();
+
+        assert_eq!(config.width(), 512);
+        assert_eq!(config.height(), 64);
+        assert_eq!(config.image_size(), (512, 72));
+        assert_eq!(config.map.scale, AmplitudeScale::Db);
+        assert_eq!(config.map.floor_db, -48.0);
+        assert_eq!(config.balance_strip, Some(8));
+        assert_eq!(config.overview_ratio.map(|r| r.value()), Some(0.25));
+        assert_eq!(config.duration_badge, Some(Corner::BottomRight));
+        assert!(config.pcm_hash);
+        assert_eq!(config.stdin_format.as_ref().map(|f| f.as_str()), Some("mp3"));
+        assert_eq!(config.range, Some(TimeRange { start: 0.0, end: Some(30.0) }));
+        assert_eq!(config.format(), ImageFormat::Jpeg);
+        assert_eq!(config.jpeg_quality.value(), 70);
+        assert_eq!(config.existing_output, ExistingOutput::IfNewer);
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        assert!(WaveformConfig::builder().width(15).build().is_err(), "Width too small");
+        assert!(WaveformConfig::builder().height(65).build().is_err(), "Odd height");
+        assert!(WaveformConfig::builder().db_floor(0.0).build().is_err(), "Non-negative floor");
+        assert!(WaveformConfig::builder().db_floor(f32::NAN).build().is_err(), "NaN floor");
+        assert!(WaveformConfig::builder().db_floor(f32::NEG_INFINITY).build().is_err(), "Infinite floor");
+        assert_eq!(WaveformConfig::builder().contrast(2.0).build().unwrap().map.contrast, 2.0);
+        assert!(WaveformConfig::builder().contrast(0.0).build().is_err(), "Contrast of nothing");
+        assert!(WaveformConfig::builder().contrast(f32::INFINITY).build().is_err());
+        assert!(
+            WaveformConfig::builder().contrast(2.0).mode(RenderMode::Spectrogram).build().is_err(),
+            "The spectrogram has no heights"
+        );
+        assert!(WaveformConfig::builder().balance_strip(Some(0)).build().is_err());
+        assert!(WaveformConfig::builder().balance_strip(Some(1025)).build().is_err());
+        assert!(WaveformConfig::builder().overview_ratio(Some(0.75)).build().is_err());
+        assert!(WaveformConfig::builder().pcm_hash(true).build().is_err(), "Hash needs a sidecar");
+        assert!(WaveformConfig::builder().stem_colors(vec![Rgba::rgb(0, 0, 0); 9]).build().is_err(), "Nine stem colors");
+        assert!(WaveformConfig::builder().stdin_format(Some(" ".to_string())).build().is_err());
+        assert!(WaveformConfig::builder().start(Some(-1.0)).build().is_err(), "Negative start");
+        assert!(WaveformConfig::builder().start(Some(5.0)).end(Some(5.0)).build().is_err(), "Empty range");
+        assert!(
+            WaveformConfig::builder().antialias(true).tint_by_loudness(true).build().is_err(),
+            "Anti-aliasing and tint share the palette"
+        );
+        assert!(WaveformConfig::builder().envelope(true).antialias(true).build().is_err());
+        assert!(WaveformConfig::builder().rms_layer(true).tint_by_loudness(true).build().is_err());
+        assert!(WaveformConfig::builder().rms_layer(true).balance_strip(Some(4)).build().is_ok());
+        assert!(WaveformConfig::builder().jpeg_quality(0).build().is_err(), "Quality too low");
+        let stacked = || WaveformConfig::builder().layout(WaveLayout::Stacked);
+        assert!(stacked().build().is_ok());
+        assert!(stacked().envelope(true).build().is_err(), "Lanes are plain bars");
+        assert!(stacked().rms_layer(true).build().is_err(), "Lanes have no RMS layer");
+        assert!(stacked().channels(ChannelMode::Mono).build().is_err(), "Lanes are every channel");
+        let styled = || WaveformConfig::builder().mono_style(MonoStyle::Top);
+        assert!(styled().rms_layer(true).build().is_ok());
+        assert!(styled().envelope(true).build().is_err(), "The envelope is signed around the center");
+        assert!(styled().layout(WaveLayout::Stacked).build().is_err(), "Lanes are drawn around their centers");
+        assert!(styled().mode(RenderMode::Spectrogram).build().is_err());
+        let spectrogram = || WaveformConfig::builder().mode(RenderMode::Spectrogram);
+        assert!(spectrogram().channels(ChannelMode::Left).build().is_ok());
+        assert!(spectrogram().format(ImageFormat::Jpeg).build().is_err(), "Spectrogram is PNG only");
+        assert!(spectrogram().balance_strip(Some(8)).build().is_err(), "No waveform drawing options");
+        assert!(spectrogram().preview(true).build().is_err(), "The preview is made from the peaks");
+        let seeded = WaveformConfig::builder().color_seed(Some(7)).build().unwrap();
+        assert_eq!(seeded.file_colors(Path::new("music/song.mp3")), seeded.file_colors(Path::new("song.mp3")));
+        assert_ne!(seeded.file_colors(Path::new("song.mp3")), seeded.file_colors(Path::new("other.mp3")));
+        assert!(spectrogram().color_seed(Some(7)).build().is_err(), "The spectrogram colors are its colormap");
+        assert!(spectrogram().auto_color(true).build().is_err());
+        let gain = || WaveformConfig::builder().auto_gain(Some(AutoGain::new(95.0).unwrap()));
+        assert!(gain().build().is_ok());
+        assert!(gain().normalize(true).build().is_err(), "Only one of the gains");
+        assert!(gain().mode(RenderMode::Spectrogram).build().is_err());
+        assert!(WaveformConfig::builder().color_map(Some("missing.csv".into())).build().is_err(), "The map is loaded when the config is built");
+        assert!(WaveformConfig::builder().color_seed(Some(7)).auto_color(true).build().is_ok(), "Seeded colors without art");
+        let pair = || WaveformConfig::builder().progress_pair(Some(Rgba::rgb(0xff, 0x55, 0x00)));
+        assert!(pair().build().unwrap().progress_pair());
+        assert!(pair().tiles(Some(512)).build().is_err(), "The played copy is a single image");
+        assert!(pair().mode(RenderMode::Spectrogram).build().is_err());
+        let played = || WaveformConfig::builder().played_colors(Some((Rgba::rgb(1, 2, 3), Rgba::rgb(4, 5, 6))));
+        assert!(played().build().unwrap().progress_pair());
+        assert!(played().video(Some(VideoFormat::Webm)).build().is_err());
+        let checker = |square| WaveformConfig::builder().preview_checkerboard(Some(square));
+        assert_eq!(checker(8).build().unwrap().checkerboard(), Some(8));
+        assert!(checker(0).build().is_err(), "Squares of no size");
+        assert!(checker(8).tiles(Some(512)).build().is_err(), "The checkerboard copy is a single image");
+        assert!(checker(8).mode(RenderMode::Spectrogram).build().is_err());
+        let backdrop = |path: &str| WaveformConfig::builder().background_image(Some(path.into()));
+        assert!(backdrop("missing.png").build().is_err(), "The image is loaded when the config is built");
+        assert!(backdrop("missing.png").format(ImageFormat::Qoi).build().is_err());
+        assert!(backdrop("missing.png").preview_checkerboard(Some(8)).build().is_err());
+        let cached = || WaveformConfig::builder().peak_cache(true);
+        assert!(cached().build().is_ok());
+        assert!(cached().sidecar(true).pcm_hash(true).build().is_err(), "The cache has no samples to hash");
+        assert!(cached().layout(WaveLayout::Stacked).build().is_err(), "The cache has no lanes");
+        assert!(cached().peak_index(true).build().is_ok());
+        assert!(WaveformConfig::builder().peak_index(true).build().is_err(), "The index is of the cache");
+        let song = Path::new("song.mp3");
+        assert_eq!(cached().peak_index(true).build().unwrap().peak_cache_paths(song), [Path::new("song.mp3.peaks"), Path::new("song.mp3.peaks.json")]);
+        assert_eq!(cached().build().unwrap().peak_cache_paths(song), [Path::new("song.mp3.peaks")]);
+        assert!(cached().build().unwrap().peak_cache_paths(Path::new("-")).is_empty(), "Audio from stdin is not cached");
+        assert!(WaveformConfig::default().peak_cache_paths(song).is_empty());
+        let segmented = || WaveformConfig::builder().segment(Some(600.0));
+        assert!(segmented().build().unwrap().segmenting().is_some());
+        assert!(segmented().segment_overlap(600.0).build().is_err(), "Overlap of a whole segment");
+        assert!(segmented().segment_name(Some("{file}.{ext}".to_string())).build().is_err(), "Names must differ");
+        assert!(segmented().start(Some(60.0)).build().is_err(), "Segments are time ranges");
+        let silences = || WaveformConfig::builder().detect_silence(true);
+        assert!(silences().build().unwrap().detect_silence());
+        assert!(!WaveformConfig::builder().silence_threshold(3.0).build().unwrap().detect_silence(), "Only checked when used");
+        assert!(silences().silence_threshold(3.0).build().is_err(), "The threshold is below full scale");
+        assert!(silences().silence_min_duration(0.0).build().is_err());
+        assert!(silences().peak_cache(true).build().is_err(), "The cache has no samples");
+        assert!(silences().mode(RenderMode::Spectrogram).build().is_err());
+        let trimmed = || WaveformConfig::builder().trim_silence(Some(-50.0));
+        assert_eq!(trimmed().build().unwrap().trim_silence, Some(-50.0));
+        assert!(WaveformConfig::builder().trim_silence(Some(0.0)).build().is_err(), "The threshold is below full scale");
+        assert!(trimmed().end(Some(30.0)).build().is_err(), "The trimmed audio is the time range");
+        assert!(trimmed().segment(Some(600.0)).build().is_err(), "Segments are time ranges");
+        let measured = || WaveformConfig::builder().measure_loudness(true);
+        assert!(measured().build().unwrap().measure_loudness());
+        assert!(measured().peak_cache(true).build().is_err(), "The cache has no samples");
+        assert!(measured().mode(RenderMode::Spectrogram).build().is_err());
+        assert!(measured().segment(Some(600.0)).build().is_err(), "Whole files only");
+        let tempo = || WaveformConfig::builder().detect_bpm(true);
+        assert!(tempo().build().unwrap().detect_bpm());
+        assert!(tempo().peak_cache(true).build().is_err(), "The cache has no samples");
+        assert!(tempo().mode(RenderMode::Spectrogram).build().is_err());
+        assert!(tempo().segment(Some(600.0)).build().is_err(), "Whole files only");
+        let embedded = || WaveformConfig::builder().embed_metadata(true);
+        assert!(embedded().build().unwrap().embed_metadata());
+        assert!(embedded().mode(RenderMode::Spectrogram).build().is_ok());
+        assert!(embedded().format(ImageFormat::Jpeg).build().is_err(), "Text chunks are PNG only");
+        assert!(embedded().tiles(Some(512)).build().is_err(), "The tiles have their manifest");
+        let axis = || WaveformConfig::builder().center_line(Some(Rgba::rgb(0x88, 0x88, 0x88)));
+        assert!(axis().borders(true).build().is_ok());
+        assert!(WaveformConfig::builder().borders(true).build().is_err(), "Borders need a center line color");
+        assert!(axis().mode(RenderMode::Spectrogram).build().is_err(), "Spectrograms have no axis");
+        let grid = |seconds| WaveformConfig::builder().grid(Some(seconds));
+        assert!(grid(60.0).build().is_ok());
+        assert!(grid(0.0).build().is_err(), "The grid needs an interval");
+        assert!(grid(60.0).mode(RenderMode::Spectrogram).build().is_err());
+        let ticks = |seconds| WaveformConfig::builder().ticks(Some(seconds));
+        assert!(ticks(30.0).ticks_json(true).build().is_ok());
+        assert!(ticks(0.0).build().is_err(), "The ticks need an interval");
+        assert!(ticks(30.0).mode(RenderMode::Spectrogram).build().is_err());
+        assert!(WaveformConfig::builder().ticks_json(true).build().is_err(), "The tick map is of the ticks");
+        let markers = || WaveformConfig::builder().cue_markers(true);
+        assert!(markers().build().is_ok());
+        assert!(markers().mode(RenderMode::Spectrogram).build().is_err(), "Spectrograms have no markers");
+        assert!(WaveformConfig::builder().markers(Some("missing.cue".into())).build().is_err(), "The file is loaded when built");
+        assert!(WaveformConfig::builder().max_packets(Some(1)).build().is_ok());
+        assert!(WaveformConfig::builder().max_packets(Some(0)).build().is_err(), "Not a single packet");
+        let video = || WaveformConfig::builder().video(Some(VideoFormat::Mp4));
+        assert_eq!(video().build().unwrap().output_extension(), "mp4");
+        assert!(video().width(1001).build().is_err(), "Video needs an even width");
+        assert!(video().balance_strip(Some(3)).build().is_err(), "Video needs an even height with the strip");
+        assert!(video().balance_strip(Some(4)).build().is_ok());
+        assert!(video().format(ImageFormat::Jpeg).build().is_err(), "Video is not an image format");
+        assert!(
+            WaveformConfig::builder().format(ImageFormat::Jpeg).tiles(Some(512)).build().is_err(),
+            "Tiles are PNG only"
+        );
+    }
+
+    #[test]
+    fn test_avif_feature() {
+        let avif = WaveformConfig::builder().format(ImageFormat::Avif).build();
+        #[cfg(feature = "avif")]
+        assert_eq!(avif.unwrap().output_extension(), "avif");
+        #[cfg(not(feature = "avif"))]
+        assert_eq!(
+            avif.err().unwrap().to_string(),
+            "Invalid argument: Writing AVIF images needs waver built with the avif feature"
+        );
+    }
+
+    #[test]
+    fn test_played_path() {
+        assert_eq!(played_path(Path::new("music/song.mp3.png")), Path::new("music/song.mp3.played.png"));
+        assert_eq!(played_path(Path::new("wave.qoi")), Path::new("wave.played.qoi"));
+        assert_eq!(played_path(Path::new("wave")), Path::new("wave.played"));
+        assert_eq!(checkerboard_path(Path::new("music/song.mp3.jpg")), Path::new("music/song.mp3.checker.png"));
+    }
+
+    // Test which existing images --verify-existing draws again
+    #[test]
+    fn test_verify_existing() {
+        let dir = tempfile::tempdir().unwrap();
+        let write_png = |name: &str, (width, height): (u32, u32)| {
+            let path = dir.path().join(name);
+            let mut encoder = png::Encoder::new(std::fs::File::create(&path).unwrap(), width, height);
+            encoder.set_color(png::ColorType::Rgba);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&vec![0; (width * height * 4) as usize]).unwrap();
+            writer.finish().unwrap();
+            path
+        };
+        let config = WaveformConfig::builder().width(64).height(16).verify_existing(true).build().unwrap();
+        let whole = write_png("whole.png", (64, 16));
+        assert!(!config.is_damaged(&whole));
+        assert!(config.is_damaged(&write_png("small.png", (32, 16))), "An image of another size is drawn again");
+
+        let cut_off = write_png("cut_off.png", (64, 16));
+        let bytes = std::fs::read(&cut_off).unwrap();
+        std::fs::write(&cut_off, &bytes[..bytes.len() - 20]).unwrap();
+        assert!(config.is_damaged(&cut_off));
+        assert!(config.is_damaged(&dir.path().join("missing.png")));
+
+        let unchecked = WaveformConfig::builder().width(64).height(16).build().unwrap();
+        assert!(!unchecked.is_damaged(&cut_off), "Without --verify-existing an image is kept as it is");
+        let jpeg = WaveformConfig::builder().format(ImageFormat::Jpeg).verify_existing(true).build().unwrap();
+        assert!(!jpeg.is_damaged(&cut_off), "Only PNG images are checked");
+    }
+}
@@ -0,0 +1,195 @@
+/// Checking the decoded audio against a reference decoder (`--cross-check`).
+///
+/// A codec whose symphonia output differs from other decoders draws a wrong
+/// waveform without any error.  To find such files while a library is
+/// generated, a sample of the files is decoded again with the reference
+/// decoder, both are reduced to the column peaks of the image and the
+/// peaks compared.  Like `waver compare` the two are aligned first, as
+/// decoders differ in how much of the encoder delay they remove.
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::audio::decode_mono;
+use crate::compare::{align, overlap, overlay_peaks, similarity};
+use crate::error::{Result, WaverError};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// How far apart (on the 0.0 to 1.0 scale) the column peaks of the two
+/// decoders may be.  A few percent is a pixel or two of the default height,
+/// far more than the rounding of different decoders yet less than any
+/// difference that can be heard.
+pub const PEAK_TOLERANCE: f32 = 0.05;
+
+/// The external decoder the audio is checked against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReferenceDecoder {
+    /// `ffmpeg` on the PATH
+    Ffmpeg,
+}
+
+impl ReferenceDecoder {
+    /// Returns the name of the decoder.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReferenceDecoder::Ffmpeg => "ffmpeg",
+        }
+    }
+
+    /// Decodes the audio file into mono samples (the average of the first
+    /// two of its `channels`, as `decode_mono` mixes them).
+    pub fn decode_mono(&self, input_path: &Path, channels: usize) -> Result<Vec<f32>> {
+        match self {
+            ReferenceDecoder::Ffmpeg => decode_ffmpeg(input_path, channels),
+        }
+    }
+}
+
+impl FromStr for ReferenceDecoder {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "ffmpeg" => Ok(ReferenceDecoder::Ffmpeg),
+            _ => Err(WaverError::argument_error("The reference decoder must be ffmpeg")),
+        }
+    }
+}
+
+/// How the column peaks of waver and the reference decoder compare.
+#[derive(Debug, Clone, Serialize)]
+pub struct CrossCheck {
+    /// Name of the reference decoder
+    pub decoder: &'static str,
+    /// How many frames the reference output is delayed against waver's
+    pub offset_frames: i64,
+    /// Correlation of the aligned samples (1.0 for identical)
+    pub correlation: f64,
+    /// Largest difference between the column peaks
+    pub max_peak_difference: f32,
+    /// Columns whose peaks differ by more than the tolerance
+    pub mismatched_columns: u32,
+    /// Number of columns compared
+    pub columns: u32,
+}
+
+impl CrossCheck {
+    /// Returns true if every column peak is within the tolerance.
+    pub fn is_match(&self) -> bool {
+        self.mismatched_columns == 0
+    }
+
+    /// Returns the warning for a file whose decoders differ.
+    pub fn discrepancy(&self) -> Option<String> {
+        (!self.is_match()).then(|| {
+            format!(
+                "The audio decoded by {} differs in {} of {} columns (peaks up to {:.3} apart, correlation {:.4})",
+                self.decoder, self.mismatched_columns, self.columns, self.max_peak_difference, self.correlation
+            )
+        })
+    }
+}
+
+/// Returns true if the file is one of every `every` files to check.
+///
+/// The files are picked by a hash of their path rather than by their place
+/// in the batch, so the same files are picked on every run.
+pub fn is_sampled(input_path: &Path, every: u32) -> bool {
+    if every <= 1 {
+        return true;
+    }
+    let mut hasher = DefaultHasher::new();
+    input_path.hash(&mut hasher);
+    hasher.finish().is_multiple_of(every as u64)
+}
+
+/// Compares the mono samples of waver and the reference decoder as column
+/// peaks of an image `width` pixels wide, after aligning them.
+pub fn compare_peaks(
+    ours: &[f32],
+    reference: &[f32],
+    sample_rate: u32,
+    width: u32,
+    decoder: ReferenceDecoder,
+) -> CrossCheck {
+    let offset = align(ours, reference, sample_rate as i64);
+    let (first, second) = overlap(ours, reference, offset);
+    let (correlation, _) = similarity(first, second);
+
+    let peaks = overlay_peaks(first, second, width);
+    let differences: Vec<f32> = peaks.columns.iter().map(|column| (column.left - column.right).abs()).collect();
+    CrossCheck {
+        decoder: decoder.name(),
+        offset_frames: offset,
+        correlation,
+        max_peak_difference: differences.iter().copied().fold(0.0, f32::max),
+        mismatched_columns: differences.iter().filter(|&&difference| difference > PEAK_TOLERANCE).count() as u32,
+        columns: width,
+    }
+}
+
+/// Decodes the file with waver and the reference decoder and compares the
+/// column peaks of an image `width` pixels wide.
+///
+/// This is always the whole file, as its samples are kept in memory, which
+/// is why only a sample of the files is checked.
+pub fn cross_check(input_path: &Path, decoder: ReferenceDecoder, width: u32) -> Result<CrossCheck> {
+    let (ours, info) = decode_mono(input_path)?;
+    let reference = decoder.decode_mono(input_path, info.channels)?;
+    Ok(compare_peaks(&ours, &reference, info.sample_rate, width, decoder))
+}
+
+/// Decodes the file with ffmpeg into raw 32-bit float samples, keeping the
+/// channels and the sample rate of the file, and mixes them to mono.
+fn decode_ffmpeg(input_path: &Path, channels: usize) -> Result<Vec<f32>> {
+    let mut child = Command::new("ffmpeg")
+        .args(["-nostdin", "-v", "error", "-i"])
+        .arg(input_path)
+        .args(["-f", "f32le", "-c:a", "pcm_f32le", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => WaverError::generation_error("The cross-check needs ffmpeg on the PATH"),
+            _ => WaverError::IoError(err),
+        })?;
+
+    let mut bytes = Vec::new();
+    child.stdout.take().expect("stdout is piped").read_to_end(&mut bytes)?;
+    if !child.wait()?.success() {
+        return Err(WaverError::generation_error(format!(
+            "ffmpeg could not decode '{}'",
+            input_path.display()
+        )));
+    }
+    Ok(mix_interleaved(&bytes, channels))
+}
+
+/// Mixes raw little endian interleaved `f32` frames of `channels` channels
+/// to mono, the average of the first two channels.
+pub fn mix_interleaved(bytes: &[u8], channels: usize) -> Vec<f32> {
+    let channels = channels.max(1);
+    let frame_bytes = channels * 4;
+    let sample = |frame: &[u8], channel: usize| {
+        let at = channel * 4;
+        f32::from_le_bytes([frame[at], frame[at + 1], frame[at + 2], frame[at + 3]])
+    };
+    bytes
+        .chunks_exact(frame_bytes)
+        .map(|frame| {
+            if channels > 1 {
+                (sample(frame, 0) + sample(frame, 1)) * 0.5
+            } else {
+                sample(frame, 0)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::str::FromStr;
+
+    use crate::cross_check::{compare_peaks, is_sampled, mix_interleaved, ReferenceDecoder};
+
+    /// A tone that fades in, with a click so the alignment is unambiguous.
+    fn signal(len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                let tone = 0.6 * (i as f32 / len as f32) * (i as f32 * 0.05).sin();
+                if i == len / 3 { 0.95 } else { tone }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reference_decoder() {
+        assert_eq!(ReferenceDecoder::from_str(" FFmpeg").unwrap(), ReferenceDecoder::Ffmpeg);
+        let result = ReferenceDecoder::from_str("gstreamer");
+        assert_eq!(result.unwrap_err().to_string(), "Invalid argument: The reference decoder must be ffmpeg");
+    }
+
+    #[test]
+    fn test_same_audio_matches() {
+        // The reference keeps less of the encoder delay
+        let ours = signal(40_000);
+        let reference = ours[1105..].to_vec();
+        let check = compare_peaks(&ours, &reference, 8000, 64, ReferenceDecoder::Ffmpeg);
+        assert_eq!(check.offset_frames, -1105);
+        assert!(check.is_match(), "{check:?}");
+        assert_eq!(check.discrepancy(), None);
+    }
+
+    #[test]
+    fn test_different_audio_is_flagged() {
+        let ours = signal(40_000);
+        let mut reference = ours.clone();
+        for sample in &mut reference[30_000..] {
+            *sample *= 0.5;
+        }
+        let check = compare_peaks(&ours, &reference, 8000, 64, ReferenceDecoder::Ffmpeg);
+        assert!(!check.is_match());
+        assert_eq!(check.mismatched_columns, 16, "The last quarter of the columns");
+        assert!(check.max_peak_difference > 0.2);
+        assert!(check.discrepancy().unwrap().starts_with("The audio decoded by ffmpeg differs in 16 of 64 columns"));
+    }
+
+    #[test]
+    fn test_mix_interleaved() {
+        let bytes: Vec<u8> = [0.5f32, -0.25, 1.0, 0.0, 0.75, 0.25]
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+        assert_eq!(mix_interleaved(&bytes, 2), [0.125, 0.5, 0.5]);
+        assert_eq!(mix_interleaved(&bytes, 3), [0.125, 0.375], "Only the first two channels");
+        assert_eq!(mix_interleaved(&bytes, 1).len(), 6);
+        assert_eq!(mix_interleaved(&bytes[..7], 1).len(), 1, "A partial frame is dropped");
+    }
+
+    #[test]
+    fn test_sampling_is_stable() {
+        let paths: Vec<String> = (0..1000).map(|i| format!("music/{i}.mp3")).collect();
+        let picked = paths.iter().filter(|path| is_sampled(Path::new(path), 10)).count();
+        assert!((60..140).contains(&picked), "About one in ten, not {picked}");
+        for path in &paths {
+            assert_eq!(is_sampled(Path::new(path), 10), is_sampled(Path::new(path), 10));
+            assert!(is_sampled(Path::new(path), 1));
+        }
+    }
+}
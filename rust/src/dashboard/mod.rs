@@ -0,0 +1,205 @@
+/// The live dashboard of a batch run (`--tui`).
+///
+/// A run over a large library prints a wall of lines from the parallel
+/// workers, or nothing at all with `--quiet`.  The dashboard shows what each
+/// worker is on, how many files are done, the files finished per second and
+/// the last errors, and lets the run be paused or the files not started yet
+/// be skipped.
+///
+/// `Dashboard` holds the state, which the workers update as they start and
+/// finish files.  Drawing it in the terminal (in `tui`) needs waver built
+/// with the `tui` feature, which brings in ratatui.
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::Result;
+use crate::progress::estimate_remaining;
+
+#[cfg(feature = "tui")]
+pub mod tui;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// Whether this build of waver can show the dashboard.
+pub const AVAILABLE: bool = cfg!(feature = "tui");
+
+/// Number of recent errors the dashboard keeps.
+pub const RECENT_ERRORS: usize = 8;
+
+/// Number of seconds of throughput the dashboard keeps.
+pub const THROUGHPUT_SECONDS: usize = 120;
+
+/// How often a paused worker looks whether the run went on.
+const PAUSE_POLL: Duration = Duration::from_millis(100);
+
+/// The state of the dashboard, behind the lock.
+#[derive(Debug)]
+struct State {
+    /// The file each worker is on and when it started it
+    workers: Vec<Option<(PathBuf, Instant)>>,
+    done: usize,
+    failed: usize,
+    done_weight: u64,
+    /// The files finished in each second of the run
+    finished_per_second: Vec<u64>,
+    /// The last errors, oldest first
+    errors: VecDeque<String>,
+}
+
+/// What the dashboard shows at one moment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    /// The file each worker is on and for how long, if any
+    pub workers: Vec<Option<(String, Duration)>>,
+    pub files: usize,
+    pub done: usize,
+    pub failed: usize,
+    /// Share of the batch done, weighted by file size (0.0 to 1.0)
+    pub ratio: f64,
+    pub elapsed: Duration,
+    pub remaining: Option<Duration>,
+    /// The files finished in each of the last seconds, oldest first
+    pub throughput: Vec<u64>,
+    /// The last errors, oldest first
+    pub errors: Vec<String>,
+    pub paused: bool,
+}
+
+/// The live state of a batch run.
+#[derive(Debug)]
+pub struct Dashboard {
+    start: Instant,
+    files: usize,
+    total_weight: u64,
+    state: Mutex<State>,
+    paused: AtomicBool,
+}
+
+impl Dashboard {
+    /// Starts the dashboard of a batch of files with the given weights (see
+    /// `progress::file_weight`) on `workers` threads.
+    pub fn new(workers: usize, weights: impl IntoIterator<Item = u64>) -> Self {
+        let (files, total_weight) = weights
+            .into_iter()
+            .fold((0, 0), |(files, total), weight| (files + 1, total + weight));
+        Self {
+            start: Instant::now(),
+            files,
+            total_weight,
+            state: Mutex::new(State {
+                workers: vec![None; workers.max(1)],
+                done: 0,
+                failed: 0,
+                done_weight: 0,
+                finished_per_second: Vec::new(),
+                errors: VecDeque::with_capacity(RECENT_ERRORS),
+            }),
+            paused: AtomicBool::new(false),
+        }
+    }
+
+    /// Records that a worker started on a file.
+    ///
+    /// Workers beyond those the dashboard was started with are not shown.
+    pub fn start_file(&self, worker: usize, path: &Path) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(slot) = state.workers.get_mut(worker) {
+            *slot = Some((path.to_path_buf(), Instant::now()));
+        }
+    }
+
+    /// Records that a worker finished its file of the given weight, with
+    /// the error the file failed with, if any.
+    pub fn finish_file(&self, worker: usize, weight: u64, error: Option<String>) {
+        let second = self.start.elapsed().as_secs() as usize;
+        let mut state = self.state.lock().unwrap();
+        if let Some(slot) = state.workers.get_mut(worker) {
+            *slot = None;
+        }
+        state.done += 1;
+        state.done_weight += weight;
+        if state.finished_per_second.len() <= second {
+            state.finished_per_second.resize(second + 1, 0);
+        }
+        state.finished_per_second[second] += 1;
+        if let Some(error) = error {
+            state.failed += 1;
+            if state.errors.len() == RECENT_ERRORS {
+                state.errors.pop_front();
+            }
+            state.errors.push_back(error);
+        }
+    }
+
+    /// Pauses the run, or lets it go on, and returns whether it is paused.
+    ///
+    /// A paused run finishes the files it is on but starts no others.
+    pub fn toggle_pause(&self) -> bool {
+        !self.paused.fetch_xor(true, Ordering::Relaxed)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Waits while the run is paused, unless it is `stopped`.
+    pub fn wait_while_paused(&self, stopped: &AtomicBool) {
+        while self.is_paused() && !stopped.load(Ordering::Relaxed) {
+            thread::sleep(PAUSE_POLL);
+        }
+    }
+
+    /// Shows the dashboard in the terminal until the batch is `finished`,
+    /// setting `stopped` when the files not started yet are to be skipped.
+    pub fn show(&self, stopped: &AtomicBool, finished: &AtomicBool) -> Result<()> {
+        #[cfg(feature = "tui")]
+        {
+            tui::run(self, stopped, finished)
+        }
+
+        #[cfg(not(feature = "tui"))]
+        {
+            let _ = (stopped, finished);
+            Err(crate::error::WaverError::argument_error(
+                "The dashboard needs waver built with the tui feature",
+            ))
+        }
+    }
+
+    /// Returns what the dashboard shows now.
+    pub fn snapshot(&self) -> Snapshot {
+        let now = Instant::now();
+        let elapsed = now - self.start;
+        let state = self.state.lock().unwrap();
+
+        // Up to the current second, which is still filling up
+        let seconds = elapsed.as_secs() as usize + 1;
+        let first = seconds.saturating_sub(THROUGHPUT_SECONDS);
+        let throughput = (first..seconds)
+            .map(|second| state.finished_per_second.get(second).copied().unwrap_or(0))
+            .collect();
+
+        Snapshot {
+            workers: state
+                .workers
+                .iter()
+                .map(|worker| worker.as_ref().map(|(path, started)| (path.display().to_string(), now - *started)))
+                .collect(),
+            files: self.files,
+            done: state.done,
+            failed: state.failed,
+            ratio: state.done_weight as f64 / self.total_weight.max(1) as f64,
+            elapsed,
+            remaining: estimate_remaining(elapsed, state.done_weight, self.total_weight),
+            throughput,
+            errors: state.errors.iter().cloned().collect(),
+            paused: self.is_paused(),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::atomic::AtomicBool;
+
+    use crate::dashboard::{Dashboard, RECENT_ERRORS};
+
+    #[test]
+    fn test_workers() {
+        let dashboard = Dashboard::new(2, [100, 300]);
+        dashboard.start_file(1, Path::new("b.flac"));
+        // Workers the dashboard was not started with are ignored
+        dashboard.start_file(5, Path::new("c.flac"));
+
+        let snapshot = dashboard.snapshot();
+        assert_eq!(snapshot.workers.len(), 2);
+        assert_eq!(snapshot.workers[0], None);
+        assert_eq!(snapshot.workers[1].as_ref().map(|(path, _)| path.as_str()), Some("b.flac"));
+        assert_eq!((snapshot.files, snapshot.done, snapshot.failed), (2, 0, 0));
+        assert_eq!(snapshot.remaining, None, "Nothing to base the estimate on yet");
+
+        dashboard.finish_file(1, 300, None);
+        let snapshot = dashboard.snapshot();
+        assert_eq!(snapshot.workers[1], None);
+        assert_eq!(snapshot.done, 1);
+        assert_eq!(snapshot.ratio, 0.75, "Weighted by file size");
+        assert!(snapshot.remaining.is_some());
+        assert_eq!(snapshot.throughput.iter().sum::<u64>(), 1);
+    }
+
+    #[test]
+    fn test_recent_errors() {
+        let dashboard = Dashboard::new(1, vec![1; 20]);
+        for file in 0..20 {
+            let error = (file % 2 == 0).then(|| format!("error {file}"));
+            dashboard.finish_file(0, 1, error);
+        }
+
+        let snapshot = dashboard.snapshot();
+        assert_eq!((snapshot.done, snapshot.failed), (20, 10));
+        assert_eq!(snapshot.errors.len(), RECENT_ERRORS);
+        assert_eq!(snapshot.errors.first().map(String::as_str), Some("error 4"), "The oldest are dropped");
+        assert_eq!(snapshot.errors.last().map(String::as_str), Some("error 18"));
+    }
+
+    #[test]
+    fn test_pause() {
+        let dashboard = Dashboard::new(1, [1]);
+        assert!(!dashboard.is_paused());
+        assert!(dashboard.toggle_pause());
+        assert!(dashboard.snapshot().paused);
+
+        // A stopped run does not wait
+        dashboard.wait_while_paused(&AtomicBool::new(true));
+        assert!(!dashboard.toggle_pause());
+        dashboard.wait_while_paused(&AtomicBool::new(false));
+    }
+
+    #[test]
+    fn test_empty_batch() {
+        let snapshot = Dashboard::new(0, []).snapshot();
+        assert_eq!(snapshot.workers.len(), 1, "At least one worker is shown");
+        assert_eq!(snapshot.ratio, 0.0);
+        assert_eq!(snapshot.throughput, vec![0]);
+    }
+}
@@ -0,0 +1,134 @@
+/// Drawing the dashboard in the terminal with ratatui.
+///
+/// The dashboard runs on its own thread next to the workers, redraws about
+/// five times a second and reads the keys in between:
+///
+/// - `p` pauses the run or lets it go on
+/// - `s`, `q`, Esc or Ctrl-C skips the files not started yet
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, List, Paragraph, Sparkline};
+use ratatui::Frame;
+
+use crate::audio::format_duration;
+use crate::dashboard::{Dashboard, Snapshot, RECENT_ERRORS};
+use crate::error::{Result, WaverError};
+
+/// How long the dashboard waits for a key before it is drawn again.
+const REDRAW: Duration = Duration::from_millis(200);
+
+/// Shows the dashboard until the batch is `finished`, setting `stopped`
+/// when the files not started yet are to be skipped.
+pub fn run(dashboard: &Dashboard, stopped: &AtomicBool, finished: &AtomicBool) -> Result<()> {
+    let mut terminal = ratatui::try_init().map_err(terminal_error)?;
+    let shown = show(&mut terminal, dashboard, stopped, finished);
+    ratatui::try_restore().map_err(terminal_error)?;
+    shown
+}
+
+fn show(
+    terminal: &mut ratatui::DefaultTerminal,
+    dashboard: &Dashboard,
+    stopped: &AtomicBool,
+    finished: &AtomicBool,
+) -> Result<()> {
+    while !finished.load(Ordering::Relaxed) {
+        let snapshot = dashboard.snapshot();
+        let skipping = stopped.load(Ordering::Relaxed);
+        terminal.draw(|frame| draw(frame, &snapshot, skipping)).map_err(terminal_error)?;
+
+        if !event::poll(REDRAW).map_err(terminal_error)? {
+            continue;
+        }
+        if let Event::Key(key) = event::read().map_err(terminal_error)? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('p') => {
+                    dashboard.toggle_pause();
+                }
+                KeyCode::Char('s') | KeyCode::Char('q') | KeyCode::Esc => {
+                    stopped.store(true, Ordering::Relaxed);
+                }
+                // The terminal does not send Ctrl-C as a signal while the
+                // dashboard reads the keys
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    stopped.store(true, Ordering::Relaxed);
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+fn draw(frame: &mut Frame, snapshot: &Snapshot, skipping: bool) {
+    let [overall, workers, throughput, errors, help] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(snapshot.workers.len() as u16 + 2),
+        Constraint::Min(5),
+        Constraint::Length(RECENT_ERRORS as u16 + 2),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let eta = snapshot
+        .remaining
+        .map(|left| format_duration(left.as_secs_f64()))
+        .unwrap_or_else(|| "-".to_string());
+    let state = if skipping {
+        " - skipping the rest"
+    } else if snapshot.paused {
+        " - paused"
+    } else {
+        ""
+    };
+    let title = format!(
+        " {}/{} files, {} failed, {} elapsed, ETA {eta}{state} ",
+        snapshot.done,
+        snapshot.files,
+        snapshot.failed,
+        format_duration(snapshot.elapsed.as_secs_f64())
+    );
+    frame.render_widget(
+        Gauge::default()
+            .block(Block::bordered().title(title))
+            .gauge_style(Style::new().fg(if snapshot.failed > 0 { Color::Yellow } else { Color::Green }))
+            .ratio(snapshot.ratio.clamp(0.0, 1.0)),
+        overall,
+    );
+
+    let lines = snapshot.workers.iter().enumerate().map(|(worker, file)| match file {
+        Some((path, time)) => format!("{:>3} {:>6} {path}", worker + 1, format_duration(time.as_secs_f64())),
+        None => format!("{:>3} {:>6} idle", worker + 1, ""),
+    });
+    frame.render_widget(List::new(lines).block(Block::bordered().title(" Workers ")), workers);
+
+    // As many of the last seconds as fit, the latest on the right
+    let columns = throughput.width.saturating_sub(2) as usize;
+    let seconds = &snapshot.throughput[snapshot.throughput.len().saturating_sub(columns)..];
+    let peak = seconds.iter().max().copied().unwrap_or(0);
+    frame.render_widget(
+        Sparkline::default()
+            .block(Block::bordered().title(format!(" Files per second (peak {peak}) ")))
+            .style(Style::new().fg(Color::Cyan))
+            .data(seconds),
+        throughput,
+    );
+
+    let lines = snapshot.errors.iter().map(|error| Line::styled(error.as_str(), Style::new().fg(Color::Red)));
+    frame.render_widget(List::new(lines).block(Block::bordered().title(" Recent errors ")), errors);
+
+    frame.render_widget(Paragraph::new(" p pause/resume   s skip the files not started yet"), help);
+}
+
+/// Returns the error for a terminal the dashboard could not use.
+fn terminal_error(error: std::io::Error) -> WaverError {
+    WaverError::generation_error(format!("Could not show the dashboard: {error}"))
+}
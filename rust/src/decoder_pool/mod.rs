@@ -0,0 +1,80 @@
+/// Per thread reuse of audio decoders across files.
+///
+/// The probe and codec registries of symphonia are already built once per
+/// process, but every file still got a freshly made decoder.  For libraries
+/// of many tiny clips that setup is a noticeable part of the total time, so
+/// each worker thread keeps a few decoders around and hands one out again
+/// (after a `reset()`) when the next file has the same codec parameters.
+use std::cell::RefCell;
+use std::mem::discriminant;
+
+use symphonia::core::codecs::{CodecParameters, Decoder, DecoderOptions};
+
+use crate::error::Result;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// How many idle decoders each thread keeps.
+const POOL_SIZE: usize = 4;
+
+thread_local! {
+    /// The idle decoders of this thread with the parameters they were made for.
+    static POOL: RefCell<Vec<(CodecParameters, Box<dyn Decoder>)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Returns true if a decoder made for `a` can decode a stream with the
+/// parameters `b`.
+///
+/// Everything the decoder is set up from has to match.  Only the length and
+/// start of the stream (and its checksum, which is not verified) may differ,
+/// as those are what make two files of the same format different.
+pub fn same_decoder_params(a: &CodecParameters, b: &CodecParameters) -> bool {
+    a.codec == b.codec
+        && a.sample_rate == b.sample_rate
+        && a.time_base == b.time_base
+        && a.sample_format.as_ref().map(discriminant) == b.sample_format.as_ref().map(discriminant)
+        && a.bits_per_sample == b.bits_per_sample
+        && a.bits_per_coded_sample == b.bits_per_coded_sample
+        && a.channels == b.channels
+        && a.channel_layout.as_ref().map(discriminant) == b.channel_layout.as_ref().map(discriminant)
+        && a.delay == b.delay
+        && a.padding == b.padding
+        && a.max_frames_per_packet == b.max_frames_per_packet
+        && a.packet_data_integrity == b.packet_data_integrity
+        && a.frames_per_block == b.frames_per_block
+        && a.extra_data == b.extra_data
+}
+
+/// Returns a decoder for the codec parameters, reusing an idle one of this
+/// thread if there is a matching one.
+pub fn take(params: &CodecParameters) -> Result<Box<dyn Decoder>> {
+    let reused = POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        let index = pool.iter().position(|(idle, _)| same_decoder_params(idle, params))?;
+        Some(pool.swap_remove(index).1)
+    });
+
+    match reused {
+        Some(mut decoder) => {
+            decoder.reset();
+            Ok(decoder)
+        }
+        None => Ok(symphonia::default::get_codecs().make(params, &DecoderOptions::default())?),
+    }
+}
+
+/// Returns a decoder that finished its stream to this thread's pool.
+///
+/// Only decoders that decoded their stream without error should be given
+/// back.  If the pool is full the decoder idle the longest is dropped.
+pub fn give_back(params: CodecParameters, decoder: Box<dyn Decoder>) {
+    POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        if pool.len() >= POOL_SIZE {
+            pool.remove(0);
+        }
+        pool.push((params, decoder));
+    });
+}
@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod tests {
+    use crate::decoder_pool::{give_back, same_decoder_params, take};
+    use symphonia::core::audio::{Channels, Layout};
+    use symphonia::core::codecs::{CodecParameters, CODEC_TYPE_FLAC, CODEC_TYPE_PCM_F32LE, CODEC_TYPE_PCM_S16LE};
+    use symphonia::core::sample::SampleFormat;
+
+    fn pcm(frames: u64) -> CodecParameters {
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_PCM_S16LE)
+            .with_sample_rate(44100)
+            .with_sample_format(SampleFormat::S16)
+            .with_bits_per_sample(16)
+            .with_bits_per_coded_sample(16)
+            .with_channels(Channels::FRONT_LEFT | Channels::FRONT_RIGHT)
+            .with_max_frames_per_packet(1152)
+            .with_n_frames(frames);
+        params
+    }
+
+    #[test]
+    fn test_same_params_ignore_length() {
+        assert!(same_decoder_params(&pcm(1000), &pcm(99_000)));
+
+        let mut start = pcm(1000);
+        start.with_start_ts(576);
+        assert!(same_decoder_params(&pcm(1000), &start));
+    }
+
+    #[test]
+    fn test_different_params() {
+        let base = pcm(1000);
+
+        let mut other = pcm(1000);
+        other.with_sample_rate(48000);
+        assert!(!same_decoder_params(&base, &other), "Sample rate");
+
+        let mut other = pcm(1000);
+        other.for_codec(CODEC_TYPE_FLAC);
+        assert!(!same_decoder_params(&base, &other), "Codec");
+
+        let mut other = pcm(1000);
+        other.with_channels(Channels::FRONT_LEFT);
+        assert!(!same_decoder_params(&base, &other), "Channels");
+
+        let mut other = pcm(1000);
+        other.with_sample_format(SampleFormat::S24);
+        assert!(!same_decoder_params(&base, &other), "Sample format");
+
+        let mut other = pcm(1000);
+        other.with_channel_layout(Layout::Stereo);
+        assert!(!same_decoder_params(&base, &other), "Layout");
+
+        let mut other = pcm(1000);
+        other.with_extra_data(vec![1, 2, 3].into_boxed_slice());
+        assert!(!same_decoder_params(&base, &other), "Extra data");
+    }
+
+    #[test]
+    fn test_take_and_give_back() {
+        // Tests run on their own threads so the pool starts out empty
+        let params = pcm(1000);
+        let decoder = take(&params).unwrap();
+        give_back(params.clone(), decoder);
+
+        // Another file with the same format gets a (reset) decoder back
+        let decoder = take(&pcm(5000)).unwrap();
+        assert_eq!(decoder.codec_params().codec, CODEC_TYPE_PCM_S16LE);
+
+        // A different format makes a new decoder
+        let mut float = pcm(1000);
+        float.for_codec(CODEC_TYPE_PCM_F32LE).with_sample_format(SampleFormat::F32).with_bits_per_sample(32);
+        let decoder = take(&float).unwrap();
+        assert_eq!(decoder.codec_params().codec, CODEC_TYPE_PCM_F32LE);
+    }
+}
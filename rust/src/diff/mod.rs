@@ -0,0 +1,156 @@
+/// Checking whether waveform images still show their audio (`waver diff`).
+///
+/// An image made with `--embed-metadata` carries the hash of the column
+/// peaks it was drawn from (see `Peaks::hash`).  Re-encoding the audio, or
+/// replacing it with another master, changes the peaks and so the hash,
+/// while retagging it does not.  `waver diff` compares the hashes of two
+/// images, or of images and the peaks their audio has now, so the stale
+/// images of a library can be found without looking at them:
+///
+/// ```text
+/// $ waver diff song.mp3.png --audio song.mp3
+/// song.mp3.png: stale, the peaks of song.mp3 changed
+/// ```
+///
+/// The peaks of the audio are those of an image as wide as the one checked,
+/// with the options of the run given after `--` (or in the environment), as
+/// the channels, the time range and the peak cache change them too.
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::error::{Result, WaverError};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The keyword of the PNG text chunk with the hash of the peaks.
+pub const PEAK_HASH_TEXT: &str = "Peak Hash";
+
+/// A waveform image and the hash of the peaks embedded in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddedImage {
+    /// Path of the image
+    pub path: String,
+    /// Width of the image in pixels (the number of columns)
+    pub width: u32,
+    /// The embedded hash of the peaks
+    pub peak_hash: String,
+}
+
+impl EmbeddedImage {
+    /// Reads the width and the peak hash of a PNG image.
+    pub fn read(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(|err| {
+            WaverError::argument_error(format!("Can not read the image '{}': {err}", path.display()))
+        })?;
+        Self::from_png(BufReader::new(file), &path.display().to_string())
+    }
+
+    /// Reads the width and the peak hash of the PNG image in `reader`.
+    pub fn from_png(reader: impl Read, path: &str) -> Result<Self> {
+        let not_png = |err| WaverError::argument_error(format!("'{path}' is not a PNG image ({err})"));
+        let reader = png::Decoder::new(reader).read_info().map_err(not_png)?;
+        let info = reader.info();
+        let peak_hash = info
+            .uncompressed_latin1_text
+            .iter()
+            .find(|chunk| chunk.keyword == PEAK_HASH_TEXT)
+            .map(|chunk| chunk.text.clone())
+            .ok_or_else(|| {
+                WaverError::argument_error(format!(
+                    "'{path}' has no peak hash, it needs to be made with --embed-metadata"
+                ))
+            })?;
+        Ok(Self { path: path.to_string(), width: info.width, peak_hash })
+    }
+}
+
+/// How an image compares with its audio.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImageCheck {
+    /// Path of the image
+    pub image: String,
+    /// The embedded hash of the peaks
+    pub peak_hash: String,
+    /// Whether the audio still has the peaks of the image (with `--audio`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub up_to_date: Option<bool>,
+}
+
+/// The result of `waver diff`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WaveformDiff {
+    /// Path of the audio file the images were checked against, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio: Option<String>,
+    /// Every image, in the order they were given
+    pub images: Vec<ImageCheck>,
+    /// Whether the two images have the same peaks (for two images)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub same_peaks: Option<bool>,
+}
+
+impl WaveformDiff {
+    /// Compares the images with each other (for two) and with the audio
+    /// (if given), whose peak hash at a width is returned by `audio_hash`.
+    pub fn new(
+        images: &[EmbeddedImage],
+        audio: Option<&Path>,
+        mut audio_hash: impl FnMut(&Path, u32) -> Result<String>,
+    ) -> Result<Self> {
+        if images.is_empty() || images.len() > 2 || (images.len() == 1 && audio.is_none()) {
+            return Err(WaverError::argument_error("Give two images to compare, or images and their --audio"));
+        }
+
+        // The audio is decoded once for every width
+        let mut hashes = HashMap::new();
+        let mut checks = Vec::with_capacity(images.len());
+        for image in images {
+            if let Some(audio) = audio.filter(|_| !hashes.contains_key(&image.width)) {
+                hashes.insert(image.width, audio_hash(audio, image.width)?);
+            }
+            let up_to_date = hashes.get(&image.width).map(|hash| *hash == image.peak_hash);
+            checks.push(ImageCheck { image: image.path.clone(), peak_hash: image.peak_hash.clone(), up_to_date });
+        }
+
+        let same_peaks = match images {
+            [old, new] => Some(old.width == new.width && old.peak_hash == new.peak_hash),
+            _ => None,
+        };
+        Ok(Self { audio: audio.map(|audio| audio.display().to_string()), images: checks, same_peaks })
+    }
+
+    /// Returns true if an image is stale or the two images differ.
+    pub fn differs(&self) -> bool {
+        self.same_peaks == Some(false) || self.images.iter().any(|check| check.up_to_date == Some(false))
+    }
+
+    /// Writes the result as pretty printed JSON.
+    pub fn write_json(&self, mut writer: impl Write) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    /// Writes the result as human readable lines.
+    pub fn write_text(&self, mut writer: impl Write) -> Result<()> {
+        let audio = self.audio.as_deref().unwrap_or_default();
+        for check in &self.images {
+            match check.up_to_date {
+                Some(true) => writeln!(writer, "{}: up to date with {audio}", check.image)?,
+                Some(false) => writeln!(writer, "{}: stale, the peaks of {audio} changed", check.image)?,
+                None => {}
+            }
+        }
+        if let (Some(same), [old, new]) = (self.same_peaks, self.images.as_slice()) {
+            let verdict = if same { "the same peaks" } else { "different peaks" };
+            writeln!(writer, "{} and {}: {verdict}", old.image, new.image)?;
+        }
+        Ok(())
+    }
+}
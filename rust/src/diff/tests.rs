@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::cli::{Height, Width};
+    use crate::color::Rgba;
+    use crate::diff::{EmbeddedImage, WaveformDiff, PEAK_HASH_TEXT};
+    use crate::error::WaverError;
+    use crate::image::WaveImage;
+
+    fn image(path: &str, width: u32, peak_hash: &str) -> EmbeddedImage {
+        EmbeddedImage { path: path.to_string(), width, peak_hash: peak_hash.to_string() }
+    }
+
+    fn png(text: &[(&str, String)]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let (background, color) = (Rgba::rgb(0, 0, 0), Rgba::rgb(0xff, 0xff, 0xff));
+        let image = WaveImage::new(Width::new(64).unwrap(), Height::new(8).unwrap());
+        image.write_png_with_text(&background, &color, &color, text, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_read_embedded_image() {
+        let bytes = png(&[("Software", "waver".to_string()), (PEAK_HASH_TEXT, "ab12".to_string())]);
+        assert_eq!(EmbeddedImage::from_png(bytes.as_slice(), "a.png").unwrap(), image("a.png", 64, "ab12"));
+
+        let error = EmbeddedImage::from_png(png(&[]).as_slice(), "b.png").unwrap_err();
+        assert!(error.to_string().contains("--embed-metadata"), "{error}");
+        assert!(EmbeddedImage::from_png(&b"GIF89a"[..], "c.gif").is_err());
+    }
+
+    #[test]
+    fn test_against_audio() {
+        let mut decoded = Vec::new();
+        let images = [image("old.png", 1024, "aa"), image("new.png", 1024, "bb"), image("wide.png", 4096, "cc")];
+        let diff = WaveformDiff::new(&images[..2], Some(Path::new("song.mp3")), |_, width| {
+            decoded.push(width);
+            Ok("bb".to_string())
+        })
+        .unwrap();
+        assert_eq!(decoded, vec![1024], "Decoded once for every width");
+        assert_eq!(diff.images[0].up_to_date, Some(false));
+        assert_eq!(diff.images[1].up_to_date, Some(true));
+        assert_eq!(diff.same_peaks, Some(false));
+        assert!(diff.differs());
+
+        let mut text = Vec::new();
+        diff.write_text(&mut text).unwrap();
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "old.png: stale, the peaks of song.mp3 changed\n\
+             new.png: up to date with song.mp3\n\
+             old.png and new.png: different peaks\n"
+        );
+
+        let diff = WaveformDiff::new(&images[2..], Some(Path::new("song.mp3")), |_, _| Ok("cc".to_string())).unwrap();
+        assert!(!diff.differs());
+        let failed = WaveformDiff::new(&images[2..], Some(Path::new("song.mp3")), |_, _| {
+            Err(WaverError::generation_error("Broken"))
+        });
+        assert!(failed.is_err());
+    }
+
+    #[test]
+    fn test_two_images() {
+        let unused = |_: &Path, _| -> crate::error::Result<String> { unreachable!("There is no audio") };
+        let same = WaveformDiff::new(&[image("a.png", 512, "aa"), image("b.png", 512, "aa")], None, unused).unwrap();
+        assert!(!same.differs());
+        let mut json = Vec::new();
+        same.write_json(&mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json["same_peaks"], true);
+        assert!(json.get("audio").is_none());
+        assert!(json["images"][0].get("up_to_date").is_none());
+
+        let wider = WaveformDiff::new(&[image("a.png", 512, "aa"), image("b.png", 1024, "aa")], None, unused).unwrap();
+        assert!(wider.differs(), "Other widths have other peaks");
+        assert!(WaveformDiff::new(&[image("a.png", 512, "aa")], None, unused).is_err(), "Nothing to compare with");
+    }
+}
@@ -14,11 +14,23 @@
 /// 5. **User-Friendly Messages**: Errors are formatted to be helpful to the user
 ///
 /// This approach makes errors easier to handle, debug, and report to users.
-use std::io;
+///
+/// The errors of the files of a batch are collected in a `Report`, which
+/// can be written as JSON for scripts that run waver with `--ignore-errors`,
+/// and an `ErrorBudget` decides how many of them a successful run may have.
+/// The `ExitStatus` tells the scripts whether some or all of them failed.
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+use serde::Serialize;
 use thiserror::Error;
 
+use crate::summary::RunId;
+
 /// Represents all possible errors that can occur in the waver application.
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum WaverError {
     /// Error when parsing or validating command line arguments.
     #[error("Invalid argument: {0}")]
@@ -28,6 +40,16 @@ pub enum WaverError {
     #[error("Waveform generation error: {0}")]
     GenerationError(String),
 
+    /// Error when a file has no audio track to draw, such as a video
+    /// without sound.  `--skip-non-audio` skips these files instead.
+    #[error("No audio track found in '{0}'")]
+    NoAudioTrack(String),
+
+    /// Error when a file is larger or longer than `--max-file-size` or
+    /// `--max-duration` allow, which skips it with a warning.
+    #[error("{0}")]
+    OverLimit(String),
+
     /// Error from the underlying IO operations.
     #[error("IO error: {0}")]
     IoError(#[from] io::Error),
@@ -39,6 +61,23 @@ pub enum WaverError {
     /// Error from the PNG encoding library.
     #[error("PNG encoding error: {0}")]
     PngError(#[from] png::EncodingError),
+
+    /// Error from the JPEG encoding library.
+    #[error("JPEG encoding error: {0}")]
+    JpegError(#[from] jpeg_encoder::EncodingError),
+
+    /// Error from the WebP encoding library.
+    #[error("WebP encoding error: {0}")]
+    WebpError(#[from] image_webp::EncodingError),
+
+    /// Error from the AVIF encoding library.
+    #[cfg(feature = "avif")]
+    #[error("AVIF encoding error: {0}")]
+    AvifError(#[from] ravif::Error),
+
+    /// Error from reading or writing JSON files.
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
 }
 
 /// Type alias for Result with WaverError.
@@ -58,4 +97,138 @@ impl WaverError {
     pub fn generation_error(msg: impl Into<String>) -> Self {
         WaverError::GenerationError(msg.into())
     }
-}
\ No newline at end of file
+
+    /// Create a new NoAudioTrack error for the file at `path`.
+    pub fn no_audio_track(path: &Path) -> Self {
+        WaverError::NoAudioTrack(path.display().to_string())
+    }
+
+    /// Returns true if the error is that the file has no audio track.
+    pub fn is_no_audio_track(&self) -> bool {
+        matches!(self, WaverError::NoAudioTrack(_))
+    }
+
+    /// Create a new OverLimit error with the limit the file is over.
+    pub fn over_limit(msg: impl Into<String>) -> Self {
+        WaverError::OverLimit(msg.into())
+    }
+
+    /// Returns true if the error is that the file is over a limit.
+    pub fn is_over_limit(&self) -> bool {
+        matches!(self, WaverError::OverLimit(_))
+    }
+}
+
+/// The exit status of waver, so a script can tell a run that had nothing
+/// to do from one where everything broke.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// Every file succeeded, or the failures were allowed or ignored
+    Success = 0,
+    /// The arguments were invalid, or waver could not run at all
+    ArgumentError = 1,
+    /// Some of the files failed
+    SomeFilesFailed = 2,
+    /// Every file of the batch failed
+    AllFilesFailed = 3,
+}
+
+impl ExitStatus {
+    /// Returns the exit code of the status.
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+}
+
+impl From<ExitStatus> for ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        ExitCode::from(status.code())
+    }
+}
+
+/// A file of a batch that could not be processed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileError {
+    /// Path of the audio file
+    pub path: String,
+    /// What went wrong
+    pub error: String,
+}
+
+/// How many files of a batch may fail while the run still succeeds.
+///
+/// With both limits the failures have to be within both.  A run without
+/// any limit has no budget: a single failure fails it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ErrorBudget {
+    /// Number of files that may fail
+    pub max_failures: Option<usize>,
+    /// Share of the files (0.0 to 1.0) that may fail
+    pub max_rate: Option<f64>,
+}
+
+impl ErrorBudget {
+    /// Returns true if `failures` out of `files` files is within the budget.
+    pub fn allows(&self, failures: usize, files: usize) -> bool {
+        if failures == 0 {
+            return true;
+        }
+        if self.max_failures.is_none() && self.max_rate.is_none() {
+            return false;
+        }
+        let within_count = self.max_failures.is_none_or(|max| failures <= max);
+        let within_rate = self
+            .max_rate
+            .is_none_or(|rate| failures as f64 <= rate * files as f64 + 1e-9);
+        within_count && within_rate
+    }
+}
+
+/// The errors of a batch of files.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Report {
+    /// Number of files in the batch
+    pub files: usize,
+    /// The ID of the run (`--run-id`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<RunId>,
+    /// The files that failed, in the order they failed
+    pub errors: Vec<FileError>,
+}
+
+impl Report {
+    /// Creates an empty report for a batch of `files` files.
+    pub fn new(files: usize) -> Self {
+        Self {
+            files,
+            run_id: None,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Records that the file at `path` failed.
+    pub fn push(&mut self, path: impl Into<String>, error: impl ToString) {
+        self.errors.push(FileError {
+            path: path.into(),
+            error: error.to_string(),
+        });
+    }
+
+    /// Returns true if no file failed.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Returns the number of files that failed.
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Writes the report as pretty printed JSON.
+    pub fn write_json(&self, mut writer: impl Write) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+}
+
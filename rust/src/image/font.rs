@@ -0,0 +1,53 @@
+//! A tiny built-in bitmap font for burning short labels into waveform images.
+//!
+//! Only the characters needed for time labels are included so there is no
+//! need for a font file or a text rendering dependency.  Each glyph is
+//! `GLYPH_WIDTH` x `GLYPH_HEIGHT` pixels, one byte per row with the leftmost
+//! pixel in bit 4.
+
+/// Width of each glyph in font pixels.
+pub const GLYPH_WIDTH: u32 = 5;
+
+/// Height of each glyph in font pixels.
+pub const GLYPH_HEIGHT: u32 = 7;
+
+/// Empty columns between two glyphs in font pixels.
+pub const GLYPH_SPACING: u32 = 1;
+
+/// Returns the rows of the glyph for the given character, if the font has it.
+pub fn glyph(c: char) -> Option<&'static [u8; GLYPH_HEIGHT as usize]> {
+    const DIGITS: [[u8; GLYPH_HEIGHT as usize]; 10] = [
+        [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+        [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+        [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+        [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+        [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+        [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+        [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+        [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+        [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+    ];
+    const COLON: [u8; GLYPH_HEIGHT as usize] =
+        [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000];
+
+    match c {
+        '0'..='9' => Some(&DIGITS[c as usize - '0' as usize]),
+        ':' => Some(&COLON),
+        _ => None,
+    }
+}
+
+/// Returns true if the glyph has its pixel at (`col`, `row`) set.
+pub fn glyph_pixel(rows: &[u8; GLYPH_HEIGHT as usize], col: u32, row: u32) -> bool {
+    (rows[row as usize] >> (GLYPH_WIDTH - 1 - col)) & 1 != 0
+}
+
+/// Returns the width in image pixels of the text drawn at the given scale.
+pub fn text_width(text: &str, scale: u32) -> u32 {
+    let count = text.chars().count() as u32;
+    if count == 0 {
+        return 0;
+    }
+    (count * (GLYPH_WIDTH + GLYPH_SPACING) - GLYPH_SPACING) * scale
+}
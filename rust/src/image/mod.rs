@@ -1,15 +1,20 @@
 /// Waveform image generation functionality with optimized 2-bit PNG output.
-use std::fs::File;
-use std::io::BufWriter;
+use std::io::Write;
 use std::path::Path;
 
 use png::{Encoder, FilterType};
 
-use crate::cli::{Height, Width};
+use crate::audit;
+use crate::backdrop::Backdrop;
+use crate::cli::{Corner, Height, MonoStyle, Overlap, Width};
 use crate::color::Rgba;
-use crate::error::Result;
+use crate::error::{Result, WaverError};
+
+mod font;
+mod qoi;
 
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests;
 
 /// Represents the different channel types in a waveform image.
@@ -55,75 +60,752 @@ pub struct WaveImage {
     /// Height of the image in pixels.
     height: u32,
 
-    /// Vertical center line position.
-    center: u32,
+    /// The band the (detail) waveform is drawn into.
+    wave: Section,
+
+    /// The band of the compressed overview waveform, if any.
+    overview: Option<Section>,
+
+    /// The bands the waveform is split into when each channel is drawn in
+    /// a lane of its own (empty otherwise).
+    lanes: Vec<Section>,
 
     /// Line size in bytes (due to 2 bits per pixel)
     line_width: u32,
 
+    /// Bits per pixel - 2 for the normal channel image, 4 when a balance
+    /// strip needs its color ramp and 8 when the columns are tinted with
+    /// the loudness color ramps.
+    depth: u8,
+
+    /// Number of rows at the bottom reserved for the balance strip.
+    strip_rows: u32,
+
+    /// Whether the bar ends are drawn with their fractional coverage using
+    /// the background to channel color ramps (8-bit only).
+    antialias: bool,
+
+    /// Whether the palette has the faded peak shades that are drawn behind
+    /// the RMS bars.
+    rms_layer: bool,
+
+    /// Where the bars of a single (mono) waveform are drawn from.
+    mono_style: MonoStyle,
+
+    /// What the pixels covered by both the left and the right bar are.
+    overlap: Overlap,
+
+    /// The row a single waveform was drawn from, if it was drawn from a
+    /// baseline at the top or bottom rather than around the center.
+    baseline: Option<u32>,
+
+    /// Color of the `AXIS` palette entry, if the axis was drawn.
+    axis_color: Option<Rgba>,
+
+    /// Number of stems drawn as layers of their own (0 for the channel
+    /// waveform).  The pixels then hold the bits of the stems covering them.
+    stems: u32,
+
+    /// Colors of the stems, which replace the channel colors in the palette
+    /// (see `set_stem_colors`).
+    stem_colors: Vec<Rgba>,
+
     /// Pixel data stored as channel indices.
     /// During image generation, we use 1 byte per pixel for simplicity.
     pixels: Vec<u8>,
 }
 
+/// A horizontal band of the image that a waveform is drawn into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Section {
+    /// First row of the band.
+    top: u32,
+    /// Row the waveform is centered on.
+    center: u32,
+    /// Row just past the end of the band.
+    bottom: u32,
+}
+
+impl Section {
+    /// Creates a band of `rows` rows starting at `top`.
+    ///
+    /// The rows should be even, an odd band leaves its last row unused.
+    fn new(top: u32, rows: u32) -> Self {
+        Self {
+            top,
+            center: top + rows / 2,
+            bottom: top + rows,
+        }
+    }
+
+    /// Returns the distance from the center to either edge of the band.
+    fn half(&self) -> u32 {
+        self.center - self.top
+    }
+
+    /// Returns the distance from the center to the top (`up`) or the bottom
+    /// edge, which is the whole band when the center is a baseline at the
+    /// other edge.
+    fn reach(&self, up: bool) -> u32 {
+        if up {
+            self.center - self.top
+        } else {
+            self.bottom - self.center
+        }
+    }
+}
+
+/// Layout and palette options for a `WaveImage`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ImageOptions {
+    /// Whether columns will be drawn with the loudness ramps.
+    pub tinted: bool,
+    /// Rows added at the bottom for the balance strip (0 for none).
+    pub balance_strip: u32,
+    /// Fraction of the waveform height given to a compressed overview
+    /// below the detail waveform, if any.
+    pub overview_ratio: Option<f32>,
+    /// Whether the bar ends are anti-aliased (can not be combined with `tinted`).
+    pub antialias: bool,
+    /// Number of lanes the waveform is split into, one per channel (0 or 1
+    /// for the single waveform).
+    pub lanes: u32,
+    /// Whether the RMS is drawn over the peaks, which adds a faded shade of
+    /// each channel color for the peaks (can not be combined with `tinted`
+    /// or `antialias`).
+    pub rms_layer: bool,
+    /// Where the bars of a single (mono) waveform are drawn from.
+    pub mono_style: MonoStyle,
+    /// Number of stems drawn as layers (or in lanes) of their own, up to
+    /// `MAX_STEMS` (0 for the channel waveform).  Can not be combined with
+    /// the other palettes.
+    pub stems: u32,
+    /// What the pixels covered by both the left and the right bar are.
+    pub overlap: Overlap,
+}
+
+/// The fewest rows a lane needs to draw a bar around its center.
+pub const MIN_LANE_ROWS: u32 = 2;
+
+/// The most stems one image can hold, as every combination of them needs
+/// a palette entry of the 8-bit image.
+pub const MAX_STEMS: usize = 8;
+
+/// How far each stem is blended over the stems below it where they
+/// overlap, so the covered ones still show through.
+const STEM_OPACITY: f32 = 0.5;
+
+/// Number of palette entries in each channel's loudness ramp.
+///
+/// The 8-bit tinted palette holds the 4 normal entries followed by the left
+/// channel ramp, the right channel ramp and then the balance ramp.
+pub const TINT_LEVELS: u8 = 120;
+
+/// Number of palette entries in the balance strip ramp.
+///
+/// This is odd so that a centered signal gets its own entry and small enough
+/// to fit after the 4 normal entries of a 4-bit image.
+pub const BALANCE_LEVELS: u8 = 11;
+
+/// Palette index of the first (darkest) left channel ramp entry.
+const LEFT_RAMP: u8 = 4;
+
+/// Palette index of the first (darkest) right channel ramp entry.
+const RIGHT_RAMP: u8 = LEFT_RAMP + TINT_LEVELS;
+
+/// Palette index of the center line and borders (see `draw_axis`).
+///
+/// This is the entry the overlay of two waveforms gets where they collide,
+/// which has the background color unless an axis color is set.  No other
+/// drawing produces it, so it is free for the axis at every depth.
+pub const AXIS: u8 = 3;
+
+/// Colors of the light and dark squares of the checkerboard (see
+/// `write_checkerboard_png`), the grays image editors show.
+const CHECKER_LIGHT: [u8; 3] = [0xff, 0xff, 0xff];
+const CHECKER_DARK: [u8; 3] = [0xcc, 0xcc, 0xcc];
+
+/// Brightness of the darkest ramp entry relative to the channel color.
+const RAMP_FLOOR: f32 = 0.25;
+
+/// Palette index of the faded left channel shade that the peaks are drawn
+/// in behind the RMS bars.  The right channel shade follows it and then the
+/// balance ramp, if any.
+const PEAK_SHADES: u8 = 4;
+
+/// How far the peak shades are blended from the channel color towards the
+/// background.
+const PEAK_SHADE_FADE: f32 = 0.5;
+
+/// Maps a loudness level in 0.0..=1.0 to the palette index within a ramp.
+fn ramp_index(ramp: u8, level: f32) -> u8 {
+    ramp + (level.clamp(0.0, 1.0) * (TINT_LEVELS - 1) as f32 + 0.5) as u8
+}
+
+/// Appends the RGB and alpha entries of a loudness ramp for a channel color.
+///
+/// Quiet levels are darkened towards black while the loudest level is the
+/// channel color itself.  Alpha is kept as given so transparency still works.
+fn push_ramp(palette: &mut Vec<u8>, transparent: &mut Vec<u8>, color: &Rgba) {
+    for level in 0..TINT_LEVELS {
+        let scale = RAMP_FLOOR + (1.0 - RAMP_FLOOR) * level as f32 / (TINT_LEVELS - 1) as f32;
+        palette.push((color.red as f32 * scale + 0.5) as u8);
+        palette.push((color.green as f32 * scale + 0.5) as u8);
+        palette.push((color.blue as f32 * scale + 0.5) as u8);
+        transparent.push(color.alpha);
+    }
+}
+
+/// Appends the RGB and alpha entries of a coverage ramp for a channel color.
+///
+/// The entries blend from the background (no coverage) to the channel color
+/// (full coverage), alpha included, so a partly covered pixel at the end of
+/// a bar looks like the edge of a smooth shape when the image is scaled.
+fn push_coverage_ramp(palette: &mut Vec<u8>, transparent: &mut Vec<u8>, background: &Rgba, color: &Rgba) {
+    for level in 0..TINT_LEVELS {
+        let t = level as f32 / (TINT_LEVELS - 1) as f32;
+        push_color(palette, transparent, &background.mix(color, t));
+    }
+}
+
+/// Appends the RGB and alpha entries of a single color.
+fn push_color(palette: &mut Vec<u8>, transparent: &mut Vec<u8>, color: &Rgba) {
+    palette.extend([color.red, color.green, color.blue]);
+    transparent.push(color.alpha);
+}
+
+/// Appends the RGB and alpha entries of the faded shade of a channel color
+/// that the peaks are drawn in behind the RMS bars.
+fn push_peak_shade(palette: &mut Vec<u8>, transparent: &mut Vec<u8>, background: &Rgba, color: &Rgba) {
+    push_color(palette, transparent, &color.mix(background, PEAK_SHADE_FADE));
+}
+
+/// Appends the entries of the balance ramp which runs from the left channel
+/// color (hard left) through an even mix of both (centered) to the right
+/// channel color (hard right).
+fn push_balance_ramp(palette: &mut Vec<u8>, transparent: &mut Vec<u8>, left: &Rgba, right: &Rgba) {
+    for level in 0..BALANCE_LEVELS {
+        let t = level as f32 / (BALANCE_LEVELS - 1) as f32;
+        push_color(palette, transparent, &left.mix(right, t));
+    }
+}
+
+/// Appends the RGB and alpha entries of every combination of stems, the
+/// palette index being the bits of the stems that cover the pixel.
+///
+/// Each stem is drawn over the ones before it with `STEM_OPACITY`, so the
+/// entry of a single stem is its own color.
+fn push_stem_layers(palette: &mut Vec<u8>, transparent: &mut Vec<u8>, stems: &[Rgba]) {
+    for mask in 1..1usize << stems.len() {
+        let mut covering = stems.iter().enumerate().filter(|(stem, _)| mask & (1 << stem) != 0).map(|(_, color)| color);
+        let first = *covering.next().unwrap_or(&stems[0]);
+        let color = covering.fold(first, |color, stem| color.mix(stem, STEM_OPACITY));
+        push_color(palette, transparent, &color);
+    }
+}
+
 /// Convert a color index to the bit location based on the x coordinate
 ///
+/// PNG packs the leftmost pixel into the high-order bits of each byte.
+///
 /// # Arguments
 ///
-/// # `color` - The 2-bit color
+/// # `color` - The color index
 /// # `x` - The horizontal position of the pixel
+/// # `depth` - The bits per pixel (2 or 4)
 ///
 /// # Returns
 ///
-/// The u8 with the color bits shifted to the correct location for 2-bpp
-fn draw_bits(color: u8, x: u32) -> u8 {
-    (color & 3) << (2 * (x & 3))
+/// The u8 with the color bits shifted to the correct location for the depth
+fn draw_bits(color: u8, x: u32, depth: u8) -> u8 {
+    let depth = depth as u32;
+    let per_byte = 8 / depth;
+    let mask = (1u8 << depth) - 1;
+    (color & mask) << (8 - depth * (x % per_byte + 1))
 }
 
 impl WaveImage {
-    /// Creates a new waveform 2-bit per pixel image with the specified
-    /// dimensions.  We render directly into the 2-bit per pixel form
-    /// to reduce memory footprint and because we can do it efficiently.
+    /// Creates a plain 2-bit per pixel image, like `with_options` with the
+    /// default options, for the tests.
+    #[cfg(test)]
+    pub fn new(width: Width, height: Height) -> Self {
+        Self::with_options(width, height, &ImageOptions::default())
+    }
+
+    /// Creates a new image with optional loudness tinting, overview and
+    /// balance strip.
     ///
-    /// # Arguments
+    /// The sections are stacked from top to bottom:
+    /// - The (detail) waveform
+    /// - The compressed overview waveform, sharing `height` with the detail
+    ///   waveform by `overview_ratio` (each is kept even and at least 2 rows)
+    /// - The balance strip, added to `height`
     ///
-    /// * `width` - Width of the image in pixels
-    /// * `height` - Height of the image in pixels (must be even)
+    /// The pixel depth is the smallest that holds the needed palette:
+    /// - 2-bit for the plain waveform
+    /// - 4-bit when a balance strip adds its ramp (see `BALANCE_LEVELS`)
+    ///   or the RMS layer adds the peak shades
+    /// - 8-bit when tinted or anti-aliased, as the loudness (or coverage)
+    ///   ramps need `TINT_LEVELS` entries per channel, or when the peak
+    ///   shades and the balance ramp do not fit 4 bits together.  This
+    ///   costs 4x the memory of the 2-bit image.
     ///
-    /// # Returns
+    /// Stems need an entry for every combination of them: 2-bit holds two
+    /// stems, 4-bit four and 8-bit up to `MAX_STEMS`.
+    ///
+    /// # Arguments
     ///
-    /// A new WaveImage instance initialized with background pixels
+    /// * `width` - Width of the image in pixels
+    /// * `height` - Height of the waveform area in pixels (must be even)
+    /// * `options` - The layout and palette options
     ///
     /// # Notes
     ///
-    /// This is a 2-bit per pixel image since we really only need
-    /// at most 4 colors:
+    /// We render directly into the packed form to reduce the memory
+    /// footprint.  The plain waveform only needs 4 colors:
     ///   0:  Background color
     ///   1:  Left Channel  (or mono)
     ///   2:  Right Channel
-    ///   3:  Background due to Left and Right collision
+    ///   3:  Center line (see `draw_axis`), or where the Left and Right
+    ///       bars overlap with `Overlap::Mark`
     ///
     /// ```
     /// use waver::cli::{Width, Height};
-    /// use waver::image::WaveImage;
+    /// use waver::image::{ImageOptions, WaveImage};
     ///
     /// // Create a 1024x128 waveform image
     /// let width = Width::new(1024).unwrap();
     /// let height = Height::new(128).unwrap();
-    /// let image = WaveImage::new(width, height);
+    /// let image = WaveImage::with_options(width, height, &ImageOptions::default());
     /// ```
-    pub fn new(width: Width, height: Height) -> Self {
+    pub fn with_options(width: Width, height: Height, options: &ImageOptions) -> Self {
+        let depth = if options.tinted || options.antialias || (options.rms_layer && options.balance_strip > 0) || options.stems > 4 {
+            8
+        } else if options.balance_strip > 0 || options.rms_layer || options.stems > 2 {
+            4
+        } else {
+            2
+        };
         let width_val = width.value();
-        let line_val = (width_val + 3) >> 2;
-        let height_val = height.value();
+        let per_byte = 8 / depth as u32;
+        let line_val = width_val.div_ceil(per_byte);
+
+        // Split the waveform height between detail and overview
+        let wave_rows = height.value();
+        // A micro image may be too low to give the overview any rows at all
+        let overview_rows = options.overview_ratio.and_then(|ratio| {
+            let rows = ((wave_rows as f32 * ratio / 2.0).round() as u32 * 2).max(2);
+            Some(rows.min(wave_rows - 2)).filter(|&rows| rows > 0)
+        });
+        let wave = Section::new(0, wave_rows - overview_rows.unwrap_or(0));
+        let overview = overview_rows.map(|rows| Section::new(wave.bottom, rows));
+
+        // The lanes share the waveform rows as evenly as possible
+        let lane_count = options.lanes.max(1);
+        let wave_height = wave.bottom - wave.top;
+        let lane_top = |lane: u32| wave.top + wave_height * lane / lane_count;
+        let lanes = if lane_count > 1 {
+            (0..lane_count)
+                .map(|lane| Section::new(lane_top(lane), lane_top(lane + 1) - lane_top(lane)))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let height_val = wave_rows + options.balance_strip;
 
         Self {
             width: width_val,
             height: height_val,
             line_width: line_val,
-            center: height.center(),
-            pixels: vec![0 as u8; (line_val * height_val) as usize],
+            wave,
+            overview,
+            lanes,
+            depth,
+            strip_rows: options.balance_strip,
+            antialias: options.antialias,
+            rms_layer: options.rms_layer,
+            mono_style: options.mono_style,
+            overlap: options.overlap,
+            baseline: None,
+            axis_color: None,
+            stems: options.stems,
+            stem_colors: Vec::new(),
+            pixels: vec![0_u8; (line_val * height_val) as usize],
+        }
+    }
+
+    /// Clears all pixels back to the background so the image can be drawn
+    /// again (see `image_pool`).
+    pub fn clear(&mut self) {
+        self.pixels.fill(0);
+        self.baseline = None;
+        self.axis_color = None;
+        self.stem_colors.clear();
+    }
+
+    /// Returns true if this image holds the 8-bit loudness tint palette.
+    pub fn is_tinted(&self) -> bool {
+        self.depth == 8 && !self.antialias && !self.rms_layer
+    }
+
+    /// Returns true if this image holds the 8-bit coverage ramps for
+    /// anti-aliased bar ends.
+    pub fn is_antialiased(&self) -> bool {
+        self.antialias
+    }
+
+    /// Returns the first row of the balance strip.
+    fn strip_top(&self) -> u32 {
+        self.height - self.strip_rows
+    }
+
+    /// Returns the palette index of the first (hard left) balance ramp entry.
+    fn balance_ramp(&self) -> u8 {
+        if self.rms_layer {
+            PEAK_SHADES + 2
+        } else if self.depth == 8 {
+            RIGHT_RAMP + TINT_LEVELS
+        } else {
+            4
+        }
+    }
+
+    /// Fills the rows `y_start..y_end` of column `x` with the palette index.
+    ///
+    /// In the packed 2-bit and 4-bit forms the index is OR-ed into the
+    /// existing bits, in the 8-bit form it replaces the pixel unless the
+    /// image holds stems, whose bits are always combined.  Where a left or
+    /// right bar covers a pixel of the other channel the `Overlap` decides
+    /// the pixel instead (for `Overlap::Mark` the packed bits already OR
+    /// into the `AXIS` index).  The caller must make sure `x` and `y_end`
+    /// are in bounds.
+    fn fill_column(&mut self, x: u32, y_start: u32, y_end: u32, index: u8) {
+        let channel = matches!(Channel::from(index), Channel::Left | Channel::Right);
+        if channel && self.stems == 0 && (self.overlap != Overlap::Mark || self.depth == 8) {
+            let other = index ^ 3;
+            for y in y_start..y_end {
+                match (self.index(x, y) == other, self.overlap) {
+                    (false, _) => self.fill_pixel(x, y, index),
+                    (true, Overlap::Mark) => self.set_pixel(x, y, AXIS),
+                    (true, Overlap::KeepLeft) if index == Channel::Left as u8 => self.set_pixel(x, y, index),
+                    (true, Overlap::KeepRight) if index == Channel::Right as u8 => self.set_pixel(x, y, index),
+                    (true, _) => {}
+                }
+            }
+            return;
+        }
+
+        if self.depth == 8 {
+            for y in y_start..y_end {
+                let idx = (x + y * self.line_width) as usize;
+                if self.stems > 0 {
+                    self.pixels[idx] |= index;
+                } else {
+                    self.pixels[idx] = index;
+                }
+            }
+        } else {
+            // The byte offset where the packed pixel will be
+            let offset = x / (8 / self.depth as u32);
+            let draw = draw_bits(index, x, self.depth);
+            for y in y_start..y_end {
+                let idx = (offset + y * self.line_width) as usize;
+                self.pixels[idx] |= draw;
+            }
+        }
+    }
+
+    /// Fills the pixel at (`x`, `y`) like `fill_column`, without looking
+    /// at the overlap.
+    fn fill_pixel(&mut self, x: u32, y: u32, index: u8) {
+        if self.depth == 8 {
+            self.pixels[(x + y * self.line_width) as usize] = index;
+        } else {
+            let idx = (x / (8 / self.depth as u32) + y * self.line_width) as usize;
+            self.pixels[idx] |= draw_bits(index, x, self.depth);
+        }
+    }
+
+    /// Returns the palette index of the pixel at (`x`, `y`), which must be
+    /// in bounds.
+    fn index(&self, x: u32, y: u32) -> u8 {
+        if self.depth == 8 {
+            return self.pixels[(x + y * self.line_width) as usize];
         }
+        let depth = self.depth as u32;
+        let per_byte = 8 / depth;
+        let byte = self.pixels[(x / per_byte + y * self.line_width) as usize];
+        (byte >> (8 - depth * (x % per_byte + 1))) & ((1 << depth) - 1)
+    }
+
+    /// Sets the pixel at (`x`, `y`) to the palette index, replacing what was there.
+    ///
+    /// Unlike the column drawing this does not OR the bits, so it can be used
+    /// to clear parts of the waveform.  Out of bounds pixels are ignored.
+    pub fn set_pixel(&mut self, x: u32, y: u32, index: u8) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+
+        if self.depth == 8 {
+            self.pixels[(x + y * self.line_width) as usize] = index;
+        } else {
+            let idx = (x / (8 / self.depth as u32) + y * self.line_width) as usize;
+            let mask = draw_bits(u8::MAX, x, self.depth);
+            self.pixels[idx] = (self.pixels[idx] & !mask) | draw_bits(index, x, self.depth);
+        }
+    }
+
+    /// Sets the pixels `x_start..x_end` of row `y` to the palette index,
+    /// replacing what was there.
+    ///
+    /// In the packed forms the bytes that are wholly inside the range are
+    /// filled at once and only the pixels sharing a byte with pixels outside
+    /// of it are set one by one.  The caller must make sure `y` is in bounds
+    /// and `x_start < x_end <= width`.
+    fn fill_row(&mut self, y: u32, x_start: u32, x_end: u32, index: u8) {
+        let row = (y * self.line_width) as usize;
+        if self.depth == 8 {
+            self.pixels[row + x_start as usize..row + x_end as usize].fill(index);
+            return;
+        }
+
+        let per_byte = 8 / self.depth as u32;
+        let first = x_start.div_ceil(per_byte);
+        let last = x_end / per_byte;
+        if first >= last {
+            for x in x_start..x_end {
+                self.set_pixel(x, y, index);
+            }
+            return;
+        }
+        let pattern = (0..per_byte).fold(0, |byte, x| byte | draw_bits(index, x, self.depth));
+        self.pixels[row + first as usize..row + last as usize].fill(pattern);
+        for x in (x_start..first * per_byte).chain(last * per_byte..x_end) {
+            self.set_pixel(x, y, index);
+        }
+    }
+
+    /// Draws a horizontal line over the pixels `x_start..x_end` of row `y`
+    /// with the palette index, clipped to the image.
+    pub fn draw_hline(&mut self, x_start: u32, x_end: u32, y: u32, index: u8) {
+        let x_end = x_end.min(self.width);
+        if y < self.height && x_start < x_end {
+            self.fill_row(y, x_start, x_end, index);
+        }
+    }
+
+    /// Draws a vertical line over the rows `y_start..y_end` of column `x`
+    /// with the palette index, clipped to the image.
+    ///
+    /// Unlike the waveform columns this replaces the pixels rather than
+    /// OR-ing the bits, like `set_pixel`.
+    pub fn draw_vline(&mut self, x: u32, y_start: u32, y_end: u32, index: u8) {
+        for y in y_start..y_end.min(self.height) {
+            self.set_pixel(x, y, index);
+        }
+    }
+
+    /// Fills the rectangle `x_start..x_end` by `y_start..y_end` with the
+    /// palette index, clipped to the image.
+    pub fn fill_rect(&mut self, x_start: u32, y_start: u32, x_end: u32, y_end: u32, index: u8) {
+        for y in y_start..y_end.min(self.height) {
+            self.draw_hline(x_start, x_end, y, index);
+        }
+    }
+
+    /// Draws the center line of the waveform (of every lane and of the
+    /// overview, if any) and with `borders` also its top and bottom rows,
+    /// in the given color.
+    ///
+    /// The lines are drawn behind the waveform: only the background pixels
+    /// of the rows are set to `AXIS`, so this is called after the columns
+    /// are drawn and the line only shows where the audio is quiet.
+    pub fn draw_axis(&mut self, color: &Rgba, borders: bool) {
+        self.axis_color = Some(*color);
+
+        let mut rows: Vec<u32> = if self.lanes.is_empty() {
+            vec![self.baseline.unwrap_or(self.wave.center)]
+        } else {
+            self.lanes.iter().map(|lane| lane.center).collect()
+        };
+        rows.extend(self.overview.map(|overview| overview.center));
+        if borders {
+            rows.extend([self.wave.top, self.wave.bottom - 1]);
+        }
+        for y in rows {
+            for x in 0..self.width {
+                if self.index(x, y) == Channel::Background as u8 {
+                    self.set_pixel(x, y, AXIS);
+                }
+            }
+        }
+    }
+
+    /// Returns the columns of the ticks at `first`, `first + spacing`,
+    /// `first + 2 * spacing` and so on, each the nearest column, up to the
+    /// right edge of the image.
+    ///
+    /// The positions are computed from `first` rather than added up, so a
+    /// fractional spacing (like the pixels per second) does not drift.
+    /// Ticks left of the image are skipped and there are none unless the
+    /// spacing is positive.
+    fn tick_columns(&self, first: f64, spacing: f64) -> impl Iterator<Item = u32> {
+        let width = self.width as f64;
+        let valid = spacing > 0.0 && first.is_finite();
+        // The first tick that does not round to a negative column
+        let skipped = if first < -0.5 { ((-0.5 - first) / spacing).ceil() } else { 0.0 };
+        std::iter::successors(Some(skipped), |tick| Some(tick + 1.0))
+            .map(move |tick| (first + tick * spacing).round())
+            .take_while(move |&x| valid && x < width)
+            .filter(|&x| x >= 0.0)
+            .map(|x| x as u32)
+    }
+
+    /// Draws tick marks over the rows `y_start..y_end`: vertical lines at
+    /// the columns of `tick_columns`.
+    pub fn draw_ticks(&mut self, first: f64, spacing: f64, y_start: u32, y_end: u32, index: u8) {
+        for x in self.tick_columns(first, spacing) {
+            self.draw_vline(x, y_start, y_end, index);
+        }
+    }
+
+    /// Draws a grid of vertical lines at the columns of `tick_columns` over
+    /// the waveform section (every lane, not the overview) in the given
+    /// color.
+    ///
+    /// Like the axis the grid is drawn behind the waveform in the `AXIS`
+    /// entry, so the two share their color.
+    pub fn draw_grid(&mut self, color: &Rgba, first: f64, spacing: f64) {
+        self.axis_color = Some(*color);
+
+        let columns: Vec<u32> = self.tick_columns(first, spacing).collect();
+        for y in self.wave.top..self.wave.bottom {
+            for &x in &columns {
+                if self.index(x, y) == Channel::Background as u8 {
+                    self.set_pixel(x, y, AXIS);
+                }
+            }
+        }
+    }
+
+    /// Draws short tick marks along the baseline of the waveform at the
+    /// columns of `tick_columns` in the given color, and returns the columns.
+    ///
+    /// The baseline is the bottom edge of the waveform section, or the top
+    /// edge when a mono waveform hangs from it (`MonoStyle::Bottom`).  The
+    /// marks are a sixteenth of the section high (at least 2 rows) and,
+    /// unlike the grid, drawn over the waveform so they stay visible where
+    /// it reaches the edge.  They share the `AXIS` entry with the grid and
+    /// the axis.
+    pub fn draw_tick_marks(&mut self, color: &Rgba, first: f64, spacing: f64) -> Vec<u32> {
+        self.axis_color = Some(*color);
+
+        let rows = ((self.wave.bottom - self.wave.top) / 16).max(2);
+        let (y_start, y_end) = if self.baseline == Some(self.wave.top) {
+            (self.wave.top, self.wave.top + rows)
+        } else {
+            (self.wave.bottom.saturating_sub(rows).max(self.wave.top), self.wave.bottom)
+        };
+        let columns: Vec<u32> = self.tick_columns(first, spacing).collect();
+        for &x in &columns {
+            self.draw_vline(x, y_start, y_end.min(self.wave.bottom), AXIS);
+        }
+        columns
+    }
+
+    /// Draws markers as vertical lines over the full height of the waveform
+    /// section (every lane, not the overview) at the given columns, in the
+    /// given color.
+    ///
+    /// Like the tick marks the markers are drawn over the waveform and share
+    /// the `AXIS` entry with the grid and the axis.  Columns right of the
+    /// image are skipped.
+    pub fn draw_markers(&mut self, color: &Rgba, columns: &[u32]) {
+        self.axis_color = Some(*color);
+
+        let width = self.width;
+        for &x in columns.iter().filter(|&&x| x < width) {
+            self.draw_vline(x, self.wave.top, self.wave.bottom, AXIS);
+        }
+    }
+
+    /// Draws text with the built-in font with its top left corner at (`x`, `y`).
+    ///
+    /// Characters the font does not have are left blank.  Each font pixel is
+    /// drawn as a `scale` x `scale` block and the text is clipped to the image.
+    pub fn draw_text(&mut self, x: u32, y: u32, text: &str, scale: u32, index: u8) {
+        let advance = (font::GLYPH_WIDTH + font::GLYPH_SPACING) * scale;
+        for (i, c) in text.chars().enumerate() {
+            let Some(rows) = font::glyph(c) else {
+                continue;
+            };
+            let glyph_x = x + i as u32 * advance;
+            for row in 0..font::GLYPH_HEIGHT {
+                for col in 0..font::GLYPH_WIDTH {
+                    if font::glyph_pixel(rows, col, row) {
+                        let px = glyph_x + col * scale;
+                        let py = y + row * scale;
+                        self.fill_rect(px, py, px + scale, py + scale, index);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draws a small text badge (like the track duration) in a corner of the
+    /// waveform area.
+    ///
+    /// The badge box is cleared to the background so the text, drawn in the
+    /// left channel color, stays readable over the waveform.  The font is
+    /// scaled up for taller images and the badge is skipped if it would not
+    /// fit in the image.
+    ///
+    /// # Arguments
+    ///
+    /// * `corner` - The corner of the waveform to put the badge in
+    /// * `text` - The badge text
+    pub fn draw_badge(&mut self, corner: Corner, text: &str) {
+        let scale = (self.wave.bottom / 48).max(1);
+        let padding = scale;
+        let badge_width = font::text_width(text, scale) + 2 * padding;
+        let badge_height = font::GLYPH_HEIGHT * scale + 2 * padding;
+        let margin = scale;
+
+        if badge_width + 2 * margin > self.width || badge_height + 2 * margin > self.wave.bottom {
+            return;
+        }
+
+        let x = if corner.is_left() {
+            margin
+        } else {
+            self.width - margin - badge_width
+        };
+        let y = if corner.is_top() {
+            margin
+        } else {
+            self.wave.bottom - margin - badge_height
+        };
+
+        self.fill_rect(x, y, x + badge_width, y + badge_height, Channel::Background as u8);
+        self.draw_text(x + padding, y + padding, text, scale, Channel::Left as u8);
+    }
+
+    /// Draws the balance strip for column `x`.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The horizontal position to draw at
+    /// * `balance` - Balance from -1.0 (hard left) through 0.0 (centered) to 1.0 (hard right)
+    pub fn draw_balance(&mut self, x: u32, balance: f32) {
+        if x >= self.width || self.strip_rows == 0 {
+            return;
+        }
+
+        let t = (balance.clamp(-1.0, 1.0) + 1.0) / 2.0;
+        let index = self.balance_ramp() + (t * (BALANCE_LEVELS - 1) as f32 + 0.5) as u8;
+        self.fill_column(x, self.strip_top(), self.height, index);
     }
 
     /// Draws a single point (left and right channels) of the waveform.
@@ -138,30 +820,166 @@ impl WaveImage {
             return;
         }
 
-        // The byte offset where the 2-bit pixel will be
-        let offset = x >> 2;
+        self.draw_stereo_column(self.wave, x, left, right, Channel::Left as u8, Channel::Right as u8);
+    }
 
-        // Draw left channel (above center, going up)
-        // The bits for the left channel at this pixel offset
-        let draw_left = draw_bits(Channel::Left as u8, x);
-        let left_height = (self.center as f32 * left.max(0.0).min(1.0) + 0.5) as u32;
-        for y in self.center.saturating_sub(left_height)..self.center {
-            let idx = (offset + y * self.line_width) as usize;
-            self.pixels[idx] |= draw_left;
+    /// Draws a single point (left and right channels) of a loudness tinted waveform.
+    ///
+    /// Works like `draw_point` but colors each channel's bar with the ramp
+    /// entry for its loudness level.  On a 2-bit image the levels are ignored
+    /// and the plain channel colors are used.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The horizontal position to draw at
+    /// * `left` - Left channel maximum amplitude
+    /// * `right` - Right channel maximum amplitude
+    /// * `left_level` - Left channel loudness level (0.0 quiet to 1.0 loud)
+    /// * `right_level` - Right channel loudness level (0.0 quiet to 1.0 loud)
+    pub fn draw_point_tinted(&mut self, x: u32, left: f32, right: f32, left_level: f32, right_level: f32) {
+        if x >= self.width {
+            return;
+        }
+
+        if self.is_tinted() {
+            self.draw_stereo_column(
+                self.wave,
+                x,
+                left,
+                right,
+                ramp_index(LEFT_RAMP, left_level),
+                ramp_index(RIGHT_RAMP, right_level),
+            );
+        } else {
+            self.draw_stereo_column(self.wave, x, left, right, Channel::Left as u8, Channel::Right as u8);
         }
+    }
+
+    /// Draws a single point (left and right channels) with the RMS level over
+    /// the peak.
+    ///
+    /// Each channel's bar is drawn up to its RMS in the channel color and
+    /// from there up to its peak in the faded peak shade.  On an image
+    /// without the peak shades only the peaks are drawn, like `draw_point`.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The horizontal position to draw at
+    /// * `left` - Left channel maximum amplitude
+    /// * `right` - Right channel maximum amplitude
+    /// * `left_rms` - Left channel RMS amplitude
+    /// * `right_rms` - Right channel RMS amplitude
+    pub fn draw_point_layered(&mut self, x: u32, left: f32, right: f32, left_rms: f32, right_rms: f32) {
+        if x >= self.width {
+            return;
+        }
+
+        if self.rms_layer {
+            let section = self.wave;
+            self.draw_layered_bar(section, x, (left, left_rms), true, Channel::Left as u8, PEAK_SHADES);
+            self.draw_layered_bar(section, x, (right, right_rms), false, Channel::Right as u8, PEAK_SHADES + 1);
+        } else {
+            self.draw_stereo_column(self.wave, x, left, right, Channel::Left as u8, Channel::Right as u8);
+        }
+    }
+
+    /// Draws a single point for mono audio with the RMS level over the peak
+    /// (see `draw_point_layered`).
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The horizontal position to draw at
+    /// * `mono` - Mono channel maximum amplitude
+    /// * `rms` - Mono channel RMS amplitude
+    pub fn draw_point_mono_layered(&mut self, x: u32, mono: f32, rms: f32) {
+        if x >= self.width {
+            return;
+        }
+
+        if !self.rms_layer {
+            self.draw_wave_column(x, mono, Channel::Left as u8);
+            return;
+        }
+        match self.baseline_section() {
+            Some((section, up)) => {
+                self.draw_layered_bar(section, x, (mono, rms), up, Channel::Left as u8, PEAK_SHADES);
+            }
+            None => {
+                let section = self.wave;
+                self.draw_layered_bar(section, x, (mono, rms), true, Channel::Left as u8, PEAK_SHADES);
+                self.draw_layered_bar(section, x, (mono, rms), false, Channel::Left as u8, PEAK_SHADES);
+            }
+        }
+    }
+
+    /// Draws a bar from the center of the section up or down, in the channel
+    /// color up to the RMS and in the peak shade from there to the peak.
+    ///
+    /// The two parts never overlap, as the packed pixel forms OR the indices
+    /// of overlapping fills together.
+    fn draw_layered_bar(&mut self, section: Section, x: u32, (peak, rms): (f32, f32), up: bool, index: u8, shade: u8) {
+        let half = section.reach(up) as f32;
+        let peak_rows = (half * peak.clamp(0.0, 1.0) + 0.5) as u32;
+        let rms_rows = ((half * rms.clamp(0.0, 1.0) + 0.5) as u32).min(peak_rows);
+
+        if up {
+            let rms_top = section.center - rms_rows;
+            self.fill_column(x, section.center - peak_rows, rms_top, shade);
+            self.fill_column(x, rms_top, section.center, index);
+        } else {
+            let rms_bottom = section.center + rms_rows;
+            self.fill_column(x, section.center, rms_bottom, index);
+            self.fill_column(x, rms_bottom, section.center + peak_rows, shade);
+        }
+    }
+
+    /// Draws the left bar above and the right bar below the center of column `x`
+    /// within the given section.
+    fn draw_stereo_column(&mut self, section: Section, x: u32, left: f32, right: f32, left_index: u8, right_index: u8) {
+        let half = section.half() as f32;
+
+        // Draw left channel (above center, going up)
+        self.draw_bar(section, x, half * left.clamp(0.0, 1.0), true, left_index);
 
         // Draw right channel (below center, going down)
-        // The bits for the right channel at this pixel offset
-        let draw_right = draw_bits(Channel::Right as u8, x);
-        let right_height = (self.center as f32 * right.max(0.0).min(1.0) + 0.5) as u32;
-        let max_y = std::cmp::min(self.center + right_height, self.height);
-        for y in self.center..max_y {
-            let idx = (offset + y * self.line_width) as usize;
-            self.pixels[idx] |= draw_right;
+        self.draw_bar(section, x, half * right.clamp(0.0, 1.0), false, right_index);
+    }
+
+    /// Draws a bar of `rows` rows from the center of the section up or down.
+    ///
+    /// Normally the height is rounded to whole rows.  Anti-aliased images
+    /// fill the whole rows and draw the partly covered row at the end of the
+    /// bar with the coverage ramp entry of the channel.
+    fn draw_bar(&mut self, section: Section, x: u32, rows: f32, up: bool, index: u8) {
+        let (full, coverage) = if self.antialias {
+            (rows as u32, rows.fract())
+        } else {
+            ((rows + 0.5) as u32, 0.0)
+        };
+
+        // The row just past the end of the bar (if it is inside the section)
+        let edge = if up {
+            let top = section.center.saturating_sub(full);
+            self.fill_column(x, top, section.center, index);
+            top.checked_sub(1).filter(|&y| y >= section.top)
+        } else {
+            let bottom = std::cmp::min(section.center + full, section.bottom);
+            self.fill_column(x, section.center, bottom, index);
+            Some(bottom).filter(|&y| y < section.bottom)
+        };
+
+        let ramp = match Channel::from(index) {
+            Channel::Left => LEFT_RAMP,
+            Channel::Right => RIGHT_RAMP,
+            Channel::Background => return,
+        };
+        if let Some(y) = edge.filter(|_| coverage > 0.0) {
+            self.set_pixel(x, y, ramp_index(ramp, coverage));
         }
     }
 
-    /// Draws a single point for mono audio (symmetric around center).
+    /// Draws a single point for mono audio (symmetric around center, or
+    /// from the baseline of the `MonoStyle`).
     ///
     /// # Arguments
     ///
@@ -172,11 +990,11 @@ impl WaveImage {
     ///
     /// ```
     /// use waver::cli::{Width, Height};
-    /// use waver::image::WaveImage;
+    /// use waver::image::{ImageOptions, WaveImage};
     ///
     /// let width = Width::new(1024).unwrap();
     /// let height = Height::new(128).unwrap();
-    /// let mut image = WaveImage::new(width, height);
+    /// let mut image = WaveImage::with_options(width, height, &ImageOptions::default());
     ///
     /// // Draw a mono sample at x=100 with 50% amplitude
     /// image.draw_point_mono(100, 0.5);
@@ -186,28 +1004,253 @@ impl WaveImage {
             return;
         }
 
-        // The byte offset where the 2-bit pixel will be
-        let offset = x >> 2;
+        self.draw_wave_column(x, mono, Channel::Left as u8);
+    }
 
-        // Bit position for the pixel
-        let draw = draw_bits(Channel::Left as u8, x);
+    /// Draws a single point for mono audio colored by its loudness level.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The horizontal position to draw at
+    /// * `mono` - Mono channel maximum amplitude
+    /// * `level` - Loudness level (0.0 quiet to 1.0 loud)
+    pub fn draw_point_mono_tinted(&mut self, x: u32, mono: f32, level: f32) {
+        if x >= self.width {
+            return;
+        }
+
+        let index = if self.is_tinted() {
+            ramp_index(LEFT_RAMP, level)
+        } else {
+            Channel::Left as u8
+        };
+        self.draw_wave_column(x, mono, index);
+    }
+
+    /// Draws a single point of the signed envelopes of the left and right
+    /// channels.
+    ///
+    /// Each channel is drawn from its lowest to its highest sample, the left
+    /// channel around the center of the upper half of the waveform and the
+    /// right channel around the center of the lower half, so asymmetric
+    /// audio keeps its shape.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The horizontal position to draw at
+    /// * `left` - Lowest and highest left channel sample (-1.0 to 1.0)
+    /// * `right` - Lowest and highest right channel sample (-1.0 to 1.0)
+    pub fn draw_envelope_point(&mut self, x: u32, left: (f32, f32), right: (f32, f32)) {
+        if x >= self.width {
+            return;
+        }
+
+        let rows = self.wave.bottom - self.wave.top;
+        let upper = Section::new(self.wave.top, rows / 2);
+        let lower = Section::new(upper.bottom, rows - rows / 2);
+        self.draw_span(upper, x, left, Channel::Left as u8);
+        self.draw_span(lower, x, right, Channel::Right as u8);
+    }
+
+    /// Draws a single point of the signed envelope of mono audio around the
+    /// center of the waveform.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The horizontal position to draw at
+    /// * `envelope` - Lowest and highest sample (-1.0 to 1.0)
+    pub fn draw_envelope_point_mono(&mut self, x: u32, envelope: (f32, f32)) {
+        if x >= self.width {
+            return;
+        }
+
+        self.draw_span(self.wave, x, envelope, Channel::Left as u8);
+    }
 
-        let wave_height = (self.center as f32 * mono.max(0.0).min(1.0) + 0.5) as u32;
-        let y_start = self.center.saturating_sub(wave_height);
-        let y_end = std::cmp::min(self.center + wave_height, self.height);
+    /// Fills the rows of column `x` between the lowest and highest sample
+    /// around the center of the section, positive values above the center.
+    ///
+    /// The ends are rounded to whole rows like the bars of `draw_bar`, so a
+    /// symmetric envelope is drawn exactly like the mono peak bar.
+    fn draw_span(&mut self, section: Section, x: u32, (min, max): (f32, f32), index: u8) {
+        let half = section.half() as f32;
+        let row = |value: f32| {
+            let offset = (half * value.clamp(-1.0, 1.0)).round() as i64;
+            (section.center as i64 - offset).clamp(section.top as i64, section.bottom as i64) as u32
+        };
+        self.fill_column(x, row(max), row(min), index);
+    }
+
+    /// Draws a single point of one channel in its own lane.
+    ///
+    /// The lanes alternate between the left and right channel colors so
+    /// neighboring lanes can be told apart.  An image without lanes draws
+    /// the point like `draw_point_mono`.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The horizontal position to draw at
+    /// * `lane` - The lane (channel) from the top, starting at 0
+    /// * `amplitude` - Maximum amplitude of the channel
+    pub fn draw_lane_point(&mut self, x: u32, lane: usize, amplitude: f32) {
+        if x >= self.width {
+            return;
+        }
+
+        let section = self.lanes.get(lane).copied().unwrap_or(self.wave);
+        let index = if lane.is_multiple_of(2) { Channel::Left } else { Channel::Right };
+        self.draw_mono_column(section, x, amplitude, index as u8);
+    }
+
+    /// Draws a single point of one stem, in a layer over the other stems or
+    /// in its lane when the image has one lane per stem.
+    ///
+    /// The layers are drawn like a mono waveform (see `MonoStyle`) and the
+    /// pixels keep the bits of every stem covering them, which the palette
+    /// of `set_stem_colors` blends.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The horizontal position to draw at
+    /// * `stem` - The stem, starting at 0 (below `MAX_STEMS`)
+    /// * `amplitude` - Maximum amplitude of the stem
+    pub fn draw_stem_point(&mut self, x: u32, stem: usize, amplitude: f32) {
+        if x >= self.width || stem >= MAX_STEMS {
+            return;
+        }
 
-        for y in y_start..y_end {
-            let idx = (offset + y * self.line_width) as usize;
-            self.pixels[idx] |= draw;
+        let index = 1 << stem;
+        match self.lanes.get(stem).copied() {
+            Some(lane) => self.draw_mono_column(lane, x, amplitude, index),
+            None => self.draw_wave_column(x, amplitude, index),
+        }
+    }
+
+    /// Sets the colors of the stems, which the image is then written in
+    /// instead of the channel colors.  Every stem drawn needs a color.
+    pub fn set_stem_colors(&mut self, colors: &[Rgba]) {
+        self.stem_colors = colors.iter().take(MAX_STEMS).copied().collect();
+    }
+
+    /// Draws a single point of the compressed overview waveform.
+    ///
+    /// The overview is always drawn symmetric around its center in the left
+    /// channel color, as the loudest of the channels reads best at the small
+    /// height.  Does nothing if the image has no overview.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The horizontal position to draw at
+    /// * `amplitude` - Maximum amplitude over all channels
+    pub fn draw_overview_point(&mut self, x: u32, amplitude: f32) {
+        if x >= self.width {
+            return;
+        }
+
+        if let Some(overview) = self.overview {
+            self.draw_mono_column(overview, x, amplitude, Channel::Left as u8);
+        }
+    }
+
+    /// Draws two mono waveforms over each other, the first in the left and
+    /// the second in the right channel color.
+    ///
+    /// Where both bars cover a pixel the `Overlap` of the image decides it:
+    /// with the default `Overlap::Mark` it is the `AXIS` entry, which is
+    /// drawn as background, so only the parts where the two waveforms
+    /// differ remain visible.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The horizontal position to draw at
+    /// * `first` - Maximum amplitude of the first waveform
+    /// * `second` - Maximum amplitude of the second waveform
+    pub fn draw_overlay_point(&mut self, x: u32, first: f32, second: f32) {
+        if x >= self.width {
+            return;
+        }
+
+        self.draw_mono_column(self.wave, x, first, Channel::Left as u8);
+        self.draw_mono_column(self.wave, x, second, Channel::Right as u8);
+    }
+
+    /// Returns the waveform band with the baseline of the `MonoStyle` as its
+    /// center and whether the bars go up from it, or `None` when they are
+    /// mirrored around the center.  This also records the baseline row for
+    /// the axis.
+    fn baseline_section(&mut self) -> Option<(Section, bool)> {
+        let wave = self.wave;
+        let (section, up, row) = match self.mono_style {
+            MonoStyle::Mirrored => return None,
+            MonoStyle::Top => (Section { center: wave.bottom, ..wave }, true, wave.bottom - 1),
+            MonoStyle::Bottom => (Section { center: wave.top, ..wave }, false, wave.top),
+        };
+        self.baseline = Some(row);
+        Some((section, up))
+    }
+
+    /// Draws a bar of the single waveform in column `x`, around the center
+    /// or from the baseline of the `MonoStyle` over the whole height.
+    fn draw_wave_column(&mut self, x: u32, mono: f32, index: u8) {
+        match self.baseline_section() {
+            Some((section, up)) => {
+                let rows = section.reach(up) as f32 * mono.clamp(0.0, 1.0);
+                self.draw_bar(section, x, rows, up, index);
+            }
+            None => self.draw_mono_column(self.wave, x, mono, index),
+        }
+    }
+
+    /// Draws a bar symmetric around the center of column `x` within the given section.
+    fn draw_mono_column(&mut self, section: Section, x: u32, mono: f32, index: u8) {
+        let rows = section.half() as f32 * mono.clamp(0.0, 1.0);
+        self.draw_bar(section, x, rows, true, index);
+        self.draw_bar(section, x, rows, false, index);
+    }
+
+    /// Returns a copy of the columns `x..x + width` as an image of its own.
+    ///
+    /// The tile keeps the layout (sections, strip) and palette of the image.
+    /// `x` must be a multiple of 8 so the tile starts on a byte boundary at
+    /// every pixel depth; the width is clipped to the right edge.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The first column of the tile
+    /// * `width` - The number of columns in the tile
+    pub fn tile(&self, x: u32, width: u32) -> WaveImage {
+        debug_assert!(x.is_multiple_of(8), "tiles must start on a byte boundary");
+        let width = width.min(self.width.saturating_sub(x));
+        let per_byte = 8 / self.depth as u32;
+        let offset = (x / per_byte) as usize;
+        let line_width = width.div_ceil(per_byte);
+
+        let mut pixels = Vec::with_capacity((line_width * self.height) as usize);
+        for row in self.pixels.chunks_exact(self.line_width as usize) {
+            pixels.extend_from_slice(&row[offset..offset + line_width as usize]);
+        }
+
+        WaveImage {
+            width,
+            line_width,
+            pixels,
+            lanes: self.lanes.clone(),
+            stem_colors: self.stem_colors.clone(),
+            axis_color: self.axis_color,
+            ..*self
         }
     }
 
     /// Returns the width of the image.
-    #[allow(dead_code)]
     pub fn width(&self) -> u32 {
         self.width
     }
 
+    /// Returns the height of the image, including any overview and strip.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
     /// Saves the waveform image as a PNG file with 2-bit pixel depth optimization.
     ///
     /// # Performance
@@ -238,24 +1281,465 @@ impl WaveImage {
         right: &Rgba,
         output_path: impl AsRef<Path>,
     ) -> Result<()> {
+        let mut file = audit::create_atomic(output_path)?;
+        self.write_png(background, left, right, &mut file)?;
+        file.commit()?;
+        Ok(())
+    }
+
+    /// Returns the RGB palette and the alpha of each palette entry.
+    fn palette(&self, background: &Rgba, left: &Rgba, right: &Rgba) -> (Vec<u8>, Vec<u8>) {
         // Create palette for indexed color PNG
-        let palette = [
+        // The last normal entry is the axis (or the overlay collision)
+        let axis = self.axis_color.as_ref().unwrap_or(background);
+        let mut palette = vec![
             background.red, background.green, background.blue,
             left.red, left.green, left.blue,
             right.red, right.green, right.blue,
-            background.red, background.green, background.blue,
+            axis.red, axis.green, axis.blue,
         ];
 
         // Create transparency array
-        let transparent = [background.alpha, left.alpha, right.alpha, background.alpha];
+        let mut transparent = vec![background.alpha, left.alpha, right.alpha, axis.alpha];
+
+        // The stems have a palette of their own after the background
+        if !self.stem_colors.is_empty() {
+            palette.truncate(3);
+            transparent.truncate(1);
+            push_stem_layers(&mut palette, &mut transparent, &self.stem_colors);
+            return (palette, transparent);
+        }
+
+        // The tinted image adds the loudness ramps after the normal entries,
+        // the anti-aliased image its coverage ramps in the same place
+        if self.is_tinted() {
+            push_ramp(&mut palette, &mut transparent, left);
+            push_ramp(&mut palette, &mut transparent, right);
+        } else if self.antialias {
+            push_coverage_ramp(&mut palette, &mut transparent, background, left);
+            push_coverage_ramp(&mut palette, &mut transparent, background, right);
+        } else if self.rms_layer {
+            push_peak_shade(&mut palette, &mut transparent, background, left);
+            push_peak_shade(&mut palette, &mut transparent, background, right);
+        }
+
+        // The balance strip ramp comes last
+        if self.strip_rows > 0 {
+            push_balance_ramp(&mut palette, &mut transparent, left, right);
+        }
+
+        (palette, transparent)
+    }
+
+    /// Returns the image as opaque RGB pixels, with every palette color
+    /// blended over the background color made opaque.
+    ///
+    /// This is for formats (and viewers) without transparency, where a
+    /// transparent background would otherwise show up as black.
+    pub fn flatten(&self, background: &Rgba, left: &Rgba, right: &Rgba) -> Vec<u8> {
+        self.expand(&self.opaque_palette(background, left, right))
+    }
+
+    /// Returns the palette colors blended over the background color made
+    /// opaque (see `flatten`).
+    fn opaque_palette(&self, background: &Rgba, left: &Rgba, right: &Rgba) -> Vec<[u8; 3]> {
+        self.palette_over(background, left, right, [background.red, background.green, background.blue])
+    }
+
+    /// Returns the palette colors blended over the opaque `base` color.
+    fn palette_over(&self, background: &Rgba, left: &Rgba, right: &Rgba, base: [u8; 3]) -> Vec<[u8; 3]> {
+        let (palette, transparent) = self.palette(background, left, right);
+        palette
+            .chunks_exact(3)
+            .zip(&transparent)
+            .map(|(color, &alpha)| {
+                let blend = |c: u8, b: u8| {
+                    ((c as u32 * alpha as u32 + b as u32 * (255 - alpha as u32) + 127) / 255) as u8
+                };
+                [blend(color[0], base[0]), blend(color[1], base[1]), blend(color[2], base[2])]
+            })
+            .collect()
+    }
+
+    /// Returns the image as RGBA pixels (8 bits per channel, top row first)
+    /// with the colors and transparency exactly as in the PNG.
+    pub fn to_rgba(&self, background: &Rgba, left: &Rgba, right: &Rgba) -> Vec<u8> {
+        let (palette, transparent) = self.palette(background, left, right);
+        let colors: Vec<[u8; 4]> = palette
+            .chunks_exact(3)
+            .zip(&transparent)
+            .map(|(color, &alpha)| [color[0], color[1], color[2], alpha])
+            .collect();
+        self.expand(&colors)
+    }
+
+    /// Replaces the palette index of every pixel by its color.
+    fn expand<const N: usize>(&self, colors: &[[u8; N]]) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(self.width as usize * self.height as usize * N);
+        for row in self.rows() {
+            self.expand_row(row, colors, &mut pixels);
+        }
+        pixels
+    }
 
-        // Create the output file and BufWriter
-        let file = File::create(output_path)?;
-        let mut encoder = Encoder::new(BufWriter::new(file), self.width, self.height);
+    /// Returns the packed rows of the image, top row first.
+    fn rows(&self) -> std::slice::ChunksExact<'_, u8> {
+        self.pixels.chunks_exact(self.line_width as usize)
+    }
+
+    /// Appends the color of every pixel of a packed row to `pixels`.
+    fn expand_row<const N: usize>(&self, row: &[u8], colors: &[[u8; N]], pixels: &mut Vec<u8>) {
+        for index in self.row_indices(row) {
+            pixels.extend_from_slice(&colors[index as usize]);
+        }
+    }
+
+    /// Returns the palette index of every pixel of a packed row.
+    fn row_indices<'a>(&self, row: &'a [u8]) -> impl Iterator<Item = u8> + 'a {
+        let depth = self.depth as usize;
+        let mask = u8::MAX >> (8 - depth);
+        // The packed pixels of a byte, leftmost in the highest bits
+        row.iter()
+            .flat_map(move |&byte| (1..=8 / depth).map(move |pixel| (byte >> (8 - depth * pixel)) & mask))
+            .take(self.width as usize)
+    }
+
+    /// Writes the rows of the image, top row first unless `bottom_up`, each
+    /// expanded to the colors and followed by `padding` zero bytes.
+    ///
+    /// Only one row is expanded at a time, so the uncompressed formats start
+    /// writing right away and never hold the whole image in memory.
+    fn write_rows<const N: usize>(
+        &self,
+        colors: &[[u8; N]],
+        bottom_up: bool,
+        padding: usize,
+        mut writer: impl Write,
+    ) -> Result<()> {
+        let mut line = Vec::with_capacity(self.width as usize * N + padding);
+        let mut write_row = |row: &[u8]| {
+            line.clear();
+            self.expand_row(row, colors, &mut line);
+            line.resize(line.len() + padding, 0);
+            writer.write_all(&line)
+        };
+        if bottom_up {
+            self.rows().rev().try_for_each(&mut write_row)?;
+        } else {
+            self.rows().try_for_each(&mut write_row)?;
+        }
+        Ok(())
+    }
+
+    /// Encodes the image as a truecolor PNG with every color blended over
+    /// a checkerboard of `square` pixel squares instead of the background.
+    ///
+    /// This is for checking the transparency by eye: the squares show
+    /// through wherever the image is (partly) transparent, which a viewer
+    /// would otherwise show in its own background color.
+    pub fn write_checkerboard_png(
+        &self,
+        background: &Rgba,
+        left: &Rgba,
+        right: &Rgba,
+        square: u32,
+        writer: impl Write,
+    ) -> Result<()> {
+        let squares = [CHECKER_LIGHT, CHECKER_DARK].map(|base| self.palette_over(background, left, right, base));
+        let square = square.max(1);
+
+        let mut encoder = Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        let mut stream = writer.stream_writer()?;
+        let mut line = Vec::with_capacity(self.width as usize * 3);
+        for (y, row) in self.rows().enumerate() {
+            line.clear();
+            for (x, index) in self.row_indices(row).enumerate() {
+                let dark = (x as u32 / square + y as u32 / square) % 2;
+                line.extend_from_slice(&squares[dark as usize][index as usize]);
+            }
+            stream.write_all(&line)?;
+        }
+        stream.finish()?;
+        Ok(())
+    }
+
+    /// Encodes the image as an RGBA PNG with every color, the background
+    /// included, blended over the pixels of a backdrop of the same size.
+    ///
+    /// The output is only transparent where both the colors and the
+    /// backdrop are.  The text chunks are added as in `write_png_with_text`.
+    pub fn write_png_over(
+        &self,
+        background: &Rgba,
+        left: &Rgba,
+        right: &Rgba,
+        backdrop: &Backdrop,
+        text: &[(&str, String)],
+        writer: impl Write,
+    ) -> Result<()> {
+        if (backdrop.width(), backdrop.height()) != (self.width, self.height) {
+            return Err(WaverError::generation_error(format!(
+                "The {}x{} backdrop does not fit the {}x{} image",
+                backdrop.width(),
+                backdrop.height(),
+                self.width,
+                self.height
+            )));
+        }
+        let (palette, transparent) = self.palette(background, left, right);
+        let colors: Vec<Rgba> = palette
+            .chunks_exact(3)
+            .zip(&transparent)
+            .map(|(color, &alpha)| Rgba::new(color[0], color[1], color[2], alpha))
+            .collect();
+
+        let mut encoder = Encoder::new(writer, self.width, self.height);
+        add_text_chunks(&mut encoder, text)?;
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        let mut stream = writer.stream_writer()?;
+        let mut line = Vec::with_capacity(self.width as usize * 4);
+        for (y, row) in self.rows().enumerate() {
+            line.clear();
+            for (x, index) in self.row_indices(row).enumerate() {
+                let pixel = colors[index as usize].blend_over(&backdrop.pixel(x as u32, y as u32));
+                line.extend_from_slice(&[pixel.red, pixel.green, pixel.blue, pixel.alpha]);
+            }
+            stream.write_all(&line)?;
+        }
+        stream.finish()?;
+        Ok(())
+    }
+
+    /// Encodes the image as a binary PPM (P6) with the background flattened
+    /// to an opaque color, for piping into tools like ImageMagick or ffmpeg.
+    ///
+    /// This skips compression altogether, for when the image is compressed
+    /// again further down the pipe anyway.
+    pub fn write_ppm(&self, background: &Rgba, left: &Rgba, right: &Rgba, mut writer: impl Write) -> Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        self.write_rows(&self.opaque_palette(background, left, right), false, 0, writer)
+    }
+
+    /// Encodes the image as QOI, which keeps the transparency like the PNG
+    /// but encodes many times faster (the files are larger).
+    pub fn write_qoi(&self, background: &Rgba, left: &Rgba, right: &Rgba, writer: impl Write) -> Result<()> {
+        qoi::encode(self.width, self.height, &self.to_rgba(background, left, right), writer)?;
+        Ok(())
+    }
+
+    /// Encodes the image as a lossless WebP, with the colors and
+    /// transparency exactly as in the PNG.
+    ///
+    /// The encoder has no palette of its own, but with the few colors of
+    /// the palette its entropy codes still take a bit or two per pixel.  If
+    /// every palette entry is opaque the pixels are encoded without alpha.
+    pub fn write_webp(&self, background: &Rgba, left: &Rgba, right: &Rgba, writer: impl Write) -> Result<()> {
+        let (palette, transparent) = self.palette(background, left, right);
+        let encoder = image_webp::WebPEncoder::new(writer);
+        if transparent.iter().all(|&alpha| alpha == u8::MAX) {
+            let colors: Vec<[u8; 3]> = palette.chunks_exact(3).map(|color| [color[0], color[1], color[2]]).collect();
+            encoder.encode(&self.expand(&colors), self.width, self.height, image_webp::ColorType::Rgb8)?;
+        } else {
+            let rgba = self.to_rgba(background, left, right);
+            encoder.encode(&rgba, self.width, self.height, image_webp::ColorType::Rgba8)?;
+        }
+        Ok(())
+    }
+
+    /// Encodes the image as a lossy AVIF with the transparency kept (the
+    /// alpha is left out when the image is opaque).
+    ///
+    /// # Arguments
+    ///
+    /// * `background` - Background color
+    /// * `left` - Left channel color
+    /// * `right` - Right channel color
+    /// * `quality` - Quality of the colors and the alpha from 1 to 100
+    /// * `writer` - Where the AVIF file is written
+    #[cfg(feature = "avif")]
+    pub fn write_avif(
+        &self,
+        background: &Rgba,
+        left: &Rgba,
+        right: &Rgba,
+        quality: u8,
+        mut writer: impl Write,
+    ) -> Result<()> {
+        let pixels: Vec<ravif::RGBA8> = self
+            .to_rgba(background, left, right)
+            .chunks_exact(4)
+            .map(|pixel| ravif::RGBA8::new(pixel[0], pixel[1], pixel[2], pixel[3]))
+            .collect();
+        // The slower speeds make smaller files but take seconds per image,
+        // too long for a library of files
+        let encoded = ravif::Encoder::new()
+            .with_quality(quality as f32)
+            .with_alpha_quality(quality as f32)
+            .with_speed(6)
+            .encode_rgba(ravif::Img::new(&pixels[..], self.width as usize, self.height as usize))?;
+        writer.write_all(&encoded.avif_file)?;
+        Ok(())
+    }
+
+    /// Encodes the image as farbfeld: a tiny header and then 16-bit big
+    /// endian RGBA, so unlike PPM the transparency is kept.
+    pub fn write_farbfeld(&self, background: &Rgba, left: &Rgba, right: &Rgba, mut writer: impl Write) -> Result<()> {
+        writer.write_all(b"farbfeld")?;
+        writer.write_all(&self.width.to_be_bytes())?;
+        writer.write_all(&self.height.to_be_bytes())?;
+
+        // Scaling by 257 maps 0xab to 0xabab, so full scale stays full scale
+        let wide: Vec<u8> = self
+            .to_rgba(background, left, right)
+            .into_iter()
+            .flat_map(|value| (value as u16 * 257).to_be_bytes())
+            .collect();
+        writer.write_all(&wide)?;
+        Ok(())
+    }
+
+    /// Saves the image as a JPEG with an opaque (flattened) background.
+    ///
+    /// JPEG has no transparency and no palette, so this is only meant for
+    /// places that mishandle transparent indexed PNGs, like some email
+    /// clients.  The PNG is smaller and exact.
+    ///
+    /// # Arguments
+    ///
+    /// * `background` - Background color (its alpha is ignored)
+    /// * `left` - Left channel color
+    /// * `right` - Right channel color
+    /// * `quality` - JPEG quality from 1 to 100
+    /// * `output_path` - Path where the JPEG file will be saved
+    pub fn save_jpeg(
+        &self,
+        background: &Rgba,
+        left: &Rgba,
+        right: &Rgba,
+        quality: u8,
+        output_path: impl AsRef<Path>,
+    ) -> Result<()> {
+        let mut file = audit::create_atomic(output_path)?;
+        self.write_jpeg(background, left, right, quality, &mut file)?;
+        file.commit()?;
+        Ok(())
+    }
+
+    /// Encodes the image as a JPEG into any writer (see `save_jpeg`).
+    pub fn write_jpeg(
+        &self,
+        background: &Rgba,
+        left: &Rgba,
+        right: &Rgba,
+        quality: u8,
+        writer: impl Write,
+    ) -> Result<()> {
+        let (Ok(width), Ok(height)) = (u16::try_from(self.width), u16::try_from(self.height)) else {
+            return Err(WaverError::generation_error(
+                "JPEG images can be at most 65535 pixels wide and high",
+            ));
+        };
+
+        let rgb = self.flatten(background, left, right);
+        jpeg_encoder::Encoder::new(writer, quality).encode(&rgb, width, height, jpeg_encoder::ColorType::Rgb)?;
+        Ok(())
+    }
+
+    /// Encodes the image as an uncompressed 24-bit BMP with the background
+    /// flattened to an opaque color.
+    ///
+    /// This is for embedded players whose decoders can not handle PNG: the
+    /// pixels are stored bottom row first in BGR order, each row padded to a
+    /// multiple of 4 bytes.
+    pub fn write_bmp(&self, background: &Rgba, left: &Rgba, right: &Rgba, mut writer: impl Write) -> Result<()> {
+        const HEADER_SIZE: u32 = 14 + 40;
+        let row_size = (self.width * 3).next_multiple_of(4);
+        let image_size = row_size as u64 * self.height as u64;
+        let Ok(file_size) = u32::try_from(image_size + HEADER_SIZE as u64) else {
+            return Err(WaverError::generation_error("The image is too large for a BMP file"));
+        };
+
+        // The file header followed by a BITMAPINFOHEADER
+        let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+        header.extend_from_slice(b"BM");
+        header.extend_from_slice(&file_size.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&HEADER_SIZE.to_le_bytes());
+        header.extend_from_slice(&40u32.to_le_bytes());
+        header.extend_from_slice(&self.width.to_le_bytes());
+        header.extend_from_slice(&self.height.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&24u16.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&((file_size - HEADER_SIZE).to_le_bytes()));
+        header.extend_from_slice(&2835u32.to_le_bytes());
+        header.extend_from_slice(&2835u32.to_le_bytes());
+        header.extend_from_slice(&[0; 8]);
+        writer.write_all(&header)?;
+
+        let bgr: Vec<[u8; 3]> = self
+            .opaque_palette(background, left, right)
+            .into_iter()
+            .map(|[red, green, blue]| [blue, green, red])
+            .collect();
+        self.write_rows(&bgr, true, (row_size - self.width * 3) as usize, writer)
+    }
+
+    /// Encodes the image as raw RGB565 pixels (16 bits each, little endian,
+    /// top row first) with the background flattened to an opaque color.
+    ///
+    /// There is no header at all, so this is the format a small display can
+    /// copy straight into its frame buffer; the size has to be known upfront.
+    pub fn write_rgb565(&self, background: &Rgba, left: &Rgba, right: &Rgba, writer: impl Write) -> Result<()> {
+        let packed: Vec<[u8; 2]> = self
+            .opaque_palette(background, left, right)
+            .into_iter()
+            .map(|[red, green, blue]| ((red as u16 >> 3) << 11 | (green as u16 >> 2) << 5 | blue as u16 >> 3).to_le_bytes())
+            .collect();
+        self.write_rows(&packed, false, 0, writer)
+    }
+
+    /// Encodes the image as a PNG into any writer, such as stdout or memory.
+    ///
+    /// The encoding is the same as for `save_png`.  The writer is not
+    /// buffered here so a file or pipe should be wrapped in a `BufWriter`.
+    pub fn write_png(
+        &self,
+        background: &Rgba,
+        left: &Rgba,
+        right: &Rgba,
+        writer: impl Write,
+    ) -> Result<()> {
+        self.write_png_with_text(background, left, right, &[], writer)
+    }
+
+    /// Encodes the image as a PNG like `write_png`, with the given keywords
+    /// and texts as text chunks (see `add_text_chunks`).
+    pub fn write_png_with_text(
+        &self,
+        background: &Rgba,
+        left: &Rgba,
+        right: &Rgba,
+        text: &[(&str, String)],
+        writer: impl Write,
+    ) -> Result<()> {
+        let (palette, transparent) = self.palette(background, left, right);
+        let mut encoder = Encoder::new(writer, self.width, self.height);
+        add_text_chunks(&mut encoder, text)?;
 
         // Configure the PNG encoder - use 2-bit depth since we only need 3 colors
+        // unless the balance strip or tinting need a bigger palette
         encoder.set_color(png::ColorType::Indexed);
-        encoder.set_depth(png::BitDepth::Two);
+        encoder.set_depth(match self.depth {
+            8 => png::BitDepth::Eight,
+            4 => png::BitDepth::Four,
+            _ => png::BitDepth::Two,
+        });
         encoder.set_palette(&palette);
         encoder.set_trns(&transparent);
 
@@ -272,4 +1756,20 @@ impl WaveImage {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Adds the keywords and texts as PNG text chunks, written before the image
+/// data so readers find them without decoding the pixels.
+///
+/// Latin-1 is all a tEXt chunk can hold, so any text that is not plain ASCII
+/// (such as a file name) is written as a UTF-8 iTXt chunk instead.
+pub fn add_text_chunks<W: Write>(encoder: &mut Encoder<'_, W>, text: &[(&str, String)]) -> Result<()> {
+    for (keyword, value) in text {
+        if value.is_ascii() {
+            encoder.add_text_chunk(keyword.to_string(), value.clone())?;
+        } else {
+            encoder.add_itxt_chunk(keyword.to_string(), value.clone())?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,102 @@
+//! A minimal encoder for the "Quite OK Image" format (QOI).
+//!
+//! QOI is a lossless RGBA format that encodes in a single pass with no
+//! entropy coding, so it is many times faster to write than a compressed
+//! PNG.  Waveforms are long runs of a few colors, which is the case QOI
+//! handles best (runs and the recently seen color index).  The format is
+//! small enough that it is simpler to write here than to add a dependency;
+//! see <https://qoiformat.org/qoi-specification.pdf>.
+
+use std::io::{self, Write};
+
+/// The 4 magic bytes at the start of every QOI file.
+const MAGIC: &[u8; 4] = b"qoif";
+
+/// The 8 bytes that mark the end of the pixel data.
+const END_MARKER: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+
+const OP_INDEX: u8 = 0x00;
+const OP_DIFF: u8 = 0x40;
+const OP_LUMA: u8 = 0x80;
+const OP_RUN: u8 = 0xc0;
+const OP_RGB: u8 = 0xfe;
+const OP_RGBA: u8 = 0xff;
+
+/// The longest run a single `OP_RUN` can encode.
+const MAX_RUN: u8 = 62;
+
+/// Returns the position of a color in the table of recently seen colors.
+fn hash(pixel: [u8; 4]) -> usize {
+    let [r, g, b, a] = pixel.map(|c| c as usize);
+    (r * 3 + g * 5 + b * 7 + a * 11) % 64
+}
+
+/// Encodes RGBA pixels (4 bytes each, top row first) as a QOI image.
+///
+/// The writer is not buffered here, the whole image is encoded in memory
+/// and written at once.
+pub fn encode(width: u32, height: u32, rgba: &[u8], mut writer: impl Write) -> io::Result<()> {
+    debug_assert_eq!(rgba.len(), width as usize * height as usize * 4);
+
+    let mut out = Vec::with_capacity(14 + rgba.len() / 8 + END_MARKER.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&width.to_be_bytes());
+    out.extend_from_slice(&height.to_be_bytes());
+    // 4 channels (RGBA), sRGB with linear alpha
+    out.extend_from_slice(&[4, 0]);
+
+    let mut seen = [[0u8; 4]; 64];
+    let mut previous = [0, 0, 0, 255];
+    let mut run = 0u8;
+
+    for chunk in rgba.chunks_exact(4) {
+        let pixel = [chunk[0], chunk[1], chunk[2], chunk[3]];
+        if pixel == previous {
+            run += 1;
+            if run == MAX_RUN {
+                out.push(OP_RUN | (run - 1));
+                run = 0;
+            }
+            continue;
+        }
+
+        if run > 0 {
+            out.push(OP_RUN | (run - 1));
+            run = 0;
+        }
+
+        let index = hash(pixel);
+        if seen[index] == pixel {
+            out.push(OP_INDEX | index as u8);
+        } else {
+            seen[index] = pixel;
+            if pixel[3] == previous[3] {
+                let dr = pixel[0].wrapping_sub(previous[0]) as i8;
+                let dg = pixel[1].wrapping_sub(previous[1]) as i8;
+                let db = pixel[2].wrapping_sub(previous[2]) as i8;
+                let dr_dg = dr.wrapping_sub(dg);
+                let db_dg = db.wrapping_sub(dg);
+
+                if (-2..=1).contains(&dr) && (-2..=1).contains(&dg) && (-2..=1).contains(&db) {
+                    out.push(OP_DIFF | ((dr + 2) as u8) << 4 | ((dg + 2) as u8) << 2 | (db + 2) as u8);
+                } else if (-32..=31).contains(&dg) && (-8..=7).contains(&dr_dg) && (-8..=7).contains(&db_dg) {
+                    out.push(OP_LUMA | (dg + 32) as u8);
+                    out.push(((dr_dg + 8) as u8) << 4 | (db_dg + 8) as u8);
+                } else {
+                    out.extend_from_slice(&[OP_RGB, pixel[0], pixel[1], pixel[2]]);
+                }
+            } else {
+                out.push(OP_RGBA);
+                out.extend_from_slice(&pixel);
+            }
+        }
+        previous = pixel;
+    }
+
+    if run > 0 {
+        out.push(OP_RUN | (run - 1));
+    }
+    out.extend_from_slice(&END_MARKER);
+
+    writer.write_all(&out)
+}
@@ -1,10 +1,15 @@
 #[cfg(test)]
 mod tests {
-    use crate::cli::{Width, Height};
-    use crate::image::{WaveImage, Channel};
+    use crate::backdrop::Backdrop;
+    use crate::cli::{Corner, MonoStyle, Overlap, Width, Height};
+    use crate::color::Rgba;
+    use crate::image::{WaveImage, ImageOptions, Channel, AXIS, TINT_LEVELS, BALANCE_LEVELS, LEFT_RAMP, RIGHT_RAMP, PEAK_SHADES, ramp_index};
 
     /// Helper extension trait for testing WaveImage
     trait WaveImageTest {
+        /// Gets the raw palette index at the given coordinates
+        fn get_index(&self, x: u32, y: u32) -> u8;
+
         /// Gets the pixel channel at the given coordinates
         fn get_pixel(&self, x: u32, y: u32) -> Channel;
 
@@ -19,20 +24,30 @@ mod tests {
     }
 
     impl WaveImageTest for WaveImage {
+        fn get_index(&self, x: u32, y: u32) -> u8 {
+            // The tinted image stores one palette index per byte
+            if self.is_tinted() {
+                return self.pixels[(x + y * self.line_width) as usize];
+            }
+
+            // Calculate the byte offset and bit position of the packed pixel
+            let depth = self.depth as u32;
+            let per_byte = 8 / depth;
+            let byte_offset = x / per_byte;
+            let bit_position = 8 - depth * (x % per_byte + 1);
+            let idx = (byte_offset + y * self.line_width) as usize;
+
+            // Extract the pixel bits from the byte
+            (self.pixels[idx] >> bit_position) & ((1 << depth) - 1) as u8
+        }
+
         fn get_pixel(&self, x: u32, y: u32) -> Channel {
             // Return background for out-of-bounds coordinates
             if x >= self.width() || y >= self.height {
                 return Channel::Background;
             }
 
-            // Calculate the byte offset and bit position
-            let byte_offset = x >> 2; // x / 4
-            let bit_position = 2 * (x & 3); // 2 * (x % 4)
-            let idx = (byte_offset + y * self.line_width) as usize;
-
-            // Extract the 2-bit value from the byte
-            let pixel_bits = (self.pixels[idx] >> bit_position) & 0x3;
-            Channel::from(pixel_bits)
+            Channel::from(self.get_index(x, y))
         }
 
         fn assert_vertical_line(&self, x: u32, y_start: u32, y_end: u32, expected_channel: Channel) {
@@ -78,7 +93,7 @@ mod tests {
             // Check dimensions
             assert_eq!(image.width(), width_val, "Image width should match requested width");
             assert_eq!(image.height, height_val, "Image height should match requested height");
-            assert_eq!(image.center, height_val / 2, "Center should be half the height");
+            assert_eq!(image.wave.center, height_val / 2, "Center should be half the height");
 
             // Check that all pixels are initialized to background
             image.assert_region_is_background(0, width_val, 0, height_val);
@@ -276,6 +291,482 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_png_pixel_order() {
+        // PNG expects the leftmost pixel of each byte in the high-order bits
+        let width = Width::new(16).unwrap();
+        let height = Height::new(6).unwrap();
+        let mut image = WaveImage::new(width, height);
+        image.draw_point_mono(0, 1.0);
+        image.draw_point_mono(3, 1.0);
+        assert_eq!(image.pixels[0], 0b0100_0001);
+
+        // Same for the 4-bit image used with the balance strip
+        let mut image = WaveImage::with_options(width, height, &ImageOptions { balance_strip: 2, ..Default::default() });
+        image.draw_point(0, 1.0, 1.0);
+        assert_eq!(image.pixels[0], 0b0001_0000);
+        assert_eq!(image.pixels[(image.line_width * 5) as usize], 0b0010_0000);
+    }
+
+    #[test]
+    fn test_clear() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(6).unwrap();
+        let mut image = WaveImage::with_options(width, height, &ImageOptions { tinted: true, ..Default::default() });
+        image.draw_point_tinted(3, 1.0, 1.0, 0.5, 0.5);
+        image.clear();
+        image.assert_region_is_background(0, 16, 0, 6);
+    }
+
+    #[test]
+    fn test_write_png_matches_save_png() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(6).unwrap();
+        let mut image = WaveImage::new(width, height);
+        image.draw_point(3, 0.5, 1.0);
+        let white = Rgba::rgb(255, 255, 255);
+        let black = Rgba::rgb(0, 0, 0);
+
+        let mut bytes = Vec::new();
+        image.write_png(&white, &black, &black, &mut bytes).unwrap();
+        assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wave.png");
+        image.save_png(&white, &black, &black, &path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_write_png_with_text() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(6).unwrap();
+        let image = WaveImage::new(width, height);
+        let white = Rgba::rgb(255, 255, 255);
+        let text = [("Duration", "12.500".to_string()), ("Source File", "Café.mp3".to_string())];
+
+        let mut bytes = Vec::new();
+        image.write_png_with_text(&white, &white, &white, &text, &mut bytes).unwrap();
+
+        let reader = png::Decoder::new(bytes.as_slice()).read_info().unwrap();
+        let info = reader.info();
+        let latin1: Vec<_> = info.uncompressed_latin1_text.iter().map(|t| (t.keyword.as_str(), t.text.as_str())).collect();
+        assert_eq!(latin1, [("Duration", "12.500")]);
+        assert_eq!(info.utf8_text.len(), 1, "Non-ASCII text is UTF-8");
+        assert_eq!(info.utf8_text[0].keyword, "Source File");
+        assert_eq!(info.utf8_text[0].get_text().unwrap(), "Café.mp3");
+    }
+
+    #[test]
+    fn test_write_checkerboard_png() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(6).unwrap();
+        let mut image = WaveImage::new(width, height);
+        image.draw_point(3, 1.0, 1.0);
+        let clear = Rgba::new(255, 255, 255, 0);
+        let left = Rgba::rgb(0, 0, 255);
+        let half = Rgba::new(0, 0, 0, 128);
+
+        let mut bytes = Vec::new();
+        image.write_checkerboard_png(&clear, &left, &half, 2, &mut bytes).unwrap();
+        let mut reader = png::Decoder::new(bytes.as_slice()).read_info().unwrap();
+        let mut rgb = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut rgb).unwrap();
+        assert_eq!((info.width, info.height, info.color_type), (16, 6, png::ColorType::Rgb));
+
+        let pixel = |x: usize, y: usize| &rgb[(y * 16 + x) * 3..(y * 16 + x) * 3 + 3];
+        assert_eq!(pixel(0, 0), [0xff, 0xff, 0xff], "Light square through the background");
+        assert_eq!(pixel(2, 0), [0xcc, 0xcc, 0xcc], "Dark square next to it");
+        assert_eq!(pixel(0, 2), [0xcc, 0xcc, 0xcc], "Dark square below it");
+        assert_eq!(pixel(3, 0), [0, 0, 255], "The opaque left channel covers the squares");
+        assert_eq!(pixel(3, 5), [0x66, 0x66, 0x66], "The half transparent right channel over a dark square");
+    }
+
+    #[test]
+    fn test_write_png_over() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(6).unwrap();
+        let mut image = WaveImage::new(width, height);
+        image.draw_point(3, 1.0, 1.0);
+        let clear = Rgba::new(255, 255, 255, 0);
+        let left = Rgba::rgb(0, 0, 255);
+        let half = Rgba::new(0, 0, 0, 128);
+        let backdrop = Backdrop::new(16, 6, vec![Rgba::rgb(200, 100, 50); 16 * 6]).unwrap();
+
+        let mut bytes = Vec::new();
+        image.write_png_over(&clear, &left, &half, &backdrop, &[], &mut bytes).unwrap();
+        let composite = Backdrop::read(bytes.as_slice()).unwrap();
+        assert_eq!(composite.pixel(0, 0), Rgba::rgb(200, 100, 50), "The backdrop through the background");
+        assert_eq!(composite.pixel(3, 0), left, "The opaque left channel covers the backdrop");
+        assert_eq!(composite.pixel(3, 5), Rgba::rgb(100, 50, 25), "The half transparent right channel");
+
+        let small = Backdrop::new(8, 6, vec![Rgba::rgb(0, 0, 0); 8 * 6]).unwrap();
+        assert!(image.write_png_over(&clear, &left, &half, &small, &[], Vec::new()).is_err(), "Not scaled to fit");
+    }
+
+    #[test]
+    fn test_flatten_blends_over_background() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(6).unwrap();
+        let mut image = WaveImage::new(width, height);
+        image.draw_point(3, 1.0, 1.0);
+        let background = Rgba::new(200, 100, 0, 0);
+        let left = Rgba::new(0, 0, 255, 255);
+        let half = Rgba::new(0, 100, 200, 128);
+
+        let rgb = image.flatten(&background, &left, &half);
+        assert_eq!(rgb.len(), 16 * 6 * 3);
+        let pixel = |x: usize, y: usize| &rgb[(x + y * 16) * 3..(x + y * 16) * 3 + 3];
+        assert_eq!(pixel(0, 0), [200, 100, 0], "The background is made opaque");
+        assert_eq!(pixel(3, 0), [0, 0, 255], "Opaque colors are kept");
+        assert_eq!(pixel(3, 5), [100, 100, 100], "Translucent colors are blended");
+    }
+
+    #[test]
+    fn test_write_jpeg() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(6).unwrap();
+        let mut image = WaveImage::new(width, height);
+        image.draw_point(3, 0.5, 1.0);
+        let white = Rgba::rgb(255, 255, 255);
+        let black = Rgba::rgb(0, 0, 0);
+
+        let mut bytes = Vec::new();
+        image.write_jpeg(&white, &black, &black, 85, &mut bytes).unwrap();
+        assert_eq!(&bytes[..2], [0xff, 0xd8], "Starts with the JPEG start marker");
+        assert_eq!(&bytes[bytes.len() - 2..], [0xff, 0xd9], "Ends with the JPEG end marker");
+    }
+
+    #[test]
+    fn test_write_bmp() {
+        let width = Width::new(17).unwrap();
+        let height = Height::new(6).unwrap();
+        let mut image = WaveImage::new(width, height);
+        image.draw_point(0, 1.0, 0.0);
+        let white = Rgba::rgb(255, 255, 255);
+        let blue = Rgba::rgb(0, 0, 255);
+
+        let mut bytes = Vec::new();
+        image.write_bmp(&white, &blue, &blue, &mut bytes).unwrap();
+        // Rows of 17 pixels are padded from 51 to 52 bytes
+        assert_eq!(bytes.len(), 54 + 52 * 6);
+        assert_eq!(&bytes[..2], b"BM");
+        assert_eq!(u32::from_le_bytes(bytes[2..6].try_into().unwrap()), bytes.len() as u32);
+        assert_eq!(u32::from_le_bytes(bytes[18..22].try_into().unwrap()), 17);
+        assert_eq!(u32::from_le_bytes(bytes[22..26].try_into().unwrap()), 6);
+
+        // The last row in the file is the top row of the image, stored as BGR
+        let top = 54 + 52 * 5;
+        assert_eq!(&bytes[top..top + 3], [255, 0, 0]);
+        assert_eq!(&bytes[top + 3..top + 6], [255, 255, 255]);
+        assert_eq!(&bytes[54..57], [255, 255, 255], "No right channel at the bottom");
+    }
+
+    #[test]
+    fn test_write_rgb565() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(6).unwrap();
+        let mut image = WaveImage::new(width, height);
+        image.draw_point(0, 1.0, 1.0);
+        let black = Rgba::rgb(0, 0, 0);
+        let left = Rgba::rgb(0xff, 0x00, 0x00);
+        let right = Rgba::rgb(0x00, 0x80, 0xff);
+
+        let mut bytes = Vec::new();
+        image.write_rgb565(&black, &left, &right, &mut bytes).unwrap();
+        assert_eq!(bytes.len(), 16 * 6 * 2);
+        assert_eq!(u16::from_le_bytes([bytes[0], bytes[1]]), 0xf800);
+        assert_eq!(u16::from_le_bytes([bytes[2], bytes[3]]), 0x0000);
+        let bottom = 16 * 5 * 2;
+        assert_eq!(u16::from_le_bytes([bytes[bottom], bytes[bottom + 1]]), 0x041f);
+    }
+
+    #[test]
+    fn test_write_ppm() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(6).unwrap();
+        let mut image = WaveImage::new(width, height);
+        image.draw_point(0, 1.0, 1.0);
+        let clear = Rgba::new(255, 255, 255, 0);
+        let red = Rgba::rgb(255, 0, 0);
+
+        let mut bytes = Vec::new();
+        image.write_ppm(&clear, &red, &red, &mut bytes).unwrap();
+        let header = b"P6\n16 6\n255\n";
+        assert_eq!(&bytes[..header.len()], header);
+        assert_eq!(bytes.len(), header.len() + 16 * 6 * 3);
+        assert_eq!(&bytes[header.len()..header.len() + 6], [255, 0, 0, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_write_ppm_rows_match_flatten() {
+        // An odd width leaves unused pixels at the end of each packed row
+        let width = Width::new(37).unwrap();
+        let height = Height::new(10).unwrap();
+        let options = ImageOptions { tinted: true, balance_strip: 3, ..Default::default() };
+        let mut image = WaveImage::with_options(width, height, &options);
+        for x in 0..37 {
+            let level = x as f32 / 36.0;
+            image.draw_point_tinted(x, level, 1.0 - level, level, 1.0 - level);
+            image.draw_balance(x, level * 2.0 - 1.0);
+        }
+        let background = Rgba::new(0x10, 0x20, 0x30, 0x80);
+        let left = Rgba::rgb(0x00, 0xff, 0x99);
+        let right = Rgba::new(0x99, 0xff, 0x00, 0xc0);
+
+        let mut bytes = Vec::new();
+        image.write_ppm(&background, &left, &right, &mut bytes).unwrap();
+        let header = b"P6\n37 13\n255\n";
+        assert_eq!(&bytes[..header.len()], header);
+        assert_eq!(&bytes[header.len()..], image.flatten(&background, &left, &right), "Written row by row");
+    }
+
+    #[test]
+    fn test_write_farbfeld() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(6).unwrap();
+        let mut image = WaveImage::new(width, height);
+        image.draw_point(0, 1.0, 1.0);
+        let clear = Rgba::new(255, 255, 255, 0);
+        let red = Rgba::new(255, 0, 0x80, 0xff);
+
+        let mut bytes = Vec::new();
+        image.write_farbfeld(&clear, &red, &red, &mut bytes).unwrap();
+        assert_eq!(&bytes[..8], b"farbfeld");
+        assert_eq!(&bytes[8..16], [0, 0, 0, 16, 0, 0, 0, 6]);
+        assert_eq!(bytes.len(), 16 + 16 * 6 * 8);
+        assert_eq!(&bytes[16..24], [0xff, 0xff, 0, 0, 0x80, 0x80, 0xff, 0xff]);
+        assert_eq!(&bytes[24..32], [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0, 0], "The background stays transparent");
+    }
+
+    /// Decodes a QOI image back to RGBA, following the specification.
+    fn decode_qoi(bytes: &[u8]) -> (u32, u32, Vec<u8>) {
+        assert_eq!(&bytes[..4], b"qoif");
+        let width = u32::from_be_bytes(bytes[4..8].try_into().unwrap());
+        let height = u32::from_be_bytes(bytes[8..12].try_into().unwrap());
+        assert_eq!(&bytes[bytes.len() - 8..], [0, 0, 0, 0, 0, 0, 0, 1]);
+
+        let mut seen = [[0u8; 4]; 64];
+        let mut pixel = [0u8, 0, 0, 255];
+        let mut rgba = Vec::new();
+        let mut data = bytes[14..bytes.len() - 8].iter().copied();
+        while let Some(op) = data.next() {
+            let mut run = 1;
+            match op {
+                0xfe => pixel[..3].copy_from_slice(&[data.next().unwrap(), data.next().unwrap(), data.next().unwrap()]),
+                0xff => pixel = [data.next().unwrap(), data.next().unwrap(), data.next().unwrap(), data.next().unwrap()],
+                _ => match op >> 6 {
+                    0 => pixel = seen[op as usize],
+                    1 => {
+                        pixel[0] = pixel[0].wrapping_add((op >> 4 & 3).wrapping_sub(2));
+                        pixel[1] = pixel[1].wrapping_add((op >> 2 & 3).wrapping_sub(2));
+                        pixel[2] = pixel[2].wrapping_add((op & 3).wrapping_sub(2));
+                    }
+                    2 => {
+                        let dg = (op & 0x3f).wrapping_sub(32);
+                        let next = data.next().unwrap();
+                        pixel[0] = pixel[0].wrapping_add(dg.wrapping_add((next >> 4).wrapping_sub(8)));
+                        pixel[1] = pixel[1].wrapping_add(dg);
+                        pixel[2] = pixel[2].wrapping_add(dg.wrapping_add((next & 0xf).wrapping_sub(8)));
+                    }
+                    _ => run = (op & 0x3f) as usize + 1,
+                },
+            }
+            let [r, g, b, a] = pixel.map(|c| c as usize);
+            seen[(r * 3 + g * 5 + b * 7 + a * 11) % 64] = pixel;
+            for _ in 0..run {
+                rgba.extend_from_slice(&pixel);
+            }
+        }
+        (width, height, rgba)
+    }
+
+    #[test]
+    fn test_write_qoi_round_trip() {
+        let width = Width::new(100).unwrap();
+        let height = Height::new(20).unwrap();
+        let options = ImageOptions { tinted: true, balance_strip: 3, ..Default::default() };
+        let mut image = WaveImage::with_options(width, height, &options);
+        for x in 0..100 {
+            let level = x as f32 / 99.0;
+            image.draw_point_tinted(x, level, 1.0 - level, level, 1.0 - level);
+            image.draw_balance(x, level * 2.0 - 1.0);
+        }
+        let background = Rgba::new(255, 255, 255, 0);
+        let left = Rgba::new(0x00, 0xff, 0x99, 0xff);
+        let right = Rgba::new(0x99, 0xff, 0x00, 0xc0);
+
+        let mut bytes = Vec::new();
+        image.write_qoi(&background, &left, &right, &mut bytes).unwrap();
+        let (decoded_width, decoded_height, rgba) = decode_qoi(&bytes);
+        assert_eq!((decoded_width, decoded_height), (100, 23));
+        assert_eq!(rgba, image.to_rgba(&background, &left, &right));
+    }
+
+    /// Decodes a WebP image back to its pixels, and whether they have alpha.
+    fn decode_webp(bytes: &[u8]) -> ((u32, u32), bool, Vec<u8>) {
+        let mut decoder = image_webp::WebPDecoder::new(std::io::Cursor::new(bytes)).unwrap();
+        let mut pixels = vec![0; decoder.output_buffer_size().unwrap()];
+        decoder.read_image(&mut pixels).unwrap();
+        (decoder.dimensions(), decoder.has_alpha(), pixels)
+    }
+
+    #[test]
+    fn test_write_webp_round_trip() {
+        let width = Width::new(100).unwrap();
+        let height = Height::new(20).unwrap();
+        let options = ImageOptions { tinted: true, balance_strip: 3, ..Default::default() };
+        let mut image = WaveImage::with_options(width, height, &options);
+        for x in 0..100 {
+            let level = x as f32 / 99.0;
+            image.draw_point_tinted(x, level, 1.0 - level, level, 1.0 - level);
+            image.draw_balance(x, level * 2.0 - 1.0);
+        }
+        let background = Rgba::new(255, 255, 255, 0);
+        let left = Rgba::new(0x00, 0xff, 0x99, 0xff);
+        let right = Rgba::new(0x99, 0xff, 0x00, 0xc0);
+
+        let mut bytes = Vec::new();
+        image.write_webp(&background, &left, &right, &mut bytes).unwrap();
+        assert_eq!(&bytes[8..12], b"WEBP");
+        let (dimensions, alpha, rgba) = decode_webp(&bytes);
+        assert_eq!(dimensions, (100, 23));
+        assert!(alpha);
+        assert_eq!(rgba, image.to_rgba(&background, &left, &right), "Lossless");
+    }
+
+    #[test]
+    fn test_write_webp_opaque() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(6).unwrap();
+        let mut image = WaveImage::new(width, height);
+        image.draw_point(3, 0.5, 1.0);
+        let white = Rgba::rgb(255, 255, 255);
+        let black = Rgba::rgb(0, 0, 0);
+
+        let mut bytes = Vec::new();
+        image.write_webp(&white, &black, &black, &mut bytes).unwrap();
+        let (dimensions, alpha, rgb) = decode_webp(&bytes);
+        assert_eq!(dimensions, (16, 6));
+        assert!(!alpha, "Opaque colors are written without alpha");
+        assert_eq!(rgb, image.flatten(&white, &black, &black));
+    }
+
+    #[cfg(feature = "avif")]
+    #[test]
+    fn test_write_avif() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(6).unwrap();
+        let mut image = WaveImage::new(width, height);
+        image.draw_point(3, 0.5, 1.0);
+        let clear = Rgba::new(255, 255, 255, 0);
+        let black = Rgba::rgb(0, 0, 0);
+
+        let mut bytes = Vec::new();
+        image.write_avif(&clear, &black, &black, 85, &mut bytes).unwrap();
+        assert_eq!(&bytes[4..12], b"ftypavif", "Starts with the AVIF file type box");
+    }
+
+    #[test]
+    fn test_antialias() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(20).unwrap();
+        let mut image = WaveImage::with_options(width, height, &ImageOptions { antialias: true, ..Default::default() });
+        assert!(image.is_antialiased());
+        assert!(!image.is_tinted());
+
+        // 2.5 rows up and 4.25 rows down from the center (half is 10 rows)
+        image.draw_point(0, 0.25, 0.425);
+        assert_eq!(image.get_index(0, 6), 0, "Above the bar");
+        assert_eq!(image.get_index(0, 7), ramp_index(LEFT_RAMP, 0.5), "Partly covered end");
+        assert_eq!(image.get_index(0, 8), Channel::Left as u8);
+        assert_eq!(image.get_index(0, 9), Channel::Left as u8);
+        assert_eq!(image.get_index(0, 10), Channel::Right as u8);
+        assert_eq!(image.get_index(0, 13), Channel::Right as u8);
+        assert_eq!(image.get_index(0, 14), ramp_index(RIGHT_RAMP, 0.25), "Partly covered end");
+        assert_eq!(image.get_index(0, 15), 0);
+
+        // Mono bars get a partly covered row at both ends
+        image.draw_point_mono(1, 0.15);
+        assert_eq!(image.get_index(1, 8), ramp_index(LEFT_RAMP, 0.5));
+        assert_eq!(image.get_index(1, 9), Channel::Left as u8);
+        assert_eq!(image.get_index(1, 10), Channel::Left as u8);
+        assert_eq!(image.get_index(1, 11), ramp_index(LEFT_RAMP, 0.5));
+
+        // Full scale bars have no room for a partial row
+        image.draw_point(2, 1.0, 1.0);
+        assert_eq!(image.get_index(2, 0), Channel::Left as u8);
+        assert_eq!(image.get_index(2, 19), Channel::Right as u8);
+    }
+
+    // Test what the pixels covered by both overlay bars are for each overlap
+    #[test]
+    fn test_overlap() {
+        let overlay = |options: &ImageOptions| {
+            let mut image = WaveImage::with_options(Width::new(16).unwrap(), Height::new(20).unwrap(), options);
+            // 2 rows each way from the center for the first, 4 for the second
+            image.draw_overlay_point(0, 0.2, 0.4);
+            image.draw_overlay_point(1, 0.4, 0.2);
+            image
+        };
+        let rows = |image: &WaveImage, x: u32| (6..14).map(|y| image.get_index(x, y)).collect::<Vec<_>>();
+        let (left, right) = (Channel::Left as u8, Channel::Right as u8);
+
+        let image = overlay(&ImageOptions::default());
+        assert_eq!(rows(&image, 0), [right, right, AXIS, AXIS, AXIS, AXIS, right, right], "Only the differences");
+        assert_eq!(rows(&image, 1), [left, left, AXIS, AXIS, AXIS, AXIS, left, left]);
+
+        let image = overlay(&ImageOptions { overlap: Overlap::KeepLeft, ..Default::default() });
+        assert_eq!(rows(&image, 0), [right, right, left, left, left, left, right, right]);
+        assert_eq!(rows(&image, 1), [left; 8]);
+
+        let image = overlay(&ImageOptions { overlap: Overlap::KeepRight, ..Default::default() });
+        assert_eq!(rows(&image, 0), [right; 8]);
+        assert_eq!(rows(&image, 1), [left, left, right, right, right, right, left, left]);
+
+        // The 8-bit image does not combine the bits, so it gets the same
+        let image = overlay(&ImageOptions { antialias: true, ..Default::default() });
+        assert_eq!(rows(&image, 0), [right, right, AXIS, AXIS, AXIS, AXIS, right, right]);
+        let image = overlay(&ImageOptions { antialias: true, overlap: Overlap::KeepLeft, ..Default::default() });
+        assert_eq!(rows(&image, 1), [left; 8]);
+
+        // The left and right bars of a stereo point never overlap
+        let mut image = WaveImage::with_options(Width::new(16).unwrap(), Height::new(20).unwrap(), &ImageOptions::default());
+        image.draw_point(0, 1.0, 1.0);
+        image.assert_vertical_line(0, 0, 10, Channel::Left);
+        image.assert_vertical_line(0, 10, 20, Channel::Right);
+    }
+
+    #[test]
+    fn test_tile() {
+        let width = Width::new(40).unwrap();
+        let height = Height::new(8).unwrap();
+        for options in [
+            ImageOptions::default(),
+            ImageOptions { balance_strip: 2, ..Default::default() },
+            ImageOptions { tinted: true, ..Default::default() },
+        ] {
+            let mut image = WaveImage::with_options(width, height, &options);
+            for x in 0..40 {
+                image.draw_point(x, (x % 5) as f32 / 4.0, (x % 3) as f32 / 2.0);
+            }
+
+            let tile = image.tile(16, 16);
+            assert_eq!((tile.width(), tile.height()), (16, image.height()));
+            let last = image.tile(32, 16);
+            assert_eq!(last.width(), 8, "The last tile is clipped to the image");
+
+            for y in 0..image.height() {
+                for x in 0..16 {
+                    assert_eq!(tile.get_index(x, y), image.get_index(16 + x, y), "({x}, {y})");
+                }
+                for x in 0..8 {
+                    assert_eq!(last.get_index(x, y), image.get_index(32 + x, y), "({x}, {y})");
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_pattern_drawing() {
         // Test drawing a simple pattern and verifying it
@@ -317,6 +808,698 @@ mod tests {
         neg_image.assert_region_is_background(0, 32, 0, 32);
     }
 
+    #[test]
+    fn test_tinted_point_drawing() {
+        let width = Width::new(100).unwrap();
+        let height = Height::new(60).unwrap();
+        let mut image = WaveImage::with_options(width, height, &ImageOptions { tinted: true, ..Default::default() });
+
+        assert!(image.is_tinted());
+        assert_eq!(image.pixels.len(), 100 * 60, "Tinted image uses one byte per pixel");
+
+        // Quiet left channel and loud right channel
+        image.draw_point_tinted(10, 0.5, 0.5, 0.0, 1.0);
+        for y in 15..30 {
+            assert_eq!(image.get_index(10, y), LEFT_RAMP, "Quiet left should use the darkest ramp entry");
+        }
+        for y in 30..45 {
+            assert_eq!(image.get_index(10, y), RIGHT_RAMP + (TINT_LEVELS - 1), "Loud right should use the brightest ramp entry");
+        }
+        image.assert_vertical_line(10, 0, 15, Channel::Background);
+        image.assert_vertical_line(10, 45, 60, Channel::Background);
+
+        // Mono tinting uses the left ramp and out of range levels are clamped
+        image.draw_point_mono_tinted(20, 0.5, 2.0);
+        image.draw_point_mono_tinted(21, 0.5, -1.0);
+        for y in 15..45 {
+            assert_eq!(image.get_index(20, y), LEFT_RAMP + (TINT_LEVELS - 1));
+            assert_eq!(image.get_index(21, y), LEFT_RAMP);
+        }
+
+        // Neighboring columns are untouched and out of bounds draws are ignored
+        image.assert_vertical_line(11, 0, 60, Channel::Background);
+        let initial_pixels = image.pixels.clone();
+        image.draw_point_tinted(100, 1.0, 1.0, 1.0, 1.0);
+        image.draw_point_mono_tinted(100, 1.0, 1.0);
+        assert_eq!(image.pixels, initial_pixels);
+    }
+
+    #[test]
+    fn test_tinted_drawing_on_plain_image() {
+        // Without the tint palette the levels are ignored
+        let width = Width::new(100).unwrap();
+        let height = Height::new(60).unwrap();
+        let mut image = WaveImage::new(width, height);
+
+        image.draw_point_tinted(10, 0.5, 0.5, 0.3, 0.9);
+        image.assert_vertical_line(10, 15, 30, Channel::Left);
+        image.assert_vertical_line(10, 30, 45, Channel::Right);
+
+        image.draw_point_mono_tinted(20, 0.5, 0.7);
+        image.assert_vertical_line(20, 15, 45, Channel::Left);
+    }
+
+    #[test]
+    fn test_rms_layer() {
+        let width = Width::new(100).unwrap();
+        let height = Height::new(60).unwrap();
+        let mut image = WaveImage::with_options(width, height, &ImageOptions { rms_layer: true, ..Default::default() });
+        assert_eq!(image.depth, 4, "The peak shades fit a 4-bit image");
+
+        // The RMS is drawn from the center in the channel color, the rest of
+        // the peak in the channel's peak shade
+        image.draw_point_layered(10, 0.5, 1.0, 0.25, 0.5);
+        image.assert_vertical_line(10, 0, 15, Channel::Background);
+        (15..22).for_each(|y| assert_eq!(image.get_index(10, y), PEAK_SHADES));
+        image.assert_vertical_line(10, 22, 30, Channel::Left);
+        image.assert_vertical_line(10, 30, 45, Channel::Right);
+        (45..60).for_each(|y| assert_eq!(image.get_index(10, y), PEAK_SHADES + 1));
+
+        // Mono is symmetric in the left shades and the RMS never exceeds the peak
+        image.draw_point_mono_layered(20, 0.5, 0.25);
+        (15..22).chain(38..45).for_each(|y| assert_eq!(image.get_index(20, y), PEAK_SHADES));
+        image.assert_vertical_line(20, 22, 38, Channel::Left);
+        image.draw_point_mono_layered(21, 0.5, 0.9);
+        image.assert_vertical_line(21, 15, 45, Channel::Left);
+
+        // The shades are halfway between the channel colors and the background
+        let background = Rgba::new(0, 0, 0, 0);
+        let (palette, alpha) = image.palette(&background, &Rgba::rgb(200, 100, 0), &Rgba::rgb(0, 50, 250));
+        assert_eq!(&palette[12..18], [100, 50, 0, 0, 25, 125]);
+        assert_eq!(&alpha[4..6], [128, 128]);
+
+        // With a balance strip the ramp follows the shades in an 8-bit image
+        let options = ImageOptions { rms_layer: true, balance_strip: 2, ..Default::default() };
+        let mut image = WaveImage::with_options(width, height, &options);
+        assert_eq!(image.depth, 8);
+        assert!(!image.is_tinted());
+        image.draw_balance(5, -1.0);
+        assert_eq!(image.get_index(5, 60), PEAK_SHADES + 2);
+
+        // Without the shades only the peaks are drawn
+        let mut plain = WaveImage::new(width, height);
+        plain.draw_point_layered(10, 0.5, 0.5, 0.25, 0.25);
+        plain.assert_vertical_line(10, 15, 30, Channel::Left);
+        plain.assert_vertical_line(10, 30, 45, Channel::Right);
+    }
+
+    #[test]
+    fn test_balance_strip() {
+        let width = Width::new(100).unwrap();
+        let height = Height::new(60).unwrap();
+        let mut image = WaveImage::with_options(width, height, &ImageOptions { balance_strip: 4, ..Default::default() });
+
+        // The strip is added below the waveform in a 4-bit image
+        assert_eq!(image.height, 64);
+        assert_eq!(image.wave.center, 30);
+        assert_eq!(image.line_width, 50, "4-bit image packs 2 pixels per byte");
+        assert_eq!(image.pixels.len(), 50 * 64);
+
+        // Full amplitude stops at the bottom of the waveform area
+        image.draw_point(10, 1.0, 1.0);
+        image.assert_vertical_line(10, 0, 30, Channel::Left);
+        image.assert_vertical_line(10, 30, 60, Channel::Right);
+        image.assert_vertical_line(10, 60, 64, Channel::Background);
+        image.draw_point_mono(11, 1.0);
+        image.assert_vertical_line(11, 0, 60, Channel::Left);
+        image.assert_vertical_line(11, 60, 64, Channel::Background);
+
+        // Hard left, centered and hard right map to the ends and middle of the ramp
+        image.draw_balance(20, -1.0);
+        image.draw_balance(21, 0.0);
+        image.draw_balance(22, 1.0);
+        image.draw_balance(23, 5.0);
+        for y in 60..64 {
+            assert_eq!(image.get_index(20, y), 4);
+            assert_eq!(image.get_index(21, y), 4 + BALANCE_LEVELS / 2);
+            assert_eq!(image.get_index(22, y), 4 + BALANCE_LEVELS - 1);
+            assert_eq!(image.get_index(23, y), 4 + BALANCE_LEVELS - 1);
+        }
+
+        // The waveform area of those columns is untouched
+        image.assert_region_is_background(20, 24, 0, 60);
+
+        // Out of bounds is ignored
+        let initial_pixels = image.pixels.clone();
+        image.draw_balance(100, 0.0);
+        assert_eq!(image.pixels, initial_pixels);
+    }
+
+    #[test]
+    fn test_balance_strip_with_tint() {
+        let width = Width::new(100).unwrap();
+        let height = Height::new(60).unwrap();
+        let mut image = WaveImage::with_options(width, height, &ImageOptions { tinted: true, balance_strip: 2, ..Default::default() });
+
+        // The balance ramp follows the two loudness ramps in the 8-bit palette
+        let balance_ramp = RIGHT_RAMP + TINT_LEVELS;
+        image.draw_balance(5, -1.0);
+        image.draw_balance(6, 1.0);
+        for y in 60..62 {
+            assert_eq!(image.get_index(5, y), balance_ramp);
+            assert_eq!(image.get_index(6, y), balance_ramp + BALANCE_LEVELS - 1);
+        }
+
+        // A plain image has no strip so drawing the balance does nothing
+        let mut plain = WaveImage::new(width, height);
+        let initial_pixels = plain.pixels.clone();
+        plain.draw_balance(5, 1.0);
+        assert_eq!(plain.pixels, initial_pixels);
+    }
+
+    #[test]
+    fn test_composite_overview() {
+        let width = Width::new(100).unwrap();
+        let height = Height::new(64).unwrap();
+        let options = ImageOptions { overview_ratio: Some(0.25), ..Default::default() };
+        let mut image = WaveImage::with_options(width, height, &options);
+
+        // The overview takes a quarter of the height below the detail waveform
+        assert_eq!(image.height, 64);
+        assert_eq!((image.wave.top, image.wave.center, image.wave.bottom), (0, 24, 48));
+        let overview = image.overview.unwrap();
+        assert_eq!((overview.top, overview.center, overview.bottom), (48, 56, 64));
+
+        // Full amplitude fills each section without spilling into the other
+        image.draw_point(10, 1.0, 1.0);
+        image.assert_vertical_line(10, 0, 24, Channel::Left);
+        image.assert_vertical_line(10, 24, 48, Channel::Right);
+        image.assert_vertical_line(10, 48, 64, Channel::Background);
+
+        image.draw_overview_point(20, 1.0);
+        image.assert_vertical_line(20, 0, 48, Channel::Background);
+        image.assert_vertical_line(20, 48, 64, Channel::Left);
+
+        // Half amplitude is scaled to the overview height
+        image.draw_overview_point(30, 0.5);
+        image.assert_vertical_line(30, 52, 60, Channel::Left);
+        image.assert_vertical_line(30, 48, 52, Channel::Background);
+        image.assert_vertical_line(30, 60, 64, Channel::Background);
+
+        // Without an overview nothing is drawn
+        let mut plain = WaveImage::new(width, height);
+        let initial_pixels = plain.pixels.clone();
+        plain.draw_overview_point(20, 1.0);
+        assert_eq!(plain.pixels, initial_pixels);
+    }
+
+    #[test]
+    fn test_composite_layout_limits() {
+        // Sections are kept even and at least 2 rows tall
+        let width = Width::new(16).unwrap();
+        let height = Height::new(6).unwrap();
+        let tiny = ImageOptions { overview_ratio: Some(0.01), ..Default::default() };
+        let image = WaveImage::with_options(width, height, &tiny);
+        assert_eq!(image.wave.bottom, 4);
+        assert_eq!(image.overview.unwrap().bottom, 6);
+
+        // The overview sits between the detail waveform and the balance strip
+        let height = Height::new(40).unwrap();
+        let options = ImageOptions { overview_ratio: Some(0.5), balance_strip: 3, ..Default::default() };
+        let mut image = WaveImage::with_options(width, height, &options);
+        assert_eq!(image.height, 43);
+        assert_eq!(image.wave.bottom, 20);
+        assert_eq!(image.overview.unwrap().bottom, 40);
+        image.draw_overview_point(0, 1.0);
+        image.draw_balance(0, 0.0);
+        image.assert_vertical_line(0, 20, 40, Channel::Left);
+        for y in 40..43 {
+            assert_eq!(image.get_index(0, y), 4 + BALANCE_LEVELS / 2);
+        }
+    }
+
+    #[test]
+    fn test_envelope_drawing() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(20).unwrap();
+
+        // A symmetric mono envelope is the same as the peak bar
+        let mut bars = WaveImage::new(width, height);
+        let mut envelope = WaveImage::new(width, height);
+        for (x, amplitude) in [(0, 0.5), (1, 0.33), (2, 1.0), (3, 0.04)] {
+            bars.draw_point_mono(x, amplitude);
+            envelope.draw_envelope_point_mono(x, (-amplitude, amplitude));
+        }
+        assert_eq!(bars.pixels, envelope.pixels);
+
+        // An offset envelope stays on its side of the center
+        let mut image = WaveImage::new(width, height);
+        image.draw_envelope_point_mono(5, (0.2, 0.6));
+        image.assert_vertical_line(5, 0, 4, Channel::Background);
+        image.assert_vertical_line(5, 4, 8, Channel::Left);
+        image.assert_vertical_line(5, 8, 20, Channel::Background);
+
+        // Stereo channels each get half of the height around their own center
+        let mut image = WaveImage::new(width, height);
+        image.draw_envelope_point(7, (-1.0, 1.0), (-0.4, 0.0));
+        image.assert_vertical_line(7, 0, 10, Channel::Left);
+        image.assert_vertical_line(7, 10, 15, Channel::Background);
+        image.assert_vertical_line(7, 15, 17, Channel::Right);
+        image.assert_vertical_line(7, 17, 20, Channel::Background);
+    }
+
+    #[test]
+    fn test_lanes() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(20).unwrap();
+        let options = ImageOptions { lanes: 3, ..Default::default() };
+        let mut image = WaveImage::with_options(width, height, &options);
+        let tops: Vec<u32> = image.lanes.iter().map(|lane| lane.top).collect();
+        assert_eq!(tops, [0, 6, 13]);
+
+        // Each lane is centered on its own and alternates the colors
+        image.draw_lane_point(0, 0, 1.0);
+        image.draw_lane_point(0, 1, 1.0);
+        image.draw_lane_point(0, 2, 0.5);
+        image.assert_vertical_line(0, 0, 6, Channel::Left);
+        image.assert_vertical_line(0, 6, 12, Channel::Right);
+        image.assert_vertical_line(0, 12, 14, Channel::Background);
+        image.assert_vertical_line(0, 14, 18, Channel::Left);
+        image.assert_vertical_line(0, 18, 20, Channel::Background);
+
+        // Without lanes the point is drawn like a mono point
+        let mut mono = WaveImage::new(width, height);
+        let mut lane = WaveImage::new(width, height);
+        mono.draw_point_mono(3, 0.5);
+        lane.draw_lane_point(3, 0, 0.5);
+        assert_eq!(mono.pixels, lane.pixels);
+    }
+
+    #[test]
+    fn test_stems() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(20).unwrap();
+        let depth = |stems| WaveImage::with_options(width, height, &ImageOptions { stems, ..Default::default() }).depth;
+        assert_eq!((depth(2), depth(3), depth(4), depth(5), depth(8)), (2, 4, 4, 8, 8));
+
+        // The layers keep the bits of every stem covering a pixel
+        let mut image = WaveImage::with_options(width, height, &ImageOptions { stems: 3, ..Default::default() });
+        image.draw_stem_point(0, 0, 1.0);
+        image.draw_stem_point(0, 1, 0.5);
+        image.draw_stem_point(0, 2, 0.2);
+        assert_eq!(image.get_index(0, 0), 0b001);
+        assert_eq!(image.get_index(0, 6), 0b011);
+        assert_eq!(image.get_index(0, 9), 0b111);
+        image.draw_stem_point(1, 8, 1.0);
+        image.assert_vertical_line(1, 0, 20, Channel::Background);
+
+        // The 8-bit image combines the bits too
+        let mut image = WaveImage::with_options(width, height, &ImageOptions { stems: 6, ..Default::default() });
+        image.draw_stem_point(0, 5, 1.0);
+        image.draw_stem_point(0, 0, 0.5);
+        assert_eq!((image.get_index(0, 0), image.get_index(0, 10)), (0b100000, 0b100001));
+
+        // With lanes every stem is drawn in its own
+        let options = ImageOptions { stems: 2, lanes: 2, ..Default::default() };
+        let mut image = WaveImage::with_options(width, height, &options);
+        image.draw_stem_point(0, 0, 1.0);
+        image.draw_stem_point(0, 1, 1.0);
+        image.assert_vertical_line(0, 0, 10, Channel::Left);
+        image.assert_vertical_line(0, 10, 20, Channel::Right);
+
+        // A single stem is its own color, overlaps blend the later stem over
+        let red = Rgba::rgb(255, 0, 0);
+        let blue = Rgba::rgb(0, 0, 255);
+        image.set_stem_colors(&[red, blue]);
+        let background = Rgba::new(255, 255, 255, 0);
+        let (palette, transparent) = image.palette(&background, &Rgba::rgb(0, 255, 0), &Rgba::rgb(0, 255, 0));
+        assert_eq!(palette, [255, 255, 255, 255, 0, 0, 0, 0, 255, 128, 0, 128]);
+        assert_eq!(transparent, [0, 255, 255, 255]);
+        assert_eq!(image.tile(0, 8).palette(&background, &red, &red).0, palette, "Tiles keep the stem colors");
+        image.clear();
+        assert_eq!(image.palette(&background, &red, &blue).0[3..9], [255, 0, 0, 0, 0, 255], "Cleared back to the channel colors");
+    }
+
+    #[test]
+    fn test_micro_sizes() {
+        // The smallest micro image has one row per channel
+        let width = Width::new_micro(5).unwrap();
+        let height = Height::new_micro(2).unwrap();
+        let mut image = WaveImage::new(width, height);
+        image.draw_point(0, 1.0, 1.0);
+        image.draw_point(1, 0.4, 0.6);
+        image.draw_point(4, 1.0, 0.0);
+        image.assert_vertical_line(0, 0, 1, Channel::Left);
+        image.assert_vertical_line(0, 1, 2, Channel::Right);
+        image.assert_vertical_line(1, 0, 1, Channel::Background);
+        image.assert_vertical_line(1, 1, 2, Channel::Right);
+        image.assert_vertical_line(4, 0, 1, Channel::Left);
+        image.assert_vertical_line(4, 1, 2, Channel::Background);
+
+        // Too low for an overview, so all rows stay with the waveform
+        let options = ImageOptions { overview_ratio: Some(0.5), ..Default::default() };
+        let image = WaveImage::with_options(width, height, &options);
+        assert!(image.overview.is_none());
+        assert_eq!(image.wave.bottom, 2);
+
+        // The anti-aliased edge of a partial bar lands in the single row
+        let options = ImageOptions { antialias: true, ..Default::default() };
+        let mut image = WaveImage::with_options(width, height, &options);
+        image.draw_point(2, 0.5, 0.25);
+        assert_eq!(image.get_index(2, 0), ramp_index(LEFT_RAMP, 0.5));
+        assert_eq!(image.get_index(2, 1), ramp_index(RIGHT_RAMP, 0.25));
+    }
+
+    #[test]
+    fn test_set_pixel_and_fill_rect() {
+        let width = Width::new(20).unwrap();
+        let height = Height::new(10).unwrap();
+        let mut image = WaveImage::new(width, height);
+
+        // Setting replaces the bits rather than OR-ing them
+        image.draw_point_mono(5, 1.0);
+        image.set_pixel(5, 2, Channel::Right as u8);
+        assert_eq!(image.get_pixel(5, 2), Channel::Right);
+        image.set_pixel(5, 2, Channel::Background as u8);
+        assert_eq!(image.get_pixel(5, 2), Channel::Background);
+
+        // Neighbors sharing the same byte are not disturbed
+        image.assert_vertical_line(4, 0, 10, Channel::Background);
+        image.assert_vertical_line(6, 0, 10, Channel::Background);
+        assert_eq!(image.get_pixel(5, 1), Channel::Left);
+        assert_eq!(image.get_pixel(5, 3), Channel::Left);
+
+        // Rectangles are clipped to the image
+        image.fill_rect(15, 6, 40, 40, Channel::Right as u8);
+        image.assert_region(15, 20, 6, 10, Channel::Right);
+        image.assert_region_is_background(15, 20, 0, 6);
+        image.set_pixel(20, 0, Channel::Right as u8);
+        image.set_pixel(0, 10, Channel::Right as u8);
+    }
+
+    #[test]
+    fn test_draw_lines() {
+        // Every depth, with lines starting and ending inside a byte
+        let options = [
+            ImageOptions::default(),
+            ImageOptions { rms_layer: true, ..Default::default() },
+            ImageOptions { antialias: true, ..Default::default() },
+        ];
+        for (options, depth) in options.iter().zip([2, 4, 8]) {
+            let width = Width::new(37).unwrap();
+            let height = Height::new(10).unwrap();
+            let mut image = WaveImage::with_options(width, height, options);
+            assert_eq!(image.depth, depth);
+
+            image.draw_point(20, 1.0, 1.0);
+            image.draw_hline(3, 30, 2, Channel::Right as u8);
+            for x in 0..37 {
+                let expected = if (3..30).contains(&x) { Channel::Right as u8 } else { 0 };
+                assert_eq!(image.get_index(x, 2), expected, "Pixel ({x}, 2) at depth {depth}");
+            }
+            image.assert_region_is_background(0, 20, 1, 2);
+            image.assert_region_is_background(0, 20, 3, 4);
+
+            // The line replaces the waveform, like set_pixel
+            image.draw_hline(0, 37, 4, Channel::Background as u8);
+            image.assert_region_is_background(0, 37, 4, 5);
+            assert_eq!(image.get_index(20, 3), Channel::Left as u8);
+
+            image.draw_vline(36, 5, 20, Channel::Left as u8);
+            image.assert_vertical_line(36, 5, 10, Channel::Left);
+            image.assert_vertical_line(36, 0, 2, Channel::Background);
+
+            // A short line within a single byte
+            image.draw_hline(9, 11, 8, Channel::Left as u8);
+            assert_eq!(image.get_index(8, 8), 0);
+            image.assert_region(9, 11, 8, 9, Channel::Left);
+            assert_eq!(image.get_index(11, 8), 0);
+        }
+    }
+
+    #[test]
+    fn test_lines_are_clipped() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(6).unwrap();
+        let mut image = WaveImage::new(width, height);
+        let empty = image.pixels.clone();
+
+        image.draw_hline(0, 16, 6, Channel::Left as u8);
+        image.draw_hline(16, 40, 0, Channel::Left as u8);
+        image.draw_hline(10, 5, 0, Channel::Left as u8);
+        image.draw_vline(16, 0, 6, Channel::Left as u8);
+        image.draw_vline(0, 6, 10, Channel::Left as u8);
+        image.fill_rect(16, 6, 30, 30, Channel::Left as u8);
+        assert_eq!(image.pixels, empty);
+
+        image.draw_hline(12, 100, 5, Channel::Right as u8);
+        image.assert_region(12, 16, 5, 6, Channel::Right);
+    }
+
+    #[test]
+    fn test_draw_axis() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(10).unwrap();
+        let mut image = WaveImage::new(width, height);
+        image.draw_point(5, 1.0, 1.0);
+        image.draw_point(6, 0.0, 0.2);
+        let axis = Rgba::new(0x80, 0x80, 0x80, 0x40);
+        image.draw_axis(&axis, true);
+
+        // The line is behind the waveform: the center row belongs to the
+        // right bar, which covers it where it is drawn
+        for x in 0..16 {
+            let expected = if x == 5 || x == 6 { Channel::Right as u8 } else { AXIS };
+            assert_eq!(image.get_index(x, 5), expected, "Center pixel {x}");
+            let top = if x == 5 { Channel::Left as u8 } else { AXIS };
+            assert_eq!(image.get_index(x, 0), top, "Top border pixel {x}");
+        }
+        assert_eq!(image.get_index(4, 9), AXIS, "Bottom border");
+        image.assert_region_is_background(0, 5, 1, 5);
+
+        // The axis entry has the axis color, and the background otherwise
+        let white = Rgba::rgb(255, 255, 255);
+        let black = Rgba::rgb(0, 0, 0);
+        let (palette, transparent) = image.palette(&white, &black, &black);
+        assert_eq!(&palette[9..12], &[0x80, 0x80, 0x80]);
+        assert_eq!(transparent[3], 0x40);
+        assert_eq!(image.tile(0, 8).palette(&white, &black, &black).0[9..12], [0x80, 0x80, 0x80]);
+        image.clear();
+        assert_eq!(image.palette(&white, &black, &black).0[9..12], [255, 255, 255]);
+    }
+
+    #[test]
+    fn test_draw_axis_lanes() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(20).unwrap();
+        let options = ImageOptions { lanes: 2, overview_ratio: Some(0.2), ..Default::default() };
+        let mut image = WaveImage::with_options(width, height, &options);
+        image.draw_axis(&Rgba::rgb(1, 2, 3), false);
+
+        // Lanes of 8 rows over the overview of 4 rows, each with its center
+        let rows: Vec<u32> = (0..20).filter(|&y| image.get_index(0, y) == AXIS).collect();
+        assert_eq!(rows, [4, 12, 18]);
+    }
+
+    #[test]
+    fn test_mono_styles() {
+        let width = Width::new(16).unwrap();
+        let height = Height::new(20).unwrap();
+        let styled = |mono_style| {
+            let options = ImageOptions { mono_style, ..Default::default() };
+            WaveImage::with_options(width, height, &options)
+        };
+
+        // Half the amplitude covers half of the whole height from the baseline
+        let mut top = styled(MonoStyle::Top);
+        top.draw_point_mono(2, 0.5);
+        top.assert_vertical_line(2, 10, 20, Channel::Left);
+        top.assert_region_is_background(2, 3, 0, 10);
+        let mut bottom = styled(MonoStyle::Bottom);
+        bottom.draw_point_mono_tinted(2, 0.25, 1.0);
+        bottom.assert_vertical_line(2, 0, 5, Channel::Left);
+        bottom.assert_region_is_background(2, 3, 5, 20);
+
+        // The axis is drawn on the baseline, stereo points stay centered
+        top.draw_point(3, 0.5, 0.5);
+        top.assert_vertical_line(3, 5, 10, Channel::Left);
+        top.assert_vertical_line(3, 10, 15, Channel::Right);
+        top.draw_axis(&Rgba::rgb(1, 2, 3), false);
+        assert_eq!(top.get_index(0, 19), AXIS);
+        assert_eq!(top.get_index(0, 10), Channel::Background as u8);
+        let mut mirrored = styled(MonoStyle::Mirrored);
+        mirrored.draw_point_mono(2, 0.5);
+        mirrored.assert_vertical_line(2, 5, 15, Channel::Left);
+    }
+
+    #[test]
+    fn test_mono_style_rms_layer() {
+        let options = ImageOptions { rms_layer: true, mono_style: MonoStyle::Top, ..Default::default() };
+        let mut image = WaveImage::with_options(Width::new(16).unwrap(), Height::new(20).unwrap(), &options);
+        image.draw_point_mono_layered(1, 0.5, 0.25);
+
+        // The RMS from the baseline and the peak shade on top of it
+        image.assert_region_is_background(1, 2, 0, 10);
+        for y in 10..15 {
+            assert_eq!(image.get_index(1, y), PEAK_SHADES, "Peak shade at {y}");
+        }
+        for y in 15..20 {
+            assert_eq!(image.get_index(1, y), Channel::Left as u8, "RMS at {y}");
+        }
+    }
+
+    #[test]
+    fn test_draw_grid() {
+        let width = Width::new(32).unwrap();
+        let height = Height::new(12).unwrap();
+        let options = ImageOptions { overview_ratio: Some(0.25), ..Default::default() };
+        let mut image = WaveImage::with_options(width, height, &options);
+        image.draw_point(10, 1.0, 1.0);
+        image.draw_grid(&Rgba::rgb(1, 2, 3), 2.5, 7.5);
+
+        // Lines at 2.5, 10, 17.5 and 25 rounded, behind the bar at 10 and
+        // over the waveform section only
+        for y in 0..8 {
+            for x in 0..32 {
+                let expected = match x {
+                    10 => image.get_index(x, y),
+                    3 | 18 | 25 => AXIS,
+                    _ => Channel::Background as u8,
+                };
+                assert_eq!(image.get_index(x, y), expected, "Pixel {x},{y}");
+            }
+        }
+        assert_ne!(image.get_index(10, 4), AXIS);
+        for x in [3, 18, 25] {
+            image.assert_region_is_background(x, 8, 1, 4);
+        }
+        assert_eq!(image.palette(&Rgba::rgb(9, 9, 9), &Rgba::rgb(0, 0, 0), &Rgba::rgb(0, 0, 0)).0[9..12], [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_draw_ticks() {
+        let width = Width::new(40).unwrap();
+        let height = Height::new(10).unwrap();
+        let mut image = WaveImage::new(width, height);
+
+        // The spacing of 7.5 pixels is not added up, so 7.5 * 4 is column 30
+        image.draw_ticks(0.0, 7.5, 8, 10, Channel::Left as u8);
+        let ticks: Vec<u32> = (0..40).filter(|&x| image.get_pixel(x, 9) == Channel::Left).collect();
+        assert_eq!(ticks, [0, 8, 15, 23, 30, 38]);
+        image.assert_region_is_background(0, 40, 0, 8);
+
+        // Ticks left of the image are skipped
+        let mut image = WaveImage::new(width, height);
+        image.draw_ticks(-25.0, 10.0, 0, 1, Channel::Right as u8);
+        let ticks: Vec<u32> = (0..40).filter(|&x| image.get_pixel(x, 0) == Channel::Right).collect();
+        assert_eq!(ticks, [5, 15, 25, 35]);
+
+        let empty = image.pixels.clone();
+        image.draw_ticks(0.0, 0.0, 0, 10, Channel::Left as u8);
+        image.draw_ticks(0.0, f64::NAN, 0, 10, Channel::Left as u8);
+        image.draw_ticks(f64::NEG_INFINITY, 1.0, 0, 10, Channel::Left as u8);
+        assert_eq!(image.pixels, empty);
+    }
+
+    #[test]
+    fn test_draw_tick_marks() {
+        let width = Width::new(40).unwrap();
+        let height = Height::new(32).unwrap();
+        let mut image = WaveImage::new(width, height);
+        image.draw_point(20, 1.0, 1.0);
+        let columns = image.draw_tick_marks(&Rgba::rgb(1, 2, 3), 0.0, 10.0);
+        assert_eq!(columns, [0, 10, 20, 30]);
+
+        // Two rows (a sixteenth of 32) up from the bottom, over the waveform
+        for x in columns {
+            assert_eq!(image.get_index(x, 31), AXIS);
+            assert_eq!(image.get_index(x, 30), AXIS);
+        }
+        assert_eq!(image.get_index(20, 29), Channel::Right as u8);
+        image.assert_region_is_background(0, 1, 0, 30);
+        assert_eq!(image.palette(&Rgba::rgb(9, 9, 9), &Rgba::rgb(0, 0, 0), &Rgba::rgb(0, 0, 0)).0[9..12], [1, 2, 3]);
+
+        // A mono waveform hanging from the top has the ticks there
+        let options = ImageOptions { mono_style: MonoStyle::Bottom, ..Default::default() };
+        let mut image = WaveImage::with_options(width, height, &options);
+        image.draw_point_mono(0, 0.0);
+        image.draw_tick_marks(&Rgba::rgb(1, 2, 3), 5.0, 10.0);
+        assert_eq!(image.get_index(5, 0), AXIS);
+        assert_eq!(image.get_index(5, 1), AXIS);
+        assert_eq!(image.get_index(5, 31), Channel::Background as u8);
+    }
+
+    #[test]
+    fn test_draw_markers() {
+        let width = Width::new(32).unwrap();
+        let height = Height::new(12).unwrap();
+        let options = ImageOptions { overview_ratio: Some(0.25), ..Default::default() };
+        let mut image = WaveImage::with_options(width, height, &options);
+        image.draw_point(10, 1.0, 1.0);
+        image.draw_markers(&Rgba::rgb(1, 2, 3), &[10, 20, 40]);
+
+        // Over the waveform section, including the bar, but not the overview
+        for y in 0..8 {
+            assert_eq!(image.get_index(10, y), AXIS, "Pixel 10,{y}");
+            assert_eq!(image.get_index(20, y), AXIS, "Pixel 20,{y}");
+        }
+        image.assert_region_is_background(10, 11, 8, 12);
+        image.assert_region_is_background(20, 21, 8, 12);
+        assert_eq!(image.palette(&Rgba::rgb(9, 9, 9), &Rgba::rgb(0, 0, 0), &Rgba::rgb(0, 0, 0)).0[9..12], [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_draw_text() {
+        let width = Width::new(40).unwrap();
+        let height = Height::new(20).unwrap();
+        let mut image = WaveImage::new(width, height);
+
+        // "1" is a vertical bar in the middle column of the glyph
+        image.draw_text(2, 3, "1", 1, Channel::Left as u8);
+        image.assert_vertical_line(4, 3, 10, Channel::Left);
+        assert_eq!(image.get_pixel(3, 4), Channel::Left, "The flag of the 1");
+        assert_eq!(image.get_pixel(3, 9), Channel::Left, "The base of the 1");
+        assert_eq!(image.get_pixel(2, 5), Channel::Background);
+
+        // At scale 2 every font pixel becomes a 2x2 block
+        let mut scaled = WaveImage::new(width, height);
+        scaled.draw_text(0, 0, "1", 2, Channel::Left as u8);
+        scaled.assert_region(4, 6, 0, 14, Channel::Left);
+        scaled.assert_region_is_background(0, 2, 0, 14);
+
+        // Unknown characters are skipped but still take up space
+        let mut unknown = WaveImage::new(width, height);
+        unknown.draw_text(0, 0, "x1", 1, Channel::Left as u8);
+        unknown.assert_region_is_background(0, 6, 0, 20);
+        unknown.assert_vertical_line(8, 0, 7, Channel::Left);
+    }
+
+    #[test]
+    fn test_draw_badge() {
+        let width = Width::new(100).unwrap();
+        let height = Height::new(40).unwrap();
+
+        // Scale 1 badge: 4 glyphs of 5 + 3 gaps = 23, plus padding = 25x9
+        for (corner, x, y) in [
+            (Corner::TopLeft, 1, 1),
+            (Corner::TopRight, 74, 1),
+            (Corner::BottomLeft, 1, 30),
+            (Corner::BottomRight, 74, 30),
+        ] {
+            let mut image = WaveImage::with_options(width, height, &ImageOptions { balance_strip: 4, ..Default::default() });
+            for x in 0..100 {
+                image.draw_point(x, 1.0, 1.0);
+            }
+            image.draw_badge(corner, "4:07");
+
+            // The box is cleared around the text
+            image.assert_vertical_line(x, y, y + 9, Channel::Background);
+            image.assert_vertical_line(x + 24, y, y + 9, Channel::Background);
+
+            // The text starts inside the padding ("4" has its top pixel in column 3)
+            assert_eq!(image.get_pixel(x + 4, y + 1), Channel::Left, "{:?}", corner);
+
+            // The waveform outside the badge is untouched, as is the strip
+            assert_ne!(image.get_pixel(x - 1, y), Channel::Background, "{:?}", corner);
+            image.assert_region_is_background(0, 100, 40, 44);
+        }
+
+        // A badge that does not fit is skipped
+        let mut small = WaveImage::new(Width::new(16).unwrap(), Height::new(6).unwrap());
+        small.draw_badge(Corner::TopLeft, "4:07");
+        small.assert_region_is_background(0, 16, 0, 6);
+    }
+
     #[test]
     fn test_memory_efficiency() {
         // Test the memory efficiency of the 2-bit-per-pixel format
@@ -350,7 +1533,7 @@ mod tests {
                 );
 
                 // Calculate expected bytes per row: ceiling(width / 4)
-                let expected_line_width = (width + 3) / 4;
+                let expected_line_width = width.div_ceil(4);
 
                 // Verify line width calculation
                 assert_eq!(
@@ -375,8 +1558,8 @@ mod tests {
                 let prev_width = width_group[i-1];
 
                 // Compare expected line widths - they should be based on ceiling(width/4)
-                let expected_current = (width + 3) / 4;
-                let expected_previous = (prev_width + 3) / 4;
+                let expected_current = width.div_ceil(4);
+                let expected_previous = prev_width.div_ceil(4);
 
                 // Verify that line widths match our expectation
                 assert_eq!(
@@ -411,7 +1594,7 @@ mod tests {
             let image = WaveImage::new(width, height);
 
             // Expected line width in bytes: ceiling(width / 4)
-            let expected_line_width = (width_val + 3) / 4;
+            let expected_line_width = width_val.div_ceil(4);
 
             // Expected total bytes: line_width * height
             let expected_bytes = expected_line_width as usize * height_val as usize;
@@ -458,118 +1641,3 @@ mod tests {
         }
     }
 }
-
-#[cfg(test)]
-mod benchmarks {
-    /// # Running Benchmarks
-    ///
-    /// ```bash
-    /// cargo test --release -- --nocapture
-    /// ```
-    ///
-    /// The `--release` flag ensures benchmarks run with optimizations.
-    /// The `--nocapture` flag ensures output is displayed.
-
-    use crate::cli::{Width, Height};
-    use crate::image::WaveImage;
-    use std::time::Instant;
-
-    /// Run a benchmark for the given operation and return the duration and per-op time
-    fn run_benchmark<F>(op: F) -> std::time::Duration
-    where
-        F: Fn(),
-    {
-        // Run the benchmark
-        let start = Instant::now();
-        op();
-        let duration = start.elapsed();
-
-        duration
-    }
-
-    #[test]
-    fn benchmark_draw_point_performance() {
-        const WIDTH_VAL: u32 = 2048;
-        const HEIGHT_VAL: u32 = 128;
-        const ITERATIONS: u32 = 1_000_000;
-        const REPEAT_COUNT: usize = 10; // Run multiple times for more reliable results
-
-        // Create Width and Height instances from raw values
-        let width = Width::new(WIDTH_VAL).unwrap();
-        let height = Height::new(HEIGHT_VAL).unwrap();
-
-        println!("\n\nRUNNING IMAGE RENDERING BENCHMARKS\n");
-
-        let mut mono_times = Vec::with_capacity(REPEAT_COUNT);
-        let mut stereo_times = Vec::with_capacity(REPEAT_COUNT);
-
-        // Run multiple iterations for more stable results
-        for i in 0..=REPEAT_COUNT {
-            // Test mono point drawing
-            let mono_duration = run_benchmark(|| {
-                let mut image = WaveImage::new(width, height);
-                for i in 0..ITERATIONS {
-                    let x = i % WIDTH_VAL;
-                    let h = ((i % 9) + 1) as f32 / 10.0;
-                    image.draw_point_mono(x, h);
-                }
-            });
-            // Throw out the first run
-            if i > 0 {
-                mono_times.push(mono_duration);
-            }
-
-            // Test stereo point drawing
-            let stereo_duration = run_benchmark(|| {
-                let mut image = WaveImage::new(width, height);
-                for i in 0..ITERATIONS {
-                    let x = i % WIDTH_VAL;
-                    let h = ((i % 9) + 1) as f32 / 10.0;
-                    image.draw_point(x, h, h);
-                }
-            });
-            // Throw out the first run
-            if i > 0 {
-                stereo_times.push(stereo_duration);
-            }
-        }
-
-        // Calculate average times
-        let avg_mono = mono_times.iter().sum::<std::time::Duration>() / REPEAT_COUNT as u32;
-        let avg_stereo = stereo_times.iter().sum::<std::time::Duration>() / REPEAT_COUNT as u32;
-
-        // Calculate min and max times
-        let min_mono = mono_times.iter().min().unwrap();
-        let max_mono = mono_times.iter().max().unwrap();
-        let min_stereo = stereo_times.iter().min().unwrap();
-        let max_stereo = stereo_times.iter().max().unwrap();
-
-        println!("\n==================================================");
-        println!("BENCHMARK SUMMARY (Average of {} runs)", REPEAT_COUNT);
-        println!("==================================================");
-        println!("Mono drawing:   {:.2?} per {} ops ({:.2?} per op)",
-                 avg_mono, ITERATIONS, avg_mono / ITERATIONS);
-        println!("  Range: {:.2?} to {:.2?}", min_mono, max_mono);
-        println!("Stereo drawing: {:.2?} per {} ops ({:.2?} per op)",
-                 avg_stereo, ITERATIONS, avg_stereo / ITERATIONS);
-        println!("  Range: {:.2?} to {:.2?}", min_stereo, max_stereo);
-        println!("==================================================");
-
-        // Compare the performance
-        println!("\n==================================================");
-        println!("PERFORMANCE COMPARISON");
-        println!("==================================================");
-        let ratio = avg_stereo.as_secs_f64() / avg_mono.as_secs_f64();
-
-        if ratio < 1.0 {
-            // If ratio < 1.0, stereo is actually faster
-            println!("Stereo is {:.2}x faster than mono", 1.0 / ratio);
-        } else {
-            println!("Stereo is {:.2}x slower than mono", ratio);
-        }
-        println!("==================================================\n");
-
-    }
-
-
-}
\ No newline at end of file
@@ -0,0 +1,75 @@
+/// Per thread reuse of waveform images across files.
+///
+/// In a batch of thousands of files every image has the same size and
+/// layout, yet each file allocated (and the allocator zeroed) a new pixel
+/// buffer for it.  Each worker thread keeps the image of its last file and
+/// hands it out again (after a `clear()`) when the next file asks for the
+/// same dimensions and options.
+use std::cell::RefCell;
+
+use crate::cli::{Height, Width};
+use crate::image::{ImageOptions, WaveImage};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// How many idle images each thread keeps.
+const POOL_SIZE: usize = 2;
+
+/// The dimensions and options an image was made with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Layout {
+    width: u32,
+    height: u32,
+    options: ImageOptions,
+}
+
+impl Layout {
+    fn new(width: Width, height: Height, options: &ImageOptions) -> Self {
+        Self {
+            width: width.value(),
+            height: height.value(),
+            options: *options,
+        }
+    }
+}
+
+thread_local! {
+    /// The idle images of this thread with the layout they were made with.
+    static POOL: RefCell<Vec<(Layout, WaveImage)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Returns a blank image, reusing an idle one of this thread if there is
+/// one with the same dimensions and options.
+pub fn take(width: Width, height: Height, options: &ImageOptions) -> WaveImage {
+    let layout = Layout::new(width, height, options);
+    let reused = POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        let index = pool.iter().position(|(idle, _)| *idle == layout)?;
+        Some(pool.swap_remove(index).1)
+    });
+
+    match reused {
+        Some(mut image) => {
+            image.clear();
+            image
+        }
+        None => WaveImage::with_options(width, height, options),
+    }
+}
+
+/// Returns an image that is no longer needed to this thread's pool.
+///
+/// The dimensions and options must be the ones it was taken with.  If the
+/// pool is full the image idle the longest is dropped.
+pub fn give_back(width: Width, height: Height, options: &ImageOptions, image: WaveImage) {
+    let layout = Layout::new(width, height, options);
+    POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        if pool.len() >= POOL_SIZE {
+            pool.remove(0);
+        }
+        pool.push((layout, image));
+    });
+}
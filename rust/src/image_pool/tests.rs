@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod tests {
+    use crate::cli::{Height, Width};
+    use crate::color::Rgba;
+    use crate::image::{ImageOptions, WaveImage};
+    use crate::image_pool::{give_back, take};
+
+    fn png(image: &WaveImage) -> Vec<u8> {
+        let white = Rgba::rgb(255, 255, 255);
+        let black = Rgba::rgb(0, 0, 0);
+        let mut bytes = Vec::new();
+        image.write_png(&white, &black, &black, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_reused_image_is_blank() {
+        let width = Width::new(64).unwrap();
+        let height = Height::new(16).unwrap();
+        let options = ImageOptions { balance_strip: 4, ..Default::default() };
+        let blank = png(&WaveImage::with_options(width, height, &options));
+
+        let mut image = take(width, height, &options);
+        image.draw_point(10, 1.0, 1.0);
+        image.draw_balance(10, 0.5);
+        assert_ne!(png(&image), blank);
+        give_back(width, height, &options, image);
+
+        // The drawing of the previous file must not show through
+        let image = take(width, height, &options);
+        assert_eq!(png(&image), blank);
+    }
+
+    #[test]
+    fn test_layout_must_match() {
+        let width = Width::new(64).unwrap();
+        let height = Height::new(16).unwrap();
+        let plain = ImageOptions::default();
+        give_back(width, height, &plain, take(width, height, &plain));
+
+        // A tinted image has a different depth than the idle plain one
+        let tinted = ImageOptions { tinted: true, ..Default::default() };
+        assert!(take(width, height, &tinted).is_tinted());
+
+        // And a different size needs its own buffer
+        let wider = Width::new(128).unwrap();
+        assert_eq!(take(wider, height, &plain).width(), 128);
+    }
+}
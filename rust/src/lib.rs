@@ -0,0 +1,88 @@
+//! Waver: Generate waveform visualizations from audio files.
+//!
+//! The `waver` command line tool is a thin layer over this library.  Other
+//! Rust programs can generate the same images by building a
+//! `WaveformConfig` and calling `generate_waveform`:
+//!
+//! ```no_run
+//! use waver::{generate_waveform, Outcome, WaveformConfig};
+//!
+//! let config = WaveformConfig::builder().width(1024).height(64).build()?;
+//! if generate_waveform("song.flac", "song.flac.png", &config)? == Outcome::Created {
+//!     println!("Created song.flac.png");
+//! }
+//! # Ok::<(), waver::WaverError>(())
+//! ```
+//!
+//! The `prelude` holds the types and functions that only change with the
+//! major version; the rest of the public modules may change in any release.
+mod art_color;
+pub mod audio;
+#[doc(hidden)]
+pub mod audit;
+mod backdrop;
+#[doc(hidden)]
+pub mod bench;
+#[doc(hidden)]
+pub mod bundle;
+#[doc(hidden)]
+pub mod cli;
+#[doc(hidden)]
+pub mod collect;
+pub mod color;
+#[doc(hidden)]
+pub mod compare;
+pub mod config;
+#[doc(hidden)]
+pub mod cross_check;
+#[doc(hidden)]
+pub mod dashboard;
+mod decoder_pool;
+#[doc(hidden)]
+pub mod diff;
+pub mod error;
+pub mod image;
+mod image_pool;
+#[doc(hidden)]
+pub mod limits;
+pub mod loudness;
+mod markers;
+pub mod metadata;
+mod mix;
+#[doc(hidden)]
+pub mod output;
+mod packet_guard;
+mod peak_cache;
+mod peaks;
+pub mod prelude;
+pub mod preview;
+pub mod quality;
+#[doc(hidden)]
+pub mod progress;
+mod render;
+#[doc(hidden)]
+pub mod resume;
+mod scale;
+#[doc(hidden)]
+pub mod scan;
+#[doc(hidden)]
+pub mod schedule;
+mod segment;
+pub mod silence;
+pub mod sidecar;
+mod spectrum;
+#[doc(hidden)]
+pub mod summary;
+mod tempo;
+pub mod ticks;
+#[doc(hidden)]
+pub mod telemetry;
+mod tiles;
+mod trim;
+mod video;
+
+pub use audio::{content_hash, generate_peaks, generate_segments, generate_stems, generate_waveform, generate_waveform_measured, peak_hash, scan_quality, Measures, Outcome};
+pub use cli::{Corner, ImageFormat, RenderMode};
+pub use mix::ChannelMode;
+pub use config::{ExistingOutput, WaveformConfig, WaveformConfigBuilder};
+pub use error::{Result, WaverError};
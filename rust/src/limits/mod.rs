@@ -0,0 +1,59 @@
+/// Skipping the audio files that are too large or too long
+/// (`--max-file-size` and `--max-duration`).
+///
+/// An eight hour recording of a live stream, or a corrupt file of many
+/// gigabytes, keeps a worker busy for minutes for an image nobody wanted.
+/// The limits are checked before the audio is decoded: the size from the
+/// file system and the length from the header of the container, or where it
+/// records none, from the packets, read without decoding them until they
+/// are past the limit.  A file over a limit is skipped with a warning.
+use std::fs;
+use std::path::Path;
+
+use crate::audio::{format_duration, format_size, longer_than};
+use crate::error::{Result, WaverError};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The largest and the longest audio files that are drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Limits {
+    /// The largest file, in bytes
+    pub max_file_size: Option<u64>,
+    /// The longest audio, in seconds
+    pub max_duration: Option<f64>,
+}
+
+impl Limits {
+    /// Returns true if there is no limit to check.
+    pub fn is_empty(&self) -> bool {
+        self.max_file_size.is_none() && self.max_duration.is_none()
+    }
+
+    /// Checks the file against the limits, returning an `OverLimit` error
+    /// for a file over one of them.  The size is checked first, so a huge
+    /// file is not opened at all.
+    pub fn check(&self, input_path: &Path) -> Result<()> {
+        if let Some(max) = self.max_file_size {
+            let size = fs::metadata(input_path)?.len();
+            if size > max {
+                return Err(WaverError::over_limit(format!(
+                    "{} is over the --max-file-size of {}",
+                    format_size(size),
+                    format_size(max)
+                )));
+            }
+        }
+        if let Some(max) = self.max_duration {
+            if longer_than(input_path, max)? {
+                return Err(WaverError::over_limit(format!(
+                    "Longer than the --max-duration of {}",
+                    format_duration(max)
+                )));
+            }
+        }
+        Ok(())
+    }
+}
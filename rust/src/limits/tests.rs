@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use crate::limits::Limits;
+
+    /// Writes a silent 8 kHz mono WAV file of the given length.
+    fn write_wav(path: &Path, seconds: u32) {
+        let data = 8000 * 2 * seconds;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        // PCM, 1 channel, 8000 Hz, 16000 bytes per second, 2 bytes per frame, 16 bits
+        for field in [1u16, 1] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        bytes.extend_from_slice(&8000u32.to_le_bytes());
+        bytes.extend_from_slice(&16000u32.to_le_bytes());
+        for field in [2u16, 16] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data.to_le_bytes());
+        bytes.resize(bytes.len() + data as usize, 0);
+        fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn test_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("three.wav");
+        write_wav(&path, 3);
+
+        assert!(Limits::default().is_empty());
+        assert!(Limits::default().check(Path::new("missing.wav")).is_ok(), "Nothing is checked without limits");
+
+        let long = Limits { max_duration: Some(2.0), ..Default::default() };
+        let e = long.check(&path).unwrap_err();
+        assert!(e.is_over_limit());
+        assert_eq!(e.to_string(), "Longer than the --max-duration of 0:02");
+        assert!(Limits { max_duration: Some(3.0), ..Default::default() }.check(&path).is_ok(), "Up to the limit is fine");
+
+        let large = Limits { max_file_size: Some(1024), max_duration: Some(60.0) };
+        let e = large.check(&path).unwrap_err();
+        assert_eq!(e.to_string(), "46.9 KiB is over the --max-file-size of 1.0 KiB");
+        assert!(Limits { max_file_size: Some(1 << 20), ..Default::default() }.check(&path).is_ok());
+
+        let missing = long.check(&dir.path().join("missing.wav")).unwrap_err();
+        assert!(!missing.is_over_limit(), "A file that can not be read is not over a limit");
+    }
+}
@@ -0,0 +1,225 @@
+/// Loudness and true peak after EBU R128 (`--measure-loudness`).
+///
+/// Music libraries level their tracks by loudness, and the maintainers
+/// want the numbers without running another tool over every file.  The
+/// `LoudnessMeter` follows the decode of the image, so the measures cost no
+/// second pass over the audio:
+///
+/// * The integrated loudness (ITU-R BS.1770-4) in LUFS: the samples are
+///   K-weighted (a high shelf for the head and a high pass), their mean
+///   square taken over blocks of 400 ms every 100 ms, and the blocks below
+///   -70 LUFS and then those more than 10 LU below the loudness of the rest
+///   are gated out.  The surround channels of 5 and 5.1 channel audio count
+///   1.41 times and the LFE channel not at all.
+/// * The true peak in dBTP: the largest sample after 4 times oversampling
+///   (2 times from 96 kHz, none from 192 kHz), which finds the peaks
+///   between the samples that a DAC or a lossy encoder turns into clipping.
+///
+/// Audio that is all silence (or shorter than one block) has no loudness.
+use std::f64::consts::PI;
+
+use serde::Serialize;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// Loudness of a block whose mean square is 1.
+const LOUDNESS_OFFSET: f64 = -0.691;
+
+/// Blocks below this loudness (in LUFS) are not counted at all.
+const ABSOLUTE_GATE: f64 = -70.0;
+
+/// Blocks more than this many LU below the loudness of the others are not
+/// counted.
+const RELATIVE_GATE: f64 = 10.0;
+
+/// The interpolation filter of the true peak has this many taps for every
+/// output sample of a phase.
+const TAPS_PER_PHASE: usize = 12;
+
+/// The loudness measures of some audio.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Loudness {
+    /// Integrated loudness in LUFS, `None` for silence
+    pub integrated_lufs: Option<f64>,
+    /// True peak in dBTP, `None` for silence
+    pub true_peak_dbtp: Option<f64>,
+}
+
+/// A biquad filter in transposed direct form II.
+#[derive(Debug, Clone, Copy, Default)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    state: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.state[0];
+        self.state[0] = self.b[1] * input - self.a[0] * output + self.state[1];
+        self.state[1] = self.b[2] * input - self.a[1] * output;
+        output
+    }
+}
+
+/// Returns the two stages of the K-weighting filter at the sample rate, as
+/// derived in libebur128 from the 48 kHz coefficients of BS.1770.
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let rate = sample_rate as f64;
+
+    // The high shelf of the head
+    let (f0, gain, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        state: [0.0; 2],
+    };
+
+    // The high pass
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        state: [0.0; 2],
+    };
+    [shelf, high_pass]
+}
+
+/// Returns the weight of each channel in the loudness.
+fn channel_weights(channels: usize) -> Vec<f64> {
+    match channels {
+        // L R C Ls Rs
+        5 => vec![1.0, 1.0, 1.0, 1.41, 1.41],
+        // L R C LFE Ls Rs
+        6 => vec![1.0, 1.0, 1.0, 0.0, 1.41, 1.41],
+        _ => vec![1.0; channels],
+    }
+}
+
+/// Returns the oversampling of the true peak at the sample rate.
+fn oversampling(sample_rate: u32) -> usize {
+    match sample_rate {
+        0..96_000 => 4,
+        96_000..192_000 => 2,
+        _ => 1,
+    }
+}
+
+/// Returns the taps of the interpolation filter for the oversampling: a
+/// windowed sinc, `TAPS_PER_PHASE` taps for every phase, whose first phase
+/// gives back the samples themselves.
+fn interpolation_filter(factor: usize) -> Vec<f64> {
+    let length = factor * TAPS_PER_PHASE;
+    let middle = (length / 2) as f64;
+    (0..length)
+        .map(|tap| {
+            let t = (tap as f64 - middle) / factor as f64;
+            let sinc = if t == 0.0 { 1.0 } else { (PI * t).sin() / (PI * t) };
+            // Hann window around the middle tap
+            let window = 0.5 - 0.5 * (2.0 * PI * tap as f64 / length as f64).cos();
+            sinc * window
+        })
+        .collect()
+}
+
+/// Measures the loudness and the true peak of streamed samples.
+#[derive(Debug, Clone)]
+pub struct LoudnessMeter {
+    /// The K-weighting filter of every channel
+    filters: Vec<[Biquad; 2]>,
+    weights: Vec<f64>,
+    /// Frames of a 100 ms step
+    step: usize,
+    /// Frames of the current step so far, and their weighted sum of squares
+    filled: usize,
+    squares: f64,
+    /// The mean squares of the finished steps
+    steps: Vec<f64>,
+    factor: usize,
+    filter: Vec<f64>,
+    /// The last `TAPS_PER_PHASE` samples of every channel, newest first
+    history: Vec<Vec<f64>>,
+    peak: f64,
+}
+
+impl LoudnessMeter {
+    /// Creates the meter for audio of `channels` channels at `sample_rate`
+    /// Hz.
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        let factor = oversampling(sample_rate);
+        Self {
+            filters: vec![k_weighting(sample_rate); channels],
+            weights: channel_weights(channels),
+            step: (sample_rate as usize / 10).max(1),
+            filled: 0,
+            squares: 0.0,
+            steps: Vec::new(),
+            factor,
+            filter: interpolation_filter(factor),
+            history: vec![vec![0.0; TAPS_PER_PHASE]; channels],
+            peak: 0.0,
+        }
+    }
+
+    /// Measures the next frames, one slice per channel.
+    pub fn push(&mut self, planes: &[&[f32]]) {
+        let frames = planes.iter().map(|plane| plane.len()).min().unwrap_or(0);
+        let planes = &planes[..planes.len().min(self.filters.len())];
+        for frame in 0..frames {
+            for (channel, plane) in planes.iter().enumerate() {
+                let sample = plane[frame] as f64;
+                let [shelf, high_pass] = &mut self.filters[channel];
+                let weighted = high_pass.process(shelf.process(sample));
+                self.squares += self.weights[channel] * weighted * weighted;
+                self.true_peak(channel, sample);
+            }
+            self.filled += 1;
+            if self.filled == self.step {
+                self.steps.push(self.squares / self.step as f64);
+                self.filled = 0;
+                self.squares = 0.0;
+            }
+        }
+    }
+
+    /// Follows the peak of the oversampled channel with the next sample.
+    fn true_peak(&mut self, channel: usize, sample: f64) {
+        let history = &mut self.history[channel];
+        history.rotate_right(1);
+        history[0] = sample;
+        if self.factor == 1 {
+            self.peak = self.peak.max(sample.abs());
+            return;
+        }
+        for phase in 0..self.factor {
+            let taps = self.filter[phase..].iter().step_by(self.factor);
+            let value: f64 = taps.zip(history.iter()).map(|(tap, sample)| tap * sample).sum();
+            self.peak = self.peak.max(value.abs());
+        }
+    }
+
+    /// Returns the measures of all the audio pushed.
+    pub fn finish(&self) -> Loudness {
+        let loudness = |mean_square: f64| LOUDNESS_OFFSET + 10.0 * mean_square.log10();
+        // Every block is 4 steps, overlapping the next by 3
+        let blocks = self.steps.windows(4).map(|steps| steps.iter().sum::<f64>() / 4.0).collect::<Vec<_>>();
+        let mean = |blocks: &[f64]| (!blocks.is_empty()).then(|| blocks.iter().sum::<f64>() / blocks.len() as f64);
+
+        let audible = blocks.iter().copied().filter(|&block| loudness(block) > ABSOLUTE_GATE).collect::<Vec<_>>();
+        let integrated_lufs = mean(&audible).and_then(|all| {
+            let gate = loudness(all) - RELATIVE_GATE;
+            let gated = audible.iter().copied().filter(|&block| loudness(block) > gate).collect::<Vec<_>>();
+            mean(&gated).map(loudness)
+        });
+        let true_peak_dbtp = (self.peak > 0.0).then(|| 20.0 * self.peak.log10());
+        Loudness { integrated_lufs, true_peak_dbtp }
+    }
+}
@@ -0,0 +1,51 @@
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::loudness::{Loudness, LoudnessMeter};
+
+    /// Returns the samples of a sine of the frequency and amplitude.
+    fn sine(frequency: f64, amplitude: f64, phase: f64, sample_rate: u32, seconds: f64) -> Vec<f32> {
+        let frames = (seconds * sample_rate as f64) as usize;
+        (0..frames)
+            .map(|frame| (amplitude * (2.0 * PI * frequency * frame as f64 / sample_rate as f64 + phase).sin()) as f32)
+            .collect()
+    }
+
+    #[test]
+    fn test_integrated_loudness() {
+        // EBU Tech 3341, case 1: 1 kHz at -23 dBFS in both channels is -23 LUFS
+        let tone = sine(997.0, 10f64.powf(-23.0 / 20.0), 0.0, 48_000, 20.0);
+        let mut meter = LoudnessMeter::new(48_000, 2);
+        meter.push(&[&tone, &tone]);
+        let lufs = meter.finish().integrated_lufs.unwrap();
+        assert!((lufs + 23.0).abs() < 0.1, "{lufs}");
+
+        // The silence before it is gated out, at any sample rate
+        let tone = sine(997.0, 10f64.powf(-23.0 / 20.0), 0.0, 44_100, 10.0);
+        let mut meter = LoudnessMeter::new(44_100, 2);
+        meter.push(&[&[0.0; 44_100 * 5], &[0.0; 44_100 * 5]]);
+        meter.push(&[&tone, &tone]);
+        let lufs = meter.finish().integrated_lufs.unwrap();
+        assert!((lufs + 23.0).abs() < 0.1, "{lufs}");
+    }
+
+    #[test]
+    fn test_true_peak() {
+        // At a quarter of the rate the samples miss the crests by 45 degrees
+        let tone = sine(12_000.0, 0.5, PI / 4.0, 48_000, 1.0);
+        assert!(tone.iter().all(|sample| sample.abs() < 0.36));
+        let mut meter = LoudnessMeter::new(48_000, 1);
+        meter.push(&[&tone]);
+        let dbtp = meter.finish().true_peak_dbtp.unwrap();
+        assert!((dbtp - 20.0 * 0.5f64.log10()).abs() < 0.2, "{dbtp}");
+    }
+
+    #[test]
+    fn test_silence() {
+        let mut meter = LoudnessMeter::new(48_000, 2);
+        meter.push(&[&[0.0; 48_000], &[0.0; 48_000]]);
+        assert_eq!(meter.finish(), Loudness { integrated_lufs: None, true_peak_dbtp: None });
+        assert_eq!(LoudnessMeter::new(48_000, 1).finish().integrated_lufs, None, "Shorter than a block");
+    }
+}
@@ -10,119 +10,711 @@
 /// 1. Parse and validate command-line arguments
 /// 2. Collect audio files to process
 /// 3. Process each file in parallel, generating waveform images
-/// 4. Report any errors that occurred during processing
+/// 4. Report any errors that occurred during processing (or stop at the
+///    first one with --fail-fast)
 ///
 /// # Performance
 ///
 /// Key performance optimizations:
-/// - Parallel processing of audio files using rayon
+/// - Parallel processing of audio files using rayon (limited with --jobs)
 /// - Streaming audio decoding rather than buffering
 /// - 2-bit pixel depth in PNG output for smaller files
 ///
 /// See ARCHITECTURE.md and OPTIMIZATIONS.md for more details.
-mod audio;
-mod cli;
-mod color;
-mod error;
-mod image;
-mod examples;
-
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
 
+use clap::FromArgMatches;
 use rayon::prelude::*;
-use walkdir::WalkDir;
+use tracing::info_span;
 
-use audio::generate_waveform;
-use cli::WaverArgs;
-use error::WaverError;
+use waver::audit;
+use waver::bundle::{bundle_path, directory_bundle_path, write_bundle};
+use waver::bench::{bench_file, BenchReport};
+use waver::cli::{self, exit_with, AudioPath, BenchArgs, BundleScope, Command, CompareArgs, ConfigArgs, DiffArgs, MetadataArgs, RenderMode, ReportFormat, WaverArgs};
+use waver::collect::{collect_audio_files, read_file_list};
+use waver::compare::compare_files;
+use waver::config::{checkerboard_path, is_stdout, played_path, STDIO_PATH};
+use waver::cross_check::{cross_check, is_sampled};
+use waver::dashboard::Dashboard;
+use waver::diff::{EmbeddedImage, WaveformDiff};
+use waver::error::ExitStatus;
+use waver::metadata::{read_metadata, write_json};
+use waver::output::{Line, LogLevel, OrderedOutput, OutputOrder};
+use waver::preview::Preview;
+use waver::progress::Progress;
+use waver::resume::ResumeState;
+use waver::schedule::{configure_pool, largest_first, WorkItem};
+use waver::sidecar::Sidecar;
+use waver::silence::SilenceMap;
+use waver::summary::{FileStatus, FileSummary, RunId, RunSummary};
+use waver::telemetry;
+use waver::ticks::TickMap;
+use waver::{content_hash, generate_segments, generate_stems, generate_waveform_measured, peak_hash, scan_quality, Measures, Outcome, WaveformConfig, WaverError};
 
 /// Main entry point for the waver application.
-fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+///
+/// An error that keeps waver from running at all exits with the status of
+/// an argument error, the failed files of a batch with their own statuses.
+fn main() -> ExitCode {
+    match run() {
+        Ok(status) => status.into(),
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            ExitStatus::ArgumentError.into()
+        }
+    }
+}
+
+/// Runs waver and returns the exit status of the batch.
+fn run() -> std::result::Result<ExitStatus, Box<dyn std::error::Error>> {
     // Parse and validate command-line arguments
     let args = WaverArgs::parse_and_validate()?;
+    if let Some(log) = &args.audit_log {
+        audit::open(log, args.run_id())?;
+    }
+    audit::set_atomic(!args.no_atomic);
+    if let Some(command) = &args.command {
+        return run_command(command);
+    }
+    let config = args.config()?;
+    let logger = args.logger();
+
+    // Kept until the end so the spans still queued are exported
+    let _telemetry = telemetry::init(&logger, args.trace, args.otlp_endpoint.as_deref())?;
 
     // Collect all audio files to process
+    let discover_span = info_span!("discover").entered();
     let mut audio_files = Vec::new();
-    for audio_path in &args.audio_paths {
-        let path = audio_path.path();
-        if path.is_file() {
-            // Directly entered file names are just used as is
-            // We don't filter it to the extensions
-            audio_files.push(path.to_path_buf());
-        } else if path.is_dir() {
-            // We use WalkDir such that the complexity of loops/etc are handled
-            // for us rather than getting us stuck
-            for entry in WalkDir::new(path)
-                .follow_links(true)
-                .into_iter()
-                .filter_map(|e| e.ok())
-                .filter(|entry| entry.file_type().is_file())
-                .filter(|entry| {
-                    entry
-                        .path()
-                        .extension()
-                        .and_then(|ext| ext.to_str())
-                        .map(|ext_str| {
-                            args.file_extensions()
-                                .iter()
-                                .any(|e| e.eq(ext_str))
-                            })
-                        .unwrap_or(false)
-                    })
-                .map(|entry| entry.into_path()) {
-                    audio_files.push(entry);
-            }
-        }
+    if args.stdin {
+        audio_files.push(PathBuf::from(STDIO_PATH));
     }
+    audio_files.extend(collect_audio_files(&args.audio_paths, &args.file_extensions(), args.traversal(), &args.exclude));
+    if let Some(list) = &args.files_from {
+        audio_files.extend(read_file_list(list, &args.exclude)?);
+    }
+    // The stems make one image, which is reported under the first stem
+    audio_files.extend(args.stems.first().map(|stem| stem.path().to_path_buf()));
+
+    drop(discover_span);
 
+    // Nothing failed the last time, so there is nothing to do
+    if audio_files.is_empty() && args.retry_from.is_some() {
+        logger.info("No failed files to retry");
+        return Ok(ExitStatus::Success);
+    }
     if audio_files.is_empty() {
         return Err(Box::new(WaverError::argument_error(
             "No matching audio files found",
         )));
     }
 
-    if args.verbose {
-        args.print_verbose(&format!("Found {} audio files to process", audio_files.len()));
+    if logger.enabled(LogLevel::Debug) {
+        logger.debug(&format!("Found {} audio files to process", audio_files.len()));
+        if let Some(run_id) = args.run_id() {
+            logger.debug(&format!("Run ID {run_id}"));
+        }
+        if args.mode == RenderMode::Waveform {
+            // In the canonical form, so they can be copied back as options
+            let colors = match args.color_seed {
+                Some(seed) => format!("Colors: --background-color {} --color-seed {seed}", args.background_color),
+                None => format!(
+                    "Colors: --background-color {} --left-color {} --right-color {}",
+                    args.background_color, args.left_color, args.right_color
+                ),
+            };
+            let colors = if args.auto_color { format!("{colors} --auto-color") } else { colors };
+            logger.debug(&match &args.color_map {
+                Some(map) => format!("{colors} --color-map {}", map.display()),
+                None => colors,
+            });
+        }
+    }
+
+    // Limit the threads before the first file runs on the pool
+    configure_pool(args.jobs.map(|jobs| jobs as usize), args.nice)?;
+    if let Some(dir) = &args.hash_names {
+        audit::create_dir_all(dir)?;
+    }
+    let resume = args.resume.as_deref().map(ResumeState::open).transpose()?;
+    if let Some(state) = resume.as_ref().filter(|state| !state.is_empty()) {
+        logger.info(&format!("Resuming, {} files were done before", state.len()));
     }
 
     // Process files in parallel, collecting errors
     // PERFORMANCE: Parallel processing is critical for handling multiple files efficiently
     // This section uses Rayon's parallel iterator to process files concurrently
-    // while safely collecting errors using a synchronized Mutex
-    let errors = Mutex::new(Vec::<String>::new());
+    // while safely collecting errors in a synchronized report
+    let files = audio_files.len();
+    // What became of every file, for the run summary
+    let results = Mutex::new(Vec::with_capacity(files));
+    let failures = AtomicUsize::new(0);
+    // The images of --hash-names that were drawn (or started) in this run
+    let hashed_images = Mutex::new(HashSet::new());
+    let limits = args.limits();
+    let started = Instant::now();
+    // The file spans run on the worker threads, so they name their parent
+    let run_id = args.run_id();
+    let batch_span = info_span!("batch", files, run_id = run_id.as_ref().map(RunId::as_str));
+    // Set on the first error with --fail-fast (or the first one over the
+    // error budget), files not started yet are skipped
+    let stopped = AtomicBool::new(false);
+    let budget = args.error_budget();
+
+    // Start the largest files first and hand the files out one at a time
+    // as threads become free, so one big file does not run alone at the end
+    let mut work: Vec<WorkItem> =
+        audio_files.into_iter().enumerate().map(|(position, path)| WorkItem::new(position, path)).collect();
+    largest_first(&mut work);
+    let progress = args.progress.then(|| Progress::new(work.iter().map(|item| item.weight)));
+    let dashboard =
+        args.tui.then(|| Dashboard::new(rayon::current_num_threads(), work.iter().map(|item| item.weight)));
+    // The messages of each file are printed together, in the --output-order,
+    // or after the dashboard is closed
+    let ordered = OrderedOutput::new(if args.tui { OutputOrder::End } else { args.output_order });
+    // The worker of the dashboard that runs the file
+    let worker = || rayon::current_thread_index().unwrap_or(0);
 
     // Convert PathBuf to AudioPath for processing
-    audio_files.into_par_iter().for_each(|file_path| {
+    let process = |WorkItem { position, path: file_path, weight }: WorkItem| {
+        if let Some(dashboard) = &dashboard {
+            dashboard.wait_while_paused(&stopped);
+        }
+        if stopped.load(Ordering::Relaxed) {
+            ordered.complete(position, Vec::new(), Line::emit);
+            return;
+        }
+        if let Some(dashboard) = &dashboard {
+            dashboard.start_file(worker(), &file_path);
+        }
+        let output = args.file_output(&file_path);
+        let _file_span = info_span!(parent: &batch_span, "file", path = %file_path.display()).entered();
+
+        // Counts the error of this file
+        let count_failure = || {
+            let failed = failures.fetch_add(1, Ordering::Relaxed) + 1;
+            if args.fail_fast && !budget.allows(failed, files) {
+                stopped.store(true, Ordering::Relaxed);
+            }
+        };
+        // Prints the message and counts the error of this file
+        let fail = |message: String| {
+            output.error(&message);
+            count_failure();
+        };
+
+        // Reports what became of one image of this file, returning the
+        // files that were written for it or the error it is counted as
+        let report_outcome = |output_file: &str, outcome: Outcome| -> std::result::Result<Vec<PathBuf>, String> {
+            // In tiles mode the manifest is what gets reported
+            let target_path = config.target_path(Path::new(output_file));
+            let target = target_path.display();
+            let mut created = Vec::new();
+            match outcome {
+                Outcome::Created => {
+                    output.info(&format!("Created {target}"));
+                    if config.preview() {
+                        let preview = Preview::path_for(Path::new(output_file));
+                        output.debug(&format!("Created {}", preview.display()));
+                        created.push(preview);
+                    }
+                    if config.detect_silence() {
+                        let silence = SilenceMap::path_for(Path::new(output_file));
+                        output.debug(&format!("Created {}", silence.display()));
+                        created.push(silence);
+                    }
+                    if config.progress_pair() {
+                        let played = played_path(Path::new(output_file));
+                        output.debug(&format!("Created {}", played.display()));
+                        created.push(played);
+                    }
+                    if config.checkerboard().is_some() {
+                        let checker = checkerboard_path(Path::new(output_file));
+                        output.debug(&format!("Created {}", checker.display()));
+                        created.push(checker);
+                    }
+                    if config.sidecar() {
+                        let sidecar_path = Sidecar::path_for(Path::new(output_file));
+                        output.debug(&format!("Created {}", sidecar_path.display()));
+                        created.push(sidecar_path);
+                    }
+                    if args.ticks_json {
+                        let tick_map = TickMap::path_for(Path::new(output_file));
+                        output.debug(&format!("Created {}", tick_map.display()));
+                        created.push(tick_map);
+                    }
+                    created.insert(0, target_path.clone());
+                }
+                // A dry run prints the plan of what a real run would do
+                Outcome::DryRun => {
+                    let action = if target_path.exists() { "overwrite" } else { "create" };
+                    let (width, height) = config.image_size();
+                    output.info(&format!("DryRun {target}: would {action} a {width}x{height} image"));
+                }
+                Outcome::UpToDate if args.dry_run => {
+                    output.info(&format!("DryRun {target}: up to date, would keep it"));
+                }
+                Outcome::UpToDate => output.debug(&format!("UpToDate {target}")),
+                // A dry run only reports what it would skip, whatever the
+                // level
+                Outcome::Skipped if args.dry_run => {
+                    output.info(&format!("DryRun {target}: exists, would skip it - use --overwrite"));
+                }
+                Outcome::Skipped if args.skip_fails() => {
+                    let e = WaverError::generation_error(format!(
+                        "Output file '{target}' already exists - use --overwrite"
+                    ));
+                    fail(format!("{}: {}", file_path.display(), e));
+                    return Err(e.to_string());
+                }
+                Outcome::Skipped => {}
+            }
+            Ok(created)
+        };
+
+        // A file done before the run was interrupted is not drawn again,
+        // even with --overwrite
+        let stdin = file_path == Path::new(STDIO_PATH);
+        let resumed = !stdin && resume.as_ref().is_some_and(|state| state.is_done(&file_path));
+
+        // Files over --max-file-size or --max-duration are skipped before
+        // anything decodes them (a stem over one skips the image of the
+        // stems); a file that can not be read is left to fail where it is
+        // drawn
+        let over_limit = if resumed || limits.is_empty() || stdin {
+            None
+        } else if args.stems.is_empty() {
+            limits.check(&file_path).err()
+        } else {
+            args.stems.iter().find_map(|stem| limits.check(stem.path()).err())
+        }
+        .filter(WaverError::is_over_limit);
+
+        // With --hash-names the image is named after the audio in it, so
+        // the copies of a track share one image
+        let hashed = args.hash_names.as_ref().filter(|_| over_limit.is_none() && !resumed).map(|dir| {
+            let _span = info_span!("content_hash").entered();
+            content_hash(&file_path)
+                .map(|hash| dir.join(format!("{hash}.{}", config.output_extension())).display().to_string())
+        });
+        // The image of the file, which the segments and the bundle are
+        // named after too
+        let output_file = match &hashed {
+            Some(Ok(name)) => name.clone(),
+            _ => args.output_filename.clone().unwrap_or_else(|| {
+                let stems = if args.stems.is_empty() { "" } else { ".stems" };
+                format!("{}{stems}.{}", file_path.display(), config.output_extension())
+            }),
+        };
+        let image = hashed.as_ref().and_then(|name| name.as_ref().ok()).cloned();
+
         // For each file, create a validated AudioPath
-        match cli::AudioPath::new(&file_path) {
-            Ok(audio_path) => {
-                let output_file = args
-                    .output_filename
-                    .clone()
-                    .unwrap_or_else(|| format!("{}.png", file_path.display()));
-
-                if let Err(e) = generate_waveform(&audio_path, &output_file, &args) {
-                    let error_msg = format!("{}: {}", file_path.display(), e);
-                    args.print_to_stderr(&error_msg);
-                    errors.lock().unwrap().push(error_msg);
+        let file_started = Instant::now();
+        let mut measures = Measures::default();
+        let outcomes = cli::AudioPath::new(&file_path).map(|audio_path| {
+            if let Some(e) = over_limit {
+                return Err(e);
+            }
+            if resumed {
+                return Ok(vec![(output_file.clone(), Outcome::UpToDate)]);
+            }
+            // The image of a copy is there already, or another thread is
+            // drawing it
+            let shared = hashed.transpose()?.is_some_and(|name| {
+                let claimed = !hashed_images.lock().unwrap().insert(name.clone());
+                claimed || (!args.overwrite && config.target_path(Path::new(&name)).exists())
+            });
+            if shared {
+                Ok(vec![(output_file.clone(), Outcome::UpToDate)])
+            } else if !args.stems.is_empty() {
+                let stems: Vec<&Path> = args.stems.iter().map(AudioPath::path).collect();
+                generate_stems(&stems, &output_file, &config).map(|outcome| vec![(output_file.clone(), outcome)])
+            } else if config.segmenting().is_some() {
+                generate_segments(audio_path.path(), &config)
+            } else {
+                generate_waveform_measured(audio_path.path(), &output_file, &config).map(|(outcome, measured)| {
+                    measures = measured;
+                    vec![(output_file.clone(), outcome)]
+                })
+            }
+        });
+
+        let mut warnings = Vec::new();
+        let (status, mut error, mut outputs) = match outcomes {
+            Ok(Ok(outcomes)) => {
+                let mut error = None;
+                let mut outputs = Vec::new();
+                for (output_file, outcome) in &outcomes {
+                    match report_outcome(output_file, *outcome) {
+                        Ok(created) => outputs.extend(created.iter().map(|path| path.display().to_string())),
+                        Err(skipped) => error = error.or(Some(skipped)),
+                    }
+                }
+                // The peak cache is kept once for the audio file, however
+                // many images were drawn from it
+                if outcomes.iter().any(|(_, outcome)| *outcome == Outcome::Created) {
+                    let kept = config.peak_cache_paths(&file_path).into_iter().filter(|path| path.exists());
+                    outputs.extend(kept.map(|path| path.display().to_string()));
                 }
-            },
+                (FileStatus::of(outcomes.iter().map(|(_, outcome)| *outcome)), error, outputs)
+            }
+            // A file without audio is only a warning when asked to skip it
+            Ok(Err(e)) if args.skip_non_audio && e.is_no_audio_track() => {
+                let warning = format!("{e}, skipped");
+                output.warn(&format!("{}: {}", file_path.display(), warning));
+                warnings.push(warning);
+                (FileStatus::NoAudio, None, Vec::new())
+            }
+            Ok(Err(e)) if e.is_over_limit() => {
+                let warning = format!("{e}, skipped");
+                output.warn(&format!("{}: {}", file_path.display(), warning));
+                warnings.push(warning);
+                (FileStatus::OverLimit, None, Vec::new())
+            }
+            Ok(Err(e)) => {
+                fail(format!("{}: {}", file_path.display(), e));
+                (FileStatus::Failed, Some(e.to_string()), Vec::new())
+            }
             Err(e) => {
-                let error_msg = format!("Invalid audio path {}: {}", file_path.display(), e);
-                args.print_to_stderr(&error_msg);
-                errors.lock().unwrap().push(error_msg);
+                fail(format!("Invalid audio path {}: {}", file_path.display(), e));
+                (FileStatus::Failed, Some(format!("Invalid audio path: {e}")), Vec::new())
+            }
+        };
+        // A sample of the rendered files is decoded again to check the decoder
+        let rendered = matches!(status, FileStatus::Created | FileStatus::DryRun);
+        if let Some(decoder) = args.cross_check.filter(|_| rendered && is_sampled(&file_path, args.cross_check_every)) {
+            let _span = info_span!("cross_check").entered();
+            let warning = match cross_check(&file_path, decoder, config.width()) {
+                Ok(check) => check.discrepancy(),
+                Err(e) => Some(format!("Cross-check failed: {e}")),
+            };
+            match warning {
+                Some(warning) => {
+                    output.warn(&format!("{}: {}", file_path.display(), warning));
+                    warnings.push(warning);
+                }
+                None => output.debug(&format!("Cross-checked {} with {}", file_path.display(), decoder.name())),
+            }
+        }
+
+        if let Some(measured) = &measures.loudness {
+            let lufs = measured.integrated_lufs.map_or("-inf".to_string(), |lufs| format!("{lufs:.1}"));
+            let dbtp = measured.true_peak_dbtp.map_or("-inf".to_string(), |dbtp| format!("{dbtp:.1}"));
+            output.debug(&format!("Loudness {lufs} LUFS, true peak {dbtp} dBTP for {}", file_path.display()));
+        }
+        if let Some(bpm) = measures.bpm {
+            output.debug(&format!("Tempo {bpm:.1} BPM for {}", file_path.display()));
+        }
+
+        // The QC score decodes the file again, so stdin can not be scored
+        let scored = !matches!(status, FileStatus::Failed | FileStatus::NoAudio | FileStatus::OverLimit) && !stdin;
+        let quality = if args.qc && scored {
+            let _span = info_span!("qc").entered();
+            match scan_quality(&file_path, &args.qc_weights.unwrap_or_default(), args.max_packets) {
+                Ok(quality) => {
+                    output.debug(&format!("QC score {} for {}", quality.score, file_path.display()));
+                    Some(quality)
+                }
+                Err(e) => {
+                    let warning = format!("QC failed: {e}");
+                    output.warn(&format!("{}: {}", file_path.display(), warning));
+                    warnings.push(warning);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // Everything written for the file is also packed into its bundle
+        if args.bundle.is_some() && args.bundle_by == BundleScope::File && !outputs.is_empty() {
+            let bundle = bundle_path(Path::new(&output_file));
+            let files: Vec<PathBuf> = outputs.iter().map(PathBuf::from).collect();
+            match write_bundle(&bundle, &files) {
+                Ok(()) => {
+                    output.debug(&format!("Created {}", bundle.display()));
+                    outputs.push(bundle.display().to_string());
+                }
+                Err(e) => {
+                    fail(format!("{}: {}", file_path.display(), e));
+                    error = error.or(Some(e.to_string()));
+                }
+            }
+        }
+
+        // With --strict the first warning is the error of the file
+        let error = match error {
+            None if args.strict && !warnings.is_empty() => {
+                count_failure();
+                warnings.first().cloned()
+            }
+            error => error,
+        };
+
+        // The file is done unless it failed (or only made a plan), so it is
+        // not drawn again when the run is resumed
+        let done = !matches!(status, FileStatus::Failed | FileStatus::DryRun) && error.is_none();
+        if let Some(state) = resume.as_ref().filter(|_| done && !resumed && !stdin) {
+            if let Err(e) = state.record(&file_path, &outputs) {
+                let warning = format!("Can not record the file in the resume state: {e}");
+                output.warn(&format!("{}: {}", file_path.display(), warning));
+                warnings.push(warning);
             }
         }
+
+        if let Some(dashboard) = &dashboard {
+            let error = error.as_ref().map(|e| format!("{}: {e}", file_path.display()));
+            dashboard.finish_file(worker(), weight, error);
+        }
+        results.lock().unwrap().push(FileSummary {
+            path: file_path.display().to_string(),
+            status,
+            error,
+            outputs,
+            warnings,
+            quality,
+            loudness: measures.loudness,
+            bpm: measures.bpm.map(|bpm| (bpm * 10.0).round() / 10.0),
+            image: image.filter(|_| !matches!(status, FileStatus::Failed | FileStatus::NoAudio | FileStatus::OverLimit)),
+            seconds: file_started.elapsed().as_secs_f64(),
+        });
+        ordered.complete(position, output.into_lines(), Line::emit);
+
+        if let Some(progress) = &progress {
+            logger.progress(&progress.complete(weight, &file_path));
+        }
+    };
+
+    // The dashboard runs next to the workers until the last file is done
+    let finished = AtomicBool::new(false);
+    let shown = thread::scope(|scope| {
+        let shown = dashboard.as_ref().map(|dashboard| scope.spawn(|| dashboard.show(&stopped, &finished)));
+        work.into_iter().par_bridge().for_each(process);
+        finished.store(true, Ordering::Relaxed);
+        shown.map(|shown| shown.join().expect("Dashboard thread panicked"))
     });
+    if let Some(Err(e)) = shown {
+        eprintln!("{e}");
+    }
+
+    ordered.finish(Line::emit);
+
+    let mut results = results.into_inner().unwrap();
+    if args.bundle.is_some() && args.bundle_by == BundleScope::Directory {
+        bundle_directories(&args, &mut results);
+    }
+
+    // Everything the run reports is taken from its summary
+    let summary = RunSummary::new(files, results, started.elapsed())
+        .with_stopped(stopped.load(Ordering::Relaxed))
+        .with_run_id(args.run_id())
+        .with_arguments(args.arguments.clone());
+    if let Some(summary_path) = &args.report {
+        if is_stdout(summary_path) {
+            summary.write_json(std::io::stdout().lock())?;
+        } else {
+            summary.write_json(audit::create(summary_path)?)?;
+        }
+    }
+    if let Some(report_path) = &args.ignore_errors {
+        let report = summary.error_report();
+        if is_stdout(report_path) {
+            report.write_json(std::io::stdout().lock())?;
+        } else {
+            report.write_json(audit::create(report_path)?)?;
+        }
+    }
+    logger.debug(&summary.totals());
+
+    // With --ignore-errors the errors are only reported
+    match summary.error_message(&budget) {
+        Some(message) if args.ignore_errors.is_none() && summary.is_failure(&budget) => {
+            eprintln!("Error: {}", WaverError::generation_error(message));
+            Ok(summary.exit_status(&budget))
+        }
+        Some(message) => {
+            logger.warn(&message);
+            Ok(ExitStatus::Success)
+        }
+        None => Ok(ExitStatus::Success),
+    }
+}
+
+/// Packs the files written for the audio files of each directory into one
+/// bundle there.  If a bundle can not be written, every file of its
+/// directory gets the error.
+fn bundle_directories(args: &WaverArgs, results: &mut [FileSummary]) {
+    let mut directories: BTreeMap<PathBuf, Vec<usize>> = BTreeMap::new();
+    for (index, result) in results.iter().enumerate().filter(|(_, result)| !result.outputs.is_empty()) {
+        let dir = Path::new(&result.path).parent().unwrap_or(Path::new(""));
+        directories.entry(dir.to_path_buf()).or_default().push(index);
+    }
+
+    for (dir, indices) in directories {
+        let bundle = directory_bundle_path(&dir);
+        let mut files: Vec<PathBuf> = indices
+            .iter()
+            .flat_map(|&index| results[index].outputs.iter().map(PathBuf::from))
+            .collect();
+        files.sort();
+        match write_bundle(&bundle, &files) {
+            Ok(()) => args.logger().info(&format!("Created {}", bundle.display())),
+            Err(e) => {
+                args.logger().error(&format!("{}: {}", bundle.display(), e));
+                for index in indices {
+                    results[index].error.get_or_insert_with(|| e.to_string());
+                }
+            }
+        }
+    }
+}
+
+/// Runs one of the tools selected by a subcommand and returns its exit
+/// status.
+fn run_command(command: &Command) -> std::result::Result<ExitStatus, Box<dyn std::error::Error>> {
+    match command {
+        Command::Compare(compare) => run_compare(compare).map(|()| ExitStatus::Success),
+        Command::Metadata(metadata) => run_metadata(metadata).map(|()| ExitStatus::Success),
+        Command::Config(config) => run_config(config).map(|()| ExitStatus::Success),
+        Command::Diff(diff) => run_diff(diff),
+        Command::Bench(bench) => run_bench(bench).map(|()| ExitStatus::Success),
+    }
+}
 
-    // Report any errors
-    let errors = errors.lock().unwrap();
-    if !errors.is_empty() {
+/// Times the stages of the waveform of every file with the options of the
+/// run and prints the table (or JSON) to stdout.
+fn run_bench(args: &BenchArgs) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let matches = args.matches().unwrap_or_else(|err| exit_with(err));
+    let run = WaverArgs::from_arg_matches(&matches).unwrap_or_else(|err| exit_with(err));
+    run.validate()?;
+    let config = run.config()?;
+
+    // Every format is checked like the --output-format of a run
+    let formats = if args.formats.is_empty() { vec![run.output_format] } else { args.formats.clone() };
+    let encodings =
+        formats.iter().map(|&format| run.config_builder().format(format).build()).collect::<Result<Vec<_>, _>>()?;
+
+    let files = args
+        .files
+        .iter()
+        .map(|file| bench_file(file.path(), &config, &encodings, args.runs))
+        .collect::<Result<Vec<_>, _>>()?;
+    let report = BenchReport { runs: args.runs, files };
+    let stdout = std::io::stdout().lock();
+    match args.report {
+        ReportFormat::Text => report.write_text(stdout)?,
+        ReportFormat::Json => report.write_json(stdout)?,
+    }
+    Ok(())
+}
+
+/// Checks the peak hashes of waveform images against each other or their
+/// audio and prints the result to stdout.
+///
+/// Stale or differing images exit with the status of failed files, so a
+/// script can re-render them.
+fn run_diff(args: &DiffArgs) -> std::result::Result<ExitStatus, Box<dyn std::error::Error>> {
+    let matches = args.matches().unwrap_or_else(|err| exit_with(err));
+    let run = WaverArgs::from_arg_matches(&matches).unwrap_or_else(|err| exit_with(err));
+    run.validate()?;
+
+    let images = args.images.iter().map(|path| EmbeddedImage::read(path)).collect::<Result<Vec<_>, _>>()?;
+    let audio = args.audio.as_ref().map(AudioPath::path);
+    let diff = WaveformDiff::new(&images, audio, |audio, width| {
+        peak_hash(audio, &run.config_builder().width(width).build()?)
+    })?;
+
+    let stdout = std::io::stdout().lock();
+    match args.report {
+        ReportFormat::Text => diff.write_text(stdout)?,
+        ReportFormat::Json => diff.write_json(stdout)?,
+    }
+    Ok(if diff.differs() { ExitStatus::SomeFilesFailed } else { ExitStatus::Success })
+}
+
+/// Prints every option a run with the given options would use, with its
+/// value and where the value came from.
+///
+/// The options are checked like those of a run, so a value that would be
+/// rejected is reported rather than shown.
+fn run_config(args: &ConfigArgs) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let matches = args.matches().unwrap_or_else(|err| exit_with(err));
+    let resolved = WaverArgs::from_arg_matches(&matches).unwrap_or_else(|err| exit_with(err));
+    resolved.validate()?;
+    resolved.config()?;
+
+    let options = WaverArgs::resolved_options(&matches);
+    let option_width = options.iter().map(|option| option.option.len()).max().unwrap_or(0);
+    let value_width = options.iter().map(|option| option.value.len()).max().unwrap_or(0);
+    let mut stdout = std::io::stdout().lock();
+    for option in options {
+        writeln!(stdout, "{:option_width$}  {:value_width$}  {}", option.option, option.value, option.origin)?;
+    }
+    Ok(())
+}
+
+/// Compares two audio files and prints the report to stdout.
+fn run_compare(args: &CompareArgs) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let config = WaveformConfig::builder()
+        .width(args.width.value())
+        .height(args.height.value())
+        .overlap(args.overlap)
+        .build()?;
+    let comparison = compare_files(
+        args.reference.path(),
+        args.candidate.path(),
+        args.diff_image.as_deref(),
+        &config,
+    )?;
+
+    let stdout = std::io::stdout().lock();
+    match args.report {
+        ReportFormat::Text => comparison.write_text(stdout)?,
+        ReportFormat::Json => comparison.write_json(stdout)?,
+    }
+    Ok(())
+}
+
+/// Reads the tags of the audio files and writes them as one JSON array.
+///
+/// Files that can not be read are reported on stderr and left out, and the
+/// run fails at the end if there were any.
+fn run_metadata(args: &MetadataArgs) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    if args.audio_paths.iter().any(|path| path.is_stdin()) {
+        return Err(Box::new(WaverError::argument_error("The metadata can not be read from stdin")));
+    }
+    let mut audio_files = collect_audio_files(&args.audio_paths, &args.file_extensions.as_strings(), args.traversal(), &args.exclude);
+    if let Some(list) = &args.files_from {
+        audio_files.extend(read_file_list(list, &args.exclude)?);
+    }
+
+    let results: Vec<_> = audio_files.par_iter().map(|path| read_metadata(path)).collect();
+    let mut tracks = Vec::with_capacity(results.len());
+    let mut errors = 0;
+    for (path, result) in audio_files.iter().zip(results) {
+        match result {
+            Ok(track) => tracks.push(track),
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                errors += 1;
+            }
+        }
+    }
+
+    match &args.output {
+        Some(output) if !is_stdout(output) => write_json(&tracks, audit::create(output)?)?,
+        _ => write_json(&tracks, std::io::stdout().lock())?,
+    }
+    if errors > 0 {
         return Err(Box::new(WaverError::generation_error(format!(
-            "{} errors occurred while processing files",
-            errors.len()
+            "{errors} errors occurred while reading the metadata"
         ))));
     }
-
     Ok(())
-}
\ No newline at end of file
+}
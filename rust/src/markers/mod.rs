@@ -0,0 +1,123 @@
+/// Markers from a file given on the command line (`--markers`).
+///
+/// Chapters of an audiobook or the cue points of a DJ set are often kept
+/// next to the audio rather than in it, and `--markers` draws them as lines
+/// across the waveform like the index points of an embedded cuesheet.  The
+/// file is a cue sheet (`.cue`) or JSON, either a list of times or of
+/// objects with their time in `seconds`, or an object with such a list of
+/// `markers` (like the sidecar):
+///
+/// ```json
+/// [{ "seconds": 0, "title": "Intro" }, { "seconds": 95.5, "title": "Chapter 1" }]
+/// ```
+///
+/// Other fields, like the titles, are left alone.  The markers of a list
+/// count as the tracks, each with one index point.
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::{Result, WaverError};
+use crate::metadata::Marker;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The frames of a second in the times of a cue sheet.
+const CUE_FRAMES_PER_SECOND: f64 = 75.0;
+
+/// A marker of a JSON marker file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonMarker {
+    Seconds(f64),
+    Object { seconds: f64 },
+}
+
+/// The contents of a JSON marker file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonMarkers {
+    List(Vec<JsonMarker>),
+    Object { markers: Vec<JsonMarker> },
+}
+
+/// Loads the markers of a cue sheet (with the `.cue` extension) or a JSON
+/// file.
+pub fn load_markers(path: &Path) -> Result<Vec<Marker>> {
+    let text = fs::read_to_string(path).map_err(|err| {
+        WaverError::argument_error(format!("Can not read the markers '{}': {err}", path.display()))
+    })?;
+    let is_cue = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("cue"));
+    let markers = if is_cue { parse_cue(&text) } else { parse_json(&text) };
+    markers.map_err(|err| match err {
+        WaverError::ArgumentError(message) => {
+            WaverError::argument_error(format!("The markers '{}': {message}", path.display()))
+        }
+        other => other,
+    })
+}
+
+/// Parses the markers of a JSON marker file.
+pub fn parse_json(text: &str) -> Result<Vec<Marker>> {
+    let markers = match serde_json::from_str(text) {
+        Ok(JsonMarkers::List(markers) | JsonMarkers::Object { markers }) => markers,
+        Err(err) => return Err(WaverError::argument_error(format!("not a list of markers ({err})"))),
+    };
+    markers
+        .iter()
+        .zip(1..)
+        .map(|(marker, track)| {
+            let (JsonMarker::Seconds(seconds) | JsonMarker::Object { seconds }) = *marker;
+            if !seconds.is_finite() || seconds < 0.0 {
+                return Err(WaverError::argument_error(format!("marker {track}: the time must not be negative")));
+            }
+            Ok(Marker { track, index: 1, seconds })
+        })
+        .collect()
+}
+
+/// Parses the index points of a cue sheet, in the order of the sheet.
+///
+/// Only the `TRACK` and `INDEX` commands matter; the others (and the
+/// `FILE` of a sheet of several files, whose times all count from the start
+/// of the audio) are skipped.
+pub fn parse_cue(text: &str) -> Result<Vec<Marker>> {
+    let mut markers = Vec::new();
+    let mut track = None;
+    let mut index = 0;
+    for (number, line) in text.lines().enumerate() {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        if command.eq_ignore_ascii_case("TRACK") {
+            let parsed = words.next().and_then(|word| word.parse().ok());
+            track = Some(parsed.ok_or_else(|| line_error(number, "a track needs its number"))?);
+            index = 0;
+        } else if command.eq_ignore_ascii_case("INDEX") {
+            let track = track.ok_or_else(|| line_error(number, "an index point needs a track before it"))?;
+            let time = words.nth(1).and_then(cue_seconds);
+            let seconds = time.ok_or_else(|| line_error(number, "an index point needs a time of mm:ss:ff"))?;
+            index += 1;
+            markers.push(Marker { track, index, seconds });
+        }
+    }
+    Ok(markers)
+}
+
+/// Returns the seconds of a cue sheet time of minutes, seconds and frames.
+fn cue_seconds(time: &str) -> Option<f64> {
+    let mut parts = time.split(':').map(|part| part.parse::<u64>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || seconds >= 60 || frames >= CUE_FRAMES_PER_SECOND as u64 {
+        return None;
+    }
+    let seconds = (minutes * 60 + seconds) as f64 + frames as f64 / CUE_FRAMES_PER_SECOND;
+    Some((seconds * 1000.0).round() / 1000.0)
+}
+
+/// Returns the error of a line of the cue sheet.
+fn line_error(number: usize, message: &str) -> WaverError {
+    WaverError::argument_error(format!("line {}: {message}", number + 1))
+}
@@ -0,0 +1,45 @@
+#[cfg(test)]
+mod tests {
+    use crate::markers::{parse_cue, parse_json};
+    use crate::metadata::Marker;
+
+    #[test]
+    fn test_parse_cue() {
+        let sheet = "\
+REM GENRE Live
+FILE \"concert.flac\" WAVE
+  TRACK 01 AUDIO
+    TITLE \"Opening\"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 00 06:50:00
+    INDEX 01 06:52:27
+  track 03 audio
+    index 01 112:00:74
+";
+        assert_eq!(
+            parse_cue(sheet).unwrap(),
+            vec![
+                Marker { track: 1, index: 1, seconds: 0.0 },
+                Marker { track: 2, index: 1, seconds: 410.0 },
+                Marker { track: 2, index: 2, seconds: 412.36 },
+                Marker { track: 3, index: 1, seconds: 6720.987 },
+            ]
+        );
+        assert!(parse_cue("INDEX 01 00:00:00").is_err(), "An index point belongs to a track");
+        assert!(parse_cue("TRACK 01 AUDIO\nINDEX 01 00:60:00").is_err());
+        assert!(parse_cue("TRACK 01 AUDIO\nINDEX 01 00:00:75").is_err(), "75 frames a second");
+        assert!(parse_cue("TRACK one AUDIO").is_err());
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let expected = vec![Marker { track: 1, index: 1, seconds: 0.0 }, Marker { track: 2, index: 1, seconds: 95.5 }];
+        assert_eq!(parse_json("[0, 95.5]").unwrap(), expected);
+        assert_eq!(parse_json(r#"[{"seconds": 0, "title": "Intro"}, {"seconds": 95.5}]"#).unwrap(), expected);
+        let sidecar = r#"{"source": "a.flac", "markers": [{"track": 1, "index": 1, "seconds": 0.0}, {"track": 2, "index": 1, "seconds": 95.5}]}"#;
+        assert_eq!(parse_json(sidecar).unwrap(), expected, "The markers of a sidecar");
+        assert!(parse_json("[-1]").is_err());
+        assert!(parse_json(r#"{"chapters": []}"#).is_err());
+    }
+}
@@ -0,0 +1,287 @@
+/// Reading the tags of audio files (`waver metadata` and `--extract-art`).
+///
+/// Symphonia reads the tags while probing a file: ID3 tags in front of an
+/// MP3 come with the probe, Vorbis comments (FLAC, Ogg) and MP4 atoms with
+/// the container.  The title, artist and album found there are written as
+/// JSON together with the duration and format of the first audio track, so
+/// a player can list a library without reading the files itself.  The
+/// embedded pictures come with the tags, so the cover art is read the same
+/// way, and so are the index points of a cuesheet embedded in a FLAC file,
+/// which become the markers of live recordings kept as a single file.
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use symphonia::core::codecs::CODEC_TYPE_NULL;
+use symphonia::core::formats::{Cue, FormatOptions};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, StandardTagKey, StandardVisualKey, Tag, Visual};
+use symphonia::core::probe::{Hint, ProbeResult};
+
+use crate::audio::count_frames;
+use crate::error::{Result, WaverError};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The tags of an audio file, each only if the file has it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Tags {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album_artist: Option<String>,
+    /// Track number as tagged (e.g. `3` or `3/12`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genre: Option<String>,
+}
+
+impl Tags {
+    /// Fills in the tags that are still missing from the given ones.
+    ///
+    /// The first value of a tag wins, so the more specific metadata (that
+    /// of the container) is added first.  Empty values are ignored.
+    pub fn fill(&mut self, tags: &[Tag]) {
+        for tag in tags {
+            let field = match tag.std_key {
+                Some(StandardTagKey::TrackTitle) => &mut self.title,
+                Some(StandardTagKey::Artist) => &mut self.artist,
+                Some(StandardTagKey::Album) => &mut self.album,
+                Some(StandardTagKey::AlbumArtist) => &mut self.album_artist,
+                Some(StandardTagKey::TrackNumber) => &mut self.track,
+                Some(StandardTagKey::Date) => &mut self.date,
+                Some(StandardTagKey::Genre) => &mut self.genre,
+                _ => continue,
+            };
+            let value = tag.value.to_string();
+            if field.is_none() && !value.trim().is_empty() {
+                *field = Some(value.trim().to_string());
+            }
+        }
+    }
+}
+
+/// The tags and format of one audio file.
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackMetadata {
+    /// Path of the audio file
+    pub path: String,
+    #[serde(flatten)]
+    pub tags: Tags,
+    /// Duration of the first audio track in seconds
+    pub duration_seconds: f64,
+    /// Sample rate in Hz (0 if unknown)
+    pub sample_rate: u32,
+    /// Number of channels in the track
+    pub channels: usize,
+    /// Index points of the embedded cuesheet, if the file has one
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub markers: Vec<Marker>,
+}
+
+/// Track numbers of the lead-out in CD-DA and other cuesheets, which marks
+/// the end of the audio rather than a point in it.
+const LEAD_OUT_TRACKS: [u32; 2] = [170, 255];
+
+/// One index point of an embedded cuesheet.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Marker {
+    /// Number of the track in the cuesheet
+    pub track: u32,
+    /// Number of the index point within the track, counted from 1 in the
+    /// order of the cuesheet
+    pub index: u32,
+    /// Time of the index point in the audio, in seconds (to the millisecond)
+    pub seconds: f64,
+}
+
+impl Marker {
+    /// Returns the index points of the cues, in the order of the cuesheet.
+    ///
+    /// The cues and their points count frames, so they are converted with
+    /// the sample rate (without one there are no markers).  The lead-out
+    /// has no index points of its own and is skipped.
+    pub fn from_cues(cues: &[Cue], sample_rate: u32) -> Vec<Marker> {
+        if sample_rate == 0 {
+            return Vec::new();
+        }
+        cues.iter()
+            .filter(|cue| !LEAD_OUT_TRACKS.contains(&cue.index))
+            .flat_map(|cue| {
+                cue.points.iter().zip(1..).map(move |(point, index)| {
+                    let frames = cue.start_ts + point.start_offset_ts;
+                    Marker {
+                        track: cue.index,
+                        index,
+                        seconds: (frames as f64 / sample_rate as f64 * 1000.0).round() / 1000.0,
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+/// An embedded picture of an audio file, like the cover of its album.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverArt {
+    /// Media type as recorded in the file (may be empty)
+    pub media_type: String,
+    /// The encoded image
+    pub data: Box<[u8]>,
+}
+
+impl CoverArt {
+    /// Picks the front cover of the pictures, or the first picture if none
+    /// of them is marked as the front cover.
+    pub fn pick(visuals: &[Visual]) -> Option<Self> {
+        visuals
+            .iter()
+            .find(|visual| visual.usage == Some(StandardVisualKey::FrontCover))
+            .or_else(|| visuals.first())
+            .map(|visual| CoverArt {
+                media_type: visual.media_type.clone(),
+                data: visual.data.clone(),
+            })
+    }
+
+    /// Returns the file extension of the image, from its media type or
+    /// else from the signature at the start of the data (`bin` if neither
+    /// is known).
+    pub fn extension(&self) -> &'static str {
+        match self.media_type.to_ascii_lowercase().as_str() {
+            "image/jpeg" | "image/jpg" => return "jpg",
+            "image/png" => return "png",
+            "image/gif" => return "gif",
+            "image/bmp" => return "bmp",
+            "image/webp" => return "webp",
+            _ => {}
+        }
+        match &self.data[..] {
+            [0xff, 0xd8, ..] => "jpg",
+            [0x89, b'P', b'N', b'G', ..] => "png",
+            [b'G', b'I', b'F', ..] => "gif",
+            [b'B', b'M', ..] => "bmp",
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "webp",
+            _ => "bin",
+        }
+    }
+
+    /// Returns the path of the art of `input_path` in `dir`: the audio file
+    /// name with the image extension added (`song.mp3.jpg`).
+    pub fn path_for(&self, dir: &Path, input_path: &Path) -> PathBuf {
+        let mut name = input_path.file_name().unwrap_or(input_path.as_os_str()).to_os_string();
+        name.push(".");
+        name.push(self.extension());
+        dir.join(name)
+    }
+}
+
+/// Probes an audio file for its format and tags, with its extension as
+/// the hint.
+fn probe(input_path: &Path) -> Result<ProbeResult> {
+    let mut hint = Hint::new();
+    if let Some(extension) = input_path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(extension);
+    }
+    let mss = MediaSourceStream::new(Box::new(File::open(input_path)?), Default::default());
+    Ok(symphonia::default::get_probe().format(
+        &hint,
+        mss,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?)
+}
+
+/// Reads the cover art of an audio file, if it has any.
+///
+/// Like the tags, the pictures of the container are preferred over those
+/// found by the probe.  Only the tags are read, not the audio.
+pub fn read_cover_art(input_path: &Path) -> Result<Option<CoverArt>> {
+    let mut probed = probe(input_path)?;
+    let art = probed
+        .format
+        .metadata()
+        .current()
+        .and_then(|revision| CoverArt::pick(revision.visuals()));
+    Ok(art.or_else(|| {
+        probed
+            .metadata
+            .get()
+            .as_ref()
+            .and_then(|metadata| metadata.current())
+            .and_then(|revision| CoverArt::pick(revision.visuals()))
+    }))
+}
+
+/// Reads the index points of the cuesheet embedded in an audio file as
+/// markers, or none if it has no cuesheet (only FLAC files carry one).
+///
+/// Only the headers are read, so this works just as well when the peaks
+/// come from the peak cache.
+pub fn read_markers(input_path: &Path) -> Result<Vec<Marker>> {
+    let probed = probe(input_path)?;
+    let format = probed.format;
+    let sample_rate = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .and_then(|track| track.codec_params.sample_rate)
+        .unwrap_or(0);
+    Ok(Marker::from_cues(format.cues(), sample_rate))
+}
+
+/// Reads the tags, the format and the cuesheet markers of an audio file.
+///
+/// Nothing is decoded: the duration is what the container records, or the
+/// end of the last packet for containers that do not record it.
+pub fn read_metadata(input_path: &Path) -> Result<TrackMetadata> {
+    let mut probed = probe(input_path)?;
+    let mut format = probed.format;
+
+    let mut tags = Tags::default();
+    if let Some(revision) = format.metadata().current() {
+        tags.fill(revision.tags());
+    }
+    if let Some(revision) = probed.metadata.get().as_ref().and_then(|metadata| metadata.current()) {
+        tags.fill(revision.tags());
+    }
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| WaverError::no_audio_track(input_path))?;
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+    let sample_rate = params.sample_rate.unwrap_or(0);
+    let frames = match params.n_frames {
+        Some(frames) => frames,
+        None => count_frames(format.as_mut(), track_id, &params, input_path)?,
+    };
+
+    Ok(TrackMetadata {
+        path: input_path.display().to_string(),
+        tags,
+        duration_seconds: if sample_rate == 0 { 0.0 } else { frames as f64 / sample_rate as f64 },
+        sample_rate,
+        channels: params.channels.map(|c| c.count()).unwrap_or(1),
+        markers: Marker::from_cues(format.cues(), sample_rate),
+    })
+}
+
+/// Writes the metadata of the files as a pretty printed JSON array.
+pub fn write_json(tracks: &[TrackMetadata], mut writer: impl Write) -> Result<()> {
+    serde_json::to_writer_pretty(&mut writer, tracks)?;
+    writeln!(writer)?;
+    Ok(())
+}
@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use symphonia::core::formats::{Cue, CuePoint};
+    use symphonia::core::meta::{StandardTagKey, StandardVisualKey, Tag, Value, Visual};
+
+    use crate::metadata::{write_json, CoverArt, Marker, Tags, TrackMetadata};
+
+    fn tag(key: StandardTagKey, value: impl Into<Value>) -> Tag {
+        Tag::new(Some(key), "", value.into())
+    }
+
+    fn visual(usage: Option<StandardVisualKey>, media_type: &str, data: &[u8]) -> Visual {
+        Visual {
+            media_type: media_type.to_string(),
+            dimensions: None,
+            bits_per_pixel: None,
+            color_mode: None,
+            usage,
+            tags: Vec::new(),
+            data: data.into(),
+        }
+    }
+
+    fn art(media_type: &str, data: &[u8]) -> CoverArt {
+        CoverArt {
+            media_type: media_type.to_string(),
+            data: data.into(),
+        }
+    }
+
+    #[test]
+    fn test_fill_keeps_first_value() {
+        let mut tags = Tags::default();
+        tags.fill(&[
+            tag(StandardTagKey::TrackTitle, "Container Title"),
+            tag(StandardTagKey::TrackNumber, 3u32),
+            Tag::new(None, "CUSTOM", Value::from("ignored")),
+        ]);
+        tags.fill(&[
+            tag(StandardTagKey::TrackTitle, "ID3 Title"),
+            tag(StandardTagKey::Artist, " Artist "),
+            tag(StandardTagKey::Album, "  "),
+        ]);
+
+        assert_eq!(tags.title.as_deref(), Some("Container Title"));
+        assert_eq!(tags.track.as_deref(), Some("3"));
+        assert_eq!(tags.artist.as_deref(), Some("Artist"), "Values are trimmed");
+        assert_eq!(tags.album, None, "Empty values are ignored");
+    }
+
+    #[test]
+    fn test_write_json_skips_missing_tags() {
+        let track = TrackMetadata {
+            path: "song.mp3".to_string(),
+            tags: Tags {
+                title: Some("Song".to_string()),
+                ..Tags::default()
+            },
+            duration_seconds: 2.5,
+            sample_rate: 44100,
+            channels: 2,
+            markers: Vec::new(),
+        };
+        let mut bytes = Vec::new();
+        write_json(&[track], &mut bytes).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json[0]["path"], "song.mp3");
+        assert_eq!(json[0]["title"], "Song");
+        assert_eq!(json[0]["duration_seconds"], 2.5);
+        assert!(json[0].get("artist").is_none());
+        assert!(json[0].get("markers").is_none());
+    }
+
+    fn cue(track: u32, start_ts: u64, offsets: &[u64]) -> Cue {
+        Cue {
+            index: track,
+            start_ts,
+            tags: Vec::new(),
+            points: offsets
+                .iter()
+                .map(|&start_offset_ts| CuePoint { start_offset_ts, tags: Vec::new() })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_markers_from_cues() {
+        let cues = [cue(1, 0, &[0]), cue(2, 441_000, &[0, 88_200]), cue(170, 882_000, &[])];
+        let markers = Marker::from_cues(&cues, 44100);
+        assert_eq!(
+            markers,
+            [
+                Marker { track: 1, index: 1, seconds: 0.0 },
+                Marker { track: 2, index: 1, seconds: 10.0 },
+                Marker { track: 2, index: 2, seconds: 12.0 },
+            ],
+            "The points are offsets from their track and the lead-out is skipped"
+        );
+
+        let rounded = Marker::from_cues(&[cue(1, 1, &[0])], 3);
+        assert_eq!(rounded[0].seconds, 0.333, "Times are rounded to the millisecond");
+        assert!(Marker::from_cues(&cues, 0).is_empty(), "No sample rate, no markers");
+    }
+
+    #[test]
+    fn test_pick_prefers_front_cover() {
+        let back = visual(Some(StandardVisualKey::BackCover), "image/png", b"back");
+        let front = visual(Some(StandardVisualKey::FrontCover), "image/jpeg", b"front");
+        assert_eq!(CoverArt::pick(&[back.clone(), front]), Some(art("image/jpeg", b"front")));
+        assert_eq!(CoverArt::pick(&[back]), Some(art("image/png", b"back")), "Any picture will do");
+        assert_eq!(CoverArt::pick(&[]), None);
+    }
+
+    #[test]
+    fn test_art_extension() {
+        assert_eq!(art("image/jpeg", b"").extension(), "jpg");
+        assert_eq!(art("Image/PNG", b"").extension(), "png");
+        assert_eq!(art("", b"\xff\xd8\xff\xe0").extension(), "jpg", "Sniffed from the data");
+        assert_eq!(art("image/unknown", b"\x89PNG\r\n").extension(), "png");
+        assert_eq!(art("", b"RIFF\0\0\0\0WEBPVP8 ").extension(), "webp");
+        assert_eq!(art("", b"??").extension(), "bin");
+
+        let path = art("image/jpeg", b"").path_for(Path::new("art"), Path::new("music/song.mp3"));
+        assert_eq!(path, Path::new("art/song.mp3.jpg"));
+    }
+}
@@ -0,0 +1,106 @@
+/// Selecting and mixing the decoded channels that are drawn.
+use std::str::FromStr;
+
+use crate::error::{Result, WaverError};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// Which channels of the audio are drawn, and how they are mixed.
+///
+/// Every mode except `Stereo` draws a single (mono) waveform.  Files with
+/// more than two channels (like 5.1) have the front left and right as
+/// their first two channels, which is what `Stereo`, `Left`, `Right`,
+/// `Mid` and `Side` use; `Mono` mixes all of the channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ChannelMode {
+    /// The left and right channel (mono files stay mono)
+    #[default]
+    Stereo,
+    /// The average of all channels
+    Mono,
+    /// Only the left (first) channel
+    Left,
+    /// Only the right (second) channel, or the only one of a mono file
+    Right,
+    /// The average of left and right, what both speakers have in common
+    Mid,
+    /// Half the difference of left and right, what makes the stereo image
+    Side,
+}
+
+impl FromStr for ChannelMode {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "stereo" => Ok(ChannelMode::Stereo),
+            "mono" => Ok(ChannelMode::Mono),
+            "left" => Ok(ChannelMode::Left),
+            "right" => Ok(ChannelMode::Right),
+            "mid" => Ok(ChannelMode::Mid),
+            "side" => Ok(ChannelMode::Side),
+            _ => Err(WaverError::argument_error(
+                "Channels must be stereo, mono, left, right, mid, or side",
+            )),
+        }
+    }
+}
+
+impl ChannelMode {
+    /// Returns true if the waveform of a track with the given number of
+    /// channels is drawn in stereo.
+    pub fn is_stereo(&self, track_channels: usize) -> bool {
+        *self == ChannelMode::Stereo && track_channels > 1
+    }
+}
+
+/// Turns the decoded channels of each packet into the one or two channels
+/// that are drawn, reusing one buffer for the mixed samples.
+#[derive(Debug, Default)]
+pub struct ChannelMixer {
+    mixed: Vec<f32>,
+}
+
+impl ChannelMixer {
+    /// Returns the channels to draw: the left (or mono) channel and the
+    /// right channel if drawn in stereo.
+    ///
+    /// `channels` are the decoded channels of a packet, all of the same
+    /// length, and there must be at least one.
+    pub fn mix<'a>(&'a mut self, channels: &[&'a [f32]], mode: ChannelMode) -> (&'a [f32], Option<&'a [f32]>) {
+        let left = channels[0];
+        let right = channels.get(1).copied();
+        self.mixed.clear();
+        match (mode, right) {
+            (ChannelMode::Stereo, right) => (left, right),
+            (ChannelMode::Left, _) | (ChannelMode::Mono | ChannelMode::Mid, None) => (left, None),
+            (ChannelMode::Right, right) => (right.unwrap_or(left), None),
+            (ChannelMode::Mono, Some(_)) => {
+                self.mixed.extend_from_slice(left);
+                for channel in &channels[1..] {
+                    for (mixed, sample) in self.mixed.iter_mut().zip(*channel) {
+                        *mixed += sample;
+                    }
+                }
+                let scale = 1.0 / channels.len() as f32;
+                self.mixed.iter_mut().for_each(|mixed| *mixed *= scale);
+                (&self.mixed, None)
+            }
+            (ChannelMode::Mid, Some(right)) => {
+                self.mixed.extend(left.iter().zip(right).map(|(l, r)| (l + r) * 0.5));
+                (&self.mixed, None)
+            }
+            (ChannelMode::Side, Some(right)) => {
+                self.mixed.extend(left.iter().zip(right).map(|(l, r)| (l - r) * 0.5));
+                (&self.mixed, None)
+            }
+            (ChannelMode::Side, None) => {
+                // A mono file has nothing that differs between the speakers
+                self.mixed.resize(left.len(), 0.0);
+                (&self.mixed, None)
+            }
+        }
+    }
+}
@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod tests {
+    use crate::mix::{ChannelMixer, ChannelMode};
+    use std::str::FromStr;
+
+    const LEFT: [f32; 3] = [0.5, -0.25, 1.0];
+    const RIGHT: [f32; 3] = [0.25, 0.25, -1.0];
+    const CENTER: [f32; 3] = [0.0, 0.5, 0.0];
+
+    #[test]
+    fn test_mode_parsing() {
+        assert_eq!(ChannelMode::from_str("stereo").unwrap(), ChannelMode::Stereo);
+        assert_eq!(ChannelMode::from_str(" Mid ").unwrap(), ChannelMode::Mid);
+        assert_eq!(ChannelMode::from_str("side").unwrap(), ChannelMode::Side);
+
+        let result = ChannelMode::from_str("surround");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid argument: Channels must be stereo, mono, left, right, mid, or side"
+        );
+    }
+
+    #[test]
+    fn test_is_stereo() {
+        assert!(ChannelMode::Stereo.is_stereo(2));
+        assert!(ChannelMode::Stereo.is_stereo(6));
+        assert!(!ChannelMode::Stereo.is_stereo(1));
+        assert!(!ChannelMode::Mid.is_stereo(2));
+    }
+
+    #[test]
+    fn test_selected_channels() {
+        let mut mixer = ChannelMixer::default();
+        assert_eq!(mixer.mix(&[&LEFT, &RIGHT], ChannelMode::Stereo), (&LEFT[..], Some(&RIGHT[..])));
+        assert_eq!(mixer.mix(&[&LEFT, &RIGHT], ChannelMode::Left), (&LEFT[..], None));
+        assert_eq!(mixer.mix(&[&LEFT, &RIGHT], ChannelMode::Right), (&RIGHT[..], None));
+
+        // A mono file stays mono whatever is selected
+        assert_eq!(mixer.mix(&[&LEFT], ChannelMode::Stereo), (&LEFT[..], None));
+        assert_eq!(mixer.mix(&[&LEFT], ChannelMode::Right), (&LEFT[..], None));
+        assert_eq!(mixer.mix(&[&LEFT], ChannelMode::Mid), (&LEFT[..], None));
+    }
+
+    #[test]
+    fn test_mixed_channels() {
+        let mut mixer = ChannelMixer::default();
+        assert_eq!(mixer.mix(&[&LEFT, &RIGHT], ChannelMode::Mid).0, [0.375, 0.0, 0.0]);
+        assert_eq!(mixer.mix(&[&LEFT, &RIGHT], ChannelMode::Side).0, [0.125, -0.25, 1.0]);
+        assert_eq!(mixer.mix(&[&LEFT], ChannelMode::Side).0, [0.0; 3]);
+
+        // Mono mixes every channel, not just the front left and right
+        let (mono, right) = mixer.mix(&[&LEFT, &RIGHT, &CENTER], ChannelMode::Mono);
+        assert_eq!(mono, [0.25, 0.5 / 3.0, 0.0]);
+        assert_eq!(right, None);
+    }
+}
@@ -0,0 +1,444 @@
+/// Printing the messages of a batch in a deterministic order.
+///
+/// The files of a batch are processed in parallel and finish in whatever
+/// order the threads get to them, so the "Created ..." lines of two runs
+/// over the same files come out in a different order and are hard to diff.
+/// Each file collects its lines in a `FileOutput` while it is processed,
+/// and the `OrderedOutput` of the batch prints them in one piece when the
+/// file is done: right away, in the order the files were found, or all of
+/// them at the end of the run.
+///
+/// Every message has a level, and the `Logger` of the run decides which
+/// are kept (`--log-level`, or `--quiet` and `--verbose`).  They are
+/// emitted as `tracing` events with the `waver::output` target and the file
+/// as a field, so a service that installs its own subscriber gets them like
+/// any other log.  The binary prints them with the `OutputLayer`: as plain
+/// lines, the errors and warnings to stderr and the rest to stdout, or with
+/// `--log-json` as one JSON object a line on stderr, for log collectors
+/// that route and filter them:
+///
+/// ```json
+/// {"time":"2026-10-17T09:41:07Z","level":"info","file":"song.mp3","message":"Created song.mp3.png"}
+/// ```
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::{Filtered, LevelFilter, Targets};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::audit::utc_timestamp;
+use crate::error::{Result, WaverError};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// When the lines of the files are printed (`--output-order`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum OutputOrder {
+    /// As each file finishes
+    #[default]
+    Completion,
+    /// In the order the files were found, as soon as the files before
+    /// them have finished
+    Input,
+    /// In the order the files were found, after all of them finished
+    End,
+}
+
+impl FromStr for OutputOrder {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "completion" => Ok(OutputOrder::Completion),
+            "input" => Ok(OutputOrder::Input),
+            "end" => Ok(OutputOrder::End),
+            _ => Err(WaverError::argument_error("Output order must be completion, input or end")),
+        }
+    }
+}
+
+/// The level of a message, from the most to the least important
+/// (`--log-level`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum LogLevel {
+    /// Nothing is printed (`--quiet`)
+    Off,
+    /// The files that failed
+    Error,
+    /// What was noticed about a file without failing it
+    Warn,
+    /// The files that were written
+    #[default]
+    Info,
+    /// Everything else that was done or found (`--verbose`)
+    Debug,
+}
+
+impl LogLevel {
+    /// Returns the name of the level, as it is given and logged.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Off => "off",
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for LogLevel {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "off" => Ok(LogLevel::Off),
+            "error" => Ok(LogLevel::Error),
+            "warn" | "warning" => Ok(LogLevel::Warn),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            _ => Err(WaverError::argument_error("Log level must be off, error, warn, info or debug")),
+        }
+    }
+}
+
+/// The target of the message events, by which a subscriber can route or
+/// filter them apart from the spans of `--trace`.
+pub const TARGET: &str = "waver::output";
+
+/// A message as it is written with `--log-json`.
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    time: String,
+    level: LogLevel,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'a str>,
+    message: &'a str,
+}
+
+/// The rules of the run for printing its messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Logger {
+    level: LogLevel,
+    json: bool,
+    /// The image goes to stdout, so the messages go to stderr
+    image_to_stdout: bool,
+}
+
+impl Logger {
+    /// Creates the logger that prints the messages up to `level`, as JSON
+    /// or as plain lines.
+    pub fn new(level: LogLevel, json: bool, image_to_stdout: bool) -> Self {
+        Self { level, json, image_to_stdout }
+    }
+
+    /// Returns true if messages of the level are printed.
+    pub fn enabled(&self, level: LogLevel) -> bool {
+        level != LogLevel::Off && level <= self.level
+    }
+
+    /// Returns the layer of the subscriber of the run that prints the
+    /// messages up to the level of the logger.
+    pub fn layer<S>(&self) -> Filtered<OutputLayer, Targets, S>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        OutputLayer { json: self.json, image_to_stdout: self.image_to_stdout }.with_filter(self.filter())
+    }
+
+    /// Returns the filter that passes the messages up to the level.
+    fn filter(&self) -> Targets {
+        let level = match self.level {
+            LogLevel::Off => LevelFilter::OFF,
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+        };
+        Targets::new().with_target(TARGET, level)
+    }
+
+    /// Emits a message of the level that belongs to no file.
+    pub fn log(&self, level: LogLevel, message: &str) {
+        if self.enabled(level) {
+            emit(level, None, message, false);
+        }
+    }
+
+    /// Emits a message about a file that failed.
+    pub fn error(&self, message: &str) {
+        self.log(LogLevel::Error, message);
+    }
+
+    /// Emits a message about something noticed without failing.
+    pub fn warn(&self, message: &str) {
+        self.log(LogLevel::Warn, message);
+    }
+
+    /// Emits a message about what was written.
+    pub fn info(&self, message: &str) {
+        self.log(LogLevel::Info, message);
+    }
+
+    /// Emits a message about anything else done or found, for `--verbose`.
+    pub fn debug(&self, message: &str) {
+        self.log(LogLevel::Debug, message);
+    }
+
+    /// Emits a line of the `--progress` at the info level, which is always
+    /// printed to stderr so it stays out of what the run writes to stdout.
+    pub fn progress(&self, message: &str) {
+        if self.enabled(LogLevel::Info) {
+            emit(LogLevel::Info, None, message, true);
+        }
+    }
+}
+
+/// Emits a message as a `tracing` event of its level, with the file it is
+/// about and whether it is a progress line as fields.
+fn emit(level: LogLevel, file: Option<&str>, message: &str, progress: bool) {
+    match level {
+        LogLevel::Off => {}
+        LogLevel::Error => tracing::error!(target: TARGET, file, progress, "{message}"),
+        LogLevel::Warn => tracing::warn!(target: TARGET, file, progress, "{message}"),
+        LogLevel::Info => tracing::info!(target: TARGET, file, progress, "{message}"),
+        LogLevel::Debug => tracing::debug!(target: TARGET, file, progress, "{message}"),
+    }
+}
+
+/// The fields of a message event.
+#[derive(Debug, Default)]
+struct Fields {
+    file: Option<String>,
+    message: String,
+    progress: bool,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "file" => self.file = Some(value.to_string()),
+            "message" => self.message = value.to_string(),
+            _ => {}
+        }
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        if field.name() == "progress" {
+            self.progress = value;
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        // The message of the macros comes as format arguments
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        }
+    }
+}
+
+/// The `tracing` layer that prints the message events of the run: as plain
+/// lines, the errors and warnings to stderr and the rest to stdout, or with
+/// `--log-json` as one JSON object a line on stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputLayer {
+    json: bool,
+    /// The image goes to stdout, so the messages go to stderr
+    image_to_stdout: bool,
+}
+
+impl OutputLayer {
+    /// Returns the stream and the text a message event is printed with, or
+    /// `None` if it is not a message.
+    fn format(&self, event: &Event<'_>) -> Option<(Stream, String)> {
+        let metadata = event.metadata();
+        if metadata.target() != TARGET {
+            return None;
+        }
+        let level = match *metadata.level() {
+            Level::ERROR => LogLevel::Error,
+            Level::WARN => LogLevel::Warn,
+            Level::INFO => LogLevel::Info,
+            _ => LogLevel::Debug,
+        };
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+
+        if self.json {
+            let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+            let line =
+                JsonLine { time: utc_timestamp(seconds), level, file: fields.file.as_deref(), message: &fields.message };
+            return Some((Stream::Stderr, serde_json::to_string(&line).unwrap_or_default()));
+        }
+        let stream = match level {
+            LogLevel::Error | LogLevel::Warn => Stream::Stderr,
+            _ if fields.progress || self.image_to_stdout => Stream::Stderr,
+            _ => Stream::Stdout,
+        };
+        Some((stream, fields.message))
+    }
+}
+
+impl<S: Subscriber> Layer<S> for OutputLayer {
+    fn on_event(&self, event: &Event<'_>, _context: Context<'_, S>) {
+        match self.format(event) {
+            Some((Stream::Stdout, text)) => println!("{text}"),
+            Some((Stream::Stderr, text)) => eprintln!("{text}"),
+            None => {}
+        }
+    }
+}
+
+/// The stream a message is printed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// One message of the output of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub level: LogLevel,
+    /// Path of the file the message is about
+    pub file: Option<String>,
+    pub text: String,
+}
+
+impl Line {
+    /// Emits the message as a `tracing` event, which the `OutputLayer`
+    /// prints.
+    pub fn emit(&self) {
+        emit(self.level, self.file.as_deref(), &self.text, false);
+    }
+}
+
+/// The lines printed for one file, collected while it is processed.
+///
+/// The rules of the `Logger` are applied as the lines are added, so only
+/// the lines that are printed are kept.
+#[derive(Debug)]
+pub struct FileOutput {
+    logger: Logger,
+    /// Path of the file, which the JSON lines name
+    file: String,
+    lines: RefCell<Vec<Line>>,
+}
+
+impl FileOutput {
+    /// Creates the output of the file with the rules of the run.
+    pub fn new(logger: Logger, file: impl Into<String>) -> Self {
+        Self { logger, file: file.into(), lines: RefCell::new(Vec::new()) }
+    }
+
+    /// Adds a message of the level, if it is printed.
+    pub fn log(&self, level: LogLevel, message: &str) {
+        if self.logger.enabled(level) {
+            let line = Line { level, file: Some(self.file.clone()), text: message.to_string() };
+            self.lines.borrow_mut().push(line);
+        }
+    }
+
+    /// Adds a message about the file failing.
+    pub fn error(&self, message: &str) {
+        self.log(LogLevel::Error, message);
+    }
+
+    /// Adds a message about something noticed without failing the file.
+    pub fn warn(&self, message: &str) {
+        self.log(LogLevel::Warn, message);
+    }
+
+    /// Adds a message about what was written for the file.
+    pub fn info(&self, message: &str) {
+        self.log(LogLevel::Info, message);
+    }
+
+    /// Adds a message about anything else done or found, for `--verbose`.
+    pub fn debug(&self, message: &str) {
+        self.log(LogLevel::Debug, message);
+    }
+
+    /// Returns the collected lines.
+    pub fn into_lines(self) -> Vec<Line> {
+        self.lines.into_inner()
+    }
+}
+
+/// The files whose lines wait for the files before them.
+#[derive(Debug, Default)]
+struct Pending {
+    /// The first file whose lines have not been printed yet
+    next: usize,
+    /// The lines of the files after it that are done, by their position
+    done: BTreeMap<usize, Vec<Line>>,
+}
+
+/// Prints the lines of the files of a batch in the chosen order.
+///
+/// Every file must be handed in with `complete` exactly once, also files
+/// that were skipped without any lines, or the files after it wait until
+/// `finish`.
+#[derive(Debug)]
+pub struct OrderedOutput {
+    order: OutputOrder,
+    pending: Mutex<Pending>,
+}
+
+impl OrderedOutput {
+    pub fn new(order: OutputOrder) -> Self {
+        Self { order, pending: Mutex::new(Pending::default()) }
+    }
+
+    /// Hands in the lines of the file at `position` (counted from 0 in the
+    /// order the files were found) and prints what is ready with `print`.
+    ///
+    /// The lines are printed while the output is locked, so the lines of
+    /// two files never mix.
+    pub fn complete(&self, position: usize, lines: Vec<Line>, mut print: impl FnMut(&Line)) {
+        let pending = &mut *self.pending.lock().unwrap();
+        match self.order {
+            OutputOrder::Completion => lines.iter().for_each(print),
+            OutputOrder::End => {
+                pending.done.insert(position, lines);
+            }
+            OutputOrder::Input => {
+                pending.done.insert(position, lines);
+                while let Some(lines) = pending.done.remove(&pending.next) {
+                    lines.iter().for_each(&mut print);
+                    pending.next += 1;
+                }
+            }
+        }
+    }
+
+    /// Prints the lines still waiting, in the order of their files.
+    pub fn finish(&self, mut print: impl FnMut(&Line)) {
+        let mut pending = self.pending.lock().unwrap();
+        for lines in std::mem::take(&mut pending.done).into_values() {
+            lines.iter().for_each(&mut print);
+        }
+    }
+}
@@ -0,0 +1,188 @@
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+
+    use tracing::{Event, Subscriber};
+    use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+    use crate::output::{FileOutput, Line, LogLevel, Logger, OrderedOutput, OutputLayer, OutputOrder, Stream};
+
+    fn lines(text: &str) -> Vec<Line> {
+        vec![Line { level: LogLevel::Info, file: None, text: text.to_string() }]
+    }
+
+    /// Keeps what the `OutputLayer` would print instead of printing it.
+    struct Capture {
+        layer: OutputLayer,
+        printed: Arc<Mutex<Vec<(Stream, String)>>>,
+    }
+
+    impl<S: Subscriber> Layer<S> for Capture {
+        fn on_event(&self, event: &Event<'_>, _context: Context<'_, S>) {
+            self.printed.lock().unwrap().extend(self.layer.format(event));
+        }
+    }
+
+    /// Runs `emit` with the subscriber of the logger and returns what it
+    /// printed.
+    fn print_with(logger: Logger, emit: impl FnOnce()) -> Vec<(Stream, String)> {
+        let printed = Arc::new(Mutex::new(Vec::new()));
+        let layer = OutputLayer { json: logger.json, image_to_stdout: logger.image_to_stdout };
+        let capture = Capture { layer, printed: printed.clone() };
+        let subscriber = tracing_subscriber::registry().with(capture.with_filter(logger.filter()));
+        tracing::subscriber::with_default(subscriber, emit);
+        Arc::try_unwrap(printed).unwrap().into_inner().unwrap()
+    }
+
+    /// Hands in the files in the given order and returns what was printed
+    /// after each of them and at the end.
+    fn run(order: OutputOrder, positions: &[usize]) -> (Vec<Vec<String>>, Vec<String>) {
+        let output = OrderedOutput::new(order);
+        let printed = positions
+            .iter()
+            .map(|&position| {
+                let mut printed = Vec::new();
+                output.complete(position, lines(&format!("file {position}")), |line| printed.push(line.text.clone()));
+                printed
+            })
+            .collect();
+        let mut rest = Vec::new();
+        output.finish(|line| rest.push(line.text.clone()));
+        (printed, rest)
+    }
+
+    #[test]
+    fn test_output_orders() {
+        let (printed, rest) = run(OutputOrder::Completion, &[2, 0, 1]);
+        assert_eq!(printed, [["file 2"], ["file 0"], ["file 1"]]);
+        assert!(rest.is_empty());
+
+        // File 2 waits for files 0 and 1
+        let (printed, rest) = run(OutputOrder::Input, &[2, 0, 1]);
+        assert_eq!(printed, [vec![], vec!["file 0"], vec!["file 1", "file 2"]]);
+        assert!(rest.is_empty());
+
+        let (printed, rest) = run(OutputOrder::End, &[2, 0, 1]);
+        assert!(printed.iter().all(Vec::is_empty));
+        assert_eq!(rest, ["file 0", "file 1", "file 2"]);
+
+        // Files after one that never completed are printed at the end
+        let (printed, rest) = run(OutputOrder::Input, &[0, 2, 3]);
+        assert_eq!(printed, [vec!["file 0"], vec![], vec![]]);
+        assert_eq!(rest, ["file 2", "file 3"]);
+    }
+
+    #[test]
+    fn test_file_output_rules() {
+        let output = FileOutput::new(Logger::new(LogLevel::Info, false, false), "song.mp3");
+        output.info("created");
+        output.warn("noticed");
+        output.error("failed");
+        output.debug("details");
+        let file = Some("song.mp3".to_string());
+        assert_eq!(
+            output.into_lines(),
+            [
+                Line { level: LogLevel::Info, file: file.clone(), text: "created".to_string() },
+                Line { level: LogLevel::Warn, file: file.clone(), text: "noticed".to_string() },
+                Line { level: LogLevel::Error, file, text: "failed".to_string() },
+            ]
+        );
+
+        let quiet = FileOutput::new(Logger::new(LogLevel::Off, false, false), "song.mp3");
+        quiet.info("created");
+        quiet.error("failed");
+        assert!(quiet.into_lines().is_empty());
+
+        let errors = FileOutput::new(Logger::new(LogLevel::Error, false, false), "song.mp3");
+        errors.warn("noticed");
+        errors.error("failed");
+        assert_eq!(errors.into_lines().len(), 1, "Only the errors");
+    }
+
+    #[test]
+    fn test_printed_lines() {
+        let logger = Logger::new(LogLevel::Info, false, false);
+        let printed = print_with(logger, || {
+            let output = FileOutput::new(logger, "song.mp3");
+            output.info("created");
+            output.warn("noticed");
+            output.into_lines().iter().for_each(Line::emit);
+            logger.debug("details");
+            logger.progress("1 of 2 files");
+            tracing::info!(target: "waver::audio", "Not a message");
+        });
+        assert_eq!(
+            printed,
+            [
+                (Stream::Stdout, "created".to_string()),
+                (Stream::Stderr, "noticed".to_string()),
+                (Stream::Stderr, "1 of 2 files".to_string()),
+            ]
+        );
+
+        // With the image on stdout the messages go to stderr
+        let logger = Logger::new(LogLevel::Debug, false, true);
+        let printed = print_with(logger, || {
+            logger.info("created");
+            logger.debug("details");
+        });
+        assert_eq!(printed.len(), 2);
+        assert!(printed.iter().all(|(stream, _)| *stream == Stream::Stderr));
+
+        // The layer filters the events of other emitters by the level too
+        let printed = print_with(Logger::new(LogLevel::Warn, false, false), || {
+            tracing::info!(target: "waver::output", "created");
+            tracing::warn!(target: "waver::output", "noticed");
+        });
+        assert_eq!(printed, [(Stream::Stderr, "noticed".to_string())]);
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let logger = Logger::new(LogLevel::Info, true, false);
+        let printed = print_with(logger, || {
+            let output = FileOutput::new(logger, "song.mp3");
+            output.info("Created song.mp3.png");
+            output.debug("details");
+            output.into_lines().iter().for_each(Line::emit);
+            logger.warn("budget");
+        });
+        assert_eq!(printed.len(), 2);
+        assert!(printed.iter().all(|(stream, _)| *stream == Stream::Stderr), "The JSON lines stay out of stdout");
+
+        let json: serde_json::Value = serde_json::from_str(&printed[0].1).unwrap();
+        assert_eq!(json["level"], "info");
+        assert_eq!(json["file"], "song.mp3");
+        assert_eq!(json["message"], "Created song.mp3.png");
+        assert!(json["time"].as_str().unwrap().ends_with('Z'));
+
+        let json: serde_json::Value = serde_json::from_str(&printed[1].1).unwrap();
+        assert_eq!(json["level"], "warn");
+        assert!(json.get("file").is_none(), "A message of no file has no file");
+    }
+
+    #[test]
+    fn test_log_levels() {
+        assert_eq!(LogLevel::from_str("debug").unwrap(), LogLevel::Debug);
+        assert_eq!(LogLevel::from_str(" Warning").unwrap(), LogLevel::Warn);
+        assert_eq!(LogLevel::from_str("off").unwrap(), LogLevel::Off);
+        assert!(LogLevel::from_str("trace").is_err());
+        assert_eq!(LogLevel::default(), LogLevel::Info);
+
+        let logger = Logger::new(LogLevel::Warn, false, false);
+        assert!(logger.enabled(LogLevel::Error) && logger.enabled(LogLevel::Warn));
+        assert!(!logger.enabled(LogLevel::Info));
+        assert!(!Logger::new(LogLevel::Debug, false, false).enabled(LogLevel::Off), "Off is never a message");
+    }
+
+    #[test]
+    fn test_output_order_from_str() {
+        assert_eq!(OutputOrder::from_str("completion").unwrap(), OutputOrder::Completion);
+        assert_eq!(OutputOrder::from_str(" Input").unwrap(), OutputOrder::Input);
+        assert_eq!(OutputOrder::from_str("end").unwrap(), OutputOrder::End);
+        let result = OutputOrder::from_str("sorted");
+        assert_eq!(result.unwrap_err().to_string(), "Invalid argument: Output order must be completion, input or end");
+    }
+}
@@ -0,0 +1,79 @@
+/// A limit on the packets read from a track (`--max-packets`).
+///
+/// The decode loops read packets until the format reader returns an error,
+/// which is how the end of the stream is found.  A damaged or crafted file
+/// can keep returning packets instead, so every loop counts its packets and
+/// stops with an error once there are far more than the length the track
+/// reports can hold.
+use std::path::Path;
+
+use crate::error::{Result, WaverError};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The fewest frames a packet of a real track holds.  The shortest packets
+/// of the supported codecs are Opus frames of 2.5 ms (120 frames at 48 kHz)
+/// and short Vorbis blocks, so this leaves room below both.
+pub const MIN_PACKET_FRAMES: u64 = 64;
+
+/// How many times more packets than the reported length can hold are read
+/// before giving up, for tracks whose length is a little off.
+pub const PACKET_MARGIN: u64 = 4;
+
+/// The fewest packets that are always allowed, so short tracks with an odd
+/// packet layout or a wrong length are never cut off.
+pub const MIN_PACKET_LIMIT: u64 = 100_000;
+
+/// The packets allowed for a track that reports no length: 24 hours of
+/// 48 kHz audio in the shortest packets.
+pub const UNKNOWN_LENGTH_PACKETS: u64 = 24 * 3600 * 48_000 / MIN_PACKET_FRAMES;
+
+/// Counts the packets of one decode loop against the limit of the track.
+#[derive(Debug, Clone, Copy)]
+pub struct PacketGuard {
+    limit: u64,
+    packets: u64,
+    /// Whether the limit was given rather than derived from the length
+    given: bool,
+}
+
+impl PacketGuard {
+    /// Creates the guard for a track that reports `n_frames` frames, or
+    /// with the `max_packets` limit instead if one is given.
+    pub fn new(n_frames: Option<u64>, max_packets: Option<u64>) -> Self {
+        let limit = match (max_packets, n_frames) {
+            (Some(max_packets), _) => max_packets,
+            (None, Some(frames)) => {
+                (frames / MIN_PACKET_FRAMES).saturating_mul(PACKET_MARGIN).max(MIN_PACKET_LIMIT)
+            }
+            (None, None) => UNKNOWN_LENGTH_PACKETS,
+        };
+        Self { limit, packets: 0, given: max_packets.is_some() }
+    }
+
+    /// Returns the number of packets allowed.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Counts a packet read from `name`, or returns the error once there
+    /// are more than the limit.
+    pub fn count(&mut self, name: &Path) -> Result<()> {
+        self.packets += 1;
+        if self.packets <= self.limit() {
+            return Ok(());
+        }
+        let reason = if self.given {
+            "the --max-packets limit"
+        } else {
+            "far more than its length can hold (a damaged file?), --max-packets raises the limit"
+        };
+        Err(WaverError::generation_error(format!(
+            "Stopped reading '{}' after {} packets, {reason}",
+            name.display(),
+            self.limit()
+        )))
+    }
+}
@@ -0,0 +1,32 @@
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::packet_guard::{PacketGuard, MIN_PACKET_LIMIT, UNKNOWN_LENGTH_PACKETS};
+
+    #[test]
+    fn test_limits() {
+        // An hour at 48 kHz in packets of at least 64 frames, four times over
+        assert_eq!(PacketGuard::new(Some(3600 * 48_000), None).limit(), 10_800_000);
+        assert_eq!(PacketGuard::new(Some(1000), None).limit(), MIN_PACKET_LIMIT, "Short tracks");
+        assert_eq!(PacketGuard::new(None, None).limit(), UNKNOWN_LENGTH_PACKETS);
+        assert_eq!(PacketGuard::new(Some(3600 * 48_000), Some(10)).limit(), 10, "The given limit wins");
+    }
+
+    #[test]
+    fn test_count() {
+        let name = Path::new("loop.mp3");
+        let mut guard = PacketGuard::new(None, Some(3));
+        for _ in 0..3 {
+            guard.count(name).unwrap();
+        }
+        assert_eq!(
+            guard.count(name).unwrap_err().to_string(),
+            "Waveform generation error: Stopped reading 'loop.mp3' after 3 packets, the --max-packets limit"
+        );
+
+        let mut guard = PacketGuard::new(Some(0), None);
+        let error = (0..=MIN_PACKET_LIMIT).try_for_each(|_| guard.count(name)).unwrap_err();
+        assert!(error.to_string().ends_with("(a damaged file?), --max-packets raises the limit"));
+    }
+}
@@ -0,0 +1,488 @@
+/// Cached peak data of decoded audio files (`.peaks` files).
+///
+/// Decoding, especially of MP3, dominates the run time, while rendering the
+/// column peaks is cheap.  With `--peak-cache` the drawn channels of a file
+/// are reduced once to blocks of `BLOCK_FRAMES` frames, each with the
+/// lowest and highest sample and the RMS of both channels, and saved next
+/// to the audio file.  Rendering the same file at another size, range or in
+/// other colors then builds the columns from those blocks without decoding.
+///
+/// The file is a small header followed by the blocks as 16-bit values, all
+/// little endian:
+///
+/// | Bytes | Contents                                           |
+/// |-------|----------------------------------------------------|
+/// | 4     | `WVPK`                                             |
+/// | 1     | Format version                                     |
+/// | 1     | Channel mode the blocks were mixed with            |
+/// | 1     | 1 for stereo blocks, 0 for mono                    |
+/// | 1     | Unused (0)                                         |
+/// | 4     | Sample rate in Hz                                  |
+/// | 4     | Number of channels of the track                    |
+/// | 8     | Number of frames                                   |
+/// | 12    | Per block: left min, max (i16), RMS (u16), right … |
+///
+/// As every block has the same size, the blocks of any stretch of time are
+/// one byte range of the file.  With `--peak-index` a `PeakIndex` lists
+/// those ranges for pages of `PAGE_BLOCKS` blocks, so a streaming player can
+/// fetch just the page it needs with an HTTP range request.
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::audit;
+use crate::config::TimeRange;
+use crate::error::{Result, WaverError};
+use crate::mix::ChannelMode;
+use crate::peaks::{ColumnPeak, Envelope, Peaks};
+use crate::scan::{min_max, sum_squares};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// Number of frames summarized by each block.
+///
+/// About 172 blocks per second at 44.1 kHz, so a cache takes about 2 KB per
+/// second of audio and still covers a 2048 pixel image of 12 seconds.
+pub const BLOCK_FRAMES: u64 = 256;
+
+/// Size of the header in front of the blocks, in bytes.
+pub const HEADER_BYTES: u64 = 24;
+
+/// Size of each block, in bytes.
+pub const BLOCK_BYTES: u64 = 12;
+
+/// Number of blocks in each page of the `PeakIndex`.
+///
+/// A page is 24 KB and about 12 seconds at 44.1 kHz, which is a screen of a
+/// zoomed in waveform in one request.
+pub const PAGE_BLOCKS: u64 = 2048;
+
+/// The 4 magic bytes at the start of every cache file.
+const MAGIC: &[u8; 4] = b"WVPK";
+
+/// Version of the file layout, a cache of another version is rebuilt.
+const VERSION: u8 = 1;
+
+/// The range of no samples, which any sample widens.
+const EMPTY_RANGE: (f32, f32) = (f32::INFINITY, f32::NEG_INFINITY);
+
+/// The lowest and highest sample and the RMS of one channel in a block,
+/// quantized to 16 bits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct ChannelBlock {
+    min: i16,
+    max: i16,
+    rms: u16,
+}
+
+impl ChannelBlock {
+    /// Quantizes the range (clamped to full scale) and RMS of a channel.
+    ///
+    /// An empty range (of a channel that was not pushed) is stored as zero.
+    fn new((min, max): (f32, f32), rms: f32) -> Self {
+        let (min, max) = if min > max { (0.0, 0.0) } else { (min, max) };
+        let sample = |value: f32| (value.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+        Self {
+            min: sample(min),
+            max: sample(max),
+            rms: (rms.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16,
+        }
+    }
+
+    fn min(&self) -> f32 {
+        self.min as f32 / i16::MAX as f32
+    }
+
+    fn max(&self) -> f32 {
+        self.max as f32 / i16::MAX as f32
+    }
+
+    fn rms(&self) -> f32 {
+        self.rms as f32 / u16::MAX as f32
+    }
+}
+
+/// One block of both channels (the right one is unused for mono).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Block {
+    left: ChannelBlock,
+    right: ChannelBlock,
+}
+
+/// The blocks of a whole audio file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeakCache {
+    /// How the channels were mixed into the blocks
+    mode: ChannelMode,
+    /// Whether the blocks have a right channel
+    stereo: bool,
+    /// Sample rate of the track in Hz
+    sample_rate: u32,
+    /// Number of channels of the track
+    channels: u32,
+    /// Number of frames of the track
+    frames: u64,
+    blocks: Vec<Block>,
+}
+
+/// Returns the number stored for a channel mode in the cache header.
+fn mode_id(mode: ChannelMode) -> u8 {
+    match mode {
+        ChannelMode::Stereo => 0,
+        ChannelMode::Mono => 1,
+        ChannelMode::Left => 2,
+        ChannelMode::Right => 3,
+        ChannelMode::Mid => 4,
+        ChannelMode::Side => 5,
+    }
+}
+
+/// Returns an error for a file that is not a (current) peak cache.
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl PeakCache {
+    /// Returns the path of the cache for the given audio file.
+    ///
+    /// The `.peaks` extension is added, so `song.mp3` gets the cache
+    /// `song.mp3.peaks`.
+    pub fn path_for(audio_path: &Path) -> PathBuf {
+        let mut path = audio_path.as_os_str().to_owned();
+        path.push(".peaks");
+        PathBuf::from(path)
+    }
+
+    /// Returns the sample rate of the track in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Returns the number of channels of the track.
+    pub fn channels(&self) -> usize {
+        self.channels as usize
+    }
+
+    /// Builds the column peaks of (a range of) the audio for an image
+    /// `width` pixels wide, along with the number of frames they cover.
+    ///
+    /// Each column gets the blocks its part of the audio covers, so the
+    /// column edges are rounded to whole blocks.  Returns `None` if there
+    /// are fewer blocks than columns, the audio then has to be decoded.
+    pub fn peaks(&self, width: u32, range: Option<TimeRange>) -> Result<Option<(Peaks, u64)>> {
+        let to_frame = |seconds: f64| ((seconds * self.sample_rate as f64).round() as u64).min(self.frames);
+        let (start, end) = match range {
+            Some(range) => (to_frame(range.start), range.end.map_or(self.frames, to_frame)),
+            None => (0, self.frames),
+        };
+        if range.is_some() && start >= end {
+            return Err(WaverError::generation_error(
+                "The start time is past the end of the audio",
+            ));
+        }
+
+        let first = start / BLOCK_FRAMES;
+        let last = end.div_ceil(BLOCK_FRAMES).min(self.blocks.len() as u64);
+        let count = last.saturating_sub(first);
+        if count < width as u64 {
+            return Ok(None);
+        }
+
+        let mut peaks = Peaks::new(self.stereo, width);
+        let column_start = |x: u64| (first + x * count / width as u64) as usize;
+        for x in 0..width as u64 {
+            let blocks = &self.blocks[column_start(x)..column_start(x + 1)];
+            peaks.columns.push(column_peak(blocks));
+        }
+        Ok(Some((peaks, end - start)))
+    }
+
+    /// Reads a cache, failing with `InvalidData` if it is not a peak cache
+    /// of the current version.
+    pub fn read(reader: impl Read) -> io::Result<Self> {
+        let mut reader = BufReader::new(reader);
+        let mut header = [0u8; HEADER_BYTES as usize];
+        reader.read_exact(&mut header)?;
+        if &header[0..4] != MAGIC {
+            return Err(invalid("Not a peak cache"));
+        }
+        if header[4] != VERSION {
+            return Err(invalid("Unsupported peak cache version"));
+        }
+        let mode = [
+            ChannelMode::Stereo,
+            ChannelMode::Mono,
+            ChannelMode::Left,
+            ChannelMode::Right,
+            ChannelMode::Mid,
+            ChannelMode::Side,
+        ]
+        .into_iter()
+        .find(|&mode| mode_id(mode) == header[5])
+        .ok_or_else(|| invalid("Unknown channel mode"))?;
+
+        let u32_at = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap());
+        let frames = u64::from_le_bytes(header[16..24].try_into().unwrap());
+        let mut cache = PeakCache {
+            mode,
+            stereo: header[6] != 0,
+            sample_rate: u32_at(8),
+            channels: u32_at(12),
+            frames,
+            blocks: Vec::with_capacity(frames.div_ceil(BLOCK_FRAMES) as usize),
+        };
+
+        let mut bytes = [0u8; BLOCK_BYTES as usize];
+        for _ in 0..frames.div_ceil(BLOCK_FRAMES) {
+            reader.read_exact(&mut bytes)?;
+            let i16_at = |at: usize| i16::from_le_bytes([bytes[at], bytes[at + 1]]);
+            let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+            let channel = |at: usize| ChannelBlock {
+                min: i16_at(at),
+                max: i16_at(at + 2),
+                rms: u16_at(at + 4),
+            };
+            cache.blocks.push(Block {
+                left: channel(0),
+                right: channel(6),
+            });
+        }
+        Ok(cache)
+    }
+
+    /// Writes the cache.
+    pub fn write(&self, writer: impl Write) -> io::Result<()> {
+        let mut writer = BufWriter::new(writer);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION, mode_id(self.mode), self.stereo as u8, 0])?;
+        writer.write_all(&self.sample_rate.to_le_bytes())?;
+        writer.write_all(&self.channels.to_le_bytes())?;
+        writer.write_all(&self.frames.to_le_bytes())?;
+        for block in &self.blocks {
+            for channel in [block.left, block.right] {
+                writer.write_all(&channel.min.to_le_bytes())?;
+                writer.write_all(&channel.max.to_le_bytes())?;
+                writer.write_all(&channel.rms.to_le_bytes())?;
+            }
+        }
+        writer.flush()
+    }
+
+    /// Loads the cache at `path`.
+    ///
+    /// Returns `None` if there is no usable cache: it is missing, not a
+    /// cache of this version, or was made with another channel mode.
+    pub fn load(path: &Path, mode: ChannelMode) -> Option<Self> {
+        let cache = Self::read(File::open(path).ok()?).ok()?;
+        Some(cache).filter(|cache| cache.mode == mode)
+    }
+
+    /// Saves the cache to the given path.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.write(audit::create(path)?)?;
+        Ok(())
+    }
+
+    /// Returns the index of the pages of this cache, saved as the file
+    /// named `peaks`.
+    pub fn index(&self, peaks: &str) -> PeakIndex {
+        let rate = self.sample_rate.max(1) as f64;
+        let blocks = self.blocks.len() as u64;
+        let pages = (0..blocks.div_ceil(PAGE_BLOCKS))
+            .map(|page| {
+                let first = page * PAGE_BLOCKS;
+                let last = (first + PAGE_BLOCKS).min(blocks);
+                IndexPage {
+                    start: (first * BLOCK_FRAMES) as f64 / rate,
+                    end: (last * BLOCK_FRAMES).min(self.frames) as f64 / rate,
+                    first_byte: HEADER_BYTES + first * BLOCK_BYTES,
+                    last_byte: HEADER_BYTES + last * BLOCK_BYTES - 1,
+                }
+            })
+            .collect();
+        PeakIndex {
+            peaks: peaks.to_string(),
+            version: VERSION,
+            stereo: self.stereo,
+            sample_rate: self.sample_rate,
+            frames: self.frames,
+            header_bytes: HEADER_BYTES,
+            block_frames: BLOCK_FRAMES,
+            block_bytes: BLOCK_BYTES,
+            pages,
+        }
+    }
+}
+
+/// The byte ranges of the pages of a peak cache (`--peak-index`).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeakIndex {
+    /// File name of the peak cache, which is next to the index
+    pub peaks: String,
+    /// Version of the peak cache layout
+    pub version: u8,
+    /// Whether the blocks have a right channel
+    pub stereo: bool,
+    /// Sample rate of the track in Hz
+    pub sample_rate: u32,
+    /// Number of frames of the track
+    pub frames: u64,
+    /// Size of the header in front of the blocks
+    pub header_bytes: u64,
+    /// Frames summarized by each block
+    pub block_frames: u64,
+    /// Size of each block
+    pub block_bytes: u64,
+    /// The pages in time order, each `PAGE_BLOCKS` blocks but the last
+    pub pages: Vec<IndexPage>,
+}
+
+/// The time and bytes of one page of a peak cache.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct IndexPage {
+    /// Start of the page in seconds
+    pub start: f64,
+    /// End of the page in seconds
+    pub end: f64,
+    /// First byte of the page in the cache file
+    pub first_byte: u64,
+    /// Last byte of the page, inclusive like an HTTP range
+    pub last_byte: u64,
+}
+
+impl PeakIndex {
+    /// Returns the path of the index for the given peak cache path.
+    ///
+    /// `.json` is added, so `song.mp3.peaks` gets the index
+    /// `song.mp3.peaks.json`.
+    pub fn path_for(cache_path: &Path) -> PathBuf {
+        let mut path = cache_path.as_os_str().to_owned();
+        path.push(".json");
+        PathBuf::from(path)
+    }
+
+    /// Writes the index as compact JSON (it is mostly one long array).
+    pub fn write(&self, writer: impl Write) -> Result<()> {
+        let mut writer = BufWriter::new(writer);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Saves the index to the given path.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.write(audit::create(path)?)
+    }
+}
+
+/// Combines blocks (at least one) into one column: the range and peak over
+/// all of them and the RMS of their mean square.
+fn column_peak(blocks: &[Block]) -> ColumnPeak {
+    let channel = |pick: fn(&Block) -> ChannelBlock| {
+        let (mut min, mut max) = EMPTY_RANGE;
+        let mut squares = 0.0f32;
+        for block in blocks.iter().map(pick) {
+            min = min.min(block.min());
+            max = max.max(block.max());
+            squares += block.rms() * block.rms();
+        }
+        let rms = (squares / blocks.len() as f32).sqrt();
+        (Envelope { min, max }, rms)
+    };
+    let (left_envelope, left_rms) = channel(|block| block.left);
+    let (right_envelope, right_rms) = channel(|block| block.right);
+    ColumnPeak {
+        left: left_envelope.max.max(-left_envelope.min),
+        right: right_envelope.max.max(-right_envelope.min),
+        left_rms,
+        right_rms,
+        left_envelope,
+        right_envelope,
+    }
+}
+
+/// Reduces streamed samples to the blocks of a `PeakCache`.
+pub struct PeakCacheBuilder {
+    cache: PeakCache,
+    /// Frames in the current block so far
+    filled: u64,
+    /// Range and sum of squares of the current block, left then right
+    ranges: [(f32, f32); 2],
+    squares: [f32; 2],
+}
+
+impl PeakCacheBuilder {
+    /// Creates a builder for the blocks of a track.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - How the channels are mixed before they are pushed
+    /// * `stereo` - Whether a right channel is pushed
+    /// * `sample_rate` - Sample rate of the track in Hz
+    /// * `channels` - Number of channels of the track
+    pub fn new(mode: ChannelMode, stereo: bool, sample_rate: u32, channels: usize) -> Self {
+        Self {
+            cache: PeakCache {
+                mode,
+                stereo,
+                sample_rate,
+                channels: channels as u32,
+                frames: 0,
+                blocks: Vec::new(),
+            },
+            filled: 0,
+            ranges: [EMPTY_RANGE; 2],
+            squares: [0.0; 2],
+        }
+    }
+
+    /// Adds the next frames of the (mixed) channels, `right` is `None` for mono.
+    pub fn push(&mut self, left: &[f32], right: Option<&[f32]>) {
+        let mut pos = 0;
+        while pos < left.len() {
+            let end = pos + ((BLOCK_FRAMES - self.filled) as usize).min(left.len() - pos);
+            for (channel, samples) in [Some(left), right].into_iter().enumerate() {
+                if let Some(samples) = samples {
+                    let run = &samples[pos..end];
+                    let (min, max) = min_max(run);
+                    let range = &mut self.ranges[channel];
+                    *range = (range.0.min(min), range.1.max(max));
+                    self.squares[channel] += sum_squares(run);
+                }
+            }
+            self.filled += (end - pos) as u64;
+            self.cache.frames += (end - pos) as u64;
+            pos = end;
+            if self.filled == BLOCK_FRAMES {
+                self.end_block();
+            }
+        }
+    }
+
+    /// Records the current block and starts the next one.
+    fn end_block(&mut self) {
+        let frames = self.filled.max(1) as f32;
+        let channel = |index: usize| ChannelBlock::new(self.ranges[index], (self.squares[index] / frames).sqrt());
+        let block = Block {
+            left: channel(0),
+            right: channel(1),
+        };
+        self.cache.blocks.push(block);
+        self.filled = 0;
+        self.ranges = [EMPTY_RANGE; 2];
+        self.squares = [0.0; 2];
+    }
+
+    /// Records the last (partial) block and returns the cache.
+    pub fn finish(mut self) -> PeakCache {
+        if self.filled > 0 {
+            self.end_block();
+        }
+        self.cache
+    }
+}
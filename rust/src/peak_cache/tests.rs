@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod tests {
+    use crate::config::TimeRange;
+    use crate::mix::ChannelMode;
+    use crate::peak_cache::{PeakCache, PeakCacheBuilder, PeakIndex, BLOCK_BYTES, BLOCK_FRAMES, HEADER_BYTES, PAGE_BLOCKS};
+    use std::path::Path;
+
+    /// Builds a stereo cache of `blocks` blocks at 1000 Hz where the left
+    /// channel of block `n` is all `n / blocks` and the right is silent.
+    fn ramp_cache(blocks: u64) -> PeakCache {
+        let mut builder = PeakCacheBuilder::new(ChannelMode::Stereo, true, 1000, 2);
+        let right = vec![0.0; BLOCK_FRAMES as usize];
+        for n in 0..blocks {
+            let left = vec![n as f32 / blocks as f32; BLOCK_FRAMES as usize];
+            // Pushed in uneven runs that do not line up with the blocks
+            builder.push(&left[..100], Some(&right[..100]));
+            builder.push(&left[100..], Some(&right[100..]));
+        }
+        builder.finish()
+    }
+
+    #[test]
+    fn test_path_for() {
+        assert_eq!(PeakCache::path_for(Path::new("music/song.mp3")), Path::new("music/song.mp3.peaks"));
+    }
+
+    #[test]
+    fn test_columns_from_blocks() {
+        let cache = ramp_cache(8);
+        assert_eq!(cache.sample_rate(), 1000);
+        assert_eq!(cache.channels(), 2);
+
+        // Two blocks per column, each column gets the louder one
+        let (peaks, frames) = cache.peaks(4, None).unwrap().unwrap();
+        assert_eq!(frames, 8 * BLOCK_FRAMES);
+        assert!(peaks.stereo);
+        let lefts: Vec<f32> = peaks.columns.iter().map(|column| column.left).collect();
+        for (left, expected) in lefts.iter().zip([0.125, 0.375, 0.625, 0.875]) {
+            assert!((left - expected).abs() < 1e-4, "{left} should be {expected}");
+        }
+        assert!(peaks.columns.iter().all(|column| column.right == 0.0));
+
+        // The RMS is that of both blocks and the envelope keeps the sign
+        let column = peaks.columns[1];
+        let rms = ((0.25f32 * 0.25 + 0.375 * 0.375) / 2.0).sqrt();
+        assert!((column.left_rms - rms).abs() < 1e-4);
+        assert!((column.left_envelope.min - 0.25).abs() < 1e-4, "Positive samples only");
+
+        // More columns than blocks needs the decoded audio
+        assert!(cache.peaks(9, None).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_time_range() {
+        // Blocks are 0.256 seconds at 1000 Hz
+        let cache = ramp_cache(8);
+        let range = TimeRange { start: 1.024, end: Some(2.048) };
+        let (peaks, frames) = cache.peaks(2, Some(range)).unwrap().unwrap();
+        assert_eq!(frames, 4 * BLOCK_FRAMES);
+        assert!((peaks.columns[0].left - 0.625).abs() < 1e-4);
+        assert!((peaks.columns[1].left - 0.875).abs() < 1e-4);
+
+        let past_end = TimeRange { start: 10.0, end: None };
+        assert!(cache.peaks(2, Some(past_end)).is_err());
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let cache = ramp_cache(5);
+        let mut bytes = Vec::new();
+        cache.write(&mut bytes).unwrap();
+        assert_eq!(&bytes[..4], b"WVPK");
+        assert_eq!(bytes.len(), 24 + 5 * 12, "A header and 12 bytes per block");
+        assert_eq!(PeakCache::read(bytes.as_slice()).unwrap(), cache);
+
+        // Other files and truncated caches are rejected
+        assert!(PeakCache::read(&b"RIFF and then some more bytes"[..]).is_err());
+        assert!(PeakCache::read(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("song.mp3.peaks");
+        assert!(PeakCache::load(&path, ChannelMode::Stereo).is_none(), "No cache yet");
+
+        let cache = ramp_cache(3);
+        cache.save(&path).unwrap();
+        assert_eq!(PeakCache::load(&path, ChannelMode::Stereo), Some(cache));
+        assert!(PeakCache::load(&path, ChannelMode::Mono).is_none(), "Made for other channels");
+    }
+
+    #[test]
+    fn test_partial_last_block() {
+        let mut builder = PeakCacheBuilder::new(ChannelMode::Mono, false, 1000, 1);
+        builder.push(&vec![-0.5; BLOCK_FRAMES as usize + 10], None);
+        let cache = builder.finish();
+        let (peaks, frames) = cache.peaks(2, None).unwrap().unwrap();
+        assert_eq!(frames, BLOCK_FRAMES + 10);
+        assert!(!peaks.stereo);
+        assert!((peaks.columns[1].left - 0.5).abs() < 1e-4, "The partial block is kept");
+        assert!((peaks.columns[1].left_envelope.max + 0.5).abs() < 1e-4, "Negative samples only");
+    }
+
+    #[test]
+    fn test_index_pages() {
+        let cache = ramp_cache(PAGE_BLOCKS + 3);
+        let index = cache.index("song.mp3.peaks");
+        assert_eq!(index.peaks, "song.mp3.peaks");
+        assert_eq!(index.frames, (PAGE_BLOCKS + 3) * BLOCK_FRAMES);
+        assert_eq!(index.pages.len(), 2);
+
+        let (first, last) = (index.pages[0], index.pages[1]);
+        assert_eq!((first.start, last.end), (0.0, index.frames as f64 / 1000.0));
+        assert_eq!(first.end, last.start, "The pages follow each other");
+        assert_eq!(first.first_byte, HEADER_BYTES);
+        assert_eq!(first.last_byte + 1, last.first_byte);
+
+        // The byte ranges are those of the written blocks
+        let mut bytes = Vec::new();
+        cache.write(&mut bytes).unwrap();
+        assert_eq!(last.last_byte + 1, bytes.len() as u64);
+        assert_eq!(last.last_byte + 1 - last.first_byte, 3 * BLOCK_BYTES);
+    }
+
+    #[test]
+    fn test_index_partial_last_block() {
+        let mut builder = PeakCacheBuilder::new(ChannelMode::Mono, false, 1000, 1);
+        builder.push(&vec![0.5; BLOCK_FRAMES as usize + 10], None);
+        let index = builder.finish().index("clip.wav.peaks");
+        assert_eq!(index.pages.len(), 1);
+        assert!(!index.stereo);
+        assert_eq!(index.pages[0].end, (BLOCK_FRAMES + 10) as f64 / 1000.0, "Ends with the audio");
+        assert_eq!(index.pages[0].last_byte, HEADER_BYTES + 2 * BLOCK_BYTES - 1);
+    }
+
+    #[test]
+    fn test_index_json() {
+        assert_eq!(PeakIndex::path_for(Path::new("music/song.mp3.peaks")), Path::new("music/song.mp3.peaks.json"));
+
+        let mut bytes = Vec::new();
+        ramp_cache(2).index("song.mp3.peaks").write(&mut bytes).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["block_bytes"], BLOCK_BYTES);
+        assert_eq!(json["pages"][0]["first_byte"], HEADER_BYTES);
+        assert_eq!(json["pages"][0]["last_byte"], HEADER_BYTES + 2 * BLOCK_BYTES - 1);
+    }
+}
@@ -0,0 +1,162 @@
+//! Per-column peak data collected while decoding the audio.
+//!
+//! The decoder reduces the audio to one `ColumnPeak` per pixel column and the
+//! renderer draws those, so whole-file adjustments (like normalization) can
+//! be applied before anything is drawn without keeping the samples around.
+
+use sha2::{Digest, Sha256};
+
+use crate::silence::Silence;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The peak and RMS amplitudes of the samples that map to one pixel column.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[non_exhaustive]
+pub struct ColumnPeak {
+    /// Maximum absolute left (or mono) sample, 0.0 to 1.0
+    pub left: f32,
+    /// Maximum absolute right sample, 0.0 to 1.0
+    pub right: f32,
+    /// RMS of the left (or mono) samples (0.0 if not collected)
+    pub left_rms: f32,
+    /// RMS of the right samples (0.0 if not collected)
+    pub right_rms: f32,
+    /// Signed range of the left (or mono) samples (zero if not collected)
+    pub left_envelope: Envelope,
+    /// Signed range of the right samples (zero if not collected)
+    pub right_envelope: Envelope,
+}
+
+/// The lowest and highest (signed) sample of a column, -1.0 to 1.0.
+///
+/// Unlike the peak this keeps the shape of asymmetric audio, like speech
+/// with a DC offset, whose envelope does not center on zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Envelope {
+    /// Lowest sample
+    pub min: f32,
+    /// Highest sample
+    pub max: f32,
+}
+
+impl ColumnPeak {
+    /// Returns the balance from -1.0 (all left) to 1.0 (all right) based on
+    /// the RMS levels.  Silent columns are reported as centered.
+    pub fn balance(&self) -> f32 {
+        let total = self.left_rms + self.right_rms;
+        if total <= 0.0 {
+            return 0.0;
+        }
+        (self.right_rms - self.left_rms) / total
+    }
+}
+
+/// The column peaks of a whole track.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct Peaks {
+    /// Whether the right channel values are used (stereo) or not (mono)
+    pub stereo: bool,
+    /// One entry per pixel column, left to right
+    pub columns: Vec<ColumnPeak>,
+    /// The peak of every column for each channel of the track, when each
+    /// channel is drawn in a lane of its own (empty otherwise)
+    pub lanes: Vec<Vec<f32>>,
+    /// The peak of every second over the drawn channels, for the hover
+    /// preview (empty otherwise)
+    pub seconds: Vec<f32>,
+    /// The silences of the audio, when they are looked for (empty
+    /// otherwise)
+    pub silences: Vec<Silence>,
+}
+
+impl Peaks {
+    /// Creates an empty set of peaks with room for `width` columns.
+    pub fn new(stereo: bool, width: u32) -> Self {
+        Self {
+            stereo,
+            columns: Vec::with_capacity(width as usize),
+            lanes: Vec::new(),
+            seconds: Vec::new(),
+            silences: Vec::new(),
+        }
+    }
+
+    /// Adds an empty lane for each of `channels` channels with room for
+    /// `width` columns.
+    pub fn with_lanes(mut self, channels: usize, width: u32) -> Self {
+        self.lanes = (0..channels).map(|_| Vec::with_capacity(width as usize)).collect();
+        self
+    }
+
+    /// Returns the largest peak over all columns and channels.
+    pub fn max_peak(&self) -> f32 {
+        let lanes = self.lanes.iter().flatten().copied();
+        self.columns
+            .iter()
+            .map(|c| if self.stereo { c.left.max(c.right) } else { c.left })
+            .chain(lanes)
+            .fold(0.0, f32::max)
+    }
+
+    /// Returns the gain that brings the given percentile (0 to 100) of the
+    /// column peaks to full scale, so the louder columns above it clip.
+    ///
+    /// The peak of a column is the larger of its channels (or lanes).
+    /// Silent tracks get a gain of 1.0 since there is nothing to scale.
+    pub fn percentile_gain(&self, percentile: f32) -> f32 {
+        let mut peaks: Vec<f32> = if self.lanes.is_empty() {
+            self.columns.iter().map(|c| if self.stereo { c.left.max(c.right) } else { c.left }).collect()
+        } else {
+            let width = self.lanes.iter().map(Vec::len).max().unwrap_or(0);
+            (0..width).map(|x| self.lanes.iter().filter_map(|lane| lane.get(x)).fold(0.0, |a, &b| f32::max(a, b))).collect()
+        };
+        percentile_peak(&mut peaks, percentile).map_or(1.0, |peak| 1.0 / peak)
+    }
+
+    /// Returns the SHA-256 (hex) of the column peaks, which `waver diff`
+    /// compares to tell whether an image still shows its audio.
+    ///
+    /// The peaks are hashed at 16 bits, which is finer than any image can
+    /// show but leaves out the rounding of the last bits of a float.  The
+    /// right channel only counts for stereo.
+    pub fn hash(&self) -> String {
+        let quantize = |peak: f32| ((peak.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16).to_le_bytes();
+        let mut hasher = Sha256::new();
+        hasher.update([self.stereo as u8]);
+        for column in &self.columns {
+            hasher.update(quantize(column.left));
+            if self.stereo {
+                hasher.update(quantize(column.right));
+            }
+        }
+        hasher.finalize().iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    /// Returns the gain that brings the largest peak to full scale.
+    ///
+    /// Silent tracks get a gain of 1.0 since there is nothing to scale.
+    pub fn normalize_gain(&self) -> f32 {
+        let max = self.max_peak();
+        if max > 0.0 {
+            1.0 / max
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Returns the peak at the percentile (0 to 100, by the nearest rank) of
+/// the peaks, if it is above silence.  The peaks are reordered.
+pub fn percentile_peak(peaks: &mut [f32], percentile: f32) -> Option<f32> {
+    if peaks.is_empty() {
+        return None;
+    }
+    let rank = ((percentile / 100.0 * peaks.len() as f32).ceil() as usize).clamp(1, peaks.len());
+    let (_, peak, _) = peaks.select_nth_unstable_by(rank - 1, f32::total_cmp);
+    Some(*peak).filter(|peak| *peak > 0.0)
+}
@@ -0,0 +1,109 @@
+#[cfg(test)]
+mod tests {
+    use crate::peaks::{ColumnPeak, Peaks};
+
+    fn column(left: f32, right: f32) -> ColumnPeak {
+        ColumnPeak { left, right, ..Default::default() }
+    }
+
+    #[test]
+    fn test_max_peak() {
+        let mut peaks = Peaks::new(true, 4);
+        assert_eq!(peaks.max_peak(), 0.0, "No columns have no peak");
+
+        peaks.columns.extend([column(0.1, 0.2), column(0.4, 0.3), column(0.2, 0.25)]);
+        assert_eq!(peaks.max_peak(), 0.4);
+
+        // Mono ignores the right channel values
+        peaks.stereo = false;
+        peaks.columns.push(column(0.3, 0.9));
+        assert_eq!(peaks.max_peak(), 0.4);
+
+        // Every lane counts, also the ones past the first two channels
+        let mut peaks = peaks.with_lanes(3, 4);
+        peaks.lanes[2].push(0.7);
+        assert_eq!(peaks.max_peak(), 0.7);
+    }
+
+    #[test]
+    fn test_normalize_gain() {
+        let mut peaks = Peaks::new(true, 2);
+        peaks.columns.extend([column(0.25, 0.125), column(0.0, 0.2)]);
+        assert_eq!(peaks.normalize_gain(), 4.0);
+
+        // Silence is left alone
+        let silent = Peaks {
+            stereo: true,
+            columns: vec![column(0.0, 0.0); 3],
+            ..Default::default()
+        };
+        assert_eq!(silent.normalize_gain(), 1.0);
+    }
+
+    #[test]
+    fn test_percentile_gain() {
+        // A single loud clap in an otherwise even recording
+        let mut peaks = Peaks::new(false, 20);
+        peaks.columns.extend((0..19).map(|x| column(0.2 + x as f32 / 1000.0, 0.0)));
+        peaks.columns.push(column(1.0, 0.0));
+        assert_eq!(peaks.percentile_gain(100.0), peaks.normalize_gain());
+        assert_eq!(peaks.percentile_gain(95.0), 1.0 / 0.218, "The clap is clipped");
+        assert_eq!(peaks.percentile_gain(50.0), 1.0 / 0.209);
+
+        // The louder channel of each column counts
+        let mut stereo = Peaks::new(true, 2);
+        stereo.columns.extend([column(0.1, 0.5), column(0.25, 0.2)]);
+        assert_eq!(stereo.percentile_gain(50.0), 4.0);
+
+        // As do all the lanes
+        let mut lanes = Peaks::new(true, 2).with_lanes(3, 2);
+        lanes.lanes[0].extend([0.1, 0.1]);
+        lanes.lanes[2].extend([0.5, 0.25]);
+        assert_eq!(lanes.percentile_gain(50.0), 4.0);
+
+        // Silence is left alone, even when it is only the percentile
+        let mut quiet = Peaks::new(false, 4);
+        quiet.columns.extend([column(0.0, 0.0), column(0.0, 0.0), column(0.0, 0.0), column(0.5, 0.0)]);
+        assert_eq!(quiet.percentile_gain(50.0), 1.0);
+        assert_eq!(Peaks::new(false, 0).percentile_gain(95.0), 1.0);
+    }
+
+    #[test]
+    fn test_balance() {
+        let centered = ColumnPeak { left_rms: 0.3, right_rms: 0.3, ..Default::default() };
+        assert_eq!(centered.balance(), 0.0);
+
+        let hard_left = ColumnPeak { left_rms: 0.5, right_rms: 0.0, ..Default::default() };
+        assert_eq!(hard_left.balance(), -1.0);
+
+        let hard_right = ColumnPeak { left_rms: 0.0, right_rms: 0.5, ..Default::default() };
+        assert_eq!(hard_right.balance(), 1.0);
+
+        let leaning_right = ColumnPeak { left_rms: 0.25, right_rms: 0.75, ..Default::default() };
+        assert_eq!(leaning_right.balance(), 0.5);
+
+        assert_eq!(ColumnPeak::default().balance(), 0.0, "Silence is centered");
+    }
+
+    #[test]
+    fn test_hash() {
+        let mut peaks = Peaks::new(true, 2);
+        peaks.columns.extend([column(0.1, 0.2), column(0.4, 0.3)]);
+        let hash = peaks.hash();
+        assert_eq!(hash.len(), 64);
+
+        let mut same = peaks.clone();
+        same.columns[0].left_rms = 0.05;
+        same.columns[1].left += 1e-7;
+        assert_eq!(same.hash(), hash, "Only the peaks count, at 16 bits");
+
+        let mut other = peaks.clone();
+        other.columns[1].right = 0.35;
+        assert_ne!(other.hash(), hash);
+        other.stereo = false;
+        let mut mono = peaks.clone();
+        mono.stereo = false;
+        assert_eq!(other.hash(), mono.hash(), "Mono has no right channel");
+        assert_ne!(mono.hash(), hash);
+    }
+}
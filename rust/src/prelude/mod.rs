@@ -0,0 +1,61 @@
+/// The stable API of the library, for `use waver::prelude::*`.
+///
+/// The build scripts of the players generate their waveforms through the
+/// library, and should not break each time waver gains an option.  What is
+/// in the prelude only changes with the major version: an item is not
+/// removed or renamed, a function keeps its signature, and an enum keeps
+/// its variants.  New builder methods may come in any release, as the
+/// defaults they keep draw the same images.
+///
+/// ```no_run
+/// use waver::prelude::*;
+///
+/// let config = WaveformConfig::builder()
+///     .width(1024)
+///     .height(64)
+///     .left_color(Rgba::rgb(0x00, 0x99, 0xff))
+///     .scale(AmplitudeScale::Db)
+///     .existing_output(ExistingOutput::IfNewer)
+///     .build()?;
+/// if generate_waveform("song.flac", "song.flac.png", &config)? == Outcome::Created {
+///     println!("Created song.flac.png");
+/// }
+/// # Ok::<(), WaverError>(())
+/// ```
+///
+/// The types the builder takes from the options of the binary (`Corner`,
+/// `ImageFormat`, `AutoGain` and the others) and `RunId` are promised here
+/// deliberately, like the rest: their names, variants, constructors and
+/// parsing from strings.  That they are also clap value enums is not part
+/// of the promise.
+///
+/// A player that draws the waveform itself takes the column peaks from
+/// `generate_peaks` (`Peaks`, with their `ColumnPeak`, `Envelope` and
+/// `Silence` entries) and the options they would be drawn with from
+/// `WaveformConfig::render_options` (`RenderOptions` and its
+/// `AmplitudeMap`).  Their fields gain a new entry with nearly every drawing
+/// option, so these structs are `#[non_exhaustive]`: the fields that are
+/// there are promised and can be read, but the structs are only made by
+/// the library, and new fields may come in any release.
+///
+/// The other public modules are the library as the `waver` binary uses it;
+/// they may change in any release.  The modules that only serve the binary
+/// (its options, reports, logs and terminal output) are hidden from the
+/// documentation.
+pub use crate::audio::{generate_peaks, generate_segments, generate_stems, generate_waveform, Outcome};
+pub use crate::cli::{AutoGain, Corner, ImageFormat, MonoStyle, Overlap, RenderMode, WaveLayout};
+pub use crate::color::Rgba;
+pub use crate::config::{ExistingOutput, WaveformConfig, WaveformConfigBuilder};
+pub use crate::error::{Result, WaverError};
+pub use crate::mix::ChannelMode;
+pub use crate::peaks::{ColumnPeak, Envelope, Peaks};
+pub use crate::render::RenderOptions;
+pub use crate::scale::{AmplitudeMap, AmplitudeScale};
+pub use crate::silence::Silence;
+pub use crate::spectrum::Colormap;
+pub use crate::summary::RunId;
+pub use crate::video::VideoFormat;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::prelude::*;
+
+    // Names every item of the prelude with the signature it is promised
+    // with, so taking one out or changing it fails here first
+    #[test]
+    fn test_prelude_items() {
+        let _ = |input: &Path, output: &Path, config: &WaveformConfig| -> Result<Outcome> {
+            generate_waveform(input, output, config)
+        };
+        let _ = |input: &Path, config: &WaveformConfig| -> Result<Vec<(String, Outcome)>> {
+            generate_segments(input, config)
+        };
+        let _ = |stems: &[&Path], output: &Path, config: &WaveformConfig| -> Result<Outcome> {
+            generate_stems(stems, output, config)
+        };
+        let _ = |input: &Path, config: &WaveformConfig| -> Result<Peaks> { generate_peaks(input, config) };
+        let _ = |peaks: &Peaks| -> (bool, f32, f32, Option<f64>) {
+            let column: Option<&ColumnPeak> = peaks.columns.first();
+            let envelope: Envelope = column.map(|c| c.left_envelope).unwrap_or_default();
+            let silence: Option<&Silence> = peaks.silences.first();
+            (peaks.stereo, column.map_or(0.0, |c| c.left.max(c.right)), envelope.min, silence.map(|s| s.end - s.start))
+        };
+
+        let builder: WaveformConfigBuilder = WaveformConfig::builder()
+            .mode(RenderMode::Waveform)
+            .left_color(Rgba::rgb(0x00, 0x99, 0xff))
+            .right_color(Rgba::new(0x99, 0xff, 0x00, 0xff))
+            .scale(AmplitudeScale::Db)
+            .channels(ChannelMode::Stereo)
+            .layout(WaveLayout::Combined)
+            .mono_style(MonoStyle::Mirrored)
+            .overlap(Overlap::Mark)
+            .auto_gain(Some(AutoGain::new(95.0).unwrap()))
+            .duration_badge(Some(Corner::BottomRight))
+            .format(ImageFormat::Png)
+            .colormap(Colormap::Magma)
+            .video(None::<VideoFormat>)
+            .run_id(Some("nightly-42".parse::<RunId>().unwrap()))
+            .existing_output(ExistingOutput::IfNewer);
+        let config = builder.build().unwrap();
+        let options: RenderOptions = config.render_options();
+        let map: AmplitudeMap = options.map;
+        assert_eq!(map.scale, AmplitudeScale::Db);
+        assert!(!options.normalize && options.auto_gain.is_some());
+
+        let error: WaverError = WaveformConfig::builder().width(0).build().unwrap_err();
+        assert!(matches!(error, WaverError::ArgumentError(_)));
+        assert_ne!(Outcome::Created, Outcome::Skipped);
+    }
+}
@@ -0,0 +1,113 @@
+/// Per-second peaks for hover previews on a seekbar.
+///
+/// With `--preview` the peak of every second of the audio is collected in
+/// the same decode as the image and written as a small JSON file next to
+/// it, so a player can show a mini waveform around the hovered position
+/// without loading the image or the audio.
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::audit;
+use crate::error::Result;
+use crate::scan::peak_abs;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The contents of a preview file.
+#[derive(Debug, Clone, Serialize)]
+pub struct Preview {
+    /// Path of the audio file the preview was made from
+    pub source: String,
+    /// Seconds of audio per peak
+    pub seconds_per_peak: u32,
+    /// The peak of every second over the drawn channels, scaled like the
+    /// image and quantized to 0 (silent) to 255 (full height)
+    pub peaks: Vec<u8>,
+}
+
+impl Preview {
+    /// Returns the path of the preview for the given image path.
+    ///
+    /// The image extension is replaced with `.preview.json`, so
+    /// `song.mp3.png` gets the preview `song.mp3.preview.json`.
+    pub fn path_for(image_path: &Path) -> PathBuf {
+        image_path.with_extension("preview.json")
+    }
+
+    /// Quantizes peaks that are already scaled to 0.0..=1.0 of the height.
+    pub fn quantize(peaks: impl IntoIterator<Item = f32>) -> Vec<u8> {
+        peaks
+            .into_iter()
+            .map(|peak| (peak.clamp(0.0, 1.0) * u8::MAX as f32).round() as u8)
+            .collect()
+    }
+
+    /// Writes the preview as compact JSON (it is mostly one long array).
+    pub fn write(&self, writer: impl Write) -> Result<()> {
+        let mut writer = BufWriter::new(writer);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Saves the preview to the given path.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.write(audit::create(path)?)
+    }
+}
+
+/// Collects the peak of every second from streamed samples.
+#[derive(Debug, Clone)]
+pub struct SecondPeaks {
+    /// Frames per second
+    rate: u64,
+    /// Frames of the current second so far
+    filled: u64,
+    /// Peak of the current second
+    current: f32,
+    peaks: Vec<f32>,
+}
+
+impl SecondPeaks {
+    /// Creates the collector for audio at `sample_rate` Hz.
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            rate: sample_rate.max(1) as u64,
+            filled: 0,
+            current: 0.0,
+            peaks: Vec::new(),
+        }
+    }
+
+    /// Adds the next frames of the drawn channels, `right` is `None` for mono.
+    pub fn push(&mut self, left: &[f32], right: Option<&[f32]>) {
+        let mut pos = 0;
+        while pos < left.len() {
+            let end = pos + ((self.rate - self.filled) as usize).min(left.len() - pos);
+            self.current = self.current.max(peak_abs(&left[pos..end]));
+            if let Some(right) = right {
+                self.current = self.current.max(peak_abs(&right[pos..end]));
+            }
+            self.filled += (end - pos) as u64;
+            pos = end;
+            if self.filled == self.rate {
+                self.peaks.push(self.current);
+                self.filled = 0;
+                self.current = 0.0;
+            }
+        }
+    }
+
+    /// Returns the peaks, including that of the last partial second.
+    pub fn finish(mut self) -> Vec<f32> {
+        if self.filled > 0 {
+            self.peaks.push(self.current);
+        }
+        self.peaks
+    }
+}
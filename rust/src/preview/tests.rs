@@ -0,0 +1,42 @@
+#[cfg(test)]
+mod tests {
+    use crate::preview::{Preview, SecondPeaks};
+    use std::path::Path;
+
+    #[test]
+    fn test_preview_path() {
+        assert_eq!(Preview::path_for(Path::new("a/song.mp3.png")), Path::new("a/song.mp3.preview.json"));
+        assert_eq!(Preview::path_for(Path::new("wave")), Path::new("wave.preview.json"));
+    }
+
+    #[test]
+    fn test_second_peaks() {
+        // 2.5 seconds at 4 Hz, pushed in runs that cross the seconds
+        let left = [0.1, 0.2, -0.3, 0.1, 0.0, 0.5, 0.0, 0.0, 0.2, -0.9];
+        let right = [0.0, 0.0, 0.0, 0.0, -0.7, 0.0, 0.0, 0.0, 0.0, 0.0];
+        let mut seconds = SecondPeaks::new(4);
+        seconds.push(&left[..3], Some(&right[..3]));
+        seconds.push(&left[3..], Some(&right[3..]));
+        assert_eq!(seconds.finish(), [0.3, 0.7, 0.9]);
+
+        // Mono ignores the missing right channel
+        let mut seconds = SecondPeaks::new(4);
+        seconds.push(&left, None);
+        assert_eq!(seconds.finish(), [0.3, 0.5, 0.9]);
+    }
+
+    #[test]
+    fn test_preview_json() {
+        let preview = Preview {
+            source: "song.mp3".to_string(),
+            seconds_per_peak: 1,
+            peaks: Preview::quantize([0.0, 0.5, 1.0, 2.0]),
+        };
+        let mut buffer = Vec::new();
+        preview.write(&mut buffer).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "{\"source\":\"song.mp3\",\"seconds_per_peak\":1,\"peaks\":[0,128,255,255]}\n"
+        );
+    }
+}
@@ -0,0 +1,79 @@
+/// Batch progress reporting with an ETA weighted by file size.
+///
+/// Counting finished files gives a useless ETA when a few feature length
+/// recordings dominate a batch of short tracks, so each file is weighted by
+/// its size in bytes.  The size is known without decoding anything and, for
+/// a library in a handful of formats, is roughly proportional to the
+/// duration and so to the decode time.
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::audio::format_duration;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// Returns the weight of a file for the progress estimate.
+///
+/// Files whose size can not be read count as one byte so they still count.
+pub fn file_weight(path: &Path) -> u64 {
+    path.metadata().map(|m| m.len()).unwrap_or(0).max(1)
+}
+
+/// Estimates the time left from the time spent on the work done so far.
+///
+/// Returns `None` until some work is done, as there is nothing to base the
+/// estimate on.
+pub fn estimate_remaining(elapsed: Duration, done_weight: u64, total_weight: u64) -> Option<Duration> {
+    if done_weight == 0 {
+        return None;
+    }
+    let remaining = total_weight.saturating_sub(done_weight);
+    Some(elapsed.mul_f64(remaining as f64 / done_weight as f64))
+}
+
+/// Tracks the progress of a batch of files processed in parallel.
+pub struct Progress {
+    total_files: usize,
+    total_weight: u64,
+    start: Instant,
+    done: Mutex<(usize, u64)>,
+}
+
+impl Progress {
+    /// Starts tracking a batch of files with the given weights.
+    pub fn new(weights: impl IntoIterator<Item = u64>) -> Self {
+        let (total_files, total_weight) = weights
+            .into_iter()
+            .fold((0, 0), |(files, total), weight| (files + 1, total + weight));
+        Self {
+            total_files,
+            total_weight,
+            start: Instant::now(),
+            done: Mutex::new((0, 0)),
+        }
+    }
+
+    /// Records a finished file and returns the progress line to report.
+    pub fn complete(&self, weight: u64, path: &Path) -> String {
+        let (files, done_weight) = {
+            let mut done = self.done.lock().unwrap();
+            done.0 += 1;
+            done.1 += weight;
+            *done
+        };
+
+        let percent = 100.0 * done_weight as f64 / self.total_weight.max(1) as f64;
+        let eta = estimate_remaining(self.start.elapsed(), done_weight, self.total_weight)
+            .map(|left| format_duration(left.as_secs_f64()))
+            .unwrap_or_else(|| "-".to_string());
+        let width = self.total_files.to_string().len();
+        format!(
+            "[{files:>width$}/{}] {percent:5.1}% ETA {eta} {}",
+            self.total_files,
+            path.display()
+        )
+    }
+}
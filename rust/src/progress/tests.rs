@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod tests {
+    use crate::progress::{estimate_remaining, file_weight, Progress};
+    use std::io::Write;
+    use std::path::Path;
+    use std::time::Duration;
+
+    #[test]
+    fn test_estimate_remaining() {
+        let elapsed = Duration::from_secs(10);
+        assert_eq!(estimate_remaining(elapsed, 0, 100), None);
+        assert_eq!(estimate_remaining(elapsed, 100, 100), Some(Duration::ZERO));
+        assert_eq!(estimate_remaining(elapsed, 25, 100), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_estimate_weighted_by_size() {
+        // Nine small files done out of ten, but the big one is 90% of the work
+        let elapsed = Duration::from_secs(9);
+        let eta = estimate_remaining(elapsed, 9, 9 + 81).unwrap();
+        assert_eq!(eta, Duration::from_secs(81));
+    }
+
+    #[test]
+    fn test_file_weight() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&[0; 1234]).unwrap();
+        assert_eq!(file_weight(file.path()), 1234);
+        assert_eq!(file_weight(Path::new("/nonexistent/file.mp3")), 1);
+    }
+
+    #[test]
+    fn test_progress_lines() {
+        let progress = Progress::new([30, 10, 60]);
+        let first = progress.complete(30, Path::new("a.mp3"));
+        assert!(first.starts_with("[1/3]  30.0% ETA "), "{first}");
+        assert!(first.ends_with(" a.mp3"), "{first}");
+
+        progress.complete(10, Path::new("b.mp3"));
+        let last = progress.complete(60, Path::new("c.mp3"));
+        assert!(last.starts_with("[3/3] 100.0% ETA 0:00 c.mp3"), "{last}");
+    }
+}
@@ -0,0 +1,218 @@
+/// A "needs attention" score of the audio for QC triage (`--qc`).
+///
+/// Checking a library of thousands of files by ear starts with the files
+/// most likely to have a problem.  The decoded samples are scanned for
+/// clipping, long silences, a DC offset and an imbalance between the left
+/// and right channel, and the four are combined into one score from 0
+/// (nothing noticed) to 100, so the run report can be sorted by it.
+///
+/// Each measure is turned into a factor from 0.0 to 1.0 that saturates at a
+/// level that clearly needs a listen (like `CLIPPED_SAMPLES_FULL`), and the
+/// score is the weighted mean of the factors with the `QualityWeights`.
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::error::{Result, WaverError};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// Samples at or above this level (about -0.01 dBFS) count as clipped.
+pub const CLIP_LEVEL: f32 = 0.999;
+
+/// Frames with all channels below this level (-60 dBFS) are silent.
+pub const SILENCE_LEVEL: f32 = 0.001;
+
+/// Silences shorter than this many seconds (pauses, fades) are not counted.
+pub const MIN_SILENCE_SECONDS: f64 = 2.0;
+
+/// Clipped samples that give the full clipping factor.
+pub const CLIPPED_SAMPLES_FULL: f64 = 100.0;
+
+/// Share of the audio in long silences that gives the full silence factor.
+pub const SILENT_SHARE_FULL: f64 = 0.25;
+
+/// DC offset (as a share of full scale) that gives the full DC factor.
+pub const DC_OFFSET_FULL: f64 = 0.02;
+
+/// Level difference of the channels in dB that gives the full imbalance
+/// factor.
+pub const IMBALANCE_DB_FULL: f64 = 6.0;
+
+/// Largest imbalance reported, for a channel that is silent throughout.
+pub const MAX_IMBALANCE_DB: f64 = 96.0;
+
+/// How much each measure counts in the score (`--qc-weights`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityWeights {
+    pub clipping: f64,
+    pub silence: f64,
+    pub dc_offset: f64,
+    pub imbalance: f64,
+}
+
+impl Default for QualityWeights {
+    fn default() -> Self {
+        Self { clipping: 1.0, silence: 1.0, dc_offset: 1.0, imbalance: 1.0 }
+    }
+}
+
+impl FromStr for QualityWeights {
+    type Err = WaverError;
+
+    /// Parses comma-separated `measure=weight` pairs, like `clipping=2,dc=0`.
+    ///
+    /// The measures are `clipping`, `silence`, `dc` and `imbalance`, and
+    /// those not given keep their weight of 1.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut weights = QualityWeights::default();
+        for pair in s.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair.split_once('=').ok_or_else(|| {
+                WaverError::argument_error("Quality weights must be measure=weight pairs")
+            })?;
+            let weight = match name.trim().to_lowercase().as_str() {
+                "clipping" => &mut weights.clipping,
+                "silence" => &mut weights.silence,
+                "dc" => &mut weights.dc_offset,
+                "imbalance" => &mut weights.imbalance,
+                _ => {
+                    return Err(WaverError::argument_error(
+                        "Quality measures must be clipping, silence, dc or imbalance",
+                    ))
+                }
+            };
+            *weight = value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|weight| weight.is_finite() && *weight >= 0.0)
+                .ok_or_else(|| WaverError::argument_error("Quality weights must be numbers of 0 or more"))?;
+        }
+        if weights.total() <= 0.0 {
+            return Err(WaverError::argument_error("At least one quality weight must be above 0"));
+        }
+        Ok(weights)
+    }
+}
+
+impl QualityWeights {
+    fn total(&self) -> f64 {
+        self.clipping + self.silence + self.dc_offset + self.imbalance
+    }
+}
+
+/// The quality measures of one audio file and its score.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Quality {
+    /// How much the file needs attention, from 0 to 100
+    pub score: f64,
+    /// Samples at full scale (of all channels)
+    pub clipped_samples: u64,
+    /// Seconds in silences of at least `MIN_SILENCE_SECONDS`
+    pub silence_seconds: f64,
+    /// The longest of those silences, in seconds
+    pub longest_silence_seconds: f64,
+    /// The largest mean of a channel, as a share of full scale
+    pub dc_offset: f64,
+    /// Level difference of the first two channels in dB (0 for mono)
+    pub imbalance_db: f64,
+}
+
+/// Collects the quality measures over the decoded packets of a file.
+#[derive(Debug, Clone)]
+pub struct QualityScanner {
+    sample_rate: u32,
+    frames: u64,
+    clipped: u64,
+    /// Sum and sum of squares of every channel
+    sums: Vec<f64>,
+    squares: Vec<f64>,
+    /// Frames of the silence the scan is in
+    silent_run: u64,
+    silent_frames: u64,
+    longest_silence: u64,
+}
+
+impl QualityScanner {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            frames: 0,
+            clipped: 0,
+            sums: Vec::new(),
+            squares: Vec::new(),
+            silent_run: 0,
+            silent_frames: 0,
+            longest_silence: 0,
+        }
+    }
+
+    /// Adds the frames of one decoded packet, one slice per channel.
+    pub fn push(&mut self, planes: &[&[f32]]) {
+        let frames = planes.iter().map(|plane| plane.len()).min().unwrap_or(0);
+        if self.sums.len() < planes.len() {
+            self.sums.resize(planes.len(), 0.0);
+            self.squares.resize(planes.len(), 0.0);
+        }
+        for (channel, plane) in planes.iter().enumerate() {
+            let plane = &plane[..frames];
+            self.clipped += plane.iter().filter(|sample| sample.abs() >= CLIP_LEVEL).count() as u64;
+            self.sums[channel] += plane.iter().map(|&sample| sample as f64).sum::<f64>();
+            self.squares[channel] += plane.iter().map(|&sample| (sample as f64).powi(2)).sum::<f64>();
+        }
+        for frame in 0..frames {
+            if planes.iter().all(|plane| plane[frame].abs() < SILENCE_LEVEL) {
+                self.silent_run += 1;
+            } else {
+                self.end_silence();
+            }
+        }
+        self.frames += frames as u64;
+    }
+
+    /// Counts the silence the scan is in, if it is long enough.
+    fn end_silence(&mut self) {
+        if self.silent_run as f64 >= MIN_SILENCE_SECONDS * self.sample_rate as f64 {
+            self.silent_frames += self.silent_run;
+            self.longest_silence = self.longest_silence.max(self.silent_run);
+        }
+        self.silent_run = 0;
+    }
+
+    /// Returns the measures of the whole file, scored with the weights.
+    pub fn finish(mut self, weights: &QualityWeights) -> Quality {
+        self.end_silence();
+        let frames = self.frames.max(1) as f64;
+        let seconds = |frames: u64| if self.sample_rate == 0 { 0.0 } else { frames as f64 / self.sample_rate as f64 };
+
+        let dc_offset = self.sums.iter().map(|sum| (sum / frames).abs()).fold(0.0, f64::max);
+        let imbalance_db = match self.squares[..] {
+            [left, right, ..] if left > 0.0 || right > 0.0 => {
+                // A silent channel is as far apart as it gets
+                let floor = frames * 10f64.powf(-MAX_IMBALANCE_DB / 10.0);
+                (10.0 * (left.max(floor) / right.max(floor)).log10()).abs().min(MAX_IMBALANCE_DB)
+            }
+            _ => 0.0,
+        };
+
+        let factors = [
+            (weights.clipping, self.clipped as f64 / CLIPPED_SAMPLES_FULL),
+            (weights.silence, self.silent_frames as f64 / frames / SILENT_SHARE_FULL),
+            (weights.dc_offset, dc_offset / DC_OFFSET_FULL),
+            (weights.imbalance, imbalance_db / IMBALANCE_DB_FULL),
+        ];
+        let weighted: f64 = factors.iter().map(|(weight, factor)| weight * factor.min(1.0)).sum();
+        let score = (weighted / weights.total() * 1000.0).round() / 10.0;
+
+        Quality {
+            score,
+            clipped_samples: self.clipped,
+            silence_seconds: seconds(self.silent_frames),
+            longest_silence_seconds: seconds(self.longest_silence),
+            dc_offset: (dc_offset * 10_000.0).round() / 10_000.0,
+            imbalance_db: (imbalance_db * 100.0).round() / 100.0,
+        }
+    }
+}
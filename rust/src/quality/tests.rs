@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::quality::{QualityScanner, QualityWeights};
+
+    /// Scans the channels as one packet with a sample rate of 10 Hz, so two
+    /// seconds are 20 frames.
+    fn scan(planes: &[&[f32]], weights: &QualityWeights) -> crate::quality::Quality {
+        let mut scanner = QualityScanner::new(10);
+        scanner.push(planes);
+        scanner.finish(weights)
+    }
+
+    #[test]
+    fn test_clean_audio_scores_zero() {
+        let tone: Vec<f32> = (0..100).map(|i| if i % 2 == 0 { 0.5 } else { -0.5 }).collect();
+        let quality = scan(&[&tone, &tone], &QualityWeights::default());
+        assert_eq!(quality.score, 0.0);
+        assert_eq!(quality.clipped_samples, 0);
+        assert_eq!(quality.silence_seconds, 0.0);
+        assert_eq!(quality.dc_offset, 0.0);
+        assert_eq!(quality.imbalance_db, 0.0);
+    }
+
+    #[test]
+    fn test_measures() {
+        // 30 silent frames (3 seconds) and a 1 frame pause, the rest loud
+        let mut left: Vec<f32> = vec![0.5; 100];
+        left[10..40].fill(0.0);
+        left[60] = 0.0;
+        left[90..].fill(1.0);
+        let right: Vec<f32> = left.iter().map(|sample| sample / 2.0).collect();
+
+        let quality = scan(&[&left, &right], &QualityWeights::default());
+        assert_eq!(quality.clipped_samples, 10, "Only the left channel reaches full scale");
+        assert_eq!(quality.silence_seconds, 3.0, "The short pause is not counted");
+        assert_eq!(quality.longest_silence_seconds, 3.0);
+        assert_eq!(quality.dc_offset, 0.395, "All samples are positive");
+        assert_eq!(quality.imbalance_db, 6.02, "Half the level is 6 dB down");
+
+        // A tenth of the clipping factor, the others saturated
+        assert_eq!(quality.score, 77.5);
+    }
+
+    #[test]
+    fn test_silence_across_packets() {
+        let mut scanner = QualityScanner::new(10);
+        let silence = [0.0f32; 15];
+        scanner.push(&[&silence]);
+        scanner.push(&[&silence]);
+        scanner.push(&[&[0.5f32; 10]]);
+        let quality = scanner.finish(&QualityWeights::default());
+        assert_eq!(quality.silence_seconds, 3.0);
+        assert_eq!(quality.imbalance_db, 0.0, "Mono has no imbalance");
+    }
+
+    #[test]
+    fn test_silent_channel() {
+        let quality = scan(&[&[0.5f32; 100], &[0.0f32; 100]], &QualityWeights::default());
+        assert_eq!(quality.imbalance_db, 89.98, "The silent channel counts as -96 dBFS, the other is at -6");
+    }
+
+    #[test]
+    fn test_weights() {
+        // Without a DC offset, with the first 10 frames at full scale
+        let left: Vec<f32> = (0..100)
+            .map(|i| if i % 2 == 0 { 1.0 } else { -1.0 } * if i < 10 { 1.0 } else { 0.5 })
+            .collect();
+        let right: Vec<f32> = left.iter().map(|sample| -sample).collect();
+
+        // Only the clipping counts, and 10 clipped samples of each channel
+        // are a fifth of the full factor
+        let weights = QualityWeights::from_str("silence=0, dc=0,imbalance=0").unwrap();
+        assert_eq!(scan(&[&left, &right], &weights).score, 20.0);
+        let weights = QualityWeights::from_str("clipping=3").unwrap();
+        assert_eq!(scan(&[&left, &right], &weights).score, 10.0);
+    }
+
+    #[test]
+    fn test_weights_from_str() {
+        let weights = QualityWeights::from_str(" Clipping=2 ,dc=0.5").unwrap();
+        assert_eq!(weights.clipping, 2.0);
+        assert_eq!(weights.dc_offset, 0.5);
+        assert_eq!(weights.silence, 1.0);
+        assert_eq!(QualityWeights::from_str("").unwrap(), QualityWeights::default());
+
+        let error = |s: &str| QualityWeights::from_str(s).unwrap_err().to_string();
+        assert_eq!(error("clipping"), "Invalid argument: Quality weights must be measure=weight pairs");
+        assert_eq!(error("loudness=1"), "Invalid argument: Quality measures must be clipping, silence, dc or imbalance");
+        assert_eq!(error("dc=-1"), "Invalid argument: Quality weights must be numbers of 0 or more");
+        assert_eq!(error("dc=nan"), "Invalid argument: Quality weights must be numbers of 0 or more");
+        assert_eq!(
+            error("clipping=0,silence=0,dc=0,imbalance=0"),
+            "Invalid argument: At least one quality weight must be above 0"
+        );
+    }
+}
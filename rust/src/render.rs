@@ -0,0 +1,180 @@
+/// Rendering of the collected column peaks into a waveform image.
+use crate::cli::AutoGain;
+use crate::image::WaveImage;
+use crate::peaks::{percentile_peak, ColumnPeak, Envelope, Peaks};
+use crate::scale::AmplitudeMap;
+
+/// Loudness (in dBFS) that maps to the darkest tint level.
+const TINT_FLOOR_DB: f32 = -48.0;
+
+/// Loudness (in dBFS) that maps to the brightest tint level.  This is the
+/// RMS level of a full scale sine wave, about as loud as real music gets.
+const TINT_CEILING_DB: f32 = -3.0;
+
+/// How the column peaks are turned into pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct RenderOptions {
+    /// Color each column by its RMS loudness
+    pub tint: bool,
+    /// Draw the L/R balance of each column into the strip below the waveform
+    pub balance_strip: bool,
+    /// Also draw each column into the compressed overview
+    pub overview: bool,
+    /// Draw the signed envelope of each column instead of the peak bars
+    pub envelope: bool,
+    /// Draw each channel in a lane of its own instead of the combined waveform
+    pub lanes: bool,
+    /// Draw the RMS of each column over its peak
+    pub rms_layer: bool,
+    /// Scale the peaks so the loudest one reaches full height
+    pub normalize: bool,
+    /// Scale the peaks so this percentile of them reaches full height
+    pub auto_gain: Option<AutoGain>,
+    /// Maps the peak amplitudes to waveform heights
+    pub map: AmplitudeMap,
+}
+
+impl RenderOptions {
+    /// Returns true if the decoder has to collect the per-column RMS.
+    pub fn needs_rms(&self) -> bool {
+        self.tint || self.balance_strip || self.rms_layer
+    }
+}
+
+/// Returns the gain the peaks are drawn with: the one that brings the
+/// percentile of the automatic gain or, when normalizing, the loudest peak
+/// to full height, otherwise 1.0.
+pub fn peak_gain(peaks: &Peaks, options: &RenderOptions) -> f32 {
+    match options.auto_gain {
+        Some(auto_gain) => peaks.percentile_gain(auto_gain.percentile()),
+        None if options.normalize => peaks.normalize_gain(),
+        None => 1.0,
+    }
+}
+
+/// Draws all of the column peaks into the image.
+///
+/// The peaks are first multiplied by the gain (see `peak_gain`) and then
+/// mapped through the amplitude scale and contrast.  The loudness tint and the
+/// balance use the RMS levels as decoded, so they are not normalized, while
+/// the RMS layer is drawn to the same scale as the peaks behind it.
+pub fn render(peaks: &Peaks, image: &mut WaveImage, options: &RenderOptions) {
+    let gain = peak_gain(peaks, options);
+
+    for (x, column) in peaks.columns.iter().enumerate() {
+        draw_column(image, x as u32, peaks.stereo, column, gain, options);
+    }
+
+    // Each channel in its own lane replaces the combined waveform
+    for (lane, channel) in peaks.lanes.iter().enumerate() {
+        for (x, &peak) in channel.iter().enumerate() {
+            image.draw_lane_point(x as u32, lane, options.map.apply(peak * gain));
+        }
+    }
+}
+
+/// Maps an RMS amplitude to a tint level on a dB scale so that the
+/// quiet passages still get distinguishable shades.
+fn loudness_level(rms: f32) -> f32 {
+    if rms <= 0.0 {
+        return 0.0;
+    }
+    let db = 20.0 * rms.log10();
+    ((db - TINT_FLOOR_DB) / (TINT_CEILING_DB - TINT_FLOOR_DB)).clamp(0.0, 1.0)
+}
+
+/// Draws one column of the waveform along with any extra per-column rendering.
+fn draw_column(
+    image: &mut WaveImage,
+    x: u32,
+    stereo: bool,
+    column: &ColumnPeak,
+    gain: f32,
+    options: &RenderOptions,
+) {
+    let left = options.map.apply(column.left * gain);
+    let right = options.map.apply(column.right * gain);
+
+    if options.envelope {
+        // The envelope keeps its sign through the gain and the amplitude map
+        let map = |value: f32| options.map.apply(value.abs() * gain).copysign(value);
+        let envelope = |envelope: &Envelope| (map(envelope.min), map(envelope.max));
+        if stereo {
+            image.draw_envelope_point(x, envelope(&column.left_envelope), envelope(&column.right_envelope));
+        } else {
+            image.draw_envelope_point_mono(x, envelope(&column.left_envelope));
+        }
+    } else if options.tint {
+        let left_level = loudness_level(column.left_rms);
+        if stereo {
+            let right_level = loudness_level(column.right_rms);
+            image.draw_point_tinted(x, left, right, left_level, right_level);
+        } else {
+            image.draw_point_mono_tinted(x, left, left_level);
+        }
+    } else if options.rms_layer {
+        let left_rms = options.map.apply(column.left_rms * gain);
+        if stereo {
+            let right_rms = options.map.apply(column.right_rms * gain);
+            image.draw_point_layered(x, left, right, left_rms, right_rms);
+        } else {
+            image.draw_point_mono_layered(x, left, left_rms);
+        }
+    } else if options.lanes {
+        // The lanes are drawn from their own peaks
+    } else if stereo {
+        image.draw_point(x, left, right);
+    } else {
+        image.draw_point_mono(x, left);
+    }
+
+    if options.overview {
+        let amplitude = if stereo { left.max(right) } else { left };
+        image.draw_overview_point(x, amplitude);
+    }
+
+    // Mono audio has no balance so it always shows as centered
+    if options.balance_strip {
+        let balance = if stereo { column.balance() } else { 0.0 };
+        image.draw_balance(x, balance);
+    }
+}
+
+/// Draws the column peaks of every stem, as a layer over the stems before it
+/// or in a lane of its own (see `WaveImage::draw_stem_point`).
+///
+/// The stems are mono peaks and share the gain, so their levels stay in
+/// proportion to each other.
+pub fn render_stems(stems: &[Peaks], image: &mut WaveImage, options: &RenderOptions) {
+    let gain = if let Some(auto_gain) = options.auto_gain {
+        // The loudest stem of each column
+        let width = stems.iter().map(|peaks| peaks.columns.len()).max().unwrap_or(0);
+        let mut columns = (0..width)
+            .map(|x| stems.iter().filter_map(|peaks| peaks.columns.get(x)).fold(0.0, |a, c| f32::max(a, c.left)))
+            .collect::<Vec<_>>();
+        percentile_peak(&mut columns, auto_gain.percentile()).map_or(1.0, |peak| 1.0 / peak)
+    } else if options.normalize {
+        let max = stems.iter().map(Peaks::max_peak).fold(0.0, f32::max);
+        if max > 0.0 { 1.0 / max } else { 1.0 }
+    } else {
+        1.0
+    };
+
+    for (stem, peaks) in stems.iter().enumerate() {
+        for (x, column) in peaks.columns.iter().enumerate() {
+            image.draw_stem_point(x as u32, stem, options.map.apply(column.left * gain));
+        }
+    }
+}
+
+/// Draws the column peaks of two sources over each other so that only
+/// their differences remain visible (see `WaveImage::draw_overlay_point`).
+///
+/// The first source is in the `left` and the second in the `right` values
+/// of the columns.
+pub fn render_overlay(peaks: &Peaks, image: &mut WaveImage) {
+    for (x, column) in peaks.columns.iter().enumerate() {
+        image.draw_overlay_point(x as u32, column.left, column.right);
+    }
+}
@@ -0,0 +1,111 @@
+/// Resuming an interrupted batch run (`--resume`).
+///
+/// A run over a large library that is stopped with Ctrl-C (or by a reboot)
+/// has no summary, and starting it again either redraws everything with
+/// `--overwrite` or skips whatever has an image, including the image that
+/// was cut off half written.  With `--resume` every file is appended to a
+/// state file of JSON lines as soon as it is done, with the outputs written
+/// for it:
+///
+/// ```text
+/// {"path":"/music/a.flac","outputs":["/music/a.flac.png"]}
+/// ```
+///
+/// Started again with the same state file, the run skips the files listed
+/// there whose outputs all still exist, even with `--overwrite`, and draws
+/// the rest.  Failed files are not listed, so they are tried again.  A line
+/// cut off by the interrupt is ignored, and the paths are absolute so the
+/// run can be resumed from another directory.
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::audit;
+use crate::error::{Result, WaverError};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// One line of the state file: a file that is done.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Done {
+    /// The absolute path of the audio file
+    pub path: String,
+    /// The absolute paths of the files written for it
+    pub outputs: Vec<String>,
+}
+
+/// The files done so far, and the state file the files done from now on
+/// are appended to.
+#[derive(Debug)]
+pub struct ResumeState {
+    done: HashMap<String, Vec<String>>,
+    file: Mutex<File>,
+}
+
+/// Returns the path as an absolute path, for the state file.
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl ResumeState {
+    /// Reads the files done from the state file, if there is one, and opens
+    /// it to append the files done from now on.
+    pub fn open(path: &Path) -> Result<Self> {
+        let error = |err: io::Error| {
+            WaverError::argument_error(format!("Can not open the resume state '{}': {err}", path.display()))
+        };
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(error(err)),
+        };
+        let done = text
+            .lines()
+            .filter_map(|line| serde_json::from_str::<Done>(line).ok())
+            .map(|done| (done.path, done.outputs))
+            .collect();
+        let mut file = audit::append(path).map_err(error)?;
+        // A line cut off by the interrupt is ended, so the next one is whole
+        if !text.is_empty() && !text.ends_with('\n') {
+            file.write_all(b"\n").map_err(error)?;
+        }
+        Ok(Self { done, file: Mutex::new(file) })
+    }
+
+    /// Returns the number of files done in the earlier runs.
+    pub fn len(&self) -> usize {
+        self.done.len()
+    }
+
+    /// Returns true if no files were done in the earlier runs.
+    pub fn is_empty(&self) -> bool {
+        self.done.is_empty()
+    }
+
+    /// Returns true if the audio file was done in an earlier run and all the
+    /// files written for it are still there.
+    pub fn is_done(&self, path: &Path) -> bool {
+        let path = absolute(path).display().to_string();
+        self.done.get(&path).is_some_and(|outputs| outputs.iter().all(|output| Path::new(output).exists()))
+    }
+
+    /// Appends the audio file and the files written for it to the state
+    /// file, as one whole line.
+    pub fn record(&self, path: &Path, outputs: &[String]) -> io::Result<()> {
+        let done = Done {
+            path: absolute(path).display().to_string(),
+            outputs: outputs.iter().map(|output| absolute(Path::new(output)).display().to_string()).collect(),
+        };
+        let mut line = serde_json::to_string(&done).map_err(io::Error::other)?;
+        line.push('\n');
+        let mut file = self.file.lock().unwrap();
+        file.write_all(line.as_bytes())?;
+        file.flush()
+    }
+}
@@ -0,0 +1,38 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::resume::ResumeState;
+
+    #[test]
+    fn test_resume_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = dir.path().join("run.state");
+        let (a, b) = (dir.path().join("a.mp3"), dir.path().join("b.mp3"));
+        let image = dir.path().join("a.mp3.png").display().to_string();
+        fs::write(&image, b"png").unwrap();
+
+        let state = ResumeState::open(&state_path).unwrap();
+        assert!(state.is_empty(), "A new state file has no files done");
+        state.record(&a, std::slice::from_ref(&image)).unwrap();
+        state.record(&b, &[]).unwrap();
+        assert!(!state.is_done(&a), "Only the files of the earlier runs are skipped");
+        drop(state);
+
+        // An interrupt can cut off the last line
+        let mut text = fs::read_to_string(&state_path).unwrap();
+        text.push_str(r#"{"path":"/music/c.mp3","outp"#);
+        fs::write(&state_path, text).unwrap();
+
+        let state = ResumeState::open(&state_path).unwrap();
+        assert_eq!(state.len(), 2);
+        assert!(state.is_done(&a) && state.is_done(&b));
+        assert!(!state.is_done(&dir.path().join("c.mp3")));
+        state.record(&dir.path().join("c.mp3"), &[]).unwrap();
+        drop(state);
+        assert_eq!(ResumeState::open(&state_path).unwrap().len(), 3, "The cut off line was ended");
+
+        fs::remove_file(&image).unwrap();
+        assert!(!ResumeState::open(&state_path).unwrap().is_done(&a), "A file whose image is gone is drawn again");
+    }
+}
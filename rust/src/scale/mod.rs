@@ -0,0 +1,92 @@
+/// Amplitude scaling applied to the column peaks before they are drawn.
+use std::str::FromStr;
+
+use crate::error::{Result, WaverError};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The default dB level that maps to the center line in dB scale.
+pub const DEFAULT_DB_FLOOR: f32 = -60.0;
+
+/// The contrast that leaves the heights as scaled.
+pub const NEUTRAL_CONTRAST: f32 = 1.0;
+
+/// How peak amplitudes are mapped to the height of the waveform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AmplitudeScale {
+    /// Height is proportional to the amplitude (the classic waveform)
+    #[default]
+    Linear,
+    /// Height is proportional to the level in dB above a floor, which is
+    /// what DAWs show and keeps quiet recordings readable
+    Db,
+}
+
+impl FromStr for AmplitudeScale {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "linear" => Ok(AmplitudeScale::Linear),
+            "db" => Ok(AmplitudeScale::Db),
+            _ => Err(WaverError::argument_error("Scale must be linear or db")),
+        }
+    }
+}
+
+/// Maps peak amplitudes (0.0 to 1.0) to waveform heights (0.0 to 1.0).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct AmplitudeMap {
+    /// The scale to map with
+    pub scale: AmplitudeScale,
+    /// The dB level that maps to zero height in dB scale (must be negative)
+    pub floor_db: f32,
+    /// Gamma applied to the scaled heights (must be positive); above 1.0
+    /// lowers the middle heights, which spreads out loud, dense material
+    pub contrast: f32,
+}
+
+impl Default for AmplitudeMap {
+    fn default() -> Self {
+        Self {
+            scale: AmplitudeScale::Linear,
+            floor_db: DEFAULT_DB_FLOOR,
+            contrast: NEUTRAL_CONTRAST,
+        }
+    }
+}
+
+impl AmplitudeMap {
+    /// Maps a peak amplitude to a waveform height.
+    ///
+    /// In dB scale the floor maps to 0.0 and full scale (0 dB) to 1.0 with
+    /// the levels in between spread linearly in dB.  The contrast comes
+    /// last, as a gamma on the scaled height, so silence and full scale stay
+    /// where they are in either scale.  Any normalization gain is applied
+    /// to the amplitude before it gets here.
+    pub fn apply(&self, amplitude: f32) -> f32 {
+        let height = self.scale(amplitude);
+        if self.contrast == NEUTRAL_CONTRAST || height <= 0.0 {
+            height
+        } else {
+            height.powf(self.contrast)
+        }
+    }
+
+    /// Maps a peak amplitude to a height with the scale alone.
+    fn scale(&self, amplitude: f32) -> f32 {
+        match self.scale {
+            AmplitudeScale::Linear => amplitude,
+            AmplitudeScale::Db => {
+                if amplitude <= 0.0 {
+                    return 0.0;
+                }
+                let db = 20.0 * amplitude.log10();
+                ((db - self.floor_db) / -self.floor_db).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod tests {
+    use crate::scale::{AmplitudeMap, AmplitudeScale, DEFAULT_DB_FLOOR};
+    use std::str::FromStr;
+
+    /// Asserts two heights are equal within rounding error
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "Expected {} but got {}", expected, actual
+        );
+    }
+
+    #[test]
+    fn test_scale_parsing() {
+        assert_eq!(AmplitudeScale::from_str("linear").unwrap(), AmplitudeScale::Linear);
+        assert_eq!(AmplitudeScale::from_str("db").unwrap(), AmplitudeScale::Db);
+        assert_eq!(AmplitudeScale::from_str(" dB ").unwrap(), AmplitudeScale::Db);
+
+        let result = AmplitudeScale::from_str("log");
+        assert!(result.is_err(), "Should reject unknown scales");
+        assert_eq!(result.unwrap_err().to_string(), "Invalid argument: Scale must be linear or db");
+    }
+
+    #[test]
+    fn test_linear_is_identity() {
+        let map = AmplitudeMap::default();
+        for amplitude in [0.0, 0.001, 0.25, 0.5, 1.0] {
+            assert_close(map.apply(amplitude), amplitude);
+        }
+    }
+
+    #[test]
+    fn test_db_scale() {
+        let map = AmplitudeMap { scale: AmplitudeScale::Db, floor_db: DEFAULT_DB_FLOOR, ..AmplitudeMap::default() };
+
+        // Full scale is full height and the floor is nothing
+        assert_close(map.apply(1.0), 1.0);
+        assert_close(map.apply(0.001), 0.0);
+
+        // -20 dB is a third of the way down from the top with a -60 dB floor
+        assert_close(map.apply(0.1), 2.0 / 3.0);
+
+        // -6 dB (half amplitude) is drawn much taller than in linear scale
+        assert!(map.apply(0.5) > 0.89);
+
+        // Below the floor and silence are clamped to nothing
+        assert_close(map.apply(0.0001), 0.0);
+        assert_close(map.apply(0.0), 0.0);
+        assert_close(map.apply(-0.5), 0.0);
+    }
+
+    #[test]
+    fn test_db_floor() {
+        // A higher floor makes the same level draw shorter
+        let deep = AmplitudeMap { scale: AmplitudeScale::Db, floor_db: -90.0, ..AmplitudeMap::default() };
+        let shallow = AmplitudeMap { scale: AmplitudeScale::Db, floor_db: -30.0, ..AmplitudeMap::default() };
+        assert_close(deep.apply(0.01), 50.0 / 90.0);
+        assert_close(shallow.apply(0.1), 10.0 / 30.0);
+        assert_close(shallow.apply(0.001), 0.0);
+    }
+
+    #[test]
+    fn test_contrast() {
+        let map = AmplitudeMap { contrast: 2.0, ..AmplitudeMap::default() };
+        assert_close(map.apply(0.5), 0.25);
+        assert_close(map.apply(0.9) - map.apply(0.8), 0.17);
+        assert_close(map.apply(1.0), 1.0);
+        assert_close(map.apply(0.0), 0.0);
+
+        // Below 1.0 raises the quiet heights instead
+        let soft = AmplitudeMap { contrast: 0.5, ..AmplitudeMap::default() };
+        assert_close(soft.apply(0.25), 0.5);
+    }
+
+    #[test]
+    fn test_contrast_after_db_scale() {
+        // The gamma is applied to the dB height, not to the amplitude
+        let map = AmplitudeMap { scale: AmplitudeScale::Db, contrast: 2.0, ..AmplitudeMap::default() };
+        assert_close(map.apply(0.1), 4.0 / 9.0);
+        assert_close(map.apply(1.0), 1.0);
+        assert_close(map.apply(0.0001), 0.0);
+        assert_close(map.apply(-0.5), 0.0);
+    }
+
+    #[test]
+    fn test_contrast_after_gain() {
+        // The normalization gain scales the amplitude before the contrast,
+        // so the loudest peak still reaches full height
+        let map = AmplitudeMap { contrast: 3.0, ..AmplitudeMap::default() };
+        let gain = 1.0 / 0.8;
+        assert_close(map.apply(0.8 * gain), 1.0);
+        assert_close(map.apply(0.4 * gain), 0.125);
+    }
+}
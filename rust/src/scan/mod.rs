@@ -0,0 +1,73 @@
+/// Chunked scanning of decoded sample slices.
+///
+/// These run over every decoded sample, so after the decoding itself they
+/// are the hot path for long files.  Each one works on a whole slice of a
+/// channel with `LANES` independent accumulators, which breaks the
+/// dependency between consecutive samples and lets the compiler turn the
+/// loop into SIMD instructions.
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// Number of independent accumulators (one SIMD register of `f32` on AVX).
+const LANES: usize = 8;
+
+/// Returns the largest absolute sample of the slice, clamped to 1.0.
+///
+/// Returns 0.0 for an empty slice.
+pub fn peak_abs(samples: &[f32]) -> f32 {
+    let chunks = samples.chunks_exact(LANES);
+    let tail = chunks
+        .remainder()
+        .iter()
+        .fold(0.0f32, |max, sample| max.max(sample.abs()));
+
+    let mut lanes = [0.0f32; LANES];
+    for chunk in chunks {
+        for (lane, sample) in lanes.iter_mut().zip(chunk) {
+            *lane = lane.max(sample.abs());
+        }
+    }
+    lanes.iter().fold(tail, |max, &lane| max.max(lane)).min(1.0)
+}
+
+/// Returns the sum of the squares of the samples.
+pub fn sum_squares(samples: &[f32]) -> f32 {
+    let chunks = samples.chunks_exact(LANES);
+    let tail: f32 = chunks.remainder().iter().map(|sample| sample * sample).sum();
+
+    let mut lanes = [0.0f32; LANES];
+    for chunk in chunks {
+        for (lane, sample) in lanes.iter_mut().zip(chunk) {
+            *lane += sample * sample;
+        }
+    }
+    lanes.iter().sum::<f32>() + tail
+}
+
+/// Returns the lowest and highest sample of the slice, unclamped.
+///
+/// An empty slice gives infinity and negative infinity, so the results of
+/// several slices can be combined with `min` and `max`.
+pub fn min_max(samples: &[f32]) -> (f32, f32) {
+    let chunks = samples.chunks_exact(LANES);
+    let tail = chunks
+        .remainder()
+        .iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &sample| {
+            (min.min(sample), max.max(sample))
+        });
+
+    let mut lows = [f32::INFINITY; LANES];
+    let mut highs = [f32::NEG_INFINITY; LANES];
+    for chunk in chunks {
+        for ((low, high), &sample) in lows.iter_mut().zip(highs.iter_mut()).zip(chunk) {
+            *low = low.min(sample);
+            *high = high.max(sample);
+        }
+    }
+    (
+        lows.iter().fold(tail.0, |min, &low| min.min(low)),
+        highs.iter().fold(tail.1, |max, &high| max.max(high)),
+    )
+}
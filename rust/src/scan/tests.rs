@@ -0,0 +1,65 @@
+#[cfg(test)]
+mod tests {
+    use crate::scan::{min_max, peak_abs, sum_squares};
+
+    /// The straightforward per-sample versions to check against.
+    fn naive_peak(samples: &[f32]) -> f32 {
+        samples.iter().fold(0.0f32, |max, s| max.max(s.abs().min(1.0)))
+    }
+
+    fn naive_sum_squares(samples: &[f32]) -> f32 {
+        samples.iter().map(|s| s * s).sum()
+    }
+
+    fn samples(len: usize) -> Vec<f32> {
+        (0..len).map(|i| ((i * 7919) % 2001) as f32 / 1000.0 - 1.0).collect()
+    }
+
+    #[test]
+    fn test_peak_abs_matches_naive() {
+        // Lengths around the lane count exercise the chunks and the remainder
+        for len in [0, 1, 7, 8, 9, 15, 16, 17, 1000, 1152] {
+            let samples = samples(len);
+            assert_eq!(peak_abs(&samples), naive_peak(&samples), "len {len}");
+        }
+    }
+
+    #[test]
+    fn test_peak_abs_negative_and_clipped() {
+        assert_eq!(peak_abs(&[0.1, -0.75, 0.5]), 0.75);
+        assert_eq!(peak_abs(&[0.0; 20]), 0.0);
+        assert_eq!(peak_abs(&[0.2, -1.5, 0.3, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]), 1.0, "Overs are clamped");
+
+        // The peak can be in any lane or in the remainder
+        for position in 0..19 {
+            let mut samples = vec![0.25; 19];
+            samples[position] = -0.9;
+            assert_eq!(peak_abs(&samples), 0.9, "position {position}");
+        }
+    }
+
+    #[test]
+    fn test_sum_squares_matches_naive() {
+        for len in [0, 1, 7, 8, 9, 1000, 1152] {
+            let samples = samples(len);
+            let expected = naive_sum_squares(&samples);
+            let actual = sum_squares(&samples);
+            assert!((actual - expected).abs() <= expected * 1e-5, "len {len}: {actual} vs {expected}");
+        }
+        assert_eq!(sum_squares(&[0.5, -0.5, 1.0]), 1.5);
+    }
+
+    #[test]
+    fn test_min_max() {
+        for len in [1, 7, 8, 9, 1000, 1152] {
+            let samples = samples(len);
+            let expected = samples.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &s| (lo.min(s), hi.max(s)));
+            assert_eq!(min_max(&samples), expected, "len {len}");
+        }
+
+        // Signed and unclamped, so an offset signal keeps its offset
+        assert_eq!(min_max(&[0.2, 0.5, 0.3]), (0.2, 0.5));
+        assert_eq!(min_max(&[-1.5, 0.0, 1.25]), (-1.5, 1.25));
+        assert_eq!(min_max(&[]), (f32::INFINITY, f32::NEG_INFINITY));
+    }
+}
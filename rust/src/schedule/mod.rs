@@ -0,0 +1,94 @@
+/// Ordering of the files of a batch for parallel processing.
+///
+/// With files in directory order a large recording that happens to come last
+/// runs alone on one core while the others are idle.  Starting the largest
+/// files first and letting the small ones fill in the remaining cores keeps
+/// all cores busy until close to the end of the batch.
+///
+/// The files are decoded on rayon's global thread pool, which by default has
+/// one thread per core.  `--jobs` limits the number of threads and `--nice`
+/// runs them at a low scheduling priority, so a long library scan on a
+/// laptop does not saturate every core or get in the way of other programs.
+use std::cmp::Reverse;
+use std::path::PathBuf;
+
+use rayon::ThreadPoolBuilder;
+
+use crate::error::{Result, WaverError};
+use crate::progress::file_weight;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// One audio file of a batch along with its estimated cost.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkItem {
+    /// Position of the file in the order the files were found
+    pub position: usize,
+    /// Path of the audio file
+    pub path: PathBuf,
+    /// Estimated cost of processing the file (its size in bytes)
+    pub weight: u64,
+}
+
+impl WorkItem {
+    /// Creates the work item of the file at `position`, weighing the file
+    /// by its size.
+    pub fn new(position: usize, path: PathBuf) -> Self {
+        let weight = file_weight(&path);
+        Self { position, path, weight }
+    }
+}
+
+/// Sorts the work items so the most expensive are started first.
+///
+/// The sort is stable so files of the same size keep their order.  The
+/// items must then be handed out in this order as threads become free (for
+/// example with rayon's `par_bridge`) rather than split into fixed chunks.
+pub fn largest_first(items: &mut [WorkItem]) {
+    items.sort_by_key(|item| Reverse(item.weight));
+}
+
+/// Scheduling priority (nice value) of the worker threads with `--nice`.
+pub const NICE_PRIORITY: i32 = 10;
+
+/// Returns the builder of a worker pool with `jobs` threads (one per core
+/// if `None`), running at a low priority if `nice` is set.
+pub fn pool_builder(jobs: Option<usize>, nice: bool) -> ThreadPoolBuilder {
+    let builder = ThreadPoolBuilder::new().num_threads(jobs.unwrap_or(0));
+    if nice {
+        builder.start_handler(|_| lower_priority())
+    } else {
+        builder
+    }
+}
+
+/// Configures the global pool the files are processed on.
+///
+/// Must be called before anything runs on the pool.
+pub fn configure_pool(jobs: Option<usize>, nice: bool) -> Result<()> {
+    pool_builder(jobs, nice)
+        .build_global()
+        .map_err(|e| WaverError::generation_error(format!("Could not start the worker threads: {e}")))
+}
+
+/// Lowers the scheduling priority of the calling thread to `NICE_PRIORITY`.
+///
+/// A thread that already runs at a lower priority is left alone.  This is
+/// best effort: where it is not allowed or supported the thread just keeps
+/// its priority.
+#[cfg(unix)]
+fn lower_priority() {
+    // On Linux `who` 0 is the calling thread, elsewhere it is the process,
+    // which is just as good since all the work runs on the pool
+    // SAFETY: getpriority and setpriority only read and change the priority
+    unsafe {
+        if libc::getpriority(libc::PRIO_PROCESS, 0) < NICE_PRIORITY {
+            libc::setpriority(libc::PRIO_PROCESS, 0, NICE_PRIORITY);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn lower_priority() {}
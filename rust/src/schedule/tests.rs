@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod tests {
+    use crate::schedule::{largest_first, pool_builder, WorkItem};
+    use std::path::PathBuf;
+
+    fn item(name: &str, weight: u64) -> WorkItem {
+        WorkItem { position: 0, path: PathBuf::from(name), weight }
+    }
+
+    #[test]
+    fn test_largest_first() {
+        let mut items = vec![item("a", 10), item("b", 500), item("c", 10), item("d", 70)];
+        largest_first(&mut items);
+
+        let names: Vec<_> = items.iter().map(|i| i.path.to_str().unwrap()).collect();
+        assert_eq!(names, ["b", "d", "a", "c"], "Ties keep their order");
+    }
+
+    #[test]
+    fn test_missing_file_weight() {
+        assert_eq!(WorkItem::new(0, PathBuf::from("/nonexistent/file.mp3")).weight, 1);
+    }
+
+    #[test]
+    fn test_pool_builder() {
+        let pool = pool_builder(Some(3), false).build().unwrap();
+        assert_eq!(pool.current_num_threads(), 3);
+
+        let pool = pool_builder(None, false).build().unwrap();
+        assert!(pool.current_num_threads() >= 1, "One thread per core");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_nice_pool() {
+        use crate::schedule::NICE_PRIORITY;
+
+        let pool = pool_builder(Some(1), true).build().unwrap();
+        // SAFETY: getpriority only reads the priority of the worker thread
+        let priority = pool.install(|| unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) });
+        assert!(priority >= NICE_PRIORITY, "Workers run at nice {priority}");
+    }
+}
@@ -0,0 +1,138 @@
+/// Splitting long recordings into images of a fixed length.
+///
+/// A lecture of two hours drawn into a single 2048 pixel image has only
+/// 3.5 seconds per column, too little to find anything in it.  With
+/// `--segment` each part of the given length gets its own image, named from
+/// a template with the index and start time of the part, and optionally
+/// overlapping the next part so nothing is lost at the cuts.
+use std::path::Path;
+
+use crate::config::TimeRange;
+use crate::error::{Result, WaverError};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The image name template used unless another one is given.
+pub const DEFAULT_TEMPLATE: &str = "{file}.{index}.{ext}";
+
+/// The placeholders a template can use.
+const PLACEHOLDERS: [&str; 4] = ["file", "index", "start", "ext"];
+
+/// One part of a segmented recording.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+    /// Position of the segment, counting from 1
+    pub index: usize,
+    /// Where the segment starts in seconds
+    pub start: f64,
+    /// Where the segment ends in seconds (including the overlap)
+    pub end: f64,
+}
+
+impl Segment {
+    /// Returns the part of the audio the segment covers.
+    pub fn range(&self) -> TimeRange {
+        TimeRange {
+            start: self.start,
+            end: Some(self.end),
+        }
+    }
+}
+
+/// How a recording is split into segments and how their images are named.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segmenting {
+    /// Length of each segment in seconds (without the overlap)
+    length: f64,
+    /// Seconds each segment runs on into the next one
+    overlap: f64,
+    /// Image name template
+    template: String,
+}
+
+impl Segmenting {
+    /// Creates validated segment settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `length` - Length of each segment in seconds
+    /// * `overlap` - Seconds each segment runs on into the next one
+    /// * `template` - Image name with the placeholders `{file}` (the audio
+    ///   file path), `{index}` (from 001 on), `{start}` (like `01h20m00s`)
+    ///   and `{ext}` (the image extension)
+    pub fn new(length: f64, overlap: f64, template: impl Into<String>) -> Result<Self> {
+        let template = template.into();
+        if !(length.is_finite() && length >= 1.0) {
+            return Err(WaverError::argument_error("Segments must be at least 1 second long"));
+        }
+        if !(overlap.is_finite() && overlap >= 0.0 && overlap < length) {
+            return Err(WaverError::argument_error(
+                "The segment overlap must be shorter than the segments",
+            ));
+        }
+
+        // Every placeholder has to be known and the segments need
+        // different names
+        let mut rest = template.as_str();
+        while let Some(open) = rest.find('{') {
+            let close = rest[open..].find('}').map(|close| open + close).ok_or_else(|| {
+                WaverError::argument_error("The segment name has an unclosed {")
+            })?;
+            let name = &rest[open + 1..close];
+            if !PLACEHOLDERS.contains(&name) {
+                return Err(WaverError::argument_error(format!(
+                    "Unknown placeholder {{{name}}} in the segment name, use {{file}}, {{index}}, {{start}} or {{ext}}"
+                )));
+            }
+            rest = &rest[close + 1..];
+        }
+        if !template.contains("{index}") && !template.contains("{start}") {
+            return Err(WaverError::argument_error(
+                "The segment name must contain {index} or {start}",
+            ));
+        }
+
+        Ok(Self {
+            length,
+            overlap,
+            template,
+        })
+    }
+
+    /// Returns the segments of audio `duration` seconds long.
+    ///
+    /// The last segment ends with the audio, so it may be shorter than the
+    /// others.  Audio of no length still gets one (empty) segment.
+    pub fn segments(&self, duration: f64) -> Vec<Segment> {
+        // A tiny rest from rounding the duration does not get its own segment
+        let count = ((duration / self.length) - 1e-6).ceil().max(1.0) as usize;
+        (0..count)
+            .map(|n| {
+                let start = n as f64 * self.length;
+                Segment {
+                    index: n + 1,
+                    start,
+                    end: (start + self.length + self.overlap).min(duration),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the image path of a segment of the audio file.
+    pub fn path(&self, audio_path: &Path, segment: &Segment, extension: &str) -> String {
+        self.template
+            .replace("{file}", &audio_path.display().to_string())
+            .replace("{index}", &format!("{:03}", segment.index))
+            .replace("{start}", &format_start(segment.start))
+            .replace("{ext}", extension)
+    }
+}
+
+/// Formats a start time for file names as `01h20m05s` (whole seconds, with
+/// no characters that are special in file names).
+pub fn format_start(seconds: f64) -> String {
+    let total = seconds.max(0.0).floor() as u64;
+    format!("{:02}h{:02}m{:02}s", total / 3600, total / 60 % 60, total % 60)
+}
@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests {
+    use crate::segment::{format_start, Segmenting, DEFAULT_TEMPLATE};
+    use std::path::Path;
+
+    #[test]
+    fn test_segments() {
+        let segmenting = Segmenting::new(600.0, 0.0, DEFAULT_TEMPLATE).unwrap();
+        let segments = segmenting.segments(1500.0);
+        let ranges: Vec<(usize, f64, f64)> = segments.iter().map(|s| (s.index, s.start, s.end)).collect();
+        assert_eq!(ranges, [(1, 0.0, 600.0), (2, 600.0, 1200.0), (3, 1200.0, 1500.0)]);
+
+        // An exact multiple has no empty segment at the end
+        assert_eq!(segmenting.segments(1200.0).len(), 2);
+        assert_eq!(segmenting.segments(1200.0000001).len(), 2, "Rounding of the duration");
+        assert_eq!(segmenting.segments(0.0).len(), 1);
+
+        // The overlap runs into the next segment but not past the end
+        let segmenting = Segmenting::new(600.0, 30.0, DEFAULT_TEMPLATE).unwrap();
+        let ends: Vec<f64> = segmenting.segments(1500.0).iter().map(|s| s.end).collect();
+        assert_eq!(ends, [630.0, 1230.0, 1500.0]);
+    }
+
+    #[test]
+    fn test_segment_path() {
+        let segmenting = Segmenting::new(600.0, 0.0, DEFAULT_TEMPLATE).unwrap();
+        let segment = segmenting.segments(4000.0)[6];
+        let audio = Path::new("talks/lecture.mp3");
+        assert_eq!(segmenting.path(audio, &segment, "png"), "talks/lecture.mp3.007.png");
+
+        let segmenting = Segmenting::new(600.0, 0.0, "{file}-{start}.{ext}").unwrap();
+        assert_eq!(segmenting.path(audio, &segment, "qoi"), "talks/lecture.mp3-01h00m00s.qoi");
+
+        assert_eq!(format_start(3725.9), "01h02m05s");
+        assert_eq!(format_start(0.0), "00h00m00s");
+    }
+
+    #[test]
+    fn test_invalid_segmenting() {
+        let message = |result: crate::error::Result<Segmenting>| result.unwrap_err().to_string();
+        assert_eq!(
+            message(Segmenting::new(0.5, 0.0, DEFAULT_TEMPLATE)),
+            "Invalid argument: Segments must be at least 1 second long"
+        );
+        assert_eq!(
+            message(Segmenting::new(60.0, 60.0, DEFAULT_TEMPLATE)),
+            "Invalid argument: The segment overlap must be shorter than the segments"
+        );
+        assert_eq!(
+            message(Segmenting::new(60.0, 0.0, "{file}.{ext}")),
+            "Invalid argument: The segment name must contain {index} or {start}"
+        );
+        assert_eq!(
+            message(Segmenting::new(60.0, 0.0, "{file}.{n}.{ext}")),
+            "Invalid argument: Unknown placeholder {n} in the segment name, use {file}, {index}, {start} or {ext}"
+        );
+        assert!(Segmenting::new(60.0, 0.0, "{file.{index}").is_err());
+        assert!(Segmenting::new(60.0, 0.0, "{index").is_err(), "Unclosed placeholder");
+    }
+}
@@ -0,0 +1,97 @@
+/// JSON sidecar files written next to the waveform images.
+///
+/// The sidecar records facts about the audio that the image was rendered
+/// from so that players and library tools can use them without decoding the
+/// audio again.
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::audit;
+use crate::color::Rgba;
+use crate::error::Result;
+use crate::loudness::Loudness;
+use crate::metadata::Marker;
+use crate::summary::RunId;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The contents of a sidecar file.
+#[derive(Debug, Clone, Serialize)]
+pub struct Sidecar {
+    /// Path of the audio file the image was rendered from
+    pub source: String,
+    /// Path of the rendered image
+    pub image: String,
+    /// Width of the image in pixels
+    pub width: u32,
+    /// Height of the image in pixels
+    pub height: u32,
+    /// Duration of the audio in seconds
+    pub duration_seconds: f64,
+    /// Sample rate in Hz
+    pub sample_rate: u32,
+    /// Number of channels in the audio
+    pub channels: usize,
+    /// Number of decoded frames (samples per channel)
+    pub frames: u64,
+    /// SHA-256 of the decoded PCM samples (not the container), if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pcm_sha256: Option<String>,
+    /// Integrated loudness and true peak of the audio, if measured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loudness: Option<Loudness>,
+    /// Colors the waveform was drawn with (not for spectrograms)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub colors: Option<Colors>,
+    /// Index points of the cuesheet embedded in the audio file, if any
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub markers: Vec<Marker>,
+    /// The ID of the run that rendered the image (`--run-id`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<RunId>,
+}
+
+/// The resolved colors of a waveform image, in the `RRGGBBAA` form that
+/// can be given back on the command line.
+#[derive(Debug, Clone, Serialize)]
+pub struct Colors {
+    pub background: Rgba,
+    pub left: Rgba,
+    pub right: Rgba,
+    /// Color of the center line and borders, if drawn
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub center_line: Option<Rgba>,
+    /// Colors of the channels in the played copy, if written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub played_left: Option<Rgba>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub played_right: Option<Rgba>,
+}
+
+impl Sidecar {
+    /// Returns the path of the sidecar for the given image path.
+    ///
+    /// The image extension is replaced with `.json`, so `song.mp3.png`
+    /// gets the sidecar `song.mp3.json`.
+    pub fn path_for(image_path: &Path) -> PathBuf {
+        image_path.with_extension("json")
+    }
+
+    /// Writes the sidecar as pretty printed JSON.
+    pub fn write(&self, writer: impl Write) -> Result<()> {
+        let mut writer = BufWriter::new(writer);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Saves the sidecar to the given path.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.write(audit::create(path)?)
+    }
+}
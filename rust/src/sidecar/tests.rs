@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod tests {
+    use crate::color::Rgba;
+    use crate::loudness::Loudness;
+    use crate::metadata::Marker;
+    use crate::sidecar::{Colors, Sidecar};
+    use std::path::Path;
+
+    fn sample() -> Sidecar {
+        Sidecar {
+            source: "music/song.flac".to_string(),
+            image: "music/song.flac.png".to_string(),
+            width: 2048,
+            height: 128,
+            duration_seconds: 247.5,
+            sample_rate: 44100,
+            channels: 2,
+            frames: 10_914_750,
+            pcm_sha256: None,
+            loudness: None,
+            colors: None,
+            markers: Vec::new(),
+            run_id: None,
+        }
+    }
+
+    #[test]
+    fn test_sidecar_path() {
+        assert_eq!(Sidecar::path_for(Path::new("a/song.mp3.png")), Path::new("a/song.mp3.json"));
+        assert_eq!(Sidecar::path_for(Path::new("wave.png")), Path::new("wave.json"));
+        assert_eq!(Sidecar::path_for(Path::new("wave")), Path::new("wave.json"));
+    }
+
+    #[test]
+    fn test_sidecar_json() {
+        let mut buffer = Vec::new();
+        sample().write(&mut buffer).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+
+        assert_eq!(json["source"], "music/song.flac");
+        assert_eq!(json["image"], "music/song.flac.png");
+        assert_eq!(json["width"], 2048);
+        assert_eq!(json["height"], 128);
+        assert_eq!(json["duration_seconds"], 247.5);
+        assert_eq!(json["sample_rate"], 44100);
+        assert_eq!(json["channels"], 2);
+        assert_eq!(json["frames"], 10_914_750);
+
+        // The hash is left out unless it was computed
+        assert!(json.get("pcm_sha256").is_none());
+        assert!(json.get("colors").is_none());
+        assert!(json.get("markers").is_none());
+        assert!(json.get("loudness").is_none());
+        assert!(json.get("run_id").is_none());
+        assert!(buffer.ends_with(b"}\n"), "Sidecar should end with a newline");
+    }
+
+    #[test]
+    fn test_sidecar_with_hash() {
+        let sidecar = Sidecar {
+            pcm_sha256: Some("ab".repeat(32)),
+            ..sample()
+        };
+        let mut buffer = Vec::new();
+        sidecar.write(&mut buffer).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json["pcm_sha256"], "ab".repeat(32));
+    }
+
+    #[test]
+    fn test_sidecar_with_run_id() {
+        let sidecar = Sidecar {
+            run_id: Some("nightly-42".parse().unwrap()),
+            ..sample()
+        };
+        let mut buffer = Vec::new();
+        sidecar.write(&mut buffer).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json["run_id"], "nightly-42");
+    }
+
+    #[test]
+    fn test_sidecar_with_loudness() {
+        let sidecar = Sidecar {
+            loudness: Some(Loudness { integrated_lufs: Some(-14.2), true_peak_dbtp: None }),
+            ..sample()
+        };
+        let mut buffer = Vec::new();
+        sidecar.write(&mut buffer).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json["loudness"], serde_json::json!({ "integrated_lufs": -14.2, "true_peak_dbtp": null }));
+    }
+
+    #[test]
+    fn test_sidecar_with_markers() {
+        let sidecar = Sidecar {
+            markers: vec![Marker { track: 2, index: 1, seconds: 61.5 }],
+            ..sample()
+        };
+        let mut buffer = Vec::new();
+        sidecar.write(&mut buffer).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json["markers"], serde_json::json!([{ "track": 2, "index": 1, "seconds": 61.5 }]));
+    }
+
+    #[test]
+    fn test_sidecar_with_colors() {
+        let sidecar = Sidecar {
+            colors: Some(Colors {
+                background: Rgba::new(0xff, 0xff, 0xff, 0x00),
+                left: Rgba::rgb(0x00, 0xff, 0x99),
+                right: Rgba::rgb(0x99, 0xff, 0x00),
+                center_line: None,
+                played_left: Some(Rgba::rgb(0xff, 0x55, 0x00)),
+                played_right: Some(Rgba::rgb(0xff, 0x55, 0x00)),
+            }),
+            ..sample()
+        };
+        let mut buffer = Vec::new();
+        sidecar.write(&mut buffer).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json["colors"]["background"], "ffffff00");
+        assert_eq!(json["colors"]["left"], "00ff99ff");
+        assert_eq!(json["colors"]["played_left"], "ff5500ff");
+        assert!(json["colors"].get("center_line").is_none());
+    }
+}
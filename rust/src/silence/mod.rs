@@ -0,0 +1,182 @@
+/// The silent parts of the audio (`--detect-silence`).
+///
+/// The gaps between the tracks of a live recording or the chapters of an
+/// audiobook are silences, and a player can show them on its seekbar or
+/// jump between them.  Waver already looks at every sample to draw the
+/// image, so with `--detect-silence` the `SilenceDetector` follows the same
+/// decode and the silences it finds are written as a small JSON file next
+/// to the image:
+///
+/// ```json
+/// {
+///   "source": "concert.flac",
+///   "threshold_db": -60.0,
+///   "min_duration_seconds": 2.0,
+///   "silences": [{ "start": 312.48, "end": 316.02 }]
+/// }
+/// ```
+///
+/// A frame is silent when every channel of the track is below the
+/// threshold, and only silences of at least the minimum duration are
+/// listed, so the short pauses within a piece are not.
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::audit;
+use crate::error::{Result, WaverError};
+use crate::scan::peak_abs;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The default level below which a frame is silent, in dBFS.
+pub const DEFAULT_THRESHOLD_DB: f32 = -60.0;
+
+/// The default shortest silence that is listed, in seconds.
+pub const DEFAULT_MIN_SECONDS: f64 = 2.0;
+
+/// What counts as a silence.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SilenceDetection {
+    /// The level below which a frame is silent, in dBFS
+    pub threshold_db: f32,
+    /// The shortest silence that is listed, in seconds
+    pub min_seconds: f64,
+}
+
+impl SilenceDetection {
+    /// Validates the threshold and the minimum duration.
+    pub fn new(threshold_db: f32, min_seconds: f64) -> Result<Self> {
+        if !threshold_db.is_finite() || threshold_db >= 0.0 {
+            return Err(WaverError::argument_error("The silence threshold must be a negative dB value"));
+        }
+        if !min_seconds.is_finite() || min_seconds <= 0.0 {
+            return Err(WaverError::argument_error("The shortest silence must be longer than zero"));
+        }
+        Ok(Self { threshold_db, min_seconds })
+    }
+}
+
+/// A silent part of the audio, in seconds from the start of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct Silence {
+    /// Where the silence starts
+    pub start: f64,
+    /// Where the silence ends
+    pub end: f64,
+}
+
+/// Finds the silences in streamed samples.
+#[derive(Debug, Clone)]
+pub struct SilenceDetector {
+    /// The least absolute sample that is not silent
+    level: f32,
+    /// The shortest silence that is listed, in frames
+    min_frames: u64,
+    sample_rate: u32,
+    /// The frame of the first sample pushed, from the start of the file
+    offset: u64,
+    /// Frames pushed so far
+    frames: u64,
+    /// The first frame of the silence the scan is in, if it is in one
+    silent_since: Option<u64>,
+    /// The listed silences, as frames
+    silences: Vec<(u64, u64)>,
+}
+
+impl SilenceDetector {
+    /// Creates the detector for audio at `sample_rate` Hz whose first frame
+    /// is `offset` frames into the file (the start of a time range).
+    pub fn new(detection: SilenceDetection, sample_rate: u32, offset: u64) -> Self {
+        Self {
+            level: 10f32.powf(detection.threshold_db / 20.0),
+            min_frames: (detection.min_seconds * sample_rate as f64).round().max(1.0) as u64,
+            sample_rate,
+            offset,
+            frames: 0,
+            silent_since: None,
+            silences: Vec::new(),
+        }
+    }
+
+    /// Scans the next frames, one slice per channel.
+    pub fn push(&mut self, planes: &[&[f32]]) {
+        let frames = planes.iter().map(|plane| plane.len()).min().unwrap_or(0);
+        if frames == 0 {
+            return;
+        }
+        let start = self.frames;
+        self.frames += frames as u64;
+
+        // Most packets are all sound or all silence
+        if planes.iter().all(|plane| peak_abs(&plane[..frames]) < self.level) {
+            self.silent_since.get_or_insert(start);
+            return;
+        }
+        for frame in 0..frames {
+            if planes.iter().all(|plane| plane[frame].abs() < self.level) {
+                self.silent_since.get_or_insert(start + frame as u64);
+            } else {
+                self.end_silence(start + frame as u64);
+            }
+        }
+    }
+
+    /// Lists the silence the scan is in, ending at the frame, if it is long
+    /// enough.
+    fn end_silence(&mut self, end: u64) {
+        if let Some(start) = self.silent_since.take() {
+            if end - start >= self.min_frames {
+                self.silences.push((start, end));
+            }
+        }
+    }
+
+    /// Returns the silences, including one that lasts to the end.
+    pub fn finish(mut self) -> Vec<Silence> {
+        self.end_silence(self.frames);
+        let seconds = |frame: u64| (self.offset + frame) as f64 / self.sample_rate.max(1) as f64;
+        self.silences.iter().map(|&(start, end)| Silence { start: seconds(start), end: seconds(end) }).collect()
+    }
+}
+
+/// The contents of a silence file.
+#[derive(Debug, Clone, Serialize)]
+pub struct SilenceMap {
+    /// Path of the audio file the silences were found in
+    pub source: String,
+    /// The level below which a frame is silent, in dBFS
+    pub threshold_db: f32,
+    /// The shortest silence that is listed, in seconds
+    pub min_duration_seconds: f64,
+    /// The silences, in the order of the audio
+    pub silences: Vec<Silence>,
+}
+
+impl SilenceMap {
+    /// Returns the path of the silence file for the given image path.
+    ///
+    /// The image extension is replaced with `.silence.json`, so
+    /// `song.mp3.png` gets the silence file `song.mp3.silence.json`.
+    pub fn path_for(image_path: &Path) -> PathBuf {
+        image_path.with_extension("silence.json")
+    }
+
+    /// Writes the silences as pretty printed JSON.
+    pub fn write(&self, writer: impl Write) -> Result<()> {
+        let mut writer = BufWriter::new(writer);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Saves the silences to the given path.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.write(audit::create(path)?)
+    }
+}
@@ -0,0 +1,57 @@
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::silence::{Silence, SilenceDetection, SilenceDetector, SilenceMap};
+
+    #[test]
+    fn test_silence_detection() {
+        assert!(SilenceDetection::new(-60.0, 2.0).is_ok());
+        assert!(SilenceDetection::new(0.0, 2.0).is_err(), "The threshold is below full scale");
+        assert!(SilenceDetection::new(-60.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_silences() {
+        // -40 dBFS is 0.01, at 10 Hz at least 2 seconds is 20 frames
+        let detection = SilenceDetection::new(-40.0, 2.0).unwrap();
+        let mut detector = SilenceDetector::new(detection, 10, 0);
+        let mut left = vec![0.5; 10];
+        left.extend([0.005; 30]);
+        let mut right = vec![0.0; 40];
+        // Too short: one channel is loud in the middle of it
+        right[15] = 0.5;
+        detector.push(&[&left, &right]);
+        // A whole packet of silence, then sound after 25 frames of it
+        detector.push(&[&[0.0; 10], &[0.0; 10]]);
+        detector.push(&[&[0.1; 5], &[0.0; 5]]);
+        // Silent to the end
+        detector.push(&[&[0.0; 25], &[0.0; 25]]);
+        assert_eq!(
+            detector.finish(),
+            [Silence { start: 1.6, end: 5.0 }, Silence { start: 5.5, end: 8.0 }]
+        );
+
+        // The frames of a time range are counted from the start of the file
+        let mut detector = SilenceDetector::new(detection, 10, 600);
+        detector.push(&[&[0.0; 30]]);
+        assert_eq!(detector.finish(), [Silence { start: 60.0, end: 63.0 }]);
+    }
+
+    #[test]
+    fn test_silence_map() {
+        assert_eq!(SilenceMap::path_for(Path::new("a/song.mp3.png")), Path::new("a/song.mp3.silence.json"));
+        let map = SilenceMap {
+            source: "concert.flac".to_string(),
+            threshold_db: -60.0,
+            min_duration_seconds: 2.0,
+            silences: vec![Silence { start: 312.5, end: 316.0 }],
+        };
+        let mut buffer = Vec::new();
+        map.write(&mut buffer).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json["threshold_db"], -60.0);
+        assert_eq!(json["silences"], serde_json::json!([{ "start": 312.5, "end": 316.0 }]));
+        assert!(buffer.ends_with(b"}\n"));
+    }
+}
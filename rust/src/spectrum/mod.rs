@@ -0,0 +1,299 @@
+/// Spectrograms: the frequency content of the audio over time as a heatmap.
+///
+/// The decoded (mono) samples are streamed into a `SpectrogramBuilder`,
+/// which keeps only the last `fft_size` samples.  Each pixel column is the
+/// spectrum of a Hann windowed block centered on the column, with the
+/// frequencies from 0 Hz at the bottom to half the sample rate at the top
+/// and the level in dB mapped through a colormap.
+use std::f32::consts::PI;
+use std::io::Write;
+use std::str::FromStr;
+
+use png::{Encoder, FilterType};
+
+use crate::error::{Result, WaverError};
+use crate::image::add_text_chunks;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The smallest FFT block, so low images still resolve the bass.
+const MIN_FFT_SIZE: usize = 512;
+
+/// Number of colors in a colormap (one 8-bit palette).
+const LEVELS: usize = 256;
+
+/// Which colors the spectrum levels are drawn with, from silence to full scale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Colormap {
+    /// Black through purple and orange to a pale yellow
+    #[default]
+    Magma,
+    /// Dark blue through green to yellow
+    Viridis,
+    /// Black to white
+    Gray,
+}
+
+impl FromStr for Colormap {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "magma" => Ok(Colormap::Magma),
+            "viridis" => Ok(Colormap::Viridis),
+            "gray" | "grey" => Ok(Colormap::Gray),
+            _ => Err(WaverError::argument_error("Colormap must be magma, viridis, or gray")),
+        }
+    }
+}
+
+impl Colormap {
+    /// Returns the RGB color stops, evenly spread from silence to full scale.
+    fn stops(&self) -> &'static [[u8; 3]] {
+        match self {
+            Colormap::Magma => &[
+                [0x00, 0x00, 0x04], [0x1c, 0x10, 0x44], [0x4f, 0x12, 0x7b], [0x81, 0x25, 0x81],
+                [0xb5, 0x36, 0x7a], [0xe5, 0x50, 0x64], [0xfb, 0x87, 0x61], [0xfe, 0xc2, 0x87],
+                [0xfc, 0xfd, 0xbf],
+            ],
+            Colormap::Viridis => &[
+                [0x44, 0x01, 0x54], [0x47, 0x2d, 0x7b], [0x3b, 0x52, 0x8b], [0x2c, 0x72, 0x8e],
+                [0x21, 0x91, 0x8c], [0x28, 0xae, 0x80], [0x5e, 0xc9, 0x62], [0xad, 0xdc, 0x30],
+                [0xfd, 0xe7, 0x25],
+            ],
+            Colormap::Gray => &[[0x00, 0x00, 0x00], [0xff, 0xff, 0xff]],
+        }
+    }
+
+    /// Returns the 256 entry RGB palette, interpolated between the stops.
+    pub fn palette(&self) -> Vec<u8> {
+        let stops = self.stops();
+        let spans = (stops.len() - 1) as f32;
+        (0..LEVELS)
+            .flat_map(|level| {
+                let position = level as f32 / (LEVELS - 1) as f32 * spans;
+                let stop = (position as usize).min(stops.len() - 2);
+                let fraction = position - stop as f32;
+                let (from, to) = (stops[stop], stops[stop + 1]);
+                (0..3).map(move |c| (from[c] as f32 + (to[c] as f32 - from[c] as f32) * fraction).round() as u8)
+            })
+            .collect()
+    }
+}
+
+/// A radix-2 fast Fourier transform of a fixed (power of two) size.
+pub struct Fft {
+    size: usize,
+    /// The cosine and sine of every twiddle factor, `size / 2` of each
+    cos: Vec<f32>,
+    sin: Vec<f32>,
+}
+
+impl Fft {
+    /// Creates the transform for blocks of `size` samples (a power of two).
+    pub fn new(size: usize) -> Self {
+        debug_assert!(size.is_power_of_two());
+        let angle = |k: usize| -2.0 * PI * k as f32 / size as f32;
+        Self {
+            size,
+            cos: (0..size / 2).map(|k| angle(k).cos()).collect(),
+            sin: (0..size / 2).map(|k| angle(k).sin()).collect(),
+        }
+    }
+
+    /// Transforms the complex block (`re`, `im`) in place.
+    pub fn transform(&self, re: &mut [f32], im: &mut [f32]) {
+        let n = self.size;
+        let bits = n.trailing_zeros();
+
+        // Put the samples in bit reversed order
+        for i in 0..n {
+            let j = i.reverse_bits() >> (usize::BITS - bits);
+            if i < j {
+                re.swap(i, j);
+                im.swap(i, j);
+            }
+        }
+
+        // Combine the transforms of doubling lengths
+        let mut len = 2;
+        while len <= n {
+            let step = n / len;
+            for start in (0..n).step_by(len) {
+                for k in 0..len / 2 {
+                    let (cos, sin) = (self.cos[k * step], self.sin[k * step]);
+                    let (a, b) = (start + k, start + k + len / 2);
+                    let t_re = re[b] * cos - im[b] * sin;
+                    let t_im = re[b] * sin + im[b] * cos;
+                    re[b] = re[a] - t_re;
+                    im[b] = im[a] - t_im;
+                    re[a] += t_re;
+                    im[a] += t_im;
+                }
+            }
+            len *= 2;
+        }
+    }
+}
+
+/// A spectrogram image: one 8-bit level per pixel, top row first.
+pub struct Spectrogram {
+    width: u32,
+    height: u32,
+    levels: Vec<u8>,
+}
+
+impl Spectrogram {
+    /// Returns the width in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the level (0 silent to 255 full scale) of the pixel at (`x`, `y`).
+    #[cfg(test)]
+    pub fn level(&self, x: u32, y: u32) -> u8 {
+        self.levels[(x + y * self.width) as usize]
+    }
+
+    /// Encodes the spectrogram as an 8-bit indexed PNG with the colormap as
+    /// its palette and the given keywords and texts as text chunks.
+    pub fn write_png(&self, colormap: Colormap, text: &[(&str, String)], writer: impl Write) -> Result<()> {
+        let mut encoder = Encoder::new(writer, self.width, self.height);
+        add_text_chunks(&mut encoder, text)?;
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(colormap.palette());
+        encoder.set_filter(FilterType::Up);
+        encoder.set_compression(png::Compression::Best);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.levels)?;
+        Ok(())
+    }
+}
+
+/// Builds a spectrogram from streamed samples.
+///
+/// Column `x` is analyzed as soon as the block centered on it has been seen,
+/// so the samples are never kept beyond one block.
+pub struct SpectrogramBuilder {
+    fft: Fft,
+    window: Vec<f32>,
+    /// The last `fft_size` samples, oldest at `next`
+    history: Vec<f32>,
+    next: usize,
+    /// Samples seen so far and the expected total
+    frames: u64,
+    total: u64,
+    /// The next column to analyze
+    column: u32,
+    /// The dB level drawn as silence (negative)
+    floor_db: f32,
+    re: Vec<f32>,
+    im: Vec<f32>,
+    image: Spectrogram,
+}
+
+impl SpectrogramBuilder {
+    /// Creates a builder for a `width` x `height` spectrogram of `total`
+    /// samples, with `floor_db` as the level drawn as silence.
+    ///
+    /// The FFT has at least two frequency bins for each row.
+    pub fn new(width: u32, height: u32, total: u64, floor_db: f32) -> Self {
+        let fft_size = (height as usize * 2).next_power_of_two().max(MIN_FFT_SIZE);
+        let window = (0..fft_size)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / fft_size as f32).cos())
+            .collect();
+        Self {
+            fft: Fft::new(fft_size),
+            window,
+            history: vec![0.0; fft_size],
+            next: 0,
+            frames: 0,
+            total: total.max(1),
+            column: 0,
+            floor_db,
+            re: vec![0.0; fft_size],
+            im: vec![0.0; fft_size],
+            image: Spectrogram {
+                width,
+                height,
+                levels: vec![0; width as usize * height as usize],
+            },
+        }
+    }
+
+    /// Returns the sample count at which the block of column `x` is complete.
+    ///
+    /// Columns near the start use the first whole block rather than one
+    /// padded with silence, which would smear the onset over all frequencies.
+    fn column_end(&self, x: u32) -> u64 {
+        let size = self.history.len() as u64;
+        let center = (2 * x as u64 + 1) * self.total / (2 * self.image.width as u64);
+        (center + size / 2).max(size)
+    }
+
+    /// Adds the next samples of the audio.
+    pub fn push(&mut self, samples: &[f32]) {
+        for &sample in samples {
+            self.history[self.next] = sample;
+            self.next = (self.next + 1) % self.history.len();
+            self.frames += 1;
+            while self.column < self.image.width && self.frames >= self.column_end(self.column) {
+                self.analyze();
+            }
+        }
+    }
+
+    /// Analyzes the remaining columns with the last block and returns the
+    /// finished spectrogram.
+    ///
+    /// Audio shorter than one block is padded with silence.
+    pub fn finish(mut self) -> Spectrogram {
+        let missing = (self.history.len() as u64).saturating_sub(self.frames);
+        for _ in 0..missing {
+            self.push(&[0.0]);
+        }
+        while self.column < self.image.width {
+            self.analyze();
+        }
+        self.image
+    }
+
+    /// Draws the spectrum of the last block into the next column.
+    fn analyze(&mut self) {
+        let size = self.history.len();
+        for i in 0..size {
+            self.re[i] = self.history[(self.next + i) % size] * self.window[i];
+            self.im[i] = 0.0;
+        }
+        self.fft.transform(&mut self.re, &mut self.im);
+
+        // A full scale sine has a magnitude of half the window sum
+        let scale = 2.0 / self.window.iter().sum::<f32>();
+        let bins = size / 2;
+        let height = self.image.height as usize;
+        let width = self.image.width as usize;
+        for row in 0..height {
+            // Each row shows the loudest of its bins, low frequencies at the bottom
+            let first = row * bins / height;
+            let last = ((row + 1) * bins / height).max(first + 1);
+            let magnitude = (first..last)
+                .map(|bin| (self.re[bin] * self.re[bin] + self.im[bin] * self.im[bin]).sqrt())
+                .fold(0.0f32, f32::max)
+                * scale;
+            let db = 20.0 * magnitude.max(f32::MIN_POSITIVE).log10();
+            let level = ((db - self.floor_db) / -self.floor_db).clamp(0.0, 1.0);
+            let y = height - 1 - row;
+            self.image.levels[self.column as usize + y * width] = (level * (LEVELS - 1) as f32).round() as u8;
+        }
+        self.column += 1;
+    }
+}
@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use crate::spectrum::{Colormap, Fft, SpectrogramBuilder};
+    use std::f32::consts::PI;
+    use std::str::FromStr;
+
+    fn sine(frequency: f32, sample_rate: f32, len: usize) -> Vec<f32> {
+        (0..len).map(|i| (2.0 * PI * frequency * i as f32 / sample_rate).sin()).collect()
+    }
+
+    #[test]
+    fn test_fft_matches_dft() {
+        let size = 16;
+        let samples: Vec<f32> = (0..size).map(|i| ((i * 7) % 5) as f32 - 2.0).collect();
+        let mut re = samples.clone();
+        let mut im = vec![0.0; size];
+        Fft::new(size).transform(&mut re, &mut im);
+
+        for k in 0..size {
+            let (mut dft_re, mut dft_im) = (0.0f32, 0.0f32);
+            for (n, sample) in samples.iter().enumerate() {
+                let angle = -2.0 * PI * (k * n) as f32 / size as f32;
+                dft_re += sample * angle.cos();
+                dft_im += sample * angle.sin();
+            }
+            assert!((re[k] - dft_re).abs() < 1e-3, "Real part of bin {k}");
+            assert!((im[k] - dft_im).abs() < 1e-3, "Imaginary part of bin {k}");
+        }
+    }
+
+    #[test]
+    fn test_colormap_parsing() {
+        assert_eq!(Colormap::from_str("magma").unwrap(), Colormap::Magma);
+        assert_eq!(Colormap::from_str(" Viridis ").unwrap(), Colormap::Viridis);
+        assert_eq!(Colormap::from_str("grey").unwrap(), Colormap::Gray);
+
+        let result = Colormap::from_str("rainbow");
+        assert_eq!(result.unwrap_err().to_string(), "Invalid argument: Colormap must be magma, viridis, or gray");
+    }
+
+    #[test]
+    fn test_colormap_palette() {
+        let gray = Colormap::Gray.palette();
+        assert_eq!(gray.len(), 256 * 3);
+        assert_eq!(&gray[..3], [0, 0, 0]);
+        assert_eq!(&gray[128 * 3..129 * 3], [128, 128, 128]);
+        assert_eq!(&gray[255 * 3..], [255, 255, 255]);
+
+        // The ends are the first and last stops
+        let magma = Colormap::Magma.palette();
+        assert_eq!(&magma[..3], [0x00, 0x00, 0x04]);
+        assert_eq!(&magma[255 * 3..], [0xfc, 0xfd, 0xbf]);
+    }
+
+    #[test]
+    fn test_sine_spectrogram() {
+        // A full scale 1 kHz sine at 8 kHz is a quarter of the way up
+        let (width, height) = (10, 32);
+        let samples = sine(1000.0, 8000.0, 8000);
+        let mut builder = SpectrogramBuilder::new(width, height, samples.len() as u64, -60.0);
+        for chunk in samples.chunks(300) {
+            builder.push(chunk);
+        }
+        let spectrogram = builder.finish();
+        assert_eq!((spectrogram.width(), spectrogram.height()), (10, 32));
+
+        let row = height - 1 - height / 4;
+        for x in 0..width {
+            assert!(spectrogram.level(x, row) >= 250, "Full scale at 1 kHz in column {x}");
+            assert!(spectrogram.level(x, 0) < 10, "Nothing near 4 kHz in column {x}");
+            assert!(spectrogram.level(x, height - 1) < 10, "Nothing near 0 Hz in column {x}");
+        }
+    }
+
+    #[test]
+    fn test_short_audio_fills_every_column() {
+        // Far fewer samples than one block still gives every column a spectrum
+        let samples = sine(1000.0, 8000.0, 100);
+        let mut builder = SpectrogramBuilder::new(64, 8, samples.len() as u64, -60.0);
+        builder.push(&samples);
+        let spectrogram = builder.finish();
+        assert!((0..64).all(|x| (0..8).any(|y| spectrogram.level(x, y) > 0)));
+    }
+}
@@ -0,0 +1,381 @@
+/// Machine-readable summary of a batch run (`--report`).
+///
+/// CI pipelines that generate the waveforms of a whole library need to know
+/// which files failed and which images were written without scraping the
+/// messages on stderr.  The summary lists every file of the batch with what
+/// became of it, the files it created and how long it took, along with the
+/// totals of the run.
+///
+/// The summary is made for every run, whether it is written or not: the
+/// error report of `--ignore-errors`, the closing message and the exit
+/// status are all taken from it, so they always agree.
+///
+/// The summary also records the options of the run, so `--retry-from` can
+/// read it back as a `PreviousRun` and process the failed files again the
+/// same way.
+///
+/// With `--run-id` the run has a `RunId`, which the summary, the error
+/// report, the audit log, the sidecars and the PNG text chunks all carry, so
+/// the files of one invocation can be found together later.  The ID is made
+/// on the machine from the time and a random number; nothing is sent
+/// anywhere.
+use std::fmt;
+use std::fs;
+use std::hash::{BuildHasher, Hasher, RandomState};
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::audio::Outcome;
+use crate::audit::utc_timestamp;
+use crate::error::{ErrorBudget, ExitStatus, Report, Result, WaverError};
+use crate::loudness::Loudness;
+use crate::quality::Quality;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// What became of one audio file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    /// The file has no audio track and was skipped (`--skip-non-audio`)
+    NoAudio,
+    /// The file is larger or longer than allowed and was skipped
+    /// (`--max-file-size` and `--max-duration`)
+    OverLimit,
+    /// The output already existed and was left alone
+    Skipped,
+    /// The output exists and is newer than the audio file
+    UpToDate,
+    /// The image was rendered but not written (dry run)
+    DryRun,
+    /// The image was written
+    Created,
+    /// The file could not be processed
+    Failed,
+}
+
+impl FileStatus {
+    /// Returns the status of a file with images of these outcomes (one,
+    /// or one per segment): the one that did the most work, or skipped if
+    /// there were none.
+    pub fn of(outcomes: impl IntoIterator<Item = Outcome>) -> Self {
+        outcomes.into_iter().map(Self::from).max().unwrap_or(FileStatus::Skipped)
+    }
+}
+
+impl From<Outcome> for FileStatus {
+    fn from(outcome: Outcome) -> Self {
+        match outcome {
+            Outcome::Created => FileStatus::Created,
+            Outcome::DryRun => FileStatus::DryRun,
+            Outcome::Skipped => FileStatus::Skipped,
+            Outcome::UpToDate => FileStatus::UpToDate,
+        }
+    }
+}
+
+/// The result of one audio file of the batch.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileSummary {
+    /// Path of the audio file
+    pub path: String,
+    /// What became of the file
+    pub status: FileStatus,
+    /// Why the file failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The files that were written for it
+    pub outputs: Vec<String>,
+    /// What was noticed about the file without failing it (like a
+    /// `--cross-check` discrepancy)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// The QC measures and score of the audio, with `--qc`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality: Option<Quality>,
+    /// The integrated loudness and true peak of the audio, with
+    /// `--measure-loudness`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loudness: Option<Loudness>,
+    /// The estimated tempo of the audio in BPM, with `--detect-bpm`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bpm: Option<f64>,
+    /// The image named after the audio content, with `--hash-names` (also
+    /// when it was there already)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// How long the file took, in seconds
+    pub seconds: f64,
+}
+
+/// The summary of a batch run.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunSummary {
+    /// Number of files in the batch
+    pub files: usize,
+    /// Files that were rendered (written, or not in a dry run)
+    pub processed: usize,
+    /// Files whose outputs already existed, or that had no audio to skip
+    pub skipped: usize,
+    /// Files that could not be processed
+    pub failed: usize,
+    /// Files that were not started because the run stopped at an error
+    pub not_started: usize,
+    /// Number of warnings of all files
+    #[serde(skip_serializing_if = "is_zero")]
+    pub warnings: usize,
+    /// Whether the run stopped at an error (`--fail-fast`)
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stopped: bool,
+    /// Wall clock time of the run, in seconds
+    pub seconds: f64,
+    /// The ID of the run (`--run-id`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<RunId>,
+    /// The options of the run as command line arguments, for `--retry-from`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<String>,
+    /// Every file that was started, in the order they finished
+    pub results: Vec<FileSummary>,
+}
+
+impl RunSummary {
+    /// Sums up the results of a batch of `files` files that took `elapsed`.
+    pub fn new(files: usize, results: Vec<FileSummary>, elapsed: Duration) -> Self {
+        let count = |pick: fn(FileStatus) -> bool| results.iter().filter(|result| pick(result.status)).count();
+        Self {
+            files,
+            processed: count(|status| matches!(status, FileStatus::Created | FileStatus::DryRun)),
+            skipped: count(|status| matches!(status, FileStatus::NoAudio | FileStatus::OverLimit | FileStatus::Skipped | FileStatus::UpToDate)),
+            failed: count(|status| status == FileStatus::Failed),
+            not_started: files.saturating_sub(results.len()),
+            warnings: results.iter().map(|result| result.warnings.len()).sum(),
+            stopped: false,
+            seconds: elapsed.as_secs_f64(),
+            run_id: None,
+            arguments: Vec::new(),
+            results,
+        }
+    }
+
+    /// Records whether the run stopped at an error, leaving the files it
+    /// had not started.
+    pub fn with_stopped(self, stopped: bool) -> Self {
+        Self { stopped, ..self }
+    }
+
+    /// Records the ID of the run.
+    pub fn with_run_id(self, run_id: Option<RunId>) -> Self {
+        Self { run_id, ..self }
+    }
+
+    /// Records the options of the run (see `WaverArgs::arguments`).
+    pub fn with_arguments(self, arguments: Vec<String>) -> Self {
+        Self { arguments, ..self }
+    }
+
+    /// Returns the number of files with an error.
+    ///
+    /// These are the failed files and, when the skipped outputs are
+    /// reported as errors (`--verbose` or `--strict`), the skipped ones,
+    /// and with `--strict` the files with warnings.
+    pub fn errors(&self) -> usize {
+        self.results.iter().filter(|result| result.error.is_some()).count()
+    }
+
+    /// Returns the errors of the run in the form of the `--ignore-errors`
+    /// report.
+    pub fn error_report(&self) -> Report {
+        let mut report = Report::new(self.files);
+        report.run_id = self.run_id.clone();
+        for result in &self.results {
+            if let Some(error) = &result.error {
+                report.push(result.path.clone(), error);
+            }
+        }
+        report
+    }
+
+    /// Returns true if the run fails: it stopped at an error, or more
+    /// files had errors than the budget allows.
+    pub fn is_failure(&self, budget: &ErrorBudget) -> bool {
+        self.stopped || !budget.allows(self.errors(), self.files)
+    }
+
+    /// Returns the exit status of the run: success unless it fails, and
+    /// otherwise whether some or all of its files had errors.
+    pub fn exit_status(&self, budget: &ErrorBudget) -> ExitStatus {
+        if !self.is_failure(budget) {
+            ExitStatus::Success
+        } else if self.errors() >= self.files {
+            ExitStatus::AllFilesFailed
+        } else {
+            ExitStatus::SomeFilesFailed
+        }
+    }
+
+    /// Returns the message the run ends with if there were errors: why it
+    /// fails, or that the errors were within the budget.
+    pub fn error_message(&self, budget: &ErrorBudget) -> Option<String> {
+        let errors = self.errors();
+        if errors == 0 {
+            return None;
+        }
+        let message = format!("{errors} errors occurred while processing files");
+        Some(if self.stopped {
+            let stopped_at = if *budget == ErrorBudget::default() {
+                "the first one"
+            } else {
+                "the first one over the allowed failures"
+            };
+            format!("{message}, stopped at {stopped_at}")
+        } else if !budget.allows(errors, self.files) {
+            message
+        } else {
+            format!("{message}, within the allowed failures")
+        })
+    }
+
+    /// Returns the totals as one line for the console.
+    pub fn totals(&self) -> String {
+        let mut totals = format!(
+            "{} of {} files processed, {} skipped, {} failed",
+            self.processed, self.files, self.skipped, self.failed
+        );
+        if self.not_started > 0 {
+            totals.push_str(&format!(", {} not started", self.not_started));
+        }
+        if self.warnings > 0 {
+            totals.push_str(&format!(", {} warnings", self.warnings));
+        }
+        totals.push_str(&format!(" in {:.1}s", self.seconds));
+        totals
+    }
+
+    /// Writes the summary as pretty printed JSON.
+    pub fn write_json(&self, mut writer: impl Write) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+}
+
+fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
+
+/// The ID of a run (`--run-id`), like `20261017T094107Z-5f3a9c2e`.
+///
+/// A generated ID is the UTC time the run started, so the IDs sort by time,
+/// and 32 random bits that keep the runs started in the same second apart.
+/// An ID given on the command line (say, the ID of a CI job) is kept as it
+/// is, if it is 1 to 64 letters, digits, `-`, `_` and `.`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RunId(String);
+
+impl RunId {
+    /// The longest ID that can be given.
+    pub const MAX_LENGTH: usize = 64;
+
+    /// Generates the ID of a run started now.
+    pub fn generate() -> Self {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        // A hasher with random keys is the random number of the standard
+        // library
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(now.as_nanos());
+        hasher.write_u32(std::process::id());
+        Self::from_parts(now.as_secs(), hasher.finish() as u32)
+    }
+
+    /// Returns the ID of a run started at the seconds since the Unix epoch
+    /// with the random bits.
+    pub fn from_parts(seconds: u64, random: u32) -> Self {
+        let time = utc_timestamp(seconds).replace(['-', ':'], "");
+        Self(format!("{time}-{random:08x}"))
+    }
+
+    /// Returns the ID as text.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for RunId {
+    type Err = WaverError;
+
+    fn from_str(id: &str) -> Result<Self> {
+        let valid = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.');
+        if id.is_empty() || id.len() > Self::MAX_LENGTH || !id.chars().all(valid) {
+            return Err(WaverError::argument_error(format!(
+                "A run ID must be 1 to {} letters, digits, '-', '_' and '.', not '{id}'",
+                Self::MAX_LENGTH
+            )));
+        }
+        Ok(Self(id.to_string()))
+    }
+}
+
+impl fmt::Display for RunId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// What `--retry-from` needs of the summary of an earlier run.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PreviousRun {
+    /// The options of the run as command line arguments
+    pub arguments: Vec<String>,
+    /// The files that failed, in the order they finished
+    pub failed: Vec<String>,
+}
+
+/// The parts of a written `RunSummary` that are read back.
+#[derive(Deserialize)]
+struct WrittenSummary {
+    #[serde(default)]
+    arguments: Vec<String>,
+    results: Vec<WrittenResult>,
+}
+
+#[derive(Deserialize)]
+struct WrittenResult {
+    path: String,
+    status: FileStatus,
+}
+
+impl PreviousRun {
+    /// Reads the `--report` summary of an earlier run.
+    pub fn read(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path).map_err(|e| {
+            WaverError::argument_error(format!("Cannot read the report {}: {e}", path.display()))
+        })?;
+        Self::from_json(&json)
+    }
+
+    /// Reads the run from the JSON of its summary.
+    ///
+    /// A summary written before the options were recorded has none, so its
+    /// files are processed with the options of the current command line.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let summary: WrittenSummary = serde_json::from_str(json)
+            .map_err(|e| WaverError::argument_error(format!("Not a report of waver --report: {e}")))?;
+        Ok(Self {
+            arguments: summary.arguments,
+            failed: summary
+                .results
+                .into_iter()
+                .filter(|result| result.status == FileStatus::Failed)
+                .map(|result| result.path)
+                .collect(),
+        })
+    }
+}
@@ -0,0 +1,221 @@
+#[cfg(test)]
+mod tests {
+    use crate::audio::Outcome;
+    use crate::error::{ErrorBudget, ExitStatus};
+    use crate::loudness::Loudness;
+    use crate::quality::Quality;
+    use crate::summary::{FileStatus, FileSummary, PreviousRun, RunId, RunSummary};
+    use std::time::Duration;
+
+    fn result(path: &str, status: FileStatus) -> FileSummary {
+        FileSummary {
+            path: path.to_string(),
+            status,
+            error: (status == FileStatus::Failed).then(|| "Broken".to_string()),
+            outputs: Vec::new(),
+            warnings: Vec::new(),
+            quality: None,
+            loudness: None,
+            bpm: None,
+            image: None,
+            seconds: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_file_status() {
+        assert_eq!(FileStatus::of([Outcome::Skipped, Outcome::Created]), FileStatus::Created);
+        assert_eq!(FileStatus::of([Outcome::UpToDate, Outcome::Skipped]), FileStatus::UpToDate);
+        assert_eq!(FileStatus::of([Outcome::DryRun]), FileStatus::DryRun);
+        assert_eq!(FileStatus::of([]), FileStatus::Skipped);
+    }
+
+    #[test]
+    fn test_totals() {
+        let results = vec![
+            result("a.mp3", FileStatus::Created),
+            result("b.mp3", FileStatus::UpToDate),
+            result("c.mp3", FileStatus::Failed),
+            result("d.mp3", FileStatus::DryRun),
+            result("e.mkv", FileStatus::NoAudio),
+        ];
+        let summary = RunSummary::new(7, results, Duration::from_millis(1500));
+        assert_eq!(
+            (summary.processed, summary.skipped, summary.failed, summary.not_started),
+            (2, 2, 1, 2)
+        );
+        assert_eq!(summary.errors(), 1, "A file without audio is not an error");
+        assert_eq!(summary.seconds, 1.5);
+    }
+
+    #[test]
+    fn test_summary_json() {
+        let mut created = result("a.mp3", FileStatus::Created);
+        created.outputs.push("a.mp3.png".to_string());
+        created.quality = Some(Quality {
+            score: 12.5,
+            clipped_samples: 50,
+            silence_seconds: 0.0,
+            longest_silence_seconds: 0.0,
+            dc_offset: 0.0,
+            imbalance_db: 0.0,
+        });
+        created.bpm = Some(128.0);
+        created.loudness = Some(Loudness { integrated_lufs: Some(-14.0), true_peak_dbtp: Some(-0.5) });
+        let summary = RunSummary::new(2, vec![created, result("b.mp3", FileStatus::Failed)], Duration::ZERO);
+        let mut buffer = Vec::new();
+        summary.write_json(&mut buffer).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json["failed"], 1);
+        assert_eq!(json["results"][0]["status"], "created");
+        assert_eq!(json["results"][0]["outputs"][0], "a.mp3.png");
+        assert_eq!(json["results"][0]["quality"]["score"], 12.5);
+        assert!(json["results"][1].get("quality").is_none(), "Only scored with --qc");
+        assert_eq!(json["results"][0]["loudness"]["integrated_lufs"], -14.0);
+        assert!(json["results"][1].get("loudness").is_none(), "Only measured with --measure-loudness");
+        assert_eq!(json["results"][0]["bpm"], 128.0);
+        assert!(json["results"][1].get("bpm").is_none(), "Only detected with --detect-bpm");
+        assert!(json["results"][0].get("error").is_none(), "Only failures have an error");
+        assert_eq!(json["results"][1]["error"], "Broken");
+        assert!(json.get("stopped").is_none(), "Only written when the run stopped");
+        assert!(json.get("warnings").is_none());
+        assert!(json["results"][0].get("warnings").is_none());
+        assert!(json.get("run_id").is_none(), "Only written with --run-id");
+    }
+
+    #[test]
+    fn test_run_id() {
+        assert_eq!(RunId::from_parts(1_792_230_067, 0x5f3a9c2e).as_str(), "20261017T094107Z-5f3a9c2e");
+        let generated = RunId::generate();
+        assert_eq!(generated.as_str().len(), 25);
+        assert_ne!(generated, RunId::generate(), "Runs in the same second differ");
+        assert_eq!("ci-job_1234.5".parse::<RunId>().unwrap().to_string(), "ci-job_1234.5");
+        for invalid in ["", "two words", "a/b", &"x".repeat(RunId::MAX_LENGTH + 1)] {
+            assert!(invalid.parse::<RunId>().is_err(), "{invalid:?}");
+        }
+
+        let run_id: RunId = "nightly-42".parse().unwrap();
+        let summary = RunSummary::new(1, vec![result("a.mp3", FileStatus::Failed)], Duration::ZERO)
+            .with_run_id(Some(run_id.clone()));
+        let mut buffer = Vec::new();
+        summary.write_json(&mut buffer).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json["run_id"], "nightly-42");
+        assert_eq!(summary.error_report().run_id, Some(run_id), "The error report has the ID too");
+    }
+
+    #[test]
+    fn test_errors_of_the_run() {
+        let mut skipped = result("c.mp3", FileStatus::Skipped);
+        skipped.error = Some("Exists".to_string());
+        let results = vec![result("a.mp3", FileStatus::Created), result("b.mp3", FileStatus::Failed), skipped];
+        let summary = RunSummary::new(4, results, Duration::ZERO);
+        assert_eq!((summary.errors(), summary.failed), (2, 1), "Skipped files may count as errors");
+
+        let report = summary.error_report();
+        assert_eq!(report.files, 4);
+        let paths: Vec<&str> = report.errors.iter().map(|error| error.path.as_str()).collect();
+        assert_eq!(paths, ["b.mp3", "c.mp3"]);
+        assert_eq!(report.errors[1].error, "Exists");
+    }
+
+    #[test]
+    fn test_failure_and_message() {
+        let none = ErrorBudget::default();
+        let two = ErrorBudget { max_failures: Some(2), ..ErrorBudget::default() };
+        let ok = RunSummary::new(2, vec![result("a.mp3", FileStatus::Created)], Duration::ZERO);
+        assert!(!ok.is_failure(&none));
+        assert_eq!(ok.error_message(&none), None);
+
+        let failed = RunSummary::new(3, vec![result("a.mp3", FileStatus::Failed)], Duration::ZERO);
+        assert!(failed.is_failure(&none));
+        assert_eq!(failed.error_message(&none).unwrap(), "1 errors occurred while processing files");
+        assert!(!failed.is_failure(&two));
+        assert_eq!(
+            failed.error_message(&two).unwrap(),
+            "1 errors occurred while processing files, within the allowed failures"
+        );
+
+        let stopped = failed.with_stopped(true);
+        assert!(stopped.is_failure(&two), "A stopped run fails");
+        assert_eq!(
+            stopped.error_message(&none).unwrap(),
+            "1 errors occurred while processing files, stopped at the first one"
+        );
+        assert!(stopped.error_message(&two).unwrap().ends_with("the first one over the allowed failures"));
+    }
+
+    #[test]
+    fn test_exit_status() {
+        let none = ErrorBudget::default();
+        let two = ErrorBudget { max_failures: Some(2), ..ErrorBudget::default() };
+        let ok = RunSummary::new(2, vec![result("a.mp3", FileStatus::Skipped)], Duration::ZERO);
+        assert_eq!(ok.exit_status(&none), ExitStatus::Success);
+
+        let results = vec![result("a.mp3", FileStatus::Created), result("b.mp3", FileStatus::Failed)];
+        let some = RunSummary::new(2, results, Duration::ZERO);
+        assert_eq!(some.exit_status(&none), ExitStatus::SomeFilesFailed);
+        assert_eq!(some.exit_status(&two), ExitStatus::Success, "Allowed failures succeed");
+
+        let results = vec![result("a.mp3", FileStatus::Failed), result("b.mp3", FileStatus::Failed)];
+        let all = RunSummary::new(2, results, Duration::ZERO);
+        assert_eq!(all.exit_status(&none), ExitStatus::AllFilesFailed);
+        assert_eq!(all.exit_status(&none).code(), 3);
+    }
+
+    #[test]
+    fn test_totals_line() {
+        let results = vec![result("a.mp3", FileStatus::Created), result("b.mp3", FileStatus::Failed)];
+        let summary = RunSummary::new(2, results.clone(), Duration::from_millis(2500));
+        assert_eq!(summary.totals(), "1 of 2 files processed, 0 skipped, 1 failed in 2.5s");
+        let summary = RunSummary::new(4, results, Duration::ZERO).with_stopped(true);
+        assert_eq!(summary.totals(), "1 of 4 files processed, 0 skipped, 1 failed, 2 not started in 0.0s");
+
+        let mut buffer = Vec::new();
+        summary.write_json(&mut buffer).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json["stopped"], true);
+    }
+
+    #[test]
+    fn test_warnings() {
+        let mut warned = result("a.mp3", FileStatus::Created);
+        warned.warnings = vec!["Decoders differ".to_string(), "Again".to_string()];
+        let summary = RunSummary::new(2, vec![warned, result("b.mp3", FileStatus::Created)], Duration::ZERO);
+        assert_eq!(summary.warnings, 2);
+        assert_eq!(summary.errors(), 0, "Warnings do not fail the file");
+        assert!(summary.totals().ends_with("0 failed, 2 warnings in 0.0s"));
+
+        let mut buffer = Vec::new();
+        summary.write_json(&mut buffer).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json["warnings"], 2);
+        assert_eq!(json["results"][0]["warnings"][0], "Decoders differ");
+    }
+
+    #[test]
+    fn test_previous_run() {
+        let results = vec![
+            result("a.mp3", FileStatus::Created),
+            result("b.mp3", FileStatus::Failed),
+            result("c.mp3", FileStatus::Skipped),
+            result("d.mp3", FileStatus::Failed),
+        ];
+        let arguments = vec!["--width=512".to_string(), "--sidecar".to_string()];
+        let summary = RunSummary::new(4, results, Duration::ZERO).with_arguments(arguments.clone());
+        let mut buffer = Vec::new();
+        summary.write_json(&mut buffer).unwrap();
+
+        let previous = PreviousRun::from_json(std::str::from_utf8(&buffer).unwrap()).unwrap();
+        assert_eq!(previous.arguments, arguments);
+        assert_eq!(previous.failed, ["b.mp3", "d.mp3"]);
+
+        // Without recorded options
+        let previous = PreviousRun::from_json(r#"{"results": [{"path": "e.mp3", "status": "failed"}]}"#).unwrap();
+        assert_eq!(previous, PreviousRun { arguments: Vec::new(), failed: vec!["e.mp3".to_string()] });
+
+        let error = PreviousRun::from_json(r#"{"files": 3, "errors": []}"#).unwrap_err().to_string();
+        assert!(error.starts_with("Invalid argument: Not a report of waver --report"), "{error}");
+    }
+}
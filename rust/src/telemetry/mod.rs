@@ -0,0 +1,133 @@
+/// Tracing of the pipeline stages and their export.
+///
+/// The stages are recorded as `tracing` spans: `discover` while collecting
+/// the files of a batch, then a `file` span for every audio file with its
+/// `decode`, `render` and `write` stages (the images are encoded while they
+/// are written, in nested `encode` spans).  Nothing is recorded unless a
+/// subscriber is installed with `init`:
+///
+/// - the messages of the run are printed by the layer of its `Logger`
+/// - `--trace` prints every span with its timing to stderr, filtered with
+///   the `WAVER_LOG` variable (like `WAVER_LOG=waver=debug` for the events
+///   within the spans as well), but not the messages, which are printed
+///   already
+/// - `--otlp-endpoint` exports the spans to a tracing backend with
+///   OTLP over HTTP, in builds with the `otlp` feature
+///
+/// Library users can install their own subscriber to see the same spans.
+use std::io::IsTerminal;
+
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::error::{Result, WaverError};
+use crate::output::{LogLevel, Logger};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The environment variable with the filter of the spans printed by
+/// `--trace`.
+pub const FILTER_ENV: &str = "WAVER_LOG";
+
+/// The filter used when `WAVER_LOG` is not set: the spans of the pipeline.
+const DEFAULT_FILTER: &str = "waver=info";
+
+/// The directive that keeps the messages of the run out of `--trace`.
+const MESSAGES_OFF: &str = "waver::output=off";
+
+/// Keeps the span export running while the program runs.
+///
+/// Dropping it sends the spans that are still queued.
+#[derive(Default)]
+pub struct Telemetry {
+    #[cfg(feature = "otlp")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.provider.take() {
+            // The run is over either way, so a backend that is gone is ignored
+            let _ = provider.shutdown();
+        }
+    }
+}
+
+/// Installs the subscriber that prints the messages of the run with the
+/// `logger`, and prints (`trace`) and exports (to the `otlp_endpoint`, like
+/// `http://localhost:4318/v1/traces`) the spans.
+///
+/// Does nothing if none of them is asked for, so the spans cost next to
+/// nothing.
+pub fn init(logger: &Logger, trace: bool, otlp_endpoint: Option<&str>) -> Result<Telemetry> {
+    if !logger.enabled(LogLevel::Error) && !trace && otlp_endpoint.is_none() {
+        return Ok(Telemetry::default());
+    }
+
+    let stderr = trace.then(|| {
+        let filter = EnvFilter::try_from_env(FILTER_ENV)
+            .unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER))
+            .add_directive(MESSAGES_OFF.parse().expect("The directive is valid"));
+        tracing_subscriber::fmt::layer()
+            .with_writer(std::io::stderr)
+            .with_ansi(std::io::stderr().is_terminal())
+            .with_span_events(FmtSpan::CLOSE)
+            .with_filter(filter)
+    });
+    let registry = tracing_subscriber::registry().with(logger.layer()).with(stderr);
+
+    #[cfg(feature = "otlp")]
+    {
+        let provider = otlp_endpoint.map(otlp_provider).transpose()?;
+        let export = provider.as_ref().map(|provider| {
+            use opentelemetry::trace::TracerProvider;
+            tracing_opentelemetry::layer()
+                .with_tracer(provider.tracer("waver"))
+                .with_filter(EnvFilter::new(DEFAULT_FILTER))
+        });
+        registry.with(export).try_init().map_err(subscriber_error)?;
+        Ok(Telemetry { provider })
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    {
+        if otlp_endpoint.is_some() {
+            return Err(WaverError::argument_error(
+                "Exporting spans with OTLP needs waver built with the otlp feature",
+            ));
+        }
+        registry.try_init().map_err(subscriber_error)?;
+        Ok(Telemetry::default())
+    }
+}
+
+/// Returns the error for a subscriber that could not be installed.
+fn subscriber_error(error: impl std::fmt::Display) -> WaverError {
+    WaverError::generation_error(format!("Could not start tracing: {error}"))
+}
+
+/// Sets up the export of the spans to an OTLP/HTTP endpoint.
+///
+/// The spans are sent in batches from a background thread, so the export
+/// does not hold up the decoding.
+#[cfg(feature = "otlp")]
+fn otlp_provider(endpoint: &str) -> Result<opentelemetry_sdk::trace::SdkTracerProvider> {
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| WaverError::argument_error(format!("Invalid OTLP endpoint: {e}")))?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name("waver").build())
+        .build())
+}
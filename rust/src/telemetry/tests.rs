@@ -0,0 +1,23 @@
+#[cfg(test)]
+mod tests {
+    use crate::output::{LogLevel, Logger};
+    use crate::telemetry::init;
+
+    #[test]
+    fn test_nothing_to_record() {
+        // No subscriber is installed, so a later one still can be
+        let quiet = Logger::new(LogLevel::Off, false, false);
+        assert!(init(&quiet, false, None).is_ok());
+        assert!(init(&quiet, false, None).is_ok());
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    #[test]
+    fn test_otlp_needs_feature() {
+        let result = init(&Logger::default(), false, Some("http://localhost:4318/v1/traces"));
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Invalid argument: Exporting spans with OTLP needs waver built with the otlp feature"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+/// Estimating the tempo of the audio (`--detect-bpm`).
+///
+/// DJ software and playlist tools sort by tempo, and waver already decodes
+/// every sample to draw the image, so the `TempoDetector` follows the same
+/// decode:
+///
+/// * The channels are mixed and their energy taken every 5 ms.
+/// * Where the energy rises (in dB) is where notes and beats start, so the
+///   rises make the onset strength, and the falls count for nothing.
+/// * The onset strength repeats at the beat, so its autocorrelation peaks
+///   at the lag of one beat.  The lag with the strongest peak between 60 and
+///   200 BPM is the tempo, weighted towards 120 BPM as music is rarely far
+///   from it, which keeps half and double the tempo from winning.
+///
+/// The estimate is of the whole file, so music whose tempo changes gets the
+/// one that is there the most.  Audio without enough onsets (silence, or
+/// less than a few beats) has no tempo.
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The onset strength is taken this many times a second.
+const ONSET_RATE: u32 = 200;
+
+/// The slowest and the fastest tempo that is found, in BPM.
+const MIN_BPM: f64 = 60.0;
+const MAX_BPM: f64 = 200.0;
+
+/// The tempo the estimate is weighted towards, and how quickly the weight
+/// falls off, in octaves.
+const PREFERRED_BPM: f64 = 120.0;
+const PREFERENCE_OCTAVES: f64 = 1.0;
+
+/// The onset strength must cover at least this many of the slowest beats.
+const MIN_BEATS: usize = 4;
+
+/// Estimates the tempo of streamed samples.
+#[derive(Debug, Clone)]
+pub struct TempoDetector {
+    /// Frames of a step of the onset strength
+    hop: usize,
+    /// Steps of the onset strength per second
+    rate: f64,
+    /// Frames of the current step so far, and the sum of their squares
+    filled: usize,
+    energy: f64,
+    /// The level of the previous step, in dB
+    previous: Option<f64>,
+    /// The onset strength of every step
+    onsets: Vec<f32>,
+}
+
+impl TempoDetector {
+    /// Creates the detector for audio at `sample_rate` Hz.
+    pub fn new(sample_rate: u32) -> Self {
+        let hop = (sample_rate / ONSET_RATE).max(1) as usize;
+        Self {
+            hop,
+            rate: sample_rate as f64 / hop as f64,
+            filled: 0,
+            energy: 0.0,
+            previous: None,
+            onsets: Vec::new(),
+        }
+    }
+
+    /// Scans the next frames, one slice per channel.
+    pub fn push(&mut self, planes: &[&[f32]]) {
+        let frames = planes.iter().map(|plane| plane.len()).min().unwrap_or(0);
+        let channels = planes.len().max(1) as f32;
+        for frame in 0..frames {
+            let mixed = planes.iter().map(|plane| plane[frame]).sum::<f32>() / channels;
+            self.energy += (mixed * mixed) as f64;
+            self.filled += 1;
+            if self.filled == self.hop {
+                self.step();
+            }
+        }
+    }
+
+    /// Turns the energy of a finished step into its onset strength.
+    fn step(&mut self) {
+        let level = 10.0 * (self.energy / self.hop as f64 + 1e-10).log10();
+        let rise = self.previous.map_or(0.0, |previous| (level - previous).max(0.0));
+        self.onsets.push(rise as f32);
+        self.previous = Some(level);
+        self.filled = 0;
+        self.energy = 0.0;
+    }
+
+    /// Returns the estimated tempo in BPM, or `None` if the audio has no
+    /// beat to find.
+    pub fn finish(&self) -> Option<f64> {
+        let min_lag = (60.0 * self.rate / MAX_BPM).floor() as usize;
+        let max_lag = (60.0 * self.rate / MIN_BPM).ceil() as usize;
+        if min_lag < 2 || self.onsets.len() < max_lag * MIN_BEATS {
+            return None;
+        }
+
+        // Without its mean the autocorrelation only peaks where the onsets
+        // repeat
+        let mean = self.onsets.iter().map(|&onset| onset as f64).sum::<f64>() / self.onsets.len() as f64;
+        let onsets: Vec<f64> = self.onsets.iter().map(|&onset| onset as f64 - mean).collect();
+        let correlation = |lag: usize| {
+            let sum: f64 = onsets.iter().zip(&onsets[lag..]).map(|(a, b)| a * b).sum();
+            sum / (onsets.len() - lag) as f64
+        };
+        let correlations: Vec<f64> = (min_lag - 1..=max_lag + 1).map(correlation).collect();
+
+        // The strongest lag, weighted towards the preferred tempo
+        let bpm = |lag: f64| 60.0 * self.rate / lag;
+        let weight = |lag: usize| {
+            let octaves = (bpm(lag as f64) / PREFERRED_BPM).log2() / PREFERENCE_OCTAVES;
+            (-0.5 * octaves * octaves).exp()
+        };
+        let (index, strongest) = (1..correlations.len() - 1)
+            .map(|index| (index, correlations[index] * weight(min_lag - 1 + index)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+        if strongest <= 0.0 {
+            return None;
+        }
+
+        // The beat falls between two steps, so the peak is placed on the
+        // parabola through the lag and its neighbours
+        let (before, peak, after) = (correlations[index - 1], correlations[index], correlations[index + 1]);
+        let curve = before - 2.0 * peak + after;
+        let shift = if curve < 0.0 { (0.5 * (before - after) / curve).clamp(-0.5, 0.5) } else { 0.0 };
+        Some(bpm((min_lag - 1 + index) as f64 + shift))
+    }
+}
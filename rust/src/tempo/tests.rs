@@ -0,0 +1,47 @@
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::tempo::TempoDetector;
+
+    /// Returns a click track: a short burst of 1 kHz at every beat.
+    fn clicks(bpm: f64, sample_rate: u32, seconds: f64) -> Vec<f32> {
+        let beat = 60.0 / bpm * sample_rate as f64;
+        let click = sample_rate as usize / 50;
+        let frames = (seconds * sample_rate as f64) as usize;
+        (0..frames)
+            .map(|frame| {
+                let into_beat = frame as f64 % beat;
+                if into_beat < click as f64 {
+                    0.8 * (2.0 * PI * 1000.0 * frame as f32 / sample_rate as f32).sin()
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_click_tracks() {
+        for (bpm, sample_rate) in [(120.0, 48_000), (90.0, 44_100), (174.0, 44_100)] {
+            let track = clicks(bpm, sample_rate, 20.0);
+            let mut detector = TempoDetector::new(sample_rate);
+            for packet in track.chunks(1152) {
+                detector.push(&[packet, packet]);
+            }
+            let estimate = detector.finish().unwrap();
+            assert!((estimate - bpm).abs() < 1.0, "{estimate} for {bpm}");
+        }
+    }
+
+    #[test]
+    fn test_no_tempo() {
+        let mut detector = TempoDetector::new(48_000);
+        detector.push(&[&vec![0.0; 48_000 * 10]]);
+        assert_eq!(detector.finish(), None, "Silence has no beat");
+
+        let mut detector = TempoDetector::new(48_000);
+        detector.push(&[&clicks(120.0, 48_000, 2.0)]);
+        assert_eq!(detector.finish(), None, "Too short for a few beats");
+    }
+}
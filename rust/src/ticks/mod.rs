@@ -0,0 +1,110 @@
+/// Tick marks every interval of the audio and their pixel positions.
+///
+/// With `--ticks` short marks are drawn along the baseline of the waveform
+/// at every interval of the audio, and with `--ticks-json` the column of
+/// each mark is written to a small JSON file next to the image.  A player
+/// can line up a time ruler with the image from it without knowing how the
+/// times map to pixels (the time range, the rounding of the columns).
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::audit;
+use crate::error::Result;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// Where the first of the ticks every `interval` seconds is, and how far
+/// apart they are, in the image of the rendered audio.
+///
+/// The ticks are on whole multiples of the interval in the audio, so with a
+/// time range they stay on the same times as in the whole track.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TickSpacing {
+    /// Time of the first tick in the audio, in seconds
+    pub first_seconds: f64,
+    /// Column of the first tick (not rounded)
+    pub first_x: f64,
+    /// Seconds between the ticks
+    pub interval: f64,
+    /// Pixels between the ticks (not rounded)
+    pub spacing: f64,
+}
+
+impl TickSpacing {
+    /// Returns the spacing of ticks every `interval` seconds in an image
+    /// `width` pixels wide of `duration` seconds of audio from `start`.
+    pub fn new(interval: f64, start: f64, duration: f64, width: u32) -> Self {
+        let pixels_per_second = width as f64 / duration;
+        let first_seconds = (start / interval).ceil() * interval;
+        Self {
+            first_seconds,
+            first_x: (first_seconds - start) * pixels_per_second,
+            interval,
+            spacing: interval * pixels_per_second,
+        }
+    }
+}
+
+/// One tick mark.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Tick {
+    /// Time of the tick in the audio, in seconds (to the millisecond)
+    pub seconds: f64,
+    /// Column of the tick mark in the image
+    pub x: u32,
+}
+
+/// The contents of a tick map file.
+#[derive(Debug, Clone, Serialize)]
+pub struct TickMap {
+    /// Path of the audio file the image was made from
+    pub source: String,
+    /// Width of the image in pixels
+    pub width: u32,
+    /// Seconds between the ticks
+    pub interval_seconds: f64,
+    /// The ticks from left to right
+    pub ticks: Vec<Tick>,
+}
+
+impl TickMap {
+    /// Returns the path of the tick map for the given image path.
+    ///
+    /// The image extension is replaced with `.ticks.json`, so `song.mp3.png`
+    /// gets the tick map `song.mp3.ticks.json`.
+    pub fn path_for(image_path: &Path) -> PathBuf {
+        image_path.with_extension("ticks.json")
+    }
+
+    /// Creates the tick map from the spacing and the columns the marks were
+    /// drawn at, which are every tick from the first in order.
+    pub fn new(source: String, width: u32, spacing: &TickSpacing, columns: &[u32]) -> Self {
+        let ticks = columns
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| {
+                let seconds = spacing.first_seconds + i as f64 * spacing.interval;
+                Tick { seconds: (seconds * 1000.0).round() / 1000.0, x }
+            })
+            .collect();
+        Self { source, width, interval_seconds: spacing.interval, ticks }
+    }
+
+    /// Writes the tick map as compact JSON.
+    pub fn write(&self, writer: impl Write) -> Result<()> {
+        let mut writer = BufWriter::new(writer);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Saves the tick map to the given path.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.write(audit::create(path)?)
+    }
+}
@@ -0,0 +1,38 @@
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::ticks::{Tick, TickMap, TickSpacing};
+
+    #[test]
+    fn test_spacing() {
+        // Ten minutes over 600 pixels, a tick every 30 seconds
+        let spacing = TickSpacing::new(30.0, 0.0, 600.0, 600);
+        assert_eq!(spacing, TickSpacing { first_seconds: 0.0, first_x: 0.0, interval: 30.0, spacing: 30.0 });
+
+        // A range from 1:45 keeps the ticks on the times of the whole track
+        let spacing = TickSpacing::new(30.0, 105.0, 60.0, 120);
+        assert_eq!(spacing.first_seconds, 120.0);
+        assert_eq!(spacing.first_x, 30.0);
+        assert_eq!(spacing.spacing, 60.0);
+    }
+
+    #[test]
+    fn test_tick_map() {
+        let spacing = TickSpacing::new(0.1, 0.0, 1.0, 50);
+        let map = TickMap::new("song.mp3".to_string(), 50, &spacing, &[0, 5, 10, 15]);
+        assert_eq!(map.ticks[3], Tick { seconds: 0.3, x: 15 }, "Seconds to the millisecond");
+
+        let mut json = Vec::new();
+        map.write(&mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json["width"], 50);
+        assert_eq!(json["interval_seconds"], 0.1);
+        assert_eq!(json["ticks"][1], serde_json::json!({ "seconds": 0.1, "x": 5 }));
+    }
+
+    #[test]
+    fn test_path_for() {
+        assert_eq!(TickMap::path_for(Path::new("music/song.mp3.png")), Path::new("music/song.mp3.ticks.json"));
+    }
+}
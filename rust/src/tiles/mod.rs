@@ -0,0 +1,116 @@
+/// Waveform tiles for zoomable players.
+///
+/// In `--tiles` mode a high resolution waveform is cut into fixed width tile
+/// PNGs next to an `index.json` manifest, similar to map tiles.  A player
+/// can then load just the tiles of the visible part of a very long
+/// recording instead of one huge image.
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::audit;
+use crate::color::Rgba;
+use crate::error::Result;
+use crate::image::WaveImage;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// The file name of the manifest in the tiles directory.
+pub const MANIFEST_NAME: &str = "index.json";
+
+/// One tile as listed in the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Tile {
+    /// File name of the tile PNG, relative to the manifest
+    pub file: String,
+    /// First column of the full waveform in this tile
+    pub x: u32,
+    /// Width of the tile in pixels (the last tile may be narrower)
+    pub width: u32,
+}
+
+/// The contents of the tiles manifest.
+#[derive(Debug, Clone, Serialize)]
+pub struct Manifest {
+    /// Path of the audio file the tiles were rendered from
+    pub source: String,
+    /// Width of the full waveform in pixels
+    pub width: u32,
+    /// Height of the tiles in pixels
+    pub height: u32,
+    /// Width of each tile in pixels (except maybe the last)
+    pub tile_width: u32,
+    /// Duration of the audio in seconds
+    pub duration_seconds: f64,
+    /// The tiles from left to right
+    pub tiles: Vec<Tile>,
+}
+
+impl Manifest {
+    /// Writes the manifest as pretty printed JSON.
+    pub fn write(&self, mut writer: impl Write) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    /// Writes the manifest to the given file, atomically like the images
+    /// (an existing manifest is what makes a run skip the tiles).
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut out = audit::create_atomic(path)?;
+        self.write(&mut out)?;
+        out.commit()?;
+        Ok(())
+    }
+}
+
+/// Returns the directory the tiles of an image go into.
+///
+/// The image extension is replaced by `.tiles`, so `song.mp3.png` gets its
+/// tiles in `song.mp3.tiles/`.
+pub fn tiles_dir(image_path: &Path) -> PathBuf {
+    image_path.with_extension("tiles")
+}
+
+/// Returns the path of the manifest of the tiles of an image.
+pub fn manifest_path(image_path: &Path) -> PathBuf {
+    tiles_dir(image_path).join(MANIFEST_NAME)
+}
+
+/// Returns the tiles covering a waveform of the given width.
+pub fn layout(width: u32, tile_width: u32) -> Vec<Tile> {
+    (0..width.div_ceil(tile_width))
+        .map(|index| {
+            let x = index * tile_width;
+            Tile {
+                file: format!("{index:04}.png"),
+                x,
+                width: tile_width.min(width - x),
+            }
+        })
+        .collect()
+}
+
+/// Cuts the image into tiles and saves them as PNGs in `dir`.
+///
+/// Returns the tiles written, for the manifest.
+pub fn save_tiles(
+    image: &WaveImage,
+    tile_width: u32,
+    background: &Rgba,
+    left: &Rgba,
+    right: &Rgba,
+    dir: &Path,
+) -> Result<Vec<Tile>> {
+    audit::create_dir_all(dir)?;
+    let tiles = layout(image.width(), tile_width);
+    for tile in &tiles {
+        image
+            .tile(tile.x, tile.width)
+            .save_png(background, left, right, dir.join(&tile.file))?;
+    }
+    Ok(tiles)
+}
@@ -0,0 +1,46 @@
+#[cfg(test)]
+mod tests {
+    use crate::cli::{Height, Width};
+    use crate::color::Rgba;
+    use crate::image::WaveImage;
+    use crate::tiles::{layout, manifest_path, save_tiles, tiles_dir, Tile};
+    use std::path::Path;
+
+    #[test]
+    fn test_tiles_paths() {
+        assert_eq!(tiles_dir(Path::new("a/song.mp3.png")), Path::new("a/song.mp3.tiles"));
+        assert_eq!(manifest_path(Path::new("wave.png")), Path::new("wave.tiles/index.json"));
+    }
+
+    #[test]
+    fn test_layout() {
+        let tiles = layout(1200, 512);
+        assert_eq!(tiles.len(), 3);
+        assert_eq!(tiles[0], Tile { file: "0000.png".to_string(), x: 0, width: 512 });
+        assert_eq!(tiles[1], Tile { file: "0001.png".to_string(), x: 512, width: 512 });
+        assert_eq!(tiles[2], Tile { file: "0002.png".to_string(), x: 1024, width: 176 });
+
+        assert_eq!(layout(1024, 512).len(), 2, "No empty tile when the width divides evenly");
+    }
+
+    #[test]
+    fn test_save_tiles() {
+        let mut image = WaveImage::new(Width::new(40).unwrap(), Height::new(8).unwrap());
+        for x in 0..40 {
+            image.draw_point(x, x as f32 / 40.0, 0.5);
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let white = Rgba::rgb(255, 255, 255);
+        let black = Rgba::rgb(0, 0, 0);
+        let tiles = save_tiles(&image, 16, &white, &black, &black, dir.path()).unwrap();
+        assert_eq!(tiles.len(), 3);
+
+        for tile in &tiles {
+            let decoder = png::Decoder::new(std::fs::File::open(dir.path().join(&tile.file)).unwrap());
+            let reader = decoder.read_info().unwrap();
+            assert_eq!(reader.info().width, tile.width);
+            assert_eq!(reader.info().height, 8);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+/// Finding the audio between the leading and trailing silence
+/// (`--trim-silence`).
+///
+/// A track that starts with seconds of room tone and ends with a long fade
+/// to nothing draws as a waveform squeezed into the middle of the image.
+/// Trimming the silence maps the width to the part that has sound in it.
+///
+/// Where that part ends is only known once the whole file is read, and the
+/// pixel mapping of the streaming scan needs it before the first sample, so
+/// the file is read twice: `SilenceBounds` finds the first and the last
+/// frame above the threshold in a first pass over the samples, and the
+/// waveform is then drawn of that time range like one given with `--start`
+/// and `--end`.
+use crate::config::TimeRange;
+use crate::scan::peak_abs;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// Finds the first and the last frame where a channel is above a level.
+#[derive(Debug, Clone)]
+pub struct SilenceBounds {
+    /// The least absolute sample that is not silence
+    level: f32,
+    /// Frames pushed so far
+    frames: u64,
+    /// The first and the last frame above the level, once there is one
+    bounds: Option<(u64, u64)>,
+}
+
+impl SilenceBounds {
+    /// Creates the bounds of the samples above the threshold in dBFS.
+    pub fn new(threshold_db: f32) -> Self {
+        Self { level: 10f32.powf(threshold_db / 20.0), frames: 0, bounds: None }
+    }
+
+    /// Scans the next frames, one slice per channel.
+    pub fn push(&mut self, planes: &[&[f32]]) {
+        let frames = planes.first().map_or(0, |plane| plane.len());
+        let start = self.frames;
+        self.frames += frames as u64;
+
+        // Most packets of a silent stretch have nothing to find
+        let loud = planes.iter().filter(|plane| peak_abs(plane) >= self.level);
+        let (first, last) = loud.fold((usize::MAX, 0), |(first, last), plane| {
+            let above = |sample: &f32| sample.abs() >= self.level;
+            let plane_first = plane.iter().position(above).unwrap_or(usize::MAX);
+            let plane_last = plane.iter().rposition(above).unwrap_or(0);
+            (first.min(plane_first), last.max(plane_last))
+        });
+        if first == usize::MAX {
+            return;
+        }
+        let (first, last) = (start + first as u64, start + last as u64);
+        self.bounds = Some(match self.bounds {
+            Some((earlier, _)) => (earlier, last),
+            None => (first, last),
+        });
+    }
+
+    /// Returns the time range from the first to just past the last frame
+    /// above the level, or `None` if it is all silence.
+    pub fn range(&self, sample_rate: u32) -> Option<TimeRange> {
+        let (first, last) = self.bounds?;
+        let seconds = |frame: u64| frame as f64 / sample_rate.max(1) as f64;
+        Some(TimeRange { start: seconds(first), end: Some(seconds(last + 1)) })
+    }
+}
@@ -0,0 +1,31 @@
+#[cfg(test)]
+mod tests {
+    use crate::config::TimeRange;
+    use crate::trim::SilenceBounds;
+
+    #[test]
+    fn test_silence_bounds() {
+        // -40 dBFS is 0.01
+        let mut bounds = SilenceBounds::new(-40.0);
+        bounds.push(&[&[0.0; 100], &[0.005; 100]]);
+        assert_eq!(bounds.range(100), None, "Below the threshold is silence");
+
+        let mut right = [0.0; 100];
+        right[30] = -0.5;
+        right[60] = 0.02;
+        let mut left = [0.0; 100];
+        left[20] = 0.1;
+        bounds.push(&[&left, &right]);
+        bounds.push(&[&[0.001; 100], &[0.0; 100]]);
+        assert_eq!(
+            bounds.range(100),
+            Some(TimeRange { start: 1.2, end: Some(1.61) }),
+            "From the first loud frame of any channel to just past the last"
+        );
+
+        let mut later = [0.0; 50];
+        later[49] = 1.0;
+        bounds.push(&[&later]);
+        assert_eq!(bounds.range(100).unwrap().end, Some(3.5), "The start stays, the end moves on");
+    }
+}
@@ -0,0 +1,210 @@
+/// Videos of the waveform with a moving playback cursor (and the audio).
+///
+/// Some platforms only accept video uploads, so audio-only content needs a
+/// video asset.  The waveform is rendered once and flattened to RGB, then
+/// every frame is that image with a cursor at the playback position.  The
+/// frames are piped as raw video into `ffmpeg`, which also muxes in the
+/// audio of the source file, so no video encoder is built into waver.
+use std::ffi::OsString;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use crate::audit;
+use crate::color::Rgba;
+use crate::config::TimeRange;
+use crate::error::{Result, WaverError};
+use crate::image::WaveImage;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+/// Frames per second of the video.  The cursor moves at most a few pixels
+/// per frame, so a higher rate would only make the files bigger.
+pub const FRAME_RATE: u32 = 25;
+
+/// Width of the playback cursor in pixels.
+const CURSOR_WIDTH: u32 = 2;
+
+/// The container (and codecs) of the video.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VideoFormat {
+    /// MP4 with H.264 video and AAC audio
+    Mp4,
+    /// WebM with VP9 video and Opus audio
+    Webm,
+}
+
+impl VideoFormat {
+    /// Returns the file extension of the format, without the dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            VideoFormat::Mp4 => "mp4",
+            VideoFormat::Webm => "webm",
+        }
+    }
+
+    /// Returns the ffmpeg codec options of the format.
+    fn codec_args(&self) -> &'static [&'static str] {
+        match self {
+            // faststart puts the index first so players can start right away
+            VideoFormat::Mp4 => &["-c:v", "libx264", "-pix_fmt", "yuv420p", "-c:a", "aac", "-movflags", "+faststart"],
+            VideoFormat::Webm => &["-c:v", "libvpx-vp9", "-pix_fmt", "yuv420p", "-c:a", "libopus"],
+        }
+    }
+}
+
+impl FromStr for VideoFormat {
+    type Err = WaverError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "mp4" => Ok(VideoFormat::Mp4),
+            "webm" => Ok(VideoFormat::Webm),
+            _ => Err(WaverError::argument_error("Video format must be mp4 or webm")),
+        }
+    }
+}
+
+/// Returns the number of frames of a video of `duration` seconds (at least one).
+pub fn frame_count(duration: f64) -> u64 {
+    ((duration.max(0.0) * FRAME_RATE as f64).ceil() as u64).max(1)
+}
+
+/// Returns the column of the cursor in frame `frame` of `frames`.
+///
+/// The cursor starts at the left edge and reaches the right edge with the
+/// last frame.
+pub fn cursor_x(frame: u64, frames: u64, width: u32) -> u32 {
+    if frames <= 1 {
+        return 0;
+    }
+    let last = width.saturating_sub(CURSOR_WIDTH) as u64;
+    (frame.min(frames - 1) * last / (frames - 1)) as u32
+}
+
+/// Returns the cursor color, black over light and white over dark backgrounds.
+fn cursor_color(background: &Rgba) -> [u8; 3] {
+    let luma = 299 * background.red as u32 + 587 * background.green as u32 + 114 * background.blue as u32;
+    if luma > 127 * 1000 {
+        [0, 0, 0]
+    } else {
+        [255, 255, 255]
+    }
+}
+
+/// Fills the columns `x..x + CURSOR_WIDTH` of an RGB frame with the color.
+fn draw_cursor(frame: &mut [u8], width: u32, x: u32, color: [u8; 3]) {
+    let stride = width as usize * 3;
+    let columns = x as usize..(x + CURSOR_WIDTH).min(width) as usize;
+    for row in frame.chunks_exact_mut(stride) {
+        for pixel in row[columns.start * 3..columns.end * 3].chunks_exact_mut(3) {
+            pixel.copy_from_slice(&color);
+        }
+    }
+}
+
+/// Returns the ffmpeg arguments that read the raw frames from stdin and
+/// encode them with the (part of the) audio file into the output.
+fn ffmpeg_args(
+    format: VideoFormat,
+    (width, height): (u32, u32),
+    audio: &Path,
+    range: Option<TimeRange>,
+    output: &Path,
+) -> Vec<OsString> {
+    let mut args: Vec<OsString> = ["-hide_banner", "-loglevel", "error", "-y"]
+        .iter()
+        .map(OsString::from)
+        .collect();
+
+    // The frames as raw RGB on stdin
+    args.extend(["-f", "rawvideo", "-pix_fmt", "rgb24"].map(OsString::from));
+    args.extend(["-video_size".into(), format!("{width}x{height}").into()]);
+    args.extend(["-framerate".into(), FRAME_RATE.to_string().into()]);
+    args.extend(["-i", "-"].map(OsString::from));
+
+    // The audio, cut to the rendered range
+    if let Some(range) = range {
+        args.extend(["-ss".into(), range.start.to_string().into()]);
+        if let Some(end) = range.end {
+            args.extend(["-t".into(), (end - range.start).to_string().into()]);
+        }
+    }
+    args.extend(["-i".into(), audio.as_os_str().to_owned()]);
+
+    args.extend(["-map", "0:v", "-map", "1:a"].map(OsString::from));
+    args.extend(format.codec_args().iter().map(OsString::from));
+    args.extend(["-shortest".into(), output.as_os_str().to_owned()]);
+    args
+}
+
+/// Encodes a video of the waveform with a cursor moving over it for
+/// `duration` seconds, with the audio of `audio`.
+///
+/// The background is flattened to an opaque color since video has no
+/// transparency.  This needs `ffmpeg` on the PATH.
+///
+/// # Arguments
+///
+/// * `image` - The rendered waveform
+/// * `colors` - The background, left and right channel colors
+/// * `audio` - The audio file the waveform was rendered from
+/// * `range` - The part of the audio that was rendered, if not all of it
+/// * `duration` - Length of the rendered audio in seconds
+/// * `format` - The video container and codecs
+/// * `output` - Path of the video file
+pub fn save_video(
+    image: &WaveImage,
+    (background, left, right): (&Rgba, &Rgba, &Rgba),
+    audio: &Path,
+    range: Option<TimeRange>,
+    duration: f64,
+    format: VideoFormat,
+    output: &Path,
+) -> Result<()> {
+    let (width, height) = (image.width(), image.height());
+    let base = image.flatten(background, left, right);
+    let color = cursor_color(background);
+
+    // ffmpeg writes the file itself, so it is recorded once it is written
+    let action = audit::write_action(output);
+    let args = ffmpeg_args(format, (width, height), audio, range, output);
+    let mut child = Command::new("ffmpeg")
+        .args(&args)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => WaverError::generation_error("Writing a video needs ffmpeg on the PATH"),
+            _ => WaverError::IoError(err),
+        })?;
+
+    // Only the cursor columns change, so they are restored from the base
+    // image after each frame instead of copying the whole frame
+    let frames = frame_count(duration);
+    let mut frame = base.clone();
+    let written = (|| -> io::Result<()> {
+        let mut stdin = BufWriter::new(child.stdin.take().expect("stdin is piped"));
+        for index in 0..frames {
+            let x = cursor_x(index, frames, width);
+            draw_cursor(&mut frame, width, x, color);
+            stdin.write_all(&frame)?;
+            for (row, base_row) in frame.chunks_exact_mut(width as usize * 3).zip(base.chunks_exact(width as usize * 3)) {
+                let columns = x as usize * 3..(x + CURSOR_WIDTH).min(width) as usize * 3;
+                row[columns.clone()].copy_from_slice(&base_row[columns]);
+            }
+        }
+        stdin.flush()
+    })();
+
+    // ffmpeg reports its own errors on stderr, a broken pipe just means it stopped early
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(WaverError::generation_error(format!("ffmpeg failed ({status})")));
+    }
+    written?;
+    audit::record(action, output)?;
+    Ok(())
+}
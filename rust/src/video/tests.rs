@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+    use crate::color::Rgba;
+    use crate::config::TimeRange;
+    use crate::video::{cursor_color, cursor_x, draw_cursor, ffmpeg_args, frame_count, VideoFormat, FRAME_RATE};
+    use std::ffi::OsString;
+    use std::path::Path;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_format_parsing() {
+        assert_eq!(VideoFormat::from_str("mp4").unwrap(), VideoFormat::Mp4);
+        assert_eq!(VideoFormat::from_str(" WebM ").unwrap(), VideoFormat::Webm);
+        assert_eq!(VideoFormat::Webm.extension(), "webm");
+
+        let result = VideoFormat::from_str("gif");
+        assert_eq!(result.unwrap_err().to_string(), "Invalid argument: Video format must be mp4 or webm");
+    }
+
+    #[test]
+    fn test_frames_and_cursor() {
+        assert_eq!(frame_count(2.0), 2 * FRAME_RATE as u64);
+        assert_eq!(frame_count(0.01), 1, "A partial frame counts");
+        assert_eq!(frame_count(0.0), 1, "Every video has a frame");
+
+        // The cursor runs from the left edge to the right edge
+        assert_eq!(cursor_x(0, 51, 102), 0);
+        assert_eq!(cursor_x(25, 51, 102), 50);
+        assert_eq!(cursor_x(50, 51, 102), 100);
+        assert_eq!(cursor_x(0, 1, 102), 0);
+    }
+
+    #[test]
+    fn test_draw_cursor() {
+        let (width, height) = (4, 2);
+        let mut frame = vec![9u8; (width * height * 3) as usize];
+        draw_cursor(&mut frame, width, 1, [1, 2, 3]);
+        for row in frame.chunks_exact(width as usize * 3) {
+            assert_eq!(row, [9, 9, 9, 1, 2, 3, 1, 2, 3, 9, 9, 9]);
+        }
+
+        // The cursor is clipped at the right edge
+        draw_cursor(&mut frame, width, 3, [0, 0, 0]);
+        assert_eq!(&frame[9..12], [0, 0, 0]);
+
+        assert_eq!(cursor_color(&Rgba::new(0xff, 0xff, 0xff, 0)), [0, 0, 0]);
+        assert_eq!(cursor_color(&Rgba::rgb(0x10, 0x20, 0x30)), [255, 255, 255]);
+    }
+
+    #[test]
+    fn test_ffmpeg_args() {
+        let audio = Path::new("song.flac");
+        let output = Path::new("song.flac.mp4");
+        let args = ffmpeg_args(VideoFormat::Mp4, (640, 120), audio, None, output);
+        let args: Vec<&str> = args.iter().map(|arg| arg.to_str().unwrap()).collect();
+        assert!(args.windows(2).any(|pair| pair == ["-video_size", "640x120"]));
+        assert!(args.windows(2).any(|pair| pair == ["-i", "-"]));
+        assert!(args.windows(2).any(|pair| pair == ["-c:v", "libx264"]));
+        assert!(!args.contains(&"-ss"), "The whole audio is used");
+        assert_eq!(args.last(), Some(&"song.flac.mp4"));
+
+        // The audio is cut to the rendered range
+        let range = TimeRange { start: 1.5, end: Some(4.0) };
+        let args = ffmpeg_args(VideoFormat::Webm, (640, 120), audio, Some(range), output);
+        let expected: Vec<OsString> = ["-ss", "1.5", "-t", "2.5", "-i", "song.flac"].map(OsString::from).to_vec();
+        assert!(args.windows(6).any(|window| window == expected.as_slice()));
+        assert!(args.contains(&OsString::from("libvpx-vp9")));
+    }
+}